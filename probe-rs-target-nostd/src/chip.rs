@@ -75,6 +75,9 @@ pub struct Chip<'a> {
     pub jtag: Option<Jtag<'a>>,
     /// The default binary format for this chip
     pub default_binary_format: Option<BinaryFormat>,
+    /// The option bytes of the chip, if probe-rs can read and write them.
+    #[serde(default)]
+    pub option_bytes: Option<OptionBytes<'a>>,
 }
 
 impl Chip<'_> {
@@ -103,6 +106,7 @@ impl Chip<'_> {
             rtt_scan_ranges: None,
             jtag: None,
             default_binary_format: Some(BinaryFormat::Raw),
+            option_bytes: None,
         }
     }
 
@@ -116,6 +120,33 @@ impl Chip<'_> {
     }
 }
 
+/// The option bytes of a chip.
+#[derive(Debug, Clone, PartialEq, Eq, defmt::Format)]
+pub struct OptionBytes<'a> {
+    /// The address the option bytes are read from.
+    pub address: u64,
+    /// The size of the option bytes, in bytes.
+    pub size: u64,
+    /// The fields of the option bytes.
+    pub fields: &'a [OptionBytesField<'a>],
+}
+
+/// A field of the option bytes, e.g. the read protection level.
+#[derive(Debug, Clone, PartialEq, Eq, defmt::Format)]
+pub struct OptionBytesField<'a> {
+    /// The name of the field, e.g. `RDP`.
+    pub name: &'a str,
+    /// A description of the field and its values.
+    pub description: Option<&'a str>,
+    /// The offset of the 32-bit little endian word containing the field,
+    /// relative to the start of the option bytes.
+    pub offset: u64,
+    /// The position of the least significant bit of the field in its word.
+    pub bit_offset: u8,
+    /// The number of bits of the field.
+    pub bit_width: u8,
+}
+
 /// An individual core inside a chip
 #[derive(Debug, Clone, Serialize, Deserialize, defmt::Format)]
 pub struct Core<'a> {
@@ -177,6 +208,39 @@ impl From<&Chip<'_>> for probe_rs_target::Chip {
             rtt_scan_ranges: value.rtt_scan_ranges.map(|x| x.to_vec()),
             jtag: value.jtag.clone().map(|x| (&x).into()),
             default_binary_format: value.default_binary_format.clone(),
+            option_bytes: value.option_bytes.as_ref().map(|x| x.into()),
+        }
+    }
+}
+
+impl From<&OptionBytes<'_>> for probe_rs_target::OptionBytes {
+    fn from(value: &OptionBytes<'_>) -> Self {
+        Self {
+            address: value.address,
+            size: value.size,
+            fields: value.fields.iter().map(|x| x.into()).collect(),
+        }
+    }
+}
+
+impl Serialize for OptionBytes<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let allocable: probe_rs_target::OptionBytes = self.into();
+        allocable.serialize(serializer)
+    }
+}
+
+impl From<&OptionBytesField<'_>> for probe_rs_target::OptionBytesField {
+    fn from(value: &OptionBytesField<'_>) -> Self {
+        Self {
+            name: value.name.to_string(),
+            description: value.description.map(|x| x.to_string()),
+            offset: value.offset,
+            bit_offset: value.bit_offset,
+            bit_width: value.bit_width,
         }
     }
 }
@@ -1,6 +1,6 @@
 use crate::{CoreAccessOptions, CoreType, TargetDescriptionSource};
 
-use super::chip::Chip;
+use super::chip::{Chip, OptionBytes};
 use super::flash_algorithm::RawFlashAlgorithm;
 use jep106::JEP106Code;
 use serde::Serialize;
@@ -25,6 +25,8 @@ pub struct ChipFamily<'a> {
     /// - `Some("1.3.0")` if the latest pack file release was for example "1.3.0".
     /// - `None` if this was not generated from a pack file, or has been modified since it was generated.
     pub pack_file_release: Option<&'a str>,
+    /// The option bytes of all variants which don't describe their own.
+    pub option_bytes: Option<OptionBytes<'a>>,
     /// This vector holds all the variants of the family.
     pub variants: &'a [Chip<'a>],
     /// This vector holds all available algorithms.
@@ -37,12 +39,13 @@ impl defmt::Format for ChipFamily<'_> {
     fn format(&self, fmt: defmt::Formatter) {
         defmt::write!(
             fmt,
-            "ChipFamily {{ name: {}, manufacturer: {}({}), generated_from_pack: {}, pack_file_release: {}, variants: {}, flash_algorithms: {}, source: {} }}",
+            "ChipFamily {{ name: {}, manufacturer: {}({}), generated_from_pack: {}, pack_file_release: {}, option_bytes: {}, variants: {}, flash_algorithms: {}, source: {} }}",
             self.name,
             self.manufacturer.map_or("NotListed", |x| x.get().unwrap_or("Unknown")),
             self.manufacturer.map_or(None, |x| Some((x.id, x.cc))),
             self.generated_from_pack,
             self.pack_file_release,
+            self.option_bytes,
             self.variants,
             self.flash_algorithms,
             self.source,
@@ -234,6 +237,7 @@ impl From<&ChipFamily<'_>> for probe_rs_target::ChipFamily {
             manufacturer: value.manufacturer,
             generated_from_pack: value.generated_from_pack,
             pack_file_release: value.pack_file_release.map(|x| x.to_string()),
+            option_bytes: value.option_bytes.as_ref().map(|x| x.into()),
             variants: value.variants.iter().map(|x| x.into()).collect(),
             flash_algorithms: value.flash_algorithms.iter().map(|x| x.into()).collect(),
             source: value.source,
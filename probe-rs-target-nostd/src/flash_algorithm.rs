@@ -59,6 +59,9 @@ pub struct RawFlashAlgorithm<'a> {
 
     /// The encoding format accepted by the flash algorithm.
     pub transfer_encoding: Option<TransferEncoding>,
+
+    /// Arguments passed to the `Init()` entry point of the algorithm.
+    pub init_arguments: &'a [u32],
 }
 
 impl From<&RawFlashAlgorithm<'_>> for probe_rs_target::RawFlashAlgorithm {
@@ -81,6 +84,7 @@ impl From<&RawFlashAlgorithm<'_>> for probe_rs_target::RawFlashAlgorithm {
             cores: value.cores.iter().map(|x| x.to_string()).collect(),
             stack_size: value.stack_size,
             transfer_encoding: value.transfer_encoding,
+            init_arguments: value.init_arguments.to_vec(),
         }
     }
 }
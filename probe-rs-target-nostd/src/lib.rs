@@ -19,7 +19,7 @@ mod flash_properties;
 mod memory;
 pub(crate) mod serialize;
 
-pub use chip::{Chip, Core, Jtag, ScanChainElement};
+pub use chip::{Chip, Core, Jtag, OptionBytes, OptionBytesField, ScanChainElement};
 pub use chip_family::ChipFamily;
 pub use flash_algorithm::RawFlashAlgorithm;
 pub use flash_properties::FlashProperties;
@@ -0,0 +1,84 @@
+use super::chip_family::ChipFamily;
+use super::flash_algorithm::RawFlashAlgorithm;
+use super::memory::MemoryRegion;
+
+use serde::{Deserialize, Serialize};
+
+/// A board level addition to the description of an existing chip.
+///
+/// Boards often connect external memories, e.g. a QSPI or OSPI flash, which need a
+/// board specific flash algorithm. An overlay adds these memory regions and flash
+/// algorithms to a chip which is already known, instead of describing the whole chip again.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BoardOverlay {
+    /// The name of the board.
+    pub name: String,
+    /// The name of the chip on the board, as used in its target description.
+    pub chip: String,
+    /// The memory regions which are added to the memory map of the chip.
+    ///
+    /// Regions without any cores are accessible by all cores of the chip.
+    #[serde(default)]
+    pub memory_map: Vec<MemoryRegion>,
+    /// The flash algorithms which are added to the chip.
+    ///
+    /// Algorithms without any cores can be used by all cores of the chip.
+    #[serde(default)]
+    pub flash_algorithms: Vec<RawFlashAlgorithm>,
+}
+
+impl BoardOverlay {
+    /// Adds the memory regions and flash algorithms of the board to the chip in `family`.
+    pub fn apply(&self, family: &mut ChipFamily) -> Result<(), String> {
+        let variant = family
+            .variants
+            .iter_mut()
+            .find(|variant| variant.name.eq_ignore_ascii_case(&self.chip))
+            .ok_or_else(|| {
+                format!(
+                    "chip `{}` is not part of family `{}`",
+                    self.chip, family.name
+                )
+            })?;
+        let core_names = variant
+            .cores
+            .iter()
+            .map(|core| core.name.clone())
+            .collect::<Vec<_>>();
+
+        for region in &self.memory_map {
+            let mut region = region.clone();
+            let cores = match &mut region {
+                MemoryRegion::Ram(region) => &mut region.cores,
+                MemoryRegion::Generic(region) => &mut region.cores,
+                MemoryRegion::Nvm(region) => &mut region.cores,
+            };
+            if cores.is_empty() {
+                cores.clone_from(&core_names);
+            }
+            variant.memory_map.push(region);
+        }
+
+        for algorithm in &self.flash_algorithms {
+            if family
+                .flash_algorithms
+                .iter()
+                .any(|existing| existing.name == algorithm.name)
+            {
+                return Err(format!(
+                    "flash algorithm `{}` of board `{}` already exists",
+                    algorithm.name, self.name
+                ));
+            }
+
+            let mut algorithm = algorithm.clone();
+            if algorithm.cores.is_empty() {
+                algorithm.cores.clone_from(&core_names);
+            }
+            variant.flash_algorithms.push(algorithm.name.clone());
+            family.flash_algorithms.push(algorithm);
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,8 @@
 use super::memory::MemoryRegion;
-use crate::{serialize::hex_option, CoreType};
+use crate::{
+    serialize::{hex_option, hex_u_int},
+    CoreType,
+};
 use serde::{Deserialize, Serialize};
 
 /// Represents a DAP scan chain element.
@@ -81,6 +84,9 @@ pub struct Chip {
     pub jtag: Option<Jtag>,
     /// The default binary format for this chip
     pub default_binary_format: Option<BinaryFormat>,
+    /// The option bytes of the chip, if probe-rs can read and write them.
+    #[serde(default)]
+    pub option_bytes: Option<OptionBytes>,
 }
 
 impl Chip {
@@ -102,10 +108,47 @@ impl Chip {
             rtt_scan_ranges: None,
             jtag: None,
             default_binary_format: Some(BinaryFormat::Raw),
+            option_bytes: None,
         }
     }
 }
 
+/// The option bytes of a chip.
+///
+/// Option bytes, sometimes called configuration words or user row, are non-volatile memory
+/// which configures the chip, e.g. its read protection or brown-out detection. They usually
+/// can't be programmed by the flash algorithms, so the debug sequence of the chip is used to
+/// read and write them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptionBytes {
+    /// The address the option bytes are read from.
+    #[serde(serialize_with = "hex_u_int")]
+    pub address: u64,
+    /// The size of the option bytes, in bytes.
+    #[serde(serialize_with = "hex_u_int")]
+    pub size: u64,
+    /// The fields of the option bytes.
+    #[serde(default)]
+    pub fields: Vec<OptionBytesField>,
+}
+
+/// A field of the option bytes, e.g. the read protection level.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptionBytesField {
+    /// The name of the field, e.g. `RDP`.
+    pub name: String,
+    /// A description of the field and its values.
+    pub description: Option<String>,
+    /// The offset of the 32-bit little endian word containing the field,
+    /// relative to the start of the option bytes.
+    #[serde(serialize_with = "hex_u_int")]
+    pub offset: u64,
+    /// The position of the least significant bit of the field in its word.
+    pub bit_offset: u8,
+    /// The number of bits of the field.
+    pub bit_width: u8,
+}
+
 /// An individual core inside a chip
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Core {
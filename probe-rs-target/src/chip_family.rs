@@ -1,6 +1,6 @@
 use crate::CoreAccessOptions;
 
-use super::chip::Chip;
+use super::chip::{Chip, OptionBytes};
 use super::flash_algorithm::RawFlashAlgorithm;
 use jep106::JEP106Code;
 
@@ -173,6 +173,9 @@ pub struct ChipFamily {
     /// - `None` if this was not generated from a pack file, or has been modified since it was generated.
     #[serde(default)]
     pub pack_file_release: Option<String>,
+    /// The option bytes of all variants which don't describe their own.
+    #[serde(default)]
+    pub option_bytes: Option<OptionBytes>,
     /// This vector holds all the variants of the family.
     pub variants: Vec<Chip>,
     /// This vector holds all available algorithms.
@@ -299,6 +302,23 @@ impl ChipFamily {
                     memory
                 );
             }
+
+            // Ensure that the option byte fields fit into a word, and lie inside the option bytes.
+            if let Some(option_bytes) = variant.option_bytes.as_ref().or(self.option_bytes.as_ref())
+            {
+                for field in &option_bytes.fields {
+                    let end_bit = field.bit_offset as u64 + field.bit_width as u64;
+                    if field.bit_width == 0
+                        || end_bit > 32
+                        || field.offset + end_bit.div_ceil(8) > option_bytes.size
+                    {
+                        return Err(format!(
+                            "Variant {}, option byte field {} is invalid",
+                            variant.name, field.name
+                        ));
+                    }
+                }
+            }
         }
 
         Ok(())
@@ -19,6 +19,19 @@ pub enum TransferEncoding {
     /// address. The length of the compressed image is stored in the first 4 bytes of the first
     /// chunk of the image.
     Miniz,
+
+    /// Flash data is compressed into LZ4 blocks, without the LZ4 frame format.
+    ///
+    /// Images are written in chunks like with [`TransferEncoding::Miniz`], the first 4 bytes
+    /// contain the length of the compressed block.
+    Lz4,
+
+    /// Flash data is compressed with heatshrink, using a window size of 2^8 bytes and a
+    /// lookahead size of 2^4 bytes.
+    ///
+    /// Images are written in chunks like with [`TransferEncoding::Miniz`], the first 4 bytes
+    /// contain the length of the compressed data.
+    Heatshrink,
 }
 
 /// The raw flash algorithm is the description of a flash algorithm,
@@ -85,6 +98,20 @@ pub struct RawFlashAlgorithm {
     /// The encoding format accepted by the flash algorithm.
     #[serde(default)]
     pub transfer_encoding: Option<TransferEncoding>,
+
+    /// Arguments passed to the `Init()` entry point of the algorithm.
+    ///
+    /// They allow one algorithm to be used on several boards, e.g. to select the
+    /// chip-select pin of an external flash. If any arguments are given, `Init()` is called as
+    ///
+    /// ```c
+    /// int Init(uint32_t adr, uint32_t clk, uint32_t fnc, const uint32_t *args);
+    /// ```
+    ///
+    /// where `args[0]` is the number of arguments, and `args[1..]` are the arguments.
+    /// Without arguments, `args` is `NULL`.
+    #[serde(default)]
+    pub init_arguments: Vec<u32>,
 }
 
 pub fn serialize<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
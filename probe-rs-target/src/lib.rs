@@ -11,6 +11,7 @@
 //! This crate contains the schema structs for the YAML target description files.
 //!
 
+mod board;
 mod chip;
 mod chip_family;
 mod flash_algorithm;
@@ -20,9 +21,10 @@ pub(crate) mod serialize;
 
 #[cfg(feature = "std")]
 pub use {
+    board::BoardOverlay,
     chip::{
-        ArmCoreAccessOptions, BinaryFormat, Chip, Core, CoreAccessOptions, Jtag,
-        RiscvCoreAccessOptions, ScanChainElement, XtensaCoreAccessOptions,
+        ArmCoreAccessOptions, BinaryFormat, Chip, Core, CoreAccessOptions, Jtag, OptionBytes,
+        OptionBytesField, RiscvCoreAccessOptions, ScanChainElement, XtensaCoreAccessOptions,
     },
     chip_family::{Architecture, ChipFamily, CoreType, InstructionSet, TargetDescriptionSource},
     flash_algorithm::{RawFlashAlgorithm, TransferEncoding},
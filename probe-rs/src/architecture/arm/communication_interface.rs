@@ -7,6 +7,7 @@ use super::{
         Abort, Ctrl, DebugPortError, DebugPortId, DebugPortVersion, DpAccess, Select, BASEPTR0,
         BASEPTR1, DPIDR, DPIDR1,
     },
+    link_quality::{next_lower_speed, LinkMonitor},
     memory::{
         adi_v5_memory_interface::{ADIMemoryInterface, ArmProbe},
         Component,
@@ -16,7 +17,7 @@ use super::{
 };
 use crate::{
     architecture::arm::ap::DataSize,
-    probe::{DebugProbe, DebugProbeError, Probe},
+    probe::{uart::UartAccess, DebugProbe, DebugProbeError, Probe},
     CoreStatus, Error as ProbeRsError,
 };
 use jep106::JEP106Code;
@@ -30,7 +31,9 @@ use std::{
 
 /// An error in the communication with an access port or
 /// debug port.
-#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq, Copy)]
+#[derive(
+    Debug, thiserror::Error, Clone, PartialEq, Eq, Copy, serde::Serialize, serde::Deserialize,
+)]
 pub enum DapError {
     /// An error occurred during SWD communication.
     #[error("An error occurred in the SWD communication between probe and device.")]
@@ -98,6 +101,16 @@ pub trait ArmProbeInterface: DapAccess + SwdSequence + SwoAccess + Send {
         dp: DpAddress,
     ) -> Result<Option<ArmChipInfo>, ArmError>;
 
+    /// Returns the UART interface of the underlying probe, if it has one.
+    fn uart_interface(&mut self) -> Option<&mut dyn UartAccess> {
+        None
+    }
+
+    /// Starts watching the error rate of the link to the target.
+    ///
+    /// If errors pile up, the protocol speed is lowered once.
+    fn monitor_link_quality(&mut self) {}
+
     /// Closes the interface and returns back the generic probe it consumed.
     fn close(self: Box<Self>) -> Probe;
 }
@@ -151,6 +164,7 @@ pub struct Initialized {
     dps: HashMap<DpAddress, DpState>,
     use_overrun_detect: bool,
     sequence: Arc<dyn ArmDebugSequence>,
+    link_monitor: Option<LinkMonitor>,
 }
 
 impl Initialized {
@@ -164,6 +178,7 @@ impl Initialized {
             dps: HashMap::new(),
             use_overrun_detect,
             sequence,
+            link_monitor: None,
         }
     }
 }
@@ -402,6 +417,14 @@ impl ArmProbeInterface for ArmCommunicationInterface<Initialized> {
         self.state.current_dp
     }
 
+    fn uart_interface(&mut self) -> Option<&mut dyn UartAccess> {
+        self.probe_mut().get_uart_interface_mut()
+    }
+
+    fn monitor_link_quality(&mut self) {
+        self.state.link_monitor = Some(LinkMonitor::default());
+    }
+
     fn close(self: Box<Self>) -> Probe {
         ArmCommunicationInterface::close(*self)
     }
@@ -500,6 +523,30 @@ impl<'interface> ArmCommunicationInterface<Initialized> {
         }
     }
 
+    /// Records the result of a DAP access, and lowers the speed once if errors pile up.
+    fn monitor_link<T>(&mut self, result: Result<T, ArmError>) -> Result<T, ArmError> {
+        let Some(monitor) = &mut self.state.link_monitor else {
+            return result;
+        };
+
+        if monitor.record(&result) {
+            self.state.link_monitor = None;
+
+            let probe = self.probe_mut();
+            if let Some(speed_khz) = next_lower_speed(probe.speed_khz()) {
+                tracing::warn!(
+                    "Too many errors on the link to the target, lowering the speed to {} kHz",
+                    speed_khz
+                );
+                if let Err(error) = probe.set_speed(speed_khz) {
+                    tracing::warn!("Failed to lower the speed: {}", error);
+                }
+            }
+        }
+
+        result
+    }
+
     fn select_dp(&mut self, dp: DpAddress) -> Result<&mut DpState, ArmError> {
         let mut switched_dp = false;
 
@@ -730,11 +777,11 @@ impl SwoAccess for ArmCommunicationInterface<Initialized> {
 
 impl DapAccess for ArmCommunicationInterface<Initialized> {
     fn read_raw_dp_register(&mut self, dp: DpAddress, address: u8) -> Result<u32, ArmError> {
-        self.select_dp_and_dp_bank(dp, address)?;
-        let result = self
-            .probe_mut()
-            .raw_read_register(PortType::DebugPort, address & 0xf)?;
-        Ok(result)
+        let result = self.select_dp_and_dp_bank(dp, address).and_then(|_| {
+            self.probe_mut()
+                .raw_read_register(PortType::DebugPort, address & 0xf)
+        });
+        self.monitor_link(result)
     }
 
     fn write_raw_dp_register(
@@ -743,10 +790,11 @@ impl DapAccess for ArmCommunicationInterface<Initialized> {
         address: u8,
         value: u32,
     ) -> Result<(), ArmError> {
-        self.select_dp_and_dp_bank(dp, address)?;
-        self.probe_mut()
-            .raw_write_register(PortType::DebugPort, address, value)?;
-        Ok(())
+        let result = self.select_dp_and_dp_bank(dp, address).and_then(|_| {
+            self.probe_mut()
+                .raw_write_register(PortType::DebugPort, address, value)
+        });
+        self.monitor_link(result)
     }
 
     fn read_raw_ap_register(
@@ -754,13 +802,11 @@ impl DapAccess for ArmCommunicationInterface<Initialized> {
         ap: ApAddress,
         address: u8,
     ) -> std::result::Result<u32, ArmError> {
-        self.select_ap_and_ap_bank(ap, address)?;
-
-        let result = self
-            .probe_mut()
-            .raw_read_register(PortType::AccessPort, address & 0xf)?;
-
-        Ok(result)
+        let result = self.select_ap_and_ap_bank(ap, address).and_then(|_| {
+            self.probe_mut()
+                .raw_read_register(PortType::AccessPort, address & 0xf)
+        });
+        self.monitor_link(result)
     }
 
     fn read_raw_ap_register_repeated(
@@ -769,11 +815,11 @@ impl DapAccess for ArmCommunicationInterface<Initialized> {
         address: u8,
         values: &mut [u32],
     ) -> Result<(), ArmError> {
-        self.select_ap_and_ap_bank(ap, address)?;
-
-        self.probe_mut()
-            .raw_read_block(PortType::AccessPort, address, values)?;
-        Ok(())
+        let result = self.select_ap_and_ap_bank(ap, address).and_then(|_| {
+            self.probe_mut()
+                .raw_read_block(PortType::AccessPort, address, values)
+        });
+        self.monitor_link(result)
     }
 
     fn write_raw_ap_register(
@@ -782,12 +828,11 @@ impl DapAccess for ArmCommunicationInterface<Initialized> {
         address: u8,
         value: u32,
     ) -> Result<(), ArmError> {
-        self.select_ap_and_ap_bank(ap, address)?;
-
-        self.probe_mut()
-            .raw_write_register(PortType::AccessPort, address, value)?;
-
-        Ok(())
+        let result = self.select_ap_and_ap_bank(ap, address).and_then(|_| {
+            self.probe_mut()
+                .raw_write_register(PortType::AccessPort, address, value)
+        });
+        self.monitor_link(result)
     }
 
     fn write_raw_ap_register_repeated(
@@ -796,11 +841,11 @@ impl DapAccess for ArmCommunicationInterface<Initialized> {
         address: u8,
         values: &[u32],
     ) -> Result<(), ArmError> {
-        self.select_ap_and_ap_bank(ap, address)?;
-
-        self.probe_mut()
-            .raw_write_block(PortType::AccessPort, address, values)?;
-        Ok(())
+        let result = self.select_ap_and_ap_bank(ap, address).and_then(|_| {
+            self.probe_mut()
+                .raw_write_block(PortType::AccessPort, address, values)
+        });
+        self.monitor_link(result)
     }
 }
 
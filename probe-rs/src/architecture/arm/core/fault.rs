@@ -0,0 +1,673 @@
+//! Decoding of the fault status registers of Cortex-M cores.
+//!
+//! When a Cortex-M core takes a fault, the cause is recorded in the fault status registers
+//! of the System Control Block, and the interrupted context is saved on the stack.
+//! [`FaultStatus`] collects both, so the fault can be traced back to the instruction that caused it.
+
+use std::fmt;
+
+use super::exception_handling::armv8m::ExceptionReason;
+use crate::{debug::stack_frame::StackFrame, Core, CoreType, Error, MemoryInterface, RegisterId};
+
+/// CPUID - CPUID Base Register
+const CPUID: u64 = 0xE000_ED00;
+/// HFSR - HardFault Status Register
+const HFSR: u64 = 0xE000_ED2C;
+/// CFSR - Configurable Fault Status Register (UFSR[31:16], BFSR[15:8], MMFSR[7:0])
+const CFSR: u64 = 0xE000_ED28;
+/// MMFAR - MemManage Fault Address Register
+const MMFAR: u64 = 0xE000_ED34;
+/// BFAR - BusFault Address Register
+const BFAR: u64 = 0xE000_ED38;
+/// SFSR - Secure Fault Status Register
+const SFSR: u64 = 0xE000_EDE4;
+/// SFAR - Secure Fault Address Register
+const SFAR: u64 = 0xE000_EDE8;
+
+/// CFSR.MMARVALID
+const MMARVALID: u32 = 1 << 7;
+/// CFSR.BFARVALID
+const BFARVALID: u32 = 1 << 15;
+/// SFSR.SFARVALID
+const SFARVALID: u32 = 1 << 6;
+
+/// CPUID.ARCHITECTURE of ARMv7-M and of ARMv8-M with the Main Extension.
+const CPUID_ARCHITECTURE_MAINLINE: u32 = 0xF;
+
+/// The Non-secure main stack pointer, as selected in the DCRSR.
+const MSP_NS: RegisterId = RegisterId(0b1_1000);
+/// The Non-secure process stack pointer, as selected in the DCRSR.
+const PSP_NS: RegisterId = RegisterId(0b1_1001);
+/// The Secure main stack pointer, as selected in the DCRSR.
+const MSP_S: RegisterId = RegisterId(0b1_1010);
+/// The Secure process stack pointer, as selected in the DCRSR.
+const PSP_S: RegisterId = RegisterId(0b1_1011);
+
+/// The fault handler that is responsible for a [`FaultCause`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultKind {
+    /// Reported in the HFSR.
+    HardFault,
+    /// Reported in the MMFSR part of the CFSR.
+    MemManage,
+    /// Reported in the BFSR part of the CFSR.
+    BusFault,
+    /// Reported in the UFSR part of the CFSR.
+    UsageFault,
+    /// Reported in the SFSR.
+    SecureFault,
+}
+
+impl fmt::Display for FaultKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultKind::HardFault => write!(f, "HardFault"),
+            FaultKind::MemManage => write!(f, "MemManage"),
+            FaultKind::BusFault => write!(f, "BusFault"),
+            FaultKind::UsageFault => write!(f, "UsageFault"),
+            FaultKind::SecureFault => write!(f, "SecureFault"),
+        }
+    }
+}
+
+/// A status bit which is set in one of the fault status registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaultCause {
+    /// The fault handler that is responsible for this cause.
+    pub kind: FaultKind,
+    /// The name of the status bit, as used in the Architecture Reference Manual.
+    pub name: &'static str,
+    /// A human readable description of the cause.
+    pub description: &'static str,
+}
+
+const fn cause(
+    bit: u32,
+    kind: FaultKind,
+    name: &'static str,
+    description: &'static str,
+) -> (u32, FaultCause) {
+    (
+        bit,
+        FaultCause {
+            kind,
+            name,
+            description,
+        },
+    )
+}
+
+static HFSR_CAUSES: &[(u32, FaultCause)] = &[
+    cause(
+        1,
+        FaultKind::HardFault,
+        "VECTTBL",
+        "Bus error on a vector table read during exception processing",
+    ),
+    cause(
+        30,
+        FaultKind::HardFault,
+        "FORCED",
+        "A configurable fault was escalated to a HardFault",
+    ),
+    cause(
+        31,
+        FaultKind::HardFault,
+        "DEBUGEVT",
+        "A debug event occurred while halting debug was disabled",
+    ),
+];
+
+static CFSR_CAUSES: &[(u32, FaultCause)] = &[
+    cause(
+        0,
+        FaultKind::MemManage,
+        "IACCVIOL",
+        "Instruction fetch from a location that does not permit execution",
+    ),
+    cause(
+        1,
+        FaultKind::MemManage,
+        "DACCVIOL",
+        "Data access to a location that does not permit the operation",
+    ),
+    cause(
+        3,
+        FaultKind::MemManage,
+        "MUNSTKERR",
+        "MemManage fault on unstacking for a return from exception",
+    ),
+    cause(
+        4,
+        FaultKind::MemManage,
+        "MSTKERR",
+        "MemManage fault on stacking for exception entry",
+    ),
+    cause(
+        5,
+        FaultKind::MemManage,
+        "MLSPERR",
+        "MemManage fault during floating-point lazy state preservation",
+    ),
+    cause(
+        8,
+        FaultKind::BusFault,
+        "IBUSERR",
+        "Bus error on an instruction prefetch",
+    ),
+    cause(
+        9,
+        FaultKind::BusFault,
+        "PRECISERR",
+        "Precise data bus error",
+    ),
+    cause(
+        10,
+        FaultKind::BusFault,
+        "IMPRECISERR",
+        "Imprecise data bus error, the stacked PC does not point to the faulting instruction",
+    ),
+    cause(
+        11,
+        FaultKind::BusFault,
+        "UNSTKERR",
+        "Bus error on unstacking for a return from exception",
+    ),
+    cause(
+        12,
+        FaultKind::BusFault,
+        "STKERR",
+        "Bus error on stacking for exception entry",
+    ),
+    cause(
+        13,
+        FaultKind::BusFault,
+        "LSPERR",
+        "Bus error during floating-point lazy state preservation",
+    ),
+    cause(
+        16,
+        FaultKind::UsageFault,
+        "UNDEFINSTR",
+        "Undefined instruction",
+    ),
+    cause(
+        17,
+        FaultKind::UsageFault,
+        "INVSTATE",
+        "Instruction executed with an invalid EPSR.T or EPSR.IT field",
+    ),
+    cause(
+        18,
+        FaultKind::UsageFault,
+        "INVPC",
+        "Invalid EXC_RETURN value loaded into the PC",
+    ),
+    cause(
+        19,
+        FaultKind::UsageFault,
+        "NOCP",
+        "Access to a coprocessor that is disabled or not present",
+    ),
+    cause(
+        20,
+        FaultKind::UsageFault,
+        "STKOF",
+        "Stack overflow detected by a stack pointer limit check",
+    ),
+    cause(
+        24,
+        FaultKind::UsageFault,
+        "UNALIGNED",
+        "Unaligned memory access",
+    ),
+    cause(25, FaultKind::UsageFault, "DIVBYZERO", "Division by zero"),
+];
+
+static SFSR_CAUSES: &[(u32, FaultCause)] = &[
+    cause(
+        0,
+        FaultKind::SecureFault,
+        "INVEP",
+        "Invalid entry point into the Secure state",
+    ),
+    cause(
+        1,
+        FaultKind::SecureFault,
+        "INVIS",
+        "Invalid integrity signature in an exception stack frame",
+    ),
+    cause(
+        2,
+        FaultKind::SecureFault,
+        "INVER",
+        "Invalid exception return",
+    ),
+    cause(
+        3,
+        FaultKind::SecureFault,
+        "AUVIOL",
+        "Non-secure access to a Secure address, detected by the attribution unit",
+    ),
+    cause(
+        4,
+        FaultKind::SecureFault,
+        "INVTRAN",
+        "Branch from Secure to Non-secure memory without a domain crossing instruction",
+    ),
+    cause(
+        5,
+        FaultKind::SecureFault,
+        "LSPERR",
+        "SAU or IDAU violation during floating-point lazy state preservation",
+    ),
+    cause(
+        7,
+        FaultKind::SecureFault,
+        "LSERR",
+        "Error during floating-point lazy state activation or deactivation",
+    ),
+];
+
+/// The registers that the processor saved on the stack on exception entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExceptionStackFrame {
+    /// The address of the stacked R0.
+    pub address: u32,
+    /// The stacked R0.
+    pub r0: u32,
+    /// The stacked R1.
+    pub r1: u32,
+    /// The stacked R2.
+    pub r2: u32,
+    /// The stacked R3.
+    pub r3: u32,
+    /// The stacked R12.
+    pub r12: u32,
+    /// The stacked link register of the interrupted code.
+    pub lr: u32,
+    /// The stacked program counter. For precise faults, this is the address of the faulting instruction.
+    pub pc: u32,
+    /// The stacked xPSR.
+    pub xpsr: u32,
+}
+
+/// The decoded fault state of a Cortex-M core.
+///
+/// Registers which are not implemented by the architecture of the core are `None`.
+/// The fault address registers are only `Some` if their contents are marked as valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaultStatus {
+    /// The number of the active exception, from the IPSR.
+    pub exception_number: u32,
+    /// HardFault Status Register.
+    pub hfsr: Option<u32>,
+    /// Configurable Fault Status Register.
+    pub cfsr: Option<u32>,
+    /// MemManage Fault Address Register.
+    pub mmfar: Option<u32>,
+    /// BusFault Address Register.
+    pub bfar: Option<u32>,
+    /// Secure Fault Status Register.
+    pub sfsr: Option<u32>,
+    /// Secure Fault Address Register.
+    pub sfar: Option<u32>,
+    /// The status bits which are set in the fault status registers.
+    pub causes: Vec<FaultCause>,
+    /// The interrupted context, if the link register holds an EXC_RETURN value.
+    ///
+    /// This is the case when the core is halted on entry to the exception handler, e.g. by a vector catch.
+    pub exception_frame: Option<ExceptionStackFrame>,
+}
+
+impl FaultStatus {
+    /// Returns `true` if the core is executing a fault handler.
+    pub fn is_fault(&self) -> bool {
+        matches!(
+            ExceptionReason::from(self.exception_number),
+            ExceptionReason::HardFault
+                | ExceptionReason::MemoryManagementFault
+                | ExceptionReason::BusFault
+                | ExceptionReason::UsageFault
+                | ExceptionReason::SecureFault
+        )
+    }
+
+    /// Returns the stack frame of the code that caused the fault, from a stack unwound
+    /// with [`crate::debug::debug_info::DebugInfo::unwind`].
+    ///
+    /// The frame is found by the program counter in the [`ExceptionStackFrame`].
+    /// For inlined functions, the innermost frame is returned.
+    pub fn faulting_frame<'a>(&self, stack_frames: &'a [StackFrame]) -> Option<&'a StackFrame> {
+        let pc = u64::from(self.exception_frame?.pc);
+        stack_frames
+            .iter()
+            .find(|frame| TryInto::<u64>::try_into(frame.pc).ok() == Some(pc))
+    }
+}
+
+impl fmt::Display for FaultStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let exception = match ExceptionReason::from(self.exception_number) {
+            ExceptionReason::ExternalInterrupt(number) => format!("External interrupt #{number}"),
+            reason => format!("{reason:?}"),
+        };
+        writeln!(
+            f,
+            "Active exception: {exception} ({})",
+            self.exception_number
+        )?;
+
+        let registers = [
+            ("HFSR", self.hfsr),
+            ("CFSR", self.cfsr),
+            ("MMFAR", self.mmfar),
+            ("BFAR", self.bfar),
+            ("SFSR", self.sfsr),
+            ("SFAR", self.sfar),
+        ];
+        for (name, value) in registers {
+            if let Some(value) = value {
+                writeln!(f, "{name:<6}: {value:#010x}")?;
+            }
+        }
+
+        if self.causes.is_empty() {
+            writeln!(f, "No fault status bits are set.")?;
+        }
+        for cause in &self.causes {
+            writeln!(f, "{} {}: {}", cause.kind, cause.name, cause.description)?;
+        }
+
+        if let Some(frame) = &self.exception_frame {
+            writeln!(f, "Exception frame at {:#010x}:", frame.address)?;
+            writeln!(
+                f,
+                "  R0: {:#010x}  R1: {:#010x}  R2: {:#010x}   R3: {:#010x}",
+                frame.r0, frame.r1, frame.r2, frame.r3
+            )?;
+            writeln!(
+                f,
+                " R12: {:#010x}  LR: {:#010x}  PC: {:#010x} xPSR: {:#010x}",
+                frame.r12, frame.lr, frame.pc, frame.xpsr
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads and decodes the fault status of a halted Cortex-M core.
+pub(crate) fn read_fault_status(core: &mut Core) -> Result<FaultStatus, Error> {
+    let core_type = core.core_type();
+
+    let xpsr_id = core
+        .registers()
+        .psr()
+        .ok_or_else(|| Error::Register("No xPSR register. Please report this as a bug.".into()))?
+        .id();
+    let xpsr: u32 = core.read_core_reg(xpsr_id)?;
+
+    let mut status = FaultStatus {
+        exception_number: xpsr & 0x1ff,
+        hfsr: None,
+        cfsr: None,
+        mmfar: None,
+        bfar: None,
+        sfsr: None,
+        sfar: None,
+        causes: vec![],
+        exception_frame: None,
+    };
+
+    // ARMv6-M and ARMv8-M Baseline have no fault status registers, all faults are reported as HardFault.
+    if has_main_extension(core, core_type)? {
+        let hfsr = core.read_word_32(HFSR)?;
+        let cfsr = core.read_word_32(CFSR)?;
+        status.causes.extend(set_causes(HFSR_CAUSES, hfsr));
+        status.causes.extend(set_causes(CFSR_CAUSES, cfsr));
+        status.hfsr = Some(hfsr);
+        status.cfsr = Some(cfsr);
+
+        if cfsr & MMARVALID != 0 {
+            status.mmfar = Some(core.read_word_32(MMFAR)?);
+        }
+        if cfsr & BFARVALID != 0 {
+            status.bfar = Some(core.read_word_32(BFAR)?);
+        }
+    }
+
+    // The secure fault registers only exist with the Security Extension,
+    // and are not accessible from the Non-secure state.
+    if core_type == CoreType::Armv8m {
+        match core.read_word_32(SFSR) {
+            Ok(sfsr) => {
+                status.causes.extend(set_causes(SFSR_CAUSES, sfsr));
+                status.sfsr = Some(sfsr);
+                if sfsr & SFARVALID != 0 {
+                    status.sfar = core.read_word_32(SFAR).ok();
+                }
+            }
+            Err(error) => tracing::debug!("SFSR is not accessible: {error}"),
+        }
+    }
+
+    status.exception_frame = read_exception_frame(core, core_type)?;
+
+    Ok(status)
+}
+
+/// Returns `true` if the core implements the ARMv7-M or the ARMv8-M Main Extension fault registers.
+fn has_main_extension(core: &mut Core, core_type: CoreType) -> Result<bool, Error> {
+    match core_type {
+        CoreType::Armv7m | CoreType::Armv7em => Ok(true),
+        // ARMv8-M Baseline and Mainline share a core type, the CPUID tells them apart.
+        CoreType::Armv8m => {
+            let cpuid = core.read_word_32(CPUID)?;
+            Ok((cpuid >> 16) & 0xf == CPUID_ARCHITECTURE_MAINLINE)
+        }
+        _ => Ok(false),
+    }
+}
+
+fn set_causes(
+    causes: &'static [(u32, FaultCause)],
+    value: u32,
+) -> impl Iterator<Item = FaultCause> {
+    causes
+        .iter()
+        .filter(move |(bit, _)| value & (1 << bit) != 0)
+        .map(|(_, cause)| *cause)
+}
+
+/// Reads the exception stack frame, if the link register holds an EXC_RETURN value.
+fn read_exception_frame(
+    core: &mut Core,
+    core_type: CoreType,
+) -> Result<Option<ExceptionStackFrame>, Error> {
+    let exc_return: u32 = core.read_core_reg(core.return_address())?;
+    if exc_return >> 24 != 0xff {
+        return Ok(None);
+    }
+
+    // EXC_RETURN.SPSEL selects the process stack, otherwise the main stack was used.
+    let process_stack = exc_return & (1 << 2) != 0;
+
+    // On ARMv8-M with the Security Extension, EXC_RETURN.S is the security state of the stack
+    // the frame was pushed to, and EXC_RETURN.ES the security state of the exception handler.
+    // MSP and PSP are the stack pointers of the current security state, so the stack pointer
+    // of the other security state has to be selected explicitly if they differ.
+    let secure_stack = exc_return & (1 << 6) != 0;
+    let secure_exception = exc_return & 1 != 0;
+    let stack_pointer = if core_type == CoreType::Armv8m && secure_stack != secure_exception {
+        match (secure_stack, process_stack) {
+            (false, false) => MSP_NS,
+            (false, true) => PSP_NS,
+            (true, false) => MSP_S,
+            (true, true) => PSP_S,
+        }
+    } else {
+        if process_stack {
+            core.registers().psp()
+        } else {
+            core.registers().msp()
+        }
+        .ok_or_else(|| {
+            Error::Register("No MSP or PSP register. Please report this as a bug.".into())
+        })?
+        .id()
+    };
+    let mut address: u32 = core.read_core_reg(stack_pointer)?;
+
+    // On ARMv8-M, EXC_RETURN.DCRS = 0 means that the callee saved registers and the integrity
+    // signature were stacked before the basic frame.
+    if core_type == CoreType::Armv8m && exc_return & (1 << 5) == 0 {
+        address += 0x28;
+    }
+
+    let mut words = [0u32; 8];
+    core.read_32(address.into(), &mut words)?;
+    let [r0, r1, r2, r3, r12, lr, pc, xpsr] = words;
+
+    Ok(Some(ExceptionStackFrame {
+        address,
+        r0,
+        r1,
+        r2,
+        r3,
+        r12,
+        lr,
+        pc,
+        xpsr,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{CoreDump, InstructionSet, RegisterId, RegisterValue};
+
+    const MSP: RegisterId = RegisterId(0b1_0001);
+    const XPSR: RegisterId = RegisterId(0b1_0000);
+    const LR: RegisterId = RegisterId(14);
+
+    /// CPUID of a Cortex-M33, an ARMv8-M Mainline core.
+    const CORTEX_M33_CPUID: u32 = 0x410F_D213;
+    /// CPUID of a Cortex-M23, an ARMv8-M Baseline core.
+    const CORTEX_M23_CPUID: u32 = 0x410C_D200;
+
+    fn core_dump(core_type: CoreType, exc_return: u32) -> CoreDump {
+        let mut registers = HashMap::new();
+        registers.insert(XPSR, RegisterValue::U32(0x0100_0003));
+        registers.insert(LR, RegisterValue::U32(exc_return));
+        registers.insert(MSP, RegisterValue::U32(0x2000_0100));
+
+        let mut stack = Vec::new();
+        for word in [1u32, 2, 3, 4, 12, 0x0800_0101, 0x0800_0200, 0x0100_0000] {
+            stack.extend_from_slice(&word.to_le_bytes());
+        }
+
+        let mut scb = vec![0u8; 0x14];
+        // CFSR: PRECISERR and BFARVALID
+        scb[0..4].copy_from_slice(&0x0000_8200u32.to_le_bytes());
+        // HFSR: FORCED
+        scb[4..8].copy_from_slice(&0x4000_0000u32.to_le_bytes());
+        // BFAR
+        scb[0x10..0x14].copy_from_slice(&0xdead_beefu32.to_le_bytes());
+
+        CoreDump {
+            registers,
+            data: vec![
+                (0x2000_0100..0x2000_0120, stack),
+                (CFSR..CFSR + 0x14, scb),
+                (CPUID..CPUID + 4, CORTEX_M33_CPUID.to_le_bytes().to_vec()),
+            ],
+            instruction_set: InstructionSet::Thumb2,
+            supports_native_64bit_access: false,
+            core_type,
+            fpu_support: false,
+            floating_point_register_count: Some(0),
+        }
+    }
+
+    #[test]
+    fn armv7m_escalated_bus_fault() {
+        let mut dump = core_dump(CoreType::Armv7m, 0xffff_fff9);
+        let status = read_fault_status(&mut dump.core()).unwrap();
+
+        assert!(status.is_fault());
+        assert_eq!(status.exception_number, 3);
+        assert_eq!(status.hfsr, Some(0x4000_0000));
+        assert_eq!(status.cfsr, Some(0x0000_8200));
+        assert_eq!(status.bfar, Some(0xdead_beef));
+        assert_eq!(status.mmfar, None);
+        assert_eq!(
+            status
+                .causes
+                .iter()
+                .map(|cause| cause.name)
+                .collect::<Vec<_>>(),
+            ["FORCED", "PRECISERR"]
+        );
+
+        let frame = status.exception_frame.unwrap();
+        assert_eq!(frame.address, 0x2000_0100);
+        assert_eq!(frame.r12, 12);
+        assert_eq!(frame.pc, 0x0800_0200);
+    }
+
+    #[test]
+    fn armv6m_has_no_fault_status_registers() {
+        let mut dump = core_dump(CoreType::Armv6m, 0xffff_fff9);
+        let status = read_fault_status(&mut dump.core()).unwrap();
+
+        assert!(status.is_fault());
+        assert_eq!(status.hfsr, None);
+        assert_eq!(status.cfsr, None);
+        assert!(status.causes.is_empty());
+        assert_eq!(status.exception_frame.unwrap().pc, 0x0800_0200);
+    }
+
+    #[test]
+    fn armv8m_mainline_has_fault_status_registers() {
+        let mut dump = core_dump(CoreType::Armv8m, 0xffff_ffb8);
+        let status = read_fault_status(&mut dump.core()).unwrap();
+
+        assert_eq!(status.cfsr, Some(0x0000_8200));
+        assert_eq!(status.exception_frame.unwrap().pc, 0x0800_0200);
+    }
+
+    #[test]
+    fn armv8m_baseline_has_no_fault_status_registers() {
+        let mut dump = core_dump(CoreType::Armv8m, 0xffff_ffb8);
+        dump.data[2].1 = CORTEX_M23_CPUID.to_le_bytes().to_vec();
+        let status = read_fault_status(&mut dump.core()).unwrap();
+
+        assert_eq!(status.hfsr, None);
+        assert_eq!(status.cfsr, None);
+        assert!(status.causes.is_empty());
+    }
+
+    #[test]
+    fn armv8m_non_secure_frame_in_secure_handler() {
+        // EXC_RETURN with S = 0 and ES = 1: a Non-secure thread was interrupted by a Secure exception.
+        let mut dump = core_dump(CoreType::Armv8m, 0xffff_ffb9);
+        dump.registers.insert(MSP, RegisterValue::U32(0x3000_0000));
+        dump.registers
+            .insert(MSP_NS, RegisterValue::U32(0x2000_0100));
+        let status = read_fault_status(&mut dump.core()).unwrap();
+
+        let frame = status.exception_frame.unwrap();
+        assert_eq!(frame.address, 0x2000_0100);
+        assert_eq!(frame.pc, 0x0800_0200);
+    }
+
+    #[test]
+    fn no_exception_frame_without_exc_return() {
+        let mut dump = core_dump(CoreType::Armv7em, 0x0800_0101);
+        let status = read_fault_status(&mut dump.core()).unwrap();
+
+        assert_eq!(status.exception_frame, None);
+    }
+}
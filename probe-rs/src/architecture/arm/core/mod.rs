@@ -10,6 +10,7 @@ pub mod armv7a;
 pub mod armv7m;
 pub mod armv8a;
 pub mod armv8m;
+pub mod fault;
 
 pub(crate) mod armv7a_debug_regs;
 pub(crate) mod armv8a_debug_regs;
@@ -0,0 +1,283 @@
+//! Checking the quality of the link to an ARM target, to select the protocol speed automatically.
+//!
+//! [`negotiate_speed`] starts at the highest speed the probe accepts, and steps down until
+//! [`verify_link`] passes. During the session, the error rate of the DAP transfers is watched,
+//! and the speed is lowered once if errors pile up.
+
+use super::{
+    ap::{MemoryAp, TAR},
+    dp::DPIDR,
+    AccessPort, ArmError, ArmProbeInterface, DapError, DpAddress, Register,
+};
+use crate::{
+    probe::{DebugProbeError, Probe},
+    Error,
+};
+
+/// The speeds tried by the automatic speed selection, in kHz.
+pub const AUTO_SPEED_STEPS_KHZ: &[u32] = &[24_000, 12_000, 8_000, 4_000, 2_000, 1_000, 400, 100];
+
+/// How often DPIDR is read by the link test.
+const IDCODE_READS: usize = 8;
+
+/// The values written to TAR and read back by the link test.
+///
+/// They toggle every bit of the transfer, but keep the lowest bits clear,
+/// as some MEM-APs only implement word-aligned addresses.
+const TAR_PATTERNS: [u32; 4] = [0xAAAA_AAA8, 0x5555_5554, 0xFFFF_FFFC, 0x0000_0000];
+
+/// The number of DAP accesses over which the error rate is measured.
+const MONITOR_WINDOW: u32 = 1000;
+
+/// The number of failed accesses within [`MONITOR_WINDOW`] which lowers the speed.
+const MONITOR_MAX_ERRORS: u32 = 10;
+
+/// The next speed of [`AUTO_SPEED_STEPS_KHZ`] below `speed_khz`.
+pub fn next_lower_speed(speed_khz: u32) -> Option<u32> {
+    AUTO_SPEED_STEPS_KHZ
+        .iter()
+        .copied()
+        .find(|&step| step < speed_khz)
+}
+
+/// Sets the highest speed of [`AUTO_SPEED_STEPS_KHZ`] which the probe accepts.
+///
+/// Returns the speed used by the probe.
+pub fn select_max_speed(probe: &mut Probe) -> Result<u32, DebugProbeError> {
+    for &step in AUTO_SPEED_STEPS_KHZ {
+        match probe.set_speed(step) {
+            Ok(speed_khz) => return Ok(speed_khz),
+            Err(DebugProbeError::UnsupportedSpeed(_)) => continue,
+            Err(error) => return Err(error),
+        }
+    }
+
+    tracing::warn!(
+        "The probe accepts none of the automatic speeds, keeping {} kHz",
+        probe.speed_khz()
+    );
+    Ok(probe.speed_khz())
+}
+
+/// Tests the link to the debug port `dp`.
+///
+/// DPIDR is read several times and has to stay the same. If a `memory_ap` is given,
+/// bit patterns are written to its TAR and read back, which also exercises the
+/// RDBUFF reads of the posted AP reads. TAR is restored afterwards.
+pub fn verify_link(
+    interface: &mut dyn ArmProbeInterface,
+    dp: DpAddress,
+    memory_ap: Option<MemoryAp>,
+) -> Result<(), ArmError> {
+    let idcode = interface.read_raw_dp_register(dp, DPIDR::ADDRESS)?;
+    for _ in 1..IDCODE_READS {
+        let read = interface.read_raw_dp_register(dp, DPIDR::ADDRESS)?;
+        check_readback(idcode, read)?;
+    }
+
+    if let Some(memory_ap) = memory_ap {
+        let address = memory_ap.ap_address();
+        let original = interface.read_raw_ap_register(address, TAR::ADDRESS)?;
+
+        let result = TAR_PATTERNS.iter().try_for_each(|&pattern| {
+            interface.write_raw_ap_register(address, TAR::ADDRESS, pattern)?;
+            let read = interface.read_raw_ap_register(address, TAR::ADDRESS)?;
+            check_readback(pattern, read)
+        });
+
+        interface.write_raw_ap_register(address, TAR::ADDRESS, original)?;
+        result?;
+    }
+
+    Ok(())
+}
+
+fn check_readback(expected: u32, read: u32) -> Result<(), ArmError> {
+    if expected == read {
+        Ok(())
+    } else {
+        Err(ArmError::LinkTestMismatch { expected, read })
+    }
+}
+
+/// Whether `error` is a transfer response caused by a bad signal, rather than by the target.
+fn is_link_dap_error(error: &DapError) -> bool {
+    matches!(
+        error,
+        DapError::WaitResponse | DapError::NoAcknowledge | DapError::IncorrectParity
+    )
+}
+
+/// Whether `error`, or any error causing it, is a failure of the link which a lower speed may fix.
+///
+/// These are unusable transfer responses, a failed link test and I/O errors of the probe.
+fn is_link_error(error: &Error) -> bool {
+    let error: &(dyn std::error::Error + 'static) = error;
+    std::iter::successors(Some(error), |&e| e.source()).any(|cause| {
+        if let Some(error) = cause.downcast_ref::<DapError>() {
+            is_link_dap_error(error)
+        } else if let Some(error) = cause.downcast_ref::<ArmError>() {
+            matches!(error, ArmError::LinkTestMismatch { .. })
+        } else if let Some(error) = cause.downcast_ref::<DebugProbeError>() {
+            matches!(error, DebugProbeError::Usb(_))
+        } else {
+            false
+        }
+    })
+}
+
+/// Connects to the target at decreasing speeds, until the link test passes.
+///
+/// `probe` has to be attached, usually at the speed set by [`select_max_speed`].
+/// `connect` creates the ARM interface from the probe, e.g. by initializing the debug port.
+/// If connecting or the link test fails because of the link, the probe is attached again at
+/// the next lower speed. Any other error is returned right away.
+///
+/// If no speed works, the probe is handed back with the last error.
+pub fn negotiate_speed<F>(
+    mut probe: Probe,
+    dp: DpAddress,
+    memory_ap: Option<MemoryAp>,
+    mut connect: F,
+) -> Result<Box<dyn ArmProbeInterface>, (Probe, Error)>
+where
+    F: FnMut(Probe) -> Result<Box<dyn ArmProbeInterface>, (Probe, Error)>,
+{
+    loop {
+        let speed_khz = probe.speed_khz();
+
+        let error = match connect(probe) {
+            Ok(mut interface) => match verify_link(&mut *interface, dp, memory_ap) {
+                Ok(()) => {
+                    tracing::info!("Selected a protocol speed of {} kHz", speed_khz);
+                    return Ok(interface);
+                }
+                Err(error) => {
+                    probe = interface.close();
+                    Error::Arm(error)
+                }
+            },
+            Err((returned, error)) => {
+                probe = returned;
+                error
+            }
+        };
+
+        if !is_link_error(&error) {
+            return Err((probe, error));
+        }
+
+        let Some(lower_speed_khz) = next_lower_speed(speed_khz) else {
+            return Err((probe, error));
+        };
+
+        tracing::warn!(
+            "The link test failed at {} kHz, retrying at {} kHz: {}",
+            speed_khz,
+            lower_speed_khz,
+            error
+        );
+
+        let reattached = probe.detach().and_then(|()| {
+            probe.set_speed(lower_speed_khz)?;
+            probe.attach_to_unspecified()
+        });
+        if let Err(error) = reattached {
+            return Err((probe, error));
+        }
+    }
+}
+
+/// Watches the error rate of the DAP accesses during a session.
+#[derive(Debug, Default)]
+pub(crate) struct LinkMonitor {
+    accesses: u32,
+    errors: u32,
+}
+
+impl LinkMonitor {
+    /// Records the result of a DAP access.
+    ///
+    /// Returns `true` once the errors piled up, and the speed should be lowered.
+    pub(crate) fn record<T>(&mut self, result: &Result<T, ArmError>) -> bool {
+        if self.accesses == MONITOR_WINDOW {
+            self.accesses = 0;
+            self.errors = 0;
+        }
+
+        self.accesses += 1;
+        if matches!(result, Err(ArmError::Dap(error)) if is_link_dap_error(error)) {
+            self.errors += 1;
+        }
+
+        self.errors >= MONITOR_MAX_ERRORS
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::architecture::arm::DapError;
+
+    #[test]
+    fn lower_speeds() {
+        assert_eq!(next_lower_speed(24_000), Some(12_000));
+        assert_eq!(next_lower_speed(5_000), Some(4_000));
+        assert_eq!(next_lower_speed(100), None);
+    }
+
+    #[test]
+    fn monitor_errors_pile_up() {
+        let mut monitor = LinkMonitor::default();
+        let error: Result<(), ArmError> = Err(DapError::WaitResponse.into());
+
+        for _ in 1..MONITOR_MAX_ERRORS {
+            assert!(!monitor.record(&error));
+        }
+        assert!(monitor.record(&error));
+    }
+
+    #[test]
+    fn monitor_ignores_fault_responses() {
+        let mut monitor = LinkMonitor::default();
+        let error: Result<(), ArmError> = Err(DapError::FaultResponse.into());
+
+        for _ in 0..MONITOR_MAX_ERRORS * 2 {
+            assert!(!monitor.record(&error));
+        }
+    }
+
+    #[test]
+    fn link_errors() {
+        assert!(is_link_error(&Error::Arm(DapError::NoAcknowledge.into())));
+        assert!(is_link_error(&Error::Arm(ArmError::LinkTestMismatch {
+            expected: 0x5555_5554,
+            read: 0x5555_5555,
+        })));
+        assert!(is_link_error(&Error::Probe(DebugProbeError::Usb(
+            std::io::ErrorKind::BrokenPipe.into()
+        ))));
+        assert!(is_link_error(&Error::Arm(ArmError::Probe(
+            DebugProbeError::Usb(std::io::ErrorKind::TimedOut.into())
+        ))));
+
+        assert!(!is_link_error(&Error::Arm(DapError::FaultResponse.into())));
+        assert!(!is_link_error(&Error::Arm(ArmError::Timeout)));
+        assert!(!is_link_error(&Error::Probe(
+            DebugProbeError::TargetNotFound
+        )));
+    }
+
+    #[test]
+    fn monitor_errors_spread_out() {
+        let mut monitor = LinkMonitor::default();
+        let error: Result<(), ArmError> = Err(DapError::WaitResponse.into());
+
+        for _ in 0..MONITOR_MAX_ERRORS * 3 {
+            assert!(!monitor.record(&error));
+            for _ in 0..MONITOR_WINDOW / 2 {
+                assert!(!monitor.record(&Ok(())));
+            }
+        }
+    }
+}
@@ -5,12 +5,14 @@ pub(crate) mod communication_interface;
 pub mod component;
 pub(crate) mod core;
 pub mod dp;
+pub mod link_quality;
 pub mod memory;
+pub mod multidrop;
 pub mod sequences;
 pub mod swo;
 mod traits;
 
-pub use self::core::{armv6m, armv7a, armv7m, armv8a, armv8m, Dump};
+pub use self::core::{armv6m, armv7a, armv7m, armv8a, armv8m, fault, Dump};
 use self::{
     ap::{AccessPort, AccessPortError},
     communication_interface::RegisterParseError,
@@ -128,6 +130,15 @@ pub enum ArmError {
     #[error("The operation requires the following extension(s): {0:?}")]
     ExtensionRequired(&'static [&'static str]),
 
+    /// A value read back during the link test did not match the expected value.
+    #[error("The link test read {read:#010x} instead of {expected:#010x}.")]
+    LinkTestMismatch {
+        /// The expected value.
+        expected: u32,
+        /// The value which was read.
+        read: u32,
+    },
+
     /// Any other error occurred.
     Other(#[from] anyhow::Error),
 }
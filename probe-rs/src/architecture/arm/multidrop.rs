@@ -0,0 +1,298 @@
+//! Discovery of the debug ports on an SWD multi-drop bus.
+//!
+//! With SWD protocol version 2, several debug ports can share the same SWD lines.
+//! A debug port only responds after it was selected by writing its `TARGETSEL` value,
+//! which has the same layout as the TARGETID register, with the instance number from DLPIDR
+//! in the upper four bits. Which values are in use can't be read back, so
+//! [`scan`] tries a list of candidates, usually the ones from [`targetsel_candidates`].
+
+use super::{
+    communication_interface::DapProbe,
+    dp::{Select, DPIDR, TARGETID},
+    sequences::{ArmDebugSequence, DefaultArmSequence},
+    ArmError, DpAddress, PortType, Register,
+};
+use crate::probe::WireProtocol;
+use probe_rs_target::CoreAccessOptions;
+
+/// The bits of TARGETSEL which hold the instance number.
+const INSTANCE_MASK: u32 = 0xF000_0000;
+
+/// A debug port which responded on an SWD multi-drop bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultidropDebugPort {
+    /// The `TARGETSEL` value which selects the debug port.
+    pub targetsel: u32,
+    /// The value of the DPIDR register.
+    pub dpidr: u32,
+    /// The value of the TARGETID register, or `None` if it could not be read.
+    pub targetid: Option<u32>,
+}
+
+impl MultidropDebugPort {
+    /// The address to access this debug port with.
+    pub fn address(&self) -> DpAddress {
+        DpAddress::Multidrop(self.targetsel)
+    }
+}
+
+/// Collects the `TARGETSEL` values to try when scanning a multi-drop bus.
+///
+/// These are the values used by the target descriptions in the registry. As parts with
+/// several identical debug ports only differ in the instance number, every part is
+/// tried with all sixteen instance numbers.
+pub fn targetsel_candidates() -> Vec<u32> {
+    let families = crate::config::families().unwrap_or_default();
+
+    let psels = families
+        .iter()
+        .flat_map(|family| &family.variants)
+        .flat_map(|chip| &chip.cores)
+        .filter_map(|core| match &core.core_access_options {
+            CoreAccessOptions::Arm(options) if options.psel != 0 => Some(options.psel),
+            _ => None,
+        });
+
+    expand_instances(psels)
+}
+
+fn expand_instances(psels: impl IntoIterator<Item = u32>) -> Vec<u32> {
+    let mut parts = psels
+        .into_iter()
+        .map(|psel| psel & !INSTANCE_MASK)
+        .collect::<Vec<_>>();
+    parts.sort_unstable();
+    parts.dedup();
+
+    parts
+        .into_iter()
+        .flat_map(|part| (0..16).map(move |instance| instance << 28 | part))
+        .collect()
+}
+
+/// Scans the multi-drop bus for debug ports, by selecting each of the `candidates` in turn.
+///
+/// Only SWD supports multi-drop, so the probe has to use it. The debug ports are
+/// only selected, and not powered up.
+pub fn scan(
+    interface: &mut dyn DapProbe,
+    candidates: &[u32],
+) -> Result<Vec<MultidropDebugPort>, ArmError> {
+    if interface.active_protocol() != Some(WireProtocol::Swd) {
+        return Err(ArmError::Other(anyhow::anyhow!(
+            "SWD multi-drop requires the SWD protocol"
+        )));
+    }
+
+    let sequence = DefaultArmSequence(());
+    let mut found = Vec::new();
+
+    for &targetsel in candidates {
+        if let Err(error) = sequence.debug_port_setup(interface, DpAddress::Multidrop(targetsel)) {
+            tracing::debug!(
+                "No debug port with TARGETSEL {:#010x}: {}",
+                targetsel,
+                error
+            );
+            continue;
+        }
+
+        let dpidr = interface.raw_read_register(PortType::DebugPort, DPIDR::ADDRESS)?;
+
+        let targetid = match read_targetid(interface) {
+            Ok(targetid) => Some(targetid),
+            Err(error) => {
+                tracing::warn!(
+                    "Failed to read TARGETID of the debug port with TARGETSEL {:#010x}: {}",
+                    targetsel,
+                    error
+                );
+                None
+            }
+        };
+
+        tracing::debug!(
+            "Found debug port with TARGETSEL {:#010x}: DPIDR {:#010x}, TARGETID {:x?}",
+            targetsel,
+            dpidr,
+            targetid
+        );
+
+        found.push(MultidropDebugPort {
+            targetsel,
+            dpidr,
+            targetid,
+        });
+    }
+
+    Ok(found)
+}
+
+/// Reads TARGETID from DP bank 2, and switches back to bank 0.
+fn read_targetid(interface: &mut dyn DapProbe) -> Result<u32, ArmError> {
+    interface.raw_write_register(PortType::DebugPort, Select::ADDRESS, 2)?;
+    let targetid = interface.raw_read_register(PortType::DebugPort, TARGETID::ADDRESS & 0xf);
+
+    // Switch back even if the read failed, the next candidate expects bank 0.
+    let restored = interface.raw_write_register(PortType::DebugPort, Select::ADDRESS, 0);
+
+    let targetid = targetid?;
+    restored?;
+    Ok(targetid)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{architecture::arm::DapError, probe::fake_probe::FakeProbe};
+
+    /// A debug port on the simulated multi-drop bus.
+    struct BusDebugPort {
+        targetsel: u32,
+        dpidr: u32,
+        /// The number of TARGETID reads which succeed, before all further reads fail.
+        targetid_reads: usize,
+    }
+
+    /// The state of the simulated bus.
+    #[derive(Default)]
+    struct Bus {
+        /// The index of the selected debug port, if any responds.
+        selected: Option<usize>,
+        dp_bank: u32,
+        targetid_reads: usize,
+    }
+
+    /// A fake probe on a multi-drop bus with the given debug ports.
+    fn multidrop_probe(debug_ports: Vec<BusDebugPort>) -> FakeProbe {
+        let debug_ports = Arc::new(debug_ports);
+        let bus = Arc::new(Mutex::new(Bus::default()));
+        let mut probe = FakeProbe::new();
+
+        let (ports, state) = (debug_ports.clone(), bus.clone());
+        probe.set_swj_sequence_handler(Box::new(move |bit_len, bits| {
+            // A TARGETSEL write is a request for DP register 0xC with the value appended.
+            if bit_len == 48 && bits & 0x1fff == 0x1f99 {
+                let targetsel = (bits >> 13) as u32;
+                let mut bus = state.lock().unwrap();
+                bus.selected = ports.iter().position(|dp| dp.targetsel == targetsel);
+                bus.targetid_reads = 0;
+            }
+            Ok(())
+        }));
+
+        let (ports, state) = (debug_ports.clone(), bus.clone());
+        probe.set_dap_register_read_handler(Box::new(move |port, address| {
+            let mut bus = state.lock().unwrap();
+            let Some(dp) = bus.selected.map(|index| &ports[index]) else {
+                return Err(DapError::NoAcknowledge.into());
+            };
+            assert_eq!(port, PortType::DebugPort);
+
+            match (address, bus.dp_bank) {
+                (0x0, _) => Ok(dp.dpidr),
+                (0x4, 0) => Ok(0),
+                (0x4, 2) if bus.targetid_reads < dp.targetid_reads => {
+                    bus.targetid_reads += 1;
+                    Ok(dp.targetsel & 0x0FFF_FFFF)
+                }
+                (0x4, 2) => Err(DapError::FaultResponse.into()),
+                (0x4, 3) => Ok(dp.targetsel & 0xF000_0000),
+                _ => panic!("Unexpected read of DP register {address:#x}"),
+            }
+        }));
+
+        probe.set_dap_register_write_handler(Box::new(move |_port, address, value| {
+            let mut bus = bus.lock().unwrap();
+            if bus.selected.is_none() {
+                return Err(DapError::NoAcknowledge.into());
+            }
+            if address == Select::ADDRESS {
+                bus.dp_bank = value & 0xf;
+            }
+            Ok(())
+        }));
+
+        probe
+    }
+
+    #[test]
+    fn scan_finds_debug_ports() {
+        let mut probe = multidrop_probe(vec![
+            BusDebugPort {
+                targetsel: 0x0100_2927,
+                dpidr: 0x0BC1_2477,
+                targetid_reads: usize::MAX,
+            },
+            BusDebugPort {
+                targetsel: 0x1100_2927,
+                dpidr: 0x0BC1_2477,
+                targetid_reads: usize::MAX,
+            },
+        ]);
+
+        let found = scan(&mut probe, &[0x0100_2927, 0x1100_2927, 0x2100_2927]).unwrap();
+
+        assert_eq!(
+            found,
+            [
+                MultidropDebugPort {
+                    targetsel: 0x0100_2927,
+                    dpidr: 0x0BC1_2477,
+                    targetid: Some(0x0100_2927),
+                },
+                MultidropDebugPort {
+                    targetsel: 0x1100_2927,
+                    dpidr: 0x0BC1_2477,
+                    targetid: Some(0x0100_2927),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_continues_after_unreadable_targetid() {
+        let mut probe = multidrop_probe(vec![
+            BusDebugPort {
+                targetsel: 0x0100_2927,
+                dpidr: 0x0BC1_2477,
+                // Only the check while connecting succeeds.
+                targetid_reads: 1,
+            },
+            BusDebugPort {
+                targetsel: 0x0000_1234,
+                dpidr: 0x6BA0_2477,
+                targetid_reads: usize::MAX,
+            },
+        ]);
+
+        let found = scan(&mut probe, &[0x0100_2927, 0x0000_1234]).unwrap();
+
+        assert_eq!(
+            found,
+            [
+                MultidropDebugPort {
+                    targetsel: 0x0100_2927,
+                    dpidr: 0x0BC1_2477,
+                    targetid: None,
+                },
+                MultidropDebugPort {
+                    targetsel: 0x0000_1234,
+                    dpidr: 0x6BA0_2477,
+                    targetid: Some(0x0000_1234),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn candidates_cover_all_instances() {
+        let candidates = expand_instances([0x0100_2927, 0x1100_2927, 0xF100_2927]);
+
+        assert_eq!(candidates.len(), 16);
+        assert_eq!(candidates[0], 0x0100_2927);
+        assert_eq!(candidates[15], 0xF100_2927);
+    }
+}
@@ -15,6 +15,7 @@ use crate::{
         dp::{DLPIDR, TARGETID},
         ArmProbeInterface,
     },
+    flashing::{option_bytes::OptionBytesSequence, protection::ProtectionSequence},
     probe::{DebugProbeError, WireProtocol},
     MemoryMappedRegister,
 };
@@ -956,6 +957,16 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
     fn debug_erase_sequence(&self) -> Option<Arc<dyn DebugEraseSequence>> {
         None
     }
+
+    /// Return the implementation to read and write the option bytes, if the device supports it
+    fn option_bytes_sequence(&self) -> Option<Arc<dyn OptionBytesSequence>> {
+        None
+    }
+
+    /// Return the implementation to manage the readout protection, if the device supports it
+    fn protection_sequence(&self) -> Option<Arc<dyn ProtectionSequence>> {
+        None
+    }
 }
 
 /// Chip-Erase Handling via the Device's Debug Interface
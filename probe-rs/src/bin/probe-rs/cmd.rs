@@ -6,14 +6,21 @@ pub mod chip;
 pub mod dap_server;
 pub mod debug;
 pub mod download;
+pub mod dump_flash;
 pub mod erase;
+pub mod fault;
 pub mod gdb;
 pub mod info;
 pub mod itm;
 pub mod list;
+pub mod option_bytes;
+pub mod power;
 pub mod profile;
+pub mod protect;
 pub mod read;
 pub mod reset;
 pub mod run;
+pub mod serve_probe;
 pub mod trace;
+pub mod uart;
 pub mod write;
@@ -53,6 +53,13 @@ pub struct Cmd {
     /// both reduce the amount of jitter, and also quantify it (via standard deviation calcs)
     #[clap(long = "iterations", value_parser= parse_usize, default_value="5")]
     iterations: usize,
+
+    /// Repeat each test with command queuing disabled and report the speedup.
+    ///
+    /// Only supported by probes which can batch several commands into one
+    /// USB transfer, such as CMSIS-DAP probes with DAP_ExecuteCommands.
+    #[clap(long = "compare-command-queuing")]
+    compare_command_queuing: bool,
 }
 
 fn parse_usize(src: &str) -> Result<usize, ParseIntError> {
@@ -112,7 +119,31 @@ impl Cmd {
                     self.address,
                     self.word_size,
                     self.iterations,
-                );
+                    true,
+                )
+                .and_then(|queued| {
+                    if !self.compare_command_queuing {
+                        return Ok(());
+                    }
+                    let unqueued = Cmd::benchmark(
+                        &common_options,
+                        lister,
+                        *speed,
+                        size,
+                        self.address,
+                        self.word_size,
+                        self.iterations,
+                        false,
+                    )?;
+                    if let (Some(queued), Some(unqueued)) = (queued, unqueued) {
+                        println!(
+                            "Command queuing speedup: Read: {:.2}x, Write: {:.2}x\n",
+                            queued.read / unqueued.read,
+                            queued.write / unqueued.write
+                        );
+                    }
+                    Ok(())
+                });
                 match res {
                     core::result::Result::Ok(_) => {}
                     core::result::Result::Err(e) => {
@@ -147,7 +178,8 @@ impl Cmd {
         Ok(())
     }
 
-    /// Run a specific benchmark
+    /// Run a specific benchmark, returning the mean throughput if it succeeded
+    #[allow(clippy::too_many_arguments)]
     fn benchmark(
         common_options: &LoadedProbeOptions,
         lister: &Lister,
@@ -156,18 +188,28 @@ impl Cmd {
         address: u64,
         word_size: u32,
         iterations: usize,
-    ) -> Result<(), anyhow::Error> {
+        command_queuing: bool,
+    ) -> Result<Option<Throughput>, anyhow::Error> {
         let mut probe = common_options.attach_probe(lister)?;
         let target = common_options.get_target_selector()?;
+        if !command_queuing && probe.set_command_queuing(false).is_err() {
+            println!("Probe does not support command queuing, skipping comparison\n");
+            return Ok(None);
+        }
         if probe.set_speed(speed).is_ok() {
             let mut session = common_options.attach_session(probe, target)?;
             let mut test = TestData::new(address, word_size, size);
             println!(
-                "Test: Speed {}, Word size {}bit, Data length {} bytes, Number of iterations {}",
+                "Test: Speed {}, Word size {}bit, Data length {} bytes, Number of iterations {}{}",
                 speed,
                 word_size,
                 test.data_type.size() * size,
-                iterations
+                iterations,
+                if command_queuing {
+                    ""
+                } else {
+                    ", Command queuing disabled"
+                }
             );
             let mut core = session.core(0).context("Failed to attach to core")?;
             core.halt(Duration::from_millis(100))
@@ -187,11 +229,15 @@ impl Cmd {
                     break 'inner;
                 }
             }
+            let throughput = Throughput {
+                read: mean(&read_results).expect("invalid mean"),
+                write: mean(&write_results).expect("invalid mean"),
+            };
             println!(
                 "Results: Read: {:.2} bytes/s Std Dev {:.2}, Write: {:.2} bytes/s Std Dev {:.2}",
-                mean(&read_results).expect("invalid mean"),
+                throughput.read,
                 std_deviation(&read_results).expect("invalid std deviation"),
-                mean(&write_results).expect("invalid mean"),
+                throughput.write,
                 std_deviation(&write_results).expect("invalid std deviation")
             );
             if read_results.len() != iterations || write_results.len() != iterations {
@@ -204,13 +250,20 @@ impl Cmd {
             }
             // Insert another blank line to visually seperate results
             println!();
+            Ok(Some(throughput))
         } else {
             println!("failed to set speed {}", speed);
+            Ok(None)
         }
-        Ok(())
     }
 }
 
+/// Mean throughput of a test run, in bytes/s
+struct Throughput {
+    read: f64,
+    write: f64,
+}
+
 impl DataType {
     pub fn new(word_size: u32) -> DataType {
         match word_size {
@@ -127,6 +127,19 @@ pub struct Rtt {
     pub log_enabled: bool,
     /// Where to save rtt history buffer relative to manifest path.
     pub log_path: PathBuf,
+    /// The tab showing the target UART routed through the probe.
+    pub uart: UartTab,
+}
+
+/// The config for the RTTUI tab showing the target UART, for probes which route one.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct UartTab {
+    pub enabled: bool,
+    /// Baud rate of the target UART.
+    pub baud: u32,
+    /// The name of the tab.
+    pub name: String,
 }
 
 impl Rtt {
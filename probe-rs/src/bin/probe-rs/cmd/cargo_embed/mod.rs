@@ -8,6 +8,7 @@ use colored::*;
 use parking_lot::FairMutex;
 use probe_rs::gdb_server::GdbInstanceConfiguration;
 use probe_rs::probe::list::Lister;
+use probe_rs::probe::uart::UartConfig;
 use probe_rs::rtt::ScanRegion;
 use probe_rs::{probe::DebugProbeSelector, Session};
 use probe_rs_target::MemoryRegion;
@@ -175,6 +176,8 @@ fn main_try(mut args: Vec<OsString>, offset: UtcOffset) -> Result<()> {
                 vendor_id: u16::from_str_radix(vid, 16)?,
                 product_id: u16::from_str_radix(pid, 16)?,
                 serial_number: config.probe.serial.clone(),
+                remote: None,
+                simulator: None,
             }),
             (vid, pid) => {
                 if vid.is_some() {
@@ -200,9 +203,17 @@ fn main_try(mut args: Vec<OsString>, offset: UtcOffset) -> Result<()> {
         protocol: Some(config.probe.protocol),
         probe: selector,
         speed: config.probe.speed,
+        auto_speed: false,
+        dp: None,
+        ftdi_layout: None,
+        ftdi_layout_file: None,
         connect_under_reset: config.general.connect_under_reset,
+        wait_for_target_voltage: None,
         dry_run: false,
         allow_erase_all: config.flashing.enabled || config.gdb.enabled,
+        record_probe: None,
+        replay_probe: None,
+        virtual_probe: false,
     };
 
     let (mut session, probe_options) = match probe_options.simple_attach(&lister) {
@@ -248,12 +259,14 @@ fn main_try(mut args: Vec<OsString>, offset: UtcOffset) -> Result<()> {
             restore_unwritten: config.flashing.restore_unwritten_bytes,
             flash_layout_output_path: None,
             verify: false,
+            skip_unchanged: false,
+            report: None,
         };
         let format_options = FormatOptions::default();
         let loader = build_loader(&mut session, path, format_options, image_instr_set)?;
         run_flash_download(
             &mut session,
-            path,
+            &[path],
             &download_options,
             &probe_options,
             loader,
@@ -436,7 +449,9 @@ fn run_rttui_app(
         / 1_000_000;
 
     let logname = format!("{name}_{chip_name}_{timestamp_millis}");
-    let mut app = rttui::app::App::new(rtt, config, logname)?;
+    let uart = config.rtt.uart.enabled && enable_uart(session, config.rtt.uart.baud)?;
+
+    let mut app = rttui::app::App::new(rtt, config, logname, uart)?;
     loop {
         app.render();
 
@@ -450,12 +465,31 @@ fn run_rttui_app(
             }
 
             app.poll_rtt(&mut core)?;
+            drop(core);
+
+            if let Some(uart) = session_handle.get_uart_interface() {
+                app.poll_uart(uart)?;
+            }
         }
 
         std::thread::sleep(Duration::from_millis(10));
     }
 }
 
+/// Enables the target UART of the probe, returning whether it is available.
+fn enable_uart(session: &FairMutex<Session>, baud: u32) -> anyhow::Result<bool> {
+    let mut session_handle = session.lock();
+    let Some(uart) = session_handle.get_uart_interface() else {
+        tracing::warn!("The probe does not provide access to a target UART, not showing it.");
+        return Ok(false);
+    };
+
+    uart.enable_uart(&UartConfig::new(baud))
+        .context("Failed to enable the target UART")?;
+
+    Ok(true)
+}
+
 #[allow(clippy::too_many_arguments)]
 fn attach_to_rtt_shared(
     session: &FairMutex<Session>,
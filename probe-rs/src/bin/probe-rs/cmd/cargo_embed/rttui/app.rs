@@ -4,7 +4,7 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use probe_rs::Core;
+use probe_rs::{probe::uart::UartAccess, Core};
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout},
@@ -16,7 +16,10 @@ use std::{collections::BTreeMap, io::Write};
 use std::{path::PathBuf, sync::mpsc::TryRecvError};
 
 use crate::{
-    cmd::cargo_embed::rttui::{channel::ChannelData, tab::TabConfig},
+    cmd::cargo_embed::rttui::{
+        channel::{ChannelData, UartChannel},
+        tab::{TabConfig, TabSource},
+    },
     util::rtt::{DefmtState, RttActiveDownChannel, RttActiveTarget},
 };
 
@@ -40,10 +43,17 @@ pub struct App {
 
     down_channels: BTreeMap<usize, RttActiveDownChannel>,
     up_channels: BTreeMap<usize, UpChannel>,
+    uart: Option<UartChannel>,
 }
 
 impl App {
-    pub fn new(rtt: RttActiveTarget, config: config::Config, logname: String) -> Result<Self> {
+    /// Creates the UI. If `uart` is set, the target UART is shown in an additional tab.
+    pub fn new(
+        rtt: RttActiveTarget,
+        config: config::Config,
+        logname: String,
+        uart: bool,
+    ) -> Result<Self> {
         let mut tab_config = config.rtt.tabs;
 
         // Create channel states
@@ -112,6 +122,9 @@ impl App {
                 );
             }
         }
+        if uart {
+            tabs.push(Tab::new_uart(config.rtt.uart.name));
+        }
 
         // Code farther down relies on tabs being configured and might panic
         // otherwise.
@@ -158,6 +171,7 @@ impl App {
                 .into_iter()
                 .map(|(num, (channel, socket))| (num, UpChannel::new(channel, socket)))
                 .collect(),
+            uart: uart.then(UartChannel::default),
         })
     }
 
@@ -173,7 +187,8 @@ impl App {
                 let width = chunks[1].width as usize;
 
                 let current_tab = &mut self.tabs[self.current_tab];
-                current_tab.update_messages(width, &self.up_channels);
+                let data = channel_data(current_tab.source(), &self.up_channels, &self.uart);
+                current_tab.update_messages(width, data);
 
                 let messages = List::new(current_tab.messages(height))
                     .block(Block::default().borders(Borders::NONE));
@@ -216,12 +231,9 @@ impl App {
                 };
 
                 for (i, tab) in self.tabs.iter().enumerate() {
-                    let up_channel = self
-                        .up_channels
-                        .get(&tab.up_channel())
-                        .expect("up channel disappeared");
+                    let data = channel_data(tab.source(), &self.up_channels, &self.uart);
 
-                    let extension = match up_channel.data {
+                    let extension = match data {
                         ChannelData::Strings { .. } => "txt",
                         ChannelData::Binary { .. } => "dat",
                     };
@@ -234,7 +246,7 @@ impl App {
                         sanitize_filename::sanitize_with_options(name, sanitize_options);
                     let final_path = path.join(sanitized_name);
 
-                    match &up_channel.data {
+                    match data {
                         ChannelData::Strings { messages } => {
                             let mut file = match std::fs::File::create(&final_path) {
                                 Ok(file) => file,
@@ -302,8 +314,42 @@ impl App {
         Ok(())
     }
 
+    /// Exchanges data with the target UART, if it is shown.
+    pub fn poll_uart(&mut self, uart: &mut dyn UartAccess) -> Result<()> {
+        if let Some(channel) = self.uart.as_mut() {
+            channel.poll_uart(uart)?;
+        }
+
+        Ok(())
+    }
+
     pub fn push_rtt(&mut self, core: &mut Core) {
-        _ = self.tabs[self.current_tab].send_input(core, &mut self.down_channels);
+        let tab = &mut self.tabs[self.current_tab];
+        match tab.source() {
+            TabSource::UpChannel(_) => _ = tab.send_input(core, &mut self.down_channels),
+            TabSource::Uart => {
+                if let (Some(channel), Some(input)) = (self.uart.as_mut(), tab.take_input()) {
+                    channel.send(&input);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the data shown in a tab with the given source.
+fn channel_data<'a>(
+    source: TabSource,
+    up_channels: &'a BTreeMap<usize, UpChannel>,
+    uart: &'a Option<UartChannel>,
+) -> &'a ChannelData {
+    match source {
+        TabSource::UpChannel(number) => {
+            &up_channels
+                .get(&number)
+                .expect("up channel disappeared")
+                .data
+        }
+        TabSource::Uart => &uart.as_ref().expect("UART channel disappeared").data,
     }
 }
 
@@ -1,5 +1,7 @@
 use std::net::SocketAddr;
 
+use probe_rs::probe::uart::UartAccess;
+
 use crate::{
     cmd::cargo_embed::rttui::tcp::TcpPublisher,
     util::rtt::{ChannelDataCallbacks, ChannelDataConfig, DefmtState, RttActiveUpChannel},
@@ -74,3 +76,108 @@ impl UpChannel {
         )
     }
 }
+
+/// The target UART routed through the debug probe.
+pub struct UartChannel {
+    pub data: ChannelData,
+    /// Input which the probe did not accept yet.
+    pending: Vec<u8>,
+    /// Received bytes after the last newline, completed by later reads.
+    line_buffer: Vec<u8>,
+}
+
+impl Default for UartChannel {
+    fn default() -> Self {
+        Self {
+            data: ChannelData::Strings {
+                messages: Vec::new(),
+            },
+            pending: Vec::new(),
+            line_buffer: Vec::new(),
+        }
+    }
+}
+
+impl UartChannel {
+    /// Queues `input` for transmission to the target.
+    pub fn send(&mut self, input: &str) {
+        self.pending.extend_from_slice(input.as_bytes());
+    }
+
+    pub fn poll_uart(&mut self, uart: &mut dyn UartAccess) -> anyhow::Result<()> {
+        if !self.pending.is_empty() {
+            let written = uart.write_uart(&self.pending)?;
+            self.pending.drain(..written);
+        }
+
+        let incoming = uart.read_uart()?;
+        self.line_buffer.extend_from_slice(&incoming);
+
+        let ChannelData::Strings { messages } = &mut self.data else {
+            unreachable!()
+        };
+        while let Some(end) = self.line_buffer.iter().position(|&b| b == b'\n') {
+            let line = self.line_buffer.drain(..=end).collect::<Vec<_>>();
+            let line = String::from_utf8_lossy(&line[..end]);
+            messages.push(line.trim_end_matches('\r').to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+
+    use probe_rs::probe::{uart::UartConfig, DebugProbeError};
+
+    use super::*;
+
+    /// Returns one queued chunk per read.
+    struct FakeUart {
+        reads: VecDeque<Vec<u8>>,
+    }
+
+    impl UartAccess for FakeUart {
+        fn enable_uart(&mut self, _config: &UartConfig) -> Result<(), DebugProbeError> {
+            Ok(())
+        }
+
+        fn disable_uart(&mut self) -> Result<(), DebugProbeError> {
+            Ok(())
+        }
+
+        fn read_uart(&mut self) -> Result<Vec<u8>, DebugProbeError> {
+            Ok(self.reads.pop_front().unwrap_or_default())
+        }
+
+        fn write_uart(&mut self, data: &[u8]) -> Result<usize, DebugProbeError> {
+            Ok(data.len())
+        }
+    }
+
+    #[test]
+    fn uart_lines_split_across_reads() {
+        let mut uart = FakeUart {
+            reads: VecDeque::from([
+                b"hel".to_vec(),
+                b"lo\r\nwor".to_vec(),
+                // A multi-byte character split across two reads.
+                b"ld \xC3".to_vec(),
+                b"\xA4\n".to_vec(),
+            ]),
+        };
+        let mut channel = UartChannel::default();
+
+        channel.poll_uart(&mut uart).unwrap();
+        channel.poll_uart(&mut uart).unwrap();
+        channel.poll_uart(&mut uart).unwrap();
+        channel.poll_uart(&mut uart).unwrap();
+
+        let ChannelData::Strings { messages } = &channel.data else {
+            unreachable!()
+        };
+        assert_eq!(messages, &["hello", "world ä"]);
+    }
+}
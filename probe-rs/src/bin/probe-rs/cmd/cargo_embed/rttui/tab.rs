@@ -8,8 +8,6 @@ use crate::{
     util::rtt::{RttActiveDownChannel, RttActiveUpChannel},
 };
 
-use super::channel::UpChannel;
-
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct TabConfig {
     /// Which up channel to use.
@@ -28,10 +26,20 @@ pub struct TabConfig {
     pub hide: bool,
 }
 
+/// Where the messages shown in a tab come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabSource {
+    /// An RTT up channel.
+    UpChannel(usize),
+    /// The target UART routed through the debug probe.
+    Uart,
+}
+
 #[derive(Debug)]
 pub struct Tab {
-    up_channel: usize,
-    down_channel: Option<(usize, String)>,
+    source: TabSource,
+    down_channel: Option<usize>,
+    input: Option<String>,
     name: String,
     scroll_offset: usize,
     messages: Vec<String>,
@@ -46,8 +54,9 @@ impl Tab {
         name: Option<String>,
     ) -> Self {
         Self {
-            up_channel: up_channel.number(),
-            down_channel: down_channel.map(|down| (down.number(), String::new())),
+            source: TabSource::UpChannel(up_channel.number()),
+            down_channel: down_channel.map(|down| down.number()),
+            input: down_channel.map(|_| String::new()),
             name: name.unwrap_or_else(|| up_channel.channel_name.clone()),
             scroll_offset: 0,
             messages: Vec::new(),
@@ -56,6 +65,20 @@ impl Tab {
         }
     }
 
+    /// Creates a tab showing the target UART, which always accepts input.
+    pub fn new_uart(name: String) -> Self {
+        Self {
+            source: TabSource::Uart,
+            down_channel: None,
+            input: Some(String::new()),
+            name,
+            scroll_offset: 0,
+            messages: Vec::new(),
+            last_processed: 0,
+            last_width: 0,
+        }
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -68,8 +91,8 @@ impl Tab {
         self.scroll_offset = value;
     }
 
-    pub fn up_channel(&self) -> usize {
-        self.up_channel
+    pub fn source(&self) -> TabSource {
+        self.source
     }
 
     pub fn scroll_up(&mut self) {
@@ -89,19 +112,26 @@ impl Tab {
     }
 
     pub fn push_input(&mut self, c: char) {
-        if let Some((_, input)) = self.down_channel.as_mut() {
+        if let Some(input) = self.input.as_mut() {
             input.push(c);
         }
     }
 
     pub fn pop_input(&mut self) {
-        if let Some((_, input)) = self.down_channel.as_mut() {
+        if let Some(input) = self.input.as_mut() {
             input.pop();
         }
     }
 
     pub fn input(&self) -> Option<&str> {
-        self.down_channel.as_ref().map(|(_, input)| input.as_str())
+        self.input.as_deref()
+    }
+
+    /// Takes the entered line, including its line terminator.
+    pub fn take_input(&mut self) -> Option<String> {
+        let input = self.input.as_mut()?;
+        input.push('\n');
+        Some(std::mem::take(input))
     }
 
     pub fn send_input(
@@ -109,21 +139,19 @@ impl Tab {
         core: &mut Core,
         channels: &mut BTreeMap<usize, RttActiveDownChannel>,
     ) -> anyhow::Result<()> {
-        if let Some((channel, input)) = self.down_channel.as_mut() {
-            let channel = channels.get_mut(channel).expect("down channel disappeared");
-            input.push('\n');
-            channel.push_rtt(core, input.as_str())?;
-            input.clear();
+        if let Some(channel) = self.down_channel {
+            let channel = channels
+                .get_mut(&channel)
+                .expect("down channel disappeared");
+            if let Some(input) = self.take_input() {
+                channel.push_rtt(core, input.as_str())?;
+            }
         }
 
         Ok(())
     }
 
-    pub fn update_messages(&mut self, width: usize, up_channels: &BTreeMap<usize, UpChannel>) {
-        let up_channel = up_channels
-            .get(&self.up_channel)
-            .expect("up channel disappeared");
-
+    pub fn update_messages(&mut self, width: usize, data: &ChannelData) {
         if self.last_width != width {
             self.last_width = width;
             self.last_processed = 0;
@@ -132,7 +160,7 @@ impl Tab {
         }
 
         let old_message_count = self.messages.len();
-        match &up_channel.data {
+        match data {
             ChannelData::Strings { messages, .. } => {
                 // We strip ANSI sequences because they interfere with text wrapping.
                 //  - It's not obvious how we could tell defmt_parser to not emit ANSI sequences.
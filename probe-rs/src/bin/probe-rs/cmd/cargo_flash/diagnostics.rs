@@ -86,6 +86,18 @@ pub(crate) fn render_diagnostics(error: OperationError) {
             error.to_string(),
             vec![],
         ),
+        OperationError::FtdiLayoutFileNotFound { .. } => (
+            error.to_string(),
+            vec![],
+        ),
+        OperationError::FailedFtdiLayoutParsing { .. } => (
+            error.to_string(),
+            vec![],
+        ),
+        OperationError::FailedToSelectFtdiLayout { .. } => (
+            error.to_string(),
+            vec!["Additional layouts can be loaded with `--ftdi-layout-file`.".into()],
+        ),
         OperationError::FailedToChangeWorkingDirectory { .. } => (
             error.to_string(),
             vec![],
@@ -167,6 +179,13 @@ pub(crate) fn render_diagnostics(error: OperationError) {
                 format!("Try specifying a speed lower than {speed} kHz")
             ],
         ),
+        OperationError::TargetVoltageNotSettled(_e) => (
+            error.to_string(),
+            vec![
+                "Make sure the target is powered, or power it from the probe with `probe-rs power on`.".into(),
+                "Try a longer timeout with `--wait-for-target-voltage`.".into(),
+            ],
+        ),
         OperationError::AttachingFailed { source, connect_under_reset } => match source {
             ProbeRsError::ChipNotFound(RegistryError::ChipAutodetectFailed) => (
                 error.to_string(),
@@ -142,7 +142,7 @@ fn main_try(mut args: Vec<OsString>, lister: &Lister) -> Result<(), OperationErr
         flash::build_loader(&mut session, &path, opt.format_options, image_instr_set).unwrap();
     flash::run_flash_download(
         &mut session,
-        &path,
+        &[&path],
         &opt.download_options,
         &probe_options,
         loader,
@@ -29,7 +29,7 @@ use probe_rs::{
     },
     debug::{
         stack_frame::StackFrameInfo, ColumnType, ObjectRef, SourceLocation, SteppingMode,
-        VariableName, VerifiedBreakpoint,
+        UnwindMethod, VariableName, VerifiedBreakpoint,
     },
     Architecture::Riscv,
     CoreStatus, Error, HaltReason, MemoryInterface, RegisterValue,
@@ -1037,6 +1037,7 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
             source_location: Option<SourceLocation>,
             pc: RegisterValue,
             is_inlined: bool,
+            unwind_method: UnwindMethod,
         }
 
         let frame_set = if levels == 1 && start_frame == 0 {
@@ -1085,6 +1086,7 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
             source_location: stack_frame.source_location.clone(),
             pc: stack_frame.pc,
             is_inlined: stack_frame.is_inlined,
+            unwind_method: stack_frame.unwind_method,
         })
         .collect::<Vec<PartialStackFrameData>>();
 
@@ -1131,7 +1133,15 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                     end_column: None,
                     end_line: None,
                     module_id: None,
-                    presentation_hint: Some("normal".to_owned()),
+                    // Frames that were unwound heuristically may be wrong, so we de-emphasize them.
+                    presentation_hint: Some(
+                        if frame.unwind_method == UnwindMethod::Heuristic {
+                            "subtle"
+                        } else {
+                            "normal"
+                        }
+                        .to_owned(),
+                    ),
                     can_restart: Some(false),
                     instruction_pointer_reference: Some(format!("{}", frame.pc)),
                 }
@@ -1793,6 +1803,23 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
             .is_ok()
     }
 
+    /// Send output written by the target program, e.g. to the semihosting console, to the MS DAP Client.
+    /// The `category` is either `stdout` or `stderr`.
+    pub fn program_output(&mut self, category: &str, output: String) -> bool {
+        let event_body = OutputEventBody {
+            output,
+            category: Some(category.to_owned()),
+            variables_reference: None,
+            source: None,
+            line: None,
+            column: None,
+            data: None,
+            group: None,
+        };
+
+        self.send_event("output", Some(event_body)).is_ok()
+    }
+
     fn new_progress_id(&mut self) -> ProgressId {
         let id = self.progress_id;
 
@@ -7,7 +7,10 @@ use super::{
     repl_types::*,
     request_helpers::set_instruction_breakpoint,
 };
-use crate::cmd::dap_server::{server::core_data::CoreHandle, DebuggerError};
+use crate::{
+    cmd::dap_server::{server::core_data::CoreHandle, DebuggerError},
+    util::fault::describe_fault,
+};
 use itertools::Itertools;
 use probe_rs::{
     debug::{ObjectRef, VariableName},
@@ -234,6 +237,27 @@ pub(crate) static REPL_COMMANDS: &[ReplCommand<ReplHandler>] = &[
             })
         },
     },
+    ReplCommand {
+        command: "fault",
+        sub_commands: None,
+        help_text: "Decode the fault status registers of a Cortex-M core, and locate the faulting instruction.",
+        args: None,
+        handler: |target_core, _command_arguments, _request_arguments| {
+            let status = target_core.core.fault_status()?;
+            Ok(Response {
+                command: "fault".to_string(),
+                success: true,
+                message: Some(describe_fault(
+                    &status,
+                    &target_core.core_data.stack_frames,
+                )),
+                type_: "response".to_string(),
+                request_seq: 0,
+                seq: 0,
+                body: None,
+            })
+        },
+    },
     ReplCommand {
         command: "info",
         help_text: "Information of specified program data.",
@@ -408,7 +432,7 @@ pub(crate) static REPL_COMMANDS: &[ReplCommand<ReplHandler>] = &[
     },
     ReplCommand {
         command: "dump",
-        help_text: "Create a core dump at a target location. Specify memory ranges to dump, or leave blank to dump in-scope memory regions.",
+        help_text: "Create a core dump at a target location. Specify memory ranges to dump, or leave blank to dump in-scope memory regions. Use a path ending in `.core` to create an ELF core file for GDB.",
         sub_commands: None,
         args: Some(&[
             ReplCommandArgs::Optional("memory start address"),
@@ -466,7 +490,12 @@ pub(crate) static REPL_COMMANDS: &[ReplCommand<ReplHandler>] = &[
                 range_string = range_string.trim_end_matches(", ").to_string();
                 range_string = format!("(Includes memory ranges: {range_string})");
             }
-            target_core.core.dump(ranges)?.store(location)?;
+            let core_dump = target_core.core.dump(ranges)?;
+            if location.extension().is_some_and(|extension| extension == "core") {
+                core_dump.store_elf(location)?;
+            } else {
+                core_dump.store(location)?;
+            }
 
             Ok(Response {
                 command: "dump".to_string(),
@@ -96,6 +96,44 @@ impl SessionConfig {
                         None
                     }
                 };
+            // Update the `core_dump` and validate that the file exists.
+            target_core_config.core_dump =
+                match get_absolute_path(self.cwd.as_ref(), target_core_config.core_dump.as_ref()) {
+                    Ok(core_dump) => {
+                        if !core_dump.is_file() {
+                            return Err(DebuggerError::Other(anyhow!(
+                                "Core dump {} not found.",
+                                core_dump.display()
+                            )));
+                        }
+                        Some(core_dump)
+                    }
+                    Err(error) => {
+                        // The core dump is only used for post-mortem debugging.
+                        tracing::debug!("Core dump not specified: {:?}", error);
+                        None
+                    }
+                };
+            // Update the `semihosting_file_root` and validate that the directory exists.
+            target_core_config.semihosting_file_root = match get_absolute_path(
+                self.cwd.as_ref(),
+                target_core_config.semihosting_file_root.as_ref(),
+            ) {
+                Ok(file_root) => {
+                    if !file_root.is_dir() {
+                        return Err(DebuggerError::Other(anyhow!(
+                            "Semihosting file root {} is not a directory.",
+                            file_root.display()
+                        )));
+                    }
+                    Some(file_root)
+                }
+                Err(error) => {
+                    // The semihosting file root is not mandatory.
+                    tracing::debug!("Semihosting file root not specified: {:?}", error);
+                    None
+                }
+            };
         }
 
         self.chip_description_path =
@@ -119,6 +157,13 @@ impl SessionConfig {
         Ok(())
     }
 
+    /// Returns `true` if a core dump is debugged post-mortem, instead of a target connected with a probe.
+    pub(crate) fn is_post_mortem(&self) -> bool {
+        self.core_configs
+            .iter()
+            .any(|core_config| core_config.core_dump.is_some())
+    }
+
     /// Validate the new given cwd for this process exists, or else update the cwd setting to use the running process' current working directory.
     pub(crate) fn resolve_cwd(&self) -> Result<Option<PathBuf>, DebuggerError> {
         let path = match self.cwd {
@@ -143,9 +188,17 @@ impl SessionConfig {
             protocol: self.wire_protocol,
             probe: self.probe.clone(),
             speed: self.speed,
+            auto_speed: false,
+            dp: None,
+            ftdi_layout: None,
+            ftdi_layout_file: None,
             connect_under_reset: self.connect_under_reset,
+            wait_for_target_voltage: None,
             dry_run: false,
             allow_erase_all: self.allow_erase_all,
+            record_probe: None,
+            replay_probe: None,
+            virtual_probe: false,
         }
     }
 }
@@ -214,6 +267,14 @@ pub struct CoreConfig {
     /// CMSIS-SVD file for the target. Relative to `cwd`, or fully qualified.
     pub(crate) svd_file: Option<PathBuf>,
 
+    /// A core dump to debug post-mortem, instead of connecting to the target with a probe. Relative to `cwd`, or fully qualified.
+    /// The dump can be in the probe-rs format, or an ELF core file.
+    pub(crate) core_dump: Option<PathBuf>,
+
+    /// The directory the target may access through semihosting file operations. Relative to `cwd`, or fully qualified.
+    /// Without it, the target can only use the semihosting console.
+    pub(crate) semihosting_file_root: Option<PathBuf>,
+
     #[serde(flatten)]
     pub(crate) rtt_config: rtt::RttConfig,
 }
@@ -2,6 +2,7 @@ use std::{ops::Range, path::Path};
 
 use super::session_data::{self, ActiveBreakpoint, BreakpointType, SourceLocationScope};
 use crate::util::rtt::{self, ChannelMode, DataFormat, DefmtState, RttActiveTarget};
+use crate::util::semihosting::{ConsoleStream, SemihostingConsole, SemihostingHandler};
 use crate::{
     cmd::dap_server::{
         debug_adapter::{
@@ -25,7 +26,7 @@ use probe_rs::{
         debug_info::DebugInfo, stack_frame::StackFrameInfo, ColumnType, ObjectRef, VariableCache,
     },
     rtt::{Rtt, ScanRegion},
-    Core, CoreStatus, Error, HaltReason,
+    BreakpointCause, Core, CoreStatus, Error, HaltReason, SemihostingCommand,
 };
 use time::UtcOffset;
 use typed_path::TypedPathBuf;
@@ -49,6 +50,26 @@ pub struct CoreData {
     pub stack_frames: Vec<probe_rs::debug::stack_frame::StackFrame>,
     pub breakpoints: Vec<session_data::ActiveBreakpoint>,
     pub rtt_connection: Option<debug_rtt::RttConnection>,
+    /// Executes the semihosting file and console requests of the target.
+    pub semihosting: SemihostingHandler,
+}
+
+/// Collects the semihosting console output of the target, so it can be sent to the client.
+/// The client has no way to provide console input, so reading the console always returns end of file.
+#[derive(Default)]
+struct DapConsole {
+    output: Vec<(ConsoleStream, Vec<u8>)>,
+}
+
+impl SemihostingConsole for DapConsole {
+    fn write(&mut self, stream: ConsoleStream, data: &[u8]) -> std::io::Result<()> {
+        self.output.push((stream, data.to_vec()));
+        Ok(())
+    }
+
+    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(0)
+    }
 }
 
 /// [CoreHandle] provides handles to various data structures required to debug a single instance of a core. The actual state is stored in [session_data::SessionData].
@@ -72,6 +93,32 @@ impl<'p> CoreHandle<'p> {
         debug_adapter.all_cores_halted = false;
     }
 
+    /// Executes a semihosting request of the target, and forwards any console output to the client.
+    ///
+    /// Returns `true` if the request was answered and the core can continue running.
+    fn handle_semihosting<P: ProtocolAdapter>(
+        &mut self,
+        debug_adapter: &mut DebugAdapter<P>,
+        command: SemihostingCommand,
+    ) -> Result<bool, Error> {
+        let mut console = DapConsole::default();
+        let handled = self
+            .core_data
+            .semihosting
+            .handle(&mut self.core, command, &mut console)
+            .map_err(Error::Other)?;
+
+        for (stream, output) in console.output {
+            let category = match stream {
+                ConsoleStream::Stdout => "stdout",
+                ConsoleStream::Stderr => "stderr",
+            };
+            debug_adapter.program_output(category, String::from_utf8_lossy(&output).into_owned());
+        }
+
+        Ok(handled)
+    }
+
     /// - Whenever we check the status, we compare it against `last_known_status` and send the appropriate event to the client.
     /// - If we cannot determine the core status, then there is no sense in continuing the debug session, so please propagate the error.
     /// - If the core status has changed, then we update `last_known_status` to the new value, and return `true` as part of the Result<>.
@@ -81,7 +128,19 @@ impl<'p> CoreHandle<'p> {
     ) -> Result<CoreStatus, Error> {
         if debug_adapter.configuration_is_done() {
             match self.core.status() {
-                Ok(status) => {
+                Ok(mut status) => {
+                    if let CoreStatus::Halted(HaltReason::Breakpoint(
+                        BreakpointCause::Semihosting(command),
+                    )) = status
+                    {
+                        if status != self.core_data.last_known_status
+                            && self.handle_semihosting(debug_adapter, command)?
+                        {
+                            // The request was answered, so the client does not need to know about this halt.
+                            self.core.run()?;
+                            status = CoreStatus::Running;
+                        }
+                    }
                     let has_changed_state = status != self.core_data.last_known_status;
                     if has_changed_state {
                         match status {
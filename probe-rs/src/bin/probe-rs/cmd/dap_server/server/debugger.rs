@@ -1,6 +1,6 @@
 use super::{
     configuration::{self, ConsoleLog},
-    session_data::SessionData,
+    session_data::{DebugTarget, SessionData},
     startup::{get_file_timestamp, TargetSessionType},
 };
 use crate::{
@@ -190,6 +190,31 @@ impl Debugger {
                     _ => {}
                 }
 
+                if self.config.is_post_mortem()
+                    && matches!(
+                        request.command.as_str(),
+                        "next"
+                            | "stepIn"
+                            | "stepOut"
+                            | "pause"
+                            | "continue"
+                            | "restart"
+                            | "writeMemory"
+                            | "setVariable"
+                            | "setBreakpoints"
+                            | "setInstructionBreakpoints"
+                    )
+                {
+                    debug_adapter.send_response::<()>(
+                        &request,
+                        Err(&DebuggerError::UserMessage(format!(
+                            "'{}' is not available while debugging a core dump.",
+                            request.command
+                        ))),
+                    )?;
+                    return Ok(DebugSessionStatus::Continue);
+                }
+
                 let mut debug_session = DebugSessionStatus::Continue;
 
                 // Now we are ready to execute supported commands, or return an error if it isn't supported.
@@ -425,6 +450,20 @@ impl Debugger {
             }
         }
 
+        if self.config.is_post_mortem()
+            && (self.config.flashing_config.flashing_enabled
+                || self.config.flashing_config.full_chip_erase
+                || self.config.flashing_config.restore_unwritten_bytes)
+        {
+            let error = DebuggerError::Other(anyhow!(
+                "Please do not use any of the `flashing_enabled`, `full_chip_erase`, or `restore_unwritten_bytes` options when debugging a `coreDump`."
+            ));
+
+            debug_adapter.send_response::<()>(launch_attach_request, Err(&error))?;
+
+            return Err(error);
+        }
+
         debug_adapter
             .set_console_log_level(self.config.console_log_level.unwrap_or(ConsoleLog::Console));
 
@@ -444,7 +483,9 @@ impl Debugger {
                 }
             };
 
-        debug_adapter.halt_after_reset = self.config.flashing_config.halt_after_reset;
+        // A core dump can not run, so always report it as stopped once configuration is done.
+        debug_adapter.halt_after_reset =
+            self.config.flashing_config.halt_after_reset || self.config.is_post_mortem();
 
         if self.config.flashing_config.flashing_enabled {
             let target_core_config = self.config.core_configs.first_mut().ok_or_else(|| {
@@ -504,7 +545,9 @@ impl Debugger {
                 };
         }
 
-        if requested_target_session_type == TargetSessionType::LaunchRequest {
+        if self.config.is_post_mortem() {
+            // There is nothing to reset or prepare for breakpoints.
+        } else if requested_target_session_type == TargetSessionType::LaunchRequest {
             // This will effectively do a `reset` and `halt` of the core, which is what we want until after the `configuration_done` request.
             debug_adapter
                 .restart(&mut target_core, None)
@@ -594,6 +637,12 @@ impl Debugger {
         request_id: i64,
         session_data: &mut SessionData,
     ) -> Result<DebugAdapter<P>, DebuggerError> {
+        let DebugTarget::Probe(session) = &mut session_data.target else {
+            let error = DebuggerError::UserMessage("A core dump can not be flashed.".to_string());
+            debug_adapter.show_error_message(&error)?;
+            return Err(error);
+        };
+
         debug_adapter.log_to_console(format!(
             "FLASHING: Starting write of {:?} to device memory",
             &path_to_elf
@@ -669,6 +718,16 @@ impl Debugger {
                             .update_progress(Some(1.0), Some("Reading Old Pages Complete!"), id)
                             .ok();
                     }
+                    ProgressEvent::SectorsSkipped { count, size } => {
+                        flash_progress.total_sector_size -= size as usize;
+                        debug_adapter
+                            .update_progress(
+                                None,
+                                Some(format!("Skipping {count} Unchanged Sectors")),
+                                id,
+                            )
+                            .ok();
+                    }
                     ProgressEvent::StartedErasing => {
                         debug_adapter
                             .update_progress(None, Some("Erasing Sectors"), id)
@@ -724,14 +783,14 @@ impl Debugger {
         download_options.progress = flash_progress;
 
         let loader = build_loader(
-            &mut session_data.session,
+            session,
             path_to_elf,
             self.config.flashing_config.format_options.clone(),
             None,
         )?;
 
         let flash_result = loader
-            .commit(&mut session_data.session, download_options)
+            .commit(session, download_options)
             .map_err(FileDownloadError::Flash);
 
         debug_adapter = match Rc::try_unwrap(rc_debug_adapter) {
@@ -1744,4 +1803,104 @@ mod test {
             .debug_session(debug_adapter, "initial info message", &lister)
             .unwrap();
     }
+
+    #[test]
+    fn launch_core_dump() {
+        let manifest_dir = PathBuf::from(std::env!("CARGO_MANIFEST_DIR"));
+        let debug_info = manifest_dir.join("tests/debug-unwind-tests/nRF52833_xxAA.elf");
+        let core_dump = manifest_dir.join("tests/debug-unwind-tests/nRF52833_xxAA.coredump");
+
+        let mut protocol_adapter = MockProtocolAdapter::new();
+
+        protocol_adapter
+            .add_request("initialize")
+            .with_arguments(default_initialize_args())
+            .and_succesful_response()
+            .with_body(expected_capabilites());
+
+        protocol_adapter.expect_output_event("Starting debug session...\n");
+        protocol_adapter.expect_output_event("initial info message\n");
+
+        let launch_args = SessionConfig {
+            core_configs: vec![CoreConfig {
+                core_index: 0,
+                program_binary: Some(debug_info),
+                core_dump: Some(core_dump),
+                ..CoreConfig::default()
+            }],
+            ..SessionConfig::default()
+        };
+
+        protocol_adapter
+            .add_request("launch")
+            .with_arguments(launch_args)
+            .and_succesful_response();
+
+        protocol_adapter.expect_event("initialized", None::<u32>);
+
+        protocol_adapter
+            .add_request("configurationDone")
+            .and_succesful_response();
+
+        // The core dump is always halted, which is reported by `configurationDone`,
+        // and again by the first poll of the core, which also unwinds the stack.
+        protocol_adapter.expect_event(
+            "stopped",
+            Some(json!({
+                "allThreadsStopped": false,
+                "description": "Core halted: unrecognized cause",
+                "reason": "unrecognized",
+                "threadId": 0,
+            })),
+        );
+        protocol_adapter.expect_event(
+            "stopped",
+            Some(json!({
+                "allThreadsStopped": true,
+                "description": "Core halted: unrecognized cause",
+                "preserveFocusHint": false,
+                "reason": "unrecognized",
+                "threadId": 0,
+            })),
+        );
+
+        protocol_adapter
+            .add_request("threads")
+            .and_succesful_response()
+            .with_body(ThreadsResponseBody {
+                threads: vec![Thread {
+                    id: 0,
+                    name: "0-core dump".to_string(),
+                }],
+            });
+
+        let message = "'continue' is not available while debugging a core dump.";
+        protocol_adapter
+            .add_request("continue")
+            .and_error_response()
+            .with_body(ErrorResponseBody {
+                error: Some(error_message(message)),
+            });
+        protocol_adapter.expect_output_event(&format!("{message}\n"));
+
+        protocol_adapter
+            .add_request("disconnect")
+            .with_arguments(DisconnectArguments {
+                restart: Some(false),
+                suspend_debuggee: Some(false),
+                terminate_debuggee: Some(false),
+            })
+            .and_succesful_response();
+
+        let debug_adapter = DebugAdapter::new(protocol_adapter);
+
+        let mut debugger = Debugger::new(UtcOffset::UTC);
+
+        // No probe is needed to debug a core dump.
+        let lister = Lister::with_lister(Box::new(TestLister::new()));
+
+        debugger
+            .debug_session(debug_adapter, "initial info message", &lister)
+            .unwrap();
+    }
 }
@@ -10,17 +10,18 @@ use crate::{
         },
         DebuggerError,
     },
-    util::common_options::OperationError,
+    util::{common_options::OperationError, semihosting::SemihostingHandler},
 };
 use anyhow::{anyhow, Result};
+use object::{Object, ObjectSection, SectionKind};
 use probe_rs::{
     config::TargetSelector,
     debug::{debug_info::DebugInfo, DebugRegisters, SourceLocation},
     exception_handler_for_core,
     probe::list::Lister,
-    CoreStatus, Session,
+    CoreDump, CoreStatus, Session,
 };
-use std::env::set_current_dir;
+use std::{env::set_current_dir, fs, path::Path};
 use time::UtcOffset;
 
 /// The supported breakpoint types
@@ -51,11 +52,18 @@ pub struct ActiveBreakpoint {
     pub(crate) address: u64,
 }
 
+/// What a debug session inspects: a live target connected with a probe, or a saved core dump.
+pub(crate) enum DebugTarget {
+    Probe(Session),
+    /// Post-mortem debugging. Only reads of registers and memory are possible.
+    CoreDump(CoreDump),
+}
+
 /// SessionData is designed to be similar to [probe_rs::Session], in as much that it provides handles to the [CoreHandle] instances for each of the available [probe_rs::Core] involved in the debug session.
 /// To get access to the [CoreHandle] for a specific [probe_rs::Core], the
 /// TODO: Adjust [SessionConfig] to allow multiple cores (and if appropriate, their binaries) to be specified.
 pub(crate) struct SessionData {
-    pub(crate) session: Session,
+    pub(crate) target: DebugTarget,
     /// [SessionData] will manage one [CoreData] per target core, that is also present in [SessionConfig::core_configs]
     pub(crate) core_data: Vec<CoreData>,
 
@@ -71,32 +79,19 @@ impl SessionData {
         config: &mut configuration::SessionConfig,
         timestamp_offset: UtcOffset,
     ) -> Result<Self, DebuggerError> {
-        let target_selector = TargetSelector::from(config.chip.as_deref());
-
-        let options = config.probe_options().load()?;
-        let target_probe = options.attach_probe(lister)?;
-        let target_session = options
-            .attach_session(target_probe, target_selector)
-            .map_err(|operation_error| {
-                match operation_error {
-                    OperationError::AttachingFailed {
-                        source,
-                        connect_under_reset,
-                    } => match source {
-                        probe_rs::Error::Timeout => {
-                            let shared_cause = "This can happen if the target is in a state where it can not be attached to. A hard reset during attach usually helps. For probes that support this option, please try using the `connect_under_reset` option.";
-                            if !connect_under_reset {
-                                DebuggerError::UserMessage(format!("{source} {shared_cause}"))
-                            } else {
-                                DebuggerError::UserMessage(format!("{source} {shared_cause} It is possible that your probe does not support this behaviour, or something else is preventing the attach. Please try again without `connect_under_reset`."))
-                            }
-                        }
-                        other_attach_error => other_attach_error.into(),
-                    },
-                    // Return the orginal error.
-                    other => other.into(),
-                }
-            })?;
+        let target = if let Some(core_dump) = config
+            .core_configs
+            .iter()
+            .find_map(|core_config| core_config.core_dump.clone())
+        {
+            // There is no target to receive RTT data from.
+            for core_config in config.core_configs.iter_mut() {
+                core_config.rtt_config.enabled = false;
+            }
+            DebugTarget::CoreDump(load_core_dump(&core_dump, &config.core_configs)?)
+        } else {
+            DebugTarget::Probe(attach_session(lister, config)?)
+        };
 
         // Change the current working directory if `config.cwd` is `Some(T)`.
         if let Some(new_cwd) = config.cwd.clone() {
@@ -118,12 +113,16 @@ impl SessionData {
         }
 
         // Filter `CoreConfig` entries based on those that match an actual core on the target probe.
-        let valid_core_configs = config.core_configs.iter().filter(|&core_config| {
-            target_session
-                .list_cores()
-                .iter()
-                .any(|(target_core_index, _)| *target_core_index == core_config.core_index)
-        });
+        let valid_core_configs = config
+            .core_configs
+            .iter()
+            .filter(|&core_config| match &target {
+                DebugTarget::Probe(target_session) => target_session
+                    .list_cores()
+                    .iter()
+                    .any(|(target_core_index, _)| *target_core_index == core_config.core_index),
+                DebugTarget::CoreDump(_) => core_config.core_dump.is_some(),
+            });
 
         let mut core_data_vec = vec![];
 
@@ -131,22 +130,30 @@ impl SessionData {
             core_data_vec.push(CoreData {
                 core_index: core_configuration.core_index,
                 last_known_status: CoreStatus::Unknown,
-                target_name: format!(
-                    "{}-{}",
-                    core_configuration.core_index,
-                    target_session.target().name
-                ),
+                target_name: match &target {
+                    DebugTarget::Probe(target_session) => format!(
+                        "{}-{}",
+                        core_configuration.core_index,
+                        target_session.target().name
+                    ),
+                    DebugTarget::CoreDump(_) => {
+                        format!("{}-core dump", core_configuration.core_index)
+                    }
+                },
                 debug_info: debug_info_from_binary(core_configuration)?,
                 static_variables: None,
                 core_peripherals: None,
                 stack_frames: vec![],
                 breakpoints: vec![],
                 rtt_connection: None,
+                semihosting: SemihostingHandler::new(
+                    core_configuration.semihosting_file_root.clone(),
+                ),
             })
         }
 
         Ok(SessionData {
-            session: target_session,
+            target,
             core_data: core_data_vec,
             timestamp_offset,
         })
@@ -173,8 +180,13 @@ impl SessionData {
 
     /// Do a 'light weight'(just get references to existing data structures) attach to the core and return relevant debug data.
     pub(crate) fn attach_core(&mut self, core_index: usize) -> Result<CoreHandle, DebuggerError> {
-        if let (Ok(target_core), Some(core_data)) = (
-            self.session.core(core_index),
+        let target_core = match &mut self.target {
+            DebugTarget::Probe(session) => session.core(core_index).ok(),
+            // A core dump holds a single core.
+            DebugTarget::CoreDump(core_dump) => Some(core_dump.core()),
+        };
+        if let (Some(target_core), Some(core_data)) = (
+            target_core,
             self.core_data
                 .iter_mut()
                 .find(|core_data| core_data.core_index == core_index),
@@ -302,6 +314,71 @@ impl SessionData {
     }
 }
 
+/// Attach to the target selected by the probe options in `config`.
+fn attach_session(lister: &Lister, config: &SessionConfig) -> Result<Session, DebuggerError> {
+    let target_selector = TargetSelector::from(config.chip.as_deref());
+
+    let options = config.probe_options().load()?;
+    let target_probe = options.attach_probe(lister)?;
+    options
+        .attach_session(target_probe, target_selector)
+        .map_err(|operation_error| {
+            match operation_error {
+                OperationError::AttachingFailed {
+                    source,
+                    connect_under_reset,
+                } => match source {
+                    probe_rs::Error::Timeout => {
+                        let shared_cause = "This can happen if the target is in a state where it can not be attached to. A hard reset during attach usually helps. For probes that support this option, please try using the `connect_under_reset` option.";
+                        if !connect_under_reset {
+                            DebuggerError::UserMessage(format!("{source} {shared_cause}"))
+                        } else {
+                            DebuggerError::UserMessage(format!("{source} {shared_cause} It is possible that your probe does not support this behaviour, or something else is preventing the attach. Please try again without `connect_under_reset`."))
+                        }
+                    }
+                    other_attach_error => other_attach_error.into(),
+                },
+                // Return the orginal error.
+                other => other.into(),
+            }
+        })
+}
+
+/// Load the core dump, and add the read-only sections of the program binaries to it,
+/// so that code and constants are available even if the dump did not capture them.
+fn load_core_dump(path: &Path, core_configs: &[CoreConfig]) -> Result<CoreDump, DebuggerError> {
+    let mut core_dump = CoreDump::load(path)
+        .map_err(|error| anyhow!("Failed to load core dump {}: {error}", path.display()))?;
+
+    for binary_path in core_configs
+        .iter()
+        .filter_map(|core_config| core_config.program_binary.as_ref())
+    {
+        let binary = fs::read(binary_path)
+            .map_err(|error| anyhow!("Failed to read {}: {error}", binary_path.display()))?;
+        let elf = object::File::parse(binary.as_slice())
+            .map_err(|error| anyhow!("Failed to parse {}: {error}", binary_path.display()))?;
+
+        for section in elf.sections() {
+            if !matches!(
+                section.kind(),
+                SectionKind::Text | SectionKind::ReadOnlyData | SectionKind::ReadOnlyString
+            ) || section.address() == 0
+            {
+                continue;
+            }
+            let Ok(data) = section.data() else {
+                continue;
+            };
+            let range = section.address()..section.address() + data.len() as u64;
+            // Memory captured in the dump takes precedence, because reads use the first matching range.
+            core_dump.data.push((range, data.to_vec()));
+        }
+    }
+
+    Ok(core_dump)
+}
+
 fn debug_info_from_binary(core_configuration: &CoreConfig) -> anyhow::Result<DebugInfo> {
     let Some(ref binary_path) = core_configuration.program_binary else {
         return Err(anyhow!(
@@ -17,7 +17,9 @@ use probe_rs::probe::list::Lister;
 use probe_rs::probe::DebugProbeError;
 use probe_rs::CoreDumpError;
 use probe_rs::{
-    debug::{debug_info::DebugInfo, registers::DebugRegisters, stack_frame::StackFrame},
+    debug::{
+        debug_info::DebugInfo, registers::DebugRegisters, stack_frame::StackFrame, UnwindMethod,
+    },
     Core, CoreType, InstructionSet, MemoryInterface, RegisterValue,
 };
 use rustyline::DefaultEditor;
@@ -576,6 +578,12 @@ impl DebugCli {
                                 if frame.is_inlined {
                                     print!(" inline");
                                 }
+                                if !matches!(
+                                    frame.unwind_method,
+                                    UnwindMethod::CoreRegisters | UnwindMethod::DebugFrame
+                                ) {
+                                    print!(" (unwound using {})", frame.unwind_method);
+                                }
                                 println!();
 
                                 if let Some(location) = &frame.source_location {
@@ -843,7 +851,7 @@ impl DebugCli {
 
         cli.add_command(Command {
             name: "dump",
-            help_text: "Dump the core memory & registers",
+            help_text: "Dump the core memory & registers. Use a path ending in `.core` to create an ELF core file for GDB.",
 
             function: |cli_data, args| {
                 let mut args = args.to_vec();
@@ -893,7 +901,12 @@ impl DebugCli {
 
                 println!("Dumping core");
 
-                cli_data.core.dump(ranges)?.store(location)?;
+                let core_dump = cli_data.core.dump(ranges)?;
+                if location.extension().is_some_and(|extension| extension == "core") {
+                    core_dump.store_elf(location)?;
+                } else {
+                    core_dump.store(location)?;
+                }
 
                 println!("Done.");
 
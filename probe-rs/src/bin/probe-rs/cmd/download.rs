@@ -1,11 +1,16 @@
-use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
 
+use anyhow::anyhow;
+use probe_rs::flashing::{FileDownloadError, Format};
 use probe_rs::probe::list::Lister;
 
 use crate::util::common_options::BinaryDownloadOptions;
 use crate::util::common_options::ProbeOptions;
+use crate::util::flash::add_image;
 use crate::util::flash::build_loader;
 use crate::util::flash::run_flash_download;
+use crate::util::{parse_u32, parse_u64};
 use crate::FormatOptions;
 
 #[derive(clap::Parser)]
@@ -20,6 +25,17 @@ pub struct Cmd {
     #[clap(long)]
     chip_erase: bool,
 
+    /// An additional image, which is downloaded together with the first one. Can be given multiple times.
+    ///
+    /// The format is `PATH[,format=FORMAT][,base-address=ADDRESS][,skip=BYTES]`,
+    /// where the options have the same meaning as `--binary-format`, `--base-address` and `--skip`.
+    #[clap(
+        long = "image",
+        value_name = "IMAGE",
+        help_heading = "DOWNLOAD CONFIGURATION"
+    )]
+    images: Vec<ImageOptions>,
+
     #[clap(flatten)]
     download_options: BinaryDownloadOptions,
 
@@ -31,10 +47,35 @@ impl Cmd {
     pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
         let (mut session, probe_options) = self.probe_options.simple_attach(lister)?;
 
-        let loader = build_loader(&mut session, &self.path, self.format_options, None)?;
+        let mut loader = build_loader(&mut session, &self.path, self.format_options, None)?;
+
+        let mut paths = vec![PathBuf::from(&self.path)];
+        for image in self.images {
+            add_image(
+                &mut session,
+                &mut loader,
+                &image.path,
+                image.format_options,
+                None,
+            )
+            .map_err(|error| match error.downcast_ref::<FileDownloadError>() {
+                Some(FileDownloadError::ImageOverlaps {
+                    existing_image,
+                    addresses,
+                    ..
+                }) => anyhow!(
+                    "{} overlaps with {} at {addresses:#010x?}",
+                    image.path.display(),
+                    paths[*existing_image].display()
+                ),
+                _ => error,
+            })?;
+            paths.push(image.path);
+        }
+
         run_flash_download(
             &mut session,
-            Path::new(&self.path),
+            &paths,
             &self.download_options,
             &probe_options,
             loader,
@@ -44,3 +85,85 @@ impl Cmd {
         Ok(())
     }
 }
+
+/// An additional image for the `download` command.
+#[derive(Clone, Debug)]
+struct ImageOptions {
+    path: PathBuf,
+    format_options: FormatOptions,
+}
+
+impl FromStr for ImageOptions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',');
+        let path = parts
+            .next()
+            .filter(|path| !path.is_empty())
+            .ok_or_else(|| "The image path is missing.".to_string())?;
+
+        let mut format_options = FormatOptions::default();
+        for option in parts {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("Expected an option like 'key=value', got '{option}'."))?;
+
+            match key {
+                "format" => format_options.binary_format = Some(Format::from_str(value)?),
+                "base-address" => {
+                    format_options.base_address = Some(
+                        parse_u64(value)
+                            .map_err(|e| format!("Invalid base address '{value}': {e}"))?,
+                    )
+                }
+                "skip" => {
+                    format_options.skip =
+                        parse_u32(value).map_err(|e| format!("Invalid skip '{value}': {e}"))?
+                }
+                _ => return Err(format!("Unknown image option '{key}'.")),
+            }
+        }
+
+        Ok(Self {
+            path: PathBuf::from(path),
+            format_options,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use probe_rs::flashing::BinOptions;
+
+    use super::*;
+
+    #[test]
+    fn parse_image_options() {
+        let image =
+            ImageOptions::from_str("app.bin,format=bin,base-address=0x8000,skip=16").unwrap();
+
+        assert_eq!(image.path, PathBuf::from("app.bin"));
+        assert_eq!(
+            image.format_options.binary_format,
+            Some(Format::Bin(BinOptions {
+                base_address: None,
+                skip: 0
+            }))
+        );
+        assert_eq!(image.format_options.base_address, Some(0x8000));
+        assert_eq!(image.format_options.skip, 16);
+
+        let image = ImageOptions::from_str("bootloader.elf").unwrap();
+        assert_eq!(image.path, PathBuf::from("bootloader.elf"));
+        assert_eq!(image.format_options.binary_format, None);
+    }
+
+    #[test]
+    fn parse_invalid_image_options() {
+        assert!(ImageOptions::from_str("").is_err());
+        assert!(ImageOptions::from_str("app.bin,base-address").is_err());
+        assert!(ImageOptions::from_str("app.bin,base-address=zero").is_err());
+        assert!(ImageOptions::from_str("app.bin,offset=4").is_err());
+    }
+}
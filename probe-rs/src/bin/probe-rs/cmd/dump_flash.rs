@@ -0,0 +1,470 @@
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use ihex::Record;
+use indicatif::{ProgressBar, ProgressStyle};
+use probe_rs::config::MemoryRegion;
+use probe_rs::probe::list::Lister;
+use probe_rs::{memory_to_elf, Architecture, MemoryInterface};
+
+use crate::util::common_options::ProbeOptions;
+use crate::util::parse_u64;
+
+/// The number of bytes which are read from the target at once.
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// Read the contents of flash, or any other memory of the target, and save them to a file
+///
+/// Without `--address` and `--size`, all non-volatile memory regions of the target are read.
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    probe_options: ProbeOptions,
+
+    /// The file to write the memory contents to
+    path: PathBuf,
+
+    /// The start address of the range to read
+    #[clap(long, value_parser = parse_u64, requires = "size")]
+    address: Option<u64>,
+
+    /// The number of bytes to read
+    #[clap(long, value_parser = parse_u64, requires = "address")]
+    size: Option<u64>,
+
+    /// The format of the output file. If not given, it is derived from the file extension,
+    /// and `bin` is used for unknown extensions.
+    #[clap(long, value_enum, ignore_case = true)]
+    format: Option<DumpFormat>,
+
+    #[clap(long)]
+    disable_progressbars: bool,
+}
+
+/// The file formats which memory can be dumped to.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum DumpFormat {
+    /// The raw memory contents. Only a single contiguous range can be written.
+    Bin,
+    /// An Intel HEX file.
+    Hex,
+    /// An ELF file with one section for each memory region.
+    Elf,
+}
+
+impl DumpFormat {
+    fn from_extension(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("hex" | "ihex") => DumpFormat::Hex,
+            Some("elf") => DumpFormat::Elf,
+            _ => DumpFormat::Bin,
+        }
+    }
+}
+
+/// A part of the dump, which lies in a single memory region.
+#[derive(Debug, PartialEq, Eq)]
+struct Block {
+    name: String,
+    range: Range<u64>,
+    /// The name of the core used to read the block, if the region specifies one.
+    core: Option<String>,
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        let format = self
+            .format
+            .unwrap_or_else(|| DumpFormat::from_extension(&self.path));
+        let range = requested_range(self.address, self.size)?;
+
+        let (mut session, _probe_options) = self.probe_options.simple_attach(lister)?;
+        let target = session.target();
+
+        let blocks = plan_blocks(&target.memory_map, range)?;
+        if blocks.is_empty() {
+            bail!("The target has no non-volatile memory regions to read.");
+        }
+        if format == DumpFormat::Bin && !is_contiguous(&blocks) {
+            bail!("The memory to read is not contiguous, which can't be written to a binary file. Use the `hex` or `elf` format instead.");
+        }
+
+        let core_indices = blocks
+            .iter()
+            .map(|block| {
+                block
+                    .core
+                    .as_ref()
+                    .and_then(|name| target.cores.iter().position(|core| &core.name == name))
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+        let architecture = target.architecture();
+
+        let total_size = blocks
+            .iter()
+            .map(|block| block.range.end - block.range.start)
+            .sum();
+        let progress = if self.disable_progressbars {
+            ProgressBar::hidden()
+        } else {
+            let progress = ProgressBar::new(total_size);
+            progress.set_style(
+                ProgressStyle::default_bar()
+                    .progress_chars("--")
+                    .template("{msg:.green.bold} [{elapsed_precise}] [{wide_bar}] {bytes:>8}/{total_bytes:>8} @ {bytes_per_sec:>10} (eta {eta:3})")
+                    .expect("Error in progress bar creation. This is a bug, please report it."),
+            );
+            progress.set_message("Reading");
+            progress
+        };
+
+        let mut contents = Vec::with_capacity(blocks.len());
+        for (block, core_index) in blocks.iter().zip(core_indices) {
+            let mut core = session.core(core_index)?;
+            let mut data = vec![0; (block.range.end - block.range.start) as usize];
+            for (index, chunk) in data.chunks_mut(READ_CHUNK_SIZE).enumerate() {
+                let address = block.range.start + (index * READ_CHUNK_SIZE) as u64;
+                core.read(address, chunk)
+                    .with_context(|| format!("Failed to read memory at {address:#010x}"))?;
+                progress.inc(chunk.len() as u64);
+            }
+            contents.push(data);
+        }
+        progress.finish();
+
+        let file = match format {
+            DumpFormat::Bin => contents.concat(),
+            DumpFormat::Hex => write_ihex(blocks.iter().zip(&contents))?.into_bytes(),
+            DumpFormat::Elf => write_elf(blocks.iter().zip(&contents), architecture)?,
+        };
+        std::fs::write(&self.path, file)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// Splits the memory to read into blocks which don't cross memory region boundaries.
+///
+/// If no range is given, all non-volatile memory regions which are not an alias are used.
+fn plan_blocks(
+    memory_map: &[MemoryRegion],
+    range: Option<Range<u64>>,
+) -> anyhow::Result<Vec<Block>> {
+    let block = |region: &MemoryRegion, range: Range<u64>| {
+        let name = match region {
+            MemoryRegion::Nvm(region) => region.name.clone(),
+            MemoryRegion::Ram(region) => region.name.clone(),
+            MemoryRegion::Generic(region) => region.name.clone(),
+        };
+        Block {
+            name: name.unwrap_or_else(|| format!("region_{:08x}", range.start)),
+            range,
+            core: region.cores().first().cloned(),
+        }
+    };
+
+    let Some(range) = range else {
+        let mut blocks = memory_map
+            .iter()
+            .filter(|region| matches!(region, MemoryRegion::Nvm(nvm) if !nvm.is_alias))
+            .map(|region| block(region, region.address_range()))
+            .collect::<Vec<_>>();
+        blocks.sort_by_key(|block| block.range.start);
+        return Ok(blocks);
+    };
+
+    let mut blocks = vec![];
+    let mut address = range.start;
+    while address < range.end {
+        let Some(region) = memory_map.iter().find(|region| region.contains(address)) else {
+            bail!("The address {address:#010x} is not part of any memory region of the target.");
+        };
+        let end = region.address_range().end.min(range.end);
+        blocks.push(block(region, address..end));
+        address = end;
+    }
+
+    Ok(blocks)
+}
+
+/// Returns the range given with `--address` and `--size`, if any.
+fn requested_range(address: Option<u64>, size: Option<u64>) -> anyhow::Result<Option<Range<u64>>> {
+    let (Some(address), Some(size)) = (address, size) else {
+        return Ok(None);
+    };
+    if size == 0 {
+        bail!("The size of the range to read must not be zero.");
+    }
+    let Some(end) = address.checked_add(size) else {
+        bail!("The range of {size:#x} bytes at {address:#010x} exceeds the 64-bit address space.");
+    };
+    Ok(Some(address..end))
+}
+
+fn is_contiguous(blocks: &[Block]) -> bool {
+    blocks
+        .windows(2)
+        .all(|pair| pair[0].range.end == pair[1].range.start)
+}
+
+/// Encodes the blocks as an Intel HEX file.
+fn write_ihex<'a>(
+    blocks: impl Iterator<Item = (&'a Block, &'a Vec<u8>)>,
+) -> anyhow::Result<String> {
+    let mut records = vec![];
+    let mut upper_address = None;
+
+    for (block, data) in blocks {
+        if block.range.end > 1 << 32 {
+            bail!("Intel HEX files can't contain data above 4 GiB, use the `elf` format instead.");
+        }
+
+        let mut offset = 0;
+        while offset < data.len() {
+            let address = block.range.start + offset as u64;
+            let upper = (address >> 16) as u16;
+            if upper_address != Some(upper) {
+                records.push(Record::ExtendedLinearAddress(upper));
+                upper_address = Some(upper);
+            }
+
+            // Records must not cross a 64 KiB boundary.
+            let len = (data.len() - offset)
+                .min(16)
+                .min(0x1_0000 - (address as usize & 0xffff));
+            records.push(Record::Data {
+                offset: address as u16,
+                value: data[offset..offset + len].to_vec(),
+            });
+            offset += len;
+        }
+    }
+    records.push(Record::EndOfFile);
+
+    Ok(ihex::create_object_file_representation(&records)?)
+}
+
+/// Encodes the blocks as a 32-bit little endian ELF file, with a loadable segment
+/// and a section for each block.
+fn write_elf<'a>(
+    blocks: impl Iterator<Item = (&'a Block, &'a Vec<u8>)>,
+    architecture: Architecture,
+) -> anyhow::Result<Vec<u8>> {
+    let blocks = blocks
+        .map(|(block, data)| (block.name.as_str(), block.range.start, data.as_slice()))
+        .collect::<Vec<_>>();
+
+    Ok(memory_to_elf(architecture, &blocks)?)
+}
+
+#[cfg(test)]
+mod test {
+    use object::elf;
+    use object::read::elf::{FileHeader, ProgramHeader, SectionHeader};
+    use object::Endianness;
+    use probe_rs::config::{NvmRegion, RamRegion};
+
+    use super::*;
+
+    fn memory_map() -> Vec<MemoryRegion> {
+        vec![
+            MemoryRegion::Nvm(NvmRegion {
+                name: Some("BANK_2".to_string()),
+                range: 0x0800_8000..0x0801_0000,
+                is_boot_memory: false,
+                cores: vec!["main".to_string()],
+                is_alias: false,
+            }),
+            MemoryRegion::Nvm(NvmRegion {
+                name: Some("BANK_1".to_string()),
+                range: 0x0800_0000..0x0800_8000,
+                is_boot_memory: true,
+                cores: vec!["main".to_string()],
+                is_alias: false,
+            }),
+            MemoryRegion::Nvm(NvmRegion {
+                name: None,
+                range: 0x0000_0000..0x0001_0000,
+                is_boot_memory: false,
+                cores: vec!["main".to_string()],
+                is_alias: true,
+            }),
+            MemoryRegion::Ram(RamRegion {
+                name: None,
+                range: 0x2000_0000..0x2000_4000,
+                is_boot_memory: false,
+                cores: vec!["main".to_string()],
+            }),
+        ]
+    }
+
+    fn block(name: &str, range: Range<u64>) -> Block {
+        Block {
+            name: name.to_string(),
+            range,
+            core: Some("main".to_string()),
+        }
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(
+            DumpFormat::from_extension(Path::new("flash.HEX")),
+            DumpFormat::Hex
+        );
+        assert_eq!(
+            DumpFormat::from_extension(Path::new("flash.elf")),
+            DumpFormat::Elf
+        );
+        assert_eq!(
+            DumpFormat::from_extension(Path::new("flash.img")),
+            DumpFormat::Bin
+        );
+        assert_eq!(
+            DumpFormat::from_extension(Path::new("flash")),
+            DumpFormat::Bin
+        );
+    }
+
+    #[test]
+    fn plan_all_nvm_regions() {
+        assert_eq!(
+            plan_blocks(&memory_map(), None).unwrap(),
+            vec![
+                block("BANK_1", 0x0800_0000..0x0800_8000),
+                block("BANK_2", 0x0800_8000..0x0801_0000),
+            ]
+        );
+    }
+
+    #[test]
+    fn plan_range_across_regions() {
+        let blocks = plan_blocks(&memory_map(), Some(0x0800_7000..0x0800_9000)).unwrap();
+
+        assert_eq!(
+            blocks,
+            vec![
+                block("BANK_1", 0x0800_7000..0x0800_8000),
+                block("BANK_2", 0x0800_8000..0x0800_9000),
+            ]
+        );
+        assert!(is_contiguous(&blocks));
+
+        let blocks = plan_blocks(&memory_map(), Some(0x2000_0000..0x2000_0010)).unwrap();
+        assert_eq!(
+            blocks,
+            vec![block("region_20000000", 0x2000_0000..0x2000_0010)]
+        );
+    }
+
+    #[test]
+    fn plan_unmapped_range() {
+        assert!(plan_blocks(&memory_map(), Some(0x0800_f000..0x0801_1000)).is_err());
+    }
+
+    #[test]
+    fn requested_ranges() {
+        assert_eq!(requested_range(None, None).unwrap(), None);
+        assert_eq!(
+            requested_range(Some(0x0800_0000), Some(0x100)).unwrap(),
+            Some(0x0800_0000..0x0800_0100)
+        );
+        assert!(requested_range(Some(0x0800_0000), Some(0)).is_err());
+        assert!(requested_range(Some(u64::MAX), Some(2)).is_err());
+    }
+
+    #[test]
+    fn ihex_round_trip() {
+        let blocks = [
+            block("BANK_1", 0x0800_fff8..0x0801_0008),
+            block("BANK_2", 0x2000_0000..0x2000_0003),
+        ];
+        let contents = [(0..16).collect::<Vec<u8>>(), vec![0xaa, 0xbb, 0xcc]];
+
+        let file = write_ihex(blocks.iter().zip(&contents)).unwrap();
+
+        let mut upper = 0;
+        let mut data = vec![];
+        for record in ihex::Reader::new(&file) {
+            match record.unwrap() {
+                Record::ExtendedLinearAddress(address) => upper = (address as u64) << 16,
+                Record::Data { offset, value } => data.push((upper + offset as u64, value)),
+                Record::EndOfFile => break,
+                record => panic!("Unexpected record {record:?}"),
+            }
+        }
+
+        assert_eq!(
+            data,
+            vec![
+                (0x0800_fff8, (0..8).collect()),
+                (0x0801_0000, (8..16).collect()),
+                (0x2000_0000, vec![0xaa, 0xbb, 0xcc]),
+            ]
+        );
+    }
+
+    #[test]
+    fn elf_sections() {
+        let blocks = [
+            block("BANK_1", 0x0800_0000..0x0800_0004),
+            block("BANK_2", 0x0800_8000..0x0800_8003),
+        ];
+        let contents = [vec![1, 2, 3, 4], vec![5, 6, 7]];
+
+        let file = write_elf(blocks.iter().zip(&contents), Architecture::Arm).unwrap();
+
+        let header = elf::FileHeader32::<Endianness>::parse(&*file).unwrap();
+        let endian = header.endian().unwrap();
+        assert_eq!(header.e_machine(endian), elf::EM_ARM);
+
+        let segments = header
+            .program_headers(endian, &*file)
+            .unwrap()
+            .iter()
+            .map(|segment| {
+                (
+                    segment.p_vaddr(endian),
+                    segment.data(endian, &*file).unwrap().to_vec(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            segments,
+            vec![
+                (0x0800_0000, vec![1, 2, 3, 4]),
+                (0x0800_8000, vec![5, 6, 7])
+            ]
+        );
+
+        let sections = header.sections(endian, &*file).unwrap();
+        let sections = sections
+            .iter()
+            .skip(1)
+            .map(|section| {
+                (
+                    String::from_utf8(sections.section_name(endian, section).unwrap().to_vec())
+                        .unwrap(),
+                    section.sh_addr(endian),
+                    section.sh_size(endian),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sections,
+            vec![
+                ("BANK_1".to_string(), 0x0800_0000, 4),
+                ("BANK_2".to_string(), 0x0800_8000, 3),
+                (".shstrtab".to_string(), 0, 25),
+            ]
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use probe_rs::{
+    debug::{debug_info::DebugInfo, registers::DebugRegisters},
+    exception_handler_for_core,
+    probe::list::Lister,
+};
+
+use crate::{util::common_options::ProbeOptions, util::fault::describe_fault, CoreOptions};
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    shared: CoreOptions,
+
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    /// Binary running on the target, to find the function and source line which caused the fault
+    #[clap(long, value_parser)]
+    exe: Option<PathBuf>,
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        let debug_info = self.exe.as_ref().map(DebugInfo::from_file).transpose()?;
+
+        let (mut session, _probe_options) = self.common.simple_attach(lister)?;
+        let mut core = session.core(self.shared.core)?;
+
+        let was_running = !core.core_halted()?;
+        if was_running {
+            core.halt(Duration::from_millis(100))?;
+        }
+
+        let status = core.fault_status()?;
+
+        let stack_frames = match &debug_info {
+            Some(debug_info) => {
+                let initial_registers = DebugRegisters::from_core(&mut core);
+                let exception_interface = exception_handler_for_core(core.core_type());
+                let instruction_set = core.instruction_set().ok();
+                debug_info.unwind(
+                    &mut core,
+                    initial_registers,
+                    exception_interface.as_ref(),
+                    instruction_set,
+                )?
+            }
+            None => vec![],
+        };
+
+        print!("{}", describe_fault(&status, &stack_frames));
+
+        if was_running {
+            core.run()?;
+        }
+
+        Ok(())
+    }
+}
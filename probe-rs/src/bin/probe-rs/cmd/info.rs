@@ -9,7 +9,9 @@ use probe_rs::{
             armv6m::Demcr,
             component::Scs,
             dp::{DebugPortId, DebugPortVersion, MinDpSupport, DLPIDR, DPIDR, TARGETID},
+            link_quality,
             memory::{Component, CoresightComponent, PeripheralType},
+            multidrop,
             sequences::DefaultArmSequence,
             ApAddress, ApInformation, ArmProbeInterface, DpAddress, MemoryApInformation, Register,
         },
@@ -32,8 +34,9 @@ pub struct Cmd {
     /// SWD Multidrop target selection value
     ///
     /// If provided, this value is written into the debug port TARGETSEL register
-    /// when connecting. This is required for targets using SWD multidrop
-    #[arg(long, value_parser = parse_hex)]
+    /// when connecting. This is required for targets using SWD multidrop.
+    /// Otherwise, the SWD multidrop bus is scanned for debug ports.
+    #[arg(long, value_parser = parse_hex, conflicts_with = "dp")]
     target_sel: Option<u32>,
 }
 
@@ -85,12 +88,6 @@ impl Cmd {
     }
 }
 
-const DEFAULT_DP_ADRESSES: &[DpAddress] = &[
-    DpAddress::Default,
-    DpAddress::Multidrop(0x01002927),
-    DpAddress::Multidrop(0x11002927),
-];
-
 fn try_show_info(
     mut probe: Probe,
     protocol: WireProtocol,
@@ -101,6 +98,12 @@ fn try_show_info(
         return (probe, Err(e.into()));
     }
 
+    if probe.auto_speed() {
+        if let Err(e) = link_quality::select_max_speed(&mut probe) {
+            return (probe, Err(e.into()));
+        }
+    }
+
     let attach_result = if connect_under_reset {
         probe.attach_to_unspecified_under_reset()
     } else {
@@ -114,15 +117,25 @@ fn try_show_info(
     let mut probe = probe;
 
     if probe.has_arm_interface() {
-        let provided_target_sel;
+        let target_sel = target_sel.or(match probe.selected_debug_port() {
+            DpAddress::Multidrop(targetsel) => Some(targetsel),
+            DpAddress::Default => None,
+        });
 
         let dp_addresses = if let Some(target_sel) = target_sel {
-            provided_target_sel = DpAddress::Multidrop(target_sel);
-            std::slice::from_ref(&provided_target_sel)
+            vec![DpAddress::Multidrop(target_sel)]
+        } else if protocol == WireProtocol::Swd {
+            let mut dp_addresses = vec![DpAddress::Default];
+            dp_addresses.extend(show_multidrop_debug_ports(&mut probe));
+            dp_addresses
         } else {
-            DEFAULT_DP_ADRESSES
+            vec![DpAddress::Default]
         };
 
+        if probe.auto_speed() {
+            probe = negotiate_arm_speed(probe, dp_addresses[0]);
+        }
+
         let mut dp_version = None;
 
         for address in dp_addresses {
@@ -134,7 +147,7 @@ fn try_show_info(
                 }
             }
 
-            (probe, dp_version) = show_arm_dp_info(probe, *address);
+            (probe, dp_version) = show_arm_dp_info(probe, address);
         }
     } else {
         println!("No DAP interface was found on the connected probe. ARM-specific information cannot be printed.");
@@ -197,6 +210,77 @@ fn try_show_info(
     (probe, Ok(()))
 }
 
+/// Scans the SWD multi-drop bus, and lists the debug ports which responded.
+///
+/// Returns the addresses of the debug ports.
+fn show_multidrop_debug_ports(probe: &mut Probe) -> Vec<DpAddress> {
+    if probe.try_as_dap_probe().is_none() {
+        return Vec::new();
+    }
+
+    let candidates = multidrop::targetsel_candidates();
+    let debug_ports = match probe.scan_multidrop(&candidates) {
+        Ok(debug_ports) => debug_ports,
+        Err(e) => {
+            println!("Error scanning the SWD multi-drop bus: {:?}", anyhow!(e));
+            println!();
+            return Vec::new();
+        }
+    };
+
+    if debug_ports.is_empty() {
+        return Vec::new();
+    }
+
+    let mut tree = Tree::new("SWD multi-drop debug ports".to_string());
+    for debug_port in &debug_ports {
+        let targetid = match debug_port.targetid {
+            Some(targetid) => format!("{targetid:#010x}"),
+            None => "unreadable".to_string(),
+        };
+        tree.push(format!(
+            "TARGETSEL: {:#010x}, DPIDR: {:#010x}, TARGETID: {}",
+            debug_port.targetsel, debug_port.dpidr, targetid
+        ));
+    }
+    println!("{tree}");
+
+    debug_ports
+        .iter()
+        .map(|debug_port| debug_port.address())
+        .collect()
+}
+
+/// Selects the protocol speed by testing the link to the debug port at the given address.
+fn negotiate_arm_speed(probe: Probe, dp_address: DpAddress) -> Probe {
+    let connect = |probe: Probe| {
+        let interface = probe
+            .try_into_arm_interface()
+            .map_err(|(probe, e)| (probe, e.into()))?;
+
+        interface
+            .initialize(DefaultArmSequence::create(), dp_address)
+            .map_err(|(interface, e)| (interface.close(), e))
+    };
+
+    match link_quality::negotiate_speed(probe, dp_address, None, connect) {
+        Ok(interface) => {
+            let probe = interface.close();
+            println!(
+                "Protocol speed: {} kHz (selected automatically)",
+                probe.speed_khz()
+            );
+            println!();
+            probe
+        }
+        Err((probe, e)) => {
+            println!("Error selecting the protocol speed: {:?}", anyhow!(e));
+            println!();
+            probe
+        }
+    }
+}
+
 fn show_arm_dp_info(probe: Probe, dp_address: DpAddress) -> (Probe, Option<DebugPortVersion>) {
     tracing::debug!("Trying to show ARM chip information");
     match probe.try_into_arm_interface() {
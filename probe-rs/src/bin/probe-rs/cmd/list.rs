@@ -1,11 +1,22 @@
 use probe_rs::probe::list::Lister;
+use probe_rs::probe::remote::RemoteProbeFactory;
 
 #[derive(clap::Parser)]
-pub struct Cmd {}
+pub struct Cmd {
+    /// Also list the probes shared by the probe server at this address, e.g. `lab-1:6250`
+    #[clap(long, value_name = "ADDRESS")]
+    remote: Vec<String>,
+}
 
 impl Cmd {
     pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
-        let probes = lister.list_all();
+        let mut probes = lister.list_all();
+        for address in &self.remote {
+            match RemoteProbeFactory.list_probes_at(address) {
+                Ok(remote_probes) => probes.extend(remote_probes),
+                Err(error) => eprintln!("Failed to list the probes at {address}: {error}"),
+            }
+        }
 
         if !probes.is_empty() {
             println!("The following debug probes were found:");
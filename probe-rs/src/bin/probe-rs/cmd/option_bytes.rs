@@ -0,0 +1,181 @@
+use std::str::FromStr;
+
+use probe_rs::flashing::option_bytes::{read_option_bytes, write_option_bytes, OptionBytesValues};
+use probe_rs::probe::list::Lister;
+
+use crate::util::common_options::ProbeOptions;
+use crate::util::parse_u32;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Subcommand,
+}
+
+#[derive(clap::Subcommand)]
+/// Read or write the option bytes of the target
+enum Subcommand {
+    /// Read the option bytes and decode their fields
+    Read {
+        #[clap(flatten)]
+        probe_options: ProbeOptions,
+    },
+    /// Modify fields of the option bytes, and program them
+    ///
+    /// e.g. probe-rs option-bytes write --chip STM32F407VGTx BOR_LEV=0 nWRP=0xffe
+    #[clap(verbatim_doc_comment)]
+    Write {
+        #[clap(flatten)]
+        probe_options: ProbeOptions,
+
+        /// The fields to modify, as `NAME=VALUE`
+        #[clap(required = true)]
+        fields: Vec<FieldAssignment>,
+    },
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        match self.subcommand {
+            Subcommand::Read { probe_options } => {
+                let (mut session, _probe_options) = probe_options.simple_attach(lister)?;
+                let values = read_option_bytes(&mut session)?;
+
+                print_option_bytes(std::io::stdout().lock(), &values)
+            }
+            Subcommand::Write {
+                probe_options,
+                fields,
+            } => {
+                let (mut session, _probe_options) = probe_options.simple_attach(lister)?;
+                let mut values = read_option_bytes(&mut session)?;
+                for field in fields {
+                    values.set_field(&field.name, field.value)?;
+                }
+
+                write_option_bytes(&mut session, &values)?;
+                println!("The option bytes were written, they take effect after a reset.");
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A `NAME=VALUE` argument of `option-bytes write`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct FieldAssignment {
+    name: String,
+    value: u32,
+}
+
+impl FromStr for FieldAssignment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected a field like 'NAME=VALUE', got '{s}'."))?;
+        let value = parse_u32(value.trim())
+            .map_err(|e| format!("Invalid value '{value}' for field '{name}': {e}"))?;
+
+        Ok(Self {
+            name: name.trim().to_string(),
+            value,
+        })
+    }
+}
+
+fn print_option_bytes(
+    mut output: impl std::io::Write,
+    values: &OptionBytesValues,
+) -> anyhow::Result<()> {
+    let description = values.description();
+    write!(
+        output,
+        "Option bytes at {:#010x} ({} bytes):",
+        description.address, description.size
+    )?;
+    for byte in values.data() {
+        write!(output, " {byte:02x}")?;
+    }
+    writeln!(output)?;
+
+    let name_width = description
+        .fields
+        .iter()
+        .map(|field| field.name.len())
+        .max()
+        .unwrap_or(0);
+    for (field, value) in values.fields() {
+        let value = match value {
+            Ok(value) => format!("{value:#x}"),
+            Err(error) => error.to_string(),
+        };
+        match &field.description {
+            Some(description) => writeln!(
+                output,
+                "    {:name_width$} = {value:<10} {description}",
+                field.name
+            )?,
+            None => writeln!(output, "    {:name_width$} = {value}", field.name)?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use probe_rs::config::{OptionBytes, OptionBytesField};
+
+    use super::*;
+
+    #[test]
+    fn parse_field_assignment() {
+        assert_eq!(
+            FieldAssignment::from_str("RDP=0xaa").unwrap(),
+            FieldAssignment {
+                name: "RDP".to_string(),
+                value: 0xaa
+            }
+        );
+        assert!(FieldAssignment::from_str("RDP").is_err());
+        assert!(FieldAssignment::from_str("RDP=level0").is_err());
+    }
+
+    #[test]
+    fn print_fields() {
+        let description = OptionBytes {
+            address: 0x4002_3c14,
+            size: 4,
+            fields: vec![
+                OptionBytesField {
+                    name: "BOR_LEV".to_string(),
+                    description: Some("Brown-out reset level".to_string()),
+                    offset: 0,
+                    bit_offset: 2,
+                    bit_width: 2,
+                },
+                OptionBytesField {
+                    name: "RDP".to_string(),
+                    description: None,
+                    offset: 0,
+                    bit_offset: 8,
+                    bit_width: 8,
+                },
+            ],
+        };
+        let values = OptionBytesValues::new(description, vec![0xed, 0xaa, 0xff, 0x0f]).unwrap();
+
+        let mut output = vec![];
+        print_option_bytes(&mut output, &values).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Option bytes at 0x40023c14 (4 bytes): ed aa ff 0f\n    \
+            BOR_LEV = 0x3        Brown-out reset level\n    \
+            RDP     = 0xaa\n"
+        );
+    }
+}
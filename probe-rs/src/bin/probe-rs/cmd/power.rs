@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use probe_rs::probe::list::Lister;
+use probe_rs::probe::power::TargetPowerVoltage;
+use probe_rs::probe::Probe;
+
+use crate::util::common_options::ProbeOptions;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum Action {
+    /// Switch the target power on.
+    On,
+    /// Switch the target power off.
+    Off,
+    /// Switch the target power off, and on again.
+    Cycle,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum Voltage {
+    #[clap(name = "3.3")]
+    V3_3,
+    #[clap(name = "5")]
+    V5,
+}
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    #[clap(value_enum)]
+    action: Action,
+
+    /// The supply voltage in Volts. Defaults to the first voltage supported by the probe
+    /// when switching the power on, and to all supported voltages when switching it off.
+    #[clap(long, value_enum)]
+    voltage: Option<Voltage>,
+
+    /// How long the target stays unpowered during a power cycle, in milliseconds.
+    #[clap(long, default_value = "500")]
+    off_time: u64,
+
+    /// How long to wait for the target voltage to settle after switching the power on, in milliseconds.
+    #[clap(long, default_value = "1000")]
+    settle_timeout: u64,
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        let probe_options = self.common.load()?;
+        let mut probe = probe_options.attach_probe(lister)?;
+        let settle_timeout = Duration::from_millis(self.settle_timeout);
+
+        let supported = probe.target_power_voltages();
+        if supported.is_empty() {
+            return Err(anyhow!("The selected probe cannot power the target"));
+        }
+
+        // Without a voltage, power on with the first one, but switch all of them off.
+        let (voltage, off_voltages) = match self.voltage {
+            Some(Voltage::V3_3) => (TargetPowerVoltage::V3_3, vec![TargetPowerVoltage::V3_3]),
+            Some(Voltage::V5) => (TargetPowerVoltage::V5, vec![TargetPowerVoltage::V5]),
+            None => (supported[0], supported),
+        };
+
+        match self.action {
+            Action::On => power_on(&mut probe, voltage, settle_timeout)?,
+            Action::Off => power_off(&mut probe, &off_voltages)?,
+            Action::Cycle => {
+                power_off(&mut probe, &off_voltages)?;
+                std::thread::sleep(Duration::from_millis(self.off_time));
+                power_on(&mut probe, voltage, settle_timeout)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn power_on(
+    probe: &mut Probe,
+    voltage: TargetPowerVoltage,
+    settle_timeout: Duration,
+) -> anyhow::Result<()> {
+    probe.set_target_power(true, voltage)?;
+
+    let settled = probe
+        .wait_for_target_voltage(settle_timeout)
+        .context("The target voltage did not settle")?;
+    if let Some(volts) = settled {
+        println!("Target voltage: {volts:.2} V");
+    }
+
+    Ok(())
+}
+
+fn power_off(probe: &mut Probe, voltages: &[TargetPowerVoltage]) -> anyhow::Result<()> {
+    for &voltage in voltages {
+        probe.set_target_power(false, voltage)?;
+    }
+
+    Ok(())
+}
@@ -1,5 +1,4 @@
 use std::collections::HashMap;
-use std::path::Path;
 use std::time::Duration;
 
 use itm::TracePacket;
@@ -90,7 +89,7 @@ impl ProfileCmd {
         if self.flash {
             run_flash_download(
                 &mut session,
-                Path::new(&self.run.shared_options.path),
+                &[&self.run.shared_options.path],
                 &self.run.shared_options.download_options,
                 &probe_options,
                 loader,
@@ -0,0 +1,196 @@
+use probe_rs::architecture::arm::ArmError;
+use probe_rs::config::TargetSelector;
+use probe_rs::flashing::protection::{
+    disable_protection, disable_protection_unattached, enable_protection, protection_status,
+    ProtectionState, ProtectionStatus,
+};
+use probe_rs::probe::{list::Lister, Probe};
+use probe_rs::{Permissions, Session};
+
+use crate::util::common_options::{LoadedProbeOptions, OperationError, ProbeOptions};
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Subcommand,
+}
+
+#[derive(clap::Subcommand)]
+/// Show or change the readout protection of the target
+enum Subcommand {
+    /// Show whether the readout protection is enabled
+    Status {
+        #[clap(flatten)]
+        probe_options: ProbeOptions,
+    },
+    /// Enable the readout protection, so the debugger can't read the flash anymore
+    ///
+    /// The protection can be disabled again with `probe-rs protect disable`, which erases the
+    /// whole chip. Permanent protection levels are never set.
+    Enable {
+        #[clap(flatten)]
+        probe_options: ProbeOptions,
+
+        /// Confirm that the debugger can't access the chip until it is erased
+        #[clap(long)]
+        allow_enable_protection: bool,
+    },
+    /// Disable the readout protection, which erases the whole chip
+    ///
+    /// This requires `--allow-erase-all`.
+    Disable {
+        #[clap(flatten)]
+        probe_options: ProbeOptions,
+    },
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        match self.subcommand {
+            Subcommand::Status { probe_options } => {
+                let mut session = attach(probe_options, lister)?;
+                let status = protection_status(&mut session)?;
+
+                println!("{}", describe(&status));
+            }
+            Subcommand::Enable {
+                probe_options,
+                allow_enable_protection,
+            } => {
+                let mut permissions = Permissions::new();
+                if allow_enable_protection {
+                    permissions = permissions.allow_enable_protection();
+                }
+
+                let mut session = attach(probe_options, lister)?;
+                let status = enable_protection(&mut session, &permissions)?;
+
+                println!("{}", describe(&status));
+            }
+            Subcommand::Disable { probe_options } => {
+                let mut permissions = Permissions::new();
+                if probe_options.allow_erase_all {
+                    permissions = permissions.allow_erase_all();
+                }
+
+                let mut session = attach_for_disable(probe_options, lister, &permissions)?;
+                let status = disable_protection(&mut session, &permissions)?;
+
+                println!("{}", describe(&status));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Attaches to the target.
+///
+/// Erasing the chip is never permitted while attaching, the protection commands only change the
+/// protection through the [`ProtectionSequence`](probe_rs::flashing::protection::ProtectionSequence)
+/// of the chip.
+fn attach(probe_options: ProbeOptions, lister: &Lister) -> anyhow::Result<Session> {
+    let (probe_options, target, probe) = open_probe(probe_options, lister)?;
+
+    probe_options
+        .attach_session(probe, target)
+        .map_err(protected_error)
+}
+
+/// Attaches to the target for disabling its protection.
+///
+/// A target which is protected so that attaching to it fails has its protection disabled on the
+/// bare probe first.
+fn attach_for_disable(
+    probe_options: ProbeOptions,
+    lister: &Lister,
+    permissions: &Permissions,
+) -> anyhow::Result<Session> {
+    let (probe_options, target, probe) = open_probe(probe_options, lister)?;
+
+    match probe_options.attach_session(probe, target.clone()) {
+        Err(error) if is_missing_permissions(&error) => {
+            let TargetSelector::Specified(target) = target else {
+                anyhow::bail!(
+                    "The target is protected, so it can't be detected. Select it with `--chip`."
+                );
+            };
+
+            // The probe is consumed by the failed attach, so it has to be opened again.
+            let probe = probe_options.attach_probe(lister)?;
+            let probe = disable_protection_unattached(probe, &target, permissions)?;
+
+            Ok(probe_options.attach_session(probe, TargetSelector::Specified(target))?)
+        }
+        result => Ok(result?),
+    }
+}
+
+fn open_probe(
+    mut probe_options: ProbeOptions,
+    lister: &Lister,
+) -> anyhow::Result<(LoadedProbeOptions, TargetSelector, Probe)> {
+    probe_options.allow_erase_all = false;
+
+    let probe_options = probe_options.load()?;
+    let target = probe_options.get_target_selector()?;
+    let probe = probe_options.attach_probe(lister)?;
+
+    Ok((probe_options, target, probe))
+}
+
+fn is_missing_permissions(error: &OperationError) -> bool {
+    matches!(
+        error,
+        OperationError::AttachingFailed {
+            source: probe_rs::Error::Arm(ArmError::MissingPermissions(_)),
+            ..
+        }
+    )
+}
+
+/// Explains that the target is protected if attaching failed because of it.
+fn protected_error(error: OperationError) -> anyhow::Error {
+    if is_missing_permissions(&error) {
+        anyhow::anyhow!(
+            "The target is protected, the debugger can't access it without erasing the whole chip. \
+             Use `probe-rs protect disable --allow-erase-all` to remove the protection."
+        )
+    } else {
+        error.into()
+    }
+}
+
+fn describe(status: &ProtectionStatus) -> String {
+    let summary = match (status.state, status.active) {
+        (ProtectionState::Unprotected, _) => "The target is not protected",
+        (ProtectionState::Protected, true) => "The target is protected",
+        (ProtectionState::Protected, false) => "The target will be protected after the next reset",
+        (ProtectionState::PermanentlyProtected, _) => "The target is permanently protected",
+    };
+
+    format!("{summary}: {}.", status.description)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn describe_status() {
+        assert_eq!(
+            describe(&ProtectionStatus::active(
+                ProtectionState::Unprotected,
+                "RDP level 0"
+            )),
+            "The target is not protected: RDP level 0."
+        );
+        assert_eq!(
+            describe(&ProtectionStatus::pending(
+                ProtectionState::Protected,
+                "UICR.APPROTECT enabled"
+            )),
+            "The target will be protected after the next reset: UICR.APPROTECT enabled."
+        );
+    }
+}
@@ -14,7 +14,7 @@ use crate::CoreOptions;
 ///     00000000 00000000 (32-bit)
 ///     0000000000000000 0000000000000000 (64-bit)
 ///
-/// NOTE: Only supports RAM addresses
+/// NOTE: Only supports RAM addresses. Use `probe-rs dump-flash` to read flash.
 #[derive(clap::Parser)]
 #[clap(verbatim_doc_comment)]
 pub struct Cmd {
@@ -12,7 +12,7 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Result};
-use probe_rs::debug::{DebugInfo, DebugRegisters};
+use probe_rs::debug::{DebugInfo, DebugRegisters, UnwindMethod};
 use probe_rs::flashing::FileDownloadError;
 use probe_rs::rtt::ScanRegion;
 use probe_rs::{
@@ -108,7 +108,7 @@ impl Cmd {
             )?;
             run_flash_download(
                 &mut session,
-                &self.shared_options.path,
+                &[&self.shared_options.path],
                 &self.shared_options.download_options,
                 &probe_options,
                 loader,
@@ -396,6 +396,12 @@ fn print_stacktrace<S: Write + ?Sized>(
         if frame.is_inlined {
             write!(output_stream, " inline")?;
         }
+        if !matches!(
+            frame.unwind_method,
+            UnwindMethod::CoreRegisters | UnwindMethod::DebugFrame
+        ) {
+            write!(output_stream, " (unwound using {})", frame.unwind_method)?;
+        }
         writeln!(output_stream)?;
 
         if let Some(location) = &frame.source_location {
@@ -1,6 +1,8 @@
 use crate::cmd::run::{OutputStream, RunLoop, RunMode};
+use crate::util::semihosting::{SemihostingHandler, StdConsole};
 use anyhow::anyhow;
 use probe_rs::{BreakpointCause, Core, HaltReason, SemihostingCommand, Session};
+use std::path::PathBuf;
 
 /// Options only used in normal run mode
 #[derive(Debug, clap::Parser, Clone)]
@@ -11,6 +13,10 @@ pub struct NormalRunOptions {
     /// Enable hardfault vector catch if its supported on the target.
     #[clap(long, help_heading = "RUN OPTIONS")]
     pub catch_hardfault: bool,
+    /// The directory the target may access through semihosting file operations.
+    /// Without it, the target can only use the semihosting console.
+    #[clap(long, value_name = "DIR", help_heading = "RUN OPTIONS")]
+    pub semihosting_file_root: Option<PathBuf>,
 }
 
 /// Normal run mode (non-test)
@@ -27,8 +33,14 @@ impl RunMode for NormalRunMode {
     fn run(&self, mut session: Session, run_loop: RunLoop) -> anyhow::Result<()> {
         let mut core = session.core(run_loop.core_id)?;
 
-        let halt_handler = |halt_reason: HaltReason, _core: &mut Core| match halt_reason {
+        let mut semihosting =
+            SemihostingHandler::new(self.run_options.semihosting_file_root.clone());
+
+        let halt_handler = |halt_reason: HaltReason, core: &mut Core| match halt_reason {
             HaltReason::Breakpoint(BreakpointCause::Semihosting(cmd)) => {
+                if semihosting.handle(core, cmd, &mut StdConsole)? {
+                    return Ok(None); // Continue running
+                }
                 match cmd {
                     SemihostingCommand::ExitSuccess => {
                         Ok(Some(())) // Exit the run loop
@@ -45,6 +57,7 @@ impl RunMode for NormalRunMode {
                         tracing::warn!("Target wanted to run semihosting operation SYS_GET_CMDLINE, but probe-rs does not support this operation yet. Continuing...");
                         Ok(None) // Continue running
                     }
+                    _ => Ok(None), // Already handled above
                 }
             }
             _ => Err(anyhow!("CPU halted unexpectedly.")),
@@ -0,0 +1,32 @@
+use std::net::TcpListener;
+
+use anyhow::Context;
+use probe_rs::probe::list::Lister;
+use probe_rs::probe::remote::ProbeServer;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    /// The address to listen on, e.g. `0.0.0.0:6250`
+    ///
+    /// Clients are not authenticated, so only listen on trusted networks.
+    #[clap(default_value = "127.0.0.1:6250")]
+    address: String,
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        let listener = TcpListener::bind(&self.address)
+            .with_context(|| format!("Failed to listen on {}", self.address))?;
+        let address = listener.local_addr()?;
+
+        println!("Sharing the following debug probes at tcp://{address}:");
+        for probe in lister.list_all() {
+            println!("  {probe}");
+        }
+        println!("Select them with `--probe tcp://{address}/VID:PID:SERIAL` on the client.");
+
+        ProbeServer::new(lister).serve(&listener)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use probe_rs::probe::list::Lister;
+use probe_rs::probe::uart::{UartConfig, UartParity, UartStopBits};
+use signal_hook::consts::signal;
+
+use crate::util::common_options::ProbeOptions;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum Parity {
+    None,
+    Odd,
+    Even,
+}
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    /// The baud rate of the target UART.
+    #[clap(long, default_value = "115200")]
+    baud: u32,
+
+    /// The number of data bits per frame.
+    #[clap(long, default_value = "8", value_parser = clap::value_parser!(u8).range(5..=8))]
+    data_bits: u8,
+
+    /// The parity bit of each frame.
+    #[clap(long, value_enum, default_value = "none")]
+    parity: Parity,
+
+    /// Use two stop bits instead of one.
+    #[clap(long)]
+    two_stop_bits: bool,
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        let probe_options = self.common.load()?;
+        let mut probe = probe_options.attach_probe(lister)?;
+
+        let config = UartConfig::new(self.baud)
+            .set_data_bits(self.data_bits)
+            .set_parity(match self.parity {
+                Parity::None => UartParity::None,
+                Parity::Odd => UartParity::Odd,
+                Parity::Even => UartParity::Even,
+            })
+            .set_stop_bits(if self.two_stop_bits {
+                UartStopBits::Two
+            } else {
+                UartStopBits::One
+            });
+
+        let uart = probe.get_uart_interface_mut().ok_or_else(|| {
+            anyhow!("The selected probe does not provide access to a target UART")
+        })?;
+        uart.enable_uart(&config)?;
+
+        // Forward stdin from a separate thread, as reading from it blocks.
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut buffer = [0; 64];
+            let mut stdin = std::io::stdin();
+            while let Ok(n @ 1..) = stdin.read(&mut buffer) {
+                if sender.send(buffer[..n].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let exit = Arc::new(AtomicBool::new(false));
+        let sig_id = signal_hook::flag::register(signal::SIGINT, exit.clone())?;
+
+        let mut stdout = std::io::stdout();
+        let mut forward = || -> anyhow::Result<()> {
+            while !exit.load(Ordering::Relaxed) {
+                // Keep showing the target output after stdin was closed.
+                if let Ok(data) = receiver.try_recv() {
+                    uart.write_all_uart(&data)?;
+                }
+
+                let data = uart.read_uart()?;
+                if data.is_empty() {
+                    std::thread::sleep(Duration::from_millis(10));
+                } else {
+                    stdout.write_all(&data)?;
+                    stdout.flush()?;
+                }
+            }
+
+            Ok(())
+        };
+        let result = forward();
+
+        // Stopped with Control+C, or by an error. Leave the UART disabled either way.
+        let disabled = uart.disable_uart();
+
+        signal_hook::low_level::unregister(sig_id);
+        signal_hook::flag::register_conditional_default(signal::SIGINT, exit)?;
+
+        result?;
+        Ok(disabled?)
+    }
+}
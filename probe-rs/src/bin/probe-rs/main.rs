@@ -13,7 +13,7 @@ use std::{ffi::OsString, path::PathBuf};
 use anyhow::{Context, Result};
 use clap::Parser;
 use itertools::Itertools;
-use probe_rs::flashing::{BinOptions, Format, IdfOptions};
+use probe_rs::flashing::{is_titxt, BinOptions, Format, IdfOptions};
 use probe_rs::{probe::list::Lister, Target};
 use serde::Serialize;
 use serde::{de::Error, Deserialize, Deserializer};
@@ -62,8 +62,12 @@ enum Subcommand {
     Debug(cmd::debug::Cmd),
     /// Download memory to attached target
     Download(cmd::download::Cmd),
+    /// Read flash or other memory of the target, and save it as a binary, Intel HEX or ELF file
+    DumpFlash(cmd::dump_flash::Cmd),
     /// Erase all nonvolatile memory of attached target
     Erase(cmd::erase::Cmd),
+    /// Decode the fault status registers of a Cortex-M core, and locate the faulting instruction
+    Fault(cmd::fault::Cmd),
     /// Flash and run an ELF program
     #[clap(name = "run")]
     Run(cmd::run::Cmd),
@@ -77,8 +81,18 @@ enum Subcommand {
     #[clap(name = "itm")]
     Itm(cmd::itm::Cmd),
     Chip(cmd::chip::Cmd),
+    /// Read or write the option bytes of the target
+    OptionBytes(cmd::option_bytes::Cmd),
+    /// Show or change the readout protection of the target
+    Protect(cmd::protect::Cmd),
     /// Measure the throughput of the selected debug probe
     Benchmark(cmd::benchmark::Cmd),
+    /// Show the target UART routed through the debug probe, and send stdin to it
+    Uart(cmd::uart::Cmd),
+    /// Switch the power supply of the target through the debug probe
+    Power(cmd::power::Cmd),
+    /// Share the connected debug probes over TCP, see `--probe tcp://HOST:PORT/VID:PID:SERIAL`
+    ServeProbe(cmd::serve_probe::Cmd),
     /// Profile on-target runtime performance of target ELF program
     Profile(cmd::profile::ProfileCmd),
     Read(cmd::read::Cmd),
@@ -134,11 +148,13 @@ pub struct FormatOptions {
 
 impl FormatOptions {
     /// If a format is provided, use it.
+    /// If `path` has the file extension of an S-record file, or of a TI-TXT file and contents to match, we use that format.
     /// If a target has a preferred format, we use that.
-    /// Finally, if neither of the above cases are true, we default to [`Format::default()`].
-    pub fn into_format(self, target: &Target) -> anyhow::Result<Format> {
+    /// Finally, if none of the above cases are true, we default to [`Format::default()`].
+    pub fn into_format(self, target: &Target, path: &Path) -> anyhow::Result<Format> {
         let format = self
             .binary_format
+            .or_else(|| format_from_extension(path))
             .unwrap_or_else(|| match target.default_format {
                 probe_rs_target::BinaryFormat::Idf => Format::Idf(Default::default()),
                 probe_rs_target::BinaryFormat::Raw => Default::default(),
@@ -155,10 +171,26 @@ impl FormatOptions {
                 partition_table: self.idf_partition_table,
             }),
             Format::Uf2 => Format::Uf2,
+            Format::Srec => Format::Srec,
+            Format::TiTxt => Format::TiTxt,
         })
     }
 }
 
+/// Returns the format of the S-record and TI-TXT files, which are recognized by their file extension.
+///
+/// Files with the generic `.txt` extension are only treated as TI-TXT if their contents look like it.
+fn format_from_extension(path: &Path) -> Option<Format> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "srec" | "s19" | "s28" | "s37" | "mot" => Some(Format::Srec),
+        "txt" => std::fs::read(path)
+            .is_ok_and(|contents| is_titxt(&contents))
+            .then_some(Format::TiTxt),
+        _ => None,
+    }
+}
+
 /// Determine the default location for the logfile
 ///
 /// This has to be called as early as possible, and while the program
@@ -297,13 +329,20 @@ fn main() -> Result<()> {
         Subcommand::Reset(cmd) => cmd.run(&lister),
         Subcommand::Debug(cmd) => cmd.run(&lister),
         Subcommand::Download(cmd) => cmd.run(&lister),
+        Subcommand::DumpFlash(cmd) => cmd.run(&lister),
         Subcommand::Run(cmd) => cmd.run(&lister, true, utc_offset),
         Subcommand::Attach(cmd) => cmd.run(&lister, utc_offset),
         Subcommand::Erase(cmd) => cmd.run(&lister),
+        Subcommand::Fault(cmd) => cmd.run(&lister),
         Subcommand::Trace(cmd) => cmd.run(&lister),
         Subcommand::Itm(cmd) => cmd.run(&lister),
         Subcommand::Chip(cmd) => cmd.run(),
+        Subcommand::OptionBytes(cmd) => cmd.run(&lister),
+        Subcommand::Protect(cmd) => cmd.run(&lister),
         Subcommand::Benchmark(cmd) => cmd.run(&lister),
+        Subcommand::Uart(cmd) => cmd.run(&lister),
+        Subcommand::Power(cmd) => cmd.run(&lister),
+        Subcommand::ServeProbe(cmd) => cmd.run(&lister),
         Subcommand::Profile(cmd) => cmd.run(&lister),
         Subcommand::Read(cmd) => cmd.run(&lister),
         Subcommand::Write(cmd) => cmd.run(&lister),
@@ -1,13 +1,20 @@
-use std::{fs::File, path::Path, path::PathBuf};
+use std::{fs::File, path::Path, path::PathBuf, sync::OnceLock, time::Duration};
 
 use super::cargo::ArtifactError;
-use crate::util::parse_u64;
+use crate::util::{parse_u32, parse_u64};
 use probe_rs::{
+    architecture::arm::DpAddress,
     config::{RegistryError, TargetSelector},
-    flashing::{FileDownloadError, FlashError},
-    integration::FakeProbe,
+    flashing::{FileDownloadError, FlashError, ProbeReport},
+    integration::{FakeProbe, VirtualProbe},
     probe::{
-        list::Lister, DebugProbeError, DebugProbeInfo, DebugProbeSelector, Probe, WireProtocol,
+        ftdi::{
+            layout::{add_layouts_from_toml, get_layout, FtdiLayout, FtdiLayoutError},
+            FtdiProbeFactory,
+        },
+        list::Lister,
+        replay::ReplayProbe,
+        DebugProbeError, DebugProbeInfo, DebugProbeSelector, Probe, WireProtocol,
     },
     Permissions, Session, Target,
 };
@@ -32,9 +39,20 @@ pub struct BinaryDownloadOptions {
         help_heading = "DOWNLOAD CONFIGURATION"
     )]
     pub flash_layout_output_path: Option<String>,
-    /// After flashing, read back all the flashed data to verify it has been written correctly.
+    /// After flashing, verify that all the data has been written correctly.
+    ///
+    /// Where possible, CRCs are calculated on the target instead of reading back all the data.
     #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
     pub verify: bool,
+    /// Only erase and program the flash sectors whose contents differ from the data to be flashed.
+    #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
+    pub skip_unchanged: bool,
+    /// Write a report of the flashing run in JSON format to the given file.
+    ///
+    /// The report contains the flash layout, the timings and throughput of each phase,
+    /// the chip and probe used, the hash of the image and the verification result.
+    #[arg(value_name = "filename", long, help_heading = "DOWNLOAD CONFIGURATION")]
+    pub report: Option<PathBuf>,
 }
 
 /// Supported bit-widths for read/write commands (not every device may support each width).
@@ -86,6 +104,45 @@ pub struct ProbeOptions {
     /// The protocol speed in kHz.
     #[arg(long, env = "PROBE_RS_SPEED", help_heading = "PROBE CONFIGURATION")]
     pub speed: Option<u32>,
+    /// Select the protocol speed automatically, by testing the link to the target
+    /// at decreasing speeds. The speed is lowered once more if errors pile up later on.
+    #[arg(
+        long,
+        env = "PROBE_RS_AUTO_SPEED",
+        conflicts_with = "speed",
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub auto_speed: bool,
+    /// The TARGETSEL value of the debug port to use on an SWD multi-drop bus.
+    ///
+    /// Used for the cores which the target description does not assign to a debug port.
+    /// `probe-rs info` lists the debug ports found on the bus.
+    #[arg(
+        long,
+        value_name = "TARGETSEL",
+        value_parser = parse_u32,
+        env = "PROBE_RS_DP",
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub dp: Option<u32>,
+    /// The pin layout of an FTDI MPSSE adapter, e.g. 'olimex-arm-usb-ocd-h' or 'tigard'.
+    ///
+    /// The layout describes which pins drive the reset lines, LEDs and buffers of the adapter,
+    /// and whether SWD is wired up. Only FTDI probes are opened when a layout is selected.
+    #[arg(
+        long,
+        env = "PROBE_RS_FTDI_LAYOUT",
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub ftdi_layout: Option<String>,
+    /// A TOML file with additional FTDI pin layouts, which can then be selected with `--ftdi-layout`.
+    #[arg(
+        long,
+        value_name = "FTDI layout file path",
+        env = "PROBE_RS_FTDI_LAYOUT_FILE",
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub ftdi_layout_file: Option<PathBuf>,
     /// Use this flag to assert the nreset & ntrst pins during attaching the probe to
     /// the chip.
     #[arg(
@@ -94,6 +151,15 @@ pub struct ProbeOptions {
         help_heading = "PROBE CONFIGURATION"
     )]
     pub connect_under_reset: bool,
+    /// Wait up to the given number of milliseconds for the target voltage to settle before
+    /// attaching, e.g. after powering the target with `probe-rs power on`.
+    #[arg(
+        long,
+        value_name = "milliseconds",
+        env = "PROBE_RS_WAIT_FOR_TARGET_VOLTAGE",
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub wait_for_target_voltage: Option<u64>,
     #[arg(long, env = "PROBE_RS_DRY_RUN", help_heading = "PROBE CONFIGURATION")]
     pub dry_run: bool,
     /// Use this flag to allow all memory, including security keys and 3rd party
@@ -104,6 +170,28 @@ pub struct ProbeOptions {
         help_heading = "PROBE CONFIGURATION"
     )]
     pub allow_erase_all: bool,
+    /// Record the communication with the probe to the given file, to replay it later with
+    /// `--replay-probe`.
+    #[arg(
+        long,
+        value_name = "filename",
+        conflicts_with = "replay_probe",
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub record_probe: Option<PathBuf>,
+    /// Replay a recording made with `--record-probe` instead of using a probe.
+    #[arg(long, value_name = "filename", help_heading = "PROBE CONFIGURATION")]
+    pub replay_probe: Option<PathBuf>,
+    /// Connect to an emulation of the chip given with `--chip` instead of using a probe.
+    ///
+    /// Only the core and the memory of the chip are emulated, peripherals are not.
+    #[arg(
+        long,
+        requires = "chip",
+        conflicts_with_all = ["dry_run", "replay_probe", "record_probe"],
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub virtual_probe: bool,
 }
 
 impl ProbeOptions {
@@ -128,14 +216,16 @@ impl ProbeOptions {
 }
 
 /// Common options and logic when interfacing with a [Probe] which already did all pre operation preparation.
+///
+/// The second field describes the probe, once it was opened by [`LoadedProbeOptions::attach_probe`].
 #[derive(Debug)]
-pub struct LoadedProbeOptions(ProbeOptions);
+pub struct LoadedProbeOptions(ProbeOptions, OnceLock<ProbeReport>);
 
 impl LoadedProbeOptions {
     /// Performs necessary init calls such as loading all chip descriptions
     /// and returns a newtype that ensures initialization.
     pub(crate) fn new(probe_options: ProbeOptions) -> Result<Self, OperationError> {
-        let options = Self(probe_options);
+        let options = Self(probe_options, OnceLock::new());
         // Load the target description, if given in the cli parameters.
         options.maybe_load_chip_desc()?;
         Ok(options)
@@ -184,13 +274,36 @@ impl LoadedProbeOptions {
 
     /// Attaches to specified probe and configures it.
     pub fn attach_probe(&self, lister: &Lister) -> Result<Probe, OperationError> {
+        let mut selected = None;
         let mut probe = if self.0.dry_run {
             Probe::from_specific_probe(Box::new(FakeProbe::with_mocked_core()))
+        } else if let Some(path) = &self.0.replay_probe {
+            let replay = ReplayProbe::open(path)
+                .map_err(|error| OperationError::FailedToOpenProbe(error.into()))?;
+            Probe::from_specific_probe(Box::new(replay))
+        } else if self.0.virtual_probe {
+            let TargetSelector::Specified(target) = self.get_target_selector()? else {
+                unreachable!("clap requires a chip for --virtual-probe");
+            };
+            VirtualProbe::new(&target).into_probe()
         } else {
+            // The pin layout is part of opening an FTDI probe, so it also applies
+            // to a probe which is recorded.
+            let ftdi_layout = self.ftdi_layout()?;
+            let open = |selector: DebugProbeSelector| match &ftdi_layout {
+                Some(layout) => FtdiProbeFactory
+                    .open_with_layout(&selector, layout.clone())
+                    .map(Probe::from_specific_probe),
+                None => lister.open(selector),
+            };
+
             // If we got a probe selector as an argument, open the probe
             // matching the selector if possible.
             let probe = match &self.0.probe {
-                Some(selector) => lister.open(selector),
+                Some(selector) => {
+                    selected = Some(selector.to_string());
+                    open(selector.clone())
+                }
                 None => {
                     // Only automatically select a probe if there is
                     // only a single probe detected.
@@ -203,13 +316,21 @@ impl LoadedProbeOptions {
                         return Err(OperationError::NoProbesFound);
                     };
 
-                    lister.open(info)
+                    let selector = DebugProbeSelector::from(info);
+                    selected = Some(selector.to_string());
+                    open(selector)
                 }
             };
 
             probe.map_err(OperationError::FailedToOpenProbe)?
         };
 
+        if let Some(path) = &self.0.record_probe {
+            probe = probe
+                .record_to(path)
+                .map_err(OperationError::FailedToOpenProbe)?;
+        }
+
         if let Some(protocol) = self.0.protocol {
             // Select protocol and speed
             probe.select_protocol(protocol).map_err(|error| {
@@ -244,16 +365,67 @@ impl LoadedProbeOptions {
             tracing::info!("Protocol speed {} kHz", protocol_speed);
         }
 
+        probe.set_auto_speed(self.0.auto_speed);
+        if let Some(targetsel) = self.0.dp {
+            probe.select_debug_port(DpAddress::Multidrop(targetsel));
+        }
+
+        let _ = self.1.set(ProbeReport {
+            name: probe.get_name(),
+            selector: selected,
+            protocol: probe.protocol().map(|protocol| protocol.to_string()),
+            speed_khz: probe.speed_khz(),
+        });
+
         Ok(probe)
     }
 
+    /// Looks up the FTDI pin layout selected with `--ftdi-layout`, in the layouts of
+    /// `--ftdi-layout-file` first.
+    fn ftdi_layout(&self) -> Result<Option<FtdiLayout>, OperationError> {
+        let Some(name) = &self.0.ftdi_layout else {
+            return Ok(None);
+        };
+
+        let mut ftdi_layouts = Vec::new();
+        if let Some(path) = &self.0.ftdi_layout_file {
+            let layouts = std::fs::read_to_string(path).map_err(|error| {
+                OperationError::FtdiLayoutFileNotFound {
+                    source: error,
+                    path: path.clone(),
+                }
+            })?;
+            ftdi_layouts = add_layouts_from_toml(&layouts).map_err(|error| {
+                OperationError::FailedFtdiLayoutParsing {
+                    source: error,
+                    path: path.clone(),
+                }
+            })?;
+        }
+
+        get_layout(name, &ftdi_layouts).map(Some).map_err(|error| {
+            OperationError::FailedToSelectFtdiLayout {
+                source: error,
+                name: name.clone(),
+            }
+        })
+    }
+
     /// Attaches to target device session. Attaches under reset if
     /// specified by [ProbeOptions::connect_under_reset].
     pub fn attach_session(
         &self,
-        probe: Probe,
+        mut probe: Probe,
         target: TargetSelector,
     ) -> Result<Session, OperationError> {
+        if let Some(timeout) = self.0.wait_for_target_voltage {
+            match probe.wait_for_target_voltage(Duration::from_millis(timeout)) {
+                Ok(Some(volts)) => tracing::info!("Target voltage settled at {:.2} V", volts),
+                Ok(None) => tracing::warn!("The probe cannot read the target voltage"),
+                Err(error) => return Err(OperationError::TargetVoltageNotSettled(error)),
+            }
+        }
+
         let mut permissions = Permissions::new();
         if self.0.allow_erase_all {
             permissions = permissions.allow_erase_all();
@@ -287,6 +459,11 @@ impl LoadedProbeOptions {
     pub(crate) fn chip(&self) -> Option<String> {
         self.0.chip.clone()
     }
+
+    /// The probe opened by [`LoadedProbeOptions::attach_probe`].
+    pub(crate) fn probe(&self) -> Option<&ProbeReport> {
+        self.1.get()
+    }
 }
 
 impl AsRef<ProbeOptions> for LoadedProbeOptions {
@@ -460,6 +637,24 @@ pub enum OperationError {
     },
     #[error("Failed to build the cargo project.")]
     FailedToBuildCargoProject(#[source] ArtifactError),
+    #[error("Failed to open the FTDI layout file '{path}'.")]
+    FtdiLayoutFileNotFound {
+        #[source]
+        source: std::io::Error,
+        path: PathBuf,
+    },
+    #[error("Failed to parse the FTDI layout file '{path}'.")]
+    FailedFtdiLayoutParsing {
+        #[source]
+        source: FtdiLayoutError,
+        path: PathBuf,
+    },
+    #[error("The FTDI layout '{name}' could not be selected.")]
+    FailedToSelectFtdiLayout {
+        #[source]
+        source: FtdiLayoutError,
+        name: String,
+    },
     #[error("The chip '{name}' was not found in the database.")]
     ChipNotFound {
         #[source]
@@ -478,6 +673,8 @@ pub enum OperationError {
         source: DebugProbeError,
         speed: u32,
     },
+    #[error("The target voltage did not settle.")]
+    TargetVoltageNotSettled(#[source] DebugProbeError),
     #[error("Connecting to the chip was unsuccessful.")]
     AttachingFailed {
         #[source]
@@ -515,8 +712,46 @@ impl From<std::io::Error> for OperationError {
 
 #[cfg(test)]
 mod tests {
+    use clap::Parser;
+
     use super::*;
 
+    #[test]
+    fn ftdi_layout_from_file_with_recording() {
+        let path =
+            std::env::temp_dir().join(format!("probe-rs-ftdi-layouts-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "[[layout]]\nname = \"test-adapter\"\ninit_output = 0x0108\ninit_direction = 0x010b\n",
+        )
+        .unwrap();
+
+        let options = |layout: &str| {
+            ProbeOptions::parse_from([
+                "probe-rs",
+                "--ftdi-layout",
+                layout,
+                "--ftdi-layout-file",
+                path.to_str().unwrap(),
+                "--record-probe",
+                "recording.bin",
+            ])
+            .load()
+            .unwrap()
+        };
+
+        // The layout is looked up before the probe is opened and wrapped for recording.
+        let layout = options("test-adapter").ftdi_layout();
+        let unknown = options("unknown-adapter").ftdi_layout();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(layout.unwrap().unwrap().init_output, 0x0108);
+        assert!(matches!(
+            unknown,
+            Err(OperationError::FailedToSelectFtdiLayout { .. })
+        ));
+    }
+
     #[test]
     fn to_cargo_options() {
         assert_eq!(
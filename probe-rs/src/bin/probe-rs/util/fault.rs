@@ -0,0 +1,90 @@
+//! Report of a Cortex-M fault, shared by the `fault` command and the debugger REPL.
+
+use std::fmt::Write;
+
+use probe_rs::{
+    architecture::arm::fault::FaultStatus,
+    debug::{stack_frame::StackFrame, ColumnType},
+};
+
+/// Describes the fault status, followed by the location of the code that caused the fault.
+///
+/// The location is looked up in `stack_frames`, which should be unwound from the halted core.
+/// Without stack frames, only the address of the faulting instruction is reported.
+pub fn describe_fault(status: &FaultStatus, stack_frames: &[StackFrame]) -> String {
+    let mut report = status.to_string();
+
+    if !status.is_fault() {
+        report.push_str("The core is not executing a fault handler, the fault status bits may be left over from an earlier fault.\n");
+    }
+
+    let Some(exception_frame) = status.exception_frame else {
+        report.push_str("The faulting instruction is unknown, because the core is not halted on entry to the exception handler.\n");
+        return report;
+    };
+
+    match status.faulting_frame(stack_frames) {
+        Some(frame) => {
+            let _ = write!(
+                report,
+                "Faulting instruction: {:#010x} in {}",
+                exception_frame.pc, frame.function_name
+            );
+            if let Some(location) = &frame.source_location {
+                if let Some(path) = location.combined_typed_path() {
+                    let _ = write!(report, "\n  at {}", path.to_path().display());
+                    if let Some(line) = location.line {
+                        let _ = write!(report, ":{line}");
+                        match location.column {
+                            Some(ColumnType::Column(column)) => {
+                                let _ = write!(report, ":{column}");
+                            }
+                            Some(ColumnType::LeftEdge) => report.push_str(":1"),
+                            None => {}
+                        }
+                    }
+                }
+            }
+            report.push('\n');
+        }
+        None => {
+            let _ = writeln!(report, "Faulting instruction: {:#010x}", exception_frame.pc);
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use probe_rs::architecture::arm::fault::ExceptionStackFrame;
+
+    use super::*;
+
+    #[test]
+    fn fault_without_debug_info() {
+        let status = FaultStatus {
+            exception_number: 3,
+            hfsr: Some(0),
+            cfsr: Some(0),
+            mmfar: None,
+            bfar: None,
+            sfsr: None,
+            sfar: None,
+            causes: vec![],
+            exception_frame: Some(ExceptionStackFrame {
+                address: 0x2000_0100,
+                r0: 0,
+                r1: 1,
+                r2: 2,
+                r3: 3,
+                r12: 12,
+                lr: 0x0800_0101,
+                pc: 0x0800_0200,
+                xpsr: 0x0100_0000,
+            }),
+        };
+
+        insta::assert_snapshot!(describe_fault(&status, &[]));
+    }
+}
@@ -4,6 +4,7 @@ use super::common_options::{BinaryDownloadOptions, LoadedProbeOptions, Operation
 use super::logging;
 
 use std::fs::File;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use std::{path::Path, time::Instant};
 
@@ -11,11 +12,15 @@ use colored::Colorize;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use probe_rs::InstructionSet;
 use probe_rs::{
-    flashing::{DownloadOptions, FileDownloadError, FlashLoader, FlashProgress, ProgressEvent},
+    flashing::{
+        DownloadOptions, FileDownloadError, FlashLoader, FlashProgress, FlashReport, ImageReport,
+        ProgressEvent,
+    },
     Session,
 };
 
 use anyhow::Context;
+use sha2::{Digest, Sha256};
 
 fn init_progress_bar(bar: &ProgressBar) {
     let style = bar.style().progress_chars("##-");
@@ -26,9 +31,11 @@ fn init_progress_bar(bar: &ProgressBar) {
 
 /// Performs the flash download with the given loader. Ensure that the loader has the data to load already stored.
 /// This function also manages the update and display of progress bars.
+///
+/// `paths` are the files of all images stored in the loader, starting with the main one.
 pub fn run_flash_download(
     session: &mut Session,
-    path: impl AsRef<Path>,
+    paths: &[impl AsRef<Path>],
     download_options: &BinaryDownloadOptions,
     probe_options: &LoadedProbeOptions,
     loader: FlashLoader,
@@ -43,114 +50,58 @@ pub fn run_flash_download(
     options.do_chip_erase = do_chip_erase;
     options.disable_double_buffering = download_options.disable_double_buffering;
     options.verify = download_options.verify;
+    options.skip_unchanged_sectors = download_options.skip_unchanged;
 
-    if !download_options.disable_progressbars {
-        // Create progress bars.
-        let multi_progress = MultiProgress::new();
-        logging::set_progress_bar(multi_progress.clone());
-
-        let style = ProgressStyle::default_bar()
-                    .tick_chars("⠁⠁⠉⠙⠚⠒⠂⠂⠒⠲⠴⠤⠄⠄⠤⠠⠠⠤⠦⠖⠒⠐⠐⠒⠓⠋⠉⠈⠈✔")
-                    .progress_chars("--")
-                    .template("{msg:.green.bold} {spinner} [{elapsed_precise}] [{wide_bar}] {bytes:>8}/{total_bytes:>8} @ {bytes_per_sec:>10} (eta {eta:3})").expect("Error in progress bar creation. This is a bug, please report it.");
-
-        // Create a new progress bar for the fill progress if filling is enabled.
-        let fill_progress = if download_options.restore_unwritten {
-            let fill_progress = multi_progress.add(ProgressBar::new(0));
-            fill_progress.set_style(style.clone());
-            fill_progress.set_message("Reading flash");
-            Some(fill_progress)
-        } else {
-            None
-        };
-
-        // Create a new progress bar for the erase progress.
-        let erase_progress = multi_progress.add(ProgressBar::new(0));
-        erase_progress.set_style(style.clone());
-        erase_progress.set_message("      Erasing");
-
-        // Create a new progress bar for the program progress.
-        let program_progress = multi_progress.add(ProgressBar::new(0));
-        program_progress.set_style(style);
-        program_progress.set_message("  Programming");
-
-        // Register callback to update the progress.
-        let flash_layout_output_path = download_options.flash_layout_output_path.clone();
-        let progress = FlashProgress::new(move |event| match event {
-            ProgressEvent::Initialized { flash_layout } => {
-                if let Some(fp) = fill_progress.as_ref() {
-                    let total_fill_size: u64 = flash_layout.fills().iter().map(|s| s.size()).sum();
-                    fp.set_length(total_fill_size);
-                }
-
-                let total_sector_size: u64 = flash_layout.sectors().iter().map(|s| s.size()).sum();
-                erase_progress.set_length(total_sector_size);
-
-                let visualizer = flash_layout.visualize();
-                flash_layout_output_path
-                    .as_ref()
-                    .map(|path| visualizer.write_svg(path));
-            }
-            ProgressEvent::StartedProgramming { length } => {
-                init_progress_bar(&program_progress);
-                program_progress.set_length(length);
-            }
-            ProgressEvent::StartedErasing => {
-                init_progress_bar(&erase_progress);
-            }
-            ProgressEvent::StartedFilling => {
-                if let Some(fp) = fill_progress.as_ref() {
-                    init_progress_bar(fp);
-                }
-            }
-            ProgressEvent::PageProgrammed { size, .. } => {
-                program_progress.inc(size as u64);
-            }
-            ProgressEvent::SectorErased { size, .. } => {
-                erase_progress.inc(size);
-            }
-            ProgressEvent::PageFilled { size, .. } => {
-                if let Some(fp) = fill_progress.as_ref() {
-                    fp.inc(size);
-                }
-            }
-            ProgressEvent::FailedErasing => {
-                erase_progress.abandon();
-                program_progress.abandon();
-            }
-            ProgressEvent::FinishedErasing => {
-                erase_progress.finish();
-            }
-            ProgressEvent::FailedProgramming => {
-                program_progress.abandon();
-            }
-            ProgressEvent::FinishedProgramming => {
-                program_progress.finish();
-            }
-            ProgressEvent::FailedFilling => {
-                if let Some(fp) = fill_progress.as_ref() {
-                    fp.abandon();
-                }
+    let progress_bars =
+        (!download_options.disable_progressbars).then(|| progress_bars(download_options));
+    let report = download_options
+        .report
+        .as_ref()
+        .map(|_| Arc::new(Mutex::new(FlashReport::new())));
+
+    if progress_bars.is_some() || report.is_some() {
+        let recorder = report.clone();
+        options.progress = Some(FlashProgress::new(move |event| {
+            if let Some(recorder) = &recorder {
+                recorder.lock().unwrap().record(&event);
             }
-            ProgressEvent::FinishedFilling => {
-                if let Some(fp) = fill_progress.as_ref() {
-                    fp.finish();
-                }
+            if let Some(progress_bars) = &progress_bars {
+                progress_bars(&event);
             }
-            ProgressEvent::DiagnosticMessage { .. } => (),
-        });
+        }));
+    }
+
+    let result = loader.commit(session, options);
 
-        options.progress = Some(progress);
+    if let (Some(report), Some(report_path)) = (report, &download_options.report) {
+        let mut report = report.lock().unwrap();
+        report.finish(result.as_ref().err(), download_options.verify);
+        report.chip = Some(session.target().name.clone());
+        report.probe = probe_options.probe().cloned();
+
+        // A failure to write the report must not hide the result of the flashing.
+        let written = paths
+            .iter()
+            .map(|path| image_report(path.as_ref()))
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|images| {
+                report.images = images;
+                write_report(&report, report_path)
+            });
+        if let Err(error) = written {
+            tracing::warn!(
+                "Failed to write the flashing report to '{}': {error}",
+                report_path.display()
+            );
+        }
     }
 
-    loader
-        .commit(session, options)
-        .map_err(|error| OperationError::FlashingFailed {
-            source: error,
-            target: Box::new(session.target().clone()),
-            target_spec: probe_options.chip(),
-            path: path.as_ref().to_path_buf(),
-        })?;
+    result.map_err(|error| OperationError::FlashingFailed {
+        source: error,
+        target: Box::new(session.target().clone()),
+        target_spec: probe_options.chip(),
+        path: paths[0].as_ref().to_path_buf(),
+    })?;
 
     // If we don't do this, the progress bars disappear.
     logging::clear_progress_bar();
@@ -166,6 +117,129 @@ pub fn run_flash_download(
     Ok(())
 }
 
+/// Creates the progress bars for a flash download, and returns a handler which updates them.
+fn progress_bars(download_options: &BinaryDownloadOptions) -> impl Fn(&ProgressEvent) {
+    // Create progress bars.
+    let multi_progress = MultiProgress::new();
+    logging::set_progress_bar(multi_progress.clone());
+
+    let style = ProgressStyle::default_bar()
+                .tick_chars("⠁⠁⠉⠙⠚⠒⠂⠂⠒⠲⠴⠤⠄⠄⠤⠠⠠⠤⠦⠖⠒⠐⠐⠒⠓⠋⠉⠈⠈✔")
+                .progress_chars("--")
+                .template("{msg:.green.bold} {spinner} [{elapsed_precise}] [{wide_bar}] {bytes:>8}/{total_bytes:>8} @ {bytes_per_sec:>10} (eta {eta:3})").expect("Error in progress bar creation. This is a bug, please report it.");
+
+    // Create a new progress bar for the fill progress if filling is enabled.
+    let fill_progress = if download_options.restore_unwritten {
+        let fill_progress = multi_progress.add(ProgressBar::new(0));
+        fill_progress.set_style(style.clone());
+        fill_progress.set_message("Reading flash");
+        Some(fill_progress)
+    } else {
+        None
+    };
+
+    // Create a new progress bar for the erase progress.
+    let erase_progress = multi_progress.add(ProgressBar::new(0));
+    erase_progress.set_style(style.clone());
+    erase_progress.set_message("      Erasing");
+
+    // Create a new progress bar for the program progress.
+    let program_progress = multi_progress.add(ProgressBar::new(0));
+    program_progress.set_style(style);
+    program_progress.set_message("  Programming");
+
+    // Register callback to update the progress.
+    let flash_layout_output_path = download_options.flash_layout_output_path.clone();
+    move |event: &ProgressEvent| match event {
+        ProgressEvent::Initialized { flash_layout } => {
+            if let Some(fp) = fill_progress.as_ref() {
+                let total_fill_size: u64 = flash_layout.fills().iter().map(|s| s.size()).sum();
+                fp.set_length(total_fill_size);
+            }
+
+            let total_sector_size: u64 = flash_layout.sectors().iter().map(|s| s.size()).sum();
+            erase_progress.set_length(total_sector_size);
+
+            let visualizer = flash_layout.visualize();
+            flash_layout_output_path
+                .as_ref()
+                .map(|path| visualizer.write_svg(path));
+        }
+        ProgressEvent::SectorsSkipped { count, size } => {
+            let length = erase_progress.length().unwrap_or(0);
+            erase_progress.set_length(length.saturating_sub(*size));
+            logging::println(format!(
+                "     {} {count} unchanged sectors ({size} bytes)",
+                "Skipping".green().bold()
+            ));
+        }
+        ProgressEvent::StartedProgramming { length } => {
+            init_progress_bar(&program_progress);
+            program_progress.set_length(*length);
+        }
+        ProgressEvent::StartedErasing => {
+            init_progress_bar(&erase_progress);
+        }
+        ProgressEvent::StartedFilling => {
+            if let Some(fp) = fill_progress.as_ref() {
+                init_progress_bar(fp);
+            }
+        }
+        ProgressEvent::PageProgrammed { size, .. } => {
+            program_progress.inc(*size as u64);
+        }
+        ProgressEvent::SectorErased { size, .. } => {
+            erase_progress.inc(*size);
+        }
+        ProgressEvent::PageFilled { size, .. } => {
+            if let Some(fp) = fill_progress.as_ref() {
+                fp.inc(*size);
+            }
+        }
+        ProgressEvent::FailedErasing => {
+            erase_progress.abandon();
+            program_progress.abandon();
+        }
+        ProgressEvent::FinishedErasing => {
+            erase_progress.finish();
+        }
+        ProgressEvent::FailedProgramming => {
+            program_progress.abandon();
+        }
+        ProgressEvent::FinishedProgramming => {
+            program_progress.finish();
+        }
+        ProgressEvent::FailedFilling => {
+            if let Some(fp) = fill_progress.as_ref() {
+                fp.abandon();
+            }
+        }
+        ProgressEvent::FinishedFilling => {
+            if let Some(fp) = fill_progress.as_ref() {
+                fp.finish();
+            }
+        }
+        ProgressEvent::DiagnosticMessage { .. } => (),
+    }
+}
+
+/// Describes the image at `path` for the flashing report.
+fn image_report(path: &Path) -> Result<ImageReport, std::io::Error> {
+    let data = std::fs::read(path)?;
+
+    Ok(ImageReport {
+        path: path.display().to_string(),
+        size: data.len() as u64,
+        sha256: format!("{:x}", Sha256::digest(&data)),
+    })
+}
+
+fn write_report(report: &FlashReport, path: &Path) -> Result<(), std::io::Error> {
+    let file = File::create(path)?;
+    serde_json::to_writer_pretty(file, report)?;
+    Ok(())
+}
+
 /// Builds a new flash loader for the given target and path. This
 /// will check the path for validity and check what pages have to be
 /// flashed etc.
@@ -178,14 +252,34 @@ pub fn build_loader(
     // Create the flash loader
     let mut loader = session.target().flash_loader();
 
+    add_image(
+        session,
+        &mut loader,
+        path,
+        format_options,
+        image_instruction_set,
+    )?;
+
+    Ok(loader)
+}
+
+/// Adds the image at the given path to the flash loader, so it is flashed together
+/// with the images which were added before.
+pub fn add_image(
+    session: &mut Session,
+    loader: &mut FlashLoader,
+    path: impl AsRef<Path>,
+    format_options: FormatOptions,
+    image_instruction_set: Option<InstructionSet>,
+) -> anyhow::Result<()> {
     // Add data from the BIN.
-    let mut file = match File::open(path) {
+    let mut file = match File::open(path.as_ref()) {
         Ok(file) => file,
         Err(e) => return Err(FileDownloadError::IO(e)).context("Failed to open binary file."),
     };
 
-    let format = format_options.into_format(session.target())?;
+    let format = format_options.into_format(session.target(), path.as_ref())?;
     loader.load_image(session, &mut file, format, image_instruction_set)?;
 
-    Ok(loader)
+    Ok(())
 }
@@ -2,9 +2,11 @@ use std::num::ParseIntError;
 
 pub mod cargo;
 pub mod common_options;
+pub mod fault;
 pub mod flash;
 pub mod logging;
 pub mod rtt;
+pub mod semihosting;
 
 pub fn parse_u32(input: &str) -> Result<u32, ParseIntError> {
     parse_int::parse(input)
@@ -0,0 +1,362 @@
+//! Host side implementation of the semihosting file and console operations.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::num::NonZeroU32;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use anyhow::Result;
+use probe_rs::{Core, SemihostingCommand};
+
+/// The path a target opens to access the console of the host.
+const CONSOLE_PATH: &str = ":tt";
+
+// Error numbers reported to the target through SYS_ERRNO. These follow the newlib values,
+// which are what the C library on the target usually expects.
+const EPERM: u32 = 1;
+const ENOENT: u32 = 2;
+const EIO: u32 = 5;
+const EBADF: u32 = 9;
+const EACCES: u32 = 13;
+const EEXIST: u32 = 17;
+const EINVAL: u32 = 22;
+
+/// The console streams a target can write to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleStream {
+    Stdout,
+    Stderr,
+}
+
+/// Where the console input and output of the target ends up.
+pub trait SemihostingConsole {
+    /// Writes output of the target to the console.
+    fn write(&mut self, stream: ConsoleStream, data: &[u8]) -> io::Result<()>;
+
+    /// Reads console input for the target. Returning zero bytes signals the end of the input.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+}
+
+/// Connects the target console to the standard streams of this process.
+pub struct StdConsole;
+
+impl SemihostingConsole for StdConsole {
+    fn write(&mut self, stream: ConsoleStream, data: &[u8]) -> io::Result<()> {
+        match stream {
+            ConsoleStream::Stdout => {
+                let mut stdout = io::stdout();
+                stdout.write_all(data)?;
+                stdout.flush()
+            }
+            ConsoleStream::Stderr => io::stderr().write_all(data),
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::stdin().read(buf)
+    }
+}
+
+enum OpenFile {
+    Stdin,
+    Console(ConsoleStream),
+    File(File),
+}
+
+/// Executes semihosting requests of the target on the host.
+///
+/// File access is confined to the configured file root. Without a file root, the target can
+/// only use the console.
+pub struct SemihostingHandler {
+    file_root: Option<PathBuf>,
+    files: HashMap<NonZeroU32, OpenFile>,
+    next_handle: NonZeroU32,
+    errno: u32,
+    start: Instant,
+}
+
+impl SemihostingHandler {
+    pub fn new(file_root: Option<PathBuf>) -> Self {
+        Self {
+            file_root,
+            files: HashMap::new(),
+            next_handle: NonZeroU32::MIN,
+            errno: 0,
+            start: Instant::now(),
+        }
+    }
+
+    /// Executes the semihosting request and writes the result to the target.
+    ///
+    /// Returns `false` if the request is not a file, console or time operation, in which case
+    /// it is left to the caller.
+    pub fn handle(
+        &mut self,
+        core: &mut Core,
+        command: SemihostingCommand,
+        console: &mut dyn SemihostingConsole,
+    ) -> Result<bool> {
+        match command {
+            SemihostingCommand::Open(request) => {
+                let path = request.path(core)?;
+                match self.open(&path, request.mode()) {
+                    Ok(handle) => request.respond_with_handle(core, handle)?,
+                    Err(error) => {
+                        tracing::debug!("Semihosting: failed to open {path:?}: {error}");
+                        self.errno = errno(&error);
+                    }
+                }
+            }
+            SemihostingCommand::Close(request) => {
+                let removed =
+                    NonZeroU32::new(request.handle()).and_then(|handle| self.files.remove(&handle));
+                match removed {
+                    Some(_) => request.success(core)?,
+                    None => self.errno = EBADF,
+                }
+            }
+            SemihostingCommand::WriteConsole(request) => {
+                let text = request.read(core)?;
+                console.write(ConsoleStream::Stdout, text.as_bytes())?;
+            }
+            SemihostingCommand::Write(request) => {
+                let data = request.read(core)?;
+                let result = match self.file(request.handle()) {
+                    Ok(OpenFile::Console(stream)) => {
+                        console.write(*stream, &data).map(|_| data.len())
+                    }
+                    Ok(OpenFile::File(file)) => file.write_all(&data).map(|_| data.len()),
+                    Ok(OpenFile::Stdin) => Err(io::ErrorKind::PermissionDenied.into()),
+                    Err(error) => Err(error),
+                };
+                match result {
+                    Ok(written) => request.respond(core, written as u32)?,
+                    Err(error) => self.errno = errno(&error),
+                }
+            }
+            SemihostingCommand::Read(request) => {
+                let mut buf = vec![0u8; request.len() as usize];
+                let result = match self.file(request.handle()) {
+                    Ok(OpenFile::Stdin) => console.read(&mut buf),
+                    Ok(OpenFile::File(file)) => read_up_to(file, &mut buf),
+                    Ok(OpenFile::Console(_)) => Err(io::ErrorKind::PermissionDenied.into()),
+                    Err(error) => Err(error),
+                };
+                match result {
+                    Ok(read) => request.respond(core, &buf[..read])?,
+                    Err(error) => self.errno = errno(&error),
+                }
+            }
+            SemihostingCommand::IsTty(request) => match self.file(request.handle()) {
+                Ok(file) => {
+                    let is_tty = !matches!(file, OpenFile::File(_));
+                    request.respond(core, is_tty)?
+                }
+                Err(error) => self.errno = errno(&error),
+            },
+            SemihostingCommand::Seek(request) => {
+                let result = match self.file(request.handle()) {
+                    Ok(OpenFile::File(file)) => file
+                        .seek(SeekFrom::Start(request.position() as u64))
+                        .map(|_| ()),
+                    Ok(_) => Err(io::ErrorKind::Unsupported.into()),
+                    Err(error) => Err(error),
+                };
+                match result {
+                    Ok(()) => request.success(core)?,
+                    Err(error) => self.errno = errno(&error),
+                }
+            }
+            SemihostingCommand::FileLength(request) => {
+                let result = match self.file(request.handle()) {
+                    Ok(OpenFile::File(file)) => file.metadata().map(|metadata| metadata.len()),
+                    Ok(_) => Err(io::ErrorKind::Unsupported.into()),
+                    Err(error) => Err(error),
+                };
+                match result {
+                    Ok(len) => request.respond(core, len.min(i32::MAX as u64) as u32)?,
+                    Err(error) => self.errno = errno(&error),
+                }
+            }
+            SemihostingCommand::Clock(request) => {
+                let centiseconds = self.start.elapsed().as_millis() / 10;
+                request.respond(core, centiseconds as u32)?;
+            }
+            SemihostingCommand::Time(request) => {
+                let seconds = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map(|time| time.as_secs())
+                    .unwrap_or_default();
+                request.respond(core, seconds as u32)?;
+            }
+            SemihostingCommand::Errno(request) => request.respond(core, self.errno)?,
+            SemihostingCommand::ExitSuccess
+            | SemihostingCommand::ExitError(_)
+            | SemihostingCommand::GetCommandLine(_)
+            | SemihostingCommand::Unknown(_) => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    fn open(&mut self, path: &str, mode: Option<&str>) -> io::Result<NonZeroU32> {
+        let Some(mode) = mode else {
+            return Err(io::ErrorKind::InvalidInput.into());
+        };
+
+        let file = if path == CONSOLE_PATH {
+            match mode.chars().next() {
+                Some('r') => OpenFile::Stdin,
+                Some('w') => OpenFile::Console(ConsoleStream::Stdout),
+                _ => OpenFile::Console(ConsoleStream::Stderr),
+            }
+        } else {
+            let Some(file_root) = &self.file_root else {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "no semihosting file root is configured",
+                ));
+            };
+            let path = sandboxed_path(file_root, path)?;
+            OpenFile::File(open_options(mode).open(path)?)
+        };
+
+        let handle = self.next_handle;
+        self.next_handle = self.next_handle.checked_add(1).unwrap_or(NonZeroU32::MIN);
+        self.files.insert(handle, file);
+
+        Ok(handle)
+    }
+
+    fn file(&mut self, handle: u32) -> io::Result<&mut OpenFile> {
+        NonZeroU32::new(handle)
+            .and_then(|handle| self.files.get_mut(&handle))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, BadHandle(handle)))
+    }
+}
+
+/// The target used a file handle which is not open.
+#[derive(Debug, thiserror::Error)]
+#[error("invalid file handle {0}")]
+struct BadHandle(u32);
+
+/// Resolves a path requested by the target relative to the file root.
+///
+/// Absolute paths and paths leaving the file root are rejected.
+fn sandboxed_path(file_root: &Path, requested: &str) -> io::Result<PathBuf> {
+    let requested = Path::new(requested);
+
+    let mut depth = 0usize;
+    for component in requested.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!(
+                        "{} is outside of the semihosting file root",
+                        requested.display()
+                    ),
+                ))
+            }
+        }
+    }
+    if depth == 0 {
+        return Err(io::ErrorKind::InvalidInput.into());
+    }
+
+    Ok(file_root.join(requested))
+}
+
+/// Converts an `fopen` mode into the equivalent [`OpenOptions`].
+fn open_options(mode: &str) -> OpenOptions {
+    let update = mode.contains('+');
+    let mut options = OpenOptions::new();
+    match mode.chars().next() {
+        Some('w') => options.write(true).create(true).truncate(true).read(update),
+        Some('a') => options.append(true).create(true).read(update),
+        _ => options.read(true).write(update),
+    };
+    options
+}
+
+/// Reads until the buffer is full or the end of the file is reached.
+fn read_up_to(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match file.read(&mut buf[total..]) {
+            Ok(0) => break,
+            Ok(read) => total += read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(total)
+}
+
+/// Maps a host error to the error number reported to the target.
+fn errno(error: &io::Error) -> u32 {
+    if error.get_ref().is_some_and(|inner| inner.is::<BadHandle>()) {
+        return EBADF;
+    }
+    match error.kind() {
+        io::ErrorKind::NotFound => ENOENT,
+        io::ErrorKind::PermissionDenied => EACCES,
+        io::ErrorKind::AlreadyExists => EEXIST,
+        io::ErrorKind::InvalidInput => EINVAL,
+        io::ErrorKind::Unsupported => EPERM,
+        _ => EIO,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn relative_paths_stay_in_the_file_root() {
+        let root = Path::new("/sandbox");
+
+        assert_eq!(
+            sandboxed_path(root, "coverage/out.bin").unwrap(),
+            Path::new("/sandbox/coverage/out.bin")
+        );
+        assert_eq!(
+            sandboxed_path(root, "./a/../b.txt").unwrap(),
+            Path::new("/sandbox/./a/../b.txt")
+        );
+    }
+
+    #[test]
+    fn paths_outside_the_file_root_are_rejected() {
+        let root = Path::new("/sandbox");
+
+        for path in ["/etc/passwd", "../secret", "a/../../secret", ".", ""] {
+            assert!(sandboxed_path(root, path).is_err(), "{path} was accepted");
+        }
+    }
+
+    #[test]
+    fn open_without_file_root_is_denied() {
+        let mut handler = SemihostingHandler::new(None);
+
+        let error = handler.open("out.bin", Some("wb")).unwrap_err();
+        assert_eq!(errno(&error), EACCES);
+
+        // The console is always available
+        assert_eq!(handler.open(CONSOLE_PATH, Some("w")).unwrap().get(), 1);
+        assert_eq!(handler.open(CONSOLE_PATH, Some("r")).unwrap().get(), 2);
+    }
+
+    #[test]
+    fn unknown_handles_report_ebadf() {
+        let mut handler = SemihostingHandler::new(None);
+
+        let error = handler.file(7).err().unwrap();
+        assert_eq!(errno(&error), EBADF);
+    }
+}
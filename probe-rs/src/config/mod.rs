@@ -28,9 +28,9 @@ pub mod sequences;
 mod target;
 
 pub use probe_rs_target::{
-    Chip, ChipFamily, Core, CoreType, FlashProperties, GenericRegion, InstructionSet, MemoryRange,
-    MemoryRegion, NvmRegion, PageInfo, RamRegion, RawFlashAlgorithm, ScanChainElement,
-    SectorDescription, SectorInfo, TargetDescriptionSource,
+    BoardOverlay, Chip, ChipFamily, Core, CoreType, FlashProperties, GenericRegion, InstructionSet,
+    MemoryRange, MemoryRegion, NvmRegion, OptionBytes, OptionBytesField, PageInfo, RamRegion,
+    RawFlashAlgorithm, ScanChainElement, SectorDescription, SectorInfo, TargetDescriptionSource,
 };
 
 pub use registry::{
@@ -1,6 +1,6 @@
 //! Internal target registry
 
-use super::{Chip, ChipFamily, ChipInfo, Core, Target, TargetDescriptionSource};
+use super::{BoardOverlay, Chip, ChipFamily, ChipInfo, Core, Target, TargetDescriptionSource};
 use crate::config::CoreType;
 use once_cell::sync::Lazy;
 use probe_rs_target::{BinaryFormat, CoreAccessOptions, RiscvCoreAccessOptions};
@@ -28,6 +28,8 @@ pub enum RegistryError {
     Yaml(#[from] serde_yaml::Error),
     /// Invalid chip family definition ({0.name}): {1}
     InvalidChipFamilyDefinition(Box<ChipFamily>, String),
+    /// Invalid board overlay ({0}): {1}
+    InvalidBoardOverlay(String, String),
     /// Chip's RTT scan region {0:#010X?} is not enclosed by any single RAM region.
     InvalidRttScanRange(std::ops::Range<u64>),
 }
@@ -39,6 +41,7 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
             manufacturer: None,
             generated_from_pack: false,
             pack_file_release: None,
+            option_bytes: None,
             variants: vec![
                 Chip::generic_arm("Cortex-M0", CoreType::Armv6m),
                 Chip::generic_arm("Cortex-M0+", CoreType::Armv6m),
@@ -53,6 +56,7 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
             manufacturer: None,
             generated_from_pack: false,
             pack_file_release: None,
+            option_bytes: None,
             variants: vec![Chip::generic_arm("Cortex-M3", CoreType::Armv7m)],
             flash_algorithms: vec![],
             source: TargetDescriptionSource::Generic,
@@ -62,6 +66,7 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
             manufacturer: None,
             generated_from_pack: false,
             pack_file_release: None,
+            option_bytes: None,
             variants: vec![
                 Chip::generic_arm("Cortex-M4", CoreType::Armv7em),
                 Chip::generic_arm("Cortex-M7", CoreType::Armv7em),
@@ -74,6 +79,7 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
             manufacturer: None,
             generated_from_pack: false,
             pack_file_release: None,
+            option_bytes: None,
             variants: vec![
                 Chip::generic_arm("Cortex-M23", CoreType::Armv8m),
                 Chip::generic_arm("Cortex-M33", CoreType::Armv8m),
@@ -87,6 +93,7 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
             name: "Generic RISC-V".to_owned(),
             manufacturer: None,
             pack_file_release: None,
+            option_bytes: None,
             generated_from_pack: false,
             variants: vec![Chip {
                 name: "riscv".to_owned(),
@@ -104,6 +111,7 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
                 rtt_scan_ranges: None,
                 jtag: None,
                 default_binary_format: Some(BinaryFormat::Raw),
+                option_bytes: None,
             }],
             flash_algorithms: vec![],
             source: TargetDescriptionSource::Generic,
@@ -322,7 +330,15 @@ impl Registry {
     where
         R: Read,
     {
-        let family: ChipFamily = serde_yaml::from_reader(yaml_reader)?;
+        let description: serde_yaml::Value = serde_yaml::from_reader(yaml_reader)?;
+
+        // Board overlays name the chip they extend, while chip families list their variants.
+        let family = if description.get("chip").is_some() && description.get("variants").is_none() {
+            let overlay: BoardOverlay = serde_yaml::from_value(description)?;
+            self.apply_board_overlay(&overlay)?
+        } else {
+            serde_yaml::from_value(description)?
+        };
 
         family
             .validate()
@@ -339,6 +355,35 @@ impl Registry {
 
         Ok(())
     }
+
+    /// Returns the family of the chip extended by `overlay`, with the overlay applied.
+    fn apply_board_overlay(&self, overlay: &BoardOverlay) -> Result<ChipFamily, RegistryError> {
+        let mut family = self
+            .families
+            .iter()
+            .find(|family| {
+                family
+                    .variants
+                    .iter()
+                    .any(|variant| variant.name.eq_ignore_ascii_case(&overlay.chip))
+            })
+            .ok_or_else(|| RegistryError::ChipNotFound(overlay.chip.clone()))?
+            .clone();
+
+        tracing::debug!(
+            "Applying board overlay {} to chip {} of family {}",
+            overlay.name,
+            overlay.chip,
+            family.name
+        );
+
+        overlay
+            .apply(&mut family)
+            .map_err(|e| RegistryError::InvalidBoardOverlay(overlay.name.clone(), e))?;
+        family.source = TargetDescriptionSource::External;
+
+        Ok(family)
+    }
 }
 
 /// Get a target from the internal registry based on its name.
@@ -376,6 +421,9 @@ pub(crate) fn get_target_by_chip_info(chip_info: ChipInfo) -> Result<Target, Reg
 /// Parse a target description and add the contained targets
 /// to the internal target registry.
 ///
+/// Instead of a chip family, the description can also be a [`BoardOverlay`], which
+/// adds memory regions and flash algorithms of a board to a chip which is already known.
+///
 /// # Examples
 ///
 /// ## Add targets from a YAML file
@@ -497,6 +545,34 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn builtin_option_bytes() {
+        let registry = Registry::from_builtin_families();
+        let target = registry.get_target_by_name("nRF52840_xxAA").unwrap();
+
+        let option_bytes = target.option_bytes.unwrap();
+        assert_eq!(option_bytes.address, 0x1000_1200);
+        assert!(option_bytes
+            .fields
+            .iter()
+            .any(|field| field.name == "APPROTECT"));
+    }
+
+    #[test]
+    fn builtin_option_bytes_inherited_from_family() {
+        let registry = Registry::from_builtin_families();
+
+        // The nRF52840 describes its own option bytes, which include the NFC pins.
+        let target = registry.get_target_by_name("nRF52840_xxAA").unwrap();
+        assert_eq!(target.option_bytes.unwrap().size, 0x10);
+
+        let target = registry.get_target_by_name("nRF52810_xxAA").unwrap();
+        assert_eq!(target.option_bytes.unwrap().size, 0xc);
+
+        let target = registry.get_target_by_name("STM32F401CBYx").unwrap();
+        assert_eq!(target.option_bytes.unwrap().address, 0x4002_3C14);
+    }
+
     #[test]
     fn add_targets_with_and_without_scanchain() -> TestResult {
         let file = File::open("tests/scan_chain_test.yaml")?;
@@ -529,4 +605,57 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn add_board_overlay() -> TestResult {
+        let mut registry = Registry::from_builtin_families();
+        let file = File::open("tests/board_overlay_test.yaml")?;
+        registry.add_target_from_yaml(file)?;
+
+        let target = registry.get_target_by_name("STM32H743ZITx")?;
+        let qspi = target
+            .memory_map
+            .iter()
+            .find_map(|region| {
+                region
+                    .as_nvm_region()
+                    .filter(|r| r.range.start == 0x9000_0000)
+            })
+            .unwrap();
+        assert_eq!(qspi.cores, ["main"]);
+
+        let algorithm = target
+            .flash_algorithms
+            .iter()
+            .find(|algorithm| algorithm.name == "board_overlay_test_qspi")
+            .unwrap();
+        assert_eq!(algorithm.cores, ["main"]);
+        assert_eq!(algorithm.init_arguments, [6, 1]);
+
+        // The chip keeps its own flash algorithm.
+        assert!(target.flash_algorithms.iter().any(|algorithm| algorithm
+            .flash_properties
+            .address_range
+            .start
+            == 0x0800_0000));
+
+        // Adding the same algorithm again is an error.
+        let file = File::open("tests/board_overlay_test.yaml")?;
+        assert!(matches!(
+            registry.add_target_from_yaml(file),
+            Err(RegistryError::InvalidBoardOverlay(_, _))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn board_overlay_unknown_chip() {
+        let mut registry = Registry::from_builtin_families();
+        let overlay = "name: Board\nchip: not_a_real_chip\n";
+        assert!(matches!(
+            registry.add_target_from_yaml(overlay.as_bytes()),
+            Err(RegistryError::ChipNotFound(_))
+        ));
+    }
 }
@@ -10,11 +10,16 @@ use crate::{
             ApAddress, ArmError, ArmProbeInterface, DpAddress,
         },
     },
+    flashing::{
+        option_bytes::OptionBytesSequence,
+        protection::{ProtectionSequence, ProtectionState, ProtectionStatus},
+    },
     probe::DebugProbeError,
     session::MissingPermissions,
     Permissions,
 };
 use bitfield::bitfield;
+use probe_rs_target::OptionBytes;
 use std::sync::Arc;
 
 use anyhow::Result;
@@ -212,6 +217,131 @@ impl<'a> architecture::arm::communication_interface::SwdSequence for SwdSequence
     }
 }
 
+/// Device Service Unit Device Identification Register, DSU - DID
+const DSU_DID: u64 = 0x4100_2018;
+
+/// The value of the DSU DID PROCESSOR field for Cortex-M0+ devices, which are the D1x/D2x/DAx
+/// families using the NVMCTRL described in [`nvmctrl`].
+const DID_PROCESSOR_CM0P: u32 = 1;
+
+/// The non-volatile memory controller of the D1x/D2x/DAx families.
+mod nvmctrl {
+    use std::time::{Duration, Instant};
+
+    use crate::architecture::arm::{
+        memory::adi_v5_memory_interface::ArmProbe, sequences::ArmDebugSequenceError, ArmError,
+    };
+
+    /// The user row, which contains the configuration of the device.
+    pub const USER_ROW: u64 = 0x0080_4000;
+
+    /// The size of a row, which is the unit of erasing.
+    pub const ROW_SIZE: usize = 256;
+
+    /// The size of a page, which is the unit of programming.
+    const PAGE_SIZE: usize = 64;
+
+    const NVMCTRL: u64 = 0x4100_4000;
+    const CTRLA: u64 = NVMCTRL;
+    const CTRLB: u64 = NVMCTRL + 0x04;
+    const INTFLAG: u64 = NVMCTRL + 0x14;
+    const STATUS: u64 = NVMCTRL + 0x18;
+    const ADDR: u64 = NVMCTRL + 0x1C;
+
+    /// The key which has to be written together with every command.
+    const CMDEX: u16 = 0xA5 << 8;
+    /// Erase Auxiliary Row
+    const CMD_EAR: u16 = 0x05;
+    /// Write Auxiliary Page
+    const CMD_WAP: u16 = 0x06;
+    /// Page Buffer Clear
+    const CMD_PBC: u16 = 0x44;
+    /// Set Security Bit
+    const CMD_SSB: u16 = 0x45;
+
+    /// Manual Write, pages are only written by an explicit command.
+    const CTRLB_MANW: u32 = 1 << 7;
+    const INTFLAG_READY: u8 = 1 << 0;
+    /// The PROGE, LOCKE and NVME error bits.
+    const STATUS_ERRORS: u16 = 0b1_1100;
+    /// The security bit is set, the device is protected after the next reset.
+    const STATUS_SB: u16 = 1 << 8;
+
+    /// Returns true if the security bit is set.
+    pub fn security_bit(memory: &mut dyn ArmProbe) -> Result<bool, ArmError> {
+        Ok(memory.read_word_16(STATUS)? & STATUS_SB != 0)
+    }
+
+    /// Sets the security bit, which protects the device after the next reset. It can only be
+    /// cleared by a chip erase.
+    pub fn set_security_bit(memory: &mut dyn ArmProbe) -> Result<(), ArmError> {
+        memory.write_word_16(STATUS, STATUS_ERRORS)?;
+        command(memory, CMD_SSB, 0)
+    }
+
+    /// Erases the auxiliary row at `row`, and programs it with `data`.
+    pub fn program_auxiliary_row(
+        memory: &mut dyn ArmProbe,
+        row: u64,
+        data: &[u8],
+    ) -> Result<(), ArmError> {
+        let ctrlb = memory.read_word_32(CTRLB)?;
+        memory.write_word_32(CTRLB, ctrlb | CTRLB_MANW)?;
+        memory.write_word_16(STATUS, STATUS_ERRORS)?;
+
+        let result = (|| {
+            command(memory, CMD_EAR, row)?;
+            for (index, page) in data.chunks(PAGE_SIZE).enumerate() {
+                let address = row + (index * PAGE_SIZE) as u64;
+                command(memory, CMD_PBC, address)?;
+                // The page buffer only accepts 16 and 32-bit writes.
+                let words = page
+                    .chunks(4)
+                    .map(|bytes| {
+                        let mut word = [0xFF; 4];
+                        word[..bytes.len()].copy_from_slice(bytes);
+                        u32::from_le_bytes(word)
+                    })
+                    .collect::<Vec<_>>();
+                memory.write_32(address, &words)?;
+                command(memory, CMD_WAP, address)?;
+            }
+            Ok(())
+        })();
+
+        memory.write_word_32(CTRLB, ctrlb)?;
+        result
+    }
+
+    fn command(memory: &mut dyn ArmProbe, command: u16, address: u64) -> Result<(), ArmError> {
+        wait_ready(memory)?;
+        // The address register takes 16-bit word addresses.
+        memory.write_word_32(ADDR, (address / 2) as u32)?;
+        memory.write_word_16(CTRLA, CMDEX | command)?;
+        wait_ready(memory)?;
+
+        if memory.read_word_16(STATUS)? & STATUS_ERRORS != 0 {
+            return Err(ArmDebugSequenceError::custom(format!(
+                "NVMCTRL command {command:#04x} at {address:#010x} failed"
+            ))
+            .into());
+        }
+
+        Ok(())
+    }
+
+    fn wait_ready(memory: &mut dyn ArmProbe) -> Result<(), ArmError> {
+        let start = Instant::now();
+        while memory.read_word_8(INTFLAG)? & INTFLAG_READY == 0 {
+            if start.elapsed() > Duration::from_secs(1) {
+                return Err(ArmError::Timeout);
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Marker struct indicating initialization sequencing for Atmel/Microchip ATSAM family parts.
 #[derive(Debug)]
 pub struct AtSAM {}
@@ -475,6 +605,116 @@ impl ArmDebugSequence for AtSAM {
     fn debug_erase_sequence(&self) -> Option<Arc<dyn DebugEraseSequence>> {
         Some(Self::create())
     }
+
+    fn option_bytes_sequence(&self) -> Option<Arc<dyn OptionBytesSequence>> {
+        Some(Self::create())
+    }
+
+    fn protection_sequence(&self) -> Option<Arc<dyn ProtectionSequence>> {
+        Some(Self::create())
+    }
+}
+
+impl OptionBytesSequence for AtSAM {
+    /// Writes the user row of D1x/D2x/DAx devices.
+    ///
+    /// The whole row is erased, so the bytes of the row outside of the option bytes are restored
+    /// afterwards.
+    fn write_option_bytes(
+        &self,
+        memory: &mut dyn ArmProbe,
+        option_bytes: &OptionBytes,
+        data: &[u8],
+    ) -> Result<(), ArmError> {
+        if memory.read_word_32(DSU_DID)? >> 28 != DID_PROCESSOR_CM0P {
+            return Err(DebugProbeError::NotImplemented {
+                function_name: "write_option_bytes",
+            }
+            .into());
+        }
+
+        let row = nvmctrl::USER_ROW..nvmctrl::USER_ROW + nvmctrl::ROW_SIZE as u64;
+        if !row.contains(&option_bytes.address)
+            || option_bytes.address + data.len() as u64 > row.end
+        {
+            return Err(ArmDebugSequenceError::custom(
+                "The option bytes of ATSAM devices have to be inside the user row",
+            )
+            .into());
+        }
+
+        let mut contents = vec![0; nvmctrl::ROW_SIZE];
+        memory.read_8(row.start, &mut contents)?;
+
+        let offset = (option_bytes.address - row.start) as usize;
+        if contents[offset..offset + data.len()] == *data {
+            return Ok(());
+        }
+        contents[offset..offset + data.len()].copy_from_slice(data);
+
+        nvmctrl::program_auxiliary_row(memory, row.start, &contents)
+    }
+}
+
+impl ProtectionSequence for AtSAM {
+    /// Reads the PROT bit of the DSU, and the security bit of the NVMCTRL on D1x/D2x/DAx devices.
+    fn protection_status(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+    ) -> Result<ProtectionStatus, ArmError> {
+        let mut memory = interface.memory_interface(default_ap)?;
+
+        // The DSU is accessible even when the device is protected.
+        let dsu_status_b = DsuStatusB::from(memory.read_word_8(DsuStatusB::ADDRESS)?);
+        if dsu_status_b.prot() {
+            return Ok(ProtectionStatus::active(
+                ProtectionState::Protected,
+                "security bit set",
+            ));
+        }
+
+        if memory.read_word_32(DSU_DID)? >> 28 == DID_PROCESSOR_CM0P
+            && nvmctrl::security_bit(&mut *memory)?
+        {
+            return Ok(ProtectionStatus::pending(
+                ProtectionState::Protected,
+                "security bit set",
+            ));
+        }
+
+        Ok(ProtectionStatus::active(
+            ProtectionState::Unprotected,
+            "security bit cleared",
+        ))
+    }
+
+    fn enable_protection(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+    ) -> Result<(), ArmError> {
+        let mut memory = interface.memory_interface(default_ap)?;
+        if memory.read_word_32(DSU_DID)? >> 28 != DID_PROCESSOR_CM0P {
+            return Err(DebugProbeError::NotImplemented {
+                function_name: "enable_protection",
+            }
+            .into());
+        }
+
+        nvmctrl::set_security_bit(&mut *memory)
+    }
+
+    fn disable_protection(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+    ) -> Result<(), ArmError> {
+        let mut memory = interface.memory_interface(default_ap)?;
+
+        // The chip erase clears the security bit, and returns `ReAttachRequired`.
+        AtSAM::erase_all(self, &mut *memory, &Permissions::new().allow_erase_all())
+    }
 }
 
 impl DebugEraseSequence for AtSAM {
@@ -489,3 +729,195 @@ impl DebugEraseSequence for AtSAM {
         AtSAM::erase_all(self, &mut *memory, &Permissions::new().allow_erase_all())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::nvmctrl;
+    use crate::architecture::arm::{
+        ap::MemoryAp,
+        communication_interface::{Initialized, SwdSequence},
+        memory::adi_v5_memory_interface::ArmProbe,
+        ArmCommunicationInterface, ArmError,
+    };
+    use crate::{probe::DebugProbeError, CoreStatus};
+
+    const CTRLA: u64 = 0x4100_4000;
+    const INTFLAG: u64 = 0x4100_4014;
+
+    /// A memory access, with the access width in bits.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Access {
+        Read { width: u8, address: u64 },
+        Write { width: u8, address: u64, value: u32 },
+    }
+
+    /// Records all accesses. The NVMCTRL is always ready and reports no errors.
+    #[derive(Default)]
+    struct MockNvmctrl {
+        accesses: Vec<Access>,
+    }
+
+    impl MockNvmctrl {
+        fn read(&mut self, width: u8, address: u64) -> u32 {
+            self.accesses.push(Access::Read { width, address });
+            if address == INTFLAG {
+                1
+            } else {
+                0
+            }
+        }
+
+        fn write(&mut self, width: u8, address: u64, value: u32) {
+            self.accesses.push(Access::Write {
+                width,
+                address,
+                value,
+            });
+        }
+
+        /// The commands written to CTRLA, without the key.
+        fn commands(&self) -> Vec<u16> {
+            self.accesses
+                .iter()
+                .filter_map(|access| match *access {
+                    Access::Write {
+                        address: CTRLA,
+                        value,
+                        ..
+                    } => Some(value as u16 & 0x7F),
+                    _ => None,
+                })
+                .collect()
+        }
+    }
+
+    impl ArmProbe for MockNvmctrl {
+        fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
+            for (index, value) in data.iter_mut().enumerate() {
+                *value = self.read(8, address + index as u64) as u8;
+            }
+            Ok(())
+        }
+
+        fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), ArmError> {
+            for (index, value) in data.iter_mut().enumerate() {
+                *value = self.read(16, address + 2 * index as u64) as u16;
+            }
+            Ok(())
+        }
+
+        fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+            for (index, value) in data.iter_mut().enumerate() {
+                *value = self.read(32, address + 4 * index as u64);
+            }
+            Ok(())
+        }
+
+        fn read_64(&mut self, _address: u64, _data: &mut [u64]) -> Result<(), ArmError> {
+            todo!()
+        }
+
+        fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
+            for (index, value) in data.iter().enumerate() {
+                self.write(8, address + index as u64, *value as u32);
+            }
+            Ok(())
+        }
+
+        fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), ArmError> {
+            for (index, value) in data.iter().enumerate() {
+                self.write(16, address + 2 * index as u64, *value as u32);
+            }
+            Ok(())
+        }
+
+        fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+            for (index, value) in data.iter().enumerate() {
+                self.write(32, address + 4 * index as u64, *value);
+            }
+            Ok(())
+        }
+
+        fn write_64(&mut self, _address: u64, _data: &[u64]) -> Result<(), ArmError> {
+            todo!()
+        }
+
+        fn flush(&mut self) -> Result<(), ArmError> {
+            Ok(())
+        }
+
+        fn supports_native_64bit_access(&mut self) -> bool {
+            false
+        }
+
+        fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+            Ok(true)
+        }
+
+        fn ap(&mut self) -> MemoryAp {
+            todo!()
+        }
+
+        fn get_arm_communication_interface(
+            &mut self,
+        ) -> Result<&mut ArmCommunicationInterface<Initialized>, DebugProbeError> {
+            todo!()
+        }
+
+        fn update_core_status(&mut self, _state: CoreStatus) {}
+    }
+
+    impl SwdSequence for MockNvmctrl {
+        fn swj_sequence(&mut self, _bit_len: u8, _bits: u64) -> Result<(), DebugProbeError> {
+            todo!()
+        }
+
+        fn swj_pins(
+            &mut self,
+            _pin_out: u32,
+            _pin_select: u32,
+            _pin_wait: u32,
+        ) -> Result<u32, DebugProbeError> {
+            todo!()
+        }
+    }
+
+    #[test]
+    fn program_auxiliary_row() {
+        let data: Vec<u8> = (0..nvmctrl::ROW_SIZE).map(|i| i as u8).collect();
+        let mut mock = MockNvmctrl::default();
+
+        nvmctrl::program_auxiliary_row(&mut mock, nvmctrl::USER_ROW, &data).unwrap();
+
+        // Erase Auxiliary Row, then Page Buffer Clear and Write Auxiliary Page for each page.
+        assert_eq!(
+            mock.commands(),
+            [0x05, 0x44, 0x06, 0x44, 0x06, 0x44, 0x06, 0x44, 0x06]
+        );
+
+        // The page buffer is only written with 32-bit accesses, between clearing and writing the page.
+        let row = nvmctrl::USER_ROW..nvmctrl::USER_ROW + nvmctrl::ROW_SIZE as u64;
+        let mut pages = vec![];
+        let mut last_command = None;
+        for access in &mock.accesses {
+            match *access {
+                Access::Write {
+                    address: CTRLA,
+                    value,
+                    ..
+                } => last_command = Some(value as u16 & 0x7F),
+                Access::Write {
+                    width,
+                    address,
+                    value,
+                } if row.contains(&address) => {
+                    assert_eq!(width, 32, "8 or 16-bit write to the page buffer");
+                    assert_eq!(last_command, Some(0x44));
+                    pages.extend_from_slice(&value.to_le_bytes());
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(pages, data);
+    }
+}
@@ -2,13 +2,17 @@
 
 use std::sync::Arc;
 
+use probe_rs_target::OptionBytes;
+
 use crate::architecture::arm::{
     ap::MemoryAp,
     component::TraceSink,
-    memory::CoresightComponent,
+    memory::{adi_v5_memory_interface::ArmProbe, CoresightComponent},
     sequences::{ArmDebugSequence, ArmDebugSequenceError},
     ApAddress, ArmError, ArmProbeInterface, DpAddress,
 };
+use crate::flashing::option_bytes::OptionBytesSequence;
+use crate::flashing::protection::{ProtectionSequence, ProtectionState, ProtectionStatus};
 use crate::session::MissingPermissions;
 
 /// An error when operating a core ROM table component occurred.
@@ -28,6 +32,37 @@ const ERASEALL: u8 = 0x04;
 const ERASEALLSTATUS: u8 = 0x08;
 const APPROTECTSTATUS: u8 = 0x0C;
 
+/// The address of the APPROTECT register in the UICR.
+const UICR_APPROTECT: u64 = 0x1000_1208;
+/// The value of UICR.APPROTECT which enables the protection.
+const APPROTECT_ENABLED: u32 = 0xFFFF_FF00;
+/// The value of the PALL field of UICR.APPROTECT which disables the protection on devices with
+/// hardware APPROTECT.
+const APPROTECT_HW_DISABLED: u32 = 0x5A;
+
+/// FICR.INFO.PART, e.g. `0x52840` for the nRF52840.
+const FICR_INFO_PART: u64 = 0x1000_0100;
+/// FICR.INFO.VARIANT, e.g. `AAF0` in ASCII. The third character is the build code.
+const FICR_INFO_VARIANT: u64 = 0x1000_0104;
+
+/// The first build code of each part with hardware APPROTECT. These revisions are protected
+/// after every reset, unless UICR.APPROTECT is set to `HwDisabled`.
+const HW_APPROTECT_BUILD_CODES: [(u32, u8); 7] = [
+    (0x52805, b'B'),
+    (0x52810, b'E'),
+    (0x52811, b'B'),
+    (0x52820, b'D'),
+    (0x52832, b'G'),
+    (0x52833, b'B'),
+    (0x52840, b'F'),
+];
+
+/// The CTRL-AP, which controls the APPROTECT and the erase of the whole chip.
+const CTRL_AP: ApAddress = ApAddress {
+    ap: 1,
+    dp: DpAddress::Default,
+};
+
 /// Marker struct indicating initialization sequencing for nRF52 family parts.
 #[derive(Debug)]
 pub struct Nrf52 {}
@@ -46,6 +81,36 @@ impl Nrf52 {
         let status = iface.read_raw_ap_register(ctrl_ap, APPROTECTSTATUS)?;
         Ok(status != 0)
     }
+
+    /// Returns true if the chip is a revision with hardware APPROTECT.
+    fn has_hardware_approtect(memory: &mut dyn ArmProbe) -> Result<bool, ArmError> {
+        let part = memory.read_word_32(FICR_INFO_PART)?;
+        let variant = memory.read_word_32(FICR_INFO_VARIANT)?;
+        Ok(is_hardware_approtect_revision(part, variant))
+    }
+
+    /// Erases the flash, the UICR and the RAM through the CTRL-AP, which disables the APPROTECT.
+    fn erase_all(
+        &self,
+        iface: &mut dyn ArmProbeInterface,
+        ctrl_ap: ApAddress,
+    ) -> Result<(), ArmError> {
+        // Reset
+        iface.write_raw_ap_register(ctrl_ap, RESET, 1)?;
+        iface.write_raw_ap_register(ctrl_ap, RESET, 0)?;
+
+        // Start erase
+        iface.write_raw_ap_register(ctrl_ap, ERASEALL, 1)?;
+
+        // Wait for erase done
+        while iface.read_raw_ap_register(ctrl_ap, ERASEALLSTATUS)? != 0 {}
+
+        // Reset again
+        iface.write_raw_ap_register(ctrl_ap, RESET, 1)?;
+        iface.write_raw_ap_register(ctrl_ap, RESET, 0)?;
+
+        Ok(())
+    }
 }
 
 mod clock {
@@ -83,6 +148,77 @@ mod clock {
     }
 }
 
+mod nvmc {
+    use std::time::{Duration, Instant};
+
+    use crate::architecture::arm::{memory::adi_v5_memory_interface::ArmProbe, ArmError};
+
+    /// The base address of the UICR.
+    pub const UICR: u64 = 0x1000_1000;
+
+    /// The size of the UICR which is backed up when it has to be erased.
+    pub const UICR_SIZE: usize = 0x400;
+
+    /// The base address of the NVMC peripheral.
+    const NVMC: u64 = 0x4001_E000;
+
+    const READY: u64 = NVMC + 0x400;
+    const CONFIG: u64 = NVMC + 0x504;
+    const ERASEUICR: u64 = NVMC + 0x514;
+
+    const CONFIG_REN: u32 = 0;
+    const CONFIG_WEN: u32 = 1;
+    const CONFIG_EEN: u32 = 2;
+
+    /// Writes the given words into the UICR.
+    ///
+    /// Flash bits can only be changed from 1 to 0, so the whole UICR is erased first if
+    /// any bit has to change from 0 to 1, and all other words of the UICR are restored.
+    pub fn program_uicr(
+        memory: &mut dyn ArmProbe,
+        address: u64,
+        data: &[u8],
+    ) -> Result<(), ArmError> {
+        let mut current = vec![0; UICR_SIZE];
+        memory.read_8(UICR, &mut current)?;
+
+        let offset = (address - UICR) as usize;
+        let mut new = current.clone();
+        new[offset..offset + data.len()].copy_from_slice(data);
+
+        if current.iter().zip(&new).any(|(old, new)| old & new != *new) {
+            tracing::info!("Erasing the UICR to program the option bytes");
+            memory.write_word_32(CONFIG, CONFIG_EEN)?;
+            memory.write_word_32(ERASEUICR, 1)?;
+            wait_ready(memory)?;
+            current.fill(0xFF);
+        }
+
+        memory.write_word_32(CONFIG, CONFIG_WEN)?;
+        for (index, (new, old)) in new.chunks_exact(4).zip(current.chunks_exact(4)).enumerate() {
+            if new != old {
+                let value = u32::from_le_bytes(new.try_into().unwrap());
+                memory.write_word_32(UICR + index as u64 * 4, value)?;
+                wait_ready(memory)?;
+            }
+        }
+        memory.write_word_32(CONFIG, CONFIG_REN)?;
+
+        Ok(())
+    }
+
+    fn wait_ready(memory: &mut dyn ArmProbe) -> Result<(), ArmError> {
+        let start = Instant::now();
+        while memory.read_word_32(READY)? & 1 == 0 {
+            if start.elapsed() > Duration::from_secs(1) {
+                return Err(ArmError::Timeout);
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl ArmDebugSequence for Nrf52 {
     fn debug_device_unlock(
         &self,
@@ -90,10 +226,7 @@ impl ArmDebugSequence for Nrf52 {
         _default_ap: MemoryAp,
         permissions: &crate::Permissions,
     ) -> Result<(), ArmError> {
-        let ctrl_ap = ApAddress {
-            ap: 1,
-            dp: DpAddress::Default,
-        };
+        let ctrl_ap = CTRL_AP;
 
         tracing::info!("Checking if core is unlocked");
         if self.is_core_unlocked(iface, ctrl_ap)? {
@@ -106,19 +239,7 @@ impl ArmDebugSequence for Nrf52 {
             .erase_all()
             .map_err(|MissingPermissions(desc)| ArmError::MissingPermissions(desc))?;
 
-        // Reset
-        iface.write_raw_ap_register(ctrl_ap, RESET, 1)?;
-        iface.write_raw_ap_register(ctrl_ap, RESET, 0)?;
-
-        // Start erase
-        iface.write_raw_ap_register(ctrl_ap, ERASEALL, 1)?;
-
-        // Wait for erase done
-        while iface.read_raw_ap_register(ctrl_ap, ERASEALLSTATUS)? != 0 {}
-
-        // Reset again
-        iface.write_raw_ap_register(ctrl_ap, RESET, 1)?;
-        iface.write_raw_ap_register(ctrl_ap, RESET, 0)?;
+        self.erase_all(iface, ctrl_ap)?;
 
         if !self.is_core_unlocked(iface, ctrl_ap)? {
             return Err(ArmDebugSequenceError::custom("Could not unlock core").into());
@@ -168,6 +289,106 @@ impl ArmDebugSequence for Nrf52 {
 
         Ok(())
     }
+
+    fn option_bytes_sequence(&self) -> Option<Arc<dyn OptionBytesSequence>> {
+        Some(Self::create())
+    }
+
+    fn protection_sequence(&self) -> Option<Arc<dyn ProtectionSequence>> {
+        Some(Self::create())
+    }
+}
+
+impl OptionBytesSequence for Nrf52 {
+    fn write_option_bytes(
+        &self,
+        memory: &mut dyn ArmProbe,
+        option_bytes: &OptionBytes,
+        data: &[u8],
+    ) -> Result<(), ArmError> {
+        let uicr = nvmc::UICR..nvmc::UICR + nvmc::UICR_SIZE as u64;
+        let end = option_bytes.address + data.len() as u64;
+        if !uicr.contains(&option_bytes.address)
+            || end > uicr.end
+            || option_bytes.address % 4 != 0
+            || data.len() % 4 != 0
+        {
+            return Err(ArmDebugSequenceError::custom(
+                "The option bytes of nRF52 devices have to be word aligned and inside the UICR",
+            )
+            .into());
+        }
+
+        nvmc::program_uicr(memory, option_bytes.address, data)
+    }
+}
+
+impl ProtectionSequence for Nrf52 {
+    fn protection_status(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+    ) -> Result<ProtectionStatus, ArmError> {
+        if !self.is_core_unlocked(interface, CTRL_AP)? {
+            return Ok(ProtectionStatus::active(
+                ProtectionState::Protected,
+                "APPROTECT enabled",
+            ));
+        }
+
+        let mut memory = interface.memory_interface(default_ap)?;
+        Ok(match memory.read_word_32(UICR_APPROTECT)? & 0xFF {
+            APPROTECT_HW_DISABLED => {
+                ProtectionStatus::active(ProtectionState::Unprotected, "APPROTECT disabled")
+            }
+            // An erased UICR.APPROTECT only disables the protection of older revisions.
+            0xFF if !Self::has_hardware_approtect(&mut *memory)? => {
+                ProtectionStatus::active(ProtectionState::Unprotected, "APPROTECT disabled")
+            }
+            0xFF => ProtectionStatus::pending(
+                ProtectionState::Protected,
+                "hardware APPROTECT enabled, unless the firmware disables it",
+            ),
+            _ => ProtectionStatus::pending(ProtectionState::Protected, "UICR.APPROTECT enabled"),
+        })
+    }
+
+    fn enable_protection(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+    ) -> Result<(), ArmError> {
+        let mut memory = interface.memory_interface(default_ap)?;
+        nvmc::program_uicr(
+            &mut *memory,
+            UICR_APPROTECT,
+            &APPROTECT_ENABLED.to_le_bytes(),
+        )
+    }
+
+    fn disable_protection(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+    ) -> Result<(), ArmError> {
+        tracing::warn!("Erasing the whole chip to disable the APPROTECT");
+        self.erase_all(interface, CTRL_AP)?;
+
+        // The erase leaves UICR.APPROTECT erased, which protects revisions with hardware
+        // APPROTECT again after the next reset.
+        if self.is_core_unlocked(interface, CTRL_AP)? {
+            let mut memory = interface.memory_interface(default_ap)?;
+            if Self::has_hardware_approtect(&mut *memory)? {
+                nvmc::program_uicr(
+                    &mut *memory,
+                    UICR_APPROTECT,
+                    &(0xFFFF_FF00 | APPROTECT_HW_DISABLED).to_le_bytes(),
+                )?;
+            }
+        }
+
+        Err(ArmError::ReAttachRequired)
+    }
 }
 
 impl From<ComponentError> for ArmError {
@@ -175,3 +396,43 @@ impl From<ComponentError> for ArmError {
         ArmError::DebugSequence(ArmDebugSequenceError::custom(value))
     }
 }
+
+/// Returns true if the part given by FICR.INFO.PART and FICR.INFO.VARIANT has hardware APPROTECT.
+fn is_hardware_approtect_revision(part: u32, variant: u32) -> bool {
+    let build_code = (variant >> 8) as u8;
+    HW_APPROTECT_BUILD_CODES
+        .iter()
+        .any(|&(hw_part, first_build_code)| hw_part == part && build_code >= first_build_code)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hardware_approtect_revisions() {
+        // nRF52840 QIAA-D0 and QIAA-F0
+        assert!(!is_hardware_approtect_revision(
+            0x52840,
+            u32::from_be_bytes(*b"AAD0")
+        ));
+        assert!(is_hardware_approtect_revision(
+            0x52840,
+            u32::from_be_bytes(*b"AAF0")
+        ));
+        // nRF52832 QFAA-E0 and QFAA-G0
+        assert!(!is_hardware_approtect_revision(
+            0x52832,
+            u32::from_be_bytes(*b"AAE0")
+        ));
+        assert!(is_hardware_approtect_revision(
+            0x52832,
+            u32::from_be_bytes(*b"AAG0")
+        ));
+        // Unknown parts keep the old behaviour.
+        assert!(!is_hardware_approtect_revision(
+            0x52999,
+            u32::from_be_bytes(*b"AAZ0")
+        ));
+    }
+}
@@ -10,7 +10,7 @@
 
 use std::sync::Arc;
 
-use probe_rs_target::CoreType;
+use probe_rs_target::{CoreType, OptionBytes};
 
 use crate::architecture::arm::{
     ap::MemoryAp,
@@ -19,6 +19,9 @@ use crate::architecture::arm::{
     sequences::ArmDebugSequence,
     ArmError, ArmProbeInterface,
 };
+use crate::flashing::option_bytes::OptionBytesSequence;
+use crate::flashing::protection::{ProtectionSequence, ProtectionState, ProtectionStatus};
+use crate::probe::DebugProbeError;
 
 /// Marker structure for most ARMv7 STM32 devices.
 #[derive(Debug)]
@@ -38,6 +41,19 @@ mod dbgmcu {
     /// The base address of the DBGMCU component
     const DBGMCU: u64 = 0xE004_2000;
 
+    /// The device identifiers of the STM32F2, STM32F4 and STM32F7, which share a flash controller.
+    const OPTCR_DEVICES: [u16; 14] = [
+        0x411, 0x413, 0x419, 0x421, 0x423, 0x431, 0x433, 0x434, 0x441, 0x458, 0x463, 0x449, 0x451,
+        0x452,
+    ];
+
+    /// Returns true if the device has the option control register of the STM32F2/F4/F7.
+    pub fn has_optcr(memory: &mut dyn ArmProbe) -> Result<bool, ArmError> {
+        // The DEV_ID field of the IDCODE register, at the start of the DBGMCU.
+        let dev_id = (memory.read_word_32(DBGMCU)? & 0xFFF) as u16;
+        Ok(OPTCR_DEVICES.contains(&dev_id))
+    }
+
     bitfield! {
         /// The control register (CR) of the DBGMCU. This register is described in "RM0090: STM32F7
         /// family reference manual" section 38.16.3
@@ -68,6 +84,106 @@ mod dbgmcu {
     }
 }
 
+mod flash {
+    use std::time::{Duration, Instant};
+
+    use crate::architecture::arm::{memory::adi_v5_memory_interface::ArmProbe, ArmError};
+
+    /// The start of the main flash memory.
+    pub const FLASH_BASE: u64 = 0x0800_0000;
+
+    /// The address of the option control register (OPTCR) of the STM32F2, STM32F4 and STM32F7.
+    pub const OPTCR: u64 = 0x4002_3C14;
+
+    /// The option key register (OPTKEYR), which unlocks OPTCR.
+    const OPTKEYR: u64 = 0x4002_3C08;
+
+    /// The status register (SR).
+    const SR: u64 = 0x4002_3C0C;
+
+    const OPTKEY1: u32 = 0x0819_2A3B;
+    const OPTKEY2: u32 = 0x4C5D_6E7F;
+
+    const OPTLOCK: u32 = 1 << 0;
+    const OPTSTRT: u32 = 1 << 1;
+    const SR_BSY: u32 = 1 << 16;
+
+    /// The value of the RDP field which disables the read protection.
+    pub const RDP_LEVEL_0: u32 = 0xAA;
+
+    /// The value of the RDP field which enables the read protection. Any value other than
+    /// level 0 and level 2 selects level 1.
+    pub const RDP_LEVEL_1: u32 = 0x55;
+
+    /// The value of the RDP field which permanently locks the chip.
+    pub const RDP_LEVEL_2: u32 = 0xCC;
+
+    /// Reads the read protection level (RDP) from OPTCR.
+    pub fn read_protection_level(memory: &mut dyn ArmProbe) -> Result<u32, ArmError> {
+        Ok(memory.read_word_32(OPTCR)? >> 8 & 0xFF)
+    }
+
+    /// Programs the read protection level (RDP), keeping the other option bytes.
+    ///
+    /// Going from level 1 to level 0 erases the whole flash, which takes a lot longer than
+    /// programming the option bytes alone.
+    pub fn program_protection_level(memory: &mut dyn ArmProbe, rdp: u32) -> Result<(), ArmError> {
+        let optcr = memory.read_word_32(OPTCR)? & !(0xFF << 8) | rdp << 8;
+        program_optcr(memory, optcr, Duration::from_secs(60))
+    }
+
+    /// Programs OPTCR.
+    pub fn program_option_bytes(memory: &mut dyn ArmProbe, optcr: u32) -> Result<(), ArmError> {
+        if optcr >> 8 & 0xFF == RDP_LEVEL_2 {
+            return Err(ArmError::MissingPermissions(
+                "Setting the read protection to level 2 permanently locks the chip".to_string(),
+            ));
+        }
+
+        program_optcr(memory, optcr, Duration::from_secs(5))
+    }
+
+    fn unlock(memory: &mut dyn ArmProbe) -> Result<(), ArmError> {
+        wait_not_busy(memory, Duration::from_secs(5))?;
+
+        if memory.read_word_32(OPTCR)? & OPTLOCK != 0 {
+            memory.write_word_32(OPTKEYR, OPTKEY1)?;
+            memory.write_word_32(OPTKEYR, OPTKEY2)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes `optcr` and starts programming the option bytes.
+    fn program_optcr(
+        memory: &mut dyn ArmProbe,
+        optcr: u32,
+        timeout: Duration,
+    ) -> Result<(), ArmError> {
+        unlock(memory)?;
+
+        let optcr = optcr & !(OPTLOCK | OPTSTRT);
+        memory.write_word_32(OPTCR, optcr)?;
+        memory.write_word_32(OPTCR, optcr | OPTSTRT)?;
+
+        let result = wait_not_busy(memory, timeout);
+        memory.write_word_32(OPTCR, optcr | OPTLOCK)?;
+        result
+    }
+
+    fn wait_not_busy(memory: &mut dyn ArmProbe, timeout: Duration) -> Result<(), ArmError> {
+        // Programming the option bytes erases and programs a flash sector, which can take a while.
+        let start = Instant::now();
+        while memory.read_word_32(SR)? & SR_BSY != 0 {
+            if start.elapsed() > timeout {
+                return Err(ArmError::Timeout);
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl ArmDebugSequence for Stm32Armv7 {
     fn debug_device_unlock(
         &self,
@@ -120,4 +236,98 @@ impl ArmDebugSequence for Stm32Armv7 {
         cr.write(&mut *memory)?;
         Ok(())
     }
+
+    fn option_bytes_sequence(&self) -> Option<Arc<dyn OptionBytesSequence>> {
+        Some(Self::create())
+    }
+
+    fn protection_sequence(&self) -> Option<Arc<dyn ProtectionSequence>> {
+        Some(Self::create())
+    }
+}
+
+impl OptionBytesSequence for Stm32Armv7 {
+    fn write_option_bytes(
+        &self,
+        memory: &mut dyn ArmProbe,
+        option_bytes: &OptionBytes,
+        data: &[u8],
+    ) -> Result<(), ArmError> {
+        // Only the flash controller of the STM32F2/F4/F7 is supported so far.
+        if option_bytes.address != flash::OPTCR || data.len() != 4 {
+            return Err(DebugProbeError::NotImplemented {
+                function_name: "write_option_bytes",
+            }
+            .into());
+        }
+
+        let optcr = u32::from_le_bytes(data.try_into().unwrap());
+
+        flash::program_option_bytes(memory, optcr)
+    }
+}
+
+impl Stm32Armv7 {
+    /// Returns the memory interface if the device has the flash controller of the STM32F2/F4/F7,
+    /// which is the only one supported so far.
+    fn optcr_memory<'a>(
+        interface: &'a mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+        function_name: &'static str,
+    ) -> Result<Box<dyn ArmProbe + 'a>, ArmError> {
+        let mut memory = interface.memory_interface(default_ap)?;
+        if !dbgmcu::has_optcr(&mut *memory)? {
+            return Err(DebugProbeError::NotImplemented { function_name }.into());
+        }
+
+        Ok(memory)
+    }
+}
+
+impl ProtectionSequence for Stm32Armv7 {
+    fn protection_status(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+    ) -> Result<ProtectionStatus, ArmError> {
+        let mut memory = Self::optcr_memory(interface, default_ap, "protection_status")?;
+
+        Ok(match flash::read_protection_level(&mut *memory)? {
+            flash::RDP_LEVEL_0 => {
+                ProtectionStatus::active(ProtectionState::Unprotected, "RDP level 0")
+            }
+            flash::RDP_LEVEL_2 => {
+                ProtectionStatus::active(ProtectionState::PermanentlyProtected, "RDP level 2")
+            }
+            rdp => {
+                // Level 1 only takes effect when the option bytes are reloaded, after which the
+                // debugger can't read the flash anymore.
+                let description = format!("RDP level 1, RDP = {rdp:#04x}");
+                if memory.read_word_32(flash::FLASH_BASE).is_err() {
+                    ProtectionStatus::active(ProtectionState::Protected, description)
+                } else {
+                    ProtectionStatus::pending(ProtectionState::Protected, description)
+                }
+            }
+        })
+    }
+
+    fn enable_protection(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+    ) -> Result<(), ArmError> {
+        let mut memory = Self::optcr_memory(interface, default_ap, "enable_protection")?;
+        flash::program_protection_level(&mut *memory, flash::RDP_LEVEL_1)
+    }
+
+    fn disable_protection(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+    ) -> Result<(), ArmError> {
+        let mut memory = Self::optcr_memory(interface, default_ap, "disable_protection")?;
+        tracing::warn!("Setting the read protection to level 0, this erases the whole flash");
+        flash::program_protection_level(&mut *memory, flash::RDP_LEVEL_0)
+    }
 }
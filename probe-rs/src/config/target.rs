@@ -31,7 +31,7 @@ use crate::architecture::{
     xtensa::sequences::{DefaultXtensaSequence, XtensaDebugSequence},
 };
 use crate::flashing::FlashLoader;
-use probe_rs_target::{Architecture, BinaryFormat, ChipFamily, Jtag, MemoryRange};
+use probe_rs_target::{Architecture, BinaryFormat, ChipFamily, Jtag, MemoryRange, OptionBytes};
 use std::sync::Arc;
 
 /// This describes a complete target with a fixed chip model and variant.
@@ -62,6 +62,8 @@ pub struct Target {
     pub jtag: Option<Jtag>,
     /// The default executable format for the target.
     pub default_format: BinaryFormat,
+    /// The option bytes of the target, if they are described in the target description.
+    pub option_bytes: Option<OptionBytes>,
 }
 
 impl std::fmt::Debug for Target {
@@ -232,6 +234,10 @@ impl Target {
             rtt_scan_regions,
             jtag: chip.jtag.clone(),
             default_format: chip.default_binary_format.clone().unwrap_or_default(),
+            option_bytes: chip
+                .option_bytes
+                .clone()
+                .or_else(|| family.option_bytes.clone()),
         })
     }
 
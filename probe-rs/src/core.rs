@@ -1,6 +1,9 @@
 use crate::{
-    architecture::arm::sequences::ArmDebugSequence, config::DebugSequence, debug::DebugRegisters,
-    error::Error, CoreType, InstructionSet, MemoryInterface, Target,
+    architecture::arm::{fault::FaultStatus, sequences::ArmDebugSequence},
+    config::DebugSequence,
+    debug::DebugRegisters,
+    error::Error,
+    CoreType, InstructionSet, MemoryInterface, Target,
 };
 use anyhow::anyhow;
 pub use probe_rs_target::{Architecture, CoreAccessOptions};
@@ -724,6 +727,19 @@ impl<'probe> Core<'probe> {
         self.inner.disable_vector_catch(condition)
     }
 
+    /// Reads and decodes the fault status registers of a halted Cortex-M core,
+    /// together with the exception frame of the interrupted code.
+    ///
+    /// Use [`FaultStatus::faulting_frame`] to find the function and source line which caused the fault.
+    pub fn fault_status(&mut self) -> Result<FaultStatus, Error> {
+        match self.core_type() {
+            CoreType::Armv6m | CoreType::Armv7m | CoreType::Armv7em | CoreType::Armv8m => {
+                crate::architecture::arm::core::fault::read_fault_status(self)
+            }
+            _ => Err(Error::NotImplemented("fault analysis")),
+        }
+    }
+
     /// Dumps core info with the current state.
     ///
     /// # Arguments
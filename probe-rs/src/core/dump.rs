@@ -12,7 +12,8 @@ use crate::{
         xtensa::registers::XTENSA_CORE_REGSISTERS,
     },
     debug::{DebugRegister, DebugRegisters},
-    CoreType, InstructionSet, MemoryInterface,
+    Architecture, Core, CoreInformation, CoreInterface, CoreRegister, CoreRegisters, CoreStatus,
+    CoreType, HaltReason, InstructionSet, MemoryInterface, RegisterRole,
 };
 use crate::{RegisterId, RegisterValue};
 use anyhow::anyhow;
@@ -24,10 +25,15 @@ use std::{
     mem::size_of_val,
     ops::Range,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use super::RegisterDataType;
 
+mod elf;
+
+pub use elf::memory_to_elf;
+
 /// A snapshot representation of a core state.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CoreDump {
@@ -62,22 +68,40 @@ impl CoreDump {
         Ok(())
     }
 
+    /// Store the dumped core to a file, as an ELF core file which can be opened with GDB.
+    ///
+    /// Only ARM (except ARMv8-A) and RISC-V cores are supported.
+    pub fn store_elf(&self, path: &Path) -> Result<(), CoreDumpError> {
+        let data = elf::to_elf_core(self)?;
+        std::fs::write(path, data).map_err(|e| {
+            CoreDumpError::CoreDumpFileWrite(e, dunce::canonicalize(path).unwrap_or_default())
+        })
+    }
+
     /// Load the dumped core from a file.
+    ///
+    /// Both the probe-rs format written by [`CoreDump::store`] and ELF core files are supported.
     pub fn load(path: &Path) -> Result<Self, CoreDumpError> {
-        let file = OpenOptions::new().read(true).open(path).map_err(|e| {
+        let data = std::fs::read(path).map_err(|e| {
             CoreDumpError::CoreDumpFileRead(e, dunce::canonicalize(path).unwrap_or_default())
         })?;
-        rmp_serde::from_read(&file).map_err(CoreDumpError::DecodingCoreDump)
+        Self::load_raw(&data)
     }
 
     /// Load the dumped core from a file.
+    ///
+    /// Both the probe-rs format written by [`CoreDump::store`] and ELF core files are supported.
     pub fn load_raw(data: &[u8]) -> Result<Self, CoreDumpError> {
-        rmp_serde::from_slice(data).map_err(CoreDumpError::DecodingCoreDump)
+        if data.starts_with(&object::elf::ELFMAG) {
+            elf::from_elf_core(data)
+        } else {
+            rmp_serde::from_slice(data).map_err(CoreDumpError::DecodingCoreDump)
+        }
     }
 
-    /// Read all registers defined in [`crate::core::CoreRegisters`] from the given core.
-    pub fn debug_registers(&self) -> DebugRegisters {
-        let reg_list = match self.core_type {
+    /// Returns the list of registers of the dumped core.
+    fn core_registers(&self) -> &'static CoreRegisters {
+        match self.core_type {
             CoreType::Armv6m => &CORTEX_M_CORE_REGISTERS,
             CoreType::Armv7a => match self.floating_point_register_count {
                 Some(16) => &AARCH32_WITH_FP_16_CORE_REGSISTERS,
@@ -110,7 +134,12 @@ impl CoreDump {
             }
             CoreType::Riscv => &RISCV_CORE_REGSISTERS,
             CoreType::Xtensa => &XTENSA_CORE_REGSISTERS,
-        };
+        }
+    }
+
+    /// Read all registers defined in [`crate::core::CoreRegisters`] from the given core.
+    pub fn debug_registers(&self) -> DebugRegisters {
+        let reg_list = self.core_registers();
 
         let mut debug_registers = Vec::<DebugRegister>::new();
         for (dwarf_id, core_register) in reg_list.core_registers().enumerate() {
@@ -143,6 +172,15 @@ impl CoreDump {
         DebugRegisters(debug_registers)
     }
 
+    /// Returns a [`Core`] which serves register and memory reads from the core dump,
+    /// for post-mortem debugging with the same tools as a live core.
+    ///
+    /// The core always reports itself as halted. Writes, run control and breakpoints
+    /// return an error.
+    pub fn core(&mut self) -> Core<'_> {
+        Core::new(0, "core dump", &[], CoreDumpCore(self))
+    }
+
     /// Returns the type of the core.
     pub fn core_type(&self) -> CoreType {
         self.core_type
@@ -185,7 +223,10 @@ impl CoreDump {
             self.get_memory_from_coredump(address, (size_of_val(data)) as u64)?;
         for (n, data) in data.iter_mut().enumerate() {
             *data = memory
-                .pread_with::<T>((address - memory_offset) as usize + n * 4, scroll::LE)
+                .pread_with::<T>(
+                    (address - memory_offset) as usize + n * std::mem::size_of::<T>(),
+                    scroll::LE,
+                )
                 .map_err(|e| anyhow!("{e}"))?;
         }
         Ok(())
@@ -242,43 +283,270 @@ impl MemoryInterface for CoreDump {
     }
 
     fn write_word_64(&mut self, _address: u64, _data: u64) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only("Writing memory"))
     }
 
     fn write_word_32(&mut self, _address: u64, _data: u32) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only("Writing memory"))
     }
 
     fn write_word_16(&mut self, _address: u64, _data: u16) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only("Writing memory"))
     }
 
     fn write_word_8(&mut self, _address: u64, _data: u8) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only("Writing memory"))
     }
 
     fn write_64(&mut self, _address: u64, _data: &[u64]) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only("Writing memory"))
     }
 
     fn write_32(&mut self, _address: u64, _data: &[u32]) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only("Writing memory"))
     }
 
     fn write_16(&mut self, _address: u64, _data: &[u16]) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only("Writing memory"))
     }
 
     fn write_8(&mut self, _address: u64, _data: &[u8]) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only("Writing memory"))
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, crate::Error> {
+        Ok(true)
+    }
+
+    fn flush(&mut self) -> Result<(), crate::Error> {
+        Ok(())
+    }
+}
+
+/// The error returned for operations which would change the state of a core dump.
+fn read_only(operation: &str) -> crate::Error {
+    crate::Error::Other(anyhow!("{operation} is not possible on a core dump."))
+}
+
+/// Provides the [`CoreInterface`] of a [`CoreDump`], see [`CoreDump::core`].
+struct CoreDumpCore<'a>(&'a mut CoreDump);
+
+impl CoreDumpCore<'_> {
+    fn register_with_role(&self, role: RegisterRole) -> &'static CoreRegister {
+        self.0
+            .core_registers()
+            .core_registers()
+            .find(|register| register.register_has_role(role))
+            .expect("All cores have registers with the basic roles. Please report this as a bug.")
+    }
+
+    fn halted(&mut self) -> Result<CoreInformation, crate::Error> {
+        let pc = self.read_core_reg(self.program_counter().id())?;
+        Ok(CoreInformation { pc: pc.try_into()? })
+    }
+}
+
+impl MemoryInterface for CoreDumpCore<'_> {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        self.0.supports_native_64bit_access()
+    }
+
+    fn read_word_64(&mut self, address: u64) -> Result<u64, crate::Error> {
+        self.0.read_word_64(address)
+    }
+
+    fn read_word_32(&mut self, address: u64) -> Result<u32, crate::Error> {
+        self.0.read_word_32(address)
+    }
+
+    fn read_word_16(&mut self, address: u64) -> Result<u16, crate::Error> {
+        self.0.read_word_16(address)
+    }
+
+    fn read_word_8(&mut self, address: u64) -> Result<u8, crate::Error> {
+        self.0.read_word_8(address)
+    }
+
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), crate::Error> {
+        self.0.read_64(address, data)
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), crate::Error> {
+        self.0.read_32(address, data)
+    }
+
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), crate::Error> {
+        self.0.read_16(address, data)
+    }
+
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        self.0.read_8(address, data)
+    }
+
+    fn write_word_64(&mut self, address: u64, data: u64) -> Result<(), crate::Error> {
+        self.0.write_word_64(address, data)
+    }
+
+    fn write_word_32(&mut self, address: u64, data: u32) -> Result<(), crate::Error> {
+        self.0.write_word_32(address, data)
+    }
+
+    fn write_word_16(&mut self, address: u64, data: u16) -> Result<(), crate::Error> {
+        self.0.write_word_16(address, data)
+    }
+
+    fn write_word_8(&mut self, address: u64, data: u8) -> Result<(), crate::Error> {
+        self.0.write_word_8(address, data)
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), crate::Error> {
+        self.0.write_64(address, data)
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), crate::Error> {
+        self.0.write_32(address, data)
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), crate::Error> {
+        self.0.write_16(address, data)
+    }
+
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        self.0.write_8(address, data)
     }
 
     fn supports_8bit_transfers(&self) -> Result<bool, crate::Error> {
-        todo!()
+        self.0.supports_8bit_transfers()
     }
 
     fn flush(&mut self) -> Result<(), crate::Error> {
-        todo!()
+        self.0.flush()
+    }
+}
+
+impl CoreInterface for CoreDumpCore<'_> {
+    fn wait_for_core_halted(&mut self, _timeout: Duration) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn core_halted(&mut self) -> Result<bool, crate::Error> {
+        Ok(true)
+    }
+
+    fn status(&mut self) -> Result<CoreStatus, crate::Error> {
+        Ok(CoreStatus::Halted(HaltReason::Unknown))
+    }
+
+    fn halt(&mut self, _timeout: Duration) -> Result<CoreInformation, crate::Error> {
+        self.halted()
+    }
+
+    fn run(&mut self) -> Result<(), crate::Error> {
+        Err(read_only("Running the core"))
+    }
+
+    fn reset(&mut self) -> Result<(), crate::Error> {
+        Err(read_only("Resetting the core"))
+    }
+
+    fn reset_and_halt(&mut self, _timeout: Duration) -> Result<CoreInformation, crate::Error> {
+        Err(read_only("Resetting the core"))
+    }
+
+    fn step(&mut self) -> Result<CoreInformation, crate::Error> {
+        Err(read_only("Stepping the core"))
+    }
+
+    fn read_core_reg(&mut self, address: RegisterId) -> Result<RegisterValue, crate::Error> {
+        self.0.registers.get(&address).copied().ok_or_else(|| {
+            crate::Error::Register(format!(
+                "Register {address:?} is not part of the core dump."
+            ))
+        })
+    }
+
+    fn write_core_reg(
+        &mut self,
+        _address: RegisterId,
+        _value: RegisterValue,
+    ) -> Result<(), crate::Error> {
+        Err(read_only("Writing registers"))
+    }
+
+    fn available_breakpoint_units(&mut self) -> Result<u32, crate::Error> {
+        Ok(0)
+    }
+
+    fn hw_breakpoints(&mut self) -> Result<Vec<Option<u64>>, crate::Error> {
+        Ok(vec![])
+    }
+
+    fn enable_breakpoints(&mut self, _state: bool) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn set_hw_breakpoint(&mut self, _unit_index: usize, _addr: u64) -> Result<(), crate::Error> {
+        Err(read_only("Setting breakpoints"))
+    }
+
+    fn clear_hw_breakpoint(&mut self, _unit_index: usize) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn registers(&self) -> &'static CoreRegisters {
+        self.0.core_registers()
+    }
+
+    fn program_counter(&self) -> &'static CoreRegister {
+        self.register_with_role(RegisterRole::ProgramCounter)
+    }
+
+    fn frame_pointer(&self) -> &'static CoreRegister {
+        self.register_with_role(RegisterRole::FramePointer)
+    }
+
+    fn stack_pointer(&self) -> &'static CoreRegister {
+        self.register_with_role(RegisterRole::StackPointer)
+    }
+
+    fn return_address(&self) -> &'static CoreRegister {
+        self.register_with_role(RegisterRole::ReturnAddress)
+    }
+
+    fn hw_breakpoints_enabled(&self) -> bool {
+        false
+    }
+
+    fn architecture(&self) -> Architecture {
+        self.0.core_type.architecture()
+    }
+
+    fn core_type(&self) -> CoreType {
+        self.0.core_type
+    }
+
+    fn instruction_set(&mut self) -> Result<InstructionSet, crate::Error> {
+        Ok(self.0.instruction_set)
+    }
+
+    fn fpu_support(&mut self) -> Result<bool, crate::Error> {
+        Ok(self.0.fpu_support)
+    }
+
+    fn floating_point_register_count(&mut self) -> Result<usize, crate::Error> {
+        Ok(self.0.floating_point_register_count.unwrap_or_default())
+    }
+
+    fn reset_catch_set(&mut self) -> Result<(), crate::Error> {
+        Err(read_only("Catching resets"))
+    }
+
+    fn reset_catch_clear(&mut self) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn debug_core_stop(&mut self) -> Result<(), crate::Error> {
+        Ok(())
     }
 }
 
@@ -297,4 +565,66 @@ pub enum CoreDumpError {
     /// Decoding the coredump MessagePack failed.
     #[error("Decoding the coredump MessagePack failed.")]
     DecodingCoreDump(rmp_serde::decode::Error),
+    /// Core dumps of this type of core cannot be stored as ELF core files.
+    #[error("Core dumps of {0:?} cores cannot be stored as ELF core files.")]
+    UnsupportedElfCoreType(CoreType),
+    /// Parsing the ELF core file failed.
+    #[error("Parsing the ELF core file failed.")]
+    ParsingElfCore(#[from] object::read::Error),
+    /// The ELF core file does not contain a core dump we can read.
+    #[error("Invalid ELF core file: {0}")]
+    InvalidElfCore(String),
+    /// The memory range cannot be addressed in the ELF file.
+    #[error("The memory range {0:#x?} cannot be stored in a 32-bit ELF file.")]
+    ElfCoreRangeOutOfBounds(Range<u64>),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dump() -> CoreDump {
+        let mut registers = HashMap::new();
+        registers.insert(RegisterId(15), RegisterValue::U32(0x0800_0010));
+        CoreDump {
+            registers,
+            data: vec![(0x2000_0000..0x2000_0008, vec![1, 2, 3, 4, 5, 6, 7, 8])],
+            instruction_set: InstructionSet::Thumb2,
+            supports_native_64bit_access: false,
+            core_type: CoreType::Armv6m,
+            fpu_support: false,
+            floating_point_register_count: Some(0),
+        }
+    }
+
+    #[test]
+    fn core_reads_from_the_dump() {
+        let mut dump = dump();
+        let mut core = dump.core();
+
+        assert!(core.core_halted().unwrap());
+        let pc: u32 = core.read_core_reg(core.program_counter()).unwrap();
+        assert_eq!(pc, 0x0800_0010);
+
+        let mut bytes = [0u8; 3];
+        core.read_8(0x2000_0001, &mut bytes).unwrap();
+        assert_eq!(bytes, [2, 3, 4]);
+
+        let mut half_words = [0u16; 2];
+        core.read_16(0x2000_0002, &mut half_words).unwrap();
+        assert_eq!(half_words, [0x0403, 0x0605]);
+
+        assert!(core.read_word_32(0x2000_0008).is_err());
+    }
+
+    #[test]
+    fn core_is_read_only() {
+        let mut dump = dump();
+        let mut core = dump.core();
+
+        assert!(core.write_word_32(0x2000_0000, 0).is_err());
+        assert!(core.run().is_err());
+        assert!(core.step().is_err());
+        assert!(core.write_core_reg(RegisterId(0), 0u32).is_err());
+    }
 }
@@ -0,0 +1,973 @@
+//! Conversion between [`CoreDump`] and ELF core files.
+//!
+//! The core files follow the layout GDB expects for bare metal ARM and RISC-V targets:
+//! a `PT_LOAD` segment for every dumped memory range, and a `PT_NOTE` segment with an
+//! `NT_PRSTATUS` note for the core registers and, on ARM cores with an FPU, an `NT_ARM_VFP` note
+//! for the floating point registers. An additional probe-rs note records everything else
+//! needed to restore the [`CoreDump`] exactly.
+//!
+//! Dumps of 64-bit RISC-V cores, and dumps with memory above 4 GiB, are stored as 64-bit ELF files.
+
+use std::collections::HashMap;
+
+use object::{
+    elf,
+    read::elf::{FileHeader, ProgramHeader},
+    Endianness,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{CoreDump, CoreDumpError};
+use crate::{Architecture, CoreType, InstructionSet, RegisterId, RegisterValue};
+
+/// The ELF class of a core file, which determines the size of addresses and registers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ElfClass {
+    Elf32,
+    Elf64,
+}
+
+impl ElfClass {
+    /// Size of addresses and of the entries of `pr_reg`, in bytes.
+    fn word_size(&self) -> usize {
+        match self {
+            ElfClass::Elf32 => 4,
+            ElfClass::Elf64 => 8,
+        }
+    }
+
+    /// Size of the ELF file header.
+    fn header_size(&self) -> usize {
+        match self {
+            ElfClass::Elf32 => 52,
+            ElfClass::Elf64 => 64,
+        }
+    }
+
+    /// Size of an ELF program header.
+    fn program_header_size(&self) -> usize {
+        match self {
+            ElfClass::Elf32 => 32,
+            ElfClass::Elf64 => 56,
+        }
+    }
+
+    /// Offset of `pr_reg` in the `elf_prstatus` structure.
+    fn prstatus_registers_offset(&self) -> usize {
+        match self {
+            ElfClass::Elf32 => 72,
+            ElfClass::Elf64 => 112,
+        }
+    }
+
+    fn ident(&self) -> u8 {
+        match self {
+            ElfClass::Elf32 => elf::ELFCLASS32,
+            ElfClass::Elf64 => elf::ELFCLASS64,
+        }
+    }
+
+    /// Appends a value with the size of an address.
+    fn push_word(&self, buffer: &mut Vec<u8>, value: u64) {
+        match self {
+            ElfClass::Elf32 => push_u32(buffer, value as u32),
+            ElfClass::Elf64 => buffer.extend_from_slice(&value.to_le_bytes()),
+        }
+    }
+
+    /// Splits the buffer into values with the size of an address.
+    fn words(&self, bytes: &[u8]) -> Vec<u64> {
+        bytes
+            .chunks_exact(self.word_size())
+            .map(|word| {
+                let mut value = [0u8; 8];
+                value[..word.len()].copy_from_slice(word);
+                u64::from_le_bytes(value)
+            })
+            .collect()
+    }
+
+    /// Wraps a register value of `pr_reg` in the matching [`RegisterValue`].
+    fn register_value(&self, value: u64) -> RegisterValue {
+        match self {
+            ElfClass::Elf32 => RegisterValue::U32(value as u32),
+            ElfClass::Elf64 => RegisterValue::U64(value),
+        }
+    }
+}
+
+/// Size of a section header of a 32-bit ELF file. Only [`memory_to_elf`] writes sections.
+const ELF32_SECTION_HEADER_SIZE: usize = 40;
+
+/// Index of the class in the ELF identification bytes.
+const EI_CLASS: usize = 4;
+
+/// Owner of the note which holds the probe-rs specific parts of the core dump.
+const PROBE_RS_NOTE_NAME: &[u8] = b"probe-rs";
+const NT_PROBE_RS_CORE_DUMP: u32 = 1;
+
+/// The `EF_ARM_EABI_VER5` flag, which the ARM toolchains put into all their ELF files.
+const EF_ARM_EABI_VER5: u32 = 0x0500_0000;
+
+/// Number of double precision registers in an `NT_ARM_VFP` note.
+const VFP_DOUBLE_REGISTERS: usize = 32;
+
+/// The registers of the `NT_PRSTATUS` note of ARM cores: R0 to R15, and the xPSR/CPSR.
+/// The last entry of `pr_reg` (`ORIG_r0`) is filled with the value of R0.
+const ARM_PRSTATUS_REGISTERS: [RegisterId; 17] = [
+    RegisterId(0),
+    RegisterId(1),
+    RegisterId(2),
+    RegisterId(3),
+    RegisterId(4),
+    RegisterId(5),
+    RegisterId(6),
+    RegisterId(7),
+    RegisterId(8),
+    RegisterId(9),
+    RegisterId(10),
+    RegisterId(11),
+    RegisterId(12),
+    RegisterId(13),
+    RegisterId(14),
+    RegisterId(15),
+    RegisterId(0b1_0000),
+];
+
+/// The program counter of RISC-V cores, which takes the place of `x0` in `pr_reg`.
+const RISCV_PC: RegisterId = RegisterId(0x7b1);
+/// The first general purpose register (`x0`) of RISC-V cores.
+const RISCV_X0: u16 = 0x1000;
+
+/// The FPSCR and the first single precision register of Cortex-M cores.
+const CORTEX_M_FPSCR: RegisterId = RegisterId(33);
+const CORTEX_M_S0: u16 = 64;
+/// The FPSCR and the first double precision register of ARMv7-A cores.
+const AARCH32_FPSCR: RegisterId = RegisterId(49);
+const AARCH32_D0: u16 = 17;
+
+/// The probe-rs specific parts of a core dump, which have no place in the standard ELF notes.
+#[derive(Debug, Serialize, Deserialize)]
+struct ProbeRsNote {
+    instruction_set: InstructionSet,
+    supports_native_64bit_access: bool,
+    core_type: CoreType,
+    fpu_support: bool,
+    floating_point_register_count: Option<usize>,
+    /// Registers which are not part of the standard notes.
+    registers: HashMap<RegisterId, RegisterValue>,
+}
+
+/// How the registers of a core map onto the ELF notes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RegisterLayout {
+    /// ARM core, with the single precision registers and FPSCR of a Cortex-M FPU, if any.
+    CortexM {
+        fpu: bool,
+    },
+    /// ARMv7-A core, with the given number of double precision registers.
+    Aarch32 {
+        double_registers: usize,
+    },
+    Riscv,
+}
+
+impl RegisterLayout {
+    fn for_core_dump(dump: &CoreDump) -> Result<Self, CoreDumpError> {
+        Ok(match dump.core_type {
+            CoreType::Armv6m | CoreType::Armv7m | CoreType::Armv7em | CoreType::Armv8m => {
+                RegisterLayout::CortexM {
+                    fpu: dump.fpu_support,
+                }
+            }
+            CoreType::Armv7a => RegisterLayout::Aarch32 {
+                double_registers: match dump.floating_point_register_count {
+                    Some(count) if dump.fpu_support => count.min(VFP_DOUBLE_REGISTERS),
+                    _ => 0,
+                },
+            },
+            CoreType::Riscv => RegisterLayout::Riscv,
+            core_type @ (CoreType::Armv8a | CoreType::Xtensa) => {
+                return Err(CoreDumpError::UnsupportedElfCoreType(core_type))
+            }
+        })
+    }
+
+    fn machine(&self) -> u16 {
+        match self {
+            RegisterLayout::CortexM { .. } | RegisterLayout::Aarch32 { .. } => elf::EM_ARM,
+            RegisterLayout::Riscv => elf::EM_RISCV,
+        }
+    }
+
+    fn flags(&self) -> u32 {
+        match self {
+            RegisterLayout::CortexM { .. } | RegisterLayout::Aarch32 { .. } => EF_ARM_EABI_VER5,
+            RegisterLayout::Riscv => 0,
+        }
+    }
+
+    /// The registers stored in `pr_reg`, in order.
+    fn prstatus_registers(&self) -> Vec<RegisterId> {
+        match self {
+            RegisterLayout::CortexM { .. } | RegisterLayout::Aarch32 { .. } => {
+                ARM_PRSTATUS_REGISTERS.to_vec()
+            }
+            RegisterLayout::Riscv => std::iter::once(RISCV_PC)
+                .chain((1..32).map(|n| RegisterId(RISCV_X0 + n)))
+                .collect(),
+        }
+    }
+
+    /// The number of entries in `pr_reg`.
+    fn prstatus_register_words(&self) -> usize {
+        match self {
+            // R0-R15, CPSR and ORIG_r0
+            RegisterLayout::CortexM { .. } | RegisterLayout::Aarch32 { .. } => 18,
+            // PC and x1-x31
+            RegisterLayout::Riscv => 32,
+        }
+    }
+
+    /// The floating point registers stored in the `NT_ARM_VFP` note, with their index in the note.
+    /// Cortex-M single precision registers are stored as the halves of the double precision registers.
+    fn vfp_registers(&self) -> Vec<(usize, RegisterId)> {
+        match *self {
+            RegisterLayout::CortexM { fpu: true } => (0..32)
+                .map(|n| (n, RegisterId(CORTEX_M_S0 + n as u16)))
+                .collect(),
+            RegisterLayout::Aarch32 { double_registers } => (0..double_registers)
+                .map(|n| (n, RegisterId(AARCH32_D0 + n as u16)))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn fpscr(&self) -> Option<RegisterId> {
+        match *self {
+            RegisterLayout::CortexM { fpu: true } => Some(CORTEX_M_FPSCR),
+            RegisterLayout::Aarch32 { double_registers } if double_registers > 0 => {
+                Some(AARCH32_FPSCR)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Encodes the core dump as an ELF core file.
+pub(super) fn to_elf_core(dump: &CoreDump) -> Result<Vec<u8>, CoreDumpError> {
+    let layout = RegisterLayout::for_core_dump(dump)?;
+    let class = elf_class(dump, layout)?;
+    let register = |id: &RegisterId| -> u64 {
+        match dump.registers.get(id) {
+            Some(RegisterValue::U32(value)) => *value as u64,
+            Some(RegisterValue::U64(value)) => *value,
+            Some(RegisterValue::U128(value)) => *value as u64,
+            None => 0,
+        }
+    };
+
+    let mut standard_registers = layout.prstatus_registers();
+    let mut notes = vec![];
+
+    // NT_PRSTATUS: everything up to `pr_reg` is process information which does not apply here.
+    let mut prstatus = vec![0u8; class.prstatus_registers_offset()];
+    for id in layout.prstatus_registers() {
+        class.push_word(&mut prstatus, register(&id));
+    }
+    if matches!(layout.machine(), elf::EM_ARM) {
+        // ORIG_r0
+        class.push_word(&mut prstatus, register(&RegisterId(0)));
+    }
+    // pr_fpvalid
+    push_u32(&mut prstatus, layout.fpscr().is_some() as u32);
+    // The structure is padded to the alignment of its members.
+    prstatus.resize(prstatus.len().next_multiple_of(class.word_size()), 0);
+    push_note(&mut notes, b"CORE", elf::NT_PRSTATUS, &prstatus);
+
+    if let Some(fpscr) = layout.fpscr() {
+        let mut vfp = [0u64; VFP_DOUBLE_REGISTERS];
+        for (index, id) in layout.vfp_registers() {
+            match layout {
+                RegisterLayout::CortexM { .. } => {
+                    vfp[index / 2] |= (register(&id) & 0xffff_ffff) << (32 * (index % 2))
+                }
+                _ => vfp[index] = register(&id),
+            }
+            standard_registers.push(id);
+        }
+        let mut desc: Vec<u8> = vfp.iter().flat_map(|value| value.to_le_bytes()).collect();
+        desc.extend_from_slice(&(register(&fpscr) as u32).to_le_bytes());
+        standard_registers.push(fpscr);
+        push_note(&mut notes, b"LINUX", elf::NT_ARM_VFP, &desc);
+    }
+
+    let probe_rs_note = ProbeRsNote {
+        instruction_set: dump.instruction_set,
+        supports_native_64bit_access: dump.supports_native_64bit_access,
+        core_type: dump.core_type,
+        fpu_support: dump.fpu_support,
+        floating_point_register_count: dump.floating_point_register_count,
+        registers: dump
+            .registers
+            .iter()
+            .filter(|(id, _)| !standard_registers.contains(id))
+            .map(|(id, value)| (*id, *value))
+            .collect(),
+    };
+    let desc = rmp_serde::to_vec_named(&probe_rs_note).map_err(CoreDumpError::EncodingCoreDump)?;
+    push_note(&mut notes, PROBE_RS_NOTE_NAME, NT_PROBE_RS_CORE_DUMP, &desc);
+
+    // Layout: ELF header, program headers, notes, memory contents.
+    let program_header_count = 1 + dump.data.len();
+    let notes_offset = class.header_size() + program_header_count * class.program_header_size();
+    let mut data_offset = notes_offset + notes.len();
+
+    let mut file = Vec::with_capacity(
+        data_offset + dump.data.iter().map(|(_, data)| data.len()).sum::<usize>(),
+    );
+
+    push_file_header(
+        &mut file,
+        class,
+        elf::ET_CORE,
+        layout.machine(),
+        layout.flags(),
+        program_header_count,
+        0,
+        0,
+    );
+
+    // Program headers
+    push_program_header(
+        &mut file,
+        class,
+        elf::PT_NOTE,
+        notes_offset,
+        0,
+        notes.len(),
+        0,
+        4,
+    );
+    for (range, data) in &dump.data {
+        push_program_header(
+            &mut file,
+            class,
+            elf::PT_LOAD,
+            data_offset,
+            range.start,
+            data.len(),
+            elf::PF_R | elf::PF_W | elf::PF_X,
+            1,
+        );
+        data_offset += data.len();
+    }
+
+    file.extend_from_slice(&notes);
+    for (_, data) in &dump.data {
+        file.extend_from_slice(data);
+    }
+
+    Ok(file)
+}
+
+/// Encodes memory contents as a 32-bit little endian ELF executable, with a loadable segment
+/// and a section for each of the `blocks`, given as their name, start address and contents.
+///
+/// This is used for memory contents which are not part of a core dump, like a copy of the flash.
+pub fn memory_to_elf(
+    architecture: Architecture,
+    blocks: &[(&str, u64, &[u8])],
+) -> Result<Vec<u8>, CoreDumpError> {
+    let class = ElfClass::Elf32;
+    let (machine, flags) = match architecture {
+        Architecture::Arm => (elf::EM_ARM, EF_ARM_EABI_VER5),
+        Architecture::Riscv => (elf::EM_RISCV, 0),
+        Architecture::Xtensa => (elf::EM_XTENSA, 0),
+    };
+
+    for (_, address, data) in blocks {
+        let range = *address..*address + data.len() as u64;
+        if range.end > u32::MAX as u64 + 1 {
+            return Err(CoreDumpError::ElfCoreRangeOutOfBounds(range));
+        }
+    }
+
+    // Section names, starting with the empty name of the null section.
+    let mut names = vec![0u8];
+    let mut name_offsets = vec![];
+    for (name, _, _) in blocks {
+        name_offsets.push(names.len());
+        names.extend_from_slice(name.as_bytes());
+        names.push(0);
+    }
+    let shstrtab_name = names.len();
+    names.extend_from_slice(b".shstrtab\0");
+
+    // Layout: ELF header, program headers, memory contents, section names, section headers.
+    let data_offset = class.header_size() + blocks.len() * class.program_header_size();
+    let names_offset = data_offset + blocks.iter().map(|(_, _, data)| data.len()).sum::<usize>();
+    let section_headers_offset = (names_offset + names.len()).next_multiple_of(4);
+    let section_count = blocks.len() + 2;
+
+    let mut file =
+        Vec::with_capacity(section_headers_offset + section_count * ELF32_SECTION_HEADER_SIZE);
+
+    push_file_header(
+        &mut file,
+        class,
+        elf::ET_EXEC,
+        machine,
+        flags,
+        blocks.len(),
+        section_headers_offset,
+        section_count,
+    );
+
+    let mut offset = data_offset;
+    for (_, address, data) in blocks {
+        push_program_header(
+            &mut file,
+            class,
+            elf::PT_LOAD,
+            offset,
+            *address,
+            data.len(),
+            elf::PF_R | elf::PF_X,
+            1,
+        );
+        offset += data.len();
+    }
+
+    for (_, _, data) in blocks {
+        file.extend_from_slice(data);
+    }
+    file.extend_from_slice(&names);
+    file.resize(section_headers_offset, 0);
+
+    // Section headers, starting with the null section.
+    file.resize(file.len() + ELF32_SECTION_HEADER_SIZE, 0);
+    let mut offset = data_offset;
+    for ((_, address, data), name) in blocks.iter().zip(name_offsets) {
+        push_section_header(
+            &mut file,
+            name,
+            elf::SHT_PROGBITS,
+            elf::SHF_ALLOC | elf::SHF_EXECINSTR,
+            *address,
+            offset,
+            data.len(),
+        );
+        offset += data.len();
+    }
+    push_section_header(
+        &mut file,
+        shstrtab_name,
+        elf::SHT_STRTAB,
+        0,
+        0,
+        names_offset,
+        names.len(),
+    );
+
+    Ok(file)
+}
+
+/// Decodes an ELF core file. Files which were not written by probe-rs are supported as long as
+/// they contain an `NT_PRSTATUS` note in the layout GDB uses.
+pub(super) fn from_elf_core(data: &[u8]) -> Result<CoreDump, CoreDumpError> {
+    match data.get(EI_CLASS) {
+        Some(&elf::ELFCLASS64) => {
+            from_elf_core_with::<elf::FileHeader64<Endianness>>(data, ElfClass::Elf64)
+        }
+        _ => from_elf_core_with::<elf::FileHeader32<Endianness>>(data, ElfClass::Elf32),
+    }
+}
+
+fn from_elf_core_with<Elf: FileHeader<Endian = Endianness>>(
+    data: &[u8],
+    class: ElfClass,
+) -> Result<CoreDump, CoreDumpError> {
+    let header = Elf::parse(data)?;
+    let endian = header.endian()?;
+
+    if header.e_type(endian) != elf::ET_CORE {
+        return Err(CoreDumpError::InvalidElfCore(
+            "The file is not an ELF core file.".to_string(),
+        ));
+    }
+
+    let machine = header.e_machine(endian);
+    if machine != elf::EM_ARM && machine != elf::EM_RISCV {
+        return Err(CoreDumpError::InvalidElfCore(format!(
+            "Core files for ELF machine {machine} are not supported."
+        )));
+    }
+
+    let mut memory = vec![];
+    let mut prstatus = None;
+    let mut vfp = None;
+    let mut probe_rs_note = None;
+
+    for segment in header.program_headers(endian, data)? {
+        match segment.p_type(endian) {
+            elf::PT_LOAD => {
+                let start: u64 = segment.p_vaddr(endian).into();
+                let contents = segment.data(endian, data).map_err(|()| {
+                    CoreDumpError::InvalidElfCore("Invalid PT_LOAD segment.".to_string())
+                })?;
+                memory.push((start..start + contents.len() as u64, contents.to_vec()));
+            }
+            elf::PT_NOTE => {
+                let Some(mut notes) = segment.notes(endian, data)? else {
+                    continue;
+                };
+                while let Some(note) = notes.next()? {
+                    match (note.name(), note.n_type(endian)) {
+                        (b"CORE", elf::NT_PRSTATUS) => prstatus = Some(note.desc()),
+                        (b"LINUX", elf::NT_ARM_VFP) => vfp = Some(note.desc()),
+                        (PROBE_RS_NOTE_NAME, NT_PROBE_RS_CORE_DUMP) => {
+                            probe_rs_note = Some(
+                                rmp_serde::from_slice::<ProbeRsNote>(note.desc())
+                                    .map_err(CoreDumpError::DecodingCoreDump)?,
+                            )
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Some(prstatus) = prstatus else {
+        return Err(CoreDumpError::InvalidElfCore(
+            "The core file contains no NT_PRSTATUS note.".to_string(),
+        ));
+    };
+
+    let mut dump = CoreDump {
+        registers: HashMap::new(),
+        data: memory,
+        instruction_set: InstructionSet::RV32C,
+        supports_native_64bit_access: false,
+        core_type: CoreType::Riscv,
+        fpu_support: vfp.is_some(),
+        floating_point_register_count: Some(0),
+    };
+
+    let prstatus_words = class.words(
+        prstatus
+            .get(class.prstatus_registers_offset()..)
+            .unwrap_or_default(),
+    );
+
+    match probe_rs_note {
+        Some(note) => {
+            dump.instruction_set = note.instruction_set;
+            dump.supports_native_64bit_access = note.supports_native_64bit_access;
+            dump.core_type = note.core_type;
+            dump.fpu_support = note.fpu_support;
+            dump.floating_point_register_count = note.floating_point_register_count;
+            dump.registers = note.registers;
+        }
+        None if machine == elf::EM_ARM => {
+            // Without the probe-rs note, the kind of core has to be inferred from the registers.
+            // The Thumb bit of the xPSR is always set on Cortex-M cores, while on other ARM cores
+            // the same bit of the CPSR is the rarely used Jazelle bit.
+            let psr = prstatus_words.get(16).copied().unwrap_or_default();
+            if psr & (1 << 24) != 0 {
+                dump.core_type = if vfp.is_some() {
+                    CoreType::Armv7em
+                } else {
+                    CoreType::Armv7m
+                };
+                dump.instruction_set = InstructionSet::Thumb2;
+                dump.floating_point_register_count = Some(32);
+            } else {
+                dump.core_type = CoreType::Armv7a;
+                dump.instruction_set = if psr & (1 << 5) != 0 {
+                    InstructionSet::Thumb2
+                } else {
+                    InstructionSet::A32
+                };
+                dump.floating_point_register_count = Some(if vfp.is_some() {
+                    VFP_DOUBLE_REGISTERS
+                } else {
+                    0
+                });
+            }
+        }
+        None => {}
+    }
+
+    let layout = RegisterLayout::for_core_dump(&dump)?;
+
+    let prstatus_registers = layout.prstatus_registers();
+    if prstatus_words.len() < layout.prstatus_register_words() {
+        return Err(CoreDumpError::InvalidElfCore(
+            "The NT_PRSTATUS note is too short.".to_string(),
+        ));
+    }
+    for (id, value) in prstatus_registers.into_iter().zip(prstatus_words) {
+        dump.registers.insert(id, class.register_value(value));
+    }
+
+    if let (Some(vfp), Some(fpscr)) = (vfp, layout.fpscr()) {
+        if vfp.len() < VFP_DOUBLE_REGISTERS * 8 + 4 {
+            return Err(CoreDumpError::InvalidElfCore(
+                "The NT_ARM_VFP note is too short.".to_string(),
+            ));
+        }
+        let double =
+            |index: usize| u64::from_le_bytes(vfp[index * 8..index * 8 + 8].try_into().unwrap());
+        for (index, id) in layout.vfp_registers() {
+            let value = match layout {
+                RegisterLayout::CortexM { .. } => {
+                    RegisterValue::U32((double(index / 2) >> (32 * (index % 2))) as u32)
+                }
+                _ => RegisterValue::U64(double(index)),
+            };
+            dump.registers.insert(id, value);
+        }
+        let fpscr_offset = VFP_DOUBLE_REGISTERS * 8;
+        dump.registers.insert(
+            fpscr,
+            RegisterValue::U32(u32::from_le_bytes(
+                vfp[fpscr_offset..fpscr_offset + 4].try_into().unwrap(),
+            )),
+        );
+    }
+
+    Ok(dump)
+}
+
+/// Selects the ELF class which can hold all registers and memory addresses of the core dump.
+fn elf_class(dump: &CoreDump, layout: RegisterLayout) -> Result<ElfClass, CoreDumpError> {
+    let has_64bit_registers = layout.prstatus_registers().iter().any(|id| {
+        matches!(
+            dump.registers.get(id),
+            Some(RegisterValue::U64(_) | RegisterValue::U128(_))
+        )
+    });
+    let out_of_range = dump
+        .data
+        .iter()
+        .map(|(range, _)| range)
+        .find(|range| range.end > u32::MAX as u64 + 1);
+
+    match layout {
+        // GDB only reads 32-bit ARM core files.
+        RegisterLayout::CortexM { .. } | RegisterLayout::Aarch32 { .. } => match out_of_range {
+            Some(range) => Err(CoreDumpError::ElfCoreRangeOutOfBounds(range.clone())),
+            None => Ok(ElfClass::Elf32),
+        },
+        RegisterLayout::Riscv if has_64bit_registers || out_of_range.is_some() => {
+            Ok(ElfClass::Elf64)
+        }
+        RegisterLayout::Riscv => Ok(ElfClass::Elf32),
+    }
+}
+
+fn push_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Appends the ELF file header. The program headers have to follow it directly.
+#[allow(clippy::too_many_arguments)]
+fn push_file_header(
+    buffer: &mut Vec<u8>,
+    class: ElfClass,
+    e_type: u16,
+    machine: u16,
+    flags: u32,
+    program_header_count: usize,
+    section_headers_offset: usize,
+    section_count: usize,
+) {
+    buffer.extend_from_slice(&elf::ELFMAG);
+    buffer.extend_from_slice(&[class.ident(), elf::ELFDATA2LSB, elf::EV_CURRENT, 0]);
+    buffer.extend_from_slice(&[0; 8]);
+    push_u16(buffer, e_type);
+    push_u16(buffer, machine);
+    push_u32(buffer, elf::EV_CURRENT as u32);
+    class.push_word(buffer, 0); // e_entry
+    class.push_word(buffer, class.header_size() as u64); // e_phoff
+    class.push_word(buffer, section_headers_offset as u64);
+    push_u32(buffer, flags);
+    push_u16(buffer, class.header_size() as u16);
+    push_u16(buffer, class.program_header_size() as u16);
+    push_u16(buffer, program_header_count as u16);
+    if section_count > 0 {
+        push_u16(buffer, ELF32_SECTION_HEADER_SIZE as u16);
+        push_u16(buffer, section_count as u16);
+        // The section names are the last section.
+        push_u16(buffer, section_count as u16 - 1); // e_shstrndx
+    } else {
+        push_u16(buffer, 0); // e_shentsize
+        push_u16(buffer, 0); // e_shnum
+        push_u16(buffer, 0); // e_shstrndx
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn push_program_header(
+    buffer: &mut Vec<u8>,
+    class: ElfClass,
+    p_type: u32,
+    offset: usize,
+    address: u64,
+    size: usize,
+    flags: u32,
+    align: u64,
+) {
+    push_u32(buffer, p_type);
+    if class == ElfClass::Elf64 {
+        push_u32(buffer, flags);
+    }
+    class.push_word(buffer, offset as u64);
+    class.push_word(buffer, address); // p_vaddr
+    class.push_word(buffer, address); // p_paddr
+    class.push_word(buffer, size as u64); // p_filesz
+    class.push_word(buffer, size as u64); // p_memsz
+    if class == ElfClass::Elf32 {
+        push_u32(buffer, flags);
+    }
+    class.push_word(buffer, align);
+}
+
+/// Appends a 32-bit ELF section header.
+fn push_section_header(
+    buffer: &mut Vec<u8>,
+    name: usize,
+    sh_type: u32,
+    flags: u32,
+    address: u64,
+    offset: usize,
+    size: usize,
+) {
+    push_u32(buffer, name as u32);
+    push_u32(buffer, sh_type);
+    push_u32(buffer, flags);
+    push_u32(buffer, address as u32);
+    push_u32(buffer, offset as u32);
+    push_u32(buffer, size as u32);
+    push_u32(buffer, 0); // sh_link
+    push_u32(buffer, 0); // sh_info
+    push_u32(buffer, 1); // sh_addralign
+    push_u32(buffer, 0); // sh_entsize
+}
+
+/// Appends an ELF note, with the name and descriptor padded to a multiple of 4 bytes.
+fn push_note(buffer: &mut Vec<u8>, name: &[u8], n_type: u32, desc: &[u8]) {
+    fn pad(buffer: &mut Vec<u8>) {
+        buffer.resize(buffer.len().next_multiple_of(4), 0);
+    }
+
+    push_u32(buffer, name.len() as u32 + 1);
+    push_u32(buffer, desc.len() as u32);
+    push_u32(buffer, n_type);
+    buffer.extend_from_slice(name);
+    buffer.push(0);
+    pad(buffer);
+    buffer.extend_from_slice(desc);
+    pad(buffer);
+}
+
+#[cfg(test)]
+mod test {
+    use object::read::elf::SectionHeader;
+
+    use super::*;
+
+    fn cortex_m_dump(fpu_support: bool) -> CoreDump {
+        let mut registers = HashMap::new();
+        for n in 0..16 {
+            registers.insert(RegisterId(n), RegisterValue::U32(0x1000 + n as u32));
+        }
+        registers.insert(RegisterId(0b1_0000), RegisterValue::U32(0x0100_0003));
+        // MSP, which is not part of NT_PRSTATUS
+        registers.insert(RegisterId(0b1_0001), RegisterValue::U32(0x2000_1000));
+        if fpu_support {
+            registers.insert(CORTEX_M_FPSCR, RegisterValue::U32(0x0300_0000));
+            for n in 0..32 {
+                registers.insert(
+                    RegisterId(CORTEX_M_S0 + n),
+                    RegisterValue::U32(0x4000_0000 + n as u32),
+                );
+            }
+        }
+
+        CoreDump {
+            registers,
+            data: vec![
+                (0x2000_0000..0x2000_0008, vec![1, 2, 3, 4, 5, 6, 7, 8]),
+                (0x0800_0000..0x0800_0003, vec![9, 10, 11]),
+            ],
+            instruction_set: InstructionSet::Thumb2,
+            supports_native_64bit_access: false,
+            core_type: if fpu_support {
+                CoreType::Armv7em
+            } else {
+                CoreType::Armv6m
+            },
+            fpu_support,
+            floating_point_register_count: Some(if fpu_support { 32 } else { 0 }),
+        }
+    }
+
+    fn assert_same_dump(left: &CoreDump, right: &CoreDump) {
+        assert_eq!(left.registers, right.registers);
+        assert_eq!(left.data, right.data);
+        assert_eq!(left.instruction_set, right.instruction_set);
+        assert_eq!(left.core_type, right.core_type);
+        assert_eq!(left.fpu_support, right.fpu_support);
+        assert_eq!(
+            left.floating_point_register_count,
+            right.floating_point_register_count
+        );
+    }
+
+    #[test]
+    fn cortex_m_round_trip() {
+        for fpu_support in [false, true] {
+            let dump = cortex_m_dump(fpu_support);
+            let elf = to_elf_core(&dump).unwrap();
+            assert_same_dump(&from_elf_core(&elf).unwrap(), &dump);
+        }
+    }
+
+    #[test]
+    fn prstatus_layout() {
+        let elf = to_elf_core(&cortex_m_dump(true)).unwrap();
+
+        // The first note starts right after the ELF header and the three program headers.
+        let class = ElfClass::Elf32;
+        let note = &elf[class.header_size() + 3 * class.program_header_size()..];
+        assert_eq!(&note[..12], &[5, 0, 0, 0, 148, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(&note[12..17], b"CORE\0");
+
+        let pr_reg = &note[20 + class.prstatus_registers_offset()..];
+        // R0, R15 and xPSR
+        assert_eq!(&pr_reg[..4], &0x1000u32.to_le_bytes());
+        assert_eq!(&pr_reg[60..64], &0x100Fu32.to_le_bytes());
+        assert_eq!(&pr_reg[64..68], &0x0100_0003u32.to_le_bytes());
+        // pr_fpvalid
+        assert_eq!(&pr_reg[72..76], &1u32.to_le_bytes());
+    }
+
+    #[test]
+    fn foreign_core_file_without_probe_rs_note() {
+        let dump = cortex_m_dump(true);
+        let mut elf = to_elf_core(&dump).unwrap();
+
+        // Rename the probe-rs note, so it is ignored like any other unknown note.
+        let position = elf
+            .windows(PROBE_RS_NOTE_NAME.len())
+            .position(|window| window == PROBE_RS_NOTE_NAME)
+            .unwrap();
+        elf[position] = b'x';
+
+        let imported = from_elf_core(&elf).unwrap();
+        assert_eq!(imported.core_type, CoreType::Armv7em);
+        assert_eq!(imported.instruction_set, InstructionSet::Thumb2);
+        assert_eq!(imported.data, dump.data);
+        // MSP is only stored in the probe-rs note.
+        assert_eq!(imported.registers.len(), dump.registers.len() - 1);
+        assert_eq!(
+            imported.registers[&RegisterId(CORTEX_M_S0 + 5)],
+            RegisterValue::U32(0x4000_0005)
+        );
+    }
+
+    #[test]
+    fn existing_core_dump_round_trip() {
+        let dump = CoreDump::load_raw(include_bytes!(
+            "../../../tests/debug-unwind-tests/nRF52833_xxAA.coredump"
+        ))
+        .unwrap();
+
+        let elf = to_elf_core(&dump).unwrap();
+        assert_same_dump(&from_elf_core(&elf).unwrap(), &dump);
+    }
+
+    #[test]
+    fn riscv64_round_trip() {
+        let mut registers = HashMap::new();
+        registers.insert(RISCV_PC, RegisterValue::U64(0x8000_0000_2000_0100));
+        for n in 1..32 {
+            registers.insert(
+                RegisterId(RISCV_X0 + n),
+                RegisterValue::U64(0x1_0000_0000 + n as u64),
+            );
+        }
+        let dump = CoreDump {
+            registers,
+            data: vec![(0x1_0000_0000..0x1_0000_0004, vec![1, 2, 3, 4])],
+            instruction_set: InstructionSet::RV32C,
+            supports_native_64bit_access: true,
+            core_type: CoreType::Riscv,
+            fpu_support: false,
+            floating_point_register_count: Some(0),
+        };
+
+        let elf = to_elf_core(&dump).unwrap();
+        assert_eq!(elf[EI_CLASS], elf::ELFCLASS64);
+        assert_same_dump(&from_elf_core(&elf).unwrap(), &dump);
+    }
+
+    #[test]
+    fn arm_memory_above_4gib() {
+        let mut dump = cortex_m_dump(false);
+        dump.data.push((0x1_0000_0000..0x1_0000_0001, vec![0]));
+
+        assert!(matches!(
+            to_elf_core(&dump),
+            Err(CoreDumpError::ElfCoreRangeOutOfBounds(_))
+        ));
+    }
+
+    #[test]
+    fn unsupported_core_type() {
+        let mut dump = cortex_m_dump(false);
+        dump.core_type = CoreType::Xtensa;
+
+        assert!(matches!(
+            to_elf_core(&dump),
+            Err(CoreDumpError::UnsupportedElfCoreType(CoreType::Xtensa))
+        ));
+    }
+
+    #[test]
+    fn memory_image_sections() {
+        let file = memory_to_elf(
+            Architecture::Riscv,
+            &[
+                ("flash", 0x4200_0000, &[1, 2, 3]),
+                ("rom", 0x4000_0000, &[4]),
+            ],
+        )
+        .unwrap();
+
+        let header = elf::FileHeader32::<Endianness>::parse(&*file).unwrap();
+        let endian = header.endian().unwrap();
+        assert_eq!(header.e_type(endian), elf::ET_EXEC);
+        assert_eq!(header.e_machine(endian), elf::EM_RISCV);
+
+        let sections = header.sections(endian, &*file).unwrap();
+        let flash = sections.section_by_name(endian, b"flash").unwrap().1;
+        assert_eq!(flash.data(endian, &*file).unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn memory_image_above_4gib() {
+        assert!(matches!(
+            memory_to_elf(Architecture::Arm, &[("high", 0xffff_ffff, &[1, 2])]),
+            Err(CoreDumpError::ElfCoreRangeOutOfBounds(_))
+        ));
+    }
+}
@@ -0,0 +1,551 @@
+//! Stack unwinding using the ARM exception handling ABI ([EHABI](https://github.com/ARM-software/abi-aa/blob/main/ehabi32/ehabi32.rst))
+//! tables, which are stored in the `.ARM.exidx` and `.ARM.extab` sections.
+//!
+//! These tables are emitted by GCC and clang for C/C++ code (and by vendor toolchains), and are often present
+//! in binaries that do not have any `.debug_frame` information.
+
+use super::{DebugError, DebugRegisters};
+use crate::{core::RegisterValue, MemoryInterface};
+use anyhow::anyhow;
+
+/// The value of the second word of an index table entry, for functions that can not be unwound.
+const EXIDX_CANTUNWIND: u32 = 0x1;
+
+/// The number of ARM core registers (`R0` .. `R15`) that the unwind instructions operate on.
+const CORE_REGISTER_COUNT: usize = 16;
+
+const STACK_POINTER: usize = 13;
+const LINK_REGISTER: usize = 14;
+const PROGRAM_COUNTER: usize = 15;
+
+/// The raw contents of an ELF section, together with the address it is linked at.
+#[derive(Debug, Clone)]
+pub(crate) struct LoadedSection {
+    pub(crate) address: u64,
+    pub(crate) data: Vec<u8>,
+}
+
+impl LoadedSection {
+    /// Read a little endian word at the given (absolute) address, if it is inside this section.
+    fn read_u32(&self, address: u64) -> Option<u32> {
+        let offset = usize::try_from(address.checked_sub(self.address)?).ok()?;
+        let bytes = self.data.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn contains(&self, address: u64) -> bool {
+        address >= self.address && address < self.address + self.data.len() as u64
+    }
+}
+
+/// The unwind information for a single function, as described by an `.ARM.exidx` entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IndexEntry {
+    /// The function is marked with `EXIDX_CANTUNWIND`.
+    CantUnwind,
+    /// The unwind instructions are stored in the index table entry itself (personality routine 0).
+    Inline(u32),
+    /// The unwind instructions are stored in the `.ARM.extab` section, at the given address.
+    Table(u64),
+}
+
+/// The exception index table (`.ARM.exidx`) and the optional exception table (`.ARM.extab`) of a binary.
+#[derive(Debug, Clone)]
+pub(crate) struct ArmExceptionTables {
+    index: LoadedSection,
+    table: Option<LoadedSection>,
+}
+
+impl ArmExceptionTables {
+    /// Create the tables from the loaded sections. Returns `None` if the index table is empty.
+    pub(crate) fn new(index: LoadedSection, table: Option<LoadedSection>) -> Option<Self> {
+        if index.data.len() < 8 {
+            return None;
+        }
+        Some(Self { index, table })
+    }
+
+    /// The number of entries in the index table.
+    fn entry_count(&self) -> usize {
+        self.index.data.len() / 8
+    }
+
+    /// The start address of the function described by the entry at `entry_index`.
+    fn function_address(&self, entry_index: usize) -> Option<u64> {
+        let entry_address = self.index.address + entry_index as u64 * 8;
+        self.index
+            .read_u32(entry_address)
+            .map(|word| prel31_to_address(word, entry_address))
+    }
+
+    /// Find the index table entry for the function that contains `address`.
+    fn entry_for_address(&self, address: u64) -> Option<IndexEntry> {
+        // The entries are sorted by function address, so we can use a binary search
+        // to find the last entry that starts at, or before, `address`.
+        let (mut low, mut high) = (0, self.entry_count());
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.function_address(middle)? <= address {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        let entry_index = low.checked_sub(1)?;
+
+        let entry_address = self.index.address + entry_index as u64 * 8;
+        let data = self.index.read_u32(entry_address + 4)?;
+
+        Some(if data == EXIDX_CANTUNWIND {
+            IndexEntry::CantUnwind
+        } else if data & 0x8000_0000 != 0 {
+            IndexEntry::Inline(data)
+        } else {
+            IndexEntry::Table(prel31_to_address(data, entry_address + 4))
+        })
+    }
+
+    /// Collect the unwind instruction bytes for the given index table entry.
+    fn unwind_instructions(&self, entry: IndexEntry) -> Result<Vec<u8>, DebugError> {
+        match entry {
+            IndexEntry::CantUnwind => Err(DebugError::Other(anyhow!(
+                "The function is marked as EXIDX_CANTUNWIND."
+            ))),
+            IndexEntry::Inline(word) => {
+                let personality = (word >> 24) & 0x0f;
+                if personality != 0 {
+                    return Err(DebugError::Other(anyhow!(
+                        "Unsupported personality routine {} in .ARM.exidx entry.",
+                        personality
+                    )));
+                }
+                Ok(word.to_be_bytes()[1..].to_vec())
+            }
+            IndexEntry::Table(address) => {
+                let table = self
+                    .table
+                    .as_ref()
+                    .filter(|table| table.contains(address))
+                    .ok_or_else(|| {
+                        DebugError::Other(anyhow!(
+                            "The .ARM.extab entry at {:#010x} is not available.",
+                            address
+                        ))
+                    })?;
+                let read_word = |address: u64| {
+                    table.read_u32(address).ok_or_else(|| {
+                        DebugError::Other(anyhow!(
+                            "The .ARM.extab entry at {:#010x} is truncated.",
+                            address
+                        ))
+                    })
+                };
+
+                let first_word = read_word(address)?;
+
+                // The header word is followed by the unwind instructions, either in the compact
+                // model (bit 31 set), or after the address of a generic personality routine, in
+                // the same layout as for the compact personality routines 1 and 2.
+                let (header_word, mut next_address) = if first_word & 0x8000_0000 != 0 {
+                    (first_word, address + 4)
+                } else {
+                    (read_word(address + 4)?, address + 8)
+                };
+
+                let mut instructions = Vec::new();
+                let additional_words = if first_word & 0x8000_0000 != 0 {
+                    match (header_word >> 24) & 0x0f {
+                        0 => {
+                            instructions.extend_from_slice(&header_word.to_be_bytes()[1..]);
+                            0
+                        }
+                        1 | 2 => {
+                            instructions.extend_from_slice(&header_word.to_be_bytes()[2..]);
+                            (header_word >> 16) & 0xff
+                        }
+                        personality => {
+                            return Err(DebugError::Other(anyhow!(
+                                "Unsupported personality routine {} in .ARM.extab entry.",
+                                personality
+                            )))
+                        }
+                    }
+                } else {
+                    instructions.extend_from_slice(&header_word.to_be_bytes()[1..]);
+                    header_word >> 24
+                };
+
+                for _ in 0..additional_words {
+                    instructions.extend_from_slice(&read_word(next_address)?.to_be_bytes());
+                    next_address += 4;
+                }
+
+                Ok(instructions)
+            }
+        }
+    }
+
+    /// Returns `true` if the tables contain an entry that covers `address`.
+    pub(crate) fn has_entry_for(&self, address: u64) -> bool {
+        self.entry_for_address(address).is_some()
+    }
+
+    /// Unwind the registers of the calling frame, using the unwind instructions for the function at the current program counter.
+    ///
+    /// Returns `Ok(None)` if there is no entry for the current program counter.
+    pub(crate) fn unwind(
+        &self,
+        callee_frame_registers: &DebugRegisters,
+        memory: &mut dyn MemoryInterface,
+    ) -> Result<Option<DebugRegisters>, DebugError> {
+        let Some(program_counter) = callee_frame_registers
+            .get_program_counter()
+            .and_then(|pc| pc.value)
+        else {
+            return Ok(None);
+        };
+        let program_counter: u64 = program_counter.try_into()?;
+
+        let Some(entry) = self.entry_for_address(program_counter) else {
+            return Ok(None);
+        };
+        let instructions = self.unwind_instructions(entry)?;
+
+        let mut core_registers = [None; CORE_REGISTER_COUNT];
+        for (dwarf_id, value) in core_registers.iter_mut().enumerate() {
+            *value = callee_frame_registers
+                .get_register_by_dwarf_id(dwarf_id as u16)
+                .and_then(|register| register.value)
+                .and_then(|value| value.try_into().ok());
+        }
+
+        execute_unwind_instructions(&instructions, &mut core_registers, memory)?;
+
+        let mut caller_frame_registers = callee_frame_registers.clone();
+        for (dwarf_id, value) in core_registers.into_iter().enumerate() {
+            if let Some(register) =
+                caller_frame_registers.get_register_mut_by_dwarf_id(dwarf_id as u16)
+            {
+                register.value = value.map(|value| {
+                    if dwarf_id == PROGRAM_COUNTER {
+                        // The return address has the Thumb bit set.
+                        RegisterValue::U32(value & !0b1)
+                    } else {
+                        RegisterValue::U32(value)
+                    }
+                });
+            }
+        }
+
+        Ok(Some(caller_frame_registers))
+    }
+}
+
+/// Convert a `prel31` offset, stored at `place`, to an absolute address.
+fn prel31_to_address(word: u32, place: u64) -> u64 {
+    // Sign extend the 31 bit offset.
+    let offset = ((word << 1) as i32 >> 1) as i64;
+    place.wrapping_add_signed(offset) & 0xffff_ffff
+}
+
+/// Execute the EHABI unwind instructions, as described in section 10.3 of the EHABI specification.
+///
+/// The `core_registers` start out with the values of the callee frame, and contain the values of the
+/// calling frame when this function returns successfully.
+fn execute_unwind_instructions(
+    instructions: &[u8],
+    core_registers: &mut [Option<u32>; CORE_REGISTER_COUNT],
+    memory: &mut dyn MemoryInterface,
+) -> Result<(), DebugError> {
+    let mut vsp = core_registers[STACK_POINTER]
+        .ok_or_else(|| DebugError::Other(anyhow!("The stack pointer value is not available.")))?;
+    let mut program_counter_popped = false;
+
+    // Pop the registers selected by `mask` (bit 0 = `first_register`), lowest numbered register first.
+    let mut pop_registers = |vsp: &mut u32,
+                             core_registers: &mut [Option<u32>; CORE_REGISTER_COUNT],
+                             first_register: usize,
+                             mask: u16|
+     -> Result<bool, DebugError> {
+        let mut new_stack_pointer = None;
+        let mut program_counter_popped = false;
+        for bit in 0..16 {
+            if mask & (1 << bit) == 0 {
+                continue;
+            }
+            let register = first_register + bit;
+            let value = memory.read_word_32(*vsp as u64)?;
+            *vsp = vsp.wrapping_add(4);
+            match register {
+                STACK_POINTER => new_stack_pointer = Some(value),
+                PROGRAM_COUNTER => program_counter_popped = true,
+                _ => {}
+            }
+            core_registers[register] = Some(value);
+        }
+        if let Some(new_stack_pointer) = new_stack_pointer {
+            *vsp = new_stack_pointer;
+        }
+        Ok(program_counter_popped)
+    };
+
+    let mut bytes = instructions.iter().copied();
+    let mut next_byte = |opcode: u8| {
+        bytes.next().ok_or_else(|| {
+            DebugError::Other(anyhow!(
+                "Truncated unwind instruction {:#04x} in ARM exception table.",
+                opcode
+            ))
+        })
+    };
+
+    let spare = |opcode: u8| {
+        DebugError::Other(anyhow!(
+            "Reserved or unsupported unwind instruction {:#04x} in ARM exception table.",
+            opcode
+        ))
+    };
+
+    while let Ok(opcode) = next_byte(0) {
+        match opcode {
+            // vsp = vsp + (xxxxxx << 2) + 4
+            0x00..=0x3f => vsp = vsp.wrapping_add(((opcode as u32 & 0x3f) << 2) + 4),
+            // vsp = vsp - (xxxxxx << 2) - 4
+            0x40..=0x7f => vsp = vsp.wrapping_sub(((opcode as u32 & 0x3f) << 2) + 4),
+            // Pop up to 12 integer registers under masks {r15-r12}, {r11-r4}
+            0x80..=0x8f => {
+                let mask = ((opcode as u16 & 0x0f) << 8) | next_byte(opcode)? as u16;
+                if mask == 0 {
+                    return Err(DebugError::Other(anyhow!(
+                        "The ARM exception table refuses to unwind this function."
+                    )));
+                }
+                program_counter_popped |= pop_registers(&mut vsp, core_registers, 4, mask)?;
+            }
+            // vsp = r[nnnn]
+            0x90..=0x9f => {
+                let register = (opcode & 0x0f) as usize;
+                if register == STACK_POINTER || register == PROGRAM_COUNTER {
+                    return Err(spare(opcode));
+                }
+                vsp = core_registers[register].ok_or_else(|| {
+                    DebugError::Other(anyhow!(
+                        "The value of register r{} is not available.",
+                        register
+                    ))
+                })?;
+            }
+            // Pop r4-r[4+nnn]
+            0xa0..=0xa7 => {
+                let mask = (1u16 << ((opcode & 0x07) + 1)) - 1;
+                pop_registers(&mut vsp, core_registers, 4, mask)?;
+            }
+            // Pop r4-r[4+nnn], r14
+            0xa8..=0xaf => {
+                let mask = ((1u16 << ((opcode & 0x07) + 1)) - 1) | (1 << (LINK_REGISTER - 4));
+                pop_registers(&mut vsp, core_registers, 4, mask)?;
+            }
+            // Finish
+            0xb0 => break,
+            // Pop integer registers under mask {r3, r2, r1, r0}
+            0xb1 => {
+                let mask = next_byte(opcode)?;
+                if mask == 0 || mask & 0xf0 != 0 {
+                    return Err(spare(opcode));
+                }
+                pop_registers(&mut vsp, core_registers, 0, mask as u16)?;
+            }
+            // vsp = vsp + 0x204 + (uleb128 << 2)
+            0xb2 => {
+                let mut value = 0u32;
+                let mut shift = 0;
+                loop {
+                    let byte = next_byte(opcode)?;
+                    value |= ((byte & 0x7f) as u32).checked_shl(shift).unwrap_or(0);
+                    shift += 7;
+                    if byte & 0x80 == 0 {
+                        break;
+                    }
+                }
+                vsp = vsp.wrapping_add(0x204).wrapping_add(value << 2);
+            }
+            // Pop VFP double-precision registers D[ssss]-D[ssss+cccc] saved by FSTMFDX
+            0xb3 => {
+                let count = (next_byte(opcode)? & 0x0f) as u32 + 1;
+                vsp = vsp.wrapping_add(8 * count + 4);
+            }
+            // Pop VFP double-precision registers D[8]-D[8+nnn] saved by FSTMFDX
+            0xb8..=0xbf => vsp = vsp.wrapping_add(8 * ((opcode & 0x07) as u32 + 1) + 4),
+            // Intel Wireless MMX pop wR[10]-wR[10+nnn]
+            0xc0..=0xc5 => vsp = vsp.wrapping_add(8 * ((opcode & 0x07) as u32 + 1)),
+            // Intel Wireless MMX pop wR[ssss]-wR[ssss+cccc], or
+            // VFP double-precision registers saved by VPUSH
+            0xc6 | 0xc8 | 0xc9 => {
+                let count = (next_byte(opcode)? & 0x0f) as u32 + 1;
+                vsp = vsp.wrapping_add(8 * count);
+            }
+            // Intel Wireless MMX pop wCGR registers under mask {wCGR3,2,1,0}
+            0xc7 => {
+                let mask = next_byte(opcode)?;
+                if mask == 0 || mask & 0xf0 != 0 {
+                    return Err(spare(opcode));
+                }
+                vsp = vsp.wrapping_add(4 * mask.count_ones());
+            }
+            // Pop VFP double-precision registers D[8]-D[8+nnn] saved by VPUSH
+            0xd0..=0xd7 => vsp = vsp.wrapping_add(8 * ((opcode & 0x07) as u32 + 1)),
+            _ => return Err(spare(opcode)),
+        }
+    }
+
+    core_registers[STACK_POINTER] = Some(vsp);
+    if !program_counter_popped {
+        core_registers[PROGRAM_COUNTER] = core_registers[LINK_REGISTER];
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        architecture::arm::core::registers::cortex_m::CORTEX_M_CORE_REGISTERS,
+        debug::DebugRegister, test::MockMemory,
+    };
+
+    fn registers(stack_pointer: u32, link_register: u32, program_counter: u32) -> DebugRegisters {
+        let mut values = [0u32; 16];
+        values[STACK_POINTER] = stack_pointer;
+        values[LINK_REGISTER] = link_register;
+        values[PROGRAM_COUNTER] = program_counter;
+
+        DebugRegisters(
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(id, value)| DebugRegister {
+                    dwarf_id: Some(id as u16),
+                    core_register: CORTEX_M_CORE_REGISTERS.core_register(id),
+                    value: Some(RegisterValue::U32(value)),
+                })
+                .collect(),
+        )
+    }
+
+    fn value_of(registers: &DebugRegisters, dwarf_id: u16) -> u32 {
+        registers
+            .get_register_by_dwarf_id(dwarf_id)
+            .and_then(|register| register.value)
+            .unwrap()
+            .try_into()
+            .unwrap()
+    }
+
+    /// Encode a `prel31` offset from `place` to `target`.
+    fn prel31(target: u32, place: u32) -> u32 {
+        target.wrapping_sub(place) & 0x7fff_ffff
+    }
+
+    #[test]
+    fn prel31_offsets_are_sign_extended() {
+        assert_eq!(prel31_to_address(0x7fff_fff0, 0x1000), 0x0ff0);
+        assert_eq!(prel31_to_address(0x0000_0010, 0x1000), 0x1010);
+    }
+
+    #[test]
+    fn inline_entry_pops_registers_and_lr() {
+        // Function at 0x100: `push {r4, r5, lr}` followed by `sub sp, #8`
+        //   0x01 : vsp = vsp + 8
+        //   0xa9 : pop {r4, r5, lr}
+        //   0xb0 : finish
+        let index_address = 0x2000;
+        let mut index = Vec::new();
+        index.extend_from_slice(&prel31(0x100, index_address).to_le_bytes());
+        index.extend_from_slice(&0x8001_a9b0u32.to_le_bytes());
+        index.extend_from_slice(&prel31(0x200, index_address + 8).to_le_bytes());
+        index.extend_from_slice(&EXIDX_CANTUNWIND.to_le_bytes());
+
+        let tables = ArmExceptionTables::new(
+            LoadedSection {
+                address: index_address as u64,
+                data: index,
+            },
+            None,
+        )
+        .unwrap();
+
+        let mut memory = MockMemory::new();
+        memory.add_word_range(0x2000_0ff8, &[0, 0, 0x44, 0x55, 0x0000_0301]);
+
+        let callee = registers(0x2000_0ff8, 0xffff_ffff, 0x0000_0120);
+        let caller = tables.unwind(&callee, &mut memory).unwrap().unwrap();
+
+        assert_eq!(value_of(&caller, 4), 0x44);
+        assert_eq!(value_of(&caller, 5), 0x55);
+        assert_eq!(value_of(&caller, 13), 0x2000_100c);
+        assert_eq!(value_of(&caller, 14), 0x0000_0301);
+        assert_eq!(value_of(&caller, 15), 0x0000_0300);
+
+        let cant_unwind = registers(0x2000_0ff8, 0, 0x0000_0210);
+        assert!(tables.unwind(&cant_unwind, &mut memory).is_err());
+
+        let before_first_function = registers(0x2000_0ff8, 0, 0x0000_0010);
+        assert!(tables
+            .unwind(&before_first_function, &mut memory)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn table_entry_with_compact_personality_1() {
+        // Function at 0x400, with the unwind instructions in `.ARM.extab`:
+        //   0x97 : vsp = r7
+        //   0x84 0x08 : pop {r7, lr}
+        //   0xb0 : finish
+        let index_address = 0x2000;
+        let table_address = 0x3000;
+
+        let mut index = Vec::new();
+        index.extend_from_slice(&prel31(0x400, index_address).to_le_bytes());
+        index.extend_from_slice(&prel31(table_address, index_address + 4).to_le_bytes());
+
+        let mut table = Vec::new();
+        table.extend_from_slice(&0x8101_9784u32.to_le_bytes());
+        table.extend_from_slice(&0x08b0_b0b0u32.to_le_bytes());
+
+        let tables = ArmExceptionTables::new(
+            LoadedSection {
+                address: index_address as u64,
+                data: index,
+            },
+            Some(LoadedSection {
+                address: table_address as u64,
+                data: table,
+            }),
+        )
+        .unwrap();
+
+        let mut memory = MockMemory::new();
+        memory.add_word_range(0x2000_0f00, &[0x2000_0f40, 0x0000_0511]);
+
+        let mut callee = registers(0x2000_0ef0, 0xffff_ffff, 0x0000_0410);
+        callee.get_register_mut_by_dwarf_id(7).unwrap().value =
+            Some(RegisterValue::U32(0x2000_0f00));
+
+        let caller = tables.unwind(&callee, &mut memory).unwrap().unwrap();
+
+        assert_eq!(value_of(&caller, 7), 0x2000_0f40);
+        assert_eq!(value_of(&caller, 13), 0x2000_0f08);
+        assert_eq!(value_of(&caller, 15), 0x0000_0510);
+    }
+
+    #[test]
+    fn refuse_to_unwind() {
+        let mut memory = MockMemory::new();
+        let mut core_registers = [Some(0); CORE_REGISTER_COUNT];
+        assert!(
+            execute_unwind_instructions(&[0x80, 0x00], &mut core_registers, &mut memory).is_err()
+        );
+    }
+}
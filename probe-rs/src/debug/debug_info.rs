@@ -1,9 +1,10 @@
 use super::{
+    arm_ehabi::{ArmExceptionTables, LoadedSection},
     function_die::{Die, FunctionDie},
-    get_object_reference,
+    get_object_reference, heuristic_unwind,
     unit_info::UnitInfo,
     variable::*,
-    DebugError, DebugRegisters, StackFrame, VariableCache,
+    DebugError, DebugRegisters, StackFrame, UnwindMethod, VariableCache,
 };
 use crate::{
     core::{ExceptionInterface, RegisterRole, RegisterValue, UnwindRule},
@@ -15,12 +16,19 @@ use crate::{
 };
 use anyhow::anyhow;
 use gimli::{
-    BaseAddresses, DebugFrame, DebugInfoOffset, UnwindContext, UnwindSection, UnwindTableRow,
+    BaseAddresses, DebugFrame, DebugInfoOffset, EhFrame, UnwindContext, UnwindSection,
+    UnwindTableRow,
 };
-use object::read::{Object, ObjectSection};
+use object::read::{Object, ObjectSection, ObjectSymbol};
 use probe_rs_target::InstructionSet;
 use std::{
-    borrow, cmp::Ordering, num::NonZeroU64, ops::ControlFlow, path::Path, rc::Rc, str::from_utf8,
+    borrow,
+    cmp::Ordering,
+    num::NonZeroU64,
+    ops::{ControlFlow, Range},
+    path::Path,
+    rc::Rc,
+    str::from_utf8,
 };
 use typed_path::{TypedPath, TypedPathBuf};
 
@@ -34,6 +42,11 @@ pub(crate) type DwarfReader = gimli::read::EndianRcSlice<gimli::LittleEndian>;
 pub struct DebugInfo {
     pub(crate) dwarf: gimli::Dwarf<DwarfReader>,
     pub(crate) frame_section: gimli::DebugFrame<DwarfReader>,
+    pub(crate) eh_frame_section: gimli::EhFrame<DwarfReader>,
+    pub(crate) eh_frame_bases: BaseAddresses,
+    pub(crate) arm_exception_tables: Option<ArmExceptionTables>,
+    /// The address ranges of the functions in the ELF symbol table, sorted by start address.
+    pub(crate) function_symbols: Vec<Range<u64>>,
     pub(crate) locations_section: gimli::LocationLists<DwarfReader>,
     pub(crate) address_section: gimli::DebugAddr<DwarfReader>,
     pub(crate) debug_line_section: gimli::DebugLine<DwarfReader>,
@@ -71,6 +84,10 @@ impl DebugInfo {
 
         use gimli::Section;
         let mut frame_section = gimli::DebugFrame::load(load_section)?;
+        let mut eh_frame_section = gimli::EhFrame::load(load_section)?;
+        // Use the address size of the object file, so stripped binaries without compilation units are still
+        // decoded correctly.
+        eh_frame_section.set_address_size(if object.is_64() { 8 } else { 4 });
         let address_section = gimli::DebugAddr::load(load_section)?;
         let debug_loc = gimli::DebugLoc::load(load_section)?;
         let debug_loc_lists = gimli::DebugLocLists::load(load_section)?;
@@ -95,9 +112,45 @@ impl DebugInfo {
             };
         }
 
+        // The `.eh_frame` section uses pointer encodings that are relative to the section addresses.
+        let mut eh_frame_bases = BaseAddresses::default();
+        if let Some(section) = object.section_by_name(".eh_frame") {
+            eh_frame_bases = eh_frame_bases.set_eh_frame(section.address());
+        }
+        if let Some(section) = object.section_by_name(".text") {
+            eh_frame_bases = eh_frame_bases.set_text(section.address());
+        }
+
+        let load_section_with_address = |name: &str| {
+            object.section_by_name(name).and_then(|section| {
+                Some(LoadedSection {
+                    address: section.address(),
+                    data: section.uncompressed_data().ok()?.into_owned(),
+                })
+            })
+        };
+        let arm_exception_tables = load_section_with_address(".ARM.exidx").and_then(|index| {
+            ArmExceptionTables::new(index, load_section_with_address(".ARM.extab"))
+        });
+
+        let mut function_symbols: Vec<Range<u64>> = object
+            .symbols()
+            .filter(|symbol| symbol.kind() == object::SymbolKind::Text && symbol.address() != 0)
+            .map(|symbol| {
+                // Clear the Thumb bit of ARM function addresses.
+                let start = symbol.address() & !0b1;
+                start..start + symbol.size()
+            })
+            .collect();
+        function_symbols.sort_by_key(|range| range.start);
+
         Ok(DebugInfo {
             dwarf: dwarf_cow,
             frame_section,
+            eh_frame_section,
+            eh_frame_bases,
+            arm_exception_tables,
+            function_symbols,
             locations_section,
             address_section,
             debug_line_section,
@@ -340,6 +393,7 @@ impl DebugInfo {
         address: u64,
         unwind_context: &mut UnwindContext<DwarfReader>,
         unwind_registers: &registers::DebugRegisters,
+        unwind_method: UnwindMethod,
     ) -> Result<Vec<StackFrame>, DebugError> {
         // When reporting the address, we format it as a hex string, with the width matching
         // the configured size of the datatype used in the `RegisterValue` address.
@@ -369,9 +423,10 @@ impl DebugInfo {
         };
 
         // Determining the frame base may need the CFA (Canonical Frame Address) to be calculated first.
-        let cfa = get_unwind_info(unwind_context, &self.frame_section, address)
+        let cfa = self
+            .get_unwind_info(unwind_context, address)
             .ok()
-            .and_then(|unwind_info| determine_cfa(unwind_registers, unwind_info).ok())
+            .and_then(|(unwind_info, _)| determine_cfa(unwind_registers, unwind_info).ok())
             .flatten();
 
         // The first function is the non-inlined function, and the rest are inlined functions.
@@ -448,6 +503,7 @@ impl DebugInfo {
                     is_inlined: function_die.is_inline(),
                     local_variables,
                     canonical_frame_address: cfa,
+                    unwind_method,
                 });
             } else {
                 tracing::warn!(
@@ -497,6 +553,7 @@ impl DebugInfo {
             is_inlined: last_function.is_inline(),
             local_variables,
             canonical_frame_address: cfa,
+            unwind_method,
         });
 
         Ok(frames)
@@ -512,6 +569,11 @@ impl DebugInfo {
     /// - We encounter a LR register value of 0x0 or 0xFFFFFFFF(Arm 'Reset' value for that register).
     /// - We can not intelligently calculate a valid LR register value from the other registers, or the gimli::RegisterRule result is a value of 0x0. Note: [DWARF](https://dwarfstd.org) 6.4.4 - CIE defines the return register address used in the `gimli::RegisterRule` tables for unwind operations. Theoretically, if we encounter a function that has `Undefined` `gimli::RegisterRule` for the return register address, it means we have reached the bottom of the stack OR the function is a 'no return' type of function. I have found actual examples (e.g. local functions) where we get `Undefined` for register rule when we cannot apply this logic. Example 1: local functions in main.rs will have LR rule as `Undefined`. Example 2: main()-> ! that is called from a trampoline will have a valid LR rule.
     /// - Similarly, certain error conditions encountered in `StackFrameIterator` will also break out of the unwind loop.
+    ///
+    /// Functions without call frame information in `.debug_frame` are unwound with the information in `.eh_frame`, the ARM exception
+    /// tables in `.ARM.exidx`/`.ARM.extab`, or by analyzing the function prologue, in that order. The [`StackFrame::unwind_method`]
+    /// reports which method was used for each frame.
+    ///
     /// Note: In addition to populating the `StackFrame`s, this function will also populate the `DebugInfo::VariableCache` with `Variable`s for available Registers as well as static and function variables.
     /// TODO: Separate logic for stackframe creation and cache population
     pub fn unwind(
@@ -536,6 +598,7 @@ impl DebugInfo {
         let mut unwind_context = Box::new(gimli::UnwindContext::new());
 
         let mut unwind_registers = initial_registers;
+        let mut unwind_method = UnwindMethod::CoreRegisters;
 
         // Unwind [StackFrame]'s for as long as we can unwind a valid PC value.
         'unwind: while let Some(frame_pc_register_value) = unwind_registers
@@ -584,6 +647,7 @@ impl DebugInfo {
                 frame_pc,
                 &mut unwind_context,
                 &unwind_registers,
+                unwind_method,
             ) {
                 Ok(cached_stack_frames) => cached_stack_frames,
                 Err(e) => {
@@ -630,6 +694,7 @@ impl DebugInfo {
                             is_inlined: false,
                             local_variables: None,
                             canonical_frame_address: None,
+                            unwind_method,
                         }
                     } else {
                         let address = frame_pc;
@@ -656,6 +721,7 @@ impl DebugInfo {
                             is_inlined: false,
                             local_variables: None,
                             canonical_frame_address: None,
+                            unwind_method,
                         }
                     }
                 }
@@ -695,6 +761,7 @@ impl DebugInfo {
                     // If we are at an exception handler frame, we need to overwrite the unwind registers.
                     // This will allow us to continue unwinding from the exception handler frame.
                     unwind_registers = exception_info.calling_frame_registers;
+                    unwind_method = UnwindMethod::ExceptionFrame;
 
                     stack_frames.push(return_frame);
                     continue;
@@ -708,78 +775,116 @@ impl DebugInfo {
                 return_frame.source_location
             );
             // PART 2-a: get the `gimli::FrameDescriptorEntry` for this address and then the unwind info associated with this row.
-            let unwind_info = match get_unwind_info(
-                &mut unwind_context,
-                &self.frame_section,
-                frame_pc,
-            ) {
-                Ok(unwind_info) => unwind_info,
-
-                // We cannot do stack unwinding if we do not have debug info. However, there is one case where we can continue. When the following conditions are met:
-                // 1. The current frame is the first frame in the stack, AND ...
-                // 2. The frame registers have a valid return address/LR value.
-                // If both these conditions are met, we can push the 'unknown function' to the list of stack frames, and use the LR value to calculate the PC for the calling frame.
-                // The current logic will then use that PC to get the next frame's unwind info, and if that exists, will be able to continue unwinding.
-                // If the calling frame has no debug info, then the unwinding will end with that frame.
-                Err(_) if stack_frames.is_empty() => {
-                    let callee_frame_registers = unwind_registers.clone();
-                    let mut unwound_return_address: Option<RegisterValue> = callee_frame_registers
-                        .get_return_address()
-                        .and_then(|lr| lr.value);
-
-                    if let Some(calling_pc) = unwind_registers.get_program_counter_mut() {
-                        if let ControlFlow::Break(error) = unwind_register(
-                            calling_pc,
-                            &callee_frame_registers,
-                            None,
-                            return_frame.canonical_frame_address,
-                            &mut unwound_return_address,
-                            memory,
-                            instruction_set,
-                        ) {
-                            // This is not fatal, but we cannot continue unwinding beyond the current frame.
-                            tracing::error!("{:?}", &error);
-                            return_frame.function_name =
-                                format!("{} : ERROR : {error}", &return_frame.function_name);
+            let call_frame_information = match self.get_unwind_info(&mut unwind_context, frame_pc) {
+                Ok(unwind_info) => Some(unwind_info),
+
+                // Without call frame information, we try the ARM exception tables and the heuristic unwinder.
+                Err(error) => match self.unwind_without_call_frame_information(
+                    &unwind_registers,
+                    memory,
+                    instruction_set,
+                    stack_frames.is_empty(),
+                ) {
+                    Ok(Some((caller_frame_registers, method))) => {
+                        let caller_stack_pointer = caller_frame_registers
+                            .get_stack_pointer()
+                            .and_then(|sp| sp.value);
+                        if caller_stack_pointer
+                            == unwind_registers.get_stack_pointer().and_then(|sp| sp.value)
+                            && caller_frame_registers
+                                .get_program_counter()
+                                .and_then(|pc| pc.value)
+                                == Some(frame_pc_register_value)
+                        {
                             stack_frames.push(return_frame);
-                            break 'unwind;
+                            tracing::trace!("UNWIND: Stack unwind complete. The {} unwind made no progress at program counter {}", method, frame_pc);
+                            break;
                         }
 
-                        // The unwind registers were updated with the calling frame's PC, so we can continue unwinding.
+                        return_frame.canonical_frame_address = return_frame
+                            .canonical_frame_address
+                            .or_else(|| caller_stack_pointer.and_then(|sp| sp.try_into().ok()));
+                        unwind_registers = caller_frame_registers;
+                        unwind_method = method;
+                        None
                     }
 
-                    stack_frames.push(return_frame);
-                    continue 'unwind;
-                }
-                Err(error) => {
-                    stack_frames.push(return_frame);
-                    tracing::trace!("UNWIND: Stack unwind complete. No available debug info for program counter {}: {}", frame_pc, error);
-                    break;
-                }
+                    // We cannot do stack unwinding if we do not have debug info. However, there is one case where we can continue. When the following conditions are met:
+                    // 1. The current frame is the first frame in the stack, AND ...
+                    // 2. The frame registers have a valid return address/LR value.
+                    // If both these conditions are met, we can push the 'unknown function' to the list of stack frames, and use the LR value to calculate the PC for the calling frame.
+                    // The current logic will then use that PC to get the next frame's unwind info, and if that exists, will be able to continue unwinding.
+                    // If the calling frame has no debug info, then the unwinding will end with that frame.
+                    Ok(None) if stack_frames.is_empty() => {
+                        let callee_frame_registers = unwind_registers.clone();
+                        let mut unwound_return_address: Option<RegisterValue> =
+                            callee_frame_registers
+                                .get_return_address()
+                                .and_then(|lr| lr.value);
+
+                        if let Some(calling_pc) = unwind_registers.get_program_counter_mut() {
+                            if let ControlFlow::Break(error) = unwind_register(
+                                calling_pc,
+                                &callee_frame_registers,
+                                None,
+                                return_frame.canonical_frame_address,
+                                &mut unwound_return_address,
+                                memory,
+                                instruction_set,
+                            ) {
+                                // This is not fatal, but we cannot continue unwinding beyond the current frame.
+                                tracing::error!("{:?}", &error);
+                                return_frame.function_name =
+                                    format!("{} : ERROR : {error}", &return_frame.function_name);
+                                stack_frames.push(return_frame);
+                                break 'unwind;
+                            }
+
+                            // The unwind registers were updated with the calling frame's PC, so we can continue unwinding.
+                            unwind_method = UnwindMethod::Heuristic;
+                        }
+
+                        stack_frames.push(return_frame);
+                        continue 'unwind;
+                    }
+                    Ok(None) => {
+                        stack_frames.push(return_frame);
+                        tracing::trace!("UNWIND: Stack unwind complete. No available debug info for program counter {}: {}", frame_pc, error);
+                        break;
+                    }
+                    Err(unwind_error) => {
+                        stack_frames.push(return_frame);
+                        tracing::trace!("UNWIND: Stack unwind complete. Unable to unwind the frame at program counter {}: {}", frame_pc, unwind_error);
+                        break;
+                    }
+                },
             };
 
-            // Because we will be updating the `unwind_registers` with previous frame unwind info, we need to keep a copy of the current frame's registers that can be used to resolve [DWARF](https://dwarfstd.org) expressions.
-            let callee_frame_registers = unwind_registers.clone();
-
-            // PART 2-b: Unwind registers for the "previous/calling" frame.
-            // We sometimes need to keep a copy of the LR value to calculate the PC. For both ARM, and RISC-V, The LR will be unwound before the PC, so we can reference it safely.
-            let mut unwound_return_address: Option<RegisterValue> = None;
-            for debug_register in unwind_registers.0.iter_mut() {
-                if let ControlFlow::Break(error) = unwind_register(
-                    debug_register,
-                    &callee_frame_registers,
-                    Some(unwind_info),
-                    return_frame.canonical_frame_address,
-                    &mut unwound_return_address,
-                    memory,
-                    instruction_set,
-                ) {
-                    tracing::error!("{:?}", &error);
-                    return_frame.function_name =
-                        format!("{} : ERROR: {error}", &return_frame.function_name);
-                    stack_frames.push(return_frame);
-                    break 'unwind;
-                };
+            if let Some((unwind_info, method)) = call_frame_information {
+                // Because we will be updating the `unwind_registers` with previous frame unwind info, we need to keep a copy of the current frame's registers that can be used to resolve [DWARF](https://dwarfstd.org) expressions.
+                let callee_frame_registers = unwind_registers.clone();
+
+                // PART 2-b: Unwind registers for the "previous/calling" frame.
+                // We sometimes need to keep a copy of the LR value to calculate the PC. For both ARM, and RISC-V, The LR will be unwound before the PC, so we can reference it safely.
+                let mut unwound_return_address: Option<RegisterValue> = None;
+                for debug_register in unwind_registers.0.iter_mut() {
+                    if let ControlFlow::Break(error) = unwind_register(
+                        debug_register,
+                        &callee_frame_registers,
+                        Some(unwind_info),
+                        return_frame.canonical_frame_address,
+                        &mut unwound_return_address,
+                        memory,
+                        instruction_set,
+                    ) {
+                        tracing::error!("{:?}", &error);
+                        return_frame.function_name =
+                            format!("{} : ERROR: {error}", &return_frame.function_name);
+                        stack_frames.push(return_frame);
+                        break 'unwind;
+                    };
+                }
+                unwind_method = method;
             }
 
             stack_frames.push(return_frame);
@@ -797,6 +902,7 @@ impl DebugInfo {
                     match exception_handler.exception_details(memory, &unwind_registers) {
                         Ok(Some(details)) => {
                             unwind_registers = details.calling_frame_registers;
+                            unwind_method = UnwindMethod::ExceptionFrame;
                             let address = frame_pc;
 
                             let exception_frame = StackFrame {
@@ -813,6 +919,7 @@ impl DebugInfo {
                                 is_inlined: false,
                                 local_variables: None,
                                 canonical_frame_address: None,
+                                unwind_method,
                             };
 
                             stack_frames.push(exception_frame);
@@ -831,6 +938,126 @@ impl DebugInfo {
         Ok(stack_frames)
     }
 
+    /// Get a handle to the [`gimli::UnwindTableRow`] for this call frame, so that we can reference it to unwind register values.
+    /// The call frame information in `.debug_frame` is used if it is available, otherwise the information in `.eh_frame`.
+    fn get_unwind_info<'a>(
+        &self,
+        unwind_context: &'a mut UnwindContext<DwarfReader>,
+        frame_program_counter: u64,
+    ) -> Result<
+        (
+            &'a gimli::UnwindTableRow<DwarfReader, gimli::StoreOnHeap>,
+            UnwindMethod,
+        ),
+        DebugError,
+    > {
+        let transform_error = |error| {
+            DebugError::Other(anyhow::anyhow!(
+                "UNWIND: Error reading FrameDescriptorEntry at PC={} : {}",
+                frame_program_counter,
+                error
+            ))
+        };
+
+        let debug_frame_bases = BaseAddresses::default();
+
+        if let Ok(frame_descriptor_entry) = self.frame_section.fde_for_address(
+            &debug_frame_bases,
+            frame_program_counter,
+            DebugFrame::cie_from_offset,
+        ) {
+            return frame_descriptor_entry
+                .unwind_info_for_address(
+                    &self.frame_section,
+                    &debug_frame_bases,
+                    unwind_context,
+                    frame_program_counter,
+                )
+                .map(|unwind_info| (unwind_info, UnwindMethod::DebugFrame))
+                .map_err(transform_error);
+        }
+
+        let frame_descriptor_entry = self
+            .eh_frame_section
+            .fde_for_address(
+                &self.eh_frame_bases,
+                frame_program_counter,
+                EhFrame::cie_from_offset,
+            )
+            .map_err(transform_error)?;
+
+        frame_descriptor_entry
+            .unwind_info_for_address(
+                &self.eh_frame_section,
+                &self.eh_frame_bases,
+                unwind_context,
+                frame_program_counter,
+            )
+            .map(|unwind_info| (unwind_info, UnwindMethod::EhFrame))
+            .map_err(transform_error)
+    }
+
+    /// Unwind the registers of the calling frame, for a function without call frame information.
+    /// This uses the ARM exception tables if they describe the function, and otherwise analyzes the function prologue.
+    ///
+    /// Returns `Ok(None)` if neither method is available for the current instruction set.
+    fn unwind_without_call_frame_information(
+        &self,
+        callee_frame_registers: &DebugRegisters,
+        memory: &mut dyn MemoryInterface,
+        instruction_set: Option<InstructionSet>,
+        is_innermost_frame: bool,
+    ) -> Result<Option<(DebugRegisters, UnwindMethod)>, DebugError> {
+        if !matches!(
+            instruction_set,
+            Some(InstructionSet::Thumb2) | Some(InstructionSet::A32)
+        ) {
+            return Ok(None);
+        }
+
+        let Some(program_counter) = callee_frame_registers
+            .get_program_counter()
+            .and_then(|pc| pc.value)
+        else {
+            return Ok(None);
+        };
+        let program_counter: u64 = program_counter.try_into()?;
+
+        if let Some(tables) = self
+            .arm_exception_tables
+            .as_ref()
+            .filter(|tables| tables.has_entry_for(program_counter))
+        {
+            return Ok(tables
+                .unwind(callee_frame_registers, memory)?
+                .map(|registers| (registers, UnwindMethod::ArmExidx)));
+        }
+
+        if instruction_set == Some(InstructionSet::Thumb2) {
+            return Ok(heuristic_unwind::unwind_thumb_prologue(
+                callee_frame_registers,
+                self.function_start(program_counter),
+                memory,
+                is_innermost_frame,
+            )?
+            .map(|registers| (registers, UnwindMethod::Heuristic)));
+        }
+
+        Ok(None)
+    }
+
+    /// Find the start address of the function that contains `address`, using the ELF symbol table.
+    fn function_start(&self, address: u64) -> Option<u64> {
+        let index = self
+            .function_symbols
+            .partition_point(|range| range.start <= address);
+        self.function_symbols[..index]
+            .iter()
+            .rev()
+            .find(|range| range.is_empty() || range.contains(&address))
+            .map(|range| range.start)
+    }
+
     /// Find the program counter where a breakpoint should be set,
     /// given a source file, a line and optionally a column.
     // TODO: Move (and fix) this to the [`InstructionSequence::for_source_location`] method.
@@ -998,40 +1225,6 @@ pub(crate) fn canonical_path_eq(
     primary_path.normalize() == secondary_path.normalize()
 }
 
-/// Get a handle to the [`gimli::UnwindTableRow`] for this call frame, so that we can reference it to unwind register values.
-fn get_unwind_info<'a>(
-    unwind_context: &'a mut UnwindContext<DwarfReader>,
-    frame_section: &'a DebugFrame<DwarfReader>,
-    frame_program_counter: u64,
-) -> Result<&'a gimli::UnwindTableRow<DwarfReader, gimli::StoreOnHeap>, DebugError> {
-    let transform_error = |error| {
-        DebugError::Other(anyhow::anyhow!(
-            "UNWIND: Error reading FrameDescriptorEntry at PC={} : {}",
-            frame_program_counter,
-            error
-        ))
-    };
-
-    let unwind_bases = BaseAddresses::default();
-
-    let frame_descriptor_entry = frame_section
-        .fde_for_address(
-            &unwind_bases,
-            frame_program_counter,
-            DebugFrame::cie_from_offset,
-        )
-        .map_err(transform_error)?;
-
-    frame_descriptor_entry
-        .unwind_info_for_address(
-            frame_section,
-            &unwind_bases,
-            unwind_context,
-            frame_program_counter,
-        )
-        .map_err(transform_error)
-}
-
 /// Determines the CFA (canonical frame address) for the current [`gimli::UnwindTableRow`], using the current register values.
 fn determine_cfa<R: gimli::Reader>(
     unwind_registers: &DebugRegisters,
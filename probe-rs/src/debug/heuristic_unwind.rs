@@ -0,0 +1,379 @@
+//! Heuristic stack unwinding for code without any unwind tables.
+//!
+//! The unwinder scans the prologue of the current function for the instructions that save registers and
+//! allocate stack space, and reverses their effect. This is only implemented for the Thumb-2 instruction set,
+//! and only recognizes the instruction patterns that compilers commonly emit for function prologues.
+
+use super::{DebugError, DebugRegisters};
+use crate::{core::RegisterValue, MemoryInterface};
+
+/// The maximum number of bytes before the program counter that is searched for a function prologue,
+/// when the start of the function is not known.
+const MAX_BACKWARD_SEARCH: u64 = 1024;
+
+/// The backward search reads memory in chunks of this size, so that it stops at the start of a memory region.
+const SEARCH_CHUNK_SIZE: u64 = 256;
+
+/// The maximum number of bytes, from the start of the function, that are treated as the prologue.
+const MAX_PROLOGUE_LENGTH: u64 = 64;
+
+const STACK_POINTER: usize = 13;
+const LINK_REGISTER: usize = 14;
+const PROGRAM_COUNTER: usize = 15;
+
+/// The effect of the prologue instructions that were executed before the program counter.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct PrologueEffect {
+    /// The number of bytes the stack pointer was decremented by.
+    stack_size: u32,
+    /// For each core register, the distance (in bytes) below the stack pointer at function entry where it was saved.
+    saved_registers: [Option<u32>; 16],
+}
+
+impl PrologueEffect {
+    fn push(&mut self, register_mask: u16) {
+        let count = register_mask.count_ones();
+        self.stack_size += 4 * count;
+
+        // The lowest numbered register is stored at the lowest address.
+        let mut offset = self.stack_size;
+        for register in 0..16 {
+            if register_mask & (1 << register) != 0 {
+                self.saved_registers[register].get_or_insert(offset);
+                offset -= 4;
+            }
+        }
+    }
+}
+
+/// Returns `true` if the halfword is the first half of a 32-bit Thumb instruction.
+fn is_32bit_instruction(halfword: u16) -> bool {
+    matches!(halfword >> 11, 0b11101..=0b11111)
+}
+
+/// Returns `true` for the instructions that save the link register in a function prologue.
+fn is_push_with_link_register(first: u16, second: Option<u16>) -> bool {
+    // PUSH (T1) with the M bit set, or PUSH.W (T2) with LR in the register list.
+    first & 0xff00 == 0xb500
+        || (first == 0xe92d && second.is_some_and(|second| second & 0x4000 != 0))
+}
+
+/// Decode the modified immediate constant of a 32-bit Thumb data processing instruction (`ThumbExpandImm`).
+fn thumb_expand_imm(first: u16, second: u16) -> u32 {
+    let imm12 = (((first as u32 >> 10) & 1) << 11)
+        | (((second as u32 >> 12) & 0x7) << 8)
+        | (second as u32 & 0xff);
+    let imm8 = imm12 & 0xff;
+    match imm12 >> 8 {
+        0b0000 => imm8,
+        0b0001 => imm8 << 16 | imm8,
+        0b0010 => imm8 << 24 | imm8 << 8,
+        0b0011 => imm8 << 24 | imm8 << 16 | imm8 << 8 | imm8,
+        _ => (0x80 | (imm12 & 0x7f)).rotate_right(imm12 >> 7),
+    }
+}
+
+/// Analyze the prologue instructions in `code`, which starts at the beginning of the function
+/// and ends at the current program counter.
+fn analyze_prologue(code: &[u16]) -> PrologueEffect {
+    let mut effect = PrologueEffect::default();
+
+    let mut index = 0;
+    while index < code.len() {
+        let first = code[index];
+        if is_32bit_instruction(first) {
+            let Some(&second) = code.get(index + 1) else {
+                // The program counter points into the middle of this instruction.
+                break;
+            };
+            index += 2;
+
+            if first == 0xe92d {
+                // PUSH.W {register list} (STMDB SP!, {register list})
+                effect.push(second & 0x5fff);
+            } else if first == 0xf84d && second & 0x0fff == 0x0d04 {
+                // PUSH.W {Rt} (STR Rt, [SP, #-4]!)
+                effect.push(1 << (second >> 12));
+            } else if first & 0xfbef == 0xf1ad && second & 0x8f00 == 0x0d00 {
+                // SUB.W SP, SP, #const
+                effect.stack_size += thumb_expand_imm(first, second);
+            } else if first & 0xfbff == 0xf2ad && second & 0x8f00 == 0x0d00 {
+                // SUBW SP, SP, #imm12
+                effect.stack_size += (((first as u32 >> 10) & 1) << 11)
+                    | (((second as u32 >> 12) & 0x7) << 8)
+                    | (second as u32 & 0xff);
+            } else if first & 0xffbf == 0xed2d && second & 0x0e00 == 0x0a00 {
+                // VPUSH {register list}
+                effect.stack_size += (second as u32 & 0xff) * 4;
+            }
+        } else {
+            index += 1;
+
+            if first & 0xfe00 == 0xb400 {
+                // PUSH {register list}, with the M bit selecting LR.
+                effect.push((first & 0xff) | ((first & 0x0100) << 6));
+            } else if first & 0xff80 == 0xb080 {
+                // SUB SP, SP, #imm7
+                effect.stack_size += (first as u32 & 0x7f) << 2;
+            }
+        }
+    }
+
+    effect
+}
+
+/// Read the instructions in the given address range as halfwords.
+fn read_code(
+    memory: &mut dyn MemoryInterface,
+    start: u64,
+    end: u64,
+) -> Result<Vec<u16>, DebugError> {
+    let mut bytes = vec![0u8; end.saturating_sub(start) as usize];
+    memory.read(start, &mut bytes)?;
+    Ok(bytes
+        .chunks_exact(2)
+        .map(|halfword| u16::from_le_bytes([halfword[0], halfword[1]]))
+        .collect())
+}
+
+/// Search backwards from the program counter for an instruction that looks like the start of a function.
+fn find_function_start(memory: &mut dyn MemoryInterface, program_counter: u64) -> Option<u64> {
+    let search_limit = program_counter.saturating_sub(MAX_BACKWARD_SEARCH);
+    let mut chunk_end = program_counter;
+
+    while chunk_end > search_limit {
+        let chunk_start = chunk_end
+            .saturating_sub(SEARCH_CHUNK_SIZE)
+            .max(search_limit)
+            & !0b1;
+        // Read one additional halfword, for 32-bit instructions that start at the end of the chunk.
+        let Ok(code) = read_code(memory, chunk_start, (chunk_end + 2).min(program_counter)) else {
+            // We have reached the start of the memory region.
+            break;
+        };
+
+        let function_start = (0..code.len())
+            .rev()
+            .find(|&index| is_push_with_link_register(code[index], code.get(index + 1).copied()))
+            .map(|index| chunk_start + index as u64 * 2);
+        if function_start.is_some() {
+            return function_start;
+        }
+
+        chunk_end = chunk_start;
+    }
+
+    None
+}
+
+/// Unwind the registers of the calling frame by analyzing the prologue of the function at the current program counter.
+///
+/// - `function_start` is the start address of the current function, if it is known, e.g. from the ELF symbol table.
+/// - `is_innermost_frame` indicates that the link register still holds the return address if the prologue did not save it.
+///
+/// Returns `Ok(None)` if the prologue could not be analyzed.
+pub(crate) fn unwind_thumb_prologue(
+    callee_frame_registers: &DebugRegisters,
+    function_start: Option<u64>,
+    memory: &mut dyn MemoryInterface,
+    is_innermost_frame: bool,
+) -> Result<Option<DebugRegisters>, DebugError> {
+    let register_value = |dwarf_id: usize| -> Option<u32> {
+        callee_frame_registers
+            .get_register_by_dwarf_id(dwarf_id as u16)
+            .and_then(|register| register.value)
+            .and_then(|value| value.try_into().ok())
+    };
+
+    let (Some(program_counter), Some(stack_pointer)) = (
+        register_value(PROGRAM_COUNTER),
+        register_value(STACK_POINTER),
+    ) else {
+        return Ok(None);
+    };
+    let program_counter = program_counter as u64 & !0b1;
+
+    let function_start = match function_start {
+        Some(function_start) => Some(function_start & !0b1),
+        None => find_function_start(memory, program_counter),
+    };
+
+    let effect = match function_start {
+        Some(function_start) if function_start <= program_counter => {
+            let prologue_end = program_counter.min(function_start + MAX_PROLOGUE_LENGTH);
+            analyze_prologue(&read_code(memory, function_start, prologue_end)?)
+        }
+        // Without a prologue, we can only assume a leaf function that has not touched the stack.
+        _ => PrologueEffect::default(),
+    };
+
+    let entry_stack_pointer = stack_pointer.wrapping_add(effect.stack_size);
+
+    let mut caller_frame_registers = callee_frame_registers.clone();
+    for (dwarf_id, offset) in effect.saved_registers.iter().enumerate() {
+        let Some(offset) = offset else {
+            continue;
+        };
+        let value = memory.read_word_32(entry_stack_pointer.wrapping_sub(*offset) as u64)?;
+        if let Some(register) = caller_frame_registers.get_register_mut_by_dwarf_id(dwarf_id as u16)
+        {
+            register.value = Some(RegisterValue::U32(value));
+        }
+    }
+
+    if effect.saved_registers[LINK_REGISTER].is_none() && !is_innermost_frame {
+        // The link register was overwritten by a call in this function, but we don't know where it was saved.
+        return Ok(None);
+    }
+
+    let return_address = caller_frame_registers
+        .get_register_by_dwarf_id(LINK_REGISTER as u16)
+        .and_then(|register| register.value)
+        .and_then(|value| TryInto::<u32>::try_into(value).ok());
+
+    if let Some(register) =
+        caller_frame_registers.get_register_mut_by_dwarf_id(PROGRAM_COUNTER as u16)
+    {
+        register.value = return_address.map(|address| RegisterValue::U32(address & !0b1));
+    }
+    if let Some(register) =
+        caller_frame_registers.get_register_mut_by_dwarf_id(STACK_POINTER as u16)
+    {
+        register.value = Some(RegisterValue::U32(entry_stack_pointer));
+    }
+
+    Ok(Some(caller_frame_registers))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        architecture::arm::core::registers::cortex_m::CORTEX_M_CORE_REGISTERS,
+        debug::DebugRegister, test::MockMemory,
+    };
+
+    fn registers(stack_pointer: u32, link_register: u32, program_counter: u32) -> DebugRegisters {
+        let mut values = [0u32; 16];
+        values[STACK_POINTER] = stack_pointer;
+        values[LINK_REGISTER] = link_register;
+        values[PROGRAM_COUNTER] = program_counter;
+
+        DebugRegisters(
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(id, value)| DebugRegister {
+                    dwarf_id: Some(id as u16),
+                    core_register: CORTEX_M_CORE_REGISTERS.core_register(id),
+                    value: Some(RegisterValue::U32(value)),
+                })
+                .collect(),
+        )
+    }
+
+    fn value_of(registers: &DebugRegisters, dwarf_id: usize) -> Option<u32> {
+        registers
+            .get_register_by_dwarf_id(dwarf_id as u16)
+            .and_then(|register| register.value)
+            .map(|value| value.try_into().unwrap())
+    }
+
+    fn code(halfwords: &[u16]) -> Vec<u8> {
+        halfwords.iter().flat_map(|h| h.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn thumb_expand_immediate() {
+        // sub.w sp, sp, #0x208
+        assert_eq!(thumb_expand_imm(0xf5ad, 0x7d02), 0x208);
+        // sub.w sp, sp, #0x10
+        assert_eq!(thumb_expand_imm(0xf1ad, 0x0d10), 0x10);
+    }
+
+    #[test]
+    fn prologue_with_push_and_sub() {
+        let effect = analyze_prologue(&[
+            0xb5b0, // push {r4, r5, r7, lr}
+            0xaf02, // add r7, sp, #8
+            0xb084, // sub sp, #16
+        ]);
+
+        assert_eq!(effect.stack_size, 32);
+        assert_eq!(effect.saved_registers[4], Some(16));
+        assert_eq!(effect.saved_registers[5], Some(12));
+        assert_eq!(effect.saved_registers[7], Some(8));
+        assert_eq!(effect.saved_registers[LINK_REGISTER], Some(4));
+    }
+
+    #[test]
+    fn prologue_with_wide_instructions() {
+        let effect = analyze_prologue(&[
+            0xe92d, 0x4ff0, // push.w {r4-r11, lr}
+            0xed2d, 0x8b02, // vpush {d8}
+            0xf5ad, 0x7d02, // sub.w sp, sp, #0x208
+        ]);
+
+        assert_eq!(effect.stack_size, 36 + 8 + 0x208);
+        assert_eq!(effect.saved_registers[4], Some(36));
+        assert_eq!(effect.saved_registers[11], Some(8));
+        assert_eq!(effect.saved_registers[LINK_REGISTER], Some(4));
+    }
+
+    #[test]
+    fn unwind_with_known_function_start() {
+        let mut memory = MockMemory::new();
+        // push {r4, r7, lr} ; sub sp, #8 ; bl ...
+        memory.add_range(0x100, code(&[0xb590, 0xb082, 0xf000, 0xf800]));
+        memory.add_word_range(0x2000_0ff0, &[0, 0, 0x44, 0x2000_1010, 0x0000_0235]);
+
+        let callee = registers(0x2000_0ff0, 0xffff_ffff, 0x0000_0108);
+        let caller = unwind_thumb_prologue(&callee, Some(0x101), &mut memory, false)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(value_of(&caller, 4), Some(0x44));
+        assert_eq!(value_of(&caller, 7), Some(0x2000_1010));
+        assert_eq!(value_of(&caller, STACK_POINTER), Some(0x2000_1004));
+        assert_eq!(value_of(&caller, LINK_REGISTER), Some(0x0000_0235));
+        assert_eq!(value_of(&caller, PROGRAM_COUNTER), Some(0x0000_0234));
+    }
+
+    #[test]
+    fn unwind_searches_for_function_start() {
+        let mut memory = MockMemory::new();
+        // nop ... ; push {r7, lr} ; mov r7, sp ; nop
+        let mut function = vec![0xbf00; 0x7f];
+        function.extend_from_slice(&[0xb580, 0x466f, 0xbf00, 0xbf00]);
+        memory.add_range(0x100, code(&function));
+        memory.add_word_range(0x2000_0ff8, &[0x2000_1000, 0x0000_0301]);
+
+        let callee = registers(0x2000_0ff8, 0x0000_0155, 0x0000_0204);
+        let caller = unwind_thumb_prologue(&callee, None, &mut memory, false)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(value_of(&caller, STACK_POINTER), Some(0x2000_1000));
+        assert_eq!(value_of(&caller, PROGRAM_COUNTER), Some(0x0000_0300));
+    }
+
+    #[test]
+    fn leaf_function_uses_link_register_only_in_innermost_frame() {
+        let mut memory = MockMemory::new();
+        // movs r0, #0 ; adds r0, #1 ; bx lr
+        memory.add_range(0x100, code(&[0x2000, 0x3001, 0x4770]));
+
+        let callee = registers(0x2000_0ff8, 0x0000_0155, 0x0000_0102);
+        let caller = unwind_thumb_prologue(&callee, Some(0x100), &mut memory, true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(value_of(&caller, STACK_POINTER), Some(0x2000_0ff8));
+        assert_eq!(value_of(&caller, PROGRAM_COUNTER), Some(0x0000_0154));
+
+        assert!(
+            unwind_thumb_prologue(&callee, Some(0x100), &mut memory, false)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
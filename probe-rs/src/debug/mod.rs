@@ -5,12 +5,16 @@
 //! The `debug` module contains various debug functionality, which can be
 //! used to implement a debugger based on `probe-rs`.
 
+/// Stack unwinding using the ARM exception handling ABI tables.
+pub(crate) mod arm_ehabi;
 /// Debug information which is parsed from DWARF debugging information.
 pub mod debug_info;
 /// Stepping through a program during debug, at various granularities.
 pub mod debug_step;
 /// References to the DIE (debug information entry) of functions.
 pub mod function_die;
+/// Heuristic stack unwinding, based on the analysis of function prologues.
+pub(crate) mod heuristic_unwind;
 /// Programming languages
 pub(crate) mod language;
 /// Target Register definitions, expanded from [`crate::core::registers::CoreRegister`] to include unwind specific information.
@@ -28,8 +32,8 @@ pub mod variable_cache;
 
 pub use self::{
     debug_info::*, debug_step::SteppingMode, registers::*, source_instructions::SourceLocation,
-    source_instructions::VerifiedBreakpoint, stack_frame::StackFrame, variable::*,
-    variable_cache::VariableCache,
+    source_instructions::VerifiedBreakpoint, stack_frame::StackFrame, stack_frame::UnwindMethod,
+    variable::*, variable_cache::VariableCache,
 };
 use crate::{core::Core, MemoryInterface};
 
@@ -200,6 +200,16 @@ impl DebugRegisters {
             .find(|debug_register| debug_register.dwarf_id == Some(dwarf_id))
     }
 
+    /// Get a mutable reference to a register using its DWARF register number.
+    pub(crate) fn get_register_mut_by_dwarf_id(
+        &mut self,
+        dwarf_id: u16,
+    ) -> Option<&mut DebugRegister> {
+        self.0
+            .iter_mut()
+            .find(|debug_register| debug_register.dwarf_id == Some(dwarf_id))
+    }
+
     /// Retrieve the role name if it exists, else the actual name using the [`RegisterId`] as an identifier.
     pub fn get_register_name(&self, register_id: RegisterId) -> String {
         self.0
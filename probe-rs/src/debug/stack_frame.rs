@@ -47,6 +47,43 @@ pub struct StackFrame {
     pub local_variables: Option<VariableCache>,
     /// The value of the stack pointer just before the CALL instruction in the parent function.
     pub canonical_frame_address: Option<u64>,
+    /// The method that was used to determine the register values of this stack frame.
+    pub unwind_method: UnwindMethod,
+}
+
+/// The method that was used to determine the register values of a [`StackFrame`].
+///
+/// Unwinding prefers the [DWARF](https://dwarfstd.org) call frame information in `.debug_frame`,
+/// and falls back to the other methods, in the order listed here, if it is not available for a function.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize)]
+pub enum UnwindMethod {
+    /// The register values were read from the core, i.e. this is the innermost stack frame.
+    #[default]
+    CoreRegisters,
+    /// The register values were unwound using the call frame information in the `.debug_frame` section.
+    DebugFrame,
+    /// The register values were unwound using the call frame information in the `.eh_frame` section.
+    EhFrame,
+    /// The register values were unwound using the ARM exception handling ABI tables in the `.ARM.exidx` and `.ARM.extab` sections.
+    ArmExidx,
+    /// The register values were restored from the context that the processor saved on exception entry.
+    ExceptionFrame,
+    /// The register values were unwound by analyzing the function prologue, or by assuming that the return address is still in the link register.
+    /// The results are less reliable than for the other methods.
+    Heuristic,
+}
+
+impl std::fmt::Display for UnwindMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            UnwindMethod::CoreRegisters => write!(f, "core registers"),
+            UnwindMethod::DebugFrame => write!(f, ".debug_frame"),
+            UnwindMethod::EhFrame => write!(f, ".eh_frame"),
+            UnwindMethod::ArmExidx => write!(f, ".ARM.exidx"),
+            UnwindMethod::ExceptionFrame => write!(f, "exception frame"),
+            UnwindMethod::Heuristic => write!(f, "heuristic"),
+        }
+    }
 }
 
 impl std::fmt::Display for StackFrame {
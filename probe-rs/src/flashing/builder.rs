@@ -141,6 +141,115 @@ impl FlashLayout {
     pub fn visualize(&self) -> FlashVisualizer {
         FlashVisualizer::new(self)
     }
+
+    /// Returns the contents `sector` will have once it has been erased and all pages are programmed.
+    pub(super) fn sector_contents(&self, sector: &FlashSector, erased_byte_value: u8) -> Vec<u8> {
+        let sector_range = sector.address..sector.address + sector.size;
+        let mut contents = vec![erased_byte_value; sector.size as usize];
+
+        for page in &self.pages {
+            let page_range = page.address..page.address + page.size() as u64;
+            let start = page_range.start.max(sector_range.start);
+            let end = page_range.end.min(sector_range.end);
+            if start >= end {
+                continue;
+            }
+
+            contents[(start - sector_range.start) as usize..(end - sector_range.start) as usize]
+                .copy_from_slice(
+                    &page.data
+                        [(start - page_range.start) as usize..(end - page_range.start) as usize],
+                );
+        }
+
+        contents
+    }
+
+    /// Removes the sectors selected by `skip` from the layout, together with all pages in them,
+    /// so that they are neither erased nor programmed.
+    ///
+    /// A page that spans several sectors can only be programmed if all of them are erased,
+    /// so a selected sector is kept if it shares a page with a sector that is not skipped.
+    ///
+    /// Returns the sectors which were removed.
+    pub(super) fn skip_sectors(
+        &mut self,
+        mut skip: impl FnMut(&FlashSector) -> bool,
+    ) -> Vec<FlashSector> {
+        let mut skipped = self.sectors.iter().map(&mut skip).collect::<Vec<_>>();
+
+        let sector_ranges = self
+            .sectors
+            .iter()
+            .map(|s| s.address..s.address + s.size)
+            .collect::<Vec<_>>();
+        let page_ranges = self
+            .pages
+            .iter()
+            .map(|p| p.address..p.address + p.size() as u64)
+            .collect::<Vec<_>>();
+
+        // Keeping a sector can force other sectors to be kept, so repeat until nothing changes.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for page_range in &page_ranges {
+                let overlapping = sector_ranges
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, sector_range)| sector_range.intersects_range(page_range))
+                    .map(|(index, _)| index)
+                    .collect::<Vec<_>>();
+
+                if overlapping.iter().any(|&index| !skipped[index]) {
+                    for index in overlapping {
+                        changed |= std::mem::replace(&mut skipped[index], false);
+                    }
+                }
+            }
+        }
+
+        let is_skipped = |range: &Range<u64>| {
+            sector_ranges
+                .iter()
+                .zip(&skipped)
+                .any(|(sector_range, &skipped)| skipped && sector_range.intersects_range(range))
+        };
+
+        // Remove the pages in skipped sectors, and the fills of those pages.
+        let mut page_indices = Vec::with_capacity(self.pages.len());
+        let mut pages = Vec::with_capacity(self.pages.len());
+        for (page, page_range) in self.pages.drain(..).zip(&page_ranges) {
+            if is_skipped(page_range) {
+                page_indices.push(None);
+            } else {
+                page_indices.push(Some(pages.len()));
+                pages.push(page);
+            }
+        }
+        self.pages = pages;
+
+        self.fills
+            .retain_mut(|fill| match page_indices[fill.page_index] {
+                Some(page_index) => {
+                    fill.page_index = page_index;
+                    true
+                }
+                None => false,
+            });
+
+        let mut removed = Vec::new();
+        let mut skipped = skipped.into_iter();
+        self.sectors.retain(|sector| {
+            let skip = skipped.next().unwrap_or(false);
+            if skip {
+                removed.push(sector.clone());
+            }
+            !skip
+        });
+
+        removed
+    }
 }
 
 /// A block of data that is to be written to flash.
@@ -1147,4 +1256,105 @@ mod tests {
             }
         )
     }
+
+    #[test]
+    fn sector_contents_include_pages_and_erased_bytes() {
+        let (region, flash_algorithm) = assemble_demo_flash1();
+        let mut flash_builder = FlashBuilder::new();
+        flash_builder.add_data(0x402, &[1, 2, 3]).unwrap();
+        let flash_layout = flash_builder
+            .build_sectors_and_pages(&region, &flash_algorithm, false)
+            .unwrap();
+
+        let contents = flash_layout.sector_contents(&flash_layout.sectors()[0], 0xff);
+
+        let mut expected = vec![0xff; 4096];
+        expected[0x402..0x405].copy_from_slice(&[1, 2, 3]);
+        assert_eq!(contents, expected);
+    }
+
+    #[test]
+    fn skip_sectors_removes_pages_and_fills() {
+        let (region, flash_algorithm) = assemble_demo_flash1();
+        let mut flash_builder = FlashBuilder::new();
+        flash_builder.add_data(0x0010, &[42; 16]).unwrap();
+        flash_builder.add_data(0x1010, &[42; 16]).unwrap();
+        let mut flash_layout = flash_builder
+            .build_sectors_and_pages(&region, &flash_algorithm, true)
+            .unwrap();
+
+        let skipped = flash_layout.skip_sectors(|sector| sector.address() == 0);
+
+        assert_eq!(
+            skipped,
+            vec![FlashSector {
+                address: 0x0000,
+                size: 0x1000,
+            }]
+        );
+        assert_eq!(
+            flash_layout.sectors(),
+            &[FlashSector {
+                address: 0x1000,
+                size: 0x1000,
+            }]
+        );
+        assert_eq!(
+            flash_layout
+                .pages()
+                .iter()
+                .map(|p| p.address())
+                .collect::<Vec<_>>(),
+            vec![0x1000, 0x1400, 0x1800, 0x1C00]
+        );
+        assert_eq!(
+            flash_layout.fills(),
+            &[
+                FlashFill {
+                    address: 0x1000,
+                    size: 0x10,
+                    page_index: 0,
+                },
+                FlashFill {
+                    address: 0x1020,
+                    size: 0x3E0,
+                    page_index: 0,
+                },
+                FlashFill {
+                    address: 0x1400,
+                    size: 0x400,
+                    page_index: 1,
+                },
+                FlashFill {
+                    address: 0x1800,
+                    size: 0x400,
+                    page_index: 2,
+                },
+                FlashFill {
+                    address: 0x1C00,
+                    size: 0x400,
+                    page_index: 3,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn skip_sectors_keeps_sectors_sharing_a_page() {
+        let (region, flash_algorithm) = assemble_demo_flash2();
+        let mut flash_builder = FlashBuilder::new();
+        flash_builder.add_data(0x0000, &[42; 16]).unwrap();
+        flash_builder.add_data(0x0100, &[42; 16]).unwrap();
+        let mut flash_layout = flash_builder
+            .build_sectors_and_pages(&region, &flash_algorithm, false)
+            .unwrap();
+        let sector_count = flash_layout.sectors().len();
+
+        // Only the second sector with data differs, but it shares the page with the first one.
+        let skipped = flash_layout.skip_sectors(|sector| sector.address() != 0x0100);
+
+        assert!(skipped.is_empty());
+        assert_eq!(flash_layout.sectors().len(), sector_count);
+        assert_eq!(flash_layout.pages().len(), 1);
+    }
 }
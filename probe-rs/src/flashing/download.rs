@@ -6,6 +6,7 @@ use probe_rs_target::{InstructionSet, MemoryRange};
 
 use std::{
     fs::File,
+    ops::Range,
     path::{Path, PathBuf},
     str::FromStr,
 };
@@ -48,6 +49,10 @@ pub enum Format {
     Idf(IdfOptions),
     /// Marks a file in the [UF2](https://github.com/microsoft/uf2) format.
     Uf2,
+    /// Marks a file in the [Motorola S-record](https://en.wikipedia.org/wiki/SREC_(file_format)) format.
+    Srec,
+    /// Marks a file in the TI-TXT format used by Texas Instruments toolchains.
+    TiTxt,
 }
 
 impl FromStr for Format {
@@ -63,6 +68,8 @@ impl FromStr for Format {
             "hex" | "ihex" | "intelhex" => Ok(Format::Hex),
             "elf" => Ok(Format::Elf),
             "uf2" => Ok(Format::Uf2),
+            "srec" | "s-record" | "s19" | "s28" | "s37" | "mot" => Ok(Format::Srec),
+            "titxt" | "ti-txt" => Ok(Format::TiTxt),
             _ => Err(format!("Format '{s}' is unknown.")),
         }
     }
@@ -83,6 +90,12 @@ pub enum FileDownloadError {
     /// Failed to read or decode the IHEX file.
     IhexRead(#[from] ihex::ReaderError),
 
+    /// Failed to read the S-record file.
+    Srec(#[from] SrecError),
+
+    /// Failed to read the TI-TXT file.
+    TiTxt(#[from] TiTxtError),
+
     /// An IO error has occurred while reading the firmware file.
     IO(#[from] std::io::Error),
 
@@ -104,6 +117,16 @@ pub enum FileDownloadError {
     /// This is most likely because of a bad linker script.
     NoLoadableSegments,
 
+    /// Image {image} overlaps with image {existing_image} at {addresses:#010x?}.
+    ImageOverlaps {
+        /// The index of the image which was loaded last, starting at 0.
+        image: usize,
+        /// The index of the previously loaded image it overlaps with.
+        existing_image: usize,
+        /// The overlapping addresses.
+        addresses: Range<u64>,
+    },
+
     /// Could not determine flash size.
     FlashSizeDetection(#[from] crate::Error),
 
@@ -157,10 +180,19 @@ pub struct DownloadOptions {
     /// If the chip was pre-erased with external erasers, this flag can set to true to skip erasing
     /// It may be useful for mass production.
     pub skip_erase: bool,
-    /// After flashing, read back all the flashed data to verify it has been written correctly.
+    /// After flashing, verify that all the data has been written correctly.
+    ///
+    /// Where possible, this compares CRCs calculated by a small routine running on the target,
+    /// otherwise all the flashed data is read back.
     pub verify: bool,
     /// Disable double buffering when loading flash.
     pub disable_double_buffering: bool,
+    /// Compare the current flash contents first, and only erase and program the sectors
+    /// whose contents differ from the data to be flashed.
+    ///
+    /// This speeds up flashing an image which only changed in a few places.
+    /// It has no effect if a chip erase is done.
+    pub skip_unchanged_sectors: bool,
 }
 
 impl DownloadOptions {
@@ -373,6 +405,11 @@ mod tests {
                 skip: 0
             }))
         );
+        assert_eq!(Format::from_str("srec"), Ok(Format::Srec));
+        assert_eq!(Format::from_str("S19"), Ok(Format::Srec));
+        assert_eq!(Format::from_str("mot"), Ok(Format::Srec));
+        assert_eq!(Format::from_str("TI-TXT"), Ok(Format::TiTxt));
+        assert_eq!(Format::from_str("titxt"), Ok(Format::TiTxt));
         assert_eq!(Format::from_str("Elf"), Ok(Format::Elf));
         assert_eq!(Format::from_str("elf"), Ok(Format::Elf));
         assert_eq!(
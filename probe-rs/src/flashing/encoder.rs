@@ -28,73 +28,21 @@ impl EncoderAlgorithm for RawEncoder {
     }
 }
 
-/// Miniz-encoder.
+/// Compressing encoder.
 ///
 /// The encoder will break up the flash contents into contiguous images, compress each of them
 /// separately and it will output flash pages with the *start address* of the contiguous image.
 ///
 /// The flash loader that accepts this format must be able to track the offset in the current image.
 /// The end of an image is signaled by the first non-full page. This may include an empty page.
-struct MinizEncoder {
+struct CompressingEncoder {
     flash: FlashLayout,
     compressed_pages: Vec<FlashPage>,
 }
 
-impl MinizEncoder {
-    fn new(flash: FlashLayout) -> Self {
-        let mut compressed_pages = vec![];
-
-        let page_size = flash.pages()[0].data().len();
-
-        let mut compress_image = |image: &[u8], start_addr: u64| {
-            if image.is_empty() {
-                return;
-            }
-            // This page is not contiguous with the previous one, finish the previous image.
-            let compressed = miniz_oxide::deflate::compress_to_vec_zlib(image, 9);
-
-            let image_len = compressed.len();
-            // We chunk up the image and prepend the compressed image's length to the first chunk.
-            let first_chunk_len = image_len.min(page_size - 4);
-            let (first, rest) = compressed.split_at(first_chunk_len);
-
-            let first_chunk = (image_len as u32)
-                .to_le_bytes()
-                .into_iter()
-                .chain(first.iter().copied())
-                .collect::<Vec<u8>>();
-
-            // We add each page with `start_addr`. The address identifies the image and the
-            // flash loader is responsible for tracking the write offset in the current image.
-            compressed_pages.push(FlashPage {
-                address: start_addr,
-                data: first_chunk,
-            });
-
-            for chunk in rest.chunks(page_size) {
-                compressed_pages.push(FlashPage {
-                    address: start_addr,
-                    data: chunk.to_vec(),
-                });
-            }
-        };
-
-        // (start_addr, compressed_image)
-        let mut previous_image = vec![];
-        let mut previous_start_addr = 0;
-
-        for page in flash.pages() {
-            if page.address() != previous_start_addr + previous_image.len() as u64 {
-                compress_image(&previous_image, previous_start_addr);
-
-                previous_image.clear();
-                previous_start_addr = page.address();
-            }
-
-            previous_image.extend_from_slice(page.data());
-        }
-
-        compress_image(&previous_image, previous_start_addr);
+impl CompressingEncoder {
+    fn new(flash: FlashLayout, compress: fn(&[u8]) -> Vec<u8>) -> Self {
+        let compressed_pages = compress_pages(flash.pages(), compress);
 
         tracing::debug!(
             "Compressed/original: {}/{}",
@@ -109,7 +57,7 @@ impl MinizEncoder {
     }
 }
 
-impl EncoderAlgorithm for MinizEncoder {
+impl EncoderAlgorithm for CompressingEncoder {
     fn pages(&self) -> &[FlashPage] {
         &self.compressed_pages
     }
@@ -119,6 +67,169 @@ impl EncoderAlgorithm for MinizEncoder {
     }
 }
 
+/// Compresses each contiguous image in `pages`, and splits the compressed images into pages
+/// addressed with the start address of their image.
+fn compress_pages(pages: &[FlashPage], compress: fn(&[u8]) -> Vec<u8>) -> Vec<FlashPage> {
+    let mut compressed_pages = vec![];
+
+    let Some(page_size) = pages.first().map(|page| page.data().len()) else {
+        return compressed_pages;
+    };
+
+    let mut compress_image = |image: &[u8], start_addr: u64| {
+        if image.is_empty() {
+            return;
+        }
+        // This page is not contiguous with the previous one, finish the previous image.
+        let compressed = compress(image);
+
+        let image_len = compressed.len();
+        // We chunk up the image and prepend the compressed image's length to the first chunk.
+        let first_chunk_len = image_len.min(page_size - 4);
+        let (first, rest) = compressed.split_at(first_chunk_len);
+
+        let first_chunk = (image_len as u32)
+            .to_le_bytes()
+            .into_iter()
+            .chain(first.iter().copied())
+            .collect::<Vec<u8>>();
+
+        // We add each page with `start_addr`. The address identifies the image and the
+        // flash loader is responsible for tracking the write offset in the current image.
+        compressed_pages.push(FlashPage {
+            address: start_addr,
+            data: first_chunk,
+        });
+
+        for chunk in rest.chunks(page_size) {
+            compressed_pages.push(FlashPage {
+                address: start_addr,
+                data: chunk.to_vec(),
+            });
+        }
+    };
+
+    // (start_addr, compressed_image)
+    let mut previous_image = vec![];
+    let mut previous_start_addr = 0;
+
+    for page in pages {
+        if page.address() != previous_start_addr + previous_image.len() as u64 {
+            compress_image(&previous_image, previous_start_addr);
+
+            previous_image.clear();
+            previous_start_addr = page.address();
+        }
+
+        previous_image.extend_from_slice(page.data());
+    }
+
+    compress_image(&previous_image, previous_start_addr);
+
+    compressed_pages
+}
+
+fn compress_miniz(image: &[u8]) -> Vec<u8> {
+    miniz_oxide::deflate::compress_to_vec_zlib(image, 9)
+}
+
+fn compress_lz4(image: &[u8]) -> Vec<u8> {
+    lz4_flex::block::compress(image)
+}
+
+/// A heatshrink compressor, see <https://github.com/atomicobject/heatshrink>.
+///
+/// The output is a bit stream, most significant bit first. Each item starts with a tag bit.
+/// A set tag bit is followed by a literal byte, a cleared one by a back reference, consisting of
+/// the distance to the referenced bytes minus one, and the number of bytes minus one.
+/// The last byte is padded with zeros.
+mod heatshrink {
+    /// The number of bits of the distance of a back reference, the window size is `2^WINDOW_BITS`.
+    pub(super) const WINDOW_BITS: u32 = 8;
+    /// The number of bits of the length of a back reference.
+    pub(super) const LOOKAHEAD_BITS: u32 = 4;
+
+    const MAX_DISTANCE: usize = 1 << WINDOW_BITS;
+    const MAX_LENGTH: usize = 1 << LOOKAHEAD_BITS;
+    /// Back references up to this length are not shorter than literals.
+    const BREAK_EVEN: usize = (1 + WINDOW_BITS + LOOKAHEAD_BITS) as usize / 8;
+
+    pub(super) fn compress(data: &[u8]) -> Vec<u8> {
+        let mut output = BitWriter::default();
+
+        let mut position = 0;
+        while position < data.len() {
+            let (distance, length) = longest_match(data, position);
+
+            if length > BREAK_EVEN {
+                output.write(0, 1);
+                output.write(distance as u32 - 1, WINDOW_BITS);
+                output.write(length as u32 - 1, LOOKAHEAD_BITS);
+                position += length;
+            } else {
+                output.write(1, 1);
+                output.write(data[position] as u32, 8);
+                position += 1;
+            }
+        }
+
+        output.finish()
+    }
+
+    /// Returns the distance and length of the longest match for the data at `position`.
+    ///
+    /// Matches may overlap `position`, as decoders copy back references byte by byte.
+    fn longest_match(data: &[u8], position: usize) -> (usize, usize) {
+        let max_length = MAX_LENGTH.min(data.len() - position);
+
+        let mut best = (0, 0);
+        for distance in 1..=MAX_DISTANCE.min(position) {
+            let start = position - distance;
+            let length = (0..max_length)
+                .take_while(|&i| data[start + i] == data[position + i])
+                .count();
+
+            if length > best.1 {
+                best = (distance, length);
+                if length == max_length {
+                    break;
+                }
+            }
+        }
+
+        best
+    }
+
+    #[derive(Default)]
+    struct BitWriter {
+        bytes: Vec<u8>,
+        current: u8,
+        bits: u32,
+    }
+
+    impl BitWriter {
+        fn write(&mut self, value: u32, count: u32) {
+            for bit in (0..count).rev() {
+                self.current = self.current << 1 | (value >> bit & 1) as u8;
+                self.bits += 1;
+
+                if self.bits == 8 {
+                    self.bytes.push(self.current);
+                    self.current = 0;
+                    self.bits = 0;
+                }
+            }
+        }
+
+        fn finish(mut self) -> Vec<u8> {
+            if self.bits > 0 {
+                self.bytes.push(self.current << (8 - self.bits));
+            }
+            self.bytes
+        }
+    }
+}
+
 pub struct FlashEncoder {
     encoder: Box<dyn EncoderAlgorithm>,
 }
@@ -128,7 +239,11 @@ impl FlashEncoder {
         Self {
             encoder: match encoding {
                 TransferEncoding::Raw => Box::new(RawEncoder::new(flash)),
-                TransferEncoding::Miniz => Box::new(MinizEncoder::new(flash)),
+                TransferEncoding::Miniz => Box::new(CompressingEncoder::new(flash, compress_miniz)),
+                TransferEncoding::Lz4 => Box::new(CompressingEncoder::new(flash, compress_lz4)),
+                TransferEncoding::Heatshrink => {
+                    Box::new(CompressingEncoder::new(flash, heatshrink::compress))
+                }
             },
         }
     }
@@ -141,3 +256,208 @@ impl FlashEncoder {
         self.encoder.sectors()
     }
 }
+
+#[cfg(test)]
+mod test {
+    //! Reference decoders for the compressed transfer encodings.
+    //!
+    //! They are kept simple on purpose, so they can serve as a model for the
+    //! decompression in a flash algorithm.
+
+    use super::*;
+
+    /// Decodes an LZ4 block.
+    fn decode_lz4(input: &[u8]) -> Vec<u8> {
+        fn read_length(input: &[u8], position: &mut usize, mut length: usize) -> usize {
+            if length == 15 {
+                loop {
+                    let byte = input[*position];
+                    *position += 1;
+                    length += byte as usize;
+                    if byte != 255 {
+                        break;
+                    }
+                }
+            }
+            length
+        }
+
+        let mut output = vec![];
+        let mut position = 0;
+        loop {
+            let token = input[position];
+            position += 1;
+
+            let literals = read_length(input, &mut position, (token >> 4) as usize);
+            output.extend_from_slice(&input[position..position + literals]);
+            position += literals;
+
+            // The last sequence only contains literals.
+            if position == input.len() {
+                return output;
+            }
+
+            let distance = u16::from_le_bytes([input[position], input[position + 1]]) as usize;
+            position += 2;
+            let length = read_length(input, &mut position, (token & 0xf) as usize) + 4;
+
+            for _ in 0..length {
+                output.push(output[output.len() - distance]);
+            }
+        }
+    }
+
+    /// Decodes heatshrink data, compressed with the window and lookahead sizes of the encoder.
+    fn decode_heatshrink(input: &[u8]) -> Vec<u8> {
+        let mut bit_position = 0;
+        let mut read = |count: u32| -> Option<u32> {
+            if bit_position + count as usize > input.len() * 8 {
+                return None;
+            }
+            let mut value = 0;
+            for _ in 0..count {
+                let bit = input[bit_position / 8] >> (7 - bit_position % 8) & 1;
+                value = value << 1 | bit as u32;
+                bit_position += 1;
+            }
+            Some(value)
+        };
+
+        let mut output = vec![];
+        // The padding at the end is too short for a complete literal or back reference.
+        while let Some(tag) = read(1) {
+            if tag == 1 {
+                let Some(literal) = read(8) else { break };
+                output.push(literal as u8);
+            } else {
+                let Some(distance) = read(heatshrink::WINDOW_BITS) else {
+                    break;
+                };
+                let Some(length) = read(heatshrink::LOOKAHEAD_BITS) else {
+                    break;
+                };
+                for _ in 0..=length {
+                    output.push(output[output.len() - distance as usize - 1]);
+                }
+            }
+        }
+        output
+    }
+
+    fn decode_miniz(input: &[u8]) -> Vec<u8> {
+        miniz_oxide::inflate::decompress_to_vec_zlib(input).unwrap()
+    }
+
+    /// Two contiguous images, made of 0x400 byte pages.
+    fn pages() -> Vec<FlashPage> {
+        let data = |seed: u32, len: usize| {
+            (0..len as u32)
+                .map(|i| {
+                    if i % 64 < 40 {
+                        (i / 3) as u8
+                    } else {
+                        (i * seed) as u8 ^ 0x5a
+                    }
+                })
+                .collect::<Vec<u8>>()
+        };
+
+        let first = data(7, 0xc00);
+        let second = data(13, 0x400);
+
+        first
+            .chunks(0x400)
+            .enumerate()
+            .map(|(i, chunk)| FlashPage {
+                address: 0x1000 + 0x400 * i as u64,
+                data: chunk.to_vec(),
+            })
+            .chain([FlashPage {
+                address: 0x8000,
+                data: second,
+            }])
+            .collect()
+    }
+
+    /// Reassembles the images from the compressed pages, and decodes them.
+    fn decode_pages(pages: &[FlashPage], decode: fn(&[u8]) -> Vec<u8>) -> Vec<(u64, Vec<u8>)> {
+        let mut images = Vec::<(u64, Vec<u8>)>::new();
+        for page in pages {
+            match images.last_mut() {
+                Some((address, data)) if *address == page.address() => {
+                    data.extend_from_slice(page.data())
+                }
+                _ => images.push((page.address(), page.data().to_vec())),
+            }
+        }
+
+        images
+            .into_iter()
+            .map(|(address, data)| {
+                let (length, compressed) = data.split_at(4);
+                let length = u32::from_le_bytes(length.try_into().unwrap()) as usize;
+                assert_eq!(compressed.len(), length);
+                (address, decode(compressed))
+            })
+            .collect()
+    }
+
+    fn assert_round_trip(compress: fn(&[u8]) -> Vec<u8>, decode: fn(&[u8]) -> Vec<u8>) {
+        let pages = pages();
+        let compressed = compress_pages(&pages, compress);
+
+        assert!(compressed.iter().all(|page| page.size() <= 0x400));
+        assert!(
+            compressed.iter().map(|p| p.size()).sum::<u32>()
+                < pages.iter().map(|p| p.size()).sum::<u32>()
+        );
+
+        let expected_first = pages[..3]
+            .iter()
+            .flat_map(|page| page.data().iter().copied())
+            .collect::<Vec<u8>>();
+        assert_eq!(
+            decode_pages(&compressed, decode),
+            [(0x1000, expected_first), (0x8000, pages[3].data().to_vec())]
+        );
+    }
+
+    #[test]
+    fn miniz_round_trip() {
+        assert_round_trip(compress_miniz, decode_miniz);
+    }
+
+    #[test]
+    fn lz4_round_trip() {
+        assert_round_trip(compress_lz4, decode_lz4);
+    }
+
+    #[test]
+    fn heatshrink_round_trip() {
+        assert_round_trip(heatshrink::compress, decode_heatshrink);
+    }
+
+    #[test]
+    fn heatshrink_small_inputs() {
+        for data in [
+            &b""[..],
+            b"a",
+            b"aa",
+            b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            b"abcabcabcabd",
+        ] {
+            assert_eq!(decode_heatshrink(&heatshrink::compress(data)), data);
+        }
+    }
+
+    #[test]
+    fn heatshrink_reference_output() {
+        // Three literals, followed by a back reference to 6 bytes, starting 3 bytes back.
+        //
+        // 1 01100001 1 01100010 1 01100011 0 00000010 0101
+        assert_eq!(
+            heatshrink::compress(b"abcabcabc"),
+            [0xb0, 0xd8, 0xac, 0x60, 0x25]
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use super::FlashError;
-use crate::{architecture::riscv, core::Architecture, Target};
+use crate::{architecture::riscv, core::Architecture, InstructionSet, Target};
 use probe_rs_target::{
     FlashProperties, PageInfo, RamRegion, RawFlashAlgorithm, SectorInfo, TransferEncoding,
 };
@@ -51,6 +51,11 @@ pub struct FlashAlgorithm {
 
     /// The encoding format accepted by the flash algorithm.
     pub transfer_encoding: TransferEncoding,
+
+    /// Address of the argument block passed to `Init()`, if the algorithm has init arguments.
+    ///
+    /// See [`RawFlashAlgorithm::init_arguments`] for the layout of the block.
+    pub init_arguments: Option<u64>,
 }
 
 impl FlashAlgorithm {
@@ -178,6 +183,55 @@ impl FlashAlgorithm {
 
     const XTENSA_FLASH_BLOB_HEADER: [u32; 0] = [];
 
+    // Position-independent routines which update a CRC32 (IEEE 802.3) over a memory range.
+    //
+    // They are called with the start address, the length in bytes and the running CRC,
+    // and return the updated CRC. The running CRC is not inverted on entry or exit.
+
+    // Thumb, also runs on ARMv6-M.
+    const ARM_CRC32_ROUTINE: [u32; 9] = [
+        0x2900_4B07, // ldr r3, poly; loop: cmp r1, #0
+        0x7804_D00A, // beq done; ldrb r4, [r0]
+        0x2408_4062, // eors r2, r4; movs r4, #8
+        0xD300_0852, // bit: lsrs r2, r2, #1; bcc skip
+        0x3C01_405A, // eors r2, r3; skip: subs r4, #1
+        0x3001_D1FA, // bne bit; adds r0, #1
+        0xE7F2_3901, // subs r1, #1; b loop
+        0x4770_0010, // done: movs r0, r2; bx lr
+        0xEDB8_8320, // poly
+    ];
+
+    // RV32I, without compressed instructions.
+    const RISCV_CRC32_ROUTINE: [u32; 17] = [
+        0xEDB8_82B7, // lui t0, 0xedb88
+        0x3202_8293, // addi t0, t0, 0x320
+        0x0205_8A63, // loop: beqz a1, done
+        0x0005_4303, // lbu t1, 0(a0)
+        0x0066_4633, // xor a2, a2, t1
+        0x0080_0393, // li t2, 8
+        0x0016_7313, // bit: andi t1, a2, 1
+        0x0016_5613, // srli a2, a2, 1
+        0x0003_0463, // beqz t1, skip
+        0x0056_4633, // xor a2, a2, t0
+        0xFFF3_8393, // skip: addi t2, t2, -1
+        0xFE03_96E3, // bnez t2, bit
+        0x0015_0513, // addi a0, a0, 1
+        0xFFF5_8593, // addi a1, a1, -1
+        0xFD1F_F06F, // j loop
+        0x0006_0513, // done: mv a0, a2
+        0x0000_8067, // ret
+    ];
+
+    /// Returns the routine which calculates a CRC32 on a core with the given instruction set,
+    /// if there is one.
+    pub(super) fn crc32_routine(instruction_set: InstructionSet) -> Option<&'static [u32]> {
+        match instruction_set {
+            InstructionSet::Thumb2 => Some(&Self::ARM_CRC32_ROUTINE),
+            InstructionSet::RV32 | InstructionSet::RV32C => Some(&Self::RISCV_CRC32_ROUTINE),
+            InstructionSet::A32 | InstructionSet::A64 | InstructionSet::Xtensa => None,
+        }
+    }
+
     /// When the target architecture is not known, and we need to allocate space for the header,
     /// this function returns the maximum size of the header of supported architectures.
     pub fn get_max_algorithm_header_size() -> u64 {
@@ -244,7 +298,7 @@ impl FlashAlgorithm {
         };
 
         let header = Self::algorithm_header(target.architecture());
-        let instructions: Vec<u32> = header
+        let mut instructions: Vec<u32> = header
             .iter()
             .copied()
             .chain(
@@ -253,6 +307,17 @@ impl FlashAlgorithm {
             .chain(last_elem)
             .collect();
 
+        // The init arguments are placed directly behind the code, as a word containing
+        // their count followed by the arguments themselves.
+        let init_arguments_offset = if raw.init_arguments.is_empty() {
+            None
+        } else {
+            let offset = instructions.len() * size_of::<u32>();
+            instructions.push(raw.init_arguments.len() as u32);
+            instructions.extend_from_slice(&raw.init_arguments);
+            Some(offset as u64)
+        };
+
         let header_size = size_of_val(header) as u64;
 
         // The start address where we try to load the flash algorithm.
@@ -362,6 +427,7 @@ impl FlashAlgorithm {
             rtt_control_block: raw.rtt_location,
             flash_properties: raw.flash_properties.clone(),
             transfer_encoding: raw.transfer_encoding.unwrap_or_default(),
+            init_arguments: init_arguments_offset.map(|offset| addr_load + offset),
         })
     }
 }
@@ -538,4 +604,171 @@ mod test {
         ];
         assert_eq!(&got, expected);
     }
+
+    /// Runs the RISC-V CRC routine with a minimal interpreter for the RV32I instructions it uses.
+    fn run_riscv_crc32_routine(memory: &[u8], crc: u32) -> u32 {
+        let routine = FlashAlgorithm::crc32_routine(crate::InstructionSet::RV32).unwrap();
+        let mut x = [0u32; 32];
+        // a0: address, a1: length, a2: running CRC
+        x[10] = 0;
+        x[11] = memory.len() as u32;
+        x[12] = crc;
+
+        let mut pc = 0u32;
+        loop {
+            let insn = routine[pc as usize / 4];
+            let rd = (insn >> 7 & 0x1f) as usize;
+            let rs1 = (insn >> 15 & 0x1f) as usize;
+            let rs2 = (insn >> 20 & 0x1f) as usize;
+            let imm_i = (insn as i32 >> 20) as u32;
+            let imm_b = ((insn as i32 >> 31) as u32) << 12
+                | (insn >> 7 & 1) << 11
+                | (insn >> 25 & 0x3f) << 5
+                | (insn >> 8 & 0xf) << 1;
+            let imm_j = ((insn as i32 >> 31) as u32) << 20
+                | (insn >> 12 & 0xff) << 12
+                | (insn >> 20 & 1) << 11
+                | (insn >> 21 & 0x3ff) << 1;
+
+            let mut next_pc = pc.wrapping_add(4);
+            match (insn & 0x7f, insn >> 12 & 0x7) {
+                (0x37, _) => x[rd] = insn & 0xffff_f000,
+                (0x13, 0b000) => x[rd] = x[rs1].wrapping_add(imm_i),
+                (0x13, 0b111) => x[rd] = x[rs1] & imm_i,
+                (0x13, 0b101) => x[rd] = x[rs1] >> (imm_i & 0x1f),
+                (0x33, 0b100) => x[rd] = x[rs1] ^ x[rs2],
+                (0x03, 0b100) => x[rd] = memory[x[rs1].wrapping_add(imm_i) as usize] as u32,
+                (0x63, 0b000) if x[rs1] == x[rs2] => next_pc = pc.wrapping_add(imm_b),
+                (0x63, 0b001) if x[rs1] != x[rs2] => next_pc = pc.wrapping_add(imm_b),
+                (0x63, _) => {}
+                (0x6f, _) => next_pc = pc.wrapping_add(imm_j),
+                // ret
+                (0x67, _) => return x[10],
+                _ => panic!("Unexpected instruction {insn:#010x}"),
+            }
+            x[0] = 0;
+            pc = next_pc;
+        }
+    }
+
+    #[test]
+    fn riscv_crc32_routine() {
+        let data = b"123456789 The quick brown fox jumps over the lazy dog";
+
+        assert_eq!(
+            !run_riscv_crc32_routine(data, !0),
+            crc32fast::hash(data.as_slice())
+        );
+
+        // The running CRC allows splitting a range into several calls.
+        let (first, second) = data.split_at(20);
+        let crc = run_riscv_crc32_routine(first, !0);
+        assert_eq!(
+            !run_riscv_crc32_routine(second, crc),
+            crc32fast::hash(data.as_slice())
+        );
+    }
+
+    /// Runs the Thumb CRC routine on the Cortex-M0 core of a virtual probe.
+    fn run_arm_crc32_routine(memory: &[u8], crc: u32) -> u32 {
+        use crate::{
+            config::get_target_by_name, probe::virtual_probe::VirtualProbe, MemoryInterface,
+            Permissions,
+        };
+        use std::time::Duration;
+
+        const CODE: u64 = 0x2000_0000;
+        const BKPT: u64 = 0x2000_0100;
+        const DATA: u64 = 0x2000_0200;
+
+        let target = get_target_by_name("nrf51822_xxAC").unwrap();
+        let probe = VirtualProbe::new(&target).into_probe();
+        let mut session = probe.attach(target, Permissions::default()).unwrap();
+        let mut core = session.core(0).unwrap();
+        core.halt(Duration::from_millis(100)).unwrap();
+
+        let routine = FlashAlgorithm::crc32_routine(crate::InstructionSet::Thumb2).unwrap();
+        core.write_32(CODE, routine).unwrap();
+        // The routine returns to a `bkpt` instruction.
+        core.write_word_16(BKPT, 0xBE00).unwrap();
+        core.write(DATA, memory).unwrap();
+
+        // r0: address, r1: length, r2: running CRC
+        core.write_core_reg(0u16, DATA as u32).unwrap();
+        core.write_core_reg(1u16, memory.len() as u32).unwrap();
+        core.write_core_reg(2u16, crc).unwrap();
+        core.write_core_reg(core.return_address(), BKPT as u32 | 1)
+            .unwrap();
+        core.write_core_reg(core.program_counter(), CODE as u32)
+            .unwrap();
+
+        core.run().unwrap();
+        core.wait_for_core_halted(Duration::from_millis(1000))
+            .unwrap();
+        assert_eq!(
+            core.read_core_reg::<u32>(core.program_counter()).unwrap(),
+            BKPT as u32
+        );
+        core.read_core_reg(0u16).unwrap()
+    }
+
+    #[test]
+    fn arm_crc32_routine() {
+        let data = b"123456789 The quick brown fox jumps over the lazy dog";
+
+        assert_eq!(
+            !run_arm_crc32_routine(data, !0),
+            crc32fast::hash(data.as_slice())
+        );
+
+        // The running CRC allows splitting a range into several calls.
+        let (first, second) = data.split_at(20);
+        let crc = run_arm_crc32_routine(first, !0);
+        assert_eq!(
+            !run_arm_crc32_routine(second, crc),
+            crc32fast::hash(data.as_slice())
+        );
+    }
+
+    #[test]
+    fn no_crc32_routine_for_xtensa() {
+        assert!(FlashAlgorithm::crc32_routine(crate::InstructionSet::Xtensa).is_none());
+    }
+
+    #[test]
+    fn init_arguments_follow_the_code() {
+        let target = crate::config::get_target_by_name("nrf52840_xxAA").unwrap();
+        let ram = probe_rs_target::RamRegion {
+            name: None,
+            range: 0x2000_0000..0x2001_0000,
+            is_boot_memory: false,
+            cores: vec!["main".to_string()],
+        };
+        let raw = probe_rs_target::RawFlashAlgorithm {
+            instructions: vec![0x70, 0x47, 0x00, 0xbf],
+            flash_properties: FlashProperties {
+                page_size: 0x100,
+                ..Default::default()
+            },
+            init_arguments: vec![6, 1],
+            ..Default::default()
+        };
+
+        let algorithm = FlashAlgorithm::assemble_from_raw(&raw, &ram, &target).unwrap();
+
+        let header_words = FlashAlgorithm::ARM_FLASH_BLOB_HEADER.len();
+        assert_eq!(algorithm.instructions[header_words + 1..], [2, 6, 1]);
+        assert_eq!(
+            algorithm.init_arguments,
+            Some(algorithm.load_address + (header_words as u64 + 1) * 4)
+        );
+
+        let raw = probe_rs_target::RawFlashAlgorithm {
+            init_arguments: vec![],
+            ..raw
+        };
+        let algorithm = FlashAlgorithm::assemble_from_raw(&raw, &ram, &target).unwrap();
+        assert_eq!(algorithm.instructions.len(), header_words + 1);
+        assert_eq!(algorithm.init_arguments, None);
+    }
 }
@@ -1,13 +1,16 @@
 use probe_rs_target::{MemoryRegion, RamRegion, RawFlashAlgorithm};
 use tracing::Level;
 
-use super::{FlashAlgorithm, FlashBuilder, FlashError, FlashFill, FlashPage, FlashProgress};
+use super::{
+    FlashAlgorithm, FlashBuilder, FlashError, FlashFill, FlashLayout, FlashPage, FlashProgress,
+};
 use crate::config::NvmRegion;
 use crate::flashing::encoder::FlashEncoder;
 use crate::memory::MemoryInterface;
 use crate::{core::CoreRegisters, session::Session, Core, InstructionSet};
 use std::{
     fmt::Debug,
+    ops::Range,
     time::{Duration, Instant},
 };
 
@@ -55,6 +58,8 @@ pub(super) struct Flasher<'session> {
     core_index: usize,
     flash_algorithm: FlashAlgorithm,
     progress: FlashProgress,
+    /// Whether code can be run from the page buffers, because they are in the same RAM as the algorithm.
+    page_buffers_executable: bool,
 }
 
 impl<'session> Flasher<'session> {
@@ -126,11 +131,14 @@ impl<'session> Flasher<'session> {
             target,
         )?;
 
+        let page_buffers_executable = ram == data_ram;
+
         let mut this = Self {
             session,
             core_index,
             flash_algorithm,
             progress: progress.unwrap_or(FlashProgress::new(|_| {})),
+            page_buffers_executable,
         };
 
         this.load()?;
@@ -297,6 +305,9 @@ impl<'session> Flasher<'session> {
     /// If `restore_unwritten_bytes` is `true`, all bytes of a sector,
     /// that are not to be written during flashing will be read from the flash first
     /// and written again once the sector is erased.
+    ///
+    /// If `skip_unchanged_sectors` is `true`, sectors which already contain the data
+    /// to be flashed are neither erased nor programmed.
     pub(super) fn program(
         &mut self,
         region: &NvmRegion,
@@ -304,6 +315,7 @@ impl<'session> Flasher<'session> {
         restore_unwritten_bytes: bool,
         enable_double_buffering: bool,
         skip_erasing: bool,
+        skip_unchanged_sectors: bool,
     ) -> Result<(), FlashError> {
         tracing::debug!("Starting program procedure.");
         // Convert the list of flash operations into flash sectors and pages.
@@ -343,6 +355,10 @@ impl<'session> Flasher<'session> {
         // We successfully finished filling.
         self.progress.finished_filling();
 
+        if skip_unchanged_sectors {
+            self.skip_unchanged_sectors(&mut flash_layout)?;
+        }
+
         let flash_encoder = FlashEncoder::new(self.flash_algorithm.transfer_encoding, flash_layout);
 
         // Skip erase if necessary
@@ -381,6 +397,98 @@ impl<'session> Flasher<'session> {
         })
     }
 
+    /// Removes all sectors from `flash_layout` whose contents in flash already match
+    /// the contents they would have after flashing.
+    fn skip_unchanged_sectors(&mut self, flash_layout: &mut FlashLayout) -> Result<(), FlashError> {
+        let erased_byte_value = self.flash_algorithm.flash_properties.erased_byte_value;
+        let sectors = flash_layout.sectors().to_vec();
+        let ranges = sectors
+            .iter()
+            .map(|sector| sector.address()..sector.address() + sector.size())
+            .collect::<Vec<_>>();
+
+        let mut unchanged = Vec::new();
+        if let Some(crcs) = self.crc32(&ranges)? {
+            for (sector, crc) in sectors.iter().zip(crcs) {
+                if crc == crc32fast::hash(&flash_layout.sector_contents(sector, erased_byte_value))
+                {
+                    unchanged.push(sector.address());
+                }
+            }
+        } else {
+            self.run_verify(|active| {
+                let mut buffer = Vec::new();
+                for sector in &sectors {
+                    buffer.resize(sector.size() as usize, 0);
+                    active
+                        .core
+                        .read(sector.address(), &mut buffer)
+                        .map_err(FlashError::Core)?;
+
+                    if buffer == flash_layout.sector_contents(sector, erased_byte_value) {
+                        unchanged.push(sector.address());
+                    }
+                }
+                Ok(())
+            })?;
+        }
+
+        let skipped = flash_layout.skip_sectors(|sector| unchanged.contains(&sector.address()));
+        tracing::debug!(
+            "Skipping {} of {} sectors, their contents already match.",
+            skipped.len(),
+            sectors.len()
+        );
+
+        self.progress.sectors_skipped(
+            skipped.len(),
+            skipped.iter().map(|sector| sector.size()).sum(),
+        );
+
+        Ok(())
+    }
+
+    /// Calculates the CRC32 of each of the given memory ranges with a routine running on the target,
+    /// which is much faster than reading the memory back over the probe.
+    ///
+    /// The routine is run from the first page buffer. Returns `None` if there is no routine for the
+    /// instruction set of the core, or if it can't be run from the page buffer.
+    pub(super) fn crc32(&mut self, ranges: &[Range<u64>]) -> Result<Option<Vec<u32>>, FlashError> {
+        let instruction_set = self
+            .session
+            .core(self.core_index)
+            .map_err(FlashError::Core)?
+            .instruction_set()
+            .map_err(FlashError::Core)?;
+        let Some(routine) = FlashAlgorithm::crc32_routine(instruction_set) else {
+            tracing::debug!("No CRC routine for {instruction_set:?}, falling back to readback.");
+            return Ok(None);
+        };
+
+        let routine_address = self.flash_algorithm.page_buffers[0];
+        let page_size = self.flash_algorithm.flash_properties.page_size as usize;
+        if !self.page_buffers_executable
+            || routine_address % 4 != 0
+            || std::mem::size_of_val(routine) > page_size
+        {
+            tracing::debug!("The CRC routine doesn't fit into RAM, falling back to readback.");
+            return Ok(None);
+        }
+
+        self.run_verify(|active| {
+            active
+                .core
+                .write_32(routine_address, routine)
+                .map_err(FlashError::Core)?;
+
+            ranges
+                .iter()
+                .map(|range| active.crc32(routine_address, range.clone()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map(Some)
+    }
+
     /// Programs the pages given in `flash_layout` into the flash.
     fn program_simple(&mut self, flash_encoder: &FlashEncoder) -> Result<(), FlashError> {
         self.progress.started_programming(
@@ -583,7 +691,8 @@ impl<'probe, O: Operation> ActiveFlasher<'probe, O> {
                         r0: Some(into_reg(address)?),
                         r1: clock.or(Some(0)),
                         r2: Some(O::operation()),
-                        r3: None,
+                        // Algorithms without init arguments get a NULL pointer.
+                        r3: Some(into_reg(algo.init_arguments.unwrap_or(0))?),
                     },
                     true,
                     Duration::from_secs(2),
@@ -802,6 +911,34 @@ impl<'probe, O: Operation> ActiveFlasher<'probe, O> {
     }
 }
 
+impl<'probe> ActiveFlasher<'probe, Verify> {
+    /// Calculates the CRC32 of `range` with the CRC routine loaded at `routine_address`.
+    fn crc32(&mut self, routine_address: u64, range: Range<u64>) -> Result<u32, FlashError> {
+        // Split the range, so each call finishes quickly even on a slowly clocked core.
+        const CHUNK_SIZE: u64 = 0x4000;
+
+        let mut crc = !0;
+        let mut address = range.start;
+        while address < range.end {
+            let length = (range.end - address).min(CHUNK_SIZE);
+            crc = self.call_function_and_wait(
+                &Registers {
+                    pc: into_reg(routine_address)?,
+                    r0: Some(into_reg(address)?),
+                    r1: Some(into_reg(length)?),
+                    r2: Some(crc),
+                    r3: None,
+                },
+                false,
+                Duration::from_secs(2),
+            )?;
+            address += length;
+        }
+
+        Ok(!crc)
+    }
+}
+
 impl<'probe> ActiveFlasher<'probe, Erase> {
     pub(super) fn erase_all(&mut self) -> Result<(), FlashError> {
         tracing::debug!("Erasing entire chip.");
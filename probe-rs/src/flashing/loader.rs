@@ -12,8 +12,8 @@ use std::str::FromStr;
 
 use super::builder::FlashBuilder;
 use super::{
-    extract_from_elf, BinOptions, DownloadOptions, FileDownloadError, FlashError, Flasher,
-    IdfOptions,
+    extract_from_elf, srec, titxt, BinOptions, DownloadOptions, FileDownloadError, FlashError,
+    Flasher, IdfOptions,
 };
 use crate::config::DebugSequence;
 use crate::flashing::Format;
@@ -31,6 +31,10 @@ pub struct FlashLoader {
     memory_map: Vec<MemoryRegion>,
     builder: FlashBuilder,
 
+    /// The address ranges of each image loaded with [FlashLoader::load_image],
+    /// used to detect overlapping images.
+    images: Vec<Vec<Range<u64>>>,
+
     /// Source of the flash description,
     /// used for diagnostics.
     source: TargetDescriptionSource,
@@ -42,6 +46,7 @@ impl FlashLoader {
         Self {
             memory_map,
             builder: FlashBuilder::new(),
+            images: Vec::new(),
             source,
         }
     }
@@ -87,6 +92,9 @@ impl FlashLoader {
     }
 
     /// Reads the image according to the file format and adds it to the loader.
+    ///
+    /// Several images can be loaded, to flash them together with a single erase and program plan.
+    /// Loading an image which overlaps with a previously loaded image fails with [FileDownloadError::ImageOverlaps].
     pub fn load_image<T: Read + Seek>(
         &mut self,
         session: &mut Session,
@@ -117,13 +125,51 @@ impl FlashLoader {
                 });
             }
         }
+
+        // Load the image on its own first, to find out whether it overlaps with a previously loaded image.
+        let mut image = FlashLoader::new(self.memory_map.clone(), self.source);
         match format {
-            Format::Bin(options) => self.load_bin_data(file, options),
-            Format::Elf => self.load_elf_data(file),
-            Format::Hex => self.load_hex_data(file),
-            Format::Idf(options) => self.load_idf_data(session, file, options),
-            Format::Uf2 => self.load_uf2_data(file),
+            Format::Bin(options) => image.load_bin_data(file, options),
+            Format::Elf => image.load_elf_data(file),
+            Format::Hex => image.load_hex_data(file),
+            Format::Idf(options) => image.load_idf_data(session, file, options),
+            Format::Uf2 => image.load_uf2_data(file),
+            Format::Srec => image.load_srec_data(file),
+            Format::TiTxt => image.load_titxt_data(file),
+        }?;
+
+        self.add_image(image.builder)
+    }
+
+    /// Adds the data of an image, unless it overlaps with a previously loaded image.
+    fn add_image(&mut self, image: FlashBuilder) -> Result<(), FileDownloadError> {
+        let ranges = image
+            .data
+            .iter()
+            .map(|(&address, data)| address..address + data.len() as u64)
+            .collect::<Vec<_>>();
+
+        for (existing_image, existing_ranges) in self.images.iter().enumerate() {
+            for range in &ranges {
+                if let Some(existing) = existing_ranges
+                    .iter()
+                    .find(|existing| existing.intersects_range(range))
+                {
+                    return Err(FileDownloadError::ImageOverlaps {
+                        image: self.images.len(),
+                        existing_image,
+                        addresses: range.start.max(existing.start)..range.end.min(existing.end),
+                    });
+                }
+            }
+        }
+
+        for (address, data) in image.data {
+            self.builder.add_data(address, &data)?;
         }
+        self.images.push(ranges);
+
+        Ok(())
     }
 
     /// Reads the data from the binary file and adds it to the loader without splitting it into flash instructions yet.
@@ -266,6 +312,30 @@ impl FlashLoader {
         Ok(())
     }
 
+    /// Reads the data records of a Motorola S-record file and adds them as loadable data blocks to the loader.
+    /// This does not create any flash loader instructions yet.
+    pub fn load_srec_data<T: Read>(&mut self, file: &mut T) -> Result<(), FileDownloadError> {
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+
+        for (address, data) in srec::parse(&data)? {
+            self.add_data(address, &data)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the sections of a TI-TXT file and adds them as loadable data blocks to the loader.
+    /// This does not create any flash loader instructions yet.
+    pub fn load_titxt_data<T: Read>(&mut self, file: &mut T) -> Result<(), FileDownloadError> {
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+
+        for (address, data) in titxt::parse(&data)? {
+            self.add_data(address, &data)?;
+        }
+        Ok(())
+    }
+
     /// Prepares the data sections that have to be loaded into flash from an ELF file.
     /// This will validate the ELF file and transform all its data into sections but no flash loader commands yet.
     pub fn load_elf_data<T: Read>(&mut self, file: &mut T) -> Result<(), FileDownloadError> {
@@ -430,6 +500,9 @@ impl FlashLoader {
             return Ok(());
         }
 
+        // Flash regions whose contents were already verified with a CRC calculated on the target.
+        let mut verified_regions = Vec::new();
+
         // Iterate all flash algorithms we need to use.
         for ((algo_name, core_name), regions) in algos {
             tracing::debug!("Flashing ranges for algo: {}", algo_name);
@@ -466,7 +539,7 @@ impl FlashLoader {
                 do_use_double_buffering = false;
             }
 
-            for region in regions {
+            for region in &regions {
                 tracing::debug!(
                     "    programming region: {:#010X?} ({} bytes)",
                     region.range,
@@ -475,13 +548,38 @@ impl FlashLoader {
 
                 // Program the data.
                 flasher.program(
-                    &region,
+                    region,
                     &self.builder,
                     options.keep_unwritten_bytes,
                     do_use_double_buffering,
                     options.skip_erase || do_chip_erase,
+                    options.skip_unchanged_sectors && !do_chip_erase,
                 )?;
             }
+
+            if options.verify {
+                // Verify before the RAM is written, as the CRC routine runs from RAM.
+                let blocks = regions
+                    .iter()
+                    .flat_map(|region| self.builder.data_in_range(&region.range))
+                    .collect::<Vec<_>>();
+                let ranges = blocks
+                    .iter()
+                    .map(|(address, data)| *address..*address + data.len() as u64)
+                    .collect::<Vec<_>>();
+
+                if let Some(crcs) = flasher.crc32(&ranges)? {
+                    tracing::debug!("Verified {} data blocks on the target.", blocks.len());
+                    if blocks
+                        .iter()
+                        .zip(crcs)
+                        .any(|((_, data), crc)| crc32fast::hash(data) != crc)
+                    {
+                        return Err(FlashError::Verify);
+                    }
+                    verified_regions.extend(regions.iter().map(|region| region.range.clone()));
+                }
+            }
         }
 
         tracing::debug!("committing RAM!");
@@ -531,6 +629,14 @@ impl FlashLoader {
         if options.verify {
             tracing::debug!("Verifying!");
             for (&address, data) in &self.builder.data {
+                let range = address..address + data.len() as u64;
+                if verified_regions
+                    .iter()
+                    .any(|region: &Range<u64>| region.contains_range(&range))
+                {
+                    continue;
+                }
+
                 tracing::debug!(
                     "    data: {:#010X}..{:#010X} ({} bytes)",
                     address,
@@ -613,3 +719,72 @@ impl FlashLoader {
             .map(|(address, data)| (*address, data.as_slice()))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use probe_rs_target::{MemoryRegion, NvmRegion, TargetDescriptionSource};
+
+    use super::*;
+
+    fn loader() -> FlashLoader {
+        FlashLoader::new(
+            vec![MemoryRegion::Nvm(NvmRegion {
+                name: Some("FLASH".into()),
+                is_boot_memory: true,
+                range: 0..0x1_0000,
+                cores: vec!["main".into()],
+                is_alias: false,
+            })],
+            TargetDescriptionSource::BuiltIn,
+        )
+    }
+
+    fn image(blocks: &[(u64, usize)]) -> FlashBuilder {
+        let mut builder = FlashBuilder::new();
+        for &(address, size) in blocks {
+            builder.add_data(address, &vec![0xAA; size]).unwrap();
+        }
+        builder
+    }
+
+    #[test]
+    fn add_adjacent_images() {
+        let mut loader = loader();
+
+        loader.add_image(image(&[(0x0000, 0x100)])).unwrap();
+        loader
+            .add_image(image(&[(0x0100, 0x100), (0x8000, 0x10)]))
+            .unwrap();
+
+        assert_eq!(
+            loader
+                .data()
+                .map(|(address, data)| (address, data.len()))
+                .collect::<Vec<_>>(),
+            vec![(0x0000, 0x200), (0x8000, 0x10)]
+        );
+    }
+
+    #[test]
+    fn overlapping_images() {
+        let mut loader = loader();
+
+        loader.add_image(image(&[(0x0000, 0x100)])).unwrap();
+        loader.add_image(image(&[(0x4000, 0x100)])).unwrap();
+        let error = loader
+            .add_image(image(&[(0x2000, 0x10), (0x40F0, 0x20)]))
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            FileDownloadError::ImageOverlaps {
+                image: 2,
+                existing_image: 1,
+                addresses,
+            } if addresses == (0x40F0..0x4100)
+        ));
+
+        // Nothing of the rejected image was added.
+        assert_eq!(loader.data().count(), 2);
+    }
+}
@@ -2,9 +2,13 @@
 //!
 //! This modules provides a means to do flash unlocking, erasing and programming.
 //!
-//! It provides a convenient high level interface that can flash an ELF, IHEX or BIN file
+//! It provides a convenient high level interface that can flash an ELF, IHEX, S-record, TI-TXT or BIN file
 //! as well as a lower level block based interface.
 //!
+//! The option bytes of a chip can be read and written with the [`option_bytes`] module.
+//!
+//! A [`FlashReport`] records the progress of a flashing run in a machine readable form.
+//!
 //!
 //! ## Examples
 //!
@@ -51,7 +55,12 @@ mod error;
 mod flash_algorithm;
 mod flasher;
 mod loader;
+pub mod option_bytes;
 mod progress;
+pub mod protection;
+mod report;
+mod srec;
+mod titxt;
 mod visualizer;
 
 use builder::*;
@@ -64,4 +73,7 @@ pub use error::*;
 pub use flash_algorithm::*;
 pub use loader::*;
 pub use progress::*;
+pub use report::*;
+pub use srec::SrecError;
+pub use titxt::{is_titxt, TiTxtError};
 pub use visualizer::*;
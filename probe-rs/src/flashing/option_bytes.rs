@@ -0,0 +1,342 @@
+//! Reading and writing the option bytes of a chip.
+//!
+//! The layout of the option bytes is described in the target description, while accessing them
+//! is done by an [`OptionBytesSequence`] provided by the debug sequence of the chip.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::architecture::arm::{memory::adi_v5_memory_interface::ArmProbe, ArmError};
+use crate::config::{CoreExt, DebugSequence, OptionBytes, OptionBytesField};
+use crate::Session;
+
+/// An error which occurred while accessing the option bytes of a chip.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum OptionBytesError {
+    /// The target description of {0} does not describe any option bytes.
+    NotDescribed(String),
+
+    /// Accessing the option bytes of {0} is not supported.
+    NotSupported(String),
+
+    /// The option bytes have no field named '{0}'.
+    UnknownField(String),
+
+    /// The field '{0}' lies outside of the option bytes.
+    InvalidField(String),
+
+    /// The value {value:#x} does not fit into the {bit_width} bits of the field '{name}'.
+    ValueTooLarge {
+        /// The name of the field.
+        name: String,
+        /// The value which should be written.
+        value: u32,
+        /// The size of the field.
+        bit_width: u8,
+    },
+
+    /// Expected {expected} bytes of option bytes, but got {actual}.
+    InvalidSize {
+        /// The size of the option bytes in the target description.
+        expected: u64,
+        /// The size of the given data.
+        actual: usize,
+    },
+
+    /// The option bytes read back after writing them don't match the written values.
+    VerifyFailed,
+
+    /// An ARM specific error occurred while accessing the option bytes.
+    Arm(#[from] ArmError),
+
+    /// An error occurred while halting the core.
+    Core(#[from] crate::Error),
+}
+
+/// Access to the option bytes by vendor specific means.
+///
+/// Debug sequences return an implementation of this trait from
+/// [`ArmDebugSequence::option_bytes_sequence`](crate::architecture::arm::sequences::ArmDebugSequence::option_bytes_sequence).
+pub trait OptionBytesSequence: Send + Sync {
+    /// Reads the raw option bytes described by `option_bytes`.
+    ///
+    /// The default implementation reads them from memory.
+    fn read_option_bytes(
+        &self,
+        memory: &mut dyn ArmProbe,
+        option_bytes: &OptionBytes,
+    ) -> Result<Vec<u8>, ArmError> {
+        let mut data = vec![0; option_bytes.size as usize];
+        memory.read_8(option_bytes.address, &mut data)?;
+        Ok(data)
+    }
+
+    /// Programs the option bytes described by `option_bytes` with `data`.
+    ///
+    /// On most chips, the new values only take effect after a reset.
+    fn write_option_bytes(
+        &self,
+        memory: &mut dyn ArmProbe,
+        option_bytes: &OptionBytes,
+        data: &[u8],
+    ) -> Result<(), ArmError>;
+}
+
+/// The contents of the option bytes of a chip, decoded according to their description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionBytesValues {
+    description: OptionBytes,
+    data: Vec<u8>,
+}
+
+impl OptionBytesValues {
+    /// Creates the option bytes from their description and raw contents.
+    pub fn new(description: OptionBytes, data: Vec<u8>) -> Result<Self, OptionBytesError> {
+        if data.len() as u64 != description.size {
+            return Err(OptionBytesError::InvalidSize {
+                expected: description.size,
+                actual: data.len(),
+            });
+        }
+
+        Ok(Self { description, data })
+    }
+
+    /// The description of the option bytes.
+    pub fn description(&self) -> &OptionBytes {
+        &self.description
+    }
+
+    /// The raw contents of the option bytes.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns all fields of the option bytes together with their values.
+    pub fn fields(
+        &self,
+    ) -> impl Iterator<Item = (&OptionBytesField, Result<u32, OptionBytesError>)> + '_ {
+        self.description
+            .fields
+            .iter()
+            .map(|field| (field, self.field_value(field)))
+    }
+
+    /// Returns the value of the field called `name`.
+    pub fn field(&self, name: &str) -> Result<u32, OptionBytesError> {
+        self.field_value(self.find_field(name)?)
+    }
+
+    /// Sets the field called `name` to `value`.
+    ///
+    /// This only modifies the values in memory, use [`write_option_bytes`] to program them.
+    pub fn set_field(&mut self, name: &str, value: u32) -> Result<(), OptionBytesError> {
+        let field = self.find_field(name)?.clone();
+        let (word_offset, mask) = field_location(&field, self.data.len())?;
+
+        if value > mask {
+            return Err(OptionBytesError::ValueTooLarge {
+                name: field.name,
+                value,
+                bit_width: field.bit_width,
+            });
+        }
+
+        let word = self.word(word_offset) & !(mask << field.bit_offset) | value << field.bit_offset;
+        for (byte, value) in self.data[word_offset..].iter_mut().zip(word.to_le_bytes()) {
+            *byte = value;
+        }
+
+        Ok(())
+    }
+
+    fn find_field(&self, name: &str) -> Result<&OptionBytesField, OptionBytesError> {
+        self.description
+            .fields
+            .iter()
+            .find(|field| field.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| OptionBytesError::UnknownField(name.to_string()))
+    }
+
+    fn field_value(&self, field: &OptionBytesField) -> Result<u32, OptionBytesError> {
+        let (word_offset, mask) = field_location(field, self.data.len())?;
+        Ok(self.word(word_offset) >> field.bit_offset & mask)
+    }
+
+    /// Reads the little endian word at `offset`. Bytes beyond the end of the option bytes are zero.
+    fn word(&self, offset: usize) -> u32 {
+        let mut bytes = [0; 4];
+        for (byte, value) in bytes.iter_mut().zip(&self.data[offset..]) {
+            *byte = *value;
+        }
+        u32::from_le_bytes(bytes)
+    }
+}
+
+/// Returns the offset of the word containing the field, and the mask for its value.
+fn field_location(field: &OptionBytesField, size: usize) -> Result<(usize, u32), OptionBytesError> {
+    let end_bit = field.bit_offset as u32 + field.bit_width as u32;
+    let end_byte = field.offset as usize + end_bit.div_ceil(8) as usize;
+
+    if field.bit_width == 0 || end_bit > 32 || end_byte > size {
+        return Err(OptionBytesError::InvalidField(field.name.clone()));
+    }
+
+    let mask = (u64::MAX >> (64 - field.bit_width)) as u32;
+    Ok((field.offset as usize, mask))
+}
+
+/// Reads the option bytes of the target.
+pub fn read_option_bytes(session: &mut Session) -> Result<OptionBytesValues, OptionBytesError> {
+    let (sequence, description, memory_ap) = option_bytes_access(session)?;
+
+    let interface = session.get_arm_interface()?;
+    let mut memory = interface.memory_interface(memory_ap)?;
+    let data = sequence.read_option_bytes(&mut *memory, &description)?;
+
+    OptionBytesValues::new(description, data)
+}
+
+/// Programs the option bytes of the target, and verifies them afterwards.
+///
+/// The default core is halted, so the firmware can't interfere with the flash controller.
+/// Depending on the chip, the new values only take effect after a reset, and some values,
+/// e.g. read protection levels, can lock the chip.
+pub fn write_option_bytes(
+    session: &mut Session,
+    values: &OptionBytesValues,
+) -> Result<(), OptionBytesError> {
+    let (sequence, description, memory_ap) = option_bytes_access(session)?;
+    if values.data.len() as u64 != description.size {
+        return Err(OptionBytesError::InvalidSize {
+            expected: description.size,
+            actual: values.data.len(),
+        });
+    }
+
+    session.core(0)?.halt(Duration::from_millis(100))?;
+
+    let interface = session.get_arm_interface()?;
+    let mut memory = interface.memory_interface(memory_ap)?;
+    sequence.write_option_bytes(&mut *memory, &description, &values.data)?;
+
+    if sequence.read_option_bytes(&mut *memory, &description)? != values.data {
+        return Err(OptionBytesError::VerifyFailed);
+    }
+
+    Ok(())
+}
+
+fn option_bytes_access(
+    session: &Session,
+) -> Result<
+    (
+        Arc<dyn OptionBytesSequence>,
+        OptionBytes,
+        crate::architecture::arm::ap::MemoryAp,
+    ),
+    OptionBytesError,
+> {
+    let target = session.target();
+    let not_supported = || OptionBytesError::NotSupported(target.name.clone());
+
+    let description = target
+        .option_bytes
+        .clone()
+        .ok_or_else(|| OptionBytesError::NotDescribed(target.name.clone()))?;
+
+    let DebugSequence::Arm(debug_sequence) = &target.debug_sequence else {
+        return Err(not_supported());
+    };
+    let sequence = debug_sequence
+        .option_bytes_sequence()
+        .ok_or_else(not_supported)?;
+    let memory_ap = target
+        .default_core()
+        .memory_ap()
+        .ok_or_else(not_supported)?;
+
+    Ok((sequence, description, memory_ap))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn description() -> OptionBytes {
+        let field = |name: &str, offset, bit_offset, bit_width| OptionBytesField {
+            name: name.to_string(),
+            description: None,
+            offset,
+            bit_offset,
+            bit_width,
+        };
+
+        OptionBytes {
+            address: 0x4002_3c14,
+            size: 8,
+            fields: vec![
+                field("BOR_LEV", 0, 2, 2),
+                field("RDP", 0, 8, 8),
+                field("nWRP", 0, 16, 12),
+                field("LOCK", 4, 0, 32),
+                field("OUTSIDE", 6, 0, 32),
+            ],
+        }
+    }
+
+    #[test]
+    fn decode_fields() {
+        let values = OptionBytesValues::new(
+            description(),
+            vec![0xed, 0xaa, 0xff, 0x0f, 0x78, 0x56, 0x34, 0x12],
+        )
+        .unwrap();
+
+        assert_eq!(values.field("BOR_LEV").unwrap(), 0b11);
+        assert_eq!(values.field("rdp").unwrap(), 0xaa);
+        assert_eq!(values.field("nWRP").unwrap(), 0xfff);
+        assert_eq!(values.field("LOCK").unwrap(), 0x1234_5678);
+        assert!(matches!(
+            values.field("OUTSIDE"),
+            Err(OptionBytesError::InvalidField(_))
+        ));
+        assert!(matches!(
+            values.field("nDBANK"),
+            Err(OptionBytesError::UnknownField(_))
+        ));
+    }
+
+    #[test]
+    fn modify_fields() {
+        let mut values = OptionBytesValues::new(
+            description(),
+            vec![0xed, 0xaa, 0xff, 0x0f, 0x78, 0x56, 0x34, 0x12],
+        )
+        .unwrap();
+
+        values.set_field("BOR_LEV", 0b01).unwrap();
+        values.set_field("nWRP", 0xffe).unwrap();
+        values.set_field("LOCK", 0xffff_ffff).unwrap();
+
+        assert_eq!(
+            values.data(),
+            &[0xe5, 0xaa, 0xfe, 0x0f, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert!(matches!(
+            values.set_field("RDP", 0x100),
+            Err(OptionBytesError::ValueTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_size() {
+        assert!(matches!(
+            OptionBytesValues::new(description(), vec![0; 4]),
+            Err(OptionBytesError::InvalidSize {
+                expected: 8,
+                actual: 4
+            })
+        ));
+    }
+}
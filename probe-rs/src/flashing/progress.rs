@@ -62,6 +62,11 @@ impl FlashProgress {
         self.emit(ProgressEvent::SectorErased { size, time });
     }
 
+    /// Signalize that sectors were left out, because their contents already match.
+    pub(super) fn sectors_skipped(&self, count: usize, size: u64) {
+        self.emit(ProgressEvent::SectorsSkipped { count, size });
+    }
+
     /// Signalize that the page filling procedure has made progress.
     pub(super) fn page_filled(&self, size: u64, time: Duration) {
         self.emit(ProgressEvent::PageFilled { size, time });
@@ -111,6 +116,7 @@ impl FlashProgress {
 /// * `StartedFilling`
 /// * `PageFilled` for every page
 /// * `FinishedFilling`
+/// * `SectorsSkipped`, if unchanged sectors are skipped
 /// * `StartedErasing`
 /// * `SectorErased` for every sector
 /// * `FinishedErasing`
@@ -143,6 +149,14 @@ pub enum ProgressEvent {
     FailedFilling,
     /// Filling of the pages has finished successfully.
     FinishedFilling,
+    /// Sectors whose contents already match the data to be flashed have been
+    /// removed from the flash layout, and will be neither erased nor programmed.
+    SectorsSkipped {
+        /// The number of skipped sectors.
+        count: usize,
+        /// The total size of the skipped sectors in bytes.
+        size: u64,
+    },
     /// Erasing of flash has started.
     StartedErasing,
     /// A sector has been erased successfully.
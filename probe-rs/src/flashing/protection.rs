@@ -0,0 +1,278 @@
+//! Managing the readout protection of a chip.
+//!
+//! Most chips can prevent the debugger from reading the flash, e.g. the read protection (RDP) of
+//! the STM32, the APPROTECT of the nRF or the security bit of the ATSAM. Enabling and disabling
+//! it is done by a [`ProtectionSequence`] provided by the debug sequence of the chip.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::architecture::arm::ap::{AccessPort, MemoryAp};
+use crate::architecture::arm::{ArmError, ArmProbeInterface};
+use crate::config::{CoreExt, DebugSequence};
+use crate::probe::Probe;
+use crate::session::MissingPermissions;
+use crate::{Permissions, Session, Target};
+
+/// An error which occurred while accessing the readout protection of a chip.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum ProtectionError {
+    /// Managing the readout protection of {0} is not supported.
+    NotSupported(String),
+
+    /// The readout protection can't be changed without the '{0}' permission.
+    MissingPermissions(String),
+
+    /// The readout protection of the chip is permanent and can't be disabled.
+    PermanentlyProtected,
+
+    /// The readout protection is still {0} after changing it.
+    VerifyFailed(ProtectionState),
+
+    /// An ARM specific error occurred while accessing the readout protection.
+    Arm(#[from] ArmError),
+
+    /// An error occurred while halting the core.
+    Core(#[from] crate::Error),
+}
+
+impl From<MissingPermissions> for ProtectionError {
+    fn from(MissingPermissions(permission): MissingPermissions) -> Self {
+        Self::MissingPermissions(permission)
+    }
+}
+
+/// The readout protection state of a chip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, docsplay::Display)]
+pub enum ProtectionState {
+    /// unprotected
+    Unprotected,
+    /// protected
+    Protected,
+    /// permanently protected
+    PermanentlyProtected,
+}
+
+/// The readout protection of a chip, as reported by its [`ProtectionSequence`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtectionStatus {
+    /// The configured protection state.
+    pub state: ProtectionState,
+    /// Whether the configured state is already in effect, or only takes effect after a reset.
+    pub active: bool,
+    /// A vendor specific description of the state, e.g. `RDP level 1`.
+    pub description: String,
+}
+
+impl ProtectionStatus {
+    /// Creates the status of a protection which is in effect.
+    pub fn active(state: ProtectionState, description: impl Into<String>) -> Self {
+        Self {
+            state,
+            active: true,
+            description: description.into(),
+        }
+    }
+
+    /// Creates the status of a protection which only takes effect after a reset.
+    pub fn pending(state: ProtectionState, description: impl Into<String>) -> Self {
+        Self {
+            state,
+            active: false,
+            description: description.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ProtectionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.state, self.description)?;
+        if !self.active {
+            write!(f, ", takes effect after a reset")?;
+        }
+        Ok(())
+    }
+}
+
+/// Access to the readout protection by vendor specific means.
+///
+/// Debug sequences return an implementation of this trait from
+/// [`ArmDebugSequence::protection_sequence`](crate::architecture::arm::sequences::ArmDebugSequence::protection_sequence).
+/// The permissions are checked before the sequence is called.
+pub trait ProtectionSequence: Send + Sync {
+    /// Reads the readout protection state of the chip.
+    fn protection_status(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+    ) -> Result<ProtectionStatus, ArmError>;
+
+    /// Enables the readout protection.
+    ///
+    /// This must never make the protection permanent.
+    fn enable_protection(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+    ) -> Result<(), ArmError>;
+
+    /// Disables the readout protection, which usually erases the whole chip.
+    ///
+    /// Returns [`ArmError::ReAttachRequired`] if the probe has to re-attach afterwards.
+    fn disable_protection(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+    ) -> Result<(), ArmError>;
+}
+
+/// Reads the readout protection state of the target.
+pub fn protection_status(session: &mut Session) -> Result<ProtectionStatus, ProtectionError> {
+    let (sequence, memory_ap) = protection_access(session.target())?;
+
+    let interface = session.get_arm_interface()?;
+    Ok(sequence.protection_status(interface, memory_ap)?)
+}
+
+/// Enables the readout protection of the target.
+///
+/// This requires the [`Permissions::allow_enable_protection`] permission. The default core is
+/// halted, so the firmware can't interfere with the flash controller. Nothing is done if the
+/// target is already protected.
+pub fn enable_protection(
+    session: &mut Session,
+    permissions: &Permissions,
+) -> Result<ProtectionStatus, ProtectionError> {
+    permissions.enable_protection()?;
+
+    let (sequence, memory_ap) = protection_access(session.target())?;
+    let status = sequence.protection_status(session.get_arm_interface()?, memory_ap)?;
+    if status.state != ProtectionState::Unprotected {
+        return Ok(status);
+    }
+
+    session.core(0)?.halt(Duration::from_millis(100))?;
+    let interface = session.get_arm_interface()?;
+    sequence.enable_protection(interface, memory_ap)?;
+
+    let status = sequence.protection_status(interface, memory_ap)?;
+    match status.state {
+        ProtectionState::Unprotected => Err(ProtectionError::VerifyFailed(status.state)),
+        _ => Ok(status),
+    }
+}
+
+/// Disables the readout protection of the target.
+///
+/// Disabling the protection erases the whole chip on most devices, so this requires the
+/// [`Permissions::allow_erase_all`] permission. Nothing is done if the target is not protected.
+pub fn disable_protection(
+    session: &mut Session,
+    permissions: &Permissions,
+) -> Result<ProtectionStatus, ProtectionError> {
+    permissions.erase_all()?;
+
+    let (sequence, memory_ap) = protection_access(session.target())?;
+    let status = sequence.protection_status(session.get_arm_interface()?, memory_ap)?;
+    match status.state {
+        ProtectionState::Unprotected => return Ok(status),
+        ProtectionState::PermanentlyProtected => return Err(ProtectionError::PermanentlyProtected),
+        ProtectionState::Protected => {}
+    }
+
+    session.core(0)?.halt(Duration::from_millis(100))?;
+    match sequence.disable_protection(session.get_arm_interface()?, memory_ap) {
+        Ok(()) => {}
+        Err(ArmError::ReAttachRequired) => session.reattach_arm()?,
+        Err(error) => return Err(error.into()),
+    }
+
+    let status = sequence.protection_status(session.get_arm_interface()?, memory_ap)?;
+    match status.state {
+        ProtectionState::Unprotected => Ok(status),
+        _ => Err(ProtectionError::VerifyFailed(status.state)),
+    }
+}
+
+/// Disables the readout protection of a target which can't be attached to because of it.
+///
+/// Attaching to such a target fails with [`ArmError::MissingPermissions`], unless erasing it is
+/// permitted, in which case the debug sequence erases it while attaching. This runs the
+/// [`ProtectionSequence`] of `target` on the bare probe instead, and returns the probe, so a
+/// [`Session`] can be attached to the now unprotected target. This requires the
+/// [`Permissions::allow_erase_all`] permission.
+pub fn disable_protection_unattached(
+    mut probe: Probe,
+    target: &Target,
+    permissions: &Permissions,
+) -> Result<Probe, ProtectionError> {
+    permissions.erase_all()?;
+
+    let (sequence, memory_ap) = protection_access(target)?;
+    let DebugSequence::Arm(debug_sequence) = &target.debug_sequence else {
+        return Err(ProtectionError::NotSupported(target.name.clone()));
+    };
+
+    probe.attach_to_unspecified()?;
+    let interface = probe
+        .try_into_arm_interface()
+        .map_err(|(_probe, error)| crate::Error::from(error))?;
+    let mut interface = interface
+        .initialize(debug_sequence.clone(), memory_ap.ap_address().dp)
+        .map_err(|(_interface, error)| error)?;
+
+    let result = sequence.disable_protection(&mut *interface, memory_ap);
+
+    let mut probe = interface.close();
+    probe.detach()?;
+    match result {
+        Ok(()) | Err(ArmError::ReAttachRequired) => Ok(probe),
+        Err(error) => Err(error.into()),
+    }
+}
+
+fn protection_access(
+    target: &Target,
+) -> Result<(Arc<dyn ProtectionSequence>, MemoryAp), ProtectionError> {
+    let not_supported = || ProtectionError::NotSupported(target.name.clone());
+
+    let DebugSequence::Arm(debug_sequence) = &target.debug_sequence else {
+        return Err(not_supported());
+    };
+    let sequence = debug_sequence
+        .protection_sequence()
+        .ok_or_else(not_supported)?;
+    let memory_ap = target
+        .default_core()
+        .memory_ap()
+        .ok_or_else(not_supported)?;
+
+    Ok((sequence, memory_ap))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display_status() {
+        assert_eq!(
+            ProtectionStatus::active(ProtectionState::Protected, "RDP level 1").to_string(),
+            "protected (RDP level 1)"
+        );
+        assert_eq!(
+            ProtectionStatus::pending(ProtectionState::Protected, "UICR.APPROTECT enabled")
+                .to_string(),
+            "protected (UICR.APPROTECT enabled), takes effect after a reset"
+        );
+    }
+
+    #[test]
+    fn missing_permissions() {
+        let error = ProtectionError::from(MissingPermissions("erase_all".into()));
+        assert_eq!(
+            error.to_string(),
+            "The readout protection can't be changed without the 'erase_all' permission."
+        );
+    }
+}
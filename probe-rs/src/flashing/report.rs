@@ -0,0 +1,398 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use super::{FlashError, FlashLayout, ProgressEvent};
+
+/// A machine readable report of a flashing run.
+///
+/// The report collects the [`ProgressEvent`]s of a run with [`FlashReport::record`], and is
+/// completed with [`FlashReport::finish`]. It can be serialized, e.g. to JSON, to log the
+/// flashing of devices on a production line.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::sync::{Arc, Mutex};
+///
+/// use probe_rs::flashing::{DownloadOptions, FlashProgress, FlashReport};
+///
+/// # let mut session = probe_rs::Session::auto_attach("nrf51822", probe_rs::Permissions::default())?;
+/// # let loader = session.target().flash_loader();
+/// let report = Arc::new(Mutex::new(FlashReport::new()));
+///
+/// let mut options = DownloadOptions::default();
+/// let recorder = report.clone();
+/// options.progress = Some(FlashProgress::new(move |event| {
+///     recorder.lock().unwrap().record(&event)
+/// }));
+///
+/// let result = loader.commit(&mut session, options);
+/// report.lock().unwrap().finish(result.as_ref().err(), false);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct FlashReport {
+    /// The name of the flashed chip.
+    pub chip: Option<String>,
+    /// The probe used for flashing.
+    pub probe: Option<ProbeReport>,
+    /// The flashed images.
+    pub images: Vec<ImageReport>,
+    /// The flash layouts, one for each flashed region.
+    pub layouts: Vec<LayoutReport>,
+    /// The phases of the run, in the order they were started.
+    pub phases: Vec<PhaseReport>,
+    /// The sectors which were skipped because their contents already matched.
+    pub skipped_sectors: SkippedSectorsReport,
+    /// The number of phases which failed, and were started again afterwards.
+    pub retries: usize,
+    /// Whether the flash contents were verified successfully.
+    ///
+    /// This is `None` if no verification was requested, or flashing failed before it.
+    pub verified: Option<bool>,
+    /// Whether the run was successful.
+    pub success: bool,
+    /// The error which ended the run, if it failed.
+    pub error: Option<String>,
+    /// The duration of the whole run, in seconds.
+    pub seconds: f64,
+    /// The diagnostic messages of the flash algorithms.
+    pub messages: Vec<String>,
+
+    #[serde(skip)]
+    started: Instant,
+}
+
+/// The probe which was used for flashing.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProbeReport {
+    /// The name of the probe.
+    pub name: String,
+    /// The selector of the probe, as `VID:PID[:SERIAL]`.
+    pub selector: Option<String>,
+    /// The wire protocol which was used.
+    pub protocol: Option<String>,
+    /// The speed of the wire protocol in kHz.
+    pub speed_khz: u32,
+}
+
+/// An image which was flashed.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImageReport {
+    /// The path of the image file.
+    pub path: String,
+    /// The size of the image file in bytes.
+    pub size: u64,
+    /// The SHA-256 hash of the image file, as a hex string.
+    pub sha256: String,
+}
+
+/// The layout of a flashed region, without the flashed data.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LayoutReport {
+    /// The sectors which are erased.
+    pub sectors: Vec<AreaReport>,
+    /// The pages which are programmed.
+    pub pages: Vec<AreaReport>,
+    /// The parts of the erased sectors which are restored.
+    pub fills: Vec<AreaReport>,
+}
+
+impl From<&FlashLayout> for LayoutReport {
+    fn from(layout: &FlashLayout) -> Self {
+        Self {
+            sectors: layout
+                .sectors()
+                .iter()
+                .map(|sector| AreaReport::new(sector.address(), sector.size()))
+                .collect(),
+            pages: layout
+                .pages()
+                .iter()
+                .map(|page| AreaReport::new(page.address(), page.size() as u64))
+                .collect(),
+            fills: layout
+                .fills()
+                .iter()
+                .map(|fill| AreaReport::new(fill.address(), fill.size()))
+                .collect(),
+        }
+    }
+}
+
+/// An area of the flash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AreaReport {
+    /// The start address of the area.
+    pub address: u64,
+    /// The size of the area in bytes.
+    pub size: u64,
+}
+
+impl AreaReport {
+    fn new(address: u64, size: u64) -> Self {
+        Self { address, size }
+    }
+}
+
+/// The sectors which were skipped because their contents already matched.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SkippedSectorsReport {
+    /// The number of skipped sectors.
+    pub count: usize,
+    /// The total size of the skipped sectors in bytes.
+    pub size: u64,
+}
+
+/// A phase of the flashing process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlashPhase {
+    /// Reading the flash contents which are restored after erasing.
+    Fill,
+    /// Erasing sectors.
+    Erase,
+    /// Programming pages.
+    Program,
+}
+
+/// The outcome of a phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhaseOutcome {
+    /// The phase was started, but did not finish.
+    Incomplete,
+    /// The phase finished successfully.
+    Finished,
+    /// The phase failed.
+    Failed,
+}
+
+/// The timing and throughput of one run of a phase.
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseReport {
+    /// The phase.
+    pub phase: FlashPhase,
+    /// The outcome of the phase.
+    pub outcome: PhaseOutcome,
+    /// The number of pages or sectors which were processed.
+    pub operations: usize,
+    /// The number of bytes which were processed.
+    pub bytes: u64,
+    /// The duration of the phase, in seconds.
+    pub seconds: f64,
+    /// The time spent in the operations themselves, in seconds.
+    pub operation_seconds: f64,
+    /// The throughput of the phase in bytes per second, if it took a measurable time.
+    pub bytes_per_second: Option<f64>,
+
+    #[serde(skip)]
+    started: Instant,
+}
+
+impl Default for FlashReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlashReport {
+    /// Creates an empty report for a run which starts now.
+    pub fn new() -> Self {
+        Self {
+            chip: None,
+            probe: None,
+            images: vec![],
+            layouts: vec![],
+            phases: vec![],
+            skipped_sectors: SkippedSectorsReport::default(),
+            retries: 0,
+            verified: None,
+            success: false,
+            error: None,
+            seconds: 0.0,
+            messages: vec![],
+            started: Instant::now(),
+        }
+    }
+
+    /// Adds a progress event to the report.
+    pub fn record(&mut self, event: &ProgressEvent) {
+        match event {
+            ProgressEvent::Initialized { flash_layout } => self.layouts.push(flash_layout.into()),
+            ProgressEvent::StartedFilling => self.start_phase(FlashPhase::Fill),
+            ProgressEvent::StartedErasing => self.start_phase(FlashPhase::Erase),
+            ProgressEvent::StartedProgramming { .. } => self.start_phase(FlashPhase::Program),
+            ProgressEvent::PageFilled { size, time } => {
+                self.add_operation(FlashPhase::Fill, *size, *time)
+            }
+            ProgressEvent::SectorErased { size, time } => {
+                self.add_operation(FlashPhase::Erase, *size, *time)
+            }
+            ProgressEvent::PageProgrammed { size, time } => {
+                self.add_operation(FlashPhase::Program, *size as u64, *time)
+            }
+            ProgressEvent::FinishedFilling => {
+                self.end_phase(FlashPhase::Fill, PhaseOutcome::Finished)
+            }
+            ProgressEvent::FinishedErasing => {
+                self.end_phase(FlashPhase::Erase, PhaseOutcome::Finished)
+            }
+            ProgressEvent::FinishedProgramming => {
+                self.end_phase(FlashPhase::Program, PhaseOutcome::Finished)
+            }
+            ProgressEvent::FailedFilling => self.end_phase(FlashPhase::Fill, PhaseOutcome::Failed),
+            ProgressEvent::FailedErasing => self.end_phase(FlashPhase::Erase, PhaseOutcome::Failed),
+            ProgressEvent::FailedProgramming => {
+                self.end_phase(FlashPhase::Program, PhaseOutcome::Failed)
+            }
+            ProgressEvent::SectorsSkipped { count, size } => {
+                self.skipped_sectors.count += count;
+                self.skipped_sectors.size += size;
+            }
+            ProgressEvent::DiagnosticMessage { message } => self.messages.push(message.clone()),
+        }
+    }
+
+    /// Completes the report with the result of the run.
+    ///
+    /// `verify` tells whether verification of the flash contents was requested.
+    pub fn finish(&mut self, error: Option<&FlashError>, verify: bool) {
+        self.seconds = self.started.elapsed().as_secs_f64();
+        self.success = error.is_none();
+        self.error = error.map(|error| error.to_string());
+        self.verified = match error {
+            None if verify => Some(true),
+            Some(FlashError::Verify) => Some(false),
+            _ => None,
+        };
+    }
+
+    fn start_phase(&mut self, phase: FlashPhase) {
+        if self
+            .phases
+            .iter()
+            .rev()
+            .find(|report| report.phase == phase)
+            .is_some_and(|report| report.outcome == PhaseOutcome::Failed)
+        {
+            self.retries += 1;
+        }
+
+        self.phases.push(PhaseReport {
+            phase,
+            outcome: PhaseOutcome::Incomplete,
+            operations: 0,
+            bytes: 0,
+            seconds: 0.0,
+            operation_seconds: 0.0,
+            bytes_per_second: None,
+            started: Instant::now(),
+        });
+    }
+
+    /// Returns the running phase of the given kind.
+    fn running_phase(&mut self, phase: FlashPhase) -> Option<&mut PhaseReport> {
+        self.phases
+            .iter_mut()
+            .rev()
+            .find(|report| report.phase == phase)
+            .filter(|report| report.outcome == PhaseOutcome::Incomplete)
+    }
+
+    fn add_operation(&mut self, phase: FlashPhase, size: u64, time: Duration) {
+        if let Some(report) = self.running_phase(phase) {
+            report.operations += 1;
+            report.bytes += size;
+            report.operation_seconds += time.as_secs_f64();
+        }
+    }
+
+    fn end_phase(&mut self, phase: FlashPhase, outcome: PhaseOutcome) {
+        // Phases which were never started, e.g. in a dry run, are not reported.
+        if let Some(report) = self.running_phase(phase) {
+            report.outcome = outcome;
+            report.seconds = report.started.elapsed().as_secs_f64();
+            report.bytes_per_second =
+                (report.seconds > 0.0).then(|| report.bytes as f64 / report.seconds);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record_phases() {
+        let mut report = FlashReport::new();
+        let time = Duration::from_millis(10);
+
+        for event in [
+            ProgressEvent::StartedErasing,
+            ProgressEvent::SectorErased { size: 0x1000, time },
+            ProgressEvent::SectorErased { size: 0x1000, time },
+            ProgressEvent::FinishedErasing,
+            ProgressEvent::SectorsSkipped {
+                count: 2,
+                size: 0x2000,
+            },
+            ProgressEvent::StartedProgramming { length: 0x800 },
+            ProgressEvent::PageProgrammed { size: 0x400, time },
+            ProgressEvent::FailedProgramming,
+            ProgressEvent::StartedProgramming { length: 0x800 },
+            ProgressEvent::PageProgrammed { size: 0x400, time },
+            ProgressEvent::PageProgrammed { size: 0x400, time },
+            ProgressEvent::FinishedProgramming,
+        ] {
+            report.record(&event);
+        }
+        report.finish(None, true);
+
+        let summary = report
+            .phases
+            .iter()
+            .map(|phase| (phase.phase, phase.outcome, phase.operations, phase.bytes))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (FlashPhase::Erase, PhaseOutcome::Finished, 2, 0x2000),
+                (FlashPhase::Program, PhaseOutcome::Failed, 1, 0x400),
+                (FlashPhase::Program, PhaseOutcome::Finished, 2, 0x800),
+            ]
+        );
+        assert_eq!(report.phases[0].operation_seconds, 0.02);
+        assert_eq!(report.skipped_sectors.count, 2);
+        assert_eq!(report.retries, 1);
+        assert_eq!(report.verified, Some(true));
+        assert!(report.success);
+    }
+
+    #[test]
+    fn dry_run_has_no_phases() {
+        let mut report = FlashReport::new();
+        for event in [
+            ProgressEvent::FailedFilling,
+            ProgressEvent::FailedErasing,
+            ProgressEvent::FailedProgramming,
+        ] {
+            report.record(&event);
+        }
+        report.finish(None, false);
+
+        assert!(report.phases.is_empty());
+        assert_eq!(report.verified, None);
+    }
+
+    #[test]
+    fn failed_verification() {
+        let mut report = FlashReport::new();
+        report.finish(Some(&FlashError::Verify), true);
+
+        assert!(!report.success);
+        assert_eq!(report.verified, Some(false));
+        assert!(report.error.is_some());
+    }
+}
@@ -0,0 +1,194 @@
+//! Parser for [Motorola S-record](https://en.wikipedia.org/wiki/SREC_(file_format)) files.
+
+/// An error in an S-record file.
+#[derive(Debug, thiserror::Error, docsplay::Display, PartialEq, Eq)]
+pub enum SrecError {
+    /// Line {line}: the record does not start with 'S'.
+    MissingStartCode {
+        /// The line of the file, starting at 1.
+        line: usize,
+    },
+
+    /// Line {line}: unknown record type 'S{record_type}'.
+    UnknownRecordType {
+        /// The line of the file, starting at 1.
+        line: usize,
+        /// The character following the 'S'.
+        record_type: char,
+    },
+
+    /// Line {line}: the record contains characters which are not hexadecimal digits.
+    InvalidHexDigits {
+        /// The line of the file, starting at 1.
+        line: usize,
+    },
+
+    /// Line {line}: the byte count of the record does not match its length.
+    InvalidLength {
+        /// The line of the file, starting at 1.
+        line: usize,
+    },
+
+    /// Line {line}: the checksum of the record is {found:#04x}, but should be {expected:#04x}.
+    ChecksumMismatch {
+        /// The line of the file, starting at 1.
+        line: usize,
+        /// The checksum calculated from the record.
+        expected: u8,
+        /// The checksum stored in the record.
+        found: u8,
+    },
+}
+
+/// Parses the data records of an S-record file, and returns the address and data of each record.
+///
+/// Header, count and start address records are validated, but otherwise ignored.
+pub(super) fn parse(contents: &str) -> Result<Vec<(u64, Vec<u8>)>, SrecError> {
+    let mut records = Vec::new();
+
+    for (index, text) in contents.lines().enumerate() {
+        let line = index + 1;
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        let Some(record) = text.strip_prefix(['S', 's']) else {
+            return Err(SrecError::MissingStartCode { line });
+        };
+        let mut chars = record.chars();
+        let record_type = chars.next().unwrap_or(' ');
+        let address_size = match record_type {
+            '0' | '1' | '5' | '9' => 2,
+            '2' | '6' | '8' => 3,
+            '3' | '7' => 4,
+            _ => return Err(SrecError::UnknownRecordType { line, record_type }),
+        };
+
+        let bytes = decode_hex(chars.as_str()).ok_or(SrecError::InvalidHexDigits { line })?;
+
+        // The byte count covers the address, data and checksum, and there has to be at least a full address.
+        let Some((&count, rest)) = bytes.split_first() else {
+            return Err(SrecError::InvalidLength { line });
+        };
+        if count as usize != rest.len() || rest.len() < address_size + 1 {
+            return Err(SrecError::InvalidLength { line });
+        }
+
+        let (payload, found) = (&bytes[..bytes.len() - 1], bytes[bytes.len() - 1]);
+        let expected = !payload
+            .iter()
+            .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        if expected != found {
+            return Err(SrecError::ChecksumMismatch {
+                line,
+                expected,
+                found,
+            });
+        }
+
+        if let '1' | '2' | '3' = record_type {
+            let (address, data) = payload[1..].split_at(address_size);
+            let address = address
+                .iter()
+                .fold(0u64, |address, byte| address << 8 | *byte as u64);
+
+            if !data.is_empty() {
+                records.push((address, data.to_vec()));
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 || !text.is_ascii() {
+        return None;
+    }
+
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_records() {
+        let contents = "\
+S00F000068656C6C6F202020202000003C
+S11F00007C0802A6900100049421FFF07C6C1B787C8C23783C6000003863000026
+S11F001C4BFFFFE5398000007D83637880010014382100107C0803A64E800020E9
+S208010000112233444C
+S30A00020000AABBCCDDEEF7
+
+S5030003F9
+S9030000FC
+";
+
+        assert_eq!(
+            parse(contents).unwrap(),
+            vec![
+                (
+                    0x0000,
+                    vec![
+                        0x7C, 0x08, 0x02, 0xA6, 0x90, 0x01, 0x00, 0x04, 0x94, 0x21, 0xFF, 0xF0,
+                        0x7C, 0x6C, 0x1B, 0x78, 0x7C, 0x8C, 0x23, 0x78, 0x3C, 0x60, 0x00, 0x00,
+                        0x38, 0x63, 0x00, 0x00
+                    ]
+                ),
+                (
+                    0x001C,
+                    vec![
+                        0x4B, 0xFF, 0xFF, 0xE5, 0x39, 0x80, 0x00, 0x00, 0x7D, 0x83, 0x63, 0x78,
+                        0x80, 0x01, 0x00, 0x14, 0x38, 0x21, 0x00, 0x10, 0x7C, 0x08, 0x03, 0xA6,
+                        0x4E, 0x80, 0x00, 0x20
+                    ]
+                ),
+                (0x01_0000, vec![0x11, 0x22, 0x33, 0x44]),
+                (0x0002_0000, vec![0xAA, 0xBB, 0xCC, 0xDD, 0xEE]),
+            ]
+        );
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let contents = "S00F000068656C6C6F202020202000003C\nS208010000112233444D\n";
+
+        assert_eq!(
+            parse(contents),
+            Err(SrecError::ChecksumMismatch {
+                line: 2,
+                expected: 0x4C,
+                found: 0x4D
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_records() {
+        assert_eq!(
+            parse(":0400000001020304F2"),
+            Err(SrecError::MissingStartCode { line: 1 })
+        );
+        assert_eq!(
+            parse("\nS4030000FC"),
+            Err(SrecError::UnknownRecordType {
+                line: 2,
+                record_type: '4'
+            })
+        );
+        assert_eq!(
+            parse("S1050000GG00FA"),
+            Err(SrecError::InvalidHexDigits { line: 1 })
+        );
+        assert_eq!(
+            parse("S10600000102F6"),
+            Err(SrecError::InvalidLength { line: 1 })
+        );
+    }
+}
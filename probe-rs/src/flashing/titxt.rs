@@ -0,0 +1,155 @@
+//! Parser for the TI-TXT format, which is used by the Texas Instruments toolchains and programmers.
+//!
+//! A section starts with `@ADDR`, followed by lines of hex bytes which are separated by spaces.
+//! The file ends with a `q`.
+
+/// An error in a TI-TXT file.
+#[derive(Debug, thiserror::Error, docsplay::Display, PartialEq, Eq)]
+pub enum TiTxtError {
+    /// Line {line}: the section address is not a hexadecimal number.
+    InvalidAddress {
+        /// The line of the file, starting at 1.
+        line: usize,
+    },
+
+    /// Line {line}: '{value}' is not a hexadecimal byte.
+    InvalidByte {
+        /// The line of the file, starting at 1.
+        line: usize,
+        /// The value which couldn't be parsed.
+        value: String,
+    },
+
+    /// Line {line}: data has to follow a section address.
+    MissingAddress {
+        /// The line of the file, starting at 1.
+        line: usize,
+    },
+
+    /// The file ends without a 'q', it might be truncated.
+    MissingEndOfFile,
+}
+
+/// Returns `true` if the contents look like a TI-TXT file: the first line is a section address,
+/// and the last line is the `q` which ends the file.
+///
+/// The file extension `.txt` is too common to select the format on its own.
+pub fn is_titxt(contents: &[u8]) -> bool {
+    let Ok(contents) = std::str::from_utf8(contents) else {
+        return false;
+    };
+    let mut lines = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+
+    let starts_with_address = lines.next().is_some_and(|line| line.starts_with('@'));
+    let ends_with_q = lines
+        .last()
+        .is_some_and(|line| line.eq_ignore_ascii_case("q"));
+
+    starts_with_address && ends_with_q
+}
+
+/// Parses a TI-TXT file, and returns the address and data of each section.
+///
+/// The format has no checksums, so only the syntax can be validated.
+pub(super) fn parse(contents: &str) -> Result<Vec<(u64, Vec<u8>)>, TiTxtError> {
+    let mut sections: Vec<(u64, Vec<u8>)> = Vec::new();
+
+    for (index, text) in contents.lines().enumerate() {
+        let line = index + 1;
+        let text = text.trim();
+
+        if text.eq_ignore_ascii_case("q") {
+            return Ok(sections
+                .into_iter()
+                .filter(|(_, data)| !data.is_empty())
+                .collect());
+        }
+
+        if let Some(address) = text.strip_prefix('@') {
+            let address = u64::from_str_radix(address.trim(), 16)
+                .map_err(|_| TiTxtError::InvalidAddress { line })?;
+            sections.push((address, Vec::new()));
+            continue;
+        }
+
+        for value in text.split_whitespace() {
+            let Some((_, data)) = sections.last_mut() else {
+                return Err(TiTxtError::MissingAddress { line });
+            };
+
+            let byte = (value.len() == 2)
+                .then(|| u8::from_str_radix(value, 16).ok())
+                .flatten()
+                .ok_or_else(|| TiTxtError::InvalidByte {
+                    line,
+                    value: value.to_string(),
+                })?;
+            data.push(byte);
+        }
+    }
+
+    Err(TiTxtError::MissingEndOfFile)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_sections() {
+        let contents = "\
+@F000
+31 40 00 03 B2 40 80 5A 20 01 D2 D3 22 00 D2 E3
+21 00
+@FFFE
+00 F0
+q
+";
+
+        assert_eq!(
+            parse(contents).unwrap(),
+            vec![
+                (
+                    0xF000,
+                    vec![
+                        0x31, 0x40, 0x00, 0x03, 0xB2, 0x40, 0x80, 0x5A, 0x20, 0x01, 0xD2, 0xD3,
+                        0x22, 0x00, 0xD2, 0xE3, 0x21, 0x00
+                    ]
+                ),
+                (0xFFFE, vec![0x00, 0xF0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn detect_titxt() {
+        assert!(is_titxt(b"@F000\n00 11\nq\n"));
+        assert!(is_titxt(b"\r\n@F000\r\n00 11\r\nQ\r\n\r\n"));
+        assert!(!is_titxt(b"Release notes\n@F000\nq\n"));
+        assert!(!is_titxt(b"@F000\n00 11\n"));
+        assert!(!is_titxt(&[0x40, 0xff, 0x0a, 0x71]));
+    }
+
+    #[test]
+    fn invalid_files() {
+        assert_eq!(parse("@F000\n00 11\n"), Err(TiTxtError::MissingEndOfFile));
+        assert_eq!(
+            parse("00 11\nq\n"),
+            Err(TiTxtError::MissingAddress { line: 1 })
+        );
+        assert_eq!(
+            parse("@F00G\nq\n"),
+            Err(TiTxtError::InvalidAddress { line: 1 })
+        );
+        assert_eq!(
+            parse("@F000\n00 11\n22 3\nq\n"),
+            Err(TiTxtError::InvalidByte {
+                line: 3,
+                value: "3".to_string()
+            })
+        );
+    }
+}
@@ -2,3 +2,4 @@
 
 pub use crate::probe::fake_probe::{FakeProbe, Operation};
 pub use crate::probe::list::ProbeLister;
+pub use crate::probe::virtual_probe::VirtualProbe;
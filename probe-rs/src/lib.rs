@@ -91,7 +91,7 @@ mod test;
 
 pub use crate::config::{CoreType, InstructionSet, Target};
 pub use crate::core::{
-    dump::{CoreDump, CoreDumpError},
+    dump::{memory_to_elf, CoreDump, CoreDumpError},
     exception_handler_for_core, Architecture, BreakpointCause, Core, CoreInformation,
     CoreInterface, CoreRegister, CoreRegisters, CoreState, CoreStatus, HaltReason,
     MemoryMappedRegister, RegisterId, RegisterRole, RegisterValue, SpecificCoreState,
@@ -100,6 +100,8 @@ pub use crate::core::{
 pub use crate::error::Error;
 pub use crate::memory::MemoryInterface;
 pub use crate::semihosting::{
-    ExitErrorDetails, GetCommandLineRequest, SemihostingCommand, UnknownCommandDetails,
+    ClockRequest, CloseRequest, ErrnoRequest, ExitErrorDetails, FileLengthRequest,
+    GetCommandLineRequest, IsTtyRequest, OpenRequest, ReadRequest, SeekRequest, SemihostingCommand,
+    TimeRequest, UnknownCommandDetails, WriteConsoleRequest, WriteRequest,
 };
 pub use crate::session::{Permissions, Session};
@@ -9,15 +9,22 @@ pub mod fake_probe;
 pub mod ftdi;
 pub mod jlink;
 pub mod list;
+pub mod power;
+pub mod remote;
+pub mod replay;
+pub mod simulator;
 pub mod stlink;
+pub mod uart;
+pub mod virtual_probe;
 pub mod wlink;
 
+use crate::architecture::arm::multidrop::{self, MultidropDebugPort};
 use crate::architecture::arm::sequences::{ArmDebugSequence, DefaultArmSequence};
-use crate::architecture::arm::ArmError;
 use crate::architecture::arm::{
     communication_interface::{DapProbe, UninitializedArmProbe},
     PortType, SwoAccess,
 };
+use crate::architecture::arm::{ArmError, DpAddress};
 use crate::architecture::riscv::communication_interface::{
     RiscvCommunicationInterface, RiscvError,
 };
@@ -27,12 +34,15 @@ use crate::architecture::xtensa::communication_interface::{
 use crate::config::RegistryError;
 use crate::config::TargetSelector;
 use crate::probe::common::IdCode;
+use crate::probe::power::TargetPowerVoltage;
+use crate::probe::uart::UartAccess;
 use crate::{Error, Permissions, Session};
 use nusb::DeviceInfo;
 use probe_rs_target::ScanChainElement;
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Used to log warnings when the measured target voltage is
 /// lower than 1.4V, if at all measurable.
@@ -141,6 +151,9 @@ pub enum DebugProbeError {
     /// The probe does not support he requested speed setting ({0} kHz).
     UnsupportedSpeed(u32),
 
+    /// The probe cannot supply {0} to the target.
+    UnsupportedTargetPowerVoltage(TargetPowerVoltage),
+
     /// You need to be attached to the target to perform this action.
     ///
     /// The debug probe did not yet perform the init sequence.
@@ -230,6 +243,8 @@ pub enum ProbeCreationError {
 pub struct Probe {
     inner: Box<dyn DebugProbe>,
     attached: bool,
+    auto_speed: bool,
+    debug_port: DpAddress,
 }
 
 impl Probe {
@@ -238,6 +253,8 @@ impl Probe {
         Self {
             inner: Box::new(probe),
             attached: false,
+            auto_speed: false,
+            debug_port: DpAddress::Default,
         }
     }
 
@@ -245,6 +262,8 @@ impl Probe {
         Self {
             inner: probe,
             attached: true,
+            auto_speed: false,
+            debug_port: DpAddress::Default,
         }
     }
 
@@ -253,9 +272,26 @@ impl Probe {
         Probe {
             inner: probe,
             attached: false,
+            auto_speed: false,
+            debug_port: DpAddress::Default,
         }
     }
 
+    /// Records all further communication with the probe to the file at `path`.
+    ///
+    /// The recording can be played back with [`ReplayProbe`](replay::ReplayProbe), see
+    /// [`RecordingProbe`](replay::RecordingProbe).
+    pub fn record_to(self, path: impl AsRef<std::path::Path>) -> Result<Self, DebugProbeError> {
+        let recording = replay::RecordingProbe::create(self.inner, path)?;
+
+        Ok(Self {
+            inner: Box::new(recording),
+            attached: self.attached,
+            auto_speed: self.auto_speed,
+            debug_port: self.debug_port,
+        })
+    }
+
     /// Get the human readable name for the probe.
     pub fn get_name(&self) -> String {
         self.inner.get_name().to_string()
@@ -388,6 +424,13 @@ impl Probe {
         }
     }
 
+    /// Enable or disable sending several independent commands in a single USB transfer.
+    ///
+    /// See [`DebugProbe::set_command_queuing`] for more information.
+    pub fn set_command_queuing(&mut self, enabled: bool) -> Result<(), DebugProbeError> {
+        self.inner.set_command_queuing(enabled)
+    }
+
     /// Get the currently used maximum speed for the debug protocol in kHz.
     ///
     /// Not all probes report which speed is used, meaning this value is not
@@ -397,6 +440,54 @@ impl Probe {
         self.inner.speed_khz()
     }
 
+    /// Select the protocol speed automatically when attaching to an ARM target.
+    ///
+    /// The session starts at the highest speed the probe accepts, and steps down until
+    /// the link to the target passes a short test. During the session, the speed is
+    /// lowered once if errors pile up.
+    ///
+    /// See [`crate::architecture::arm::link_quality`] for more information.
+    pub fn set_auto_speed(&mut self, enabled: bool) {
+        self.auto_speed = enabled;
+    }
+
+    /// Check if the protocol speed is selected automatically.
+    pub fn auto_speed(&self) -> bool {
+        self.auto_speed
+    }
+
+    /// Select the debug port on an SWD multi-drop bus to use for the session.
+    ///
+    /// The debug port is used by all cores for which the target description
+    /// does not select one, and for the auto-detection of the target.
+    pub fn select_debug_port(&mut self, debug_port: DpAddress) {
+        self.debug_port = debug_port;
+    }
+
+    /// The debug port selected with [`Probe::select_debug_port`].
+    pub fn selected_debug_port(&self) -> DpAddress {
+        self.debug_port
+    }
+
+    /// Scan the SWD multi-drop bus for debug ports, by trying each of the `TARGETSEL` values
+    /// of `candidates`.
+    ///
+    /// The probe has to be attached using SWD. See [`multidrop::targetsel_candidates`] for the
+    /// usual candidates.
+    pub fn scan_multidrop(&mut self, candidates: &[u32]) -> Result<Vec<MultidropDebugPort>, Error> {
+        if !self.attached {
+            return Err(DebugProbeError::NotAttached.into());
+        }
+
+        let interface = self
+            .try_as_dap_probe()
+            .ok_or(DebugProbeError::InterfaceNotAvailable {
+                interface_name: "ARM",
+            })?;
+
+        Ok(multidrop::scan(interface, candidates)?)
+    }
+
     /// Check if the probe has an interface to
     /// debug Xtensa chips.
     pub fn has_xtensa_interface(&self) -> bool {
@@ -479,6 +570,20 @@ impl Probe {
         self.inner.get_swo_interface_mut()
     }
 
+    /// Gets a UART interface from the debug probe.
+    ///
+    /// This does not work on all probes.
+    pub fn get_uart_interface(&self) -> Option<&dyn UartAccess> {
+        self.inner.get_uart_interface()
+    }
+
+    /// Gets a mutable UART interface from the debug probe.
+    ///
+    /// This does not work on all probes.
+    pub fn get_uart_interface_mut(&mut self) -> Option<&mut dyn UartAccess> {
+        self.inner.get_uart_interface_mut()
+    }
+
     /// Gets a DAP interface from the debug probe.
     ///
     /// This does not work on all probes.
@@ -486,12 +591,68 @@ impl Probe {
         self.inner.try_as_dap_probe()
     }
 
+    /// Gets a JTAG interface from the debug probe.
+    ///
+    /// This does not work on all probes.
+    pub fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        self.inner.try_as_jtag_probe()
+    }
+
     /// Try reading the target voltage of via the connected voltage pin.
     ///
     /// This does not work on all probes.
     pub fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
         self.inner.get_target_voltage()
     }
+
+    /// The voltages this probe can supply to the target.
+    ///
+    /// This is empty if the probe cannot power the target.
+    pub fn target_power_voltages(&self) -> Vec<TargetPowerVoltage> {
+        self.inner.target_power_voltages()
+    }
+
+    /// Switch the power supply of the target on or off.
+    ///
+    /// This does not work on all probes.
+    pub fn set_target_power(
+        &mut self,
+        on: bool,
+        voltage: TargetPowerVoltage,
+    ) -> Result<(), DebugProbeError> {
+        self.inner.set_target_power(on, voltage)
+    }
+
+    /// Wait until the target voltage settled, e.g. after powering the target.
+    ///
+    /// Returns the settled voltage, or `Ok(None)` if the probe cannot read the target voltage.
+    /// Fails with [`DebugProbeError::Timeout`] if the voltage did not settle within `timeout`.
+    pub fn wait_for_target_voltage(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<f32>, DebugProbeError> {
+        let start = Instant::now();
+        let mut previous = None;
+
+        loop {
+            let Some(volts) = self.get_target_voltage()? else {
+                return Ok(None);
+            };
+
+            if previous.is_some_and(|previous| power::voltage_settled(previous, volts)) {
+                tracing::debug!("Target voltage settled at {:.2} V", volts);
+                return Ok(Some(volts));
+            }
+
+            if start.elapsed() > timeout {
+                tracing::warn!("Target voltage did not settle, last reading {:.2} V", volts);
+                return Err(DebugProbeError::Timeout);
+            }
+
+            previous = Some(volts);
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
 }
 
 /// An abstraction over a probe driver type.
@@ -554,6 +715,18 @@ pub trait DebugProbe: Send + fmt::Debug {
     ///
     fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError>;
 
+    /// Enable or disable sending several independent commands in a single USB transfer.
+    ///
+    /// Probes which support command queuing enable it by default. Disabling it is
+    /// mostly useful to measure its benefit, or to work around firmware bugs.
+    /// Probes which cannot queue commands return an error, whether enabling or disabling it.
+    fn set_command_queuing(&mut self, enabled: bool) -> Result<(), DebugProbeError> {
+        let _ = enabled;
+        Err(DebugProbeError::CommandNotSupportedByProbe {
+            command_name: "set_command_queuing",
+        })
+    }
+
     /// Attach to the chip.
     ///
     /// This should run all the necessary protocol init routines.
@@ -653,6 +826,20 @@ pub trait DebugProbe: Send + fmt::Debug {
         None
     }
 
+    /// Get a UART interface from the debug probe.
+    ///
+    /// This is not available on all debug probes.
+    fn get_uart_interface(&self) -> Option<&dyn UartAccess> {
+        None
+    }
+
+    /// Get a mutable UART interface from the debug probe.
+    ///
+    /// This is not available on all debug probes.
+    fn get_uart_interface_mut(&mut self) -> Option<&mut dyn UartAccess> {
+        None
+    }
+
     /// Boxes itself.
     fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe>;
 
@@ -663,11 +850,39 @@ pub trait DebugProbe: Send + fmt::Debug {
         None
     }
 
+    /// Try getting low-level access to the JTAG protocol of the given probe.
+    ///
+    /// This is not available on all probes.
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        None
+    }
+
     /// Reads the target voltage in Volts, if possible. Returns `Ok(None)`
     /// if the probe doesn’t support reading the target voltage.
     fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
         Ok(None)
     }
+
+    /// The voltages this probe can supply to the target, in the order of preference.
+    ///
+    /// This is empty if the probe cannot power the target.
+    fn target_power_voltages(&self) -> Vec<TargetPowerVoltage> {
+        vec![]
+    }
+
+    /// Switch the power supply of the target on or off.
+    ///
+    /// `voltage` has to be one of the [`DebugProbe::target_power_voltages`].
+    fn set_target_power(
+        &mut self,
+        on: bool,
+        voltage: TargetPowerVoltage,
+    ) -> Result<(), DebugProbeError> {
+        let _ = (on, voltage);
+        Err(DebugProbeError::CommandNotSupportedByProbe {
+            command_name: "set_target_power",
+        })
+    }
 }
 
 impl PartialEq for dyn ProbeFactory {
@@ -701,20 +916,26 @@ pub struct DebugProbeInfo {
 
     /// A reference to the [`ProbeFactory`] that created this info object.
     probe_factory: &'static dyn ProbeFactory,
+
+    /// The address of the probe server, if the probe is shared over TCP.
+    remote: Option<String>,
 }
 
 impl std::fmt::Display for DebugProbeInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
             f,
-            "{} (VID: {:04x}, PID: {:04x}, {}{})",
+            "{} (VID: {:04x}, PID: {:04x}, {}{}{})",
             self.identifier,
             self.vendor_id,
             self.product_id,
             self.serial_number
                 .as_ref()
                 .map_or("".to_owned(), |v| format!("Serial: {v}, ")),
-            self.probe_factory
+            self.probe_factory,
+            self.remote
+                .as_ref()
+                .map_or("".to_owned(), |v| format!(" at tcp://{v}")),
         )
     }
 }
@@ -736,6 +957,15 @@ impl DebugProbeInfo {
             serial_number,
             probe_factory,
             hid_interface,
+            remote: None,
+        }
+    }
+
+    /// Marks the probe as shared by the probe server at `address`.
+    pub(crate) fn with_remote(self, address: impl Into<String>) -> Self {
+        Self {
+            remote: Some(address.into()),
+            ..self
         }
     }
 
@@ -759,7 +989,7 @@ pub enum DebugProbeSelectorParseError {
     /// Could not parse VID or PID: {0}
     ParseInt(#[from] std::num::ParseIntError),
 
-    /// The format of the selector is invalid. Please use a string in the form `VID:PID:<Serial>`, where Serial is optional.
+    /// The format of the selector is invalid. Please use a string in the form `VID:PID:<Serial>`, where Serial is optional, optionally prefixed by `tcp://<Host>:<Port>/` for remote probes, or `remote_bitbang://<Host>:<Port>` or `jtag_vpi://<Host>:<Port>` for simulators.
     Format,
 }
 
@@ -770,6 +1000,12 @@ pub enum DebugProbeSelectorParseError {
 /// where the serial number is optional, and VID and PID are
 /// parsed as hexadecimal numbers.
 ///
+/// Probes shared by a probe server are selected with
+/// "tcp://HOST:PORT/VID:PID:SERIALNUMBER".
+///
+/// Simulated targets are selected with "remote_bitbang://HOST:PORT" or
+/// "jtag_vpi://HOST:PORT", see [`simulator`].
+///
 /// ## Example:
 ///
 /// ```
@@ -789,11 +1025,17 @@ pub struct DebugProbeSelector {
     pub product_id: u16,
     /// The the serial number of the debug probe to be used.
     pub serial_number: Option<String>,
+    /// The address of the probe server sharing the debug probe, see [`remote`].
+    pub remote: Option<String>,
+    /// The simulator exposing the JTAG port of a simulated target, see [`simulator`].
+    pub simulator: Option<simulator::SimulatorSelector>,
 }
 
 impl DebugProbeSelector {
     pub(crate) fn matches(&self, info: &DeviceInfo) -> bool {
-        info.vendor_id() == self.vendor_id
+        self.remote.is_none()
+            && self.simulator.is_none()
+            && info.vendor_id() == self.vendor_id
             && info.product_id() == self.product_id
             && self
                 .serial_number
@@ -806,12 +1048,41 @@ impl DebugProbeSelector {
 impl TryFrom<&str> for DebugProbeSelector {
     type Error = DebugProbeSelectorParseError;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
+        if let Some((protocol, address)) = simulator::SimulatorProtocol::from_url(value) {
+            if address.is_empty() {
+                return Err(DebugProbeSelectorParseError::Format);
+            }
+
+            return Ok(DebugProbeSelector {
+                vendor_id: 0,
+                product_id: 0,
+                serial_number: None,
+                remote: None,
+                simulator: Some(simulator::SimulatorSelector {
+                    protocol,
+                    address: address.to_string(),
+                }),
+            });
+        }
+
+        let (remote, value) = match value.strip_prefix("tcp://") {
+            Some(value) => {
+                let (address, value) = value
+                    .split_once('/')
+                    .ok_or(DebugProbeSelectorParseError::Format)?;
+                (Some(address.to_string()), value)
+            }
+            None => (None, value),
+        };
+
         let split = value.split(':').collect::<Vec<_>>();
         let mut selector = if split.len() > 1 {
             DebugProbeSelector {
                 vendor_id: u16::from_str_radix(split[0], 16)?,
                 product_id: u16::from_str_radix(split[1], 16)?,
                 serial_number: None,
+                remote,
+                simulator: None,
             }
         } else {
             return Err(DebugProbeSelectorParseError::Format);
@@ -845,6 +1116,8 @@ impl From<DebugProbeInfo> for DebugProbeSelector {
             vendor_id: selector.vendor_id,
             product_id: selector.product_id,
             serial_number: selector.serial_number,
+            remote: selector.remote,
+            simulator: None,
         }
     }
 }
@@ -855,6 +1128,8 @@ impl From<&DebugProbeInfo> for DebugProbeSelector {
             vendor_id: selector.vendor_id,
             product_id: selector.product_id,
             serial_number: selector.serial_number.clone(),
+            remote: selector.remote.clone(),
+            simulator: None,
         }
     }
 }
@@ -867,6 +1142,12 @@ impl From<&DebugProbeSelector> for DebugProbeSelector {
 
 impl fmt::Display for DebugProbeSelector {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref simulator) = self.simulator {
+            return write!(f, "{simulator}");
+        }
+        if let Some(ref remote) = self.remote {
+            write!(f, "tcp://{remote}/")?;
+        }
         write!(f, "{:04x}:{:04x}", self.vendor_id, self.product_id)?;
         if let Some(ref sn) = self.serial_number {
             write!(f, ":{sn}")?;
@@ -1236,4 +1517,20 @@ mod test {
         assert!(probe_info.is_probe_type::<ftdi::FtdiProbeFactory>());
         assert!(!probe_info.is_probe_type::<espusbjtag::EspUsbJtagFactory>());
     }
+
+    #[test]
+    fn parse_remote_selector() {
+        let selector: DebugProbeSelector = "tcp://lab-1:6250/0d28:0204:ABC".parse().unwrap();
+
+        assert_eq!(selector.remote.as_deref(), Some("lab-1:6250"));
+        assert_eq!(selector.vendor_id, 0x0d28);
+        assert_eq!(selector.product_id, 0x0204);
+        assert_eq!(selector.serial_number.as_deref(), Some("ABC"));
+        assert_eq!(selector.to_string(), "tcp://lab-1:6250/0d28:0204:ABC");
+
+        assert!(matches!(
+            "tcp://lab-1:6250".parse::<DebugProbeSelector>(),
+            Err(DebugProbeSelectorParseError::Format)
+        ));
+    }
 }
@@ -66,7 +66,7 @@ impl Request for TestDomainTimeCommand {
     }
 }
 
-info_command!(0xFE, UartReceiveBufferSizeCommand, u32);
+info_command!(0xFB, UartReceiveBufferSizeCommand, u32);
 info_command!(0xFC, UartTransmitBufferSizeCommand, u32);
 info_command!(0xFD, SWOTraceBufferSizeCommand, u32);
 info_command!(0xFE, PacketCountCommand, u8);
@@ -130,10 +130,10 @@ pub struct Capabilities {
     pub(crate) _jtag_implemented: bool,
     pub(crate) swo_uart_implemented: bool,
     pub(crate) swo_manchester_implemented: bool,
-    pub(crate) _atomic_commands_implemented: bool,
+    pub(crate) atomic_commands_implemented: bool,
     pub(crate) _test_domain_timer_implemented: bool,
     pub(crate) swo_streaming_trace_implemented: bool,
-    pub(crate) _uart_communication_port_implemented: bool,
+    pub(crate) uart_communication_port_implemented: bool,
     pub(crate) uart_com_port_implemented: bool,
 }
 
@@ -147,10 +147,10 @@ impl ParseFromResponse for Capabilities {
                 _jtag_implemented: buffer[1] & 0x02 > 0,
                 swo_uart_implemented: buffer[1] & 0x04 > 0,
                 swo_manchester_implemented: buffer[1] & 0x08 > 0,
-                _atomic_commands_implemented: buffer[1] & 0x10 > 0,
+                atomic_commands_implemented: buffer[1] & 0x10 > 0,
                 _test_domain_timer_implemented: buffer[1] & 0x20 > 0,
                 swo_streaming_trace_implemented: buffer[1] & 0x40 > 0,
-                _uart_communication_port_implemented: buffer[1] & 0x80 > 0,
+                uart_communication_port_implemented: buffer[1] & 0x80 > 0,
                 uart_com_port_implemented: false,
             };
 
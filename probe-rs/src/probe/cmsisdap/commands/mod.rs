@@ -4,11 +4,13 @@ pub mod swd;
 pub mod swj;
 pub mod swo;
 pub mod transfer;
+pub mod uart;
 
 use crate::probe::cmsisdap::commands::general::info::PacketSizeCommand;
 use crate::probe::usb_util::InterfaceExt;
 use crate::probe::DebugProbeError;
 use std::io::ErrorKind;
+use std::ops::Range;
 use std::str::Utf8Error;
 use std::time::Duration;
 
@@ -33,6 +35,12 @@ pub enum CmsisDapError {
     SwoModeNotAvailable,
     #[error("USB Error reading SWO data.")]
     SwoReadError(#[source] std::io::Error),
+    #[error("This probe does not support the UART commands")]
+    UartNotAvailable,
+    #[error("Requested UART frame format is not supported by the probe")]
+    UartConfigurationNotSupported,
+    #[error("The UART has not been enabled")]
+    UartNotEnabled,
     #[error("Could not determine a suitable packet size for this probe")]
     NoPacketSize,
     #[error("Invalid IDCODE detected")]
@@ -94,6 +102,16 @@ pub enum CmsisDapDevice {
 }
 
 impl CmsisDapDevice {
+    /// The size of a buffer holding a packet, including the HID report ID.
+    fn buffer_len(&self) -> usize {
+        match self {
+            CmsisDapDevice::V1 { report_size, .. } => *report_size + 1,
+            CmsisDapDevice::V2 {
+                max_packet_size, ..
+            } => *max_packet_size + 1,
+        }
+    }
+
     /// Read from the probe into `buf`, returning the number of bytes read on success.
     fn read(&self, buf: &mut [u8]) -> Result<usize, SendError> {
         match self {
@@ -305,7 +323,6 @@ pub enum CommandId {
     UartTransport = 0x1F,
     UartConfigure = 0x20,
     UartControl = 0x22,
-    UartStatus = 0x23,
     UartTransfer = 0x21,
 }
 
@@ -326,7 +343,7 @@ pub(crate) fn send_command<Req: Request>(
     device: &mut CmsisDapDevice,
     request: Req,
 ) -> Result<Req::Response, CmsisDapError> {
-    send_command_inner(device, request).map_err(|e| CmsisDapError::Send {
+    send_command_inner(device, &request).map_err(|e| CmsisDapError::Send {
         command_id: Req::COMMAND_ID,
         source: e,
     })
@@ -334,19 +351,12 @@ pub(crate) fn send_command<Req: Request>(
 
 fn send_command_inner<Req: Request>(
     device: &mut CmsisDapDevice,
-    request: Req,
+    request: &Req,
 ) -> Result<Req::Response, SendError> {
     // Size the buffer for the maximum packet size.
     // On v1, we always send this full-sized report, while
     // on v2 we can truncate to just the required data.
-    // Add one byte for HID report ID.
-    let buffer_len: usize = match device {
-        CmsisDapDevice::V1 { report_size, .. } => *report_size + 1,
-        CmsisDapDevice::V2 {
-            max_packet_size, ..
-        } => *max_packet_size + 1,
-    };
-    let mut buffer = vec![0; buffer_len];
+    let mut buffer = vec![0; device.buffer_len()];
 
     // Leave byte 0 as the HID report, and write the command and request to the buffer.
     buffer[1] = Req::COMMAND_ID as u8;
@@ -380,6 +390,185 @@ fn send_command_inner<Req: Request>(
     }
 }
 
+/// A request which can be sent together with others in a single
+/// DAP_ExecuteCommands or DAP_QueueCommands packet.
+///
+/// The responses of such packets are concatenated, so the size of each
+/// response has to be known to split them up again.
+pub(crate) trait QueueableRequest: Request {
+    /// The largest possible size of the response, excluding the command ID.
+    fn max_response_size(&self) -> usize;
+
+    /// The size of the response at the start of `buffer`, excluding the command ID.
+    fn response_size(&self, buffer: &[u8]) -> Result<usize, SendError>;
+}
+
+/// Send several independent requests to the probe with as few USB round trips as possible.
+///
+/// As many requests as fit are packed into each DAP_ExecuteCommands packet, and up to
+/// `queue_depth` packets are sent as DAP_QueueCommands before reading any response.
+/// A `queue_depth` of zero sends the requests one by one, for probes which don't
+/// support these commands.
+pub(crate) fn send_commands<Req: QueueableRequest>(
+    device: &mut CmsisDapDevice,
+    requests: &[Req],
+    queue_depth: usize,
+) -> Result<Vec<Req::Response>, CmsisDapError> {
+    if queue_depth == 0 {
+        return requests
+            .iter()
+            .map(|request| {
+                send_command_inner(device, request).map_err(|e| CmsisDapError::Send {
+                    command_id: Req::COMMAND_ID,
+                    source: e,
+                })
+            })
+            .collect();
+    }
+
+    send_commands_inner(device, requests, queue_depth).map_err(|e| {
+        // Responses to packets which were already queued would
+        // otherwise be mistaken for responses to the next request.
+        device.drain();
+        CmsisDapError::Send {
+            command_id: CommandId::ExecuteCommands,
+            source: e,
+        }
+    })
+}
+
+fn send_commands_inner<Req: QueueableRequest>(
+    device: &mut CmsisDapDevice,
+    requests: &[Req],
+    queue_depth: usize,
+) -> Result<Vec<Req::Response>, SendError> {
+    let buffer_len = device.buffer_len();
+    let packets = pack_requests(requests, buffer_len - 1)?;
+
+    let mut responses = Vec::with_capacity(requests.len());
+    for window in packets.chunks(queue_depth) {
+        for (i, packet) in window.iter().enumerate() {
+            // The probe starts executing the queued packets once it receives one
+            // which is not DAP_QueueCommands, so only the last one is sent as such.
+            let command_id = if i + 1 == window.len() {
+                CommandId::ExecuteCommands
+            } else {
+                CommandId::QueueCommands
+            };
+
+            let mut buffer = vec![0; buffer_len];
+            buffer[1] = command_id as u8;
+            buffer[2] = packet.len() as u8;
+            let mut size = 3;
+            for request in &requests[packet.clone()] {
+                buffer[size] = Req::COMMAND_ID as u8;
+                size += 1 + request.to_bytes(&mut buffer[size + 1..])?;
+            }
+
+            if let CmsisDapDevice::V1 { .. } = device {
+                size = buffer_len;
+            }
+
+            let _ = device.write(&buffer[..size])?;
+            trace_buffer("Transmit buffer", &buffer[..size]);
+        }
+
+        for packet in window {
+            let mut buffer = vec![0; buffer_len];
+            let bytes_read = device.read(&mut buffer)?;
+            let response_data = &buffer[..bytes_read];
+            trace_buffer("Receive buffer", response_data);
+
+            responses.extend(parse_execute_response(
+                &requests[packet.clone()],
+                response_data,
+            )?);
+        }
+    }
+
+    Ok(responses)
+}
+
+/// Split `requests` into groups which fit into a single DAP_ExecuteCommands
+/// packet of `packet_size` bytes, both for the request and the response.
+fn pack_requests<Req: QueueableRequest>(
+    requests: &[Req],
+    packet_size: usize,
+) -> Result<Vec<Range<usize>>, SendError> {
+    // Each packet starts with the command ID and the number of commands.
+    const HEADER_SIZE: usize = 2;
+
+    let mut packets = Vec::new();
+    let mut scratch = vec![0; packet_size];
+    let mut start = 0;
+    let mut request_size = HEADER_SIZE;
+    let mut response_size = HEADER_SIZE;
+
+    for (i, request) in requests.iter().enumerate() {
+        let size = 1 + request.to_bytes(&mut scratch)?;
+        let max_response = 1 + request.max_response_size();
+
+        let full = request_size + size > packet_size
+            || response_size + max_response > packet_size
+            || i - start == u8::MAX as usize;
+        if full && i > start {
+            packets.push(start..i);
+            start = i;
+            request_size = HEADER_SIZE;
+            response_size = HEADER_SIZE;
+        }
+
+        request_size += size;
+        response_size += max_response;
+    }
+
+    if start < requests.len() {
+        packets.push(start..requests.len());
+    }
+
+    Ok(packets)
+}
+
+/// Parse the concatenated responses of a DAP_ExecuteCommands packet.
+fn parse_execute_response<Req: QueueableRequest>(
+    requests: &[Req],
+    response: &[u8],
+) -> Result<Vec<Req::Response>, SendError> {
+    // Queued packets are answered like DAP_ExecuteCommands.
+    match response.first() {
+        Some(&id) if id == CommandId::ExecuteCommands as u8 => {}
+        Some(&id) if id == CommandId::QueueCommands as u8 => {}
+        Some(&id) => return Err(SendError::CommandIdMismatch(id)),
+        None => return Err(SendError::NotEnoughData),
+    }
+
+    match response.get(1) {
+        Some(&count) if count as usize == requests.len() => {}
+        Some(_) => return Err(SendError::UnexpectedAnswer),
+        None => return Err(SendError::NotEnoughData),
+    }
+
+    let mut offset = 2;
+    let mut responses = Vec::with_capacity(requests.len());
+    for request in requests {
+        match response.get(offset) {
+            Some(&id) if id == Req::COMMAND_ID as u8 => {}
+            Some(&id) => return Err(SendError::CommandIdMismatch(id)),
+            None => return Err(SendError::NotEnoughData),
+        }
+
+        let data = &response[offset + 1..];
+        let size = request.response_size(data)?;
+        if size > data.len() {
+            return Err(SendError::NotEnoughData);
+        }
+        responses.push(request.parse_response(&data[..size])?);
+        offset += 1 + size;
+    }
+
+    Ok(responses)
+}
+
 /// Trace log a buffer, including only the first trailing zero.
 ///
 /// This is useful for the CMSIS-DAP USB buffers, which often contain many trailing
@@ -393,3 +582,87 @@ fn trace_buffer(name: &str, buf: &[u8]) {
         tracing::trace!("{}: {:02X?}...", name, &buf[..end]);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::transfer::TransferBlockRequest;
+    use super::*;
+    use crate::architecture::arm::PortType;
+
+    #[test]
+    fn pack_requests_splits_by_response_size() {
+        // Each read of 4 words needs 1 + 3 + 16 bytes of response.
+        let requests: Vec<_> = (0..4)
+            .map(|_| TransferBlockRequest::read_request(0x0C, PortType::AccessPort, 4))
+            .collect();
+
+        let packets = pack_requests(&requests, 64).unwrap();
+
+        assert_eq!(packets, vec![0..3, 3..4]);
+    }
+
+    #[test]
+    fn pack_requests_splits_by_request_size() {
+        // Each write of 4 words needs 1 + 4 + 16 bytes of request.
+        let requests: Vec<_> = (0..4)
+            .map(|_| TransferBlockRequest::write_request(0x0C, PortType::AccessPort, vec![0; 4]))
+            .collect();
+
+        let packets = pack_requests(&requests, 64).unwrap();
+
+        assert_eq!(packets, vec![0..2, 2..4]);
+    }
+
+    #[test]
+    fn parse_execute_response_splits_responses() {
+        let requests = [
+            TransferBlockRequest::read_request(0x0C, PortType::AccessPort, 1),
+            TransferBlockRequest::write_request(0x0C, PortType::AccessPort, vec![0; 2]),
+        ];
+
+        let response = [
+            0x7F, 0x02, // DAP_ExecuteCommands, 2 commands
+            0x06, 0x01, 0x00, 0x01, 0x78, 0x56, 0x34, 0x12, // Read one word
+            0x06, 0x02, 0x00, 0x01, // Write two words
+        ];
+
+        let responses = parse_execute_response(&requests, &response).unwrap();
+
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].transfer_response, 1);
+        assert_eq!(responses[0].transfer_data, vec![0x1234_5678]);
+        assert_eq!(responses[1].transfer_response, 1);
+    }
+
+    #[test]
+    fn parse_execute_response_wrong_count() {
+        let requests = [
+            TransferBlockRequest::read_request(0x0C, PortType::AccessPort, 1),
+            TransferBlockRequest::read_request(0x0C, PortType::AccessPort, 1),
+        ];
+
+        let response = [0x7F, 0x01, 0x06, 0x01, 0x00, 0x01, 0x78, 0x56, 0x34, 0x12];
+
+        assert!(matches!(
+            parse_execute_response(&requests, &response),
+            Err(SendError::UnexpectedAnswer)
+        ));
+    }
+
+    #[test]
+    fn parse_execute_response_unsupported() {
+        let requests = [TransferBlockRequest::read_request(
+            0x0C,
+            PortType::AccessPort,
+            1,
+        )];
+
+        // Older firmware answers unknown commands with 0xFF.
+        let response = [0xFF];
+
+        assert!(matches!(
+            parse_execute_response(&requests, &response),
+            Err(SendError::CommandIdMismatch(0xFF))
+        ));
+    }
+}
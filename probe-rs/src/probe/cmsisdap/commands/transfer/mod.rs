@@ -1,6 +1,6 @@
 pub mod configure;
 
-use super::{CommandId, Request, SendError};
+use super::{CommandId, QueueableRequest, Request, SendError};
 use crate::architecture::arm::PortType;
 use scroll::{Pread, Pwrite, LE};
 
@@ -331,6 +331,27 @@ impl Request for TransferBlockRequest {
     }
 }
 
+impl QueueableRequest for TransferBlockRequest {
+    fn max_response_size(&self) -> usize {
+        match self.transfer_request.r_n_w {
+            RW::R => 3 + 4 * self.transfer_count as usize,
+            RW::W => 3,
+        }
+    }
+
+    fn response_size(&self, buffer: &[u8]) -> Result<usize, SendError> {
+        // Read data is only returned for the transfers which were executed.
+        let transfer_count: u16 = buffer
+            .pread_with(0, LE)
+            .map_err(|_| SendError::NotEnoughData)?;
+
+        Ok(match self.transfer_request.r_n_w {
+            RW::R => 3 + 4 * transfer_count as usize,
+            RW::W => 3,
+        })
+    }
+}
+
 impl TransferBlockRequest {
     pub(crate) fn write_request(address: u8, port: PortType, data: Vec<u32>) -> Self {
         let inner = InnerTransferBlockRequest {
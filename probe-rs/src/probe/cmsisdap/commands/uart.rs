@@ -0,0 +1,266 @@
+use scroll::{Pread, LE};
+
+use super::{CommandId, Request, SendError, Status};
+
+#[repr(u8)]
+#[allow(unused)]
+#[derive(Copy, Clone, Debug)]
+pub enum TransportRequest {
+    NoTransport = 0,
+    UsbComPort = 1,
+    DapCommand = 2,
+}
+
+impl Request for TransportRequest {
+    const COMMAND_ID: CommandId = CommandId::UartTransport;
+
+    type Response = TransportResponse;
+
+    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize, SendError> {
+        buffer[0] = *self as u8;
+        Ok(1)
+    }
+
+    fn parse_response(&self, buffer: &[u8]) -> Result<Self::Response, SendError> {
+        Ok(TransportResponse(Status::from_byte(buffer[0])?))
+    }
+}
+
+#[derive(Debug)]
+pub struct TransportResponse(pub(crate) Status);
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug)]
+pub enum Parity {
+    None = 0,
+    Odd = 1,
+    Even = 2,
+}
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug)]
+pub enum StopBits {
+    One = 0,
+    Two = 2,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct ConfigureRequest {
+    /// Number of data bits, between 5 and 8.
+    pub(crate) data_bits: u8,
+    pub(crate) parity: Parity,
+    pub(crate) stop_bits: StopBits,
+    pub(crate) baud: u32,
+}
+
+impl Request for ConfigureRequest {
+    const COMMAND_ID: CommandId = CommandId::UartConfigure;
+
+    type Response = ConfigureResponse;
+
+    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize, SendError> {
+        assert!(
+            buffer.len() >= 5,
+            "Buffer for CMSIS-DAP command is too small. This is a bug, please report it."
+        );
+        buffer[0] =
+            (self.data_bits & 0x0F) | ((self.parity as u8) << 4) | ((self.stop_bits as u8) << 6);
+        buffer[1..5].copy_from_slice(&self.baud.to_le_bytes());
+        Ok(5)
+    }
+
+    fn parse_response(&self, buffer: &[u8]) -> Result<Self::Response, SendError> {
+        if buffer.len() < 5 {
+            return Err(SendError::NotEnoughData);
+        }
+
+        let baud: u32 = buffer
+            .pread_with(1, LE)
+            .map_err(|_| SendError::NotEnoughData)?;
+
+        Ok(ConfigureResponse {
+            error: ConfigureError::from(buffer[0]),
+            baud,
+        })
+    }
+}
+
+/// Settings the probe could not apply.
+#[derive(Copy, Clone, Debug)]
+pub struct ConfigureError {
+    pub(crate) data_bits: bool,
+    pub(crate) parity: bool,
+    pub(crate) stop_bits: bool,
+}
+
+impl ConfigureError {
+    pub(crate) fn any(&self) -> bool {
+        self.data_bits || self.parity || self.stop_bits
+    }
+}
+
+impl From<u8> for ConfigureError {
+    fn from(value: u8) -> Self {
+        Self {
+            data_bits: value & (1 << 0) != 0,
+            parity: value & (1 << 1) != 0,
+            stop_bits: value & (1 << 2) != 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigureResponse {
+    pub(crate) error: ConfigureError,
+    /// The baud rate actually configured by the probe.
+    pub(crate) baud: u32,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ControlRequest {
+    pub(crate) rx_enable: bool,
+    pub(crate) rx_disable: bool,
+    pub(crate) rx_flush: bool,
+    pub(crate) tx_enable: bool,
+    pub(crate) tx_disable: bool,
+    pub(crate) tx_flush: bool,
+}
+
+impl Request for ControlRequest {
+    const COMMAND_ID: CommandId = CommandId::UartControl;
+
+    type Response = ControlResponse;
+
+    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize, SendError> {
+        buffer[0] = (self.rx_enable as u8)
+            | ((self.rx_disable as u8) << 1)
+            | ((self.rx_flush as u8) << 2)
+            | ((self.tx_enable as u8) << 4)
+            | ((self.tx_disable as u8) << 5)
+            | ((self.tx_flush as u8) << 6);
+        Ok(1)
+    }
+
+    fn parse_response(&self, buffer: &[u8]) -> Result<Self::Response, SendError> {
+        Ok(ControlResponse(Status::from_byte(buffer[0])?))
+    }
+}
+
+#[derive(Debug)]
+pub struct ControlResponse(pub(crate) Status);
+
+#[derive(Copy, Clone, Debug)]
+pub struct UartStatus {
+    pub(crate) _rx_enabled: bool,
+    pub(crate) rx_data_lost: bool,
+    pub(crate) framing_error: bool,
+    pub(crate) parity_error: bool,
+    pub(crate) _tx_enabled: bool,
+}
+
+impl From<u8> for UartStatus {
+    fn from(value: u8) -> Self {
+        Self {
+            _rx_enabled: value & (1 << 0) != 0,
+            rx_data_lost: value & (1 << 1) != 0,
+            framing_error: value & (1 << 2) != 0,
+            parity_error: value & (1 << 3) != 0,
+            _tx_enabled: value & (1 << 4) != 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TransferRequest<'a> {
+    pub(crate) data: &'a [u8],
+}
+
+impl Request for TransferRequest<'_> {
+    const COMMAND_ID: CommandId = CommandId::UartTransfer;
+
+    type Response = TransferResponse;
+
+    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize, SendError> {
+        assert!(
+            buffer.len() >= 2 + self.data.len(),
+            "Buffer for CMSIS-DAP command is too small. This is a bug, please report it."
+        );
+        buffer[0..2].copy_from_slice(&(self.data.len() as u16).to_le_bytes());
+        buffer[2..2 + self.data.len()].copy_from_slice(self.data);
+        Ok(2 + self.data.len())
+    }
+
+    fn parse_response(&self, buffer: &[u8]) -> Result<Self::Response, SendError> {
+        if buffer.len() < 5 {
+            return Err(SendError::NotEnoughData);
+        }
+
+        let tx_accepted: u16 = buffer
+            .pread_with(1, LE)
+            .map_err(|_| SendError::NotEnoughData)?;
+        let rx_count: u16 = buffer
+            .pread_with(3, LE)
+            .map_err(|_| SendError::NotEnoughData)?;
+
+        let start = 5;
+        let end = start + rx_count as usize;
+        if end > buffer.len() {
+            return Err(SendError::NotEnoughData);
+        }
+
+        Ok(TransferResponse {
+            status: UartStatus::from(buffer[0]),
+            tx_accepted,
+            data: buffer[start..end].to_vec(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct TransferResponse {
+    pub(crate) status: UartStatus,
+    /// Number of bytes of the request the probe queued for transmission.
+    pub(crate) tx_accepted: u16,
+    pub(crate) data: Vec<u8>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn configure_8n1() {
+        let request = ConfigureRequest {
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            baud: 115_200,
+        };
+
+        let mut buffer = [0u8; 5];
+        assert_eq!(request.to_bytes(&mut buffer).unwrap(), 5);
+        assert_eq!(buffer, [0x08, 0x00, 0xC2, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn transfer_response_with_data() {
+        let request = TransferRequest { data: b"hi" };
+
+        let response = request
+            .parse_response(&[0x11, 0x02, 0x00, 0x03, 0x00, b'a', b'b', b'c'])
+            .unwrap();
+
+        assert_eq!(response.tx_accepted, 2);
+        assert_eq!(response.data, b"abc");
+        assert!(!response.status.rx_data_lost);
+    }
+
+    #[test]
+    fn transfer_response_too_short() {
+        let request = TransferRequest { data: &[] };
+
+        let response = request.parse_response(&[0x11, 0x00, 0x00, 0x04, 0x00, b'a']);
+
+        assert!(matches!(response, Err(SendError::NotEnoughData)));
+    }
+}
@@ -12,9 +12,13 @@ use crate::{
     },
     probe::{
         cmsisdap::commands::{
-            general::info::{CapabilitiesCommand, PacketCountCommand, SWOTraceBufferSizeCommand},
-            CmsisDapError,
+            general::info::{
+                CapabilitiesCommand, PacketCountCommand, SWOTraceBufferSizeCommand,
+                UartTransmitBufferSizeCommand,
+            },
+            CmsisDapError, QueueableRequest, SendError,
         },
+        uart::{UartAccess, UartConfig, UartParity, UartStopBits},
         BatchCommand, DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector,
         JtagChainItem, ProbeFactory, WireProtocol,
     },
@@ -47,10 +51,9 @@ use commands::{
     swo,
     transfer::{
         configure::{ConfigureRequest, ConfigureResponse},
-        Ack, InnerTransferRequest, TransferBlockRequest, TransferBlockResponse, TransferRequest,
-        RW,
+        Ack, InnerTransferRequest, TransferBlockRequest, TransferRequest, RW,
     },
-    CmsisDapDevice, Status,
+    uart, CmsisDapDevice, Status,
 };
 use probe_rs_target::ScanChainElement;
 
@@ -92,9 +95,15 @@ pub struct CmsisDap {
     packet_size: u16,
     packet_count: u8,
     capabilities: Capabilities,
+    /// Whether independent commands are sent with DAP_ExecuteCommands and DAP_QueueCommands.
+    command_queuing: bool,
     swo_buffer_size: Option<usize>,
     swo_active: bool,
     swo_streaming: bool,
+    uart_tx_buffer_size: Option<usize>,
+    uart_active: bool,
+    /// UART data received while transmitting, returned by the next read.
+    uart_rx: Vec<u8>,
     connected: bool,
 
     /// Speed in kHz
@@ -111,9 +120,12 @@ impl std::fmt::Debug for CmsisDap {
             .field("packet_size", &self.packet_size)
             .field("packet_count", &self.packet_count)
             .field("capabilities", &self.capabilities)
+            .field("command_queuing", &self.command_queuing)
             .field("swo_buffer_size", &self.swo_buffer_size)
             .field("swo_active", &self.swo_active)
             .field("swo_streaming", &self.swo_streaming)
+            .field("uart_tx_buffer_size", &self.uart_tx_buffer_size)
+            .field("uart_active", &self.uart_active)
             .field("speed_khz", &self.speed_khz)
             .finish()
     }
@@ -139,6 +151,12 @@ impl CmsisDap {
             swo_buffer_size = Some(swo_size as usize);
             tracing::debug!("Probe SWO buffer size: {}", swo_size);
         }
+        let mut uart_tx_buffer_size = None;
+        if caps.uart_communication_port_implemented {
+            let uart_size = commands::send_command(&mut device, UartTransmitBufferSizeCommand {})?;
+            uart_tx_buffer_size = Some(uart_size as usize);
+            tracing::debug!("Probe UART transmit buffer size: {}", uart_size);
+        }
 
         Ok(Self {
             device,
@@ -148,9 +166,13 @@ impl CmsisDap {
             packet_count,
             packet_size,
             capabilities: caps,
+            command_queuing: caps.atomic_commands_implemented,
             swo_buffer_size,
             swo_active: false,
             swo_streaming: false,
+            uart_tx_buffer_size,
+            uart_active: false,
+            uart_rx: Vec::new(),
             connected: false,
             speed_khz: 1_000,
             scan_chain: None,
@@ -572,6 +594,52 @@ impl CmsisDap {
         Err(DapError::FaultResponse.into())
     }
 
+    /// Send independent requests, packing and pipelining them if the probe supports it.
+    ///
+    /// Falls back to sending them one by one if the probe rejects DAP_ExecuteCommands,
+    /// as some firmware advertises the capability without implementing it.
+    fn send_queued<Req: QueueableRequest>(
+        &mut self,
+        requests: &[Req],
+    ) -> Result<Vec<Req::Response>, CmsisDapError> {
+        let queue_depth = if self.command_queuing {
+            usize::max(self.packet_count as usize, 1)
+        } else {
+            0
+        };
+
+        match commands::send_commands(&mut self.device, requests, queue_depth) {
+            Err(CmsisDapError::Send {
+                source: SendError::CommandIdMismatch(0xFF),
+                ..
+            }) if queue_depth > 0 => {
+                tracing::warn!(
+                    "Probe does not support DAP_ExecuteCommands, disabling command queuing"
+                );
+                self.command_queuing = false;
+                commands::send_commands(&mut self.device, requests, 0)
+            }
+            result => result,
+        }
+    }
+
+    /// The number of words which fit into a single DAP_TransferBlock packet.
+    fn max_block_words(&self) -> usize {
+        // the overhead for a single packet is 6 bytes
+        //
+        // [0]: HID overhead
+        // [1]: Category
+        // [2]: DAP Index
+        // [3]: Len 1
+        // [4]: Len 2
+        // [5]: Request type
+        //
+        // DAP_ExecuteCommands adds its own command ID and the number of commands.
+        let overhead = if self.command_queuing { 8 } else { 6 };
+
+        (self.packet_size as usize - overhead) / 4
+    }
+
     /// Add a BatchCommand to our current batch.
     ///
     /// If the BatchCommand is a Read, this will immediately process the batch
@@ -696,6 +764,52 @@ impl CmsisDap {
         }
     }
 
+    /// Set the UART port to use the requested transport.
+    fn set_uart_transport(
+        &mut self,
+        transport: uart::TransportRequest,
+    ) -> Result<(), DebugProbeError> {
+        let response = commands::send_command(&mut self.device, transport)?;
+        match response {
+            uart::TransportResponse(Status::DAPOk) => Ok(()),
+            uart::TransportResponse(Status::DAPError) => Err(CmsisDapError::ErrorResponse.into()),
+        }
+    }
+
+    /// Enable, disable or flush the UART receiver and transmitter.
+    fn uart_control(&mut self, request: uart::ControlRequest) -> Result<(), DebugProbeError> {
+        let response = commands::send_command(&mut self.device, request)?;
+        match response {
+            uart::ControlResponse(Status::DAPOk) => Ok(()),
+            uart::ControlResponse(Status::DAPError) => Err(CmsisDapError::ErrorResponse.into()),
+        }
+    }
+
+    /// Exchange UART data with the probe by sending a DAP_UART_Transfer request.
+    ///
+    /// Returns the number of bytes of `data` the probe accepted.
+    /// Received data is appended to the internal receive buffer.
+    fn uart_transfer(&mut self, data: &[u8]) -> Result<usize, DebugProbeError> {
+        // The request holds the command ID and a two byte length before the data.
+        let max_tx = usize::min(
+            self.packet_size as usize - 3,
+            self.uart_tx_buffer_size.unwrap_or(usize::MAX),
+        );
+        let data = &data[..data.len().min(max_tx)];
+
+        let response = commands::send_command(&mut self.device, uart::TransferRequest { data })?;
+        if response.status.rx_data_lost {
+            tracing::warn!("UART receive buffer of the probe overflowed, data was lost");
+        }
+        if response.status.framing_error || response.status.parity_error {
+            tracing::debug!("UART reception error: {:?}", response.status);
+        }
+        self.uart_rx.extend_from_slice(&response.data);
+
+        // Never report more bytes as sent than we handed to the probe.
+        Ok(usize::min(response.tx_accepted as usize, data.len()))
+    }
+
     fn connect_if_needed(&mut self) -> Result<(), DebugProbeError> {
         if self.connected {
             return Ok(());
@@ -755,6 +869,17 @@ impl DebugProbe for CmsisDap {
         Ok(())
     }
 
+    fn set_command_queuing(&mut self, enabled: bool) -> Result<(), DebugProbeError> {
+        if !self.capabilities.atomic_commands_implemented {
+            return Err(DebugProbeError::CommandNotSupportedByProbe {
+                command_name: "set_command_queuing",
+            });
+        }
+
+        self.command_queuing = enabled;
+        Ok(())
+    }
+
     /// Enters debug mode.
     #[tracing::instrument(skip(self))]
     fn attach(&mut self) -> Result<(), DebugProbeError> {
@@ -866,6 +991,22 @@ impl DebugProbe for CmsisDap {
         Some(self as _)
     }
 
+    fn get_uart_interface(&self) -> Option<&dyn UartAccess> {
+        if self.capabilities.uart_communication_port_implemented {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+
+    fn get_uart_interface_mut(&mut self) -> Option<&mut dyn UartAccess> {
+        if self.capabilities.uart_communication_port_implemented {
+            Some(self as _)
+        } else {
+            None
+        }
+    }
+
     fn try_get_arm_interface<'probe>(
         self: Box<Self>,
     ) -> Result<Box<dyn UninitializedArmProbe + 'probe>, (Box<dyn DebugProbe>, DebugProbeError)>
@@ -916,32 +1057,23 @@ impl RawDapAccess for CmsisDap {
     ) -> Result<(), ArmError> {
         self.process_batch()?;
 
-        // the overhead for a single packet is 6 bytes
-        //
-        // [0]: HID overhead
-        // [1]: Category
-        // [2]: DAP Index
-        // [3]: Len 1
-        // [4]: Len 2
-        // [5]: Request type
-        //
-
-        let max_packet_size_words = (self.packet_size - 6) / 4;
-
-        let data_chunk_len = max_packet_size_words as usize;
-
-        for (i, chunk) in values.chunks(data_chunk_len).enumerate() {
-            let request =
-                TransferBlockRequest::write_request(register_address, port, Vec::from(chunk));
+        let requests: Vec<_> = values
+            .chunks(self.max_block_words())
+            .map(|chunk| {
+                TransferBlockRequest::write_request(register_address, port, Vec::from(chunk))
+            })
+            .collect();
 
-            tracing::debug!("Transfer block: chunk={}, len={} bytes", i, chunk.len() * 4);
+        tracing::debug!(
+            "Transfer block: {} chunks, len={} bytes",
+            requests.len(),
+            values.len() * 4
+        );
 
-            let resp: TransferBlockResponse =
-                commands::send_command(&mut self.device, request).map_err(DebugProbeError::from)?;
+        let responses = self.send_queued(&requests).map_err(DebugProbeError::from)?;
 
-            if resp.transfer_response != 1 {
-                return Err(DebugProbeError::from(CmsisDapError::ErrorResponse).into());
-            }
+        if responses.iter().any(|resp| resp.transfer_response != 1) {
+            return Err(DebugProbeError::from(CmsisDapError::ErrorResponse).into());
         }
 
         Ok(())
@@ -955,29 +1087,24 @@ impl RawDapAccess for CmsisDap {
     ) -> Result<(), ArmError> {
         self.process_batch()?;
 
-        // the overhead for a single packet is 6 bytes
-        //
-        // [0]: HID overhead
-        // [1]: Category
-        // [2]: DAP Index
-        // [3]: Len 1
-        // [4]: Len 2
-        // [5]: Request type
-        //
+        let data_chunk_len = self.max_block_words();
 
-        let max_packet_size_words = (self.packet_size - 6) / 4;
-
-        let data_chunk_len = max_packet_size_words as usize;
-
-        for (i, chunk) in values.chunks_mut(data_chunk_len).enumerate() {
-            let request =
-                TransferBlockRequest::read_request(register_address, port, chunk.len() as u16);
+        let requests: Vec<_> = values
+            .chunks(data_chunk_len)
+            .map(|chunk| {
+                TransferBlockRequest::read_request(register_address, port, chunk.len() as u16)
+            })
+            .collect();
 
-            tracing::debug!("Transfer block: chunk={}, len={} bytes", i, chunk.len() * 4);
+        tracing::debug!(
+            "Transfer block: {} chunks, len={} bytes",
+            requests.len(),
+            values.len() * 4
+        );
 
-            let resp: TransferBlockResponse =
-                commands::send_command(&mut self.device, request).map_err(DebugProbeError::from)?;
+        let responses = self.send_queued(&requests).map_err(DebugProbeError::from)?;
 
+        for (chunk, resp) in values.chunks_mut(data_chunk_len).zip(responses) {
             if resp.transfer_response != 1 {
                 return Err(DebugProbeError::from(CmsisDapError::ErrorResponse).into());
             }
@@ -1181,6 +1308,80 @@ impl SwoAccess for CmsisDap {
     }
 }
 
+impl UartAccess for CmsisDap {
+    fn enable_uart(&mut self, config: &UartConfig) -> Result<(), DebugProbeError> {
+        if !self.capabilities.uart_communication_port_implemented {
+            return Err(CmsisDapError::UartNotAvailable.into());
+        }
+
+        // Route the UART through DAP_UART_Transfer instead of a USB COM port.
+        self.set_uart_transport(uart::TransportRequest::DapCommand)?;
+
+        let request = uart::ConfigureRequest {
+            data_bits: config.data_bits(),
+            parity: match config.parity() {
+                UartParity::None => uart::Parity::None,
+                UartParity::Odd => uart::Parity::Odd,
+                UartParity::Even => uart::Parity::Even,
+            },
+            stop_bits: match config.stop_bits() {
+                UartStopBits::One => uart::StopBits::One,
+                UartStopBits::Two => uart::StopBits::Two,
+            },
+            baud: config.baud(),
+        };
+        let response = commands::send_command(&mut self.device, request)?;
+        if response.error.any() {
+            tracing::debug!("UART configuration rejected: {:?}", response.error);
+            return Err(CmsisDapError::UartConfigurationNotSupported.into());
+        }
+        if response.baud != config.baud() {
+            tracing::warn!(
+                "Target UART baud rate not met: requested {}, got {}",
+                config.baud(),
+                response.baud
+            );
+        }
+
+        self.uart_control(uart::ControlRequest {
+            rx_enable: true,
+            rx_flush: true,
+            tx_enable: true,
+            tx_flush: true,
+            ..Default::default()
+        })?;
+
+        self.uart_rx.clear();
+        self.uart_active = true;
+        Ok(())
+    }
+
+    fn disable_uart(&mut self) -> Result<(), DebugProbeError> {
+        tracing::debug!("Stopping UART");
+        self.uart_control(uart::ControlRequest {
+            rx_disable: true,
+            tx_disable: true,
+            ..Default::default()
+        })?;
+        self.uart_active = false;
+        Ok(())
+    }
+
+    fn read_uart(&mut self) -> Result<Vec<u8>, DebugProbeError> {
+        if self.uart_active {
+            self.uart_transfer(&[])?;
+        }
+        Ok(std::mem::take(&mut self.uart_rx))
+    }
+
+    fn write_uart(&mut self, data: &[u8]) -> Result<usize, DebugProbeError> {
+        if !self.uart_active {
+            return Err(CmsisDapError::UartNotEnabled.into());
+        }
+        self.uart_transfer(data)
+    }
+}
+
 impl Drop for CmsisDap {
     fn drop(&mut self) {
         tracing::debug!("Detaching from CMSIS-DAP probe");
@@ -1193,6 +1394,10 @@ impl Drop for CmsisDap {
             let _ = self.disable_swo();
         }
 
+        if self.uart_active {
+            let _ = self.disable_uart();
+        }
+
         let _ = self.detach();
     }
 }
@@ -65,6 +65,18 @@ impl std::fmt::Display for IdCode {
     }
 }
 
+impl From<u32> for IdCode {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl From<IdCode> for u32 {
+    fn from(value: IdCode) -> Self {
+        value.0
+    }
+}
+
 impl IdCode {
     /// Returns `true` iff the IDCODE's least significant bit is `1`
     /// and the 7-bit `manufacturer_identity` is set to one of the non-reserved values in the range `[1,126]`.
@@ -665,6 +677,9 @@ impl<Probe: DebugProbe + RawJtagIo + 'static> JTAGAccess for Probe {
 
         tracing::debug!("IR scan: {}", response_zeros);
 
+        // The IRs now contain BYPASS, so the next register access has to shift the IR again.
+        self.state_mut().current_ir_reg = u32::MAX;
+
         let response = response.as_bitslice();
         let response = common_sequence(response, response_zeros.as_bitslice());
 
@@ -189,6 +189,10 @@ impl DebugProbe for EspUsbJtag {
         self
     }
 
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        Some(self)
+    }
+
     fn try_as_dap_probe(&mut self) -> Option<&mut dyn DapProbe> {
         // This is not a DAP capable probe.
         None
@@ -8,7 +8,7 @@ use crate::{
         ap::{memory_ap::mock::MockMemoryAp, AccessPort, MemoryAp},
         armv8m::Dhcsr,
         communication_interface::{
-            ArmDebugState, Initialized, SwdSequence, Uninitialized, UninitializedArmProbe,
+            ArmDebugState, DapProbe, Initialized, SwdSequence, Uninitialized, UninitializedArmProbe,
         },
         memory::adi_v5_memory_interface::{ADIMemoryInterface, ArmProbe},
         sequences::ArmDebugSequence,
@@ -31,6 +31,8 @@ pub struct FakeProbe {
     dap_register_write_handler:
         Option<Box<dyn Fn(PortType, u8, u32) -> Result<(), ArmError> + Send>>,
 
+    swj_sequence_handler: Option<Box<dyn Fn(u8, u64) -> Result<(), DebugProbeError> + Send>>,
+
     operations: RefCell<VecDeque<Operation>>,
 
     memory_ap: MockedAp,
@@ -217,6 +219,7 @@ impl FakeProbe {
 
             dap_register_read_handler: None,
             dap_register_write_handler: None,
+            swj_sequence_handler: None,
 
             operations: RefCell::new(VecDeque::new()),
 
@@ -233,6 +236,7 @@ impl FakeProbe {
 
             dap_register_read_handler: None,
             dap_register_write_handler: None,
+            swj_sequence_handler: None,
 
             operations: RefCell::new(VecDeque::new()),
 
@@ -258,6 +262,15 @@ impl FakeProbe {
         self.dap_register_write_handler = Some(handler);
     }
 
+    /// This sets the handler for SWJ sequences.
+    /// Can be used to hook into the sequence.
+    pub fn set_swj_sequence_handler(
+        &mut self,
+        handler: Box<dyn Fn(u8, u64) -> Result<(), DebugProbeError> + Send>,
+    ) {
+        self.swj_sequence_handler = Some(handler);
+    }
+
     /// Makes a generic probe out of the [`FakeProbe`]
     pub fn into_probe(self) -> Probe {
         Probe::from_specific_probe(Box::new(self))
@@ -370,8 +383,19 @@ impl DebugProbe for FakeProbe {
     fn has_arm_interface(&self) -> bool {
         true
     }
+
+    fn try_as_dap_probe(&mut self) -> Option<&mut dyn DapProbe> {
+        // Raw DAP access is only mocked once the handlers are set.
+        if self.dap_register_read_handler.is_some() && self.dap_register_write_handler.is_some() {
+            Some(self)
+        } else {
+            None
+        }
+    }
 }
 
+impl DapProbe for FakeProbe {}
+
 impl RawDapAccess for FakeProbe {
     /// Reads the DAP register on the specified port and address
     fn raw_read_register(&mut self, port: PortType, addr: u8) -> Result<u32, ArmError> {
@@ -391,8 +415,10 @@ impl RawDapAccess for FakeProbe {
         todo!()
     }
 
-    fn swj_sequence(&mut self, _bit_len: u8, _bits: u64) -> Result<(), DebugProbeError> {
-        todo!()
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), DebugProbeError> {
+        let handler = self.swj_sequence_handler.as_ref().unwrap();
+
+        handler(bit_len, bits)
     }
 
     fn swj_pins(
@@ -0,0 +1,238 @@
+//! Pin layouts of FTDI MPSSE adapters.
+//!
+//! The MPSSE engine always uses ADBUS0-3 for TCK, TDI, TDO and TMS. How the remaining
+//! GPIOs are wired differs between adapters: they can drive the reset lines, an LED,
+//! or the output-enable pins of buffers. An [`FtdiLayout`] describes this wiring.
+//!
+//! Layouts for common adapters are built in. More can be parsed from TOML with
+//! [`add_layouts_from_toml`], using the format of the built-in `layouts.toml`,
+//! and selected when opening a probe with
+//! [`FtdiProbeFactory::open_with_layout`](super::FtdiProbeFactory::open_with_layout).
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use super::ftdaye;
+use crate::probe::DebugProbeError;
+
+static BUILTIN_LAYOUTS: Lazy<Vec<FtdiLayout>> = Lazy::new(|| {
+    parse_layouts(include_str!("layouts.toml"))
+        .expect("The built-in FTDI layouts are invalid. This is a bug, please report it.")
+});
+
+/// The layout which is used if none is selected.
+pub const DEFAULT_LAYOUT: &str = "generic";
+
+/// TCK, TDI and TMS are outputs of the MPSSE engine.
+pub(super) const JTAG_OUTPUTS: u16 = 0x000b;
+
+/// TMS starts high.
+pub(super) const TMS: u16 = 0x0008;
+
+/// An error with the FTDI layouts.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum FtdiLayoutError {
+    /// The FTDI layouts could not be parsed.
+    Parse(#[from] toml::de::Error),
+    /// The FTDI layout '{0}' is unknown. Known layouts: {1}
+    UnknownLayout(String, String),
+}
+
+impl From<FtdiLayoutError> for DebugProbeError {
+    fn from(e: FtdiLayoutError) -> Self {
+        DebugProbeError::ProbeSpecific(Box::new(e))
+    }
+}
+
+/// The FTDI interface which is connected to the debug header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FtdiInterface {
+    /// Interface A, the only one on single-channel chips.
+    #[default]
+    A,
+    /// Interface B.
+    B,
+    /// Interface C, only on the FT4232H.
+    C,
+    /// Interface D, only on the FT4232H.
+    D,
+}
+
+impl From<FtdiInterface> for ftdaye::Interface {
+    fn from(interface: FtdiInterface) -> Self {
+        match interface {
+            FtdiInterface::A => ftdaye::Interface::A,
+            FtdiInterface::B => ftdaye::Interface::B,
+            FtdiInterface::C => ftdaye::Interface::C,
+            FtdiInterface::D => ftdaye::Interface::D,
+        }
+    }
+}
+
+/// A signal which is driven by GPIOs of the adapter.
+///
+/// The masks use the same bit order as [`FtdiLayout::init_output`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FtdiSignal {
+    /// Pins which are high while the signal is asserted.
+    #[serde(default)]
+    pub data: u16,
+    /// Pins which are low while the signal is asserted.
+    #[serde(default)]
+    pub ndata: u16,
+    /// Output-enable pins of a buffer, which are high while the signal is asserted.
+    #[serde(default)]
+    pub oe: u16,
+    /// Output-enable pins of a buffer, which are low while the signal is asserted.
+    #[serde(default)]
+    pub noe: u16,
+}
+
+impl FtdiSignal {
+    /// Returns the pin levels `output`, with the signal asserted or deasserted.
+    pub fn apply(&self, output: u16, asserted: bool) -> u16 {
+        let high = self.data | self.oe;
+        let low = self.ndata | self.noe;
+
+        if asserted {
+            (output | high) & !low
+        } else {
+            (output | low) & !high
+        }
+    }
+}
+
+/// The wiring of an FTDI MPSSE adapter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FtdiLayout {
+    /// The name to select the layout with.
+    pub name: String,
+    /// The FTDI interface which is connected to the debug header.
+    #[serde(default)]
+    pub interface: FtdiInterface,
+    /// The pin levels after attaching, with ADBUS0-7 in the low byte and ACBUS0-7 in the high byte.
+    pub init_output: u16,
+    /// The pin directions after attaching. Set bits are outputs.
+    pub init_direction: u16,
+    /// The system reset line.
+    pub nsrst: Option<FtdiSignal>,
+    /// The JTAG TAP reset line. It is pulsed when attaching using JTAG.
+    pub ntrst: Option<FtdiSignal>,
+    /// An LED, which is switched on while the probe is attached.
+    pub led: Option<FtdiSignal>,
+    /// The output enable of a buffer which drives SWDIO. It is asserted while the probe drives SWDIO.
+    pub swdio_oe: Option<FtdiSignal>,
+    /// Whether SWD is wired up: SWCLK on TCK, and SWDIO on TDO, and on TDI through a resistor.
+    #[serde(default)]
+    pub swd: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayoutFile {
+    layout: Vec<FtdiLayout>,
+}
+
+fn parse_layouts(toml: &str) -> Result<Vec<FtdiLayout>, FtdiLayoutError> {
+    Ok(toml::from_str::<LayoutFile>(toml)?.layout)
+}
+
+/// Parses additional layouts from a TOML document.
+///
+/// The layouts can be selected by passing them to [`get_layout`].
+/// Layouts with the name of a built-in layout replace it.
+pub fn add_layouts_from_toml(toml: &str) -> Result<Vec<FtdiLayout>, FtdiLayoutError> {
+    parse_layouts(toml)
+}
+
+/// Looks up a layout by its name, in `layouts` first and then in the built-in layouts.
+///
+/// If several of `layouts` have the name, the last one is used.
+pub fn get_layout(name: &str, layouts: &[FtdiLayout]) -> Result<FtdiLayout, FtdiLayoutError> {
+    layouts
+        .iter()
+        .rev()
+        .chain(BUILTIN_LAYOUTS.iter())
+        .find(|layout| layout.name == name)
+        .cloned()
+        .ok_or_else(|| {
+            let mut known = layouts
+                .iter()
+                .chain(BUILTIN_LAYOUTS.iter())
+                .map(|layout| layout.name.as_str())
+                .collect::<Vec<_>>();
+            known.sort_unstable();
+            known.dedup();
+            FtdiLayoutError::UnknownLayout(name.to_string(), known.join(", "))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builtin_layouts() {
+        let layout = get_layout(DEFAULT_LAYOUT, &[]).unwrap();
+        assert_eq!(layout.interface, FtdiInterface::A);
+        assert!(!layout.swd);
+
+        let layout = get_layout("tigard", &[]).unwrap();
+        assert_eq!(layout.interface, FtdiInterface::B);
+        assert!(layout.swd);
+    }
+
+    #[test]
+    fn user_layout() {
+        let layouts = add_layouts_from_toml(
+            r#"
+            [[layout]]
+            name = "test-adapter"
+            init_output = 0x0108
+            init_direction = 0x010b
+            nsrst = { ndata = 0x0100 }
+
+            [[layout]]
+            name = "tigard"
+            init_output = 0x0008
+            init_direction = 0x000b
+            "#,
+        )
+        .unwrap();
+
+        let layout = get_layout("test-adapter", &layouts).unwrap();
+        assert_eq!(layout.init_output, 0x0108);
+        assert_eq!(
+            layout.nsrst,
+            Some(FtdiSignal {
+                ndata: 0x0100,
+                ..Default::default()
+            })
+        );
+
+        // The built-in layout is replaced.
+        let layout = get_layout("tigard", &layouts).unwrap();
+        assert_eq!(layout.interface, FtdiInterface::A);
+
+        // The layouts are only known where they are passed.
+        assert!(matches!(
+            get_layout("test-adapter", &[]),
+            Err(FtdiLayoutError::UnknownLayout(..))
+        ));
+    }
+
+    #[test]
+    fn signal_levels() {
+        let signal = FtdiSignal {
+            data: 0x0001,
+            ndata: 0x0002,
+            oe: 0x0004,
+            noe: 0x0008,
+        };
+
+        assert_eq!(signal.apply(0x00f0, true), 0x00f5);
+        assert_eq!(signal.apply(0x00f0, false), 0x00fa);
+    }
+}
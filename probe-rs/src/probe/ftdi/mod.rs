@@ -26,10 +26,12 @@ use std::{
 
 mod command_compacter;
 mod ftdaye;
+pub mod layout;
 
 use crate::architecture::riscv::dtm::jtag_dtm::JtagDtm;
 use command_compacter::Command;
 use ftdaye::{error::FtdiError, ChipType};
+use layout::{FtdiLayout, FtdiSignal, JTAG_OUTPUTS, TMS};
 
 #[derive(Debug)]
 struct JtagAdapter {
@@ -41,17 +43,29 @@ struct JtagAdapter {
     in_bit_counts: Vec<usize>,
     in_bits: BitVec<u8, Lsb0>,
     ftdi: FtdiProperties,
+
+    layout: FtdiLayout,
+    /// The current pin levels.
+    output: u16,
+    /// The current pin directions.
+    direction: u16,
+    /// Whether the MPSSE engine is enabled, which is needed to drive the pins.
+    mpsse_enabled: bool,
 }
 
 impl JtagAdapter {
-    fn open(ftdi: FtdiDevice, usb_device: DeviceInfo) -> Result<Self, DebugProbeError> {
+    fn open(
+        ftdi: FtdiDevice,
+        usb_device: DeviceInfo,
+        layout: FtdiLayout,
+    ) -> Result<Self, DebugProbeError> {
         let device = ftdaye::Builder::new()
-            .with_interface(ftdaye::Interface::A)
+            .with_interface(layout.interface.into())
             .with_read_timeout(Duration::from_secs(5))
             .with_write_timeout(Duration::from_secs(5))
             .usb_open(usb_device)?;
 
-        let ftdi = FtdiProperties::try_from((ftdi, device.chip_type()))?;
+        let properties = FtdiProperties::try_from((ftdi, device.chip_type()))?;
 
         Ok(Self {
             device,
@@ -60,11 +74,41 @@ impl JtagAdapter {
             commands: vec![],
             in_bit_counts: vec![],
             in_bits: BitVec::new(),
-            ftdi,
+            ftdi: properties,
+            output: layout.init_output,
+            direction: layout.init_direction,
+            mpsse_enabled: false,
+            layout,
         })
     }
 
     pub fn attach(&mut self) -> Result<(), FtdiError> {
+        // The pins may already be driven, e.g. to hold the target in reset while attaching.
+        if !self.mpsse_enabled {
+            self.enable_mpsse()?;
+        }
+
+        if let Some(led) = self.layout.led {
+            self.output = led.apply(self.output, true);
+        }
+        self.device.set_pins(self.output, self.direction)?;
+
+        self.apply_clock_speed(self.speed_khz)?;
+
+        Ok(())
+    }
+
+    pub fn detach(&mut self) -> Result<(), DebugProbeError> {
+        if self.mpsse_enabled {
+            let led = self.layout.led;
+            self.set_signal(led, false)?;
+        }
+        self.mpsse_enabled = false;
+
+        Ok(())
+    }
+
+    fn enable_mpsse(&mut self) -> Result<(), FtdiError> {
         self.device.usb_reset()?;
         // 0x0B configures pins for JTAG
         self.device.set_bitmode(0x0b, ftdaye::BitMode::Mpsse)?;
@@ -74,17 +118,13 @@ impl JtagAdapter {
         let mut junk = vec![];
         let _ = self.device.read_to_end(&mut junk);
 
-        // TMS starts high
-        let output = 0x0008;
-
-        // TMS, TDO and TCK are outputs
-        let direction = 0x000b;
-
-        self.device.set_pins(output, direction)?;
-
-        self.apply_clock_speed(self.speed_khz)?;
+        // The JTAG pins are always set up for the MPSSE engine, with TMS starting high.
+        self.output = self.layout.init_output | TMS;
+        self.direction = self.layout.init_direction | JTAG_OUTPUTS;
+        self.device.set_pins(self.output, self.direction)?;
 
         self.device.disable_loopback()?;
+        self.mpsse_enabled = true;
 
         Ok(())
     }
@@ -93,6 +133,32 @@ impl JtagAdapter {
         self.speed_khz
     }
 
+    /// Asserts or deasserts a signal of the layout.
+    ///
+    /// Returns `false` if the layout does not have the signal.
+    fn set_signal(
+        &mut self,
+        signal: Option<FtdiSignal>,
+        asserted: bool,
+    ) -> Result<bool, DebugProbeError> {
+        let Some(signal) = signal else {
+            return Ok(false);
+        };
+
+        if self.mpsse_enabled {
+            self.flush()?;
+        } else {
+            self.enable_mpsse()?;
+        }
+
+        self.output = signal.apply(self.output, asserted);
+        self.device
+            .set_pins(self.output, self.direction)
+            .map_err(FtdiError::from)?;
+
+        Ok(true)
+    }
+
     fn set_speed_khz(&mut self, speed_khz: u32) -> u32 {
         self.speed_khz = speed_khz;
         self.speed_khz
@@ -240,6 +306,110 @@ impl JtagAdapter {
 
         Ok(std::mem::take(&mut self.in_bits))
     }
+
+    /// Appends an MPSSE command which is not handled by [`Command`].
+    fn append_raw_command(
+        &mut self,
+        command: &[u8],
+        captured_bits: Option<usize>,
+    ) -> Result<(), DebugProbeError> {
+        // 1 byte is reserved for the send immediate command
+        if self.commands.len() + command.len() + 1 >= self.ftdi.buffer_size {
+            self.send_buffer()?;
+            self.read_response()?;
+        }
+
+        self.in_bit_counts.extend(captured_bits);
+        self.commands.extend_from_slice(command);
+
+        Ok(())
+    }
+
+    /// Clocks out SWD bits, and returns the bits read back.
+    ///
+    /// SWDIO is driven through a resistor on TDI, and read on TDO, so the target can
+    /// override the output while it drives SWDIO. If the layout has an output enable for
+    /// SWDIO, it is switched off while the target drives SWDIO.
+    fn swd_io(&mut self, bits: &[(bool, bool)]) -> Result<Vec<bool>, DebugProbeError> {
+        self.finalize_command()?;
+
+        let commands = swd_commands(bits, self.layout.swdio_oe, &mut self.output, self.direction);
+        for command in commands {
+            self.append_raw_command(&command.bytes, command.captured_bits)?;
+        }
+
+        let captured = self.read_captured_bits()?;
+        Ok(captured.iter().by_vals().collect())
+    }
+}
+
+/// An MPSSE command which is not handled by [`Command`].
+#[derive(Debug, PartialEq, Eq)]
+struct RawCommand {
+    bytes: Vec<u8>,
+    /// The number of bits the command reads back, if any.
+    captured_bits: Option<usize>,
+}
+
+/// Builds the MPSSE commands which clock out SWD `bits`, given as pairs of
+/// whether the probe drives SWDIO, and the level it drives.
+///
+/// The bits are grouped by direction. Before each group, `swdio_oe` is switched
+/// if the layout has it, which updates the pin levels in `output`.
+fn swd_commands(
+    bits: &[(bool, bool)],
+    swdio_oe: Option<FtdiSignal>,
+    output: &mut u16,
+    direction: u16,
+) -> Vec<RawCommand> {
+    let mut commands = Vec::new();
+
+    let mut remaining = bits;
+    while let Some(&(drive, _)) = remaining.first() {
+        let count = remaining
+            .iter()
+            .take_while(|(direction, _)| *direction == drive)
+            .count();
+        let (mut group, rest) = remaining.split_at(count);
+        remaining = rest;
+
+        if let Some(swdio_oe) = swdio_oe {
+            *output = swdio_oe.apply(*output, drive);
+            let [low, high] = output.to_le_bytes();
+            let [low_direction, high_direction] = direction.to_le_bytes();
+            commands.push(RawCommand {
+                bytes: vec![0x80, low, low_direction],
+                captured_bits: None,
+            });
+            commands.push(RawCommand {
+                bytes: vec![0x82, high, high_direction],
+                captured_bits: None,
+            });
+        }
+
+        while !group.is_empty() {
+            // Like for JTAG, 7 bits are sent as 6 bits and 1 bit.
+            let chunk_len = match group.len().min(8) {
+                7 => 6,
+                len => len,
+            };
+            let (chunk, rest) = group.split_at(chunk_len);
+            group = rest;
+
+            let byte = chunk
+                .iter()
+                .rev()
+                .fold(0, |byte, &(_, bit)| byte << 1 | bit as u8);
+
+            // Clock bits out on the falling edge, and in on the rising edge, LSB first.
+            commands.push(RawCommand {
+                bytes: vec![0x3b, chunk_len as u8 - 1, byte],
+                captured_bits: Some(chunk_len),
+            });
+        }
+    }
+
+    commands
 }
 
 /// A factory for creating [`FtdiProbe`] instances.
@@ -252,8 +422,16 @@ impl std::fmt::Display for FtdiProbeFactory {
     }
 }
 
-impl ProbeFactory for FtdiProbeFactory {
-    fn open(&self, selector: &DebugProbeSelector) -> Result<Box<dyn DebugProbe>, DebugProbeError> {
+impl FtdiProbeFactory {
+    /// Opens the FTDI probe matching `selector`, with its pins wired as described by `layout`.
+    ///
+    /// [`ProbeFactory::open`] uses the [default layout](layout::DEFAULT_LAYOUT). Layouts are
+    /// looked up by name with [`layout::get_layout`].
+    pub fn open_with_layout(
+        &self,
+        selector: &DebugProbeSelector,
+        layout: FtdiLayout,
+    ) -> Result<Box<dyn DebugProbe>, DebugProbeError> {
         // Only open FTDI-compatible probes
         let Some(ftdi) = FTDI_COMPAT_DEVICES
             .iter()
@@ -278,8 +456,10 @@ impl ProbeFactory for FtdiProbeFactory {
             tracing::warn!("More than one matching FTDI probe was found. Opening the first one.");
         }
 
+        tracing::info!("Using FTDI layout {}", layout.name);
         let probe = FtdiProbe {
-            adapter: JtagAdapter::open(ftdi, probes.pop().unwrap())?,
+            adapter: JtagAdapter::open(ftdi, probes.pop().unwrap(), layout)?,
+            protocol: WireProtocol::Jtag,
             jtag_state: JtagDriverState::default(),
             swd_settings: SwdSettings::default(),
             probe_statistics: ProbeStatistics::default(),
@@ -287,6 +467,13 @@ impl ProbeFactory for FtdiProbeFactory {
         tracing::debug!("opened probe: {:?}", probe);
         Ok(Box::new(probe))
     }
+}
+
+impl ProbeFactory for FtdiProbeFactory {
+    fn open(&self, selector: &DebugProbeSelector) -> Result<Box<dyn DebugProbe>, DebugProbeError> {
+        let layout = layout::get_layout(layout::DEFAULT_LAYOUT, &[])?;
+        self.open_with_layout(selector, layout)
+    }
 
     fn list_probes(&self) -> Vec<DebugProbeInfo> {
         list_ftdi_devices()
@@ -297,6 +484,7 @@ impl ProbeFactory for FtdiProbeFactory {
 #[derive(Debug)]
 pub struct FtdiProbe {
     adapter: JtagAdapter,
+    protocol: WireProtocol,
     jtag_state: JtagDriverState,
     probe_statistics: ProbeStatistics,
     swd_settings: SwdSettings,
@@ -328,6 +516,16 @@ impl DebugProbe for FtdiProbe {
             .attach()
             .map_err(|e| DebugProbeError::ProbeSpecific(Box::new(e)))?;
 
+        if self.protocol == WireProtocol::Swd {
+            return Ok(());
+        }
+
+        let ntrst = self.adapter.layout.ntrst;
+        if self.adapter.set_signal(ntrst, true)? {
+            std::thread::sleep(Duration::from_millis(1));
+            self.adapter.set_signal(ntrst, false)?;
+        }
+
         let chain = self.scan_chain()?;
         tracing::info!("Found {} TAPs on reset scan", chain.len());
 
@@ -339,40 +537,54 @@ impl DebugProbe for FtdiProbe {
     }
 
     fn detach(&mut self) -> Result<(), crate::Error> {
+        self.adapter.detach()?;
+
         Ok(())
     }
 
     fn target_reset(&mut self) -> Result<(), DebugProbeError> {
-        // TODO we could add this by using a GPIO. However, different probes may connect
-        // different pins (if any) to the reset line, so we would need to make this configurable.
-        Err(DebugProbeError::NotImplemented {
-            function_name: "target_reset",
-        })
+        self.target_reset_assert()?;
+        std::thread::sleep(Duration::from_millis(100));
+        self.target_reset_deassert()
     }
 
     fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
-        Err(DebugProbeError::NotImplemented {
-            function_name: "target_reset_assert",
-        })
+        // The reset line depends on the wiring, which is described by the layout.
+        let nsrst = self.adapter.layout.nsrst;
+        if self.adapter.set_signal(nsrst, true)? {
+            Ok(())
+        } else {
+            Err(DebugProbeError::NotImplemented {
+                function_name: "target_reset_assert",
+            })
+        }
     }
 
     fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
-        Err(DebugProbeError::NotImplemented {
-            function_name: "target_reset_deassert",
-        })
+        let nsrst = self.adapter.layout.nsrst;
+        if self.adapter.set_signal(nsrst, false)? {
+            Ok(())
+        } else {
+            Err(DebugProbeError::NotImplemented {
+                function_name: "target_reset_deassert",
+            })
+        }
     }
 
     fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
-        if protocol != WireProtocol::Jtag {
-            Err(DebugProbeError::UnsupportedProtocol(protocol))
-        } else {
-            Ok(())
+        // SWD needs the resistor wiring, which the layout has to declare.
+        match protocol {
+            WireProtocol::Jtag => {}
+            WireProtocol::Swd if self.adapter.layout.swd => {}
+            WireProtocol::Swd => return Err(DebugProbeError::UnsupportedProtocol(protocol)),
         }
+
+        self.protocol = protocol;
+        Ok(())
     }
 
     fn active_protocol(&self) -> Option<WireProtocol> {
-        // Only supports JTAG
-        Some(WireProtocol::Jtag)
+        Some(self.protocol)
     }
 
     fn try_get_riscv_interface(
@@ -396,6 +608,10 @@ impl DebugProbe for FtdiProbe {
         self
     }
 
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        Some(self)
+    }
+
     fn try_get_arm_interface<'probe>(
         self: Box<Self>,
     ) -> Result<Box<dyn UninitializedArmProbe + 'probe>, (Box<dyn DebugProbe>, DebugProbeError)>
@@ -448,14 +664,15 @@ impl RawProtocolIo for FtdiProbe {
         Ok(())
     }
 
-    fn swd_io<D, S>(&mut self, _dir: D, _swdio: S) -> Result<Vec<bool>, DebugProbeError>
+    fn swd_io<D, S>(&mut self, dir: D, swdio: S) -> Result<Vec<bool>, DebugProbeError>
     where
         D: IntoIterator<Item = bool>,
         S: IntoIterator<Item = bool>,
     {
-        Err(DebugProbeError::NotImplemented {
-            function_name: "swd_io",
-        })
+        self.probe_statistics.report_io();
+
+        let bits = dir.into_iter().zip(swdio).collect::<Vec<_>>();
+        self.adapter.swd_io(&bits)
     }
 
     fn swj_pins(
@@ -626,13 +843,15 @@ static FTDI_COMPAT_DEVICES: &[FtdiDevice] = &[
 
 fn get_device_info(device: &DeviceInfo) -> Option<DebugProbeInfo> {
     FTDI_COMPAT_DEVICES.iter().find_map(|ftdi| {
-        ftdi.matches(device).then(|| DebugProbeInfo {
-            identifier: device.product_string().unwrap_or("FTDI").to_string(),
-            vendor_id: device.vendor_id(),
-            product_id: device.product_id(),
-            serial_number: device.serial_number().map(|s| s.to_string()),
-            probe_factory: &FtdiProbeFactory,
-            hid_interface: None,
+        ftdi.matches(device).then(|| {
+            DebugProbeInfo::new(
+                device.product_string().unwrap_or("FTDI"),
+                device.vendor_id(),
+                device.product_id(),
+                device.serial_number().map(|s| s.to_string()),
+                &FtdiProbeFactory,
+                None,
+            )
         })
     })
 }
@@ -646,3 +865,85 @@ fn list_ftdi_devices() -> Vec<DebugProbeInfo> {
         Err(_) => vec![],
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn data(bits: usize, byte: u8) -> RawCommand {
+        RawCommand {
+            bytes: vec![0x3b, bits as u8 - 1, byte],
+            captured_bits: Some(bits),
+        }
+    }
+
+    fn pins(opcode: u8, level: u8, direction: u8) -> RawCommand {
+        RawCommand {
+            bytes: vec![opcode, level, direction],
+            captured_bits: None,
+        }
+    }
+
+    fn driven(levels: &[bool]) -> Vec<(bool, bool)> {
+        levels.iter().map(|&level| (true, level)).collect()
+    }
+
+    #[test]
+    fn swd_bits_fold_into_byte() {
+        let mut output = 0;
+        let commands = swd_commands(&driven(&[true, false, true, true]), None, &mut output, 0);
+
+        // The first bit is the least significant one.
+        assert_eq!(commands, [data(4, 0b1101)]);
+    }
+
+    #[test]
+    fn swd_seven_bits_split() {
+        let mut output = 0;
+
+        let commands = swd_commands(&driven(&[true; 7]), None, &mut output, 0);
+        assert_eq!(commands, [data(6, 0x3f), data(1, 0x01)]);
+
+        let commands = swd_commands(&driven(&[true; 15]), None, &mut output, 0);
+        assert_eq!(commands, [data(8, 0xff), data(6, 0x3f), data(1, 0x01)]);
+    }
+
+    #[test]
+    fn swd_bits_grouped_by_direction() {
+        let mut output = 0;
+        let bits = [(true, true), (true, true), (false, false), (false, false)];
+
+        let commands = swd_commands(&bits, None, &mut output, 0);
+
+        assert_eq!(commands, [data(2, 0b11), data(2, 0b00)]);
+    }
+
+    #[test]
+    fn swd_output_enable_toggled() {
+        let swdio_oe = FtdiSignal {
+            noe: 0x0010,
+            ..Default::default()
+        };
+        let mut output = 0x0208;
+        let direction = 0x021b;
+        let bits = [(true, true), (false, false), (false, false), (true, false)];
+
+        let commands = swd_commands(&bits, Some(swdio_oe), &mut output, direction);
+
+        assert_eq!(
+            commands,
+            [
+                pins(0x80, 0x08, 0x1b),
+                pins(0x82, 0x02, 0x02),
+                data(1, 0b1),
+                pins(0x80, 0x18, 0x1b),
+                pins(0x82, 0x02, 0x02),
+                data(2, 0b00),
+                pins(0x80, 0x08, 0x1b),
+                pins(0x82, 0x02, 0x02),
+                data(1, 0b0),
+            ]
+        );
+        assert_eq!(output, 0x0208);
+    }
+}
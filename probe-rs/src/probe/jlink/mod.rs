@@ -34,6 +34,7 @@ use crate::architecture::riscv::dtm::jtag_dtm::JtagDtm;
 use crate::architecture::xtensa::communication_interface::XtensaCommunicationInterface;
 use crate::probe::common::{JtagDriverState, RawJtagIo};
 use crate::probe::jlink::bits::IteratorExt;
+use crate::probe::power::TargetPowerVoltage;
 use crate::probe::usb_util::InterfaceExt;
 use crate::probe::JTAGAccess;
 use crate::probe::ProbeFactory;
@@ -606,6 +607,14 @@ impl JLink {
         Ok(u16::from_le_bytes(voltage))
     }
 
+    /// Switches the 5V supply on pin 19 (`KS power`) on or off.
+    ///
+    /// The supply is protected against overcurrent, and can power small target boards.
+    fn set_kickstart_power(&mut self, enable: bool) -> Result<(), JlinkError> {
+        self.require_capability(Capability::SetKsPower)?;
+        self.write_cmd(&[Command::SetKsPower as u8, enable as u8])
+    }
+
     fn shift_jtag_bit(
         &mut self,
         tms: bool,
@@ -969,6 +978,10 @@ impl DebugProbe for JLink {
         self
     }
 
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        Some(self)
+    }
+
     fn try_as_dap_probe(&mut self) -> Option<&mut dyn DapProbe> {
         Some(self)
     }
@@ -987,6 +1000,27 @@ impl DebugProbe for JLink {
         Ok(Some((self.read_target_voltage()? as f32) / 1000f32))
     }
 
+    fn target_power_voltages(&self) -> Vec<TargetPowerVoltage> {
+        if self.caps.contains(Capability::SetKsPower) {
+            vec![TargetPowerVoltage::V5]
+        } else {
+            vec![]
+        }
+    }
+
+    fn set_target_power(
+        &mut self,
+        on: bool,
+        voltage: TargetPowerVoltage,
+    ) -> Result<(), DebugProbeError> {
+        if voltage != TargetPowerVoltage::V5 {
+            return Err(DebugProbeError::UnsupportedTargetPowerVoltage(voltage));
+        }
+
+        self.set_kickstart_power(on)?;
+        Ok(())
+    }
+
     fn try_get_xtensa_interface(
         mut self: Box<Self>,
     ) -> Result<XtensaCommunicationInterface, (Box<dyn DebugProbe>, DebugProbeError)> {
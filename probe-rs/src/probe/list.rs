@@ -4,7 +4,7 @@ use crate::probe::{
     DebugProbeError, DebugProbeInfo, DebugProbeSelector, Probe, ProbeCreationError, ProbeFactory,
 };
 
-use super::{cmsisdap, espusbjtag, ftdi, jlink, stlink, wlink};
+use super::{cmsisdap, espusbjtag, ftdi, jlink, remote, simulator, stlink, wlink};
 
 /// Struct to list all attached debug probes
 #[derive(Debug)]
@@ -91,6 +91,20 @@ impl AllProbesLister {
     fn open(selector: impl Into<DebugProbeSelector>) -> Result<Probe, DebugProbeError> {
         let selector = selector.into();
 
+        // Remote probes are only opened by the probe server sharing them.
+        if selector.remote.is_some() {
+            return remote::RemoteProbeFactory
+                .open(&selector)
+                .map(Probe::from_specific_probe);
+        }
+
+        // Simulators are only reachable by their address.
+        if selector.simulator.is_some() {
+            return simulator::SimulatorProbeFactory
+                .open(&selector)
+                .map(Probe::from_specific_probe);
+        }
+
         for probe_ctor in Self::DRIVERS {
             match probe_ctor.open(&selector) {
                 Ok(link) => return Ok(Probe::from_specific_probe(link)),
@@ -0,0 +1,57 @@
+//! Powering the target from the debug probe.
+//!
+//! Some debug probes can supply power to the target through a pin of the
+//! debug connector, so a board can be power cycled without unplugging it.
+
+use serde::{Deserialize, Serialize};
+
+/// The lowest voltage which is considered as a powered target, in Volts.
+///
+/// An unpowered target usually reads as a few millivolts, while even
+/// low-voltage targets run at more than 1.2 V.
+const MIN_POWERED_VOLTAGE: f32 = 1.0;
+
+/// The largest difference between two readings of a settled voltage, in Volts.
+const MAX_SETTLED_DIFFERENCE: f32 = 0.05;
+
+/// A supply voltage which a debug probe can provide to the target.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TargetPowerVoltage {
+    /// 3.3 V
+    V3_3,
+    /// 5 V
+    V5,
+}
+
+impl TargetPowerVoltage {
+    /// The voltage in Volts.
+    pub fn volts(&self) -> f32 {
+        match self {
+            TargetPowerVoltage::V3_3 => 3.3,
+            TargetPowerVoltage::V5 => 5.0,
+        }
+    }
+}
+
+impl std::fmt::Display for TargetPowerVoltage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} V", self.volts())
+    }
+}
+
+/// Checks whether the target voltage settled, given two consecutive readings.
+pub(crate) fn voltage_settled(previous: f32, current: f32) -> bool {
+    current >= MIN_POWERED_VOLTAGE && (current - previous).abs() <= MAX_SETTLED_DIFFERENCE
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn settled_voltage() {
+        assert!(voltage_settled(3.29, 3.31));
+        assert!(!voltage_settled(0.01, 0.02));
+        assert!(!voltage_settled(1.8, 3.3));
+    }
+}
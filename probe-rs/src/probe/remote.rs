@@ -0,0 +1,1635 @@
+//! Sharing debug probes over TCP.
+//!
+//! A [`ProbeServer`] shares the probes found by a [`Lister`] with remote clients, e.g. the probes
+//! connected to a lab machine. On the client, [`RemoteProbeFactory`] and [`RemoteProbeLister`] open
+//! and list them as if they were connected locally. Remote probes are selected with
+//! `tcp://HOST:PORT/VID:PID:SERIAL`.
+//!
+//! Only the raw DAP access ([`DebugProbe::try_as_dap_probe`]) and the JTAG access
+//! ([`DebugProbe::try_as_jtag_probe`]) of a probe are shared, since the ARM, RISC-V and Xtensa
+//! interfaces are built on top of them on the client. Probes which offer neither, like the ST-Link,
+//! can be listed and opened, but not used for debugging.
+//!
+//! The server does not authenticate its clients, so it should only be reachable from trusted
+//! networks.
+//!
+//! # Protocol
+//!
+//! The client sends [`Request`]s, and the server answers every request with exactly one
+//! [`Response`]. Every message is encoded as [MessagePack], with structs encoded as maps, and is
+//! prefixed by its length as a little endian `u32`.
+//!
+//! 1. The client starts with [`Request::Hello`], containing its [`PROTOCOL_VERSION`]. The server
+//!    answers with its own version, or an error if it doesn't support the version of the client.
+//! 2. [`Request::List`] lists the probes of the server. [`Request::Open`] opens one of them for the
+//!    rest of the connection. The probe is closed when the connection is closed.
+//! 3. Requests like [`Request::SetSpeed`] or [`Request::Attach`] map directly to the methods of
+//!    [`DebugProbe`].
+//! 4. DAP operations are sent in batches with [`Request::Dap`]. The server executes them in order
+//!    until one fails, and answers with the values read by the successful operations, and the error
+//!    of the failed one. The client queues register writes until it needs the result of a read.
+//! 5. JTAG operations are sent in batches with [`Request::Jtag`], in the same way. Consecutive
+//!    register writes are executed as one batch by the probe of the server.
+//!
+//! Errors are reported as [`RemoteError`], which keeps the kind of error needed by the client, e.g.
+//! a FAULT response of the target.
+//!
+//! [MessagePack]: https://msgpack.org
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use probe_rs_target::ScanChainElement;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::architecture::arm::communication_interface::{
+    DapError, DapProbe, UninitializedArmProbe,
+};
+use crate::architecture::arm::{ArmCommunicationInterface, ArmError, PortType, RawDapAccess};
+use crate::architecture::riscv::communication_interface::{
+    RiscvCommunicationInterface, RiscvError,
+};
+use crate::architecture::riscv::dtm::jtag_dtm::JtagDtm;
+use crate::architecture::xtensa::communication_interface::XtensaCommunicationInterface;
+use crate::probe::common::IdCode;
+use crate::probe::list::{Lister, ProbeLister};
+use crate::probe::power::TargetPowerVoltage;
+use crate::probe::{
+    BatchExecutionError, CommandResult, DebugProbe, DebugProbeError, DebugProbeInfo,
+    DebugProbeSelector, DeferredResultSet, JTAGAccess, JtagChainItem, JtagCommandQueue,
+    JtagWriteCommand, Probe, ProbeCreationError, ProbeFactory, WireProtocol,
+};
+
+/// The version of the protocol, which is exchanged with [`Request::Hello`].
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// The largest message which is accepted, to detect peers which don't speak the protocol.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// The number of DAP operations the client queues before sending them without waiting for a read.
+const MAX_QUEUED_OPERATIONS: usize = 1024;
+
+/// A request from the client to the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Request {
+    /// Starts the connection, answered with [`Response::Hello`].
+    Hello {
+        /// The protocol version of the client.
+        version: u32,
+    },
+    /// Lists the probes of the server, answered with [`Response::Probes`].
+    List,
+    /// Opens the probe matching `selector` (`VID:PID:SERIAL`), answered with [`Response::Opened`].
+    Open {
+        /// The selector of the probe.
+        selector: String,
+    },
+    /// Calls [`DebugProbe::set_speed`], answered with [`Response::Speed`].
+    SetSpeed {
+        /// The requested speed.
+        speed_khz: u32,
+    },
+    /// Calls [`DebugProbe::set_scan_chain`].
+    SetScanChain {
+        /// The scan chain of the target.
+        scan_chain: Vec<ScanChainElement>,
+    },
+    /// Calls [`DebugProbe::select_protocol`].
+    SelectProtocol {
+        /// The protocol to use.
+        protocol: WireProtocol,
+    },
+    /// Calls [`DebugProbe::attach`].
+    Attach,
+    /// Calls [`DebugProbe::detach`].
+    Detach,
+    /// Calls [`DebugProbe::target_reset`].
+    TargetReset,
+    /// Calls [`DebugProbe::target_reset_assert`].
+    TargetResetAssert,
+    /// Calls [`DebugProbe::target_reset_deassert`].
+    TargetResetDeassert,
+    /// Calls [`DebugProbe::get_target_voltage`], answered with [`Response::Voltage`].
+    TargetVoltage,
+    /// Calls [`DebugProbe::set_target_power`].
+    SetTargetPower {
+        /// Whether the power is switched on.
+        on: bool,
+        /// The supply voltage.
+        voltage: TargetPowerVoltage,
+    },
+    /// Executes DAP operations, answered with [`Response::Dap`].
+    Dap {
+        /// The operations, in the order they are executed.
+        operations: Vec<DapOperation>,
+    },
+    /// Executes JTAG operations, answered with [`Response::Jtag`].
+    Jtag {
+        /// The idle cycles after each register access, see [`JTAGAccess::set_idle_cycles`].
+        idle_cycles: u8,
+        /// The operations, in the order they are executed.
+        operations: Vec<JtagOperation>,
+    },
+}
+
+/// A response from the server to the client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Response {
+    /// The answer to [`Request::Hello`].
+    Hello {
+        /// The protocol version of the server.
+        version: u32,
+    },
+    /// The probes of the server.
+    Probes {
+        /// The probes found by the server.
+        probes: Vec<RemoteProbeInfo>,
+    },
+    /// The probe was opened.
+    Opened {
+        /// The name of the probe.
+        name: String,
+        /// The speed of the probe.
+        speed_khz: u32,
+        /// The protocol selected by the probe.
+        protocol: Option<WireProtocol>,
+        /// Whether the probe offers raw DAP access.
+        dap: bool,
+        /// Whether the probe offers JTAG access.
+        jtag: bool,
+        /// The voltages the probe can supply to the target.
+        power_voltages: Vec<TargetPowerVoltage>,
+    },
+    /// The speed selected by the probe.
+    Speed {
+        /// The speed of the probe.
+        speed_khz: u32,
+    },
+    /// The target voltage.
+    Voltage {
+        /// The voltage in Volts, if the probe can measure it.
+        volts: Option<f32>,
+    },
+    /// The request was executed successfully.
+    Done,
+    /// The results of [`Request::Dap`].
+    Dap {
+        /// The values read by the successful operations.
+        values: Vec<u32>,
+        /// The error of the first failed operation.
+        error: Option<RemoteError>,
+    },
+    /// The results of [`Request::Jtag`].
+    Jtag {
+        /// The results of the successful operations.
+        results: Vec<JtagResult>,
+        /// The error of the first failed operation.
+        error: Option<RemoteError>,
+    },
+    /// The request failed.
+    Error(RemoteError),
+}
+
+/// A DAP operation, see [`RawDapAccess`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DapOperation {
+    /// Reads a register, which returns one value.
+    Read {
+        /// The port of the register.
+        port: PortType,
+        /// The address of the register.
+        address: u8,
+    },
+    /// Reads a register `count` times, which returns `count` values.
+    ReadBlock {
+        /// The port of the register.
+        port: PortType,
+        /// The address of the register.
+        address: u8,
+        /// The number of reads.
+        count: u32,
+    },
+    /// Writes a register.
+    Write {
+        /// The port of the register.
+        port: PortType,
+        /// The address of the register.
+        address: u8,
+        /// The value to write.
+        value: u32,
+    },
+    /// Writes a register once for every value.
+    WriteBlock {
+        /// The port of the register.
+        port: PortType,
+        /// The address of the register.
+        address: u8,
+        /// The values to write.
+        values: Vec<u32>,
+    },
+    /// Flushes the writes queued by the probe.
+    Flush,
+    /// Calls [`RawDapAccess::configure_jtag`].
+    ConfigureJtag,
+    /// Calls [`RawDapAccess::jtag_sequence`].
+    JtagSequence {
+        /// The number of clock cycles.
+        cycles: u8,
+        /// The value of TMS.
+        tms: bool,
+        /// The bits of TDI.
+        tdi: u64,
+    },
+    /// Calls [`RawDapAccess::swj_sequence`].
+    SwjSequence {
+        /// The number of bits.
+        bit_len: u8,
+        /// The bits of the sequence.
+        bits: u64,
+    },
+    /// Calls [`RawDapAccess::swj_pins`], which returns the state of the pins as one value.
+    SwjPins {
+        /// The values of the pins.
+        pin_out: u32,
+        /// The pins to set.
+        pin_select: u32,
+        /// The time to wait.
+        pin_wait: u32,
+    },
+}
+
+/// A JTAG operation, see [`JTAGAccess`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JtagOperation {
+    /// Reads the scan chain, which returns [`JtagResult::ScanChain`].
+    ScanChain,
+    /// Writes a register, which returns the bits shifted out as [`JtagResult::Data`].
+    WriteRegister {
+        /// The IR value of the register.
+        address: u32,
+        /// The bits to shift into the register.
+        data: Vec<u8>,
+        /// The number of bits in `data`.
+        len: u32,
+    },
+}
+
+/// The result of a [`JtagOperation`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JtagResult {
+    /// The TAPs on the scan chain.
+    ScanChain(Vec<RemoteJtagTap>),
+    /// The bits shifted out of a register.
+    Data(Vec<u8>),
+}
+
+/// A TAP on the JTAG scan chain, see [`JtagChainItem`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteJtagTap {
+    /// The IDCODE of the TAP.
+    pub idcode: Option<u32>,
+    /// The length of the instruction register.
+    pub irlen: usize,
+}
+
+impl From<&JtagChainItem> for RemoteJtagTap {
+    fn from(tap: &JtagChainItem) -> Self {
+        Self {
+            idcode: tap.idcode.map(u32::from),
+            irlen: tap.irlen,
+        }
+    }
+}
+
+impl From<RemoteJtagTap> for JtagChainItem {
+    fn from(tap: RemoteJtagTap) -> Self {
+        Self {
+            idcode: tap.idcode.map(IdCode::from),
+            irlen: tap.irlen,
+        }
+    }
+}
+
+/// A probe of the server, see [`DebugProbeInfo`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteProbeInfo {
+    /// The name of the probe.
+    pub identifier: String,
+    /// The USB vendor ID of the probe.
+    pub vendor_id: u16,
+    /// The USB product ID of the probe.
+    pub product_id: u16,
+    /// The serial number of the probe.
+    pub serial_number: Option<String>,
+}
+
+/// An error reported by the probe server, or an error in the communication with it.
+#[derive(
+    Debug, Clone, PartialEq, Eq, thiserror::Error, docsplay::Display, Serialize, Deserialize,
+)]
+/// {message}
+pub struct RemoteError {
+    /// The kind of the error.
+    pub kind: RemoteErrorKind,
+    /// The description of the error.
+    pub message: String,
+}
+
+/// The kind of a [`RemoteError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RemoteErrorKind {
+    /// The target answered a DAP operation with an error.
+    Dap(DapError),
+    /// An operation timed out.
+    Timeout,
+    /// The probe was not found.
+    NotFound,
+    /// The probe is not attached.
+    NotAttached,
+    /// The probe does not support the operation.
+    Unsupported,
+    /// The communication with the server failed, or the server doesn't understand the client.
+    Connection,
+    /// Any other error.
+    Other,
+}
+
+impl RemoteError {
+    fn new(kind: RemoteErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    fn unexpected_response(response: &Response) -> Self {
+        Self::new(
+            RemoteErrorKind::Connection,
+            format!("Unexpected response from the probe server: {response:?}"),
+        )
+    }
+}
+
+impl From<std::io::Error> for RemoteError {
+    fn from(error: std::io::Error) -> Self {
+        Self::new(
+            RemoteErrorKind::Connection,
+            format!("The connection to the probe server failed: {error}"),
+        )
+    }
+}
+
+impl From<&DebugProbeError> for RemoteError {
+    fn from(error: &DebugProbeError) -> Self {
+        let kind = match error {
+            DebugProbeError::Timeout => RemoteErrorKind::Timeout,
+            DebugProbeError::ProbeCouldNotBeCreated(ProbeCreationError::NotFound) => {
+                RemoteErrorKind::NotFound
+            }
+            DebugProbeError::NotAttached => RemoteErrorKind::NotAttached,
+            DebugProbeError::UnsupportedProtocol(_)
+            | DebugProbeError::UnsupportedSpeed(_)
+            | DebugProbeError::UnsupportedTargetPowerVoltage(_)
+            | DebugProbeError::InterfaceNotAvailable { .. }
+            | DebugProbeError::NotImplemented { .. }
+            | DebugProbeError::CommandNotSupportedByProbe { .. } => RemoteErrorKind::Unsupported,
+            _ => RemoteErrorKind::Other,
+        };
+
+        Self::new(kind, error.to_string())
+    }
+}
+
+impl From<&ArmError> for RemoteError {
+    fn from(error: &ArmError) -> Self {
+        match error {
+            ArmError::Dap(dap_error) => {
+                Self::new(RemoteErrorKind::Dap(*dap_error), error.to_string())
+            }
+            ArmError::Timeout => Self::new(RemoteErrorKind::Timeout, error.to_string()),
+            ArmError::Probe(probe_error) => probe_error.into(),
+            _ => Self::new(RemoteErrorKind::Other, error.to_string()),
+        }
+    }
+}
+
+impl From<&crate::Error> for RemoteError {
+    fn from(error: &crate::Error) -> Self {
+        match error {
+            crate::Error::Probe(probe_error) => probe_error.into(),
+            crate::Error::Arm(arm_error) => arm_error.into(),
+            crate::Error::Timeout => Self::new(RemoteErrorKind::Timeout, error.to_string()),
+            _ => Self::new(RemoteErrorKind::Other, error.to_string()),
+        }
+    }
+}
+
+impl From<RemoteError> for DebugProbeError {
+    fn from(error: RemoteError) -> Self {
+        match error.kind {
+            RemoteErrorKind::Timeout => DebugProbeError::Timeout,
+            RemoteErrorKind::NotFound => ProbeCreationError::NotFound.into(),
+            RemoteErrorKind::NotAttached => DebugProbeError::NotAttached,
+            _ => DebugProbeError::ProbeSpecific(Box::new(error)),
+        }
+    }
+}
+
+impl From<RemoteError> for ArmError {
+    fn from(error: RemoteError) -> Self {
+        match error.kind {
+            RemoteErrorKind::Dap(dap_error) => ArmError::Dap(dap_error),
+            RemoteErrorKind::Timeout => ArmError::Timeout,
+            _ => ArmError::Probe(error.into()),
+        }
+    }
+}
+
+/// Writes a length prefixed message.
+pub(super) fn write_message(
+    stream: &mut impl Write,
+    message: &impl Serialize,
+) -> std::io::Result<()> {
+    let data = rmp_serde::to_vec_named(message)
+        .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+
+    stream.write_all(&(data.len() as u32).to_le_bytes())?;
+    stream.write_all(&data)?;
+    stream.flush()
+}
+
+/// Reads a length prefixed message, or `None` if the peer closed the connection.
+pub(super) fn read_message<T: DeserializeOwned>(
+    stream: &mut impl Read,
+) -> std::io::Result<Option<T>> {
+    let mut length = [0; 4];
+    match stream.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error),
+    }
+
+    let length = u32::from_le_bytes(length) as usize;
+    if length > MAX_MESSAGE_SIZE {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Message of {length} bytes is too large"),
+        ));
+    }
+
+    let mut data = vec![0; length];
+    stream.read_exact(&mut data)?;
+
+    rmp_serde::from_slice(&data)
+        .map(Some)
+        .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+}
+
+/// Shares the probes found by a [`Lister`] over TCP.
+///
+/// Clients are served one after another, since a probe can only be used by one client anyway.
+#[derive(Debug)]
+pub struct ProbeServer<'a> {
+    lister: &'a Lister,
+}
+
+impl<'a> ProbeServer<'a> {
+    /// Creates a server sharing the probes found by `lister`.
+    pub fn new(lister: &'a Lister) -> Self {
+        Self { lister }
+    }
+
+    /// Accepts connections on `listener` and serves them until accepting a connection fails.
+    pub fn serve(&self, listener: &TcpListener) -> std::io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let peer = stream.peer_addr()?;
+
+            tracing::info!("Client {peer} connected");
+            match self.handle_connection(stream) {
+                Ok(()) => tracing::info!("Client {peer} disconnected"),
+                Err(error) => tracing::warn!("Connection to client {peer} failed: {error}"),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serves a single client until it closes the connection.
+    pub fn handle_connection(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        stream.set_nodelay(true)?;
+
+        let mut probe = None;
+        while let Some(request) = read_message(&mut stream)? {
+            tracing::trace!("Request: {request:?}");
+            let response = self.handle_request(&mut probe, request);
+            write_message(&mut stream, &response)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_request(
+        &self,
+        probe: &mut Option<Box<dyn DebugProbe>>,
+        request: Request,
+    ) -> Response {
+        let done = |result: Result<(), DebugProbeError>| match result {
+            Ok(()) => Response::Done,
+            Err(error) => Response::Error((&error).into()),
+        };
+
+        let request = match request {
+            Request::Hello { version } if version == PROTOCOL_VERSION => {
+                return Response::Hello {
+                    version: PROTOCOL_VERSION,
+                }
+            }
+            Request::Hello { version } => {
+                return Response::Error(RemoteError::new(
+                    RemoteErrorKind::Connection,
+                    format!(
+                    "The probe server supports protocol version {PROTOCOL_VERSION}, not {version}"
+                ),
+                ))
+            }
+            Request::List => {
+                let probes = self
+                    .lister
+                    .list_all()
+                    .into_iter()
+                    .map(|info| RemoteProbeInfo {
+                        identifier: info.identifier,
+                        vendor_id: info.vendor_id,
+                        product_id: info.product_id,
+                        serial_number: info.serial_number,
+                    })
+                    .collect();
+                return Response::Probes { probes };
+            }
+            Request::Open { selector } => {
+                // A probe opened before is closed first.
+                *probe = None;
+                let selector = match selector.parse::<DebugProbeSelector>() {
+                    Ok(selector) => selector,
+                    Err(error) => {
+                        return Response::Error(RemoteError::new(
+                            RemoteErrorKind::Other,
+                            error.to_string(),
+                        ))
+                    }
+                };
+
+                let mut opened = match self.lister.open(selector) {
+                    Ok(opened) => opened.inner,
+                    Err(error) => return Response::Error((&error).into()),
+                };
+                let response = Response::Opened {
+                    name: opened.get_name().to_string(),
+                    speed_khz: opened.speed_khz(),
+                    protocol: opened.active_protocol(),
+                    dap: opened.try_as_dap_probe().is_some(),
+                    jtag: opened.try_as_jtag_probe().is_some(),
+                    power_voltages: opened.target_power_voltages(),
+                };
+                *probe = Some(opened);
+                return response;
+            }
+            request => request,
+        };
+
+        let Some(probe) = probe else {
+            return Response::Error(RemoteError::new(
+                RemoteErrorKind::NotFound,
+                "No probe was opened",
+            ));
+        };
+
+        match request {
+            Request::SetSpeed { speed_khz } => match probe.set_speed(speed_khz) {
+                Ok(speed_khz) => Response::Speed { speed_khz },
+                Err(error) => Response::Error((&error).into()),
+            },
+            Request::SetScanChain { scan_chain } => done(probe.set_scan_chain(scan_chain)),
+            Request::SelectProtocol { protocol } => done(probe.select_protocol(protocol)),
+            Request::Attach => done(probe.attach()),
+            Request::Detach => match probe.detach() {
+                Ok(()) => Response::Done,
+                Err(error) => Response::Error((&error).into()),
+            },
+            Request::TargetReset => done(probe.target_reset()),
+            Request::TargetResetAssert => done(probe.target_reset_assert()),
+            Request::TargetResetDeassert => done(probe.target_reset_deassert()),
+            Request::TargetVoltage => match probe.get_target_voltage() {
+                Ok(volts) => Response::Voltage { volts },
+                Err(error) => Response::Error((&error).into()),
+            },
+            Request::SetTargetPower { on, voltage } => done(probe.set_target_power(on, voltage)),
+            Request::Dap { operations } => match probe.try_as_dap_probe() {
+                Some(dap) => execute_dap_operations(dap, operations),
+                None => Response::Error(RemoteError::new(
+                    RemoteErrorKind::Unsupported,
+                    "The probe does not offer raw DAP access",
+                )),
+            },
+            Request::Jtag {
+                idle_cycles,
+                operations,
+            } => match probe.try_as_jtag_probe() {
+                Some(jtag) => {
+                    jtag.set_idle_cycles(idle_cycles);
+                    execute_jtag_operations(jtag, operations)
+                }
+                None => Response::Error(RemoteError::new(
+                    RemoteErrorKind::Unsupported,
+                    "The probe does not offer JTAG access",
+                )),
+            },
+            Request::Hello { .. } | Request::List | Request::Open { .. } => unreachable!(),
+        }
+    }
+}
+
+fn execute_dap_operations(probe: &mut dyn DapProbe, operations: Vec<DapOperation>) -> Response {
+    let mut values = vec![];
+
+    for operation in operations {
+        if let Err(error) = execute_dap_operation(probe, operation, &mut values) {
+            return Response::Dap {
+                values,
+                error: Some((&error).into()),
+            };
+        }
+    }
+
+    Response::Dap {
+        values,
+        error: None,
+    }
+}
+
+/// Executes a DAP operation, and appends the values it reads to `values`.
+pub(super) fn execute_dap_operation(
+    probe: &mut dyn DapProbe,
+    operation: DapOperation,
+    values: &mut Vec<u32>,
+) -> Result<(), ArmError> {
+    match operation {
+        DapOperation::Read { port, address } => probe
+            .raw_read_register(port, address)
+            .map(|value| values.push(value)),
+        DapOperation::ReadBlock {
+            port,
+            address,
+            count,
+        } => {
+            // The values have to fit into a single response.
+            if count as usize > MAX_MESSAGE_SIZE / 4 {
+                return Err(RemoteError::new(
+                    RemoteErrorKind::Connection,
+                    format!("Reading a block of {count} words exceeds the maximum message size"),
+                )
+                .into());
+            }
+
+            let mut block = vec![0; count as usize];
+            probe
+                .raw_read_block(port, address, &mut block)
+                .map(|()| values.extend(block))
+        }
+        DapOperation::Write {
+            port,
+            address,
+            value,
+        } => probe.raw_write_register(port, address, value),
+        DapOperation::WriteBlock {
+            port,
+            address,
+            values,
+        } => probe.raw_write_block(port, address, &values),
+        DapOperation::Flush => probe.raw_flush(),
+        DapOperation::ConfigureJtag => probe.configure_jtag().map_err(ArmError::from),
+        DapOperation::JtagSequence { cycles, tms, tdi } => probe
+            .jtag_sequence(cycles, tms, tdi)
+            .map_err(ArmError::from),
+        DapOperation::SwjSequence { bit_len, bits } => {
+            probe.swj_sequence(bit_len, bits).map_err(ArmError::from)
+        }
+        DapOperation::SwjPins {
+            pin_out,
+            pin_select,
+            pin_wait,
+        } => probe
+            .swj_pins(pin_out, pin_select, pin_wait)
+            .map(|pins| values.push(pins))
+            .map_err(ArmError::from),
+    }
+}
+
+fn execute_jtag_operations(probe: &mut dyn JTAGAccess, operations: Vec<JtagOperation>) -> Response {
+    let mut results = vec![];
+    let mut operations = operations.into_iter().peekable();
+
+    while let Some(operation) = operations.next() {
+        let error = match operation {
+            JtagOperation::ScanChain => match probe.scan_chain() {
+                Ok(chain) => {
+                    results.push(JtagResult::ScanChain(
+                        chain.iter().map(RemoteJtagTap::from).collect(),
+                    ));
+                    continue;
+                }
+                Err(error) => RemoteError::from(&error),
+            },
+            JtagOperation::WriteRegister { address, data, len } => {
+                // Consecutive register writes are executed as one batch.
+                let mut queue = JtagCommandQueue::new();
+                let mut indices = vec![queue.schedule(raw_write_command(address, data, len))];
+                while let Some(JtagOperation::WriteRegister { .. }) = operations.peek() {
+                    let Some(JtagOperation::WriteRegister { address, data, len }) =
+                        operations.next()
+                    else {
+                        unreachable!()
+                    };
+                    indices.push(queue.schedule(raw_write_command(address, data, len)));
+                }
+
+                let (mut deferred, error) = match probe.write_register_batch(&queue) {
+                    Ok(deferred) => (deferred, None),
+                    Err(BatchExecutionError { error, results }) => (results, Some(error)),
+                };
+                for index in indices {
+                    match deferred.take(index) {
+                        Ok(CommandResult::VecU8(data)) => results.push(JtagResult::Data(data)),
+                        _ => break,
+                    }
+                }
+
+                match error {
+                    Some(error) => RemoteError::from(&error),
+                    None => continue,
+                }
+            }
+        };
+
+        return Response::Jtag {
+            results,
+            error: Some(error),
+        };
+    }
+
+    Response::Jtag {
+        results,
+        error: None,
+    }
+}
+
+/// A register write which returns the bits shifted out unmodified.
+pub(super) fn raw_write_command(address: u32, data: Vec<u8>, len: u32) -> JtagWriteCommand {
+    JtagWriteCommand {
+        address,
+        data,
+        len,
+        transform: |data| Ok(CommandResult::VecU8(data)),
+    }
+}
+
+/// The connection to a probe server.
+#[derive(Debug)]
+struct Connection {
+    stream: TcpStream,
+}
+
+impl Connection {
+    fn connect(address: &str) -> Result<Self, RemoteError> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+
+        let mut connection = Self { stream };
+        match connection.request(&Request::Hello {
+            version: PROTOCOL_VERSION,
+        })? {
+            Response::Hello { .. } => Ok(connection),
+            response => Err(RemoteError::unexpected_response(&response)),
+        }
+    }
+
+    /// Sends a request, and returns the response. [`Response::Error`] is returned as error.
+    fn request(&mut self, request: &Request) -> Result<Response, RemoteError> {
+        write_message(&mut self.stream, request)?;
+
+        match read_message(&mut self.stream)? {
+            Some(Response::Error(error)) => Err(error),
+            Some(response) => Ok(response),
+            None => Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into()),
+        }
+    }
+
+    fn request_done(&mut self, request: &Request) -> Result<(), RemoteError> {
+        match self.request(request)? {
+            Response::Done => Ok(()),
+            response => Err(RemoteError::unexpected_response(&response)),
+        }
+    }
+}
+
+/// Opens probes shared by a [`ProbeServer`], for selectors with an address.
+#[derive(Debug)]
+pub struct RemoteProbeFactory;
+
+impl std::fmt::Display for RemoteProbeFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Remote")
+    }
+}
+
+impl RemoteProbeFactory {
+    /// Lists the probes shared by the probe server at `address`.
+    pub fn list_probes_at(&self, address: &str) -> Result<Vec<DebugProbeInfo>, DebugProbeError> {
+        let mut connection = Connection::connect(address)?;
+
+        match connection.request(&Request::List)? {
+            Response::Probes { probes } => Ok(probes
+                .into_iter()
+                .map(|probe| {
+                    DebugProbeInfo::new(
+                        probe.identifier,
+                        probe.vendor_id,
+                        probe.product_id,
+                        probe.serial_number,
+                        &RemoteProbeFactory,
+                        None,
+                    )
+                    .with_remote(address)
+                })
+                .collect()),
+            response => Err(RemoteError::unexpected_response(&response).into()),
+        }
+    }
+}
+
+impl ProbeFactory for RemoteProbeFactory {
+    fn open(&self, selector: &DebugProbeSelector) -> Result<Box<dyn DebugProbe>, DebugProbeError> {
+        let Some(address) = &selector.remote else {
+            return Err(ProbeCreationError::NotFound.into());
+        };
+
+        let mut connection = Connection::connect(address)?;
+        let local_selector = DebugProbeSelector {
+            remote: None,
+            ..selector.clone()
+        };
+        let response = connection.request(&Request::Open {
+            selector: local_selector.to_string(),
+        })?;
+
+        match response {
+            Response::Opened {
+                name,
+                speed_khz,
+                protocol,
+                dap,
+                jtag,
+                power_voltages,
+            } => Ok(Box::new(RemoteProbe {
+                connection,
+                name: format!("{name} at tcp://{address}"),
+                speed_khz,
+                protocol,
+                dap,
+                jtag,
+                power_voltages,
+                queue: vec![],
+                idle_cycles: 0,
+            })),
+            response => Err(RemoteError::unexpected_response(&response).into()),
+        }
+    }
+
+    /// Remote probes can't be listed without the address of the server, see
+    /// [`RemoteProbeFactory::list_probes_at`].
+    fn list_probes(&self) -> Vec<DebugProbeInfo> {
+        vec![]
+    }
+}
+
+/// Lists and opens the probes shared by the probe server at an address.
+#[derive(Debug)]
+pub struct RemoteProbeLister {
+    address: String,
+}
+
+impl RemoteProbeLister {
+    /// Creates a lister for the probe server at `address`, e.g. `lab-1:6250`.
+    pub fn new(address: impl ToString) -> Self {
+        Self {
+            address: address.to_string(),
+        }
+    }
+}
+
+impl ProbeLister for RemoteProbeLister {
+    fn open(&self, selector: &DebugProbeSelector) -> Result<Probe, DebugProbeError> {
+        let selector = DebugProbeSelector {
+            remote: Some(self.address.clone()),
+            ..selector.clone()
+        };
+
+        RemoteProbeFactory
+            .open(&selector)
+            .map(Probe::from_specific_probe)
+    }
+
+    fn list_all(&self) -> Vec<DebugProbeInfo> {
+        RemoteProbeFactory
+            .list_probes_at(&self.address)
+            .unwrap_or_else(|error| {
+                tracing::warn!("Failed to list the probes at {}: {error}", self.address);
+                vec![]
+            })
+    }
+}
+
+/// A probe shared by a [`ProbeServer`].
+#[derive(Debug)]
+pub struct RemoteProbe {
+    connection: Connection,
+    name: String,
+    speed_khz: u32,
+    protocol: Option<WireProtocol>,
+    dap: bool,
+    jtag: bool,
+    power_voltages: Vec<TargetPowerVoltage>,
+    /// DAP operations which are sent with the next read.
+    queue: Vec<DapOperation>,
+    idle_cycles: u8,
+}
+
+impl RemoteProbe {
+    /// Sends the queued DAP operations and `operation`, and returns the values read.
+    fn execute_dap(&mut self, operation: DapOperation) -> Result<Vec<u32>, RemoteError> {
+        self.queue.push(operation);
+        let operations = std::mem::take(&mut self.queue);
+
+        match self.connection.request(&Request::Dap { operations })? {
+            Response::Dap {
+                error: Some(error), ..
+            } => Err(error),
+            Response::Dap { values, .. } => Ok(values),
+            response => Err(RemoteError::unexpected_response(&response)),
+        }
+    }
+
+    /// Queues a DAP operation which doesn't return a value.
+    fn queue_dap(&mut self, operation: DapOperation) -> Result<(), RemoteError> {
+        self.queue.push(operation);
+        if self.queue.len() >= MAX_QUEUED_OPERATIONS {
+            self.execute_dap(DapOperation::Flush)?;
+        }
+
+        Ok(())
+    }
+
+    fn execute_jtag(
+        &mut self,
+        operations: Vec<JtagOperation>,
+    ) -> Result<(Vec<JtagResult>, Option<RemoteError>), RemoteError> {
+        let request = Request::Jtag {
+            idle_cycles: self.idle_cycles,
+            operations,
+        };
+
+        match self.connection.request(&request)? {
+            Response::Jtag { results, error } => Ok((results, error)),
+            response => Err(RemoteError::unexpected_response(&response)),
+        }
+    }
+}
+
+impl DebugProbe for RemoteProbe {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn speed_khz(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        match self.connection.request(&Request::SetSpeed { speed_khz })? {
+            Response::Speed { speed_khz } => {
+                self.speed_khz = speed_khz;
+                Ok(speed_khz)
+            }
+            response => Err(RemoteError::unexpected_response(&response).into()),
+        }
+    }
+
+    fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError> {
+        Ok(self
+            .connection
+            .request_done(&Request::SetScanChain { scan_chain })?)
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        Ok(self.connection.request_done(&Request::Attach)?)
+    }
+
+    fn detach(&mut self) -> Result<(), crate::Error> {
+        if !self.queue.is_empty() {
+            self.execute_dap(DapOperation::Flush)
+                .map_err(|error| crate::Error::Arm(error.into()))?;
+        }
+
+        self.connection
+            .request_done(&Request::Detach)
+            .map_err(|error| crate::Error::Probe(error.into()))
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        Ok(self.connection.request_done(&Request::TargetReset)?)
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        Ok(self.connection.request_done(&Request::TargetResetAssert)?)
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        Ok(self
+            .connection
+            .request_done(&Request::TargetResetDeassert)?)
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        self.connection
+            .request_done(&Request::SelectProtocol { protocol })?;
+        self.protocol = Some(protocol);
+
+        Ok(())
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        self.protocol
+    }
+
+    fn has_arm_interface(&self) -> bool {
+        self.dap
+    }
+
+    fn try_get_arm_interface<'probe>(
+        self: Box<Self>,
+    ) -> Result<Box<dyn UninitializedArmProbe + 'probe>, (Box<dyn DebugProbe>, DebugProbeError)>
+    {
+        if !self.dap {
+            return Err((
+                self,
+                DebugProbeError::InterfaceNotAvailable {
+                    interface_name: "ARM",
+                },
+            ));
+        }
+
+        Ok(Box::new(ArmCommunicationInterface::new(self, false)))
+    }
+
+    fn has_riscv_interface(&self) -> bool {
+        self.jtag
+    }
+
+    fn try_get_riscv_interface(
+        self: Box<Self>,
+    ) -> Result<RiscvCommunicationInterface, (Box<dyn DebugProbe>, RiscvError)> {
+        if !self.jtag {
+            return Err((
+                self,
+                DebugProbeError::InterfaceNotAvailable {
+                    interface_name: "RISC-V",
+                }
+                .into(),
+            ));
+        }
+
+        let jtag_dtm = match JtagDtm::new(self) {
+            Ok(jtag_dtm) => Box::new(jtag_dtm),
+            Err((access, err)) => return Err((access.into_probe(), err)),
+        };
+        match RiscvCommunicationInterface::new(jtag_dtm) {
+            Ok(interface) => Ok(interface),
+            Err((probe, err)) => Err((probe.into_probe(), err)),
+        }
+    }
+
+    fn has_xtensa_interface(&self) -> bool {
+        self.jtag
+    }
+
+    fn try_get_xtensa_interface(
+        self: Box<Self>,
+    ) -> Result<XtensaCommunicationInterface, (Box<dyn DebugProbe>, DebugProbeError)> {
+        if !self.jtag {
+            return Err((
+                self,
+                DebugProbeError::InterfaceNotAvailable {
+                    interface_name: "Xtensa",
+                },
+            ));
+        }
+
+        match XtensaCommunicationInterface::new(self) {
+            Ok(interface) => Ok(interface),
+            Err((probe, err)) => Err((probe.into_probe(), err)),
+        }
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn try_as_dap_probe(&mut self) -> Option<&mut dyn DapProbe> {
+        if self.dap {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        if self.jtag {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
+        match self.connection.request(&Request::TargetVoltage)? {
+            Response::Voltage { volts } => Ok(volts),
+            response => Err(RemoteError::unexpected_response(&response).into()),
+        }
+    }
+
+    fn target_power_voltages(&self) -> Vec<TargetPowerVoltage> {
+        self.power_voltages.clone()
+    }
+
+    fn set_target_power(
+        &mut self,
+        on: bool,
+        voltage: TargetPowerVoltage,
+    ) -> Result<(), DebugProbeError> {
+        Ok(self
+            .connection
+            .request_done(&Request::SetTargetPower { on, voltage })?)
+    }
+}
+
+impl RawDapAccess for RemoteProbe {
+    fn raw_read_register(&mut self, port: PortType, address: u8) -> Result<u32, ArmError> {
+        let values = self.execute_dap(DapOperation::Read { port, address })?;
+
+        values.last().copied().ok_or_else(|| {
+            RemoteError::new(
+                RemoteErrorKind::Connection,
+                "The probe server returned no value",
+            )
+            .into()
+        })
+    }
+
+    fn raw_read_block(
+        &mut self,
+        port: PortType,
+        address: u8,
+        values: &mut [u32],
+    ) -> Result<(), ArmError> {
+        let block = self.execute_dap(DapOperation::ReadBlock {
+            port,
+            address,
+            count: values.len() as u32,
+        })?;
+
+        if block.len() != values.len() {
+            return Err(RemoteError::new(
+                RemoteErrorKind::Connection,
+                format!(
+                    "The probe server returned {} values instead of {}",
+                    block.len(),
+                    values.len()
+                ),
+            )
+            .into());
+        }
+        values.copy_from_slice(&block);
+
+        Ok(())
+    }
+
+    fn raw_write_register(
+        &mut self,
+        port: PortType,
+        address: u8,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        Ok(self.queue_dap(DapOperation::Write {
+            port,
+            address,
+            value,
+        })?)
+    }
+
+    fn raw_write_block(
+        &mut self,
+        port: PortType,
+        address: u8,
+        values: &[u32],
+    ) -> Result<(), ArmError> {
+        Ok(self.queue_dap(DapOperation::WriteBlock {
+            port,
+            address,
+            values: values.to_vec(),
+        })?)
+    }
+
+    fn raw_flush(&mut self) -> Result<(), ArmError> {
+        self.execute_dap(DapOperation::Flush)?;
+
+        Ok(())
+    }
+
+    fn configure_jtag(&mut self) -> Result<(), DebugProbeError> {
+        self.execute_dap(DapOperation::ConfigureJtag)?;
+
+        Ok(())
+    }
+
+    fn jtag_sequence(&mut self, cycles: u8, tms: bool, tdi: u64) -> Result<(), DebugProbeError> {
+        self.execute_dap(DapOperation::JtagSequence { cycles, tms, tdi })?;
+
+        Ok(())
+    }
+
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), DebugProbeError> {
+        self.execute_dap(DapOperation::SwjSequence { bit_len, bits })?;
+
+        Ok(())
+    }
+
+    fn swj_pins(
+        &mut self,
+        pin_out: u32,
+        pin_select: u32,
+        pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        let values = self.execute_dap(DapOperation::SwjPins {
+            pin_out,
+            pin_select,
+            pin_wait,
+        })?;
+
+        values.last().copied().ok_or_else(|| {
+            RemoteError::new(
+                RemoteErrorKind::Connection,
+                "The probe server returned no value",
+            )
+            .into()
+        })
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn core_status_notification(&mut self, _: crate::CoreStatus) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+}
+
+impl DapProbe for RemoteProbe {}
+
+impl JTAGAccess for RemoteProbe {
+    fn scan_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        match self.execute_jtag(vec![JtagOperation::ScanChain])? {
+            (_, Some(error)) => Err(error.into()),
+            (mut results, None) => match results.pop() {
+                Some(JtagResult::ScanChain(chain)) => {
+                    Ok(chain.into_iter().map(JtagChainItem::from).collect())
+                }
+                _ => Err(RemoteError::new(
+                    RemoteErrorKind::Connection,
+                    "The probe server returned no scan chain",
+                )
+                .into()),
+            },
+        }
+    }
+
+    fn set_idle_cycles(&mut self, idle_cycles: u8) {
+        self.idle_cycles = idle_cycles;
+    }
+
+    fn idle_cycles(&self) -> u8 {
+        self.idle_cycles
+    }
+
+    fn write_register(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        len: u32,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        let operation = JtagOperation::WriteRegister {
+            address,
+            data: data.to_vec(),
+            len,
+        };
+
+        match self.execute_jtag(vec![operation])? {
+            (_, Some(error)) => Err(error.into()),
+            (mut results, None) => match results.pop() {
+                Some(JtagResult::Data(data)) => Ok(data),
+                _ => Err(RemoteError::new(
+                    RemoteErrorKind::Connection,
+                    "The probe server returned no data",
+                )
+                .into()),
+            },
+        }
+    }
+
+    fn write_register_batch(
+        &mut self,
+        writes: &JtagCommandQueue,
+    ) -> Result<DeferredResultSet, BatchExecutionError> {
+        let operations = writes
+            .iter()
+            .map(|(_, write)| JtagOperation::WriteRegister {
+                address: write.address,
+                data: write.data.clone(),
+                len: write.len,
+            })
+            .collect();
+
+        let (results, error) = self.execute_jtag(operations).map_err(|error| {
+            BatchExecutionError::new(crate::Error::Probe(error.into()), DeferredResultSet::new())
+        })?;
+
+        let mut deferred = DeferredResultSet::with_capacity(results.len());
+        for ((index, write), result) in writes.iter().zip(results) {
+            let JtagResult::Data(data) = result else {
+                let error = RemoteError::new(
+                    RemoteErrorKind::Connection,
+                    "The probe server returned no data",
+                );
+                return Err(BatchExecutionError::new(
+                    crate::Error::Probe(error.into()),
+                    deferred,
+                ));
+            };
+
+            match (write.transform)(data) {
+                Ok(result) => deferred.push(index, result),
+                Err(error) => return Err(BatchExecutionError::new(error, deferred)),
+            }
+        }
+
+        match error {
+            Some(error) => Err(BatchExecutionError::new(
+                crate::Error::Probe(error.into()),
+                deferred,
+            )),
+            None => Ok(deferred),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::probe::fake_probe::FakeProbe;
+
+    /// Lists a single [`FakeProbe`], which mocks a target by recording the DAP writes, and answering
+    /// reads with the address and the number of writes so far.
+    #[derive(Debug)]
+    struct FakeProbeLister {
+        writes: Arc<Mutex<Vec<(PortType, u8, u32)>>>,
+    }
+
+    #[derive(Debug)]
+    struct FakeProbeFactory;
+
+    impl std::fmt::Display for FakeProbeFactory {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("Fake")
+        }
+    }
+
+    impl ProbeFactory for FakeProbeFactory {
+        fn open(&self, _: &DebugProbeSelector) -> Result<Box<dyn DebugProbe>, DebugProbeError> {
+            unreachable!()
+        }
+
+        fn list_probes(&self) -> Vec<DebugProbeInfo> {
+            unreachable!()
+        }
+    }
+
+    impl ProbeLister for FakeProbeLister {
+        fn open(&self, selector: &DebugProbeSelector) -> Result<Probe, DebugProbeError> {
+            if selector.serial_number.as_deref() != Some("fake") {
+                return Err(ProbeCreationError::NotFound.into());
+            }
+
+            let mut probe = FakeProbe::new();
+            let writes = self.writes.clone();
+            probe.set_dap_register_read_handler(Box::new(move |port, address| {
+                match (port, address) {
+                    (PortType::AccessPort, 0xC) => Err(ArmError::Dap(DapError::FaultResponse)),
+                    _ => Ok(address as u32 * 0x100 + writes.lock().unwrap().len() as u32),
+                }
+            }));
+            let writes = self.writes.clone();
+            probe.set_dap_register_write_handler(Box::new(move |port, address, value| {
+                writes.lock().unwrap().push((port, address, value));
+                Ok(())
+            }));
+
+            Ok(probe.into_probe())
+        }
+
+        fn list_all(&self) -> Vec<DebugProbeInfo> {
+            vec![DebugProbeInfo::new(
+                "Fake probe",
+                0x1234,
+                0x5678,
+                Some("fake".to_string()),
+                &FakeProbeFactory,
+                None,
+            )]
+        }
+    }
+
+    #[test]
+    fn share_fake_probe_over_loopback() {
+        let writes = Arc::new(Mutex::new(vec![]));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let server_writes = writes.clone();
+        let server = std::thread::spawn(move || {
+            let lister = Lister::with_lister(Box::new(FakeProbeLister {
+                writes: server_writes,
+            }));
+            let server = ProbeServer::new(&lister);
+
+            // The first connection lists the probes, the second one opens the probe.
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().unwrap();
+                server.handle_connection(stream).unwrap();
+            }
+        });
+
+        let lister = Lister::with_lister(Box::new(RemoteProbeLister::new(&address)));
+        let probes = lister.list_all();
+        assert_eq!(probes.len(), 1);
+        assert_eq!(probes[0].identifier, "Fake probe");
+        assert_eq!(probes[0].serial_number.as_deref(), Some("fake"));
+        assert!(probes[0].is_probe_type::<RemoteProbeFactory>());
+
+        // Open the probe the same way as `--probe tcp://HOST:PORT/VID:PID:SERIAL`.
+        let selector: DebugProbeSelector =
+            format!("tcp://{address}/1234:5678:fake").parse().unwrap();
+        assert_eq!(
+            DebugProbeSelector::from(&probes[0]).to_string(),
+            selector.to_string()
+        );
+        let mut probe = Lister::new().open(selector).unwrap();
+
+        assert_eq!(
+            probe.get_name(),
+            format!("Mock probe for testing at tcp://{address}")
+        );
+        assert_eq!(probe.set_speed(4000).unwrap(), 4000);
+        assert_eq!(probe.speed_khz(), 4000);
+        probe.select_protocol(WireProtocol::Swd).unwrap();
+        assert!(probe.has_arm_interface());
+        assert!(probe.try_as_jtag_probe().is_none());
+
+        let dap = probe.try_as_dap_probe().unwrap();
+        dap.raw_write_register(PortType::DebugPort, 0x8, 0x11)
+            .unwrap();
+        dap.raw_write_block(PortType::AccessPort, 0x4, &[1, 2, 3])
+            .unwrap();
+
+        // Writes are queued until a value is read.
+        assert!(writes.lock().unwrap().is_empty());
+        assert_eq!(
+            dap.raw_read_register(PortType::DebugPort, 0x4).unwrap(),
+            0x404
+        );
+        assert_eq!(
+            *writes.lock().unwrap(),
+            [
+                (PortType::DebugPort, 0x8, 0x11),
+                (PortType::AccessPort, 0x4, 1),
+                (PortType::AccessPort, 0x4, 2),
+                (PortType::AccessPort, 0x4, 3),
+            ]
+        );
+
+        let mut block = [0; 2];
+        dap.raw_read_block(PortType::AccessPort, 0x8, &mut block)
+            .unwrap();
+        assert_eq!(block, [0x804, 0x804]);
+
+        // Errors of the target are passed on, so the ARM interface can handle them.
+        assert!(matches!(
+            dap.raw_read_register(PortType::AccessPort, 0xC),
+            Err(ArmError::Dap(DapError::FaultResponse))
+        ));
+
+        dap.raw_write_register(PortType::DebugPort, 0x8, 0x22)
+            .unwrap();
+        probe.detach().unwrap();
+        assert_eq!(writes.lock().unwrap().len(), 5);
+
+        drop(probe);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn reject_oversized_block_read() {
+        let mut probe = FakeProbe::default();
+        probe.set_dap_register_read_handler(Box::new(|_, _| Ok(0)));
+        probe.set_dap_register_write_handler(Box::new(|_, _, _| Ok(())));
+        let dap = probe.try_as_dap_probe().unwrap();
+
+        let mut values = vec![];
+        let result = execute_dap_operation(
+            dap,
+            DapOperation::ReadBlock {
+                port: PortType::AccessPort,
+                address: 0xC,
+                count: u32::MAX,
+            },
+            &mut values,
+        );
+
+        assert!(result.is_err());
+        assert!(values.is_empty());
+    }
+
+    #[test]
+    fn reject_unknown_probe() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let server = std::thread::spawn(move || {
+            let lister = Lister::with_lister(Box::new(FakeProbeLister {
+                writes: Default::default(),
+            }));
+            let (stream, _) = listener.accept().unwrap();
+            ProbeServer::new(&lister).handle_connection(stream).unwrap();
+        });
+
+        let selector: DebugProbeSelector =
+            format!("tcp://{address}/1234:5678:other").parse().unwrap();
+        assert!(matches!(
+            Lister::new().open(selector),
+            Err(DebugProbeError::ProbeCouldNotBeCreated(
+                ProbeCreationError::NotFound
+            ))
+        ));
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn message_framing() {
+        let request = Request::Dap {
+            operations: vec![
+                DapOperation::Write {
+                    port: PortType::DebugPort,
+                    address: 0x8,
+                    value: 0xF0,
+                },
+                DapOperation::Read {
+                    port: PortType::AccessPort,
+                    address: 0xC,
+                },
+            ],
+        };
+
+        let mut buffer = vec![];
+        write_message(&mut buffer, &request).unwrap();
+        assert_eq!(
+            u32::from_le_bytes(buffer[..4].try_into().unwrap()) as usize,
+            buffer.len() - 4
+        );
+
+        let mut reader = buffer.as_slice();
+        assert_eq!(read_message::<Request>(&mut reader).unwrap(), Some(request));
+        assert_eq!(read_message::<Request>(&mut reader).unwrap(), None);
+    }
+}
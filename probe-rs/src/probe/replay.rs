@@ -0,0 +1,1415 @@
+//! Recording the communication with a probe, and replaying it without the hardware.
+//!
+//! A [`RecordingProbe`] wraps any probe offering raw DAP or JTAG access, and records every call
+//! together with its result. A [`ReplayProbe`] plays such a recording back: it answers every call
+//! with the recorded result, and fails as soon as a call differs from the recording. This makes it
+//! possible to capture a session on real hardware once, and to run it as a deterministic regression
+//! test without the hardware afterwards.
+//!
+//! Like the probes shared by a [`ProbeServer`](super::remote::ProbeServer), only the raw DAP and
+//! JTAG access of a probe is recorded, and the ARM, RISC-V and Xtensa interfaces are built on top
+//! of it.
+//!
+//! # File format
+//!
+//! A recording uses the message framing of the [remote probe protocol](super::remote): a
+//! [`RecordingHeader`] followed by one [`Transaction`] per call, each encoded as MessagePack and
+//! prefixed by its length.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use probe_rs_target::ScanChainElement;
+use serde::{Deserialize, Serialize};
+
+use crate::architecture::arm::communication_interface::{DapProbe, UninitializedArmProbe};
+use crate::architecture::arm::{ArmCommunicationInterface, ArmError, PortType, RawDapAccess};
+use crate::architecture::riscv::communication_interface::{
+    RiscvCommunicationInterface, RiscvError,
+};
+use crate::architecture::riscv::dtm::jtag_dtm::JtagDtm;
+use crate::architecture::xtensa::communication_interface::XtensaCommunicationInterface;
+use crate::probe::power::TargetPowerVoltage;
+use crate::probe::remote::{
+    execute_dap_operation, raw_write_command, read_message, write_message, DapOperation,
+    JtagOperation, JtagResult, RemoteError, RemoteJtagTap,
+};
+use crate::probe::uart::UartAccess;
+use crate::probe::{
+    BatchExecutionError, CommandResult, DebugProbe, DebugProbeError, DeferredResultSet, JTAGAccess,
+    JtagChainItem, JtagCommandQueue, WireProtocol,
+};
+use crate::CoreStatus;
+
+/// The version of the file format, which is stored in the [`RecordingHeader`].
+pub const RECORDING_VERSION: u32 = 1;
+
+/// An error while recording or replaying the communication with a probe.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum ReplayError {
+    /// Failed to access the recording.
+    Io(#[from] std::io::Error),
+
+    /// The recording is invalid: {0}
+    InvalidRecording(String),
+
+    /// Call {index} differs from the recording: expected {expected}, but got {actual}.
+    Diverged {
+        /// The index of the call.
+        index: usize,
+        /// The recorded call.
+        expected: String,
+        /// The actual call.
+        actual: String,
+    },
+
+    /// Call {index} is not part of the recording, which ended before: {actual}.
+    Finished {
+        /// The index of the call.
+        index: usize,
+        /// The actual call.
+        actual: String,
+    },
+
+    /// The probe does not offer {0} access, which is needed for recording it.
+    Unsupported(&'static str),
+}
+
+impl From<ReplayError> for DebugProbeError {
+    fn from(error: ReplayError) -> Self {
+        DebugProbeError::ProbeSpecific(Box::new(error))
+    }
+}
+
+impl From<ReplayError> for ArmError {
+    fn from(error: ReplayError) -> Self {
+        ArmError::Probe(error.into())
+    }
+}
+
+/// The start of a recording, describing the recorded probe.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordingHeader {
+    /// The version of the file format.
+    pub version: u32,
+    /// The name of the probe.
+    pub name: String,
+    /// The speed of the probe when the recording started.
+    pub speed_khz: u32,
+    /// The protocol of the probe when the recording started.
+    pub protocol: Option<WireProtocol>,
+    /// Whether the probe offers raw DAP access.
+    pub dap: bool,
+    /// Whether the probe offers JTAG access.
+    pub jtag: bool,
+    /// The JTAG idle cycles when the recording started.
+    pub idle_cycles: u8,
+    /// The voltages the probe can supply to the target.
+    #[serde(default)]
+    pub power_voltages: Vec<TargetPowerVoltage>,
+}
+
+/// A call to the probe.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Call {
+    /// [`DebugProbe::set_speed`]
+    SetSpeed {
+        /// The requested speed.
+        speed_khz: u32,
+    },
+    /// [`DebugProbe::set_scan_chain`]
+    SetScanChain {
+        /// The scan chain of the target.
+        scan_chain: Vec<ScanChainElement>,
+    },
+    /// [`DebugProbe::select_protocol`]
+    SelectProtocol {
+        /// The protocol to use.
+        protocol: WireProtocol,
+    },
+    /// [`DebugProbe::attach`]
+    Attach,
+    /// [`DebugProbe::detach`]
+    Detach,
+    /// [`DebugProbe::target_reset`]
+    TargetReset,
+    /// [`DebugProbe::target_reset_assert`]
+    TargetResetAssert,
+    /// [`DebugProbe::target_reset_deassert`]
+    TargetResetDeassert,
+    /// [`DebugProbe::get_target_voltage`]
+    TargetVoltage,
+    /// [`DebugProbe::set_target_power`]
+    SetTargetPower {
+        /// Whether the power is switched on.
+        on: bool,
+        /// The supply voltage.
+        voltage: TargetPowerVoltage,
+    },
+    /// [`DebugProbe::set_command_queuing`]
+    SetCommandQueuing {
+        /// Whether command queuing is enabled.
+        enabled: bool,
+    },
+    /// A [`RawDapAccess`] operation.
+    Dap(DapOperation),
+    /// A [`JTAGAccess`] operation.
+    Jtag {
+        /// The idle cycles after each register access.
+        idle_cycles: u8,
+        /// The operation.
+        operation: JtagOperation,
+    },
+}
+
+/// The result of a [`Call`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Outcome {
+    /// The call succeeded without returning a value.
+    Done,
+    /// The speed selected by the probe.
+    Speed(u32),
+    /// The target voltage.
+    Voltage(Option<f32>),
+    /// The values read by a DAP operation.
+    Values(Vec<u32>),
+    /// The result of a JTAG operation.
+    Jtag(JtagResult),
+    /// The call failed.
+    Error(RemoteError),
+}
+
+impl Outcome {
+    fn from_result<T, E>(result: &Result<T, E>, outcome: impl FnOnce(&T) -> Outcome) -> Self
+    where
+        for<'e> &'e E: Into<RemoteError>,
+    {
+        match result {
+            Ok(value) => outcome(value),
+            Err(error) => Outcome::Error(error.into()),
+        }
+    }
+}
+
+/// A recorded call, together with its result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transaction {
+    /// The call to the probe.
+    pub call: Call,
+    /// The result of the call.
+    pub outcome: Outcome,
+}
+
+/// Records every call to the wrapped probe, see the [module documentation](self).
+pub struct RecordingProbe {
+    probe: Box<dyn DebugProbe>,
+    writer: BufWriter<Box<dyn Write + Send>>,
+    dap: bool,
+    jtag: bool,
+    idle_cycles: u8,
+}
+
+impl std::fmt::Debug for RecordingProbe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordingProbe")
+            .field("probe", &self.probe)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RecordingProbe {
+    /// Records the calls to `probe` to the file at `path`.
+    pub fn create(probe: Box<dyn DebugProbe>, path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Self::new(probe, Box::new(File::create(path)?))
+    }
+
+    /// Records the calls to `probe` to `writer`.
+    ///
+    /// The recording should start right after the probe is opened, before it is attached.
+    pub fn new(
+        mut probe: Box<dyn DebugProbe>,
+        writer: Box<dyn Write + Send>,
+    ) -> Result<Self, ReplayError> {
+        let dap = probe.try_as_dap_probe().is_some();
+        let jtag = probe.try_as_jtag_probe().is_some();
+        let idle_cycles = probe
+            .try_as_jtag_probe()
+            .map_or(0, |jtag| jtag.idle_cycles());
+        if !dap && !jtag {
+            return Err(ReplayError::Unsupported("raw DAP or JTAG"));
+        }
+
+        let header = RecordingHeader {
+            version: RECORDING_VERSION,
+            name: probe.get_name().to_string(),
+            speed_khz: probe.speed_khz(),
+            protocol: probe.active_protocol(),
+            dap,
+            jtag,
+            idle_cycles,
+            power_voltages: probe.target_power_voltages(),
+        };
+        let mut writer = BufWriter::new(writer);
+        write_message(&mut writer, &header)?;
+
+        Ok(Self {
+            probe,
+            writer,
+            dap,
+            jtag,
+            idle_cycles,
+        })
+    }
+
+    fn record(&mut self, call: Call, outcome: Outcome) -> Result<(), ReplayError> {
+        tracing::trace!("Recording {call:?}: {outcome:?}");
+        write_message(&mut self.writer, &Transaction { call, outcome })?;
+
+        Ok(())
+    }
+
+    fn record_done(
+        &mut self,
+        call: Call,
+        result: Result<(), DebugProbeError>,
+    ) -> Result<(), DebugProbeError> {
+        self.record(call, Outcome::from_result(&result, |_| Outcome::Done))?;
+        result
+    }
+
+    fn execute_dap(&mut self, operation: DapOperation) -> Result<Vec<u32>, ArmError> {
+        let dap = self
+            .probe
+            .try_as_dap_probe()
+            .ok_or(ReplayError::Unsupported("raw DAP"))?;
+
+        let mut values = vec![];
+        let result = execute_dap_operation(dap, operation.clone(), &mut values).map(|()| values);
+        self.record(
+            Call::Dap(operation),
+            Outcome::from_result(&result, |values| Outcome::Values(values.clone())),
+        )?;
+
+        result
+    }
+
+    fn jtag(&mut self) -> Result<&mut dyn JTAGAccess, ReplayError> {
+        self.probe
+            .try_as_jtag_probe()
+            .ok_or(ReplayError::Unsupported("JTAG"))
+    }
+
+    fn jtag_call(&self, operation: JtagOperation) -> Call {
+        Call::Jtag {
+            idle_cycles: self.idle_cycles,
+            operation,
+        }
+    }
+}
+
+impl DebugProbe for RecordingProbe {
+    fn get_name(&self) -> &str {
+        self.probe.get_name()
+    }
+
+    fn speed_khz(&self) -> u32 {
+        self.probe.speed_khz()
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        let result = self.probe.set_speed(speed_khz);
+        self.record(
+            Call::SetSpeed { speed_khz },
+            Outcome::from_result(&result, |speed_khz| Outcome::Speed(*speed_khz)),
+        )?;
+
+        result
+    }
+
+    fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError> {
+        let result = self.probe.set_scan_chain(scan_chain.clone());
+        self.record_done(Call::SetScanChain { scan_chain }, result)
+    }
+
+    fn set_command_queuing(&mut self, enabled: bool) -> Result<(), DebugProbeError> {
+        let result = self.probe.set_command_queuing(enabled);
+        self.record_done(Call::SetCommandQueuing { enabled }, result)
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        let result = self.probe.attach();
+        self.record_done(Call::Attach, result)
+    }
+
+    fn detach(&mut self) -> Result<(), crate::Error> {
+        let result = self.probe.detach();
+        self.record(
+            Call::Detach,
+            Outcome::from_result(&result, |_| Outcome::Done),
+        )
+        .map_err(|error| crate::Error::Probe(error.into()))?;
+        self.writer
+            .flush()
+            .map_err(|error| crate::Error::Probe(ReplayError::from(error).into()))?;
+
+        result
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        let result = self.probe.target_reset();
+        self.record_done(Call::TargetReset, result)
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        let result = self.probe.target_reset_assert();
+        self.record_done(Call::TargetResetAssert, result)
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        let result = self.probe.target_reset_deassert();
+        self.record_done(Call::TargetResetDeassert, result)
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        let result = self.probe.select_protocol(protocol);
+        self.record_done(Call::SelectProtocol { protocol }, result)
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        self.probe.active_protocol()
+    }
+
+    fn has_arm_interface(&self) -> bool {
+        self.dap
+    }
+
+    fn try_get_arm_interface<'probe>(
+        self: Box<Self>,
+    ) -> Result<Box<dyn UninitializedArmProbe + 'probe>, (Box<dyn DebugProbe>, DebugProbeError)>
+    {
+        if !self.dap {
+            return Err((
+                self,
+                DebugProbeError::InterfaceNotAvailable {
+                    interface_name: "ARM",
+                },
+            ));
+        }
+
+        Ok(Box::new(ArmCommunicationInterface::new(self, false)))
+    }
+
+    fn has_riscv_interface(&self) -> bool {
+        self.jtag
+    }
+
+    fn try_get_riscv_interface(
+        self: Box<Self>,
+    ) -> Result<RiscvCommunicationInterface, (Box<dyn DebugProbe>, RiscvError)> {
+        if !self.jtag {
+            return Err((
+                self,
+                DebugProbeError::InterfaceNotAvailable {
+                    interface_name: "RISC-V",
+                }
+                .into(),
+            ));
+        }
+
+        let jtag_dtm = match JtagDtm::new(self) {
+            Ok(jtag_dtm) => Box::new(jtag_dtm),
+            Err((access, err)) => return Err((access.into_probe(), err)),
+        };
+        match RiscvCommunicationInterface::new(jtag_dtm) {
+            Ok(interface) => Ok(interface),
+            Err((probe, err)) => Err((probe.into_probe(), err)),
+        }
+    }
+
+    fn has_xtensa_interface(&self) -> bool {
+        self.jtag
+    }
+
+    fn try_get_xtensa_interface(
+        self: Box<Self>,
+    ) -> Result<XtensaCommunicationInterface, (Box<dyn DebugProbe>, DebugProbeError)> {
+        if !self.jtag {
+            return Err((
+                self,
+                DebugProbeError::InterfaceNotAvailable {
+                    interface_name: "Xtensa",
+                },
+            ));
+        }
+
+        match XtensaCommunicationInterface::new(self) {
+            Ok(interface) => Ok(interface),
+            Err((probe, err)) => Err((probe.into_probe(), err)),
+        }
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn try_as_dap_probe(&mut self) -> Option<&mut dyn DapProbe> {
+        if self.dap {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        if self.jtag {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
+        let result = self.probe.get_target_voltage();
+        self.record(
+            Call::TargetVoltage,
+            Outcome::from_result(&result, |volts| Outcome::Voltage(*volts)),
+        )?;
+
+        result
+    }
+
+    fn target_power_voltages(&self) -> Vec<TargetPowerVoltage> {
+        self.probe.target_power_voltages()
+    }
+
+    fn set_target_power(
+        &mut self,
+        on: bool,
+        voltage: TargetPowerVoltage,
+    ) -> Result<(), DebugProbeError> {
+        let result = self.probe.set_target_power(on, voltage);
+        self.record_done(Call::SetTargetPower { on, voltage }, result)
+    }
+
+    fn get_uart_interface(&self) -> Option<&dyn UartAccess> {
+        self.probe.get_uart_interface()
+    }
+
+    fn get_uart_interface_mut(&mut self) -> Option<&mut dyn UartAccess> {
+        self.probe.get_uart_interface_mut()
+    }
+}
+
+impl RawDapAccess for RecordingProbe {
+    fn raw_read_register(&mut self, port: PortType, address: u8) -> Result<u32, ArmError> {
+        let values = self.execute_dap(DapOperation::Read { port, address })?;
+
+        Ok(values[0])
+    }
+
+    fn raw_read_block(
+        &mut self,
+        port: PortType,
+        address: u8,
+        values: &mut [u32],
+    ) -> Result<(), ArmError> {
+        let block = self.execute_dap(DapOperation::ReadBlock {
+            port,
+            address,
+            count: values.len() as u32,
+        })?;
+        values.copy_from_slice(&block);
+
+        Ok(())
+    }
+
+    fn raw_write_register(
+        &mut self,
+        port: PortType,
+        address: u8,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        self.execute_dap(DapOperation::Write {
+            port,
+            address,
+            value,
+        })?;
+
+        Ok(())
+    }
+
+    fn raw_write_block(
+        &mut self,
+        port: PortType,
+        address: u8,
+        values: &[u32],
+    ) -> Result<(), ArmError> {
+        self.execute_dap(DapOperation::WriteBlock {
+            port,
+            address,
+            values: values.to_vec(),
+        })?;
+
+        Ok(())
+    }
+
+    fn raw_flush(&mut self) -> Result<(), ArmError> {
+        self.execute_dap(DapOperation::Flush)?;
+
+        Ok(())
+    }
+
+    fn configure_jtag(&mut self) -> Result<(), DebugProbeError> {
+        self.execute_dap(DapOperation::ConfigureJtag)
+            .map_err(dap_to_probe_error)?;
+
+        Ok(())
+    }
+
+    fn jtag_sequence(&mut self, cycles: u8, tms: bool, tdi: u64) -> Result<(), DebugProbeError> {
+        self.execute_dap(DapOperation::JtagSequence { cycles, tms, tdi })
+            .map_err(dap_to_probe_error)?;
+
+        Ok(())
+    }
+
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), DebugProbeError> {
+        self.execute_dap(DapOperation::SwjSequence { bit_len, bits })
+            .map_err(dap_to_probe_error)?;
+
+        Ok(())
+    }
+
+    fn swj_pins(
+        &mut self,
+        pin_out: u32,
+        pin_select: u32,
+        pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        let values = self
+            .execute_dap(DapOperation::SwjPins {
+                pin_out,
+                pin_select,
+                pin_wait,
+            })
+            .map_err(dap_to_probe_error)?;
+
+        Ok(values[0])
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn core_status_notification(&mut self, state: CoreStatus) -> Result<(), DebugProbeError> {
+        // Only used for status LEDs, so it isn't recorded.
+        match self.probe.try_as_dap_probe() {
+            Some(dap) => dap.core_status_notification(state),
+            None => Ok(()),
+        }
+    }
+}
+
+impl DapProbe for RecordingProbe {}
+
+impl JTAGAccess for RecordingProbe {
+    fn scan_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        let call = self.jtag_call(JtagOperation::ScanChain);
+        let result = self.jtag()?.scan_chain();
+        self.record(
+            call,
+            Outcome::from_result(&result, |chain| {
+                Outcome::Jtag(JtagResult::ScanChain(
+                    chain.iter().map(RemoteJtagTap::from).collect(),
+                ))
+            }),
+        )?;
+
+        result
+    }
+
+    fn set_idle_cycles(&mut self, idle_cycles: u8) {
+        self.idle_cycles = idle_cycles;
+        if let Some(jtag) = self.probe.try_as_jtag_probe() {
+            jtag.set_idle_cycles(idle_cycles);
+        }
+    }
+
+    fn idle_cycles(&self) -> u8 {
+        self.idle_cycles
+    }
+
+    fn write_register(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        len: u32,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        let call = self.jtag_call(JtagOperation::WriteRegister {
+            address,
+            data: data.to_vec(),
+            len,
+        });
+        let result = self.jtag()?.write_register(address, data, len);
+        self.record(
+            call,
+            Outcome::from_result(&result, |data| {
+                Outcome::Jtag(JtagResult::Data(data.clone()))
+            }),
+        )?;
+
+        result
+    }
+
+    fn write_register_batch(
+        &mut self,
+        writes: &JtagCommandQueue,
+    ) -> Result<DeferredResultSet, BatchExecutionError> {
+        let to_batch_error = |error: ReplayError| {
+            BatchExecutionError::new(crate::Error::Probe(error.into()), DeferredResultSet::new())
+        };
+
+        // The writes are executed without their transformations, so the raw data can be recorded.
+        let mut raw_writes = JtagCommandQueue::new();
+        let mut pending = vec![];
+        for (index, write) in writes.iter() {
+            let raw_index = raw_writes.schedule(raw_write_command(
+                write.address,
+                write.data.clone(),
+                write.len,
+            ));
+            let call = self.jtag_call(JtagOperation::WriteRegister {
+                address: write.address,
+                data: write.data.clone(),
+                len: write.len,
+            });
+            pending.push((index, write.transform, raw_index, call));
+        }
+
+        let (mut raw_results, error) = match self
+            .jtag()
+            .map_err(to_batch_error)?
+            .write_register_batch(&raw_writes)
+        {
+            Ok(results) => (results, None),
+            Err(BatchExecutionError { error, results }) => (results, Some(error)),
+        };
+
+        let mut results = DeferredResultSet::with_capacity(pending.len());
+        for (index, transform, raw_index, call) in pending {
+            let Ok(CommandResult::VecU8(data)) = raw_results.take(raw_index) else {
+                // The batch failed at this write.
+                if let Some(error) = &error {
+                    self.record(call, Outcome::Error(error.into()))
+                        .map_err(to_batch_error)?;
+                }
+                break;
+            };
+
+            self.record(call, Outcome::Jtag(JtagResult::Data(data.clone())))
+                .map_err(to_batch_error)?;
+            match transform(data) {
+                Ok(result) => results.push(index, result),
+                Err(error) => return Err(BatchExecutionError::new(error, results)),
+            }
+        }
+
+        match error {
+            Some(error) => Err(BatchExecutionError::new(error, results)),
+            None => Ok(results),
+        }
+    }
+}
+
+fn dap_to_probe_error(error: ArmError) -> DebugProbeError {
+    match error {
+        ArmError::Probe(error) => error,
+        error => DebugProbeError::Other(error.into()),
+    }
+}
+
+/// Replays a recording made by a [`RecordingProbe`], see the [module documentation](self).
+#[derive(Debug)]
+pub struct ReplayProbe {
+    header: RecordingHeader,
+    transactions: Vec<Transaction>,
+    /// The index of the next transaction.
+    position: usize,
+    protocol: Option<WireProtocol>,
+    speed_khz: u32,
+    idle_cycles: u8,
+}
+
+impl ReplayProbe {
+    /// Loads the recording at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Loads a recording from `reader`.
+    pub fn from_reader(mut reader: impl Read) -> Result<Self, ReplayError> {
+        let header: RecordingHeader = read_message(&mut reader)?
+            .ok_or_else(|| ReplayError::InvalidRecording("The recording is empty".into()))?;
+        if header.version != RECORDING_VERSION {
+            return Err(ReplayError::InvalidRecording(format!(
+                "Version {} is not supported",
+                header.version
+            )));
+        }
+
+        let mut transactions = vec![];
+        while let Some(transaction) = read_message(&mut reader)? {
+            transactions.push(transaction);
+        }
+
+        Ok(Self {
+            protocol: header.protocol,
+            speed_khz: header.speed_khz,
+            idle_cycles: header.idle_cycles,
+            header,
+            transactions,
+            position: 0,
+        })
+    }
+
+    /// The number of recorded calls which were not replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.transactions.len() - self.position
+    }
+
+    /// Returns the recorded outcome of `call`, and advances to the next call.
+    ///
+    /// A call which differs from the recording is reported as error, without advancing, so every
+    /// further call fails as well.
+    fn replay(&mut self, call: Call) -> Result<Outcome, ReplayError> {
+        let index = self.position;
+        let Some(transaction) = self.transactions.get(index) else {
+            return Err(ReplayError::Finished {
+                index,
+                actual: format!("{call:?}"),
+            });
+        };
+
+        if transaction.call != call {
+            return Err(ReplayError::Diverged {
+                index,
+                expected: format!("{:?}", transaction.call),
+                actual: format!("{call:?}"),
+            });
+        }
+
+        tracing::trace!("Replaying {call:?}: {:?}", transaction.outcome);
+        self.position += 1;
+        Ok(transaction.outcome.clone())
+    }
+
+    /// Replays `call`, and extracts the result with `value`.
+    fn replay_with<T, E>(
+        &mut self,
+        call: Call,
+        value: impl FnOnce(Outcome) -> Option<T>,
+    ) -> Result<T, E>
+    where
+        E: From<ReplayError> + From<RemoteError>,
+    {
+        match self.replay(call)? {
+            Outcome::Error(error) => Err(error.into()),
+            outcome => {
+                let description = format!("{outcome:?}");
+                value(outcome).ok_or_else(|| {
+                    ReplayError::InvalidRecording(format!("Unexpected outcome {description}"))
+                        .into()
+                })
+            }
+        }
+    }
+
+    fn replay_done<E>(&mut self, call: Call) -> Result<(), E>
+    where
+        E: From<ReplayError> + From<RemoteError>,
+    {
+        self.replay_with(call, |outcome| match outcome {
+            Outcome::Done => Some(()),
+            _ => None,
+        })
+    }
+
+    fn replay_dap(&mut self, operation: DapOperation) -> Result<Vec<u32>, ArmError> {
+        self.replay_with(Call::Dap(operation), |outcome| match outcome {
+            Outcome::Values(values) => Some(values),
+            _ => None,
+        })
+    }
+
+    fn replay_jtag_write(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        len: u32,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        let call = Call::Jtag {
+            idle_cycles: self.idle_cycles,
+            operation: JtagOperation::WriteRegister {
+                address,
+                data: data.to_vec(),
+                len,
+            },
+        };
+
+        self.replay_with(call, |outcome| match outcome {
+            Outcome::Jtag(JtagResult::Data(data)) => Some(data),
+            _ => None,
+        })
+    }
+}
+
+impl DebugProbe for ReplayProbe {
+    fn get_name(&self) -> &str {
+        &self.header.name
+    }
+
+    fn speed_khz(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        self.speed_khz =
+            self.replay_with::<_, DebugProbeError>(Call::SetSpeed { speed_khz }, |outcome| {
+                match outcome {
+                    Outcome::Speed(speed_khz) => Some(speed_khz),
+                    _ => None,
+                }
+            })?;
+
+        Ok(self.speed_khz)
+    }
+
+    fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError> {
+        self.replay_done(Call::SetScanChain { scan_chain })
+    }
+
+    fn set_command_queuing(&mut self, enabled: bool) -> Result<(), DebugProbeError> {
+        self.replay_done(Call::SetCommandQueuing { enabled })
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        self.replay_done(Call::Attach)
+    }
+
+    fn detach(&mut self) -> Result<(), crate::Error> {
+        self.replay_done::<DebugProbeError>(Call::Detach)?;
+
+        Ok(())
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.replay_done(Call::TargetReset)
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.replay_done(Call::TargetResetAssert)
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.replay_done(Call::TargetResetDeassert)
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        self.replay_done::<DebugProbeError>(Call::SelectProtocol { protocol })?;
+        self.protocol = Some(protocol);
+
+        Ok(())
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        self.protocol
+    }
+
+    fn has_arm_interface(&self) -> bool {
+        self.header.dap
+    }
+
+    fn try_get_arm_interface<'probe>(
+        self: Box<Self>,
+    ) -> Result<Box<dyn UninitializedArmProbe + 'probe>, (Box<dyn DebugProbe>, DebugProbeError)>
+    {
+        if !self.header.dap {
+            return Err((
+                self,
+                DebugProbeError::InterfaceNotAvailable {
+                    interface_name: "ARM",
+                },
+            ));
+        }
+
+        Ok(Box::new(ArmCommunicationInterface::new(self, false)))
+    }
+
+    fn has_riscv_interface(&self) -> bool {
+        self.header.jtag
+    }
+
+    fn try_get_riscv_interface(
+        self: Box<Self>,
+    ) -> Result<RiscvCommunicationInterface, (Box<dyn DebugProbe>, RiscvError)> {
+        if !self.header.jtag {
+            return Err((
+                self,
+                DebugProbeError::InterfaceNotAvailable {
+                    interface_name: "RISC-V",
+                }
+                .into(),
+            ));
+        }
+
+        let jtag_dtm = match JtagDtm::new(self) {
+            Ok(jtag_dtm) => Box::new(jtag_dtm),
+            Err((access, err)) => return Err((access.into_probe(), err)),
+        };
+        match RiscvCommunicationInterface::new(jtag_dtm) {
+            Ok(interface) => Ok(interface),
+            Err((probe, err)) => Err((probe.into_probe(), err)),
+        }
+    }
+
+    fn has_xtensa_interface(&self) -> bool {
+        self.header.jtag
+    }
+
+    fn try_get_xtensa_interface(
+        self: Box<Self>,
+    ) -> Result<XtensaCommunicationInterface, (Box<dyn DebugProbe>, DebugProbeError)> {
+        if !self.header.jtag {
+            return Err((
+                self,
+                DebugProbeError::InterfaceNotAvailable {
+                    interface_name: "Xtensa",
+                },
+            ));
+        }
+
+        match XtensaCommunicationInterface::new(self) {
+            Ok(interface) => Ok(interface),
+            Err((probe, err)) => Err((probe.into_probe(), err)),
+        }
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn try_as_dap_probe(&mut self) -> Option<&mut dyn DapProbe> {
+        if self.header.dap {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        if self.header.jtag {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
+        self.replay_with(Call::TargetVoltage, |outcome| match outcome {
+            Outcome::Voltage(volts) => Some(volts),
+            _ => None,
+        })
+    }
+
+    fn target_power_voltages(&self) -> Vec<TargetPowerVoltage> {
+        self.header.power_voltages.clone()
+    }
+
+    fn set_target_power(
+        &mut self,
+        on: bool,
+        voltage: TargetPowerVoltage,
+    ) -> Result<(), DebugProbeError> {
+        self.replay_done(Call::SetTargetPower { on, voltage })
+    }
+}
+
+impl RawDapAccess for ReplayProbe {
+    fn raw_read_register(&mut self, port: PortType, address: u8) -> Result<u32, ArmError> {
+        let values = self.replay_dap(DapOperation::Read { port, address })?;
+
+        values.first().copied().ok_or_else(|| {
+            ReplayError::InvalidRecording("A register read returned no value".into()).into()
+        })
+    }
+
+    fn raw_read_block(
+        &mut self,
+        port: PortType,
+        address: u8,
+        values: &mut [u32],
+    ) -> Result<(), ArmError> {
+        let block = self.replay_dap(DapOperation::ReadBlock {
+            port,
+            address,
+            count: values.len() as u32,
+        })?;
+
+        if block.len() != values.len() {
+            return Err(ReplayError::InvalidRecording(format!(
+                "A block read returned {} values instead of {}",
+                block.len(),
+                values.len()
+            ))
+            .into());
+        }
+        values.copy_from_slice(&block);
+
+        Ok(())
+    }
+
+    fn raw_write_register(
+        &mut self,
+        port: PortType,
+        address: u8,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        self.replay_dap(DapOperation::Write {
+            port,
+            address,
+            value,
+        })?;
+
+        Ok(())
+    }
+
+    fn raw_write_block(
+        &mut self,
+        port: PortType,
+        address: u8,
+        values: &[u32],
+    ) -> Result<(), ArmError> {
+        self.replay_dap(DapOperation::WriteBlock {
+            port,
+            address,
+            values: values.to_vec(),
+        })?;
+
+        Ok(())
+    }
+
+    fn raw_flush(&mut self) -> Result<(), ArmError> {
+        self.replay_dap(DapOperation::Flush)?;
+
+        Ok(())
+    }
+
+    fn configure_jtag(&mut self) -> Result<(), DebugProbeError> {
+        self.replay_dap(DapOperation::ConfigureJtag)
+            .map_err(dap_to_probe_error)?;
+
+        Ok(())
+    }
+
+    fn jtag_sequence(&mut self, cycles: u8, tms: bool, tdi: u64) -> Result<(), DebugProbeError> {
+        self.replay_dap(DapOperation::JtagSequence { cycles, tms, tdi })
+            .map_err(dap_to_probe_error)?;
+
+        Ok(())
+    }
+
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), DebugProbeError> {
+        self.replay_dap(DapOperation::SwjSequence { bit_len, bits })
+            .map_err(dap_to_probe_error)?;
+
+        Ok(())
+    }
+
+    fn swj_pins(
+        &mut self,
+        pin_out: u32,
+        pin_select: u32,
+        pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        let values = self
+            .replay_dap(DapOperation::SwjPins {
+                pin_out,
+                pin_select,
+                pin_wait,
+            })
+            .map_err(dap_to_probe_error)?;
+
+        values.first().copied().ok_or_else(|| {
+            ReplayError::InvalidRecording("Reading the pins returned no value".into()).into()
+        })
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn core_status_notification(&mut self, _: CoreStatus) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+}
+
+impl DapProbe for ReplayProbe {}
+
+impl JTAGAccess for ReplayProbe {
+    fn scan_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        let call = Call::Jtag {
+            idle_cycles: self.idle_cycles,
+            operation: JtagOperation::ScanChain,
+        };
+
+        self.replay_with(call, |outcome| match outcome {
+            Outcome::Jtag(JtagResult::ScanChain(chain)) => {
+                Some(chain.into_iter().map(JtagChainItem::from).collect())
+            }
+            _ => None,
+        })
+    }
+
+    fn set_idle_cycles(&mut self, idle_cycles: u8) {
+        self.idle_cycles = idle_cycles;
+    }
+
+    fn idle_cycles(&self) -> u8 {
+        self.idle_cycles
+    }
+
+    fn write_register(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        len: u32,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        self.replay_jtag_write(address, data, len)
+    }
+
+    fn write_register_batch(
+        &mut self,
+        writes: &JtagCommandQueue,
+    ) -> Result<DeferredResultSet, BatchExecutionError> {
+        let mut results = DeferredResultSet::with_capacity(writes.len());
+
+        for (index, write) in writes.iter() {
+            let result = self
+                .replay_jtag_write(write.address, &write.data, write.len)
+                .map_err(crate::Error::Probe)
+                .and_then(write.transform);
+
+            match result {
+                Ok(result) => results.push(index, result),
+                Err(error) => return Err(BatchExecutionError::new(error, results)),
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::architecture::arm::DapError;
+    use crate::probe::fake_probe::FakeProbe;
+
+    /// A writer whose data can be read after the recording probe was dropped.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(data)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A fake target whose registers read back the last value written to them.
+    fn fake_target() -> Box<dyn DebugProbe> {
+        let writes = Arc::new(Mutex::new(Vec::<(PortType, u8, u32)>::new()));
+
+        let mut probe = FakeProbe::new();
+        let read_writes = writes.clone();
+        probe.set_dap_register_read_handler(Box::new(move |port, address| match (port, address) {
+            (PortType::AccessPort, 0xC) => Err(ArmError::Dap(DapError::FaultResponse)),
+            _ => Ok(read_writes
+                .lock()
+                .unwrap()
+                .iter()
+                .rev()
+                .find(|write| (write.0, write.1) == (port, address))
+                .map_or(0, |write| write.2)),
+        }));
+        probe.set_dap_register_write_handler(Box::new(move |port, address, value| {
+            writes.lock().unwrap().push((port, address, value));
+            Ok(())
+        }));
+
+        Box::new(probe)
+    }
+
+    /// The session which is recorded and replayed.
+    fn session(probe: &mut dyn DebugProbe) -> Vec<Result<u32, String>> {
+        let mut results = vec![];
+
+        probe.select_protocol(WireProtocol::Swd).unwrap();
+        probe.attach().unwrap();
+
+        let dap = probe.try_as_dap_probe().unwrap();
+        dap.raw_write_register(PortType::DebugPort, 0x8, 0x1234)
+            .unwrap();
+        dap.raw_write_block(PortType::AccessPort, 0x4, &[1, 2])
+            .unwrap();
+        for (port, address) in [
+            (PortType::DebugPort, 0x8),
+            (PortType::AccessPort, 0x4),
+            (PortType::AccessPort, 0xC),
+        ] {
+            results.push(
+                dap.raw_read_register(port, address)
+                    .map_err(|error| error.to_string()),
+            );
+        }
+
+        results
+    }
+
+    fn record() -> Vec<u8> {
+        let buffer = SharedBuffer::default();
+        let mut probe = RecordingProbe::new(fake_target(), Box::new(buffer.clone())).unwrap();
+        assert_eq!(
+            session(&mut probe),
+            [
+                Ok(0x1234),
+                Ok(2),
+                Err(ArmError::Dap(DapError::FaultResponse).to_string())
+            ]
+        );
+        probe.detach().unwrap();
+        drop(probe);
+
+        let recording = buffer.0.lock().unwrap().clone();
+        recording
+    }
+
+    #[test]
+    fn replay_recorded_session() {
+        let recording = record();
+
+        let mut replay = ReplayProbe::from_reader(recording.as_slice()).unwrap();
+        assert_eq!(replay.get_name(), "Mock probe for testing");
+        assert_eq!(replay.remaining(), 8);
+
+        assert_eq!(
+            session(&mut replay),
+            [
+                Ok(0x1234),
+                Ok(2),
+                Err(ArmError::Dap(DapError::FaultResponse).to_string())
+            ]
+        );
+
+        replay.detach().unwrap();
+        assert_eq!(replay.remaining(), 0);
+
+        // Calls after the end of the recording fail.
+        assert!(matches!(
+            replay.attach(),
+            Err(DebugProbeError::ProbeSpecific(_))
+        ));
+    }
+
+    #[test]
+    fn replay_fails_on_divergence() {
+        let recording = record();
+
+        let mut replay = ReplayProbe::from_reader(recording.as_slice()).unwrap();
+        replay.select_protocol(WireProtocol::Swd).unwrap();
+        replay.attach().unwrap();
+
+        let error = replay
+            .raw_write_register(PortType::DebugPort, 0x8, 0x4321)
+            .unwrap_err();
+        let ArmError::Probe(DebugProbeError::ProbeSpecific(error)) = error else {
+            panic!("Unexpected error {error:?}");
+        };
+        assert_eq!(
+            error.to_string(),
+            "Call 2 differs from the recording: \
+             expected Dap(Write { port: DebugPort, address: 8, value: 4660 }), \
+             but got Dap(Write { port: DebugPort, address: 8, value: 17185 })."
+        );
+        assert_eq!(replay.remaining(), 6);
+    }
+
+    #[test]
+    fn replay_target_power() {
+        let buffer = SharedBuffer::default();
+        let mut probe = RecordingProbe::new(fake_target(), Box::new(buffer.clone())).unwrap();
+        assert!(probe.target_power_voltages().is_empty());
+        let recorded = probe.set_target_power(true, TargetPowerVoltage::V3_3);
+        assert!(recorded.is_err());
+        drop(probe);
+
+        let recording = buffer.0.lock().unwrap().clone();
+        let mut replay = ReplayProbe::from_reader(recording.as_slice()).unwrap();
+        assert!(replay.target_power_voltages().is_empty());
+        assert!(replay
+            .set_target_power(true, TargetPowerVoltage::V3_3)
+            .is_err());
+        assert_eq!(replay.remaining(), 0);
+    }
+
+    #[test]
+    fn replay_command_queuing() {
+        let buffer = SharedBuffer::default();
+        let mut probe = RecordingProbe::new(fake_target(), Box::new(buffer.clone())).unwrap();
+        let recorded = probe.set_command_queuing(false);
+        assert!(matches!(
+            recorded,
+            Err(DebugProbeError::CommandNotSupportedByProbe { .. })
+        ));
+        drop(probe);
+
+        let recording = buffer.0.lock().unwrap().clone();
+        let mut replay = ReplayProbe::from_reader(recording.as_slice()).unwrap();
+        assert!(replay.set_command_queuing(false).is_err());
+        assert_eq!(replay.remaining(), 0);
+    }
+
+    #[test]
+    fn reject_invalid_recording() {
+        assert!(matches!(
+            ReplayProbe::from_reader([].as_slice()),
+            Err(ReplayError::InvalidRecording(_))
+        ));
+
+        let mut recording = vec![];
+        write_message(
+            &mut recording,
+            &RecordingHeader {
+                version: RECORDING_VERSION + 1,
+                name: "Probe".into(),
+                speed_khz: 1000,
+                protocol: None,
+                dap: true,
+                jtag: false,
+                idle_cycles: 0,
+                power_voltages: vec![],
+            },
+        )
+        .unwrap();
+        assert!(matches!(
+            ReplayProbe::from_reader(recording.as_slice()),
+            Err(ReplayError::InvalidRecording(_))
+        ));
+    }
+}
@@ -0,0 +1,237 @@
+//! The JTAG-VPI protocol of OpenOCD.
+//!
+//! Every command is a `struct vpi_cmd` of OpenOCD, which is sent as is, with little endian
+//! integers. TMS sequences are sent with [`CMD_TMS_SEQ`]. Scans keep TMS low, or raise it for the
+//! last bit with [`CMD_SCAN_CHAIN_FLIP_TMS`], and are answered with the same command, with the
+//! bits read from TDO in `buffer_in`.
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+use bitvec::prelude::*;
+
+use super::SimulatorError;
+
+pub(super) const CMD_TMS_SEQ: u32 = 1;
+pub(super) const CMD_SCAN_CHAIN: u32 = 2;
+pub(super) const CMD_SCAN_CHAIN_FLIP_TMS: u32 = 3;
+
+/// The size of the buffers of a command.
+const XFERT_MAX_SIZE: usize = 512;
+
+/// The number of bits in a command.
+const MAX_BITS: usize = XFERT_MAX_SIZE * 8;
+
+/// The size of an encoded command.
+pub(super) const MESSAGE_SIZE: usize = 4 + 2 * XFERT_MAX_SIZE + 4 + 4;
+
+/// A `struct vpi_cmd`.
+#[derive(Clone)]
+pub(super) struct Command {
+    pub cmd: u32,
+    pub buffer_out: [u8; XFERT_MAX_SIZE],
+    pub buffer_in: [u8; XFERT_MAX_SIZE],
+    pub length: u32,
+    pub nb_bits: u32,
+}
+
+impl Command {
+    fn new(cmd: u32, bits: &BitSlice<u8, Lsb0>) -> Self {
+        let mut buffer_out = [0; XFERT_MAX_SIZE];
+        buffer_out.view_bits_mut::<Lsb0>()[..bits.len()].copy_from_bitslice(bits);
+
+        Self {
+            cmd,
+            buffer_out,
+            buffer_in: [0; XFERT_MAX_SIZE],
+            length: bits.len().div_ceil(8) as u32,
+            nb_bits: bits.len() as u32,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut message = Vec::with_capacity(MESSAGE_SIZE);
+        message.extend_from_slice(&self.cmd.to_le_bytes());
+        message.extend_from_slice(&self.buffer_out);
+        message.extend_from_slice(&self.buffer_in);
+        message.extend_from_slice(&self.length.to_le_bytes());
+        message.extend_from_slice(&self.nb_bits.to_le_bytes());
+        message
+    }
+
+    pub fn decode(message: &[u8; MESSAGE_SIZE]) -> Self {
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(message[offset..offset + 4].try_into().unwrap());
+        let buffer_at = |offset: usize| -> [u8; XFERT_MAX_SIZE] {
+            message[offset..offset + XFERT_MAX_SIZE].try_into().unwrap()
+        };
+
+        Self {
+            cmd: u32_at(0),
+            buffer_out: buffer_at(4),
+            buffer_in: buffer_at(4 + XFERT_MAX_SIZE),
+            length: u32_at(4 + 2 * XFERT_MAX_SIZE),
+            nb_bits: u32_at(8 + 2 * XFERT_MAX_SIZE),
+        }
+    }
+}
+
+/// A JTAG clock cycle which was not sent yet.
+#[derive(Debug, Clone, Copy)]
+struct Bit {
+    tms: bool,
+    tdi: bool,
+    capture: bool,
+}
+
+#[derive(Debug)]
+pub(super) struct JtagVpi {
+    stream: TcpStream,
+    bits: Vec<Bit>,
+    captured: BitVec<u8, Lsb0>,
+}
+
+impl JtagVpi {
+    pub fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            bits: vec![],
+            captured: BitVec::new(),
+        }
+    }
+
+    pub fn shift_bit(&mut self, tms: bool, tdi: bool, capture: bool) -> Result<(), SimulatorError> {
+        self.bits.push(Bit { tms, tdi, capture });
+
+        Ok(())
+    }
+
+    pub fn read_captured_bits(&mut self) -> Result<BitVec<u8, Lsb0>, SimulatorError> {
+        self.flush()?;
+
+        Ok(std::mem::take(&mut self.captured))
+    }
+
+    /// Sends the buffered bits as scans and TMS sequences.
+    ///
+    /// Consecutive bits with TMS low are sent as scan, which also includes a following bit with
+    /// TMS high. The remaining bits with TMS high don't shift a register, so they are sent as TMS
+    /// sequence without TDI.
+    fn flush(&mut self) -> Result<(), SimulatorError> {
+        let bits = std::mem::take(&mut self.bits);
+
+        let mut rest = bits.as_slice();
+        while !rest.is_empty() {
+            let scan_len = rest.iter().take_while(|bit| !bit.tms).count();
+
+            if scan_len == 0 {
+                let tms_len = rest.iter().take_while(|bit| bit.tms).count().min(MAX_BITS);
+                let tms = rest[..tms_len]
+                    .iter()
+                    .map(|bit| bit.tms)
+                    .collect::<BitVec<u8>>();
+                self.send(&Command::new(CMD_TMS_SEQ, &tms))?;
+                rest = &rest[tms_len..];
+                continue;
+            }
+
+            let (len, cmd) = if scan_len >= MAX_BITS {
+                // One bit with TMS low is left, so a following bit with TMS high
+                // still fits into a scan which flips TMS, and its TDO is captured.
+                (MAX_BITS - 1, CMD_SCAN_CHAIN)
+            } else if scan_len < rest.len() {
+                (scan_len + 1, CMD_SCAN_CHAIN_FLIP_TMS)
+            } else {
+                (scan_len, CMD_SCAN_CHAIN)
+            };
+
+            let (scan, remaining) = rest.split_at(len);
+            let tdi = scan.iter().map(|bit| bit.tdi).collect::<BitVec<u8>>();
+            self.send(&Command::new(cmd, &tdi))?;
+
+            let response = self.receive()?;
+            let tdo = response.buffer_in.view_bits::<Lsb0>();
+            for (bit, tdo) in scan.iter().zip(tdo.iter()) {
+                if bit.capture {
+                    self.captured.push(*tdo);
+                }
+            }
+
+            rest = remaining;
+        }
+
+        Ok(())
+    }
+
+    fn send(&mut self, command: &Command) -> Result<(), SimulatorError> {
+        tracing::trace!(
+            "Sending command {} with {} bits",
+            command.cmd,
+            command.nb_bits
+        );
+        self.stream.write_all(&command.encode())?;
+
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<Command, SimulatorError> {
+        let mut message = [0; MESSAGE_SIZE];
+        self.stream.read_exact(&mut message)?;
+
+        Ok(Command::decode(&message))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    /// Answers every scan with TDO looped back from TDI, until the client disconnects.
+    ///
+    /// Returns the command and number of bits of every command received.
+    fn serve_loopback(listener: TcpListener) -> Vec<(u32, u32)> {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut commands = vec![];
+
+        let mut message = [0; MESSAGE_SIZE];
+        while stream.read_exact(&mut message).is_ok() {
+            let mut command = Command::decode(&message);
+            commands.push((command.cmd, command.nb_bits));
+
+            if command.cmd != CMD_TMS_SEQ {
+                command.buffer_in = command.buffer_out;
+                stream.write_all(&command.encode()).unwrap();
+            }
+        }
+
+        commands
+    }
+
+    #[test]
+    fn full_scan_followed_by_tms_high() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || serve_loopback(listener));
+
+        let mut vpi = JtagVpi::new(TcpStream::connect(address).unwrap());
+        let tdi = (0..=MAX_BITS).map(|n| n % 3 == 0).collect::<Vec<_>>();
+        for (n, &tdi) in tdi.iter().enumerate() {
+            // The last bit leaves the shift state, and is captured as well.
+            vpi.shift_bit(n == MAX_BITS, tdi, true).unwrap();
+        }
+
+        let captured = vpi.read_captured_bits().unwrap();
+        assert_eq!(captured.iter().by_vals().collect::<Vec<_>>(), tdi);
+
+        drop(vpi);
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                (CMD_SCAN_CHAIN, MAX_BITS as u32 - 1),
+                (CMD_SCAN_CHAIN_FLIP_TMS, 2)
+            ]
+        );
+    }
+}
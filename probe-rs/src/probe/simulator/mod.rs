@@ -0,0 +1,597 @@
+//! Probes for simulated targets, e.g. RTL simulations in Verilator or instruction set simulators
+//! like Spike.
+//!
+//! Simulators usually expose the JTAG port of the simulated target with one of the socket
+//! protocols of OpenOCD:
+//!
+//! - [`remote_bitbang`](https://github.com/openocd-org/openocd/blob/master/doc/manual/jtag/drivers/remote_bitbang.txt),
+//!   which sends the state of the JTAG pins as single characters. It is selected with
+//!   `--probe remote_bitbang://HOST:PORT`.
+//! - JTAG-VPI, which sends TMS sequences and scans as fixed size messages. It is selected with
+//!   `--probe jtag_vpi://HOST:PORT`.
+//!
+//! The [`SimulatorProbe`] implements [`JTAGAccess`] on top of them, so
+//! the RISC-V, Xtensa and ARM JTAG-DP interfaces work against the simulated target.
+
+use std::net::TcpStream;
+
+use bitvec::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::architecture::arm::communication_interface::{DapProbe, UninitializedArmProbe};
+use crate::architecture::arm::ArmCommunicationInterface;
+use crate::architecture::riscv::communication_interface::{
+    RiscvCommunicationInterface, RiscvError,
+};
+use crate::architecture::riscv::dtm::jtag_dtm::JtagDtm;
+use crate::architecture::xtensa::communication_interface::XtensaCommunicationInterface;
+use crate::probe::arm_debug_interface::{ProbeStatistics, RawProtocolIo, SwdSettings};
+use crate::probe::common::{JtagDriverState, RawJtagIo};
+use crate::probe::{
+    DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector, JTAGAccess,
+    ProbeCreationError, ProbeFactory, ScanChainElement, WireProtocol,
+};
+
+mod jtag_vpi;
+mod remote_bitbang;
+
+use jtag_vpi::JtagVpi;
+use remote_bitbang::RemoteBitbang;
+
+/// The socket protocol spoken by a simulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, docsplay::Display)]
+pub enum SimulatorProtocol {
+    /// remote_bitbang
+    RemoteBitbang,
+    /// jtag_vpi
+    JtagVpi,
+}
+
+impl SimulatorProtocol {
+    /// The URL scheme used to select a simulator with this protocol, e.g. `remote_bitbang://`.
+    pub(crate) fn from_url(url: &str) -> Option<(Self, &str)> {
+        [Self::RemoteBitbang, Self::JtagVpi]
+            .into_iter()
+            .find_map(|protocol| {
+                url.strip_prefix(&protocol.to_string())
+                    .and_then(|rest| rest.strip_prefix("://"))
+                    .map(|address| (protocol, address))
+            })
+    }
+}
+
+/// Selects a simulator by its protocol and address, see [`DebugProbeSelector::simulator`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimulatorSelector {
+    /// The protocol of the simulator.
+    pub protocol: SimulatorProtocol,
+    /// The address of the simulator, e.g. `localhost:44853`.
+    pub address: String,
+}
+
+impl std::fmt::Display for SimulatorSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}://{}", self.protocol, self.address)
+    }
+}
+
+/// An error in the communication with a simulator.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum SimulatorError {
+    /// The connection to the simulator failed.
+    Io(#[from] std::io::Error),
+
+    /// The simulator sent an invalid response: {0:#04x}.
+    InvalidResponse(u8),
+
+    /// The simulator does not support {0}.
+    Unsupported(&'static str),
+}
+
+impl From<SimulatorError> for DebugProbeError {
+    fn from(e: SimulatorError) -> Self {
+        DebugProbeError::ProbeSpecific(Box::new(e))
+    }
+}
+
+/// A factory for creating [`SimulatorProbe`] instances.
+///
+/// Simulators can't be listed, they are only opened by selectors with
+/// [`DebugProbeSelector::simulator`] set.
+#[derive(Debug)]
+pub struct SimulatorProbeFactory;
+
+impl std::fmt::Display for SimulatorProbeFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Simulator")
+    }
+}
+
+impl ProbeFactory for SimulatorProbeFactory {
+    fn open(&self, selector: &DebugProbeSelector) -> Result<Box<dyn DebugProbe>, DebugProbeError> {
+        let Some(simulator) = &selector.simulator else {
+            return Err(DebugProbeError::ProbeCouldNotBeCreated(
+                ProbeCreationError::NotFound,
+            ));
+        };
+
+        let probe = SimulatorProbe::connect(simulator)?;
+        tracing::debug!("opened probe: {:?}", probe);
+        Ok(Box::new(probe))
+    }
+
+    fn list_probes(&self) -> Vec<DebugProbeInfo> {
+        vec![]
+    }
+}
+
+#[derive(Debug)]
+enum Transport {
+    RemoteBitbang(RemoteBitbang),
+    JtagVpi(JtagVpi),
+}
+
+impl Transport {
+    fn shift_bit(&mut self, tms: bool, tdi: bool, capture: bool) -> Result<(), SimulatorError> {
+        match self {
+            Self::RemoteBitbang(transport) => transport.shift_bit(tms, tdi, capture),
+            Self::JtagVpi(transport) => transport.shift_bit(tms, tdi, capture),
+        }
+    }
+
+    fn read_captured_bits(&mut self) -> Result<BitVec<u8, Lsb0>, SimulatorError> {
+        match self {
+            Self::RemoteBitbang(transport) => transport.read_captured_bits(),
+            Self::JtagVpi(transport) => transport.read_captured_bits(),
+        }
+    }
+
+    fn set_system_reset(&mut self, asserted: bool) -> Result<(), SimulatorError> {
+        match self {
+            Self::RemoteBitbang(transport) => transport.set_system_reset(asserted),
+            Self::JtagVpi(_) => Err(SimulatorError::Unsupported("the system reset")),
+        }
+    }
+}
+
+/// A probe for a simulated target, see the [module documentation](self).
+#[derive(Debug)]
+pub struct SimulatorProbe {
+    name: String,
+    transport: Transport,
+    speed_khz: u32,
+    jtag_state: JtagDriverState,
+    probe_statistics: ProbeStatistics,
+    swd_settings: SwdSettings,
+}
+
+impl SimulatorProbe {
+    /// Connects to the simulator selected by `selector`.
+    pub fn connect(selector: &SimulatorSelector) -> Result<Self, DebugProbeError> {
+        let stream = TcpStream::connect(&selector.address).map_err(SimulatorError::from)?;
+        stream.set_nodelay(true).map_err(SimulatorError::from)?;
+
+        let transport = match selector.protocol {
+            SimulatorProtocol::RemoteBitbang => {
+                Transport::RemoteBitbang(RemoteBitbang::new(stream))
+            }
+            SimulatorProtocol::JtagVpi => Transport::JtagVpi(JtagVpi::new(stream)),
+        };
+
+        Ok(Self {
+            name: format!("Simulator at {selector}"),
+            transport,
+            // Simulators are clocked by the probe, so the speed is only reported.
+            speed_khz: 1000,
+            jtag_state: JtagDriverState::default(),
+            probe_statistics: ProbeStatistics::default(),
+            swd_settings: SwdSettings::default(),
+        })
+    }
+}
+
+impl DebugProbe for SimulatorProbe {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn speed_khz(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        self.speed_khz = speed_khz;
+        Ok(speed_khz)
+    }
+
+    fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError> {
+        tracing::info!("Setting scan chain to {:?}", scan_chain);
+        self.jtag_state.expected_scan_chain = Some(scan_chain);
+        Ok(())
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        tracing::debug!("Attaching...");
+
+        let chain = self.scan_chain()?;
+        tracing::info!("Found {} TAPs on reset scan", chain.len());
+
+        if chain.len() > 1 {
+            tracing::info!("More than one TAP detected, defaulting to tap0");
+        }
+
+        self.select_target(&chain, 0)
+    }
+
+    fn detach(&mut self) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "target_reset",
+        })
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        Ok(self.transport.set_system_reset(true)?)
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        Ok(self.transport.set_system_reset(false)?)
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        if protocol != WireProtocol::Jtag {
+            Err(DebugProbeError::UnsupportedProtocol(protocol))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        // Only supports JTAG
+        Some(WireProtocol::Jtag)
+    }
+
+    fn try_get_riscv_interface(
+        self: Box<Self>,
+    ) -> Result<RiscvCommunicationInterface, (Box<dyn DebugProbe>, RiscvError)> {
+        let jtag_dtm = match JtagDtm::new(self) {
+            Ok(jtag_dtm) => Box::new(jtag_dtm),
+            Err((access, err)) => return Err((access.into_probe(), err)),
+        };
+        match RiscvCommunicationInterface::new(jtag_dtm) {
+            Ok(interface) => Ok(interface),
+            Err((probe, err)) => Err((probe.into_probe(), err)),
+        }
+    }
+
+    fn has_riscv_interface(&self) -> bool {
+        true
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        Some(self)
+    }
+
+    fn try_as_dap_probe(&mut self) -> Option<&mut dyn DapProbe> {
+        Some(self)
+    }
+
+    fn try_get_arm_interface<'probe>(
+        self: Box<Self>,
+    ) -> Result<Box<dyn UninitializedArmProbe + 'probe>, (Box<dyn DebugProbe>, DebugProbeError)>
+    {
+        let uninitialized_interface = ArmCommunicationInterface::new(self, true);
+
+        Ok(Box::new(uninitialized_interface))
+    }
+
+    fn has_arm_interface(&self) -> bool {
+        true
+    }
+
+    fn try_get_xtensa_interface(
+        self: Box<Self>,
+    ) -> Result<XtensaCommunicationInterface, (Box<dyn DebugProbe>, DebugProbeError)> {
+        match XtensaCommunicationInterface::new(self) {
+            Ok(interface) => Ok(interface),
+            Err((probe, err)) => Err((probe.into_probe(), err)),
+        }
+    }
+
+    fn has_xtensa_interface(&self) -> bool {
+        true
+    }
+}
+
+impl DapProbe for SimulatorProbe {}
+
+impl RawProtocolIo for SimulatorProbe {
+    fn jtag_shift_tms<M>(&mut self, tms: M, tdi: bool) -> Result<(), DebugProbeError>
+    where
+        M: IntoIterator<Item = bool>,
+    {
+        self.probe_statistics.report_io();
+
+        self.shift_bits(tms, std::iter::repeat(tdi), std::iter::repeat(false))?;
+
+        Ok(())
+    }
+
+    fn jtag_shift_tdi<I>(&mut self, tms: bool, tdi: I) -> Result<(), DebugProbeError>
+    where
+        I: IntoIterator<Item = bool>,
+    {
+        self.probe_statistics.report_io();
+
+        self.shift_bits(std::iter::repeat(tms), tdi, std::iter::repeat(false))?;
+
+        Ok(())
+    }
+
+    fn swd_io<D, S>(&mut self, _dir: D, _swdio: S) -> Result<Vec<bool>, DebugProbeError>
+    where
+        D: IntoIterator<Item = bool>,
+        S: IntoIterator<Item = bool>,
+    {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "swd_io",
+        })
+    }
+
+    fn swj_pins(
+        &mut self,
+        _pin_out: u32,
+        _pin_select: u32,
+        _pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        Err(DebugProbeError::CommandNotSupportedByProbe {
+            command_name: "swj_pins",
+        })
+    }
+
+    fn swd_settings(&self) -> &SwdSettings {
+        &self.swd_settings
+    }
+
+    fn probe_statistics(&mut self) -> &mut ProbeStatistics {
+        &mut self.probe_statistics
+    }
+}
+
+impl RawJtagIo for SimulatorProbe {
+    fn shift_bit(
+        &mut self,
+        tms: bool,
+        tdi: bool,
+        capture_tdo: bool,
+    ) -> Result<(), DebugProbeError> {
+        self.jtag_state.state.update(tms);
+        self.transport.shift_bit(tms, tdi, capture_tdo)?;
+        Ok(())
+    }
+
+    fn read_captured_bits(&mut self) -> Result<BitVec<u8, Lsb0>, DebugProbeError> {
+        Ok(self.transport.read_captured_bits()?)
+    }
+
+    fn state_mut(&mut self) -> &mut JtagDriverState {
+        &mut self.jtag_state
+    }
+
+    fn state(&self) -> &JtagDriverState {
+        &self.jtag_state
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::probe::common::{JtagState, RegisterState};
+
+    const IDCODE: u32 = 0x1000_563D;
+    const IR_LEN: usize = 4;
+    const IR_IDCODE: u32 = 0x1;
+    const IR_SCRATCH: u32 = 0x2;
+
+    /// A single TAP with an IDCODE register and a 32 bit scratch register.
+    struct TapModel {
+        state: JtagState,
+        ir: u32,
+        scratch: u32,
+        shift: BitVec<u32, Lsb0>,
+    }
+
+    impl TapModel {
+        fn new() -> Self {
+            Self {
+                state: JtagState::Reset,
+                ir: IR_IDCODE,
+                scratch: 0,
+                shift: BitVec::new(),
+            }
+        }
+
+        /// The value of TDO before the rising edge of TCK.
+        fn tdo(&self) -> bool {
+            match self.state {
+                JtagState::Ir(RegisterState::Shift) | JtagState::Dr(RegisterState::Shift) => {
+                    self.shift[0]
+                }
+                _ => false,
+            }
+        }
+
+        /// Clocks the TAP, and returns the value of TDO before the clock.
+        fn clock(&mut self, tms: bool, tdi: bool) -> bool {
+            let tdo = self.tdo();
+
+            match self.state {
+                JtagState::Reset => self.ir = IR_IDCODE,
+                JtagState::Ir(RegisterState::Capture) => {
+                    self.shift = bitvec![u32, Lsb0; 0; IR_LEN];
+                    self.shift.set(0, true);
+                }
+                JtagState::Dr(RegisterState::Capture) => {
+                    self.shift = match self.ir {
+                        IR_IDCODE => IDCODE.view_bits::<Lsb0>().to_bitvec(),
+                        IR_SCRATCH => self.scratch.view_bits::<Lsb0>().to_bitvec(),
+                        _ => bitvec![u32, Lsb0; 0; 1],
+                    };
+                }
+                JtagState::Ir(RegisterState::Shift) | JtagState::Dr(RegisterState::Shift) => {
+                    self.shift.remove(0);
+                    self.shift.push(tdi);
+                }
+                JtagState::Ir(RegisterState::Update) => self.ir = self.shift.load_le(),
+                JtagState::Dr(RegisterState::Update) if self.ir == IR_SCRATCH => {
+                    self.scratch = self.shift.load_le()
+                }
+                _ => {}
+            }
+
+            // Update-IR and Update-DR take effect when they are left.
+            self.state.update(tms);
+            tdo
+        }
+    }
+
+    /// Serves the TAP model with the remote_bitbang protocol, until the client disconnects.
+    fn serve_remote_bitbang(listener: TcpListener) {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut tap = TapModel::new();
+        let mut tck = false;
+        let mut tdo = false;
+
+        let mut buffer = [0; 4096];
+        loop {
+            let read = stream.read(&mut buffer).unwrap();
+            if read == 0 {
+                return;
+            }
+
+            let mut response = vec![];
+            for &command in &buffer[..read] {
+                match command {
+                    b'0'..=b'7' => {
+                        let bits = command - b'0';
+                        let (new_tck, tms, tdi) = (bits & 4 != 0, bits & 2 != 0, bits & 1 != 0);
+                        if new_tck && !tck {
+                            tdo = tap.clock(tms, tdi);
+                        } else if !new_tck {
+                            tdo = tap.tdo();
+                        }
+                        tck = new_tck;
+                    }
+                    b'R' => response.push(if tdo { b'1' } else { b'0' }),
+                    b'r'..=b'u' | b'B' | b'b' => {}
+                    b'Q' => return,
+                    other => panic!("Unexpected command {other:#04x}"),
+                }
+            }
+            stream.write_all(&response).unwrap();
+        }
+    }
+
+    /// Serves the TAP model with the JTAG-VPI protocol, until the client disconnects.
+    fn serve_jtag_vpi(listener: TcpListener) {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut tap = TapModel::new();
+
+        let mut message = [0; jtag_vpi::MESSAGE_SIZE];
+        while stream.read_exact(&mut message).is_ok() {
+            let command = jtag_vpi::Command::decode(&message);
+            let bits = command.buffer_out.view_bits::<Lsb0>();
+
+            match command.cmd {
+                jtag_vpi::CMD_TMS_SEQ => {
+                    for tms in &bits[..command.nb_bits as usize] {
+                        tap.clock(*tms, false);
+                    }
+                }
+                jtag_vpi::CMD_SCAN_CHAIN | jtag_vpi::CMD_SCAN_CHAIN_FLIP_TMS => {
+                    let mut response = command.clone();
+                    let nb_bits = command.nb_bits as usize;
+                    for (index, tdi) in bits[..nb_bits].iter().enumerate() {
+                        let flip = command.cmd == jtag_vpi::CMD_SCAN_CHAIN_FLIP_TMS;
+                        let tdo = tap.clock(flip && index == nb_bits - 1, *tdi);
+                        response.buffer_in.view_bits_mut::<Lsb0>().set(index, tdo);
+                    }
+                    stream.write_all(&response.encode()).unwrap();
+                }
+                other => panic!("Unexpected command {other}"),
+            }
+        }
+    }
+
+    fn exercise(protocol: SimulatorProtocol, serve: fn(TcpListener)) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || serve(listener));
+
+        let selector: DebugProbeSelector = format!("{protocol}://{address}").parse().unwrap();
+        let mut probe = SimulatorProbeFactory.open(&selector).unwrap();
+
+        let chain = probe.try_as_jtag_probe().unwrap().scan_chain().unwrap();
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain[0].idcode.map(u32::from), Some(IDCODE));
+        assert_eq!(chain[0].irlen, IR_LEN);
+
+        probe.attach().unwrap();
+        let jtag = probe.try_as_jtag_probe().unwrap();
+        assert_eq!(
+            jtag.read_register(IR_IDCODE, 32).unwrap(),
+            IDCODE.to_le_bytes()
+        );
+        assert_eq!(
+            jtag.write_register(IR_SCRATCH, &0xDEAD_BEEFu32.to_le_bytes(), 32)
+                .unwrap(),
+            [0; 4]
+        );
+        jtag.set_idle_cycles(3);
+        assert_eq!(
+            jtag.write_register(IR_SCRATCH, &0x1234_5678u32.to_le_bytes(), 32)
+                .unwrap(),
+            0xDEAD_BEEFu32.to_le_bytes()
+        );
+        assert_eq!(
+            jtag.read_register(IR_SCRATCH, 32).unwrap(),
+            0x1234_5678u32.to_le_bytes()
+        );
+
+        drop(probe);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn remote_bitbang() {
+        exercise(SimulatorProtocol::RemoteBitbang, serve_remote_bitbang);
+    }
+
+    #[test]
+    fn jtag_vpi() {
+        exercise(SimulatorProtocol::JtagVpi, serve_jtag_vpi);
+    }
+
+    #[test]
+    fn parse_selector() {
+        let selector: DebugProbeSelector = "jtag_vpi://localhost:5555".parse().unwrap();
+        assert_eq!(
+            selector.simulator,
+            Some(SimulatorSelector {
+                protocol: SimulatorProtocol::JtagVpi,
+                address: "localhost:5555".into(),
+            })
+        );
+        assert_eq!(selector.to_string(), "jtag_vpi://localhost:5555");
+    }
+}
@@ -0,0 +1,98 @@
+//! The remote_bitbang protocol of OpenOCD.
+//!
+//! Every command is a single character: `0` to `7` set TCK, TMS and TDI (as `4 * TCK + 2 * TMS +
+//! TDI`), `R` requests the value of TDO, which the simulator answers with `0` or `1`, and `r` to
+//! `u` set TRST and SRST (as `r + 2 * TRST + SRST`).
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+use bitvec::prelude::*;
+
+use super::SimulatorError;
+
+/// The number of commands which are buffered before they are sent.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub(super) struct RemoteBitbang {
+    stream: TcpStream,
+    /// Commands which were not sent yet.
+    commands: Vec<u8>,
+    /// The number of `R` commands in `commands`.
+    pending_reads: usize,
+    captured: BitVec<u8, Lsb0>,
+}
+
+impl RemoteBitbang {
+    pub fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            commands: vec![],
+            pending_reads: 0,
+            captured: BitVec::new(),
+        }
+    }
+
+    fn write_pins(&mut self, tck: bool, tms: bool, tdi: bool) {
+        self.commands
+            .push(b'0' + ((tck as u8) << 2 | (tms as u8) << 1 | tdi as u8));
+    }
+
+    pub fn shift_bit(&mut self, tms: bool, tdi: bool, capture: bool) -> Result<(), SimulatorError> {
+        // TDO is sampled after the falling edge, the TAP samples TMS and TDI on the rising edge.
+        self.write_pins(false, tms, tdi);
+        if capture {
+            self.commands.push(b'R');
+            self.pending_reads += 1;
+        }
+        self.write_pins(true, tms, tdi);
+
+        if self.commands.len() >= BUFFER_SIZE {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_captured_bits(&mut self) -> Result<BitVec<u8, Lsb0>, SimulatorError> {
+        self.flush()?;
+
+        Ok(std::mem::take(&mut self.captured))
+    }
+
+    pub fn set_system_reset(&mut self, asserted: bool) -> Result<(), SimulatorError> {
+        // TRST is never asserted.
+        self.commands.push(b'r' + asserted as u8);
+
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<(), SimulatorError> {
+        if self.commands.is_empty() {
+            return Ok(());
+        }
+
+        tracing::trace!(
+            "Sending {} commands, reading {} bits",
+            self.commands.len(),
+            self.pending_reads
+        );
+        self.stream.write_all(&self.commands)?;
+        self.commands.clear();
+
+        let mut response = vec![0; self.pending_reads];
+        self.stream.read_exact(&mut response)?;
+        self.pending_reads = 0;
+
+        for value in response {
+            match value {
+                b'0' => self.captured.push(false),
+                b'1' => self.captured.push(true),
+                other => return Err(SimulatorError::InvalidResponse(other)),
+            }
+        }
+
+        Ok(())
+    }
+}
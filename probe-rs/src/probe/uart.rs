@@ -0,0 +1,137 @@
+//! Access to a target UART through the debug probe.
+//!
+//! Some debug probes route a UART of the target through their debug
+//! connection, so a serial console can be used without a separate USB-serial adapter.
+
+use super::DebugProbeError;
+
+/// The parity bit used for UART frames.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum UartParity {
+    /// No parity bit.
+    #[default]
+    None,
+    /// Odd parity.
+    Odd,
+    /// Even parity.
+    Even,
+}
+
+/// The number of stop bits used for UART frames.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum UartStopBits {
+    /// One stop bit.
+    #[default]
+    One,
+    /// Two stop bits.
+    Two,
+}
+
+/// The config for a UART routed through the debug probe.
+#[derive(Debug, Copy, Clone)]
+pub struct UartConfig {
+    /// Baud rate of the UART, in Hz.
+    baud: u32,
+
+    /// Number of data bits per frame, between 5 and 8.
+    data_bits: u8,
+
+    /// The parity bit of each frame.
+    parity: UartParity,
+
+    /// The number of stop bits of each frame.
+    stop_bits: UartStopBits,
+}
+
+impl UartConfig {
+    /// Create a new UartConfig using the specified baud rate in Hz.
+    ///
+    /// By default frames have 8 data bits, no parity and one stop bit (8N1).
+    pub fn new(baud: u32) -> Self {
+        UartConfig {
+            baud,
+            data_bits: 8,
+            parity: UartParity::None,
+            stop_bits: UartStopBits::One,
+        }
+    }
+
+    /// Set the baud rate in Hz.
+    pub fn set_baud(mut self, baud: u32) -> Self {
+        self.baud = baud;
+        self
+    }
+
+    /// Set the number of data bits per frame.
+    pub fn set_data_bits(mut self, data_bits: u8) -> Self {
+        self.data_bits = data_bits;
+        self
+    }
+
+    /// Set the parity bit of each frame.
+    pub fn set_parity(mut self, parity: UartParity) -> Self {
+        self.parity = parity;
+        self
+    }
+
+    /// Set the number of stop bits of each frame.
+    pub fn set_stop_bits(mut self, stop_bits: UartStopBits) -> Self {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    /// Baud rate of the UART, in Hz.
+    pub fn baud(&self) -> u32 {
+        self.baud
+    }
+
+    /// Number of data bits per frame.
+    pub fn data_bits(&self) -> u8 {
+        self.data_bits
+    }
+
+    /// The parity bit of each frame.
+    pub fn parity(&self) -> UartParity {
+        self.parity
+    }
+
+    /// The number of stop bits of each frame.
+    pub fn stop_bits(&self) -> UartStopBits {
+        self.stop_bits
+    }
+}
+
+/// An interface to a target UART, to be implemented on drivers that can route one through the probe.
+pub trait UartAccess {
+    /// Configure the UART and start receiving and transmitting data.
+    fn enable_uart(&mut self, config: &UartConfig) -> Result<(), DebugProbeError>;
+
+    /// Stop receiving and transmitting data on the UART.
+    fn disable_uart(&mut self) -> Result<(), DebugProbeError>;
+
+    /// Read any available UART data without waiting.
+    ///
+    /// Returns the bytes received since the last call to `read_uart()`.
+    /// If no data was available, returns an empty Vec.
+    fn read_uart(&mut self) -> Result<Vec<u8>, DebugProbeError>;
+
+    /// Queue `data` for transmission to the target.
+    ///
+    /// Returns the number of bytes the probe accepted, which may be less
+    /// than `data.len()` if its transmit buffer is full, but never more.
+    fn write_uart(&mut self, data: &[u8]) -> Result<usize, DebugProbeError>;
+
+    /// Queue all of `data` for transmission to the target, retrying
+    /// until the probe accepted every byte.
+    fn write_all_uart(&mut self, mut data: &[u8]) -> Result<(), DebugProbeError> {
+        while !data.is_empty() {
+            let written = self.write_uart(data)?;
+            if written == 0 {
+                // Give the probe some time to drain its transmit buffer.
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            data = &data[written..];
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,517 @@
+//! A debug probe which is connected to an emulated Cortex-M core instead of a real target.
+//!
+//! The [`VirtualProbe`] runs a small Thumb interpreter, with the core registers, the debug
+//! registers, the flash patch and breakpoint unit and the memory described by a [`Target`].
+//! It is meant for tests which exercise the core, breakpoint and flashing code of probe-rs without
+//! hardware. Peripherals, exceptions and the floating point unit are not modeled.
+
+mod system;
+mod thumb;
+
+use std::{fmt::Debug, sync::Arc};
+
+use probe_rs_target::ScanChainElement;
+
+use self::system::VirtualSystem;
+use crate::{
+    architecture::arm::{
+        ap::{GenericAp, MemoryAp},
+        communication_interface::{
+            ArmDebugState, Initialized, SwdSequence, Uninitialized, UninitializedArmProbe,
+        },
+        memory::adi_v5_memory_interface::ArmProbe,
+        sequences::ArmDebugSequence,
+        valid_32bit_arm_address, ApAddress, ApInformation, ArmChipInfo, ArmError,
+        ArmProbeInterface, DapAccess, DpAddress, MemoryApInformation, Pins, SwoAccess, SwoConfig,
+    },
+    config::Target,
+    probe::{DebugProbe, DebugProbeError, Probe, WireProtocol},
+    CoreStatus, Error,
+};
+
+/// A debug probe connected to an emulated Cortex-M core.
+pub struct VirtualProbe {
+    protocol: WireProtocol,
+    speed: u32,
+    system: VirtualSystem,
+}
+
+impl Debug for VirtualProbe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VirtualProbe")
+            .field("protocol", &self.protocol)
+            .field("speed", &self.speed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl VirtualProbe {
+    /// Creates a probe connected to an emulation of the first core of `target`.
+    ///
+    /// The memory of the target starts out erased, so the core locks up after power on until
+    /// a program is loaded and the core is reset.
+    pub fn new(target: &Target) -> Self {
+        Self {
+            protocol: WireProtocol::Swd,
+            speed: 1000,
+            system: VirtualSystem::new(target),
+        }
+    }
+
+    /// Makes a generic probe out of the [`VirtualProbe`].
+    pub fn into_probe(self) -> Probe {
+        Probe::from_specific_probe(Box::new(self))
+    }
+}
+
+/// Drives the nRESET pin of the emulated target and returns the state of the pins.
+///
+/// The other pins are not modeled.
+fn swj_pins(system: &mut VirtualSystem, pin_out: u32, pin_select: u32) -> u32 {
+    let mut nreset = Pins(0);
+    nreset.set_nreset(true);
+    let nreset = nreset.0 as u32;
+
+    if pin_select & nreset != 0 {
+        system.set_reset(pin_out & nreset == 0);
+    }
+
+    if system.reset_asserted() {
+        0
+    } else {
+        nreset
+    }
+}
+
+fn unsupported(command_name: &'static str) -> ArmError {
+    DebugProbeError::CommandNotSupportedByProbe { command_name }.into()
+}
+
+impl DebugProbe for VirtualProbe {
+    fn get_name(&self) -> &str {
+        "Virtual probe"
+    }
+
+    fn speed_khz(&self) -> u32 {
+        self.speed
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        self.speed = speed_khz;
+
+        Ok(speed_khz)
+    }
+
+    fn set_scan_chain(
+        &mut self,
+        _scan_chain: Vec<ScanChainElement>,
+    ) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn detach(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.system.reset();
+        Ok(())
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.system.set_reset(true);
+        Ok(())
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.system.set_reset(false);
+        Ok(())
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        self.protocol = protocol;
+
+        Ok(())
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        Some(self.protocol)
+    }
+
+    fn has_arm_interface(&self) -> bool {
+        true
+    }
+
+    fn try_get_arm_interface<'probe>(
+        self: Box<Self>,
+    ) -> Result<Box<dyn UninitializedArmProbe + 'probe>, (Box<dyn DebugProbe>, DebugProbeError)>
+    {
+        Ok(Box::new(VirtualArmInterface::new(self)))
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+}
+
+/// The ARM interface of the [`VirtualProbe`], with a single memory AP connected to the core.
+#[derive(Debug)]
+struct VirtualArmInterface<S: ArmDebugState> {
+    probe: Box<VirtualProbe>,
+    ap_information: ApInformation,
+
+    state: S,
+}
+
+impl VirtualArmInterface<Uninitialized> {
+    fn new(probe: Box<VirtualProbe>) -> Self {
+        let ap_information = ApInformation::MemoryAp(MemoryApInformation {
+            address: ApAddress::with_default_dp(0),
+            supports_only_32bit_data_size: false,
+            debug_base_address: 0xE00F_F000,
+            supports_hnonsec: false,
+            has_large_address_extension: false,
+            has_large_data_extension: false,
+            device_enabled: true,
+        });
+        let state = Uninitialized {
+            use_overrun_detect: false,
+        };
+
+        Self {
+            probe,
+            ap_information,
+            state,
+        }
+    }
+}
+
+impl<S: ArmDebugState> SwdSequence for VirtualArmInterface<S> {
+    fn swj_sequence(&mut self, _bit_len: u8, _bits: u64) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn swj_pins(
+        &mut self,
+        pin_out: u32,
+        pin_select: u32,
+        _pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        Ok(swj_pins(&mut self.probe.system, pin_out, pin_select))
+    }
+}
+
+impl UninitializedArmProbe for VirtualArmInterface<Uninitialized> {
+    fn initialize(
+        self: Box<Self>,
+        sequence: Arc<dyn ArmDebugSequence>,
+        dp: DpAddress,
+    ) -> Result<Box<dyn ArmProbeInterface>, (Box<dyn UninitializedArmProbe>, Error)> {
+        let interface = VirtualArmInterface {
+            probe: self.probe,
+            ap_information: self.ap_information,
+            state: Initialized::new(sequence, dp, false),
+        };
+
+        Ok(Box::new(interface))
+    }
+
+    fn close(self: Box<Self>) -> Probe {
+        Probe::from_attached_probe(self.probe)
+    }
+}
+
+impl ArmProbeInterface for VirtualArmInterface<Initialized> {
+    fn memory_interface(
+        &mut self,
+        access_port: MemoryAp,
+    ) -> Result<Box<dyn ArmProbe + '_>, ArmError> {
+        Ok(Box::new(VirtualMemoryInterface {
+            system: &mut self.probe.system,
+            access_port,
+        }))
+    }
+
+    fn ap_information(&mut self, _access_port: GenericAp) -> Result<&ApInformation, ArmError> {
+        Ok(&self.ap_information)
+    }
+
+    fn num_access_ports(&mut self, _dp: DpAddress) -> Result<usize, ArmError> {
+        Ok(1)
+    }
+
+    fn read_chip_info_from_rom_table(
+        &mut self,
+        _dp: DpAddress,
+    ) -> Result<Option<ArmChipInfo>, ArmError> {
+        Ok(None)
+    }
+
+    fn close(self: Box<Self>) -> Probe {
+        Probe::from_attached_probe(self.probe)
+    }
+
+    fn current_debug_port(&self) -> DpAddress {
+        self.state.current_dp
+    }
+}
+
+impl SwoAccess for VirtualArmInterface<Initialized> {
+    fn enable_swo(&mut self, _config: &SwoConfig) -> Result<(), ArmError> {
+        Err(unsupported("enable_swo"))
+    }
+
+    fn disable_swo(&mut self) -> Result<(), ArmError> {
+        Err(unsupported("disable_swo"))
+    }
+
+    fn read_swo_timeout(&mut self, _timeout: std::time::Duration) -> Result<Vec<u8>, ArmError> {
+        Err(unsupported("read_swo_timeout"))
+    }
+}
+
+/// The debug and access port registers are not modeled, only memory accesses through
+/// [`ArmProbeInterface::memory_interface`] are.
+impl DapAccess for VirtualArmInterface<Initialized> {
+    fn read_raw_dp_register(&mut self, _dp: DpAddress, _address: u8) -> Result<u32, ArmError> {
+        Err(unsupported("raw_dp_register"))
+    }
+
+    fn write_raw_dp_register(
+        &mut self,
+        _dp: DpAddress,
+        _address: u8,
+        _value: u32,
+    ) -> Result<(), ArmError> {
+        Err(unsupported("raw_dp_register"))
+    }
+
+    fn read_raw_ap_register(&mut self, _ap: ApAddress, _address: u8) -> Result<u32, ArmError> {
+        Err(unsupported("raw_ap_register"))
+    }
+
+    fn read_raw_ap_register_repeated(
+        &mut self,
+        _ap: ApAddress,
+        _address: u8,
+        _values: &mut [u32],
+    ) -> Result<(), ArmError> {
+        Err(unsupported("raw_ap_register"))
+    }
+
+    fn write_raw_ap_register(
+        &mut self,
+        _ap: ApAddress,
+        _address: u8,
+        _value: u32,
+    ) -> Result<(), ArmError> {
+        Err(unsupported("raw_ap_register"))
+    }
+
+    fn write_raw_ap_register_repeated(
+        &mut self,
+        _ap: ApAddress,
+        _address: u8,
+        _values: &[u32],
+    ) -> Result<(), ArmError> {
+        Err(unsupported("raw_ap_register"))
+    }
+}
+
+/// Memory access to the emulated system.
+struct VirtualMemoryInterface<'probe> {
+    system: &'probe mut VirtualSystem,
+    access_port: MemoryAp,
+}
+
+impl VirtualMemoryInterface<'_> {
+    fn read_bytes(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
+        let address = valid_32bit_arm_address(address)?;
+        self.system.read(address, data);
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
+        let address = valid_32bit_arm_address(address)?;
+        self.system.write(address, data);
+        Ok(())
+    }
+}
+
+impl SwdSequence for VirtualMemoryInterface<'_> {
+    fn swj_sequence(&mut self, _bit_len: u8, _bits: u64) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn swj_pins(
+        &mut self,
+        pin_out: u32,
+        pin_select: u32,
+        _pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        Ok(swj_pins(self.system, pin_out, pin_select))
+    }
+}
+
+impl ArmProbe for VirtualMemoryInterface<'_> {
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
+        self.read_bytes(address, data)
+    }
+
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), ArmError> {
+        let mut bytes = vec![0; data.len() * 2];
+        self.read_bytes(address, &mut bytes)?;
+        for (value, bytes) in data.iter_mut().zip(bytes.chunks_exact(2)) {
+            *value = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+        Ok(())
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        let mut bytes = vec![0; data.len() * 4];
+        self.read_bytes(address, &mut bytes)?;
+        for (value, bytes) in data.iter_mut().zip(bytes.chunks_exact(4)) {
+            *value = u32::from_le_bytes(bytes.try_into().unwrap());
+        }
+        Ok(())
+    }
+
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), ArmError> {
+        let mut bytes = vec![0; data.len() * 8];
+        self.read_bytes(address, &mut bytes)?;
+        for (value, bytes) in data.iter_mut().zip(bytes.chunks_exact(8)) {
+            *value = u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        Ok(())
+    }
+
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
+        self.write_bytes(address, data)
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), ArmError> {
+        let bytes: Vec<u8> = data.iter().flat_map(|value| value.to_le_bytes()).collect();
+        self.write_bytes(address, &bytes)
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        let bytes: Vec<u8> = data.iter().flat_map(|value| value.to_le_bytes()).collect();
+        self.write_bytes(address, &bytes)
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), ArmError> {
+        let bytes: Vec<u8> = data.iter().flat_map(|value| value.to_le_bytes()).collect();
+        self.write_bytes(address, &bytes)
+    }
+
+    fn flush(&mut self) -> Result<(), ArmError> {
+        Ok(())
+    }
+
+    fn supports_native_64bit_access(&mut self) -> bool {
+        false
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+        Ok(true)
+    }
+
+    fn ap(&mut self) -> MemoryAp {
+        self.access_port
+    }
+
+    fn get_arm_communication_interface(
+        &mut self,
+    ) -> Result<
+        &mut crate::architecture::arm::ArmCommunicationInterface<Initialized>,
+        DebugProbeError,
+    > {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "get_arm_communication_interface",
+        })
+    }
+
+    fn update_core_status(&mut self, _state: CoreStatus) {}
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::VirtualProbe;
+    use crate::{
+        config::get_target_by_name, flashing::DownloadOptions, MemoryInterface, Permissions,
+    };
+
+    #[test]
+    fn run_to_breakpoint() {
+        let target = get_target_by_name("nrf51822_xxAC").unwrap();
+        let probe = VirtualProbe::new(&target).into_probe();
+        let mut session = probe.attach(target, Permissions::default()).unwrap();
+        let mut core = session.core(0).unwrap();
+
+        core.halt(Duration::from_millis(100)).unwrap();
+
+        // Sums up the numbers from 10 down to 1, the FPB of ARMv6-M only covers the code region.
+        let code: [u16; 6] = [
+            0x200A, // movs r0, #10
+            0x2100, // movs r1, #0
+            0x1809, // loop: adds r1, r1, r0
+            0x3801, // subs r0, #1
+            0xD1FC, // bne loop
+            0xE7FE, // done: b done
+        ];
+        let bytes: Vec<u8> = code.iter().flat_map(|op| op.to_le_bytes()).collect();
+        core.write(0x1000, &bytes).unwrap();
+
+        let pc = core.program_counter();
+        core.write_core_reg(pc, 0x1000u32).unwrap();
+        core.set_hw_breakpoint(0x1008).unwrap();
+        core.run().unwrap();
+        core.wait_for_core_halted(Duration::from_millis(100))
+            .unwrap();
+
+        assert_eq!(core.read_core_reg::<u32>(pc).unwrap(), 0x1008);
+        assert_eq!(core.read_core_reg::<u32>(0u16).unwrap(), 9);
+        assert_eq!(core.read_core_reg::<u32>(1u16).unwrap(), 10);
+
+        // Stepping over the breakpoint takes the branch back into the loop.
+        assert_eq!(core.step().unwrap().pc, 0x1004);
+
+        // Running from the breakpoint stops at it again in the next iteration.
+        core.run().unwrap();
+        core.wait_for_core_halted(Duration::from_millis(100))
+            .unwrap();
+        assert_eq!(core.read_core_reg::<u32>(pc).unwrap(), 0x1008);
+        assert_eq!(core.read_core_reg::<u32>(1u16).unwrap(), 19);
+
+        core.clear_all_hw_breakpoints().unwrap();
+        core.run().unwrap();
+        core.halt(Duration::from_millis(100)).unwrap();
+        assert_eq!(core.read_core_reg::<u32>(pc).unwrap(), 0x100A);
+        assert_eq!(core.read_core_reg::<u32>(1u16).unwrap(), 55);
+    }
+
+    #[test]
+    fn flash_through_algorithm() {
+        let target = get_target_by_name("STM32F301C8Tx").unwrap();
+        let probe = VirtualProbe::new(&target).into_probe();
+        let mut session = probe.attach(target, Permissions::default()).unwrap();
+
+        let data: Vec<u8> = (0..2048u32).map(|i| (i * 7) as u8).collect();
+        let mut loader = session.target().flash_loader();
+        loader.add_data(0x0800_0400, &data).unwrap();
+        loader
+            .commit(&mut session, DownloadOptions::default())
+            .unwrap();
+
+        let mut read_back = vec![0; data.len()];
+        let mut core = session.core(0).unwrap();
+        core.read(0x0800_0400, &mut read_back).unwrap();
+        assert_eq!(read_back, data);
+    }
+}
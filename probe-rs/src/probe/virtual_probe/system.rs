@@ -0,0 +1,635 @@
+//! The model of a Cortex-M system, as seen by the debugger.
+
+use std::{collections::HashMap, ops::Range};
+
+use probe_rs_target::{CoreType, MemoryRegion};
+
+use super::thumb::{Bus, Cpu, Step};
+use crate::config::Target;
+
+/// The number of instructions the core executes each time the debugger accesses it while running.
+const INSTRUCTIONS_PER_ACCESS: usize = 10_000;
+
+const PAGE_SIZE: u64 = 4096;
+
+/// The private peripheral bus, which contains the SCS and the debug components.
+const PPB: Range<u32> = 0xE000_0000..0xE010_0000;
+
+const FP_CTRL: u32 = 0xE000_2000;
+const FP_REMAP: u32 = 0xE000_2004;
+const FP_COMP0: u32 = 0xE000_2008;
+const CPUID: u32 = 0xE000_ED00;
+const VTOR: u32 = 0xE000_ED08;
+const AIRCR: u32 = 0xE000_ED0C;
+const DFSR: u32 = 0xE000_ED30;
+const DHCSR: u32 = 0xE000_EDF0;
+const DCRSR: u32 = 0xE000_EDF4;
+const DCRDR: u32 = 0xE000_EDF8;
+const DEMCR: u32 = 0xE000_EDFC;
+
+const DHCSR_C_DEBUGEN: u32 = 1 << 0;
+const DHCSR_C_HALT: u32 = 1 << 1;
+const DHCSR_C_STEP: u32 = 1 << 2;
+const DHCSR_C_MASKINTS: u32 = 1 << 3;
+const DHCSR_S_REGRDY: u32 = 1 << 16;
+const DHCSR_S_HALT: u32 = 1 << 17;
+const DHCSR_S_LOCKUP: u32 = 1 << 19;
+const DHCSR_S_RETIRE_ST: u32 = 1 << 24;
+const DHCSR_S_RESET_ST: u32 = 1 << 25;
+
+const DFSR_HALTED: u32 = 1 << 0;
+const DFSR_BKPT: u32 = 1 << 1;
+const DFSR_VCATCH: u32 = 1 << 3;
+
+const DEMCR_VC_CORERESET: u32 = 1 << 0;
+
+/// A memory region of the target, which is allocated on first write.
+#[derive(Debug)]
+struct Region {
+    range: Range<u64>,
+    /// The value of memory which was never written.
+    fill: u8,
+    pages: HashMap<u64, Box<[u8]>>,
+}
+
+/// The RAM and non-volatile memory of the target.
+///
+/// Flash is written like RAM, by the debugger as well as by the core. Erasing it needs the
+/// flash peripheral, which isn't modeled, so a flash algorithm can program but not erase it.
+#[derive(Debug)]
+pub(super) struct Memory {
+    regions: Vec<Region>,
+}
+
+impl Memory {
+    pub fn new(target: &Target) -> Self {
+        let regions = target
+            .memory_map
+            .iter()
+            .map(|region| match region {
+                MemoryRegion::Ram(ram) => (ram.range.clone(), 0x00),
+                MemoryRegion::Generic(generic) => (generic.range.clone(), 0x00),
+                MemoryRegion::Nvm(nvm) => (nvm.range.clone(), 0xFF),
+            })
+            .map(|(range, fill)| Region {
+                range,
+                fill,
+                pages: HashMap::new(),
+            })
+            .collect();
+
+        Self { regions }
+    }
+
+    fn region(&self, address: u64) -> Option<&Region> {
+        self.regions
+            .iter()
+            .find(|region| region.range.contains(&address))
+    }
+
+    fn is_mapped(&self, address: u32) -> bool {
+        self.region(address as u64).is_some()
+    }
+
+    /// Reads from memory, unmapped memory reads as zero.
+    fn read(&self, address: u32, data: &mut [u8]) {
+        for (offset, byte) in data.iter_mut().enumerate() {
+            let address = address.wrapping_add(offset as u32) as u64;
+            *byte = match self.region(address) {
+                Some(region) => region
+                    .pages
+                    .get(&(address / PAGE_SIZE))
+                    .map_or(region.fill, |page| page[(address % PAGE_SIZE) as usize]),
+                None => 0,
+            };
+        }
+    }
+
+    /// Writes to memory, writes to unmapped memory are ignored.
+    fn write(&mut self, address: u32, data: &[u8]) {
+        for (offset, byte) in data.iter().enumerate() {
+            let address = address.wrapping_add(offset as u32) as u64;
+            let Some(region) = self
+                .regions
+                .iter_mut()
+                .find(|region| region.range.contains(&address))
+            else {
+                continue;
+            };
+
+            let fill = region.fill;
+            let page = region
+                .pages
+                .entry(address / PAGE_SIZE)
+                .or_insert_with(|| vec![fill; PAGE_SIZE as usize].into_boxed_slice());
+            page[(address % PAGE_SIZE) as usize] = *byte;
+        }
+    }
+}
+
+/// The registers of the system control space and the flash patch and breakpoint unit.
+#[derive(Debug)]
+pub(super) struct Scs {
+    core_type: CoreType,
+    /// The control bits of the DHCSR.
+    dhcsr: u32,
+    dfsr: u32,
+    dcrdr: u32,
+    demcr: u32,
+    vtor: u32,
+    fp_enable: bool,
+    fp_comp: Vec<u32>,
+    /// Set by a write to the AIRCR, the system is reset after the access.
+    reset_requested: bool,
+}
+
+impl Scs {
+    fn new(core_type: CoreType) -> Self {
+        let comparators = if core_type == CoreType::Armv6m { 4 } else { 6 };
+
+        Self {
+            core_type,
+            dhcsr: 0,
+            dfsr: 0,
+            dcrdr: 0,
+            demcr: 0,
+            vtor: 0,
+            fp_enable: false,
+            fp_comp: vec![0; comparators],
+            reset_requested: false,
+        }
+    }
+
+    fn cpuid(&self) -> u32 {
+        match self.core_type {
+            CoreType::Armv6m => 0x410C_C601,
+            CoreType::Armv7m => 0x412F_C231,
+            CoreType::Armv8m => 0x410F_D214,
+            _ => 0x410F_C241,
+        }
+    }
+
+    /// The FPBv1 of ARMv6-M only matches addresses in the code region, ARMv7-M and ARMv8-M
+    /// implement FPBv2, which matches any address.
+    fn fp_revision(&self) -> u32 {
+        if self.core_type == CoreType::Armv6m {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Returns `true` if the FPB has a breakpoint set at `address`.
+    fn breakpoint_at(&self, address: u32) -> bool {
+        if !self.fp_enable {
+            return false;
+        }
+
+        self.fp_comp
+            .iter()
+            .filter(|comp| *comp & 1 != 0)
+            .any(|&comp| {
+                if self.fp_revision() == 1 {
+                    comp & !1 == address
+                } else {
+                    let word = comp & 0x1FFF_FFFC;
+                    match comp >> 30 {
+                        0b01 => address == word,
+                        0b10 => address == word | 2,
+                        0b11 => address & !3 == word,
+                        _ => false,
+                    }
+                }
+            })
+    }
+
+    fn read(&self, address: u32) -> u32 {
+        match address {
+            FP_CTRL => {
+                let comparators = self.fp_comp.len() as u32;
+                self.fp_revision() << 28
+                    | (comparators >> 4) << 12
+                    | (comparators & 0xF) << 4
+                    | self.fp_enable as u32
+            }
+            FP_REMAP => 0,
+            address if (FP_COMP0..FP_COMP0 + 4 * self.fp_comp.len() as u32).contains(&address) => {
+                self.fp_comp[((address - FP_COMP0) / 4) as usize]
+            }
+            CPUID => self.cpuid(),
+            VTOR => self.vtor,
+            AIRCR => 0xFA05 << 16,
+            DFSR => self.dfsr,
+            DHCSR => self.dhcsr | DHCSR_S_REGRDY,
+            DCRDR => self.dcrdr,
+            DEMCR => self.demcr,
+            _ => 0,
+        }
+    }
+
+    /// Writes a register. The DHCSR and DCRSR are written by [`VirtualSystem::write`].
+    fn write(&mut self, address: u32, value: u32) {
+        match address {
+            FP_CTRL if value & 0b10 != 0 => self.fp_enable = value & 1 != 0,
+            address if (FP_COMP0..FP_COMP0 + 4 * self.fp_comp.len() as u32).contains(&address) => {
+                self.fp_comp[((address - FP_COMP0) / 4) as usize] = value;
+            }
+            VTOR => self.vtor = value & !0x7F,
+            AIRCR if value >> 16 == 0x05FA => {
+                let vectreset = value & 1 != 0 && self.core_type != CoreType::Armv6m;
+                self.reset_requested = value & 0b100 != 0 || vectreset;
+            }
+            DFSR => self.dfsr &= !value,
+            DCRDR => self.dcrdr = value,
+            DEMCR => self.demcr = value,
+            _ => {}
+        }
+    }
+}
+
+/// The bus of the core, with the memory and the SCS.
+#[derive(Debug)]
+pub(super) struct SystemBus {
+    memory: Memory,
+    scs: Scs,
+}
+
+impl Bus for SystemBus {
+    fn read(&mut self, address: u32, data: &mut [u8]) {
+        if PPB.contains(&address) {
+            let mut word = None;
+            for (offset, byte) in data.iter_mut().enumerate() {
+                let address = address.wrapping_add(offset as u32);
+                let (aligned, value) = match word {
+                    Some((aligned, value)) if aligned == address & !3 => (aligned, value),
+                    _ => (address & !3, self.scs.read(address & !3)),
+                };
+                word = Some((aligned, value));
+                *byte = value.to_le_bytes()[(address & 3) as usize];
+            }
+        } else {
+            self.memory.read(address, data);
+        }
+    }
+
+    fn write(&mut self, address: u32, data: &[u8]) {
+        if PPB.contains(&address) {
+            // Sub-word writes to the SCS are merged into the current value of the register.
+            for (offset, chunk) in data.chunks(4).enumerate() {
+                let address = address.wrapping_add(4 * offset as u32);
+                let aligned = address & !3;
+                let mut bytes = self.scs.read(aligned).to_le_bytes();
+                let start = (address & 3) as usize;
+                let end = (start + chunk.len()).min(4);
+                bytes[start..end].copy_from_slice(&chunk[..end - start]);
+                self.scs.write(aligned, u32::from_le_bytes(bytes));
+            }
+        } else {
+            self.memory.write(address, data);
+        }
+    }
+
+    fn is_mapped(&self, address: u32) -> bool {
+        self.memory.is_mapped(address)
+    }
+}
+
+/// A Cortex-M core with its memory, which runs whenever it is accessed by the debugger.
+#[derive(Debug)]
+pub(super) struct VirtualSystem {
+    cpu: Cpu,
+    bus: SystemBus,
+    /// The address of the vector table after reset.
+    boot_address: u32,
+
+    halted: bool,
+    lockup: bool,
+    /// The core is held in reset by the nRESET pin.
+    reset_held: bool,
+    retired: bool,
+    was_reset: bool,
+}
+
+impl VirtualSystem {
+    /// Creates the system for the first core of `target` and powers it on.
+    pub fn new(target: &Target) -> Self {
+        let core_type = target
+            .cores
+            .first()
+            .map(|core| core.core_type)
+            .unwrap_or(CoreType::Armv7em);
+        let memory = Memory::new(target);
+
+        // Without a region at address zero, the boot memory is assumed to be aliased there.
+        let boot_address = if memory.is_mapped(0) {
+            0
+        } else {
+            target
+                .memory_map
+                .iter()
+                .find_map(|region| match region {
+                    MemoryRegion::Nvm(nvm) if nvm.is_boot_memory => Some(nvm.range.start as u32),
+                    _ => None,
+                })
+                .unwrap_or(0)
+        };
+
+        let mut system = Self {
+            cpu: Cpu::default(),
+            bus: SystemBus {
+                memory,
+                scs: Scs::new(core_type),
+            },
+            boot_address,
+            halted: false,
+            lockup: false,
+            reset_held: false,
+            retired: false,
+            was_reset: false,
+        };
+        system.reset();
+
+        system
+    }
+
+    /// Resets the core, as done by a system reset or the nRESET pin. The debug registers are
+    /// not affected.
+    pub fn reset(&mut self) {
+        let vector_table = self.boot_address;
+        let stack_pointer = self.bus.read_u32(vector_table);
+        let reset_vector = self.bus.read_u32(vector_table.wrapping_add(4));
+
+        self.cpu = Cpu::default();
+        self.cpu.regs[13] = stack_pointer & !3;
+        self.cpu.regs[14] = u32::MAX;
+        self.cpu.regs[15] = reset_vector & !1;
+        self.cpu.thumb = reset_vector & 1 != 0;
+        self.bus.scs.vtor = vector_table;
+        self.bus.scs.reset_requested = false;
+
+        self.halted = false;
+        self.lockup = false;
+        self.was_reset = true;
+
+        let scs = &self.bus.scs;
+        if scs.demcr & DEMCR_VC_CORERESET != 0 && scs.dhcsr & DHCSR_C_DEBUGEN != 0 {
+            self.halt(DFSR_VCATCH);
+        }
+    }
+
+    /// Drives the nRESET pin, the core starts executing when it is released.
+    pub fn set_reset(&mut self, asserted: bool) {
+        if self.reset_held && !asserted {
+            self.reset_held = false;
+            self.reset();
+        } else if asserted {
+            self.reset_held = true;
+            self.was_reset = true;
+        }
+    }
+
+    pub fn reset_asserted(&self) -> bool {
+        self.reset_held
+    }
+
+    fn debug_enabled(&self) -> bool {
+        self.bus.scs.dhcsr & DHCSR_C_DEBUGEN != 0
+    }
+
+    fn halt(&mut self, reason: u32) {
+        self.halted = true;
+        self.lockup = false;
+        self.bus.scs.dhcsr |= DHCSR_C_HALT;
+        self.bus.scs.dfsr |= reason;
+    }
+
+    /// Executes a single instruction, unless there is a breakpoint at it.
+    fn step(&mut self) {
+        let debug_enabled = self.debug_enabled();
+        if debug_enabled && self.bus.scs.breakpoint_at(self.cpu.regs[15]) {
+            self.halt(DFSR_BKPT);
+            return;
+        }
+
+        match self.cpu.step(&mut self.bus) {
+            Ok(Step::Executed) => self.retired = true,
+            Ok(Step::Breakpoint) if debug_enabled => self.halt(DFSR_BKPT),
+            // Without exception handling, anything that would escalate to a HardFault locks up.
+            Ok(Step::Breakpoint) => self.lockup = true,
+            Err(fault) => {
+                tracing::debug!("Virtual core locked up: {fault}");
+                self.lockup = true;
+            }
+        }
+
+        if self.bus.scs.reset_requested {
+            self.reset();
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        !self.halted && !self.lockup && !self.reset_held
+    }
+
+    /// Lets the core run for a while, if it is not halted.
+    fn run(&mut self) {
+        for _ in 0..INSTRUCTIONS_PER_ACCESS {
+            if !self.is_running() {
+                break;
+            }
+            self.step();
+        }
+    }
+
+    fn read_dhcsr(&mut self) -> u32 {
+        let mut value = self.bus.scs.dhcsr | DHCSR_S_REGRDY;
+        if self.halted {
+            value |= DHCSR_S_HALT;
+        }
+        if self.lockup {
+            value |= DHCSR_S_LOCKUP;
+        }
+        if self.retired {
+            value |= DHCSR_S_RETIRE_ST;
+        }
+        if self.was_reset {
+            value |= DHCSR_S_RESET_ST;
+        }
+
+        // The sticky bits are cleared by reading, but stay set while the reset is held.
+        self.retired = false;
+        self.was_reset = self.reset_held;
+
+        value
+    }
+
+    fn write_dhcsr(&mut self, value: u32) {
+        if value >> 16 != 0xA05F {
+            return;
+        }
+
+        let control = value & (DHCSR_C_DEBUGEN | DHCSR_C_HALT | DHCSR_C_STEP | DHCSR_C_MASKINTS);
+        self.bus.scs.dhcsr = control;
+
+        if control & DHCSR_C_DEBUGEN == 0 {
+            self.halted = false;
+        } else if control & DHCSR_C_HALT != 0 {
+            if !self.halted && !self.reset_held {
+                self.halt(DFSR_HALTED);
+            }
+        } else if self.halted {
+            self.halted = false;
+            if control & DHCSR_C_STEP != 0 {
+                // A step which faults halts as well, like it would after the exception entry.
+                self.step();
+                if !self.halted && !self.reset_held {
+                    self.halt(DFSR_HALTED);
+                }
+            }
+        }
+    }
+
+    fn read_core_register(&self, regsel: u32) -> u32 {
+        let cpu = &self.cpu;
+        match regsel {
+            0..=15 => cpu.regs[regsel as usize],
+            16 => cpu.xpsr(),
+            17 => cpu.regs[13],
+            18 => cpu.psp,
+            20 => {
+                (cpu.control as u32) << 24
+                    | (cpu.faultmask as u32) << 16
+                    | (cpu.basepri as u32) << 8
+                    | cpu.primask as u32
+            }
+            // There is no floating point unit.
+            _ => 0,
+        }
+    }
+
+    fn write_core_register(&mut self, regsel: u32, value: u32) {
+        let cpu = &mut self.cpu;
+        match regsel {
+            0..=12 | 14 => cpu.regs[regsel as usize] = value,
+            13 | 17 => cpu.regs[13] = value & !3,
+            15 => cpu.regs[15] = value & !1,
+            16 => cpu.set_xpsr(value),
+            18 => cpu.psp = value & !3,
+            20 => {
+                cpu.primask = value & 1 != 0;
+                cpu.basepri = (value >> 8) as u8;
+                cpu.faultmask = (value >> 16) & 1 != 0;
+                cpu.control = (value >> 24) as u8 & 0b101;
+            }
+            _ => {}
+        }
+    }
+
+    /// Transfers a core register between the core and the DCRDR.
+    fn write_dcrsr(&mut self, value: u32) {
+        let regsel = value & 0x7F;
+        if value & (1 << 16) != 0 {
+            self.write_core_register(regsel, self.bus.scs.dcrdr);
+        } else {
+            self.bus.scs.dcrdr = self.read_core_register(regsel);
+        }
+    }
+
+    /// Reads memory on behalf of the debugger.
+    pub fn read(&mut self, address: u32, data: &mut [u8]) {
+        self.run();
+
+        if PPB.contains(&address) {
+            for (offset, chunk) in data.chunks_mut(4).enumerate() {
+                let address = address.wrapping_add(4 * offset as u32);
+                match address {
+                    DHCSR if chunk.len() == 4 => {
+                        chunk.copy_from_slice(&self.read_dhcsr().to_le_bytes())
+                    }
+                    _ => self.bus.read(address, chunk),
+                }
+            }
+        } else {
+            self.bus.read(address, data);
+        }
+    }
+
+    /// Writes memory on behalf of the debugger.
+    pub fn write(&mut self, address: u32, data: &[u8]) {
+        self.run();
+
+        if PPB.contains(&address) {
+            for (offset, chunk) in data.chunks(4).enumerate() {
+                let address = address.wrapping_add(4 * offset as u32);
+                match address {
+                    DHCSR if chunk.len() == 4 => {
+                        self.write_dhcsr(u32::from_le_bytes(chunk.try_into().unwrap()))
+                    }
+                    DCRSR if chunk.len() == 4 => {
+                        self.write_dcrsr(u32::from_le_bytes(chunk.try_into().unwrap()))
+                    }
+                    _ => self.bus.write(address, chunk),
+                }
+            }
+        } else {
+            self.bus.write(address, data);
+        }
+
+        if self.bus.scs.reset_requested {
+            self.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn system() -> VirtualSystem {
+        let target = crate::config::get_target_by_name("nrf52833_xxAA").unwrap();
+        VirtualSystem::new(&target)
+    }
+
+    fn read_word(system: &mut VirtualSystem, address: u32) -> u32 {
+        let mut data = [0; 4];
+        system.read(address, &mut data);
+        u32::from_le_bytes(data)
+    }
+
+    fn write_word(system: &mut VirtualSystem, address: u32, value: u32) {
+        system.write(address, &value.to_le_bytes());
+    }
+
+    #[test]
+    fn blank_flash_locks_up() {
+        let mut system = system();
+
+        assert_eq!(read_word(&mut system, 0), 0xFFFF_FFFF);
+        assert_ne!(read_word(&mut system, DHCSR) & DHCSR_S_LOCKUP, 0);
+    }
+
+    #[test]
+    fn reset_catch_halts_at_reset_vector() {
+        let mut system = system();
+
+        // A vector table and an endless loop at 0x100.
+        system.write(0, &0x2000_1000u32.to_le_bytes());
+        system.write(4, &0x101u32.to_le_bytes());
+        system.write(0x100, &0xE7FEu16.to_le_bytes());
+
+        write_word(&mut system, DHCSR, 0xA05F_0001);
+        write_word(&mut system, DEMCR, DEMCR_VC_CORERESET);
+        write_word(&mut system, AIRCR, 0x05FA_0004);
+
+        let dhcsr = read_word(&mut system, DHCSR);
+        assert_ne!(dhcsr & DHCSR_S_HALT, 0);
+        assert_ne!(dhcsr & DHCSR_S_RESET_ST, 0);
+        assert_eq!(read_word(&mut system, DHCSR) & DHCSR_S_RESET_ST, 0);
+        assert_eq!(read_word(&mut system, DFSR) & DFSR_VCATCH, DFSR_VCATCH);
+
+        write_word(&mut system, DCRSR, 15);
+        assert_eq!(read_word(&mut system, DCRDR), 0x100);
+        write_word(&mut system, DCRSR, 13);
+        assert_eq!(read_word(&mut system, DCRDR), 0x2000_1000);
+    }
+}
@@ -0,0 +1,1324 @@
+//! An interpreter for the Thumb instruction set of ARMv6-M and ARMv7-M.
+//!
+//! The integer instructions of the base architecture are implemented. Floating point, DSP and
+//! saturating instructions are not, and neither are exceptions: instructions which would raise
+//! one, like `SVC` or an undefined instruction, stop the execution with a [`Fault`].
+
+/// The memory as seen by the [`Cpu`].
+pub(super) trait Bus {
+    /// Reads `data.len()` bytes starting at `address`.
+    fn read(&mut self, address: u32, data: &mut [u8]);
+
+    /// Writes `data` starting at `address`.
+    fn write(&mut self, address: u32, data: &[u8]);
+
+    /// Returns `true` if there is memory at `address` to fetch instructions from.
+    fn is_mapped(&self, _address: u32) -> bool {
+        true
+    }
+
+    fn read_u8(&mut self, address: u32) -> u8 {
+        let mut data = [0; 1];
+        self.read(address, &mut data);
+        data[0]
+    }
+
+    fn read_u16(&mut self, address: u32) -> u16 {
+        let mut data = [0; 2];
+        self.read(address, &mut data);
+        u16::from_le_bytes(data)
+    }
+
+    fn read_u32(&mut self, address: u32) -> u32 {
+        let mut data = [0; 4];
+        self.read(address, &mut data);
+        u32::from_le_bytes(data)
+    }
+}
+
+/// The result of executing a single instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Step {
+    /// The instruction was executed.
+    Executed,
+    /// The instruction is a `BKPT`, it was not executed.
+    Breakpoint,
+}
+
+/// An instruction which can't be executed without raising an exception.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error, docsplay::Display)]
+pub(super) enum Fault {
+    /// The instruction {instruction:#010x} at {address:#010x} is undefined or not supported.
+    Undefined { address: u32, instruction: u32 },
+
+    /// The instruction fetch from {address:#010x} failed, there is no memory at this address.
+    Fetch { address: u32 },
+
+    /// The instruction at {address:#010x} switched to the ARM state by branching to {target:#010x}.
+    InvalidState { address: u32, target: u32 },
+}
+
+/// Shift types, as encoded in the instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shift {
+    Lsl,
+    Lsr,
+    Asr,
+    Ror,
+    Rrx,
+}
+
+const FLAG_N: u32 = 1 << 31;
+const FLAG_Z: u32 = 1 << 30;
+const FLAG_C: u32 = 1 << 29;
+const FLAG_V: u32 = 1 << 28;
+const FLAG_Q: u32 = 1 << 27;
+
+const SP: usize = 13;
+const LR: usize = 14;
+const PC: usize = 15;
+
+/// The register state of the core.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Cpu {
+    /// R0 to R12, SP, LR and the address of the next instruction.
+    pub regs: [u32; 16],
+    /// The process stack pointer. Only the main stack pointer is used by the core.
+    pub psp: u32,
+    /// The N, Z, C, V and Q flags, at their position in the APSR.
+    pub apsr: u32,
+    /// The T bit of the EPSR. The core can't execute instructions while it is clear.
+    pub thumb: bool,
+    /// The IT state of the EPSR.
+    pub itstate: u8,
+    pub primask: bool,
+    pub faultmask: bool,
+    pub basepri: u8,
+    pub control: u8,
+
+    /// The address of the instruction which is executed.
+    current: u32,
+    /// The address of the instruction which is executed next.
+    next: u32,
+}
+
+impl Cpu {
+    /// The value of the xPSR, as read by the debugger.
+    pub fn xpsr(&self) -> u32 {
+        let it = self.itstate as u32;
+        self.apsr | (self.thumb as u32) << 24 | (it & 0b11) << 25 | (it >> 2) << 10
+    }
+
+    pub fn set_xpsr(&mut self, value: u32) {
+        self.apsr = value & (FLAG_N | FLAG_Z | FLAG_C | FLAG_V | FLAG_Q);
+        self.thumb = value & (1 << 24) != 0;
+        self.itstate = ((value >> 25) & 0b11 | ((value >> 10) & 0x3F) << 2) as u8;
+    }
+
+    /// Executes the instruction at the PC.
+    pub fn step(&mut self, bus: &mut impl Bus) -> Result<Step, Fault> {
+        let address = self.regs[PC];
+        if !self.thumb {
+            return Err(Fault::InvalidState {
+                address,
+                target: address,
+            });
+        }
+        if !bus.is_mapped(address) {
+            return Err(Fault::Fetch { address });
+        }
+
+        let hw1 = bus.read_u16(address);
+        let (instruction, size) = if hw1 >> 11 >= 0b11101 {
+            let hw2 = bus.read_u16(address.wrapping_add(2));
+            ((hw1 as u32) << 16 | hw2 as u32, 4)
+        } else {
+            (hw1 as u32, 2)
+        };
+
+        self.current = address;
+        self.next = address.wrapping_add(size);
+
+        let in_it_block = self.in_it_block();
+        let step = if in_it_block && !self.condition_passed(self.itstate >> 4) {
+            Step::Executed
+        } else if size == 2 {
+            self.execute_16(instruction, bus)?
+        } else {
+            self.execute_32(instruction, bus)?
+        };
+
+        if step == Step::Breakpoint {
+            return Ok(step);
+        }
+
+        // The IT instruction sets up the IT state itself.
+        let is_it = size == 2 && instruction & 0xFF00 == 0xBF00 && instruction & 0xF != 0;
+        if in_it_block && !is_it {
+            self.advance_it();
+        }
+
+        self.regs[PC] = self.next;
+
+        Ok(step)
+    }
+
+    fn undefined(&self, instruction: u32) -> Fault {
+        Fault::Undefined {
+            address: self.current,
+            instruction,
+        }
+    }
+
+    fn in_it_block(&self) -> bool {
+        self.itstate & 0xF != 0
+    }
+
+    fn advance_it(&mut self) {
+        if self.itstate & 0b111 == 0 {
+            self.itstate = 0;
+        } else {
+            self.itstate = (self.itstate & 0xE0) | ((self.itstate << 1) & 0x1F);
+        }
+    }
+
+    fn flag(&self, flag: u32) -> bool {
+        self.apsr & flag != 0
+    }
+
+    fn set_flag(&mut self, flag: u32, value: bool) {
+        if value {
+            self.apsr |= flag;
+        } else {
+            self.apsr &= !flag;
+        }
+    }
+
+    fn set_nz(&mut self, result: u32) {
+        self.set_flag(FLAG_N, result & (1 << 31) != 0);
+        self.set_flag(FLAG_Z, result == 0);
+    }
+
+    fn condition_passed(&self, cond: u8) -> bool {
+        let (n, z, c, v) = (
+            self.flag(FLAG_N),
+            self.flag(FLAG_Z),
+            self.flag(FLAG_C),
+            self.flag(FLAG_V),
+        );
+        let result = match cond >> 1 {
+            0b000 => z,
+            0b001 => c,
+            0b010 => n,
+            0b011 => v,
+            0b100 => c && !z,
+            0b101 => n == v,
+            0b110 => n == v && !z,
+            _ => true,
+        };
+
+        // The inverted conditions, except for AL.
+        if cond & 1 == 1 && cond != 0b1111 {
+            !result
+        } else {
+            result
+        }
+    }
+
+    /// Reads a register as operand, the PC reads as the address of the instruction plus 4.
+    fn reg(&self, n: u32) -> u32 {
+        if n as usize == PC {
+            self.current.wrapping_add(4)
+        } else {
+            self.regs[n as usize]
+        }
+    }
+
+    /// The PC aligned to a word, as used for literal addressing.
+    fn align_pc(&self) -> u32 {
+        self.current.wrapping_add(4) & !3
+    }
+
+    fn branch(&mut self, target: u32) {
+        self.next = target & !1;
+    }
+
+    /// Writes the result of a data processing instruction, which branches when written to the PC.
+    fn write_alu(&mut self, n: u32, value: u32) {
+        if n as usize == PC {
+            self.branch(value);
+        } else {
+            self.regs[n as usize] = value;
+        }
+    }
+
+    /// Branches to `target`, which has to be a Thumb address.
+    fn branch_exchange(&mut self, target: u32) -> Result<(), Fault> {
+        // Without exceptions, there are no EXC_RETURN values to branch to.
+        if target & 1 == 0 || target >= 0xF000_0000 {
+            return Err(Fault::InvalidState {
+                address: self.current,
+                target,
+            });
+        }
+
+        self.next = target & !1;
+        Ok(())
+    }
+
+    /// Writes a loaded value, which branches when written to the PC.
+    fn write_load(&mut self, n: u32, value: u32) -> Result<(), Fault> {
+        if n as usize == PC {
+            self.branch_exchange(value)
+        } else {
+            self.regs[n as usize] = value;
+            Ok(())
+        }
+    }
+
+    fn load(&mut self, bus: &mut impl Bus, address: u32, size: u32, signed: bool) -> u32 {
+        match (size, signed) {
+            (1, false) => bus.read_u8(address) as u32,
+            (1, true) => bus.read_u8(address) as i8 as u32,
+            (2, false) => bus.read_u16(address) as u32,
+            (2, true) => bus.read_u16(address) as i16 as u32,
+            _ => bus.read_u32(address),
+        }
+    }
+
+    fn store(&mut self, bus: &mut impl Bus, address: u32, size: u32, value: u32) {
+        bus.write(address, &value.to_le_bytes()[..size as usize]);
+    }
+
+    fn execute_16(&mut self, hw: u32, bus: &mut impl Bus) -> Result<Step, Fault> {
+        let setflags = !self.in_it_block();
+
+        match hw >> 10 {
+            // Shift (immediate), add, subtract, move and compare.
+            0b000000..=0b001111 => {
+                let rd = hw & 7;
+                let rn = (hw >> 3) & 7;
+                match (hw >> 11) & 7 {
+                    op @ 0..=2 => {
+                        let shift = [Shift::Lsl, Shift::Lsr, Shift::Asr][op as usize];
+                        let (shift, amount) = decode_imm_shift(shift, (hw >> 6) & 0x1F);
+                        let (result, carry) =
+                            shift_c(self.reg(rn), shift, amount, self.flag(FLAG_C));
+                        self.regs[rd as usize] = result;
+                        if setflags {
+                            self.set_nz(result);
+                            self.set_flag(FLAG_C, carry);
+                        }
+                    }
+                    3 => {
+                        let operand = if hw & (1 << 10) == 0 {
+                            self.reg((hw >> 6) & 7)
+                        } else {
+                            (hw >> 6) & 7
+                        };
+                        let subtract = hw & (1 << 9) != 0;
+                        self.add_sub(rd, self.reg(rn), operand, subtract, setflags);
+                    }
+                    op => {
+                        let rdn = (hw >> 8) & 7;
+                        let imm = hw & 0xFF;
+                        match op {
+                            4 => {
+                                self.regs[rdn as usize] = imm;
+                                if setflags {
+                                    self.set_nz(imm);
+                                }
+                            }
+                            5 => self.compare(self.reg(rdn), imm, true),
+                            6 => self.add_sub(rdn, self.reg(rdn), imm, false, setflags),
+                            _ => self.add_sub(rdn, self.reg(rdn), imm, true, setflags),
+                        }
+                    }
+                }
+            }
+            // Data processing.
+            0b010000 => {
+                let rdn = hw & 7;
+                let rm = (hw >> 3) & 7;
+                let (a, b) = (self.reg(rdn), self.reg(rm));
+                let carry = self.flag(FLAG_C);
+                match (hw >> 6) & 0xF {
+                    0b0000 => self.logical(Some(rdn), a & b, carry, setflags),
+                    0b0001 => self.logical(Some(rdn), a ^ b, carry, setflags),
+                    op @ (0b0010 | 0b0011 | 0b0100 | 0b0111) => {
+                        let shift = match op {
+                            0b0010 => Shift::Lsl,
+                            0b0011 => Shift::Lsr,
+                            0b0100 => Shift::Asr,
+                            _ => Shift::Ror,
+                        };
+                        let (result, carry) = shift_c(a, shift, b & 0xFF, carry);
+                        self.logical(Some(rdn), result, carry, setflags);
+                    }
+                    0b0101 => self.add_with_carry_to(Some(rdn), a, b, carry, setflags),
+                    0b0110 => self.add_with_carry_to(Some(rdn), a, !b, carry, setflags),
+                    0b1000 => self.logical(None, a & b, carry, true),
+                    0b1001 => self.add_sub(rdn, 0, b, true, setflags),
+                    0b1010 => self.compare(a, b, true),
+                    0b1011 => self.compare(a, b, false),
+                    0b1100 => self.logical(Some(rdn), a | b, carry, setflags),
+                    0b1101 => self.logical(Some(rdn), a.wrapping_mul(b), carry, setflags),
+                    0b1110 => self.logical(Some(rdn), a & !b, carry, setflags),
+                    _ => self.logical(Some(rdn), !b, carry, setflags),
+                }
+            }
+            // Special data instructions and branch and exchange.
+            0b010001 => {
+                let rdn = (hw >> 4) & 8 | hw & 7;
+                let rm = (hw >> 3) & 0xF;
+                match (hw >> 8) & 3 {
+                    0b00 => self.write_alu(rdn, self.reg(rdn).wrapping_add(self.reg(rm))),
+                    0b01 => self.compare(self.reg(rdn), self.reg(rm), true),
+                    0b10 => self.write_alu(rdn, self.reg(rm)),
+                    _ => {
+                        let target = self.reg(rm);
+                        if hw & (1 << 7) != 0 {
+                            self.regs[LR] = self.next | 1;
+                        }
+                        self.branch_exchange(target)?;
+                    }
+                }
+            }
+            // LDR (literal).
+            0b010010 | 0b010011 => {
+                let address = self.align_pc().wrapping_add((hw & 0xFF) << 2);
+                self.regs[((hw >> 8) & 7) as usize] = bus.read_u32(address);
+            }
+            // Load/store single data item, with register offset.
+            0b010100..=0b010111 => {
+                let rt = hw & 7;
+                let address = self
+                    .reg((hw >> 3) & 7)
+                    .wrapping_add(self.reg((hw >> 6) & 7));
+                match (hw >> 9) & 7 {
+                    0b000 => self.store(bus, address, 4, self.reg(rt)),
+                    0b001 => self.store(bus, address, 2, self.reg(rt)),
+                    0b010 => self.store(bus, address, 1, self.reg(rt)),
+                    op => {
+                        let (size, signed) = match op {
+                            0b011 => (1, true),
+                            0b100 => (4, false),
+                            0b101 => (2, false),
+                            0b110 => (1, false),
+                            _ => (2, true),
+                        };
+                        self.regs[rt as usize] = self.load(bus, address, size, signed);
+                    }
+                }
+            }
+            // Load/store single data item, with immediate offset.
+            0b011000..=0b100011 => {
+                let rt = hw & 7;
+                let size = match hw >> 13 {
+                    0b011 if hw & (1 << 12) == 0 => 4,
+                    0b011 => 1,
+                    _ => 2,
+                };
+                let address = self
+                    .reg((hw >> 3) & 7)
+                    .wrapping_add(((hw >> 6) & 0x1F) * size);
+                if hw & (1 << 11) == 0 {
+                    self.store(bus, address, size, self.reg(rt));
+                } else {
+                    self.regs[rt as usize] = self.load(bus, address, size, false);
+                }
+            }
+            // Load/store relative to the SP.
+            0b100100..=0b100111 => {
+                let rt = (hw >> 8) & 7;
+                let address = self.regs[SP].wrapping_add((hw & 0xFF) << 2);
+                if hw & (1 << 11) == 0 {
+                    self.store(bus, address, 4, self.reg(rt));
+                } else {
+                    self.regs[rt as usize] = bus.read_u32(address);
+                }
+            }
+            // ADR and ADD (SP plus immediate).
+            0b101000..=0b101011 => {
+                let base = if hw & (1 << 11) == 0 {
+                    self.align_pc()
+                } else {
+                    self.regs[SP]
+                };
+                self.regs[((hw >> 8) & 7) as usize] = base.wrapping_add((hw & 0xFF) << 2);
+            }
+            0b101100..=0b101111 => return self.execute_misc_16(hw, bus),
+            // STM and LDM.
+            0b110000..=0b110011 => {
+                let rn = (hw >> 8) & 7;
+                let list = hw & 0xFF;
+                let mut address = self.reg(rn);
+                let load = hw & (1 << 11) != 0;
+                for register in (0..8).filter(|r| list & (1 << r) != 0) {
+                    if load {
+                        self.regs[register] = bus.read_u32(address);
+                    } else {
+                        self.store(bus, address, 4, self.regs[register]);
+                    }
+                    address = address.wrapping_add(4);
+                }
+                if !load || list & (1 << rn) == 0 {
+                    self.regs[rn as usize] = address;
+                }
+            }
+            // Conditional branch, UDF and SVC.
+            0b110100..=0b110111 => {
+                let cond = ((hw >> 8) & 0xF) as u8;
+                if cond >= 0b1110 {
+                    return Err(self.undefined(hw));
+                }
+                if self.condition_passed(cond) {
+                    let offset = sign_extend((hw & 0xFF) << 1, 9);
+                    self.branch(self.reg(PC as u32).wrapping_add(offset));
+                }
+            }
+            // Unconditional branch.
+            0b111000 | 0b111001 => {
+                let offset = sign_extend((hw & 0x7FF) << 1, 12);
+                self.branch(self.reg(PC as u32).wrapping_add(offset));
+            }
+            _ => return Err(self.undefined(hw)),
+        }
+
+        Ok(Step::Executed)
+    }
+
+    fn execute_misc_16(&mut self, hw: u32, bus: &mut impl Bus) -> Result<Step, Fault> {
+        match hw & 0xFF00 {
+            // ADD and SUB (SP plus immediate).
+            0xB000 => {
+                let offset = (hw & 0x7F) << 2;
+                self.regs[SP] = if hw & (1 << 7) == 0 {
+                    self.regs[SP].wrapping_add(offset)
+                } else {
+                    self.regs[SP].wrapping_sub(offset)
+                };
+            }
+            // CBZ and CBNZ.
+            0xB100 | 0xB300 | 0xB900 | 0xBB00 => {
+                let offset = (hw >> 3) & 0x40 | ((hw >> 3) & 0x1F) << 1;
+                let nonzero = hw & (1 << 11) != 0;
+                if (self.regs[(hw & 7) as usize] != 0) == nonzero {
+                    self.branch(self.reg(PC as u32).wrapping_add(offset));
+                }
+            }
+            // SXTH, SXTB, UXTH and UXTB.
+            0xB200 => {
+                let value = self.reg((hw >> 3) & 7);
+                self.regs[(hw & 7) as usize] = match (hw >> 6) & 3 {
+                    0 => value as i16 as u32,
+                    1 => value as i8 as u32,
+                    2 => value & 0xFFFF,
+                    _ => value & 0xFF,
+                };
+            }
+            // PUSH.
+            0xB400 | 0xB500 => {
+                let list = hw & 0xFF | (hw & 0x100) << 6;
+                self.push(bus, list);
+            }
+            // CPS.
+            0xB600 if hw & 0xFFEC == 0xB660 => {
+                let disable = hw & (1 << 4) != 0;
+                if hw & 2 != 0 {
+                    self.primask = disable;
+                }
+                if hw & 1 != 0 {
+                    self.faultmask = disable;
+                }
+            }
+            // REV, REV16 and REVSH.
+            0xBA00 => {
+                let value = self.reg((hw >> 3) & 7);
+                let rd = (hw & 7) as usize;
+                self.regs[rd] = match (hw >> 6) & 3 {
+                    0 => value.swap_bytes(),
+                    1 => rev16(value),
+                    3 => (value as u16).swap_bytes() as i16 as u32,
+                    _ => return Err(self.undefined(hw)),
+                };
+            }
+            // POP.
+            0xBC00 | 0xBD00 => {
+                let list = hw & 0xFF | (hw & 0x100) << 7;
+                self.pop(bus, list)?;
+            }
+            0xBE00 => return Ok(Step::Breakpoint),
+            // IT and hints. Interrupts are not modeled, so WFI and WFE don't wait.
+            0xBF00 => {
+                if hw & 0xF != 0 {
+                    self.itstate = (hw & 0xFF) as u8;
+                }
+            }
+            _ => return Err(self.undefined(hw)),
+        }
+
+        Ok(Step::Executed)
+    }
+
+    fn push(&mut self, bus: &mut impl Bus, list: u32) {
+        let mut address = self.regs[SP].wrapping_sub(4 * list.count_ones());
+        self.regs[SP] = address;
+        for register in (0..16).filter(|r| list & (1 << r) != 0) {
+            self.store(bus, address, 4, self.regs[register]);
+            address = address.wrapping_add(4);
+        }
+    }
+
+    fn pop(&mut self, bus: &mut impl Bus, list: u32) -> Result<(), Fault> {
+        let mut address = self.regs[SP];
+        self.regs[SP] = address.wrapping_add(4 * list.count_ones());
+        for register in (0..16).filter(|r| list & (1 << r) != 0) {
+            let value = bus.read_u32(address);
+            self.write_load(register, value)?;
+            address = address.wrapping_add(4);
+        }
+        Ok(())
+    }
+
+    fn execute_32(&mut self, instruction: u32, bus: &mut impl Bus) -> Result<Step, Fault> {
+        let hw1 = instruction >> 16;
+        let hw2 = instruction & 0xFFFF;
+        let op2 = (hw1 >> 4) & 0x7F;
+
+        match (hw1 >> 11) & 3 {
+            0b01 if op2 & 0b1100100 == 0b0000000 => self.load_store_multiple(hw1, hw2, bus)?,
+            0b01 if op2 & 0b1100100 == 0b0000100 => self.load_store_dual(hw1, hw2, bus)?,
+            0b01 if op2 & 0b1100000 == 0b0100000 => {
+                let (type_, imm5) = ((hw2 >> 4) & 3, (hw2 >> 10) & 0x1C | (hw2 >> 6) & 3);
+                let shift = [Shift::Lsl, Shift::Lsr, Shift::Asr, Shift::Ror][type_ as usize];
+                let (shift, amount) = decode_imm_shift(shift, imm5);
+                let (operand, carry) =
+                    shift_c(self.reg(hw2 & 0xF), shift, amount, self.flag(FLAG_C));
+                self.data_processing(instruction, operand, carry)?;
+            }
+            0b10 if hw2 & 0x8000 == 0 && op2 & 0b0100000 == 0 => {
+                let imm12 = (hw1 & (1 << 10)) << 1 | (hw2 >> 4) & 0x700 | hw2 & 0xFF;
+                let (operand, carry) = thumb_expand_imm_c(imm12, self.flag(FLAG_C));
+                self.data_processing(instruction, operand, carry)?;
+            }
+            0b10 if hw2 & 0x8000 == 0 => self.plain_binary_immediate(hw1, hw2)?,
+            0b10 => self.branch_and_misc(hw1, hw2)?,
+            0b11 if op2 & 0b1110001 == 0b0000000 => self.load_store_single(hw1, hw2, bus)?,
+            0b11 if op2 & 0b1100001 == 0b0000001 && op2 & 0b110 != 0b110 => {
+                self.load_store_single(hw1, hw2, bus)?
+            }
+            0b11 if op2 & 0b1110000 == 0b0100000 => self.data_processing_register(hw1, hw2)?,
+            0b11 if op2 & 0b1111000 == 0b0110000 => self.multiply(hw1, hw2)?,
+            0b11 if op2 & 0b1111000 == 0b0111000 => self.long_multiply_divide(hw1, hw2)?,
+            _ => return Err(self.undefined(instruction)),
+        }
+
+        Ok(Step::Executed)
+    }
+
+    fn load_store_multiple(&mut self, hw1: u32, hw2: u32, bus: &mut impl Bus) -> Result<(), Fault> {
+        let rn = hw1 & 0xF;
+        let load = hw1 & (1 << 4) != 0;
+        let writeback = hw1 & (1 << 5) != 0;
+        let list = hw2 & if load { 0xDFFF } else { 0x5FFF };
+        let count = list.count_ones();
+
+        let start = match (hw1 >> 7) & 3 {
+            // Increment after.
+            0b01 => self.reg(rn),
+            // Decrement before.
+            0b10 => self.reg(rn).wrapping_sub(4 * count),
+            _ => return Err(self.undefined(hw1 << 16 | hw2)),
+        };
+        let end = if (hw1 >> 7) & 3 == 0b01 {
+            start.wrapping_add(4 * count)
+        } else {
+            start
+        };
+
+        let mut address = start;
+        let mut loaded_pc = None;
+        for register in (0..16).filter(|r| list & (1 << r) != 0) {
+            if load {
+                let value = bus.read_u32(address);
+                if register == PC as u32 {
+                    loaded_pc = Some(value);
+                } else {
+                    self.regs[register as usize] = value;
+                }
+            } else {
+                self.store(bus, address, 4, self.regs[register as usize]);
+            }
+            address = address.wrapping_add(4);
+        }
+
+        if writeback && !(load && list & (1 << rn) != 0) {
+            self.regs[rn as usize] = end;
+        }
+        if let Some(target) = loaded_pc {
+            self.branch_exchange(target)?;
+        }
+
+        Ok(())
+    }
+
+    fn load_store_dual(&mut self, hw1: u32, hw2: u32, bus: &mut impl Bus) -> Result<(), Fault> {
+        let rn = hw1 & 0xF;
+        let rt = hw2 >> 12;
+        let op1 = (hw1 >> 7) & 3;
+        let op2 = (hw1 >> 4) & 3;
+        let op3 = (hw2 >> 4) & 0xF;
+
+        match (op1, op2) {
+            // STREX. Exclusive accesses always succeed, as there is no other bus master.
+            (0b00, 0b00) => {
+                let address = self.reg(rn).wrapping_add((hw2 & 0xFF) << 2);
+                self.store(bus, address, 4, self.reg(rt));
+                self.regs[((hw2 >> 8) & 0xF) as usize] = 0;
+            }
+            // LDREX.
+            (0b00, 0b01) => {
+                let address = self.reg(rn).wrapping_add((hw2 & 0xFF) << 2);
+                self.regs[rt as usize] = bus.read_u32(address);
+            }
+            // STREXB and STREXH.
+            (0b01, 0b00) if op3 == 0b0100 || op3 == 0b0101 => {
+                let size = if op3 == 0b0100 { 1 } else { 2 };
+                self.store(bus, self.reg(rn), size, self.reg(rt));
+                self.regs[(hw2 & 0xF) as usize] = 0;
+            }
+            // TBB and TBH.
+            (0b01, 0b01) if op3 == 0b0000 || op3 == 0b0001 => {
+                let rm = self.reg(hw2 & 0xF);
+                let halfwords = if op3 == 0b0000 {
+                    bus.read_u8(self.reg(rn).wrapping_add(rm)) as u32
+                } else {
+                    bus.read_u16(self.reg(rn).wrapping_add(rm << 1)) as u32
+                };
+                self.branch(self.reg(PC as u32).wrapping_add(halfwords * 2));
+            }
+            // LDREXB and LDREXH.
+            (0b01, 0b01) if op3 == 0b0100 || op3 == 0b0101 => {
+                let size = if op3 == 0b0100 { 1 } else { 2 };
+                self.regs[rt as usize] = self.load(bus, self.reg(rn), size, false);
+            }
+            // STRD and LDRD.
+            (0b00 | 0b01, 0b10 | 0b11) | (0b10 | 0b11, _) => {
+                let rt2 = (hw2 >> 8) & 0xF;
+                let (index, add) = (hw1 & (1 << 8) != 0, hw1 & (1 << 7) != 0);
+                let writeback = hw1 & (1 << 5) != 0;
+                let imm = (hw2 & 0xFF) << 2;
+
+                let base = if rn == PC as u32 {
+                    self.align_pc()
+                } else {
+                    self.reg(rn)
+                };
+                let offset = if add {
+                    base.wrapping_add(imm)
+                } else {
+                    base.wrapping_sub(imm)
+                };
+                let address = if index { offset } else { base };
+
+                if hw1 & (1 << 4) != 0 {
+                    self.regs[rt as usize] = bus.read_u32(address);
+                    self.regs[rt2 as usize] = bus.read_u32(address.wrapping_add(4));
+                } else {
+                    self.store(bus, address, 4, self.reg(rt));
+                    self.store(bus, address.wrapping_add(4), 4, self.reg(rt2));
+                }
+                if writeback {
+                    self.regs[rn as usize] = offset;
+                }
+            }
+            _ => return Err(self.undefined(hw1 << 16 | hw2)),
+        }
+
+        Ok(())
+    }
+
+    /// Data processing with a modified immediate or shifted register as second operand.
+    fn data_processing(
+        &mut self,
+        instruction: u32,
+        operand: u32,
+        carry: bool,
+    ) -> Result<(), Fault> {
+        let hw1 = instruction >> 16;
+        let rn = hw1 & 0xF;
+        let rd = (instruction >> 8) & 0xF;
+        let setflags = hw1 & (1 << 4) != 0;
+        let test = rd == PC as u32 && setflags;
+        let a = self.reg(rn);
+        let c = self.flag(FLAG_C);
+
+        match (hw1 >> 5) & 0xF {
+            0b0000 if test => self.logical(None, a & operand, carry, true),
+            0b0000 => self.logical(Some(rd), a & operand, carry, setflags),
+            0b0001 => self.logical(Some(rd), a & !operand, carry, setflags),
+            0b0010 if rn == PC as u32 => self.logical(Some(rd), operand, carry, setflags),
+            0b0010 => self.logical(Some(rd), a | operand, carry, setflags),
+            0b0011 if rn == PC as u32 => self.logical(Some(rd), !operand, carry, setflags),
+            0b0011 => self.logical(Some(rd), a | !operand, carry, setflags),
+            0b0100 if test => self.logical(None, a ^ operand, carry, true),
+            0b0100 => self.logical(Some(rd), a ^ operand, carry, setflags),
+            0b1000 if test => self.compare(a, operand, false),
+            0b1000 => self.add_with_carry_to(Some(rd), a, operand, false, setflags),
+            0b1010 => self.add_with_carry_to(Some(rd), a, operand, c, setflags),
+            0b1011 => self.add_with_carry_to(Some(rd), a, !operand, c, setflags),
+            0b1101 if test => self.compare(a, operand, true),
+            0b1101 => self.add_with_carry_to(Some(rd), a, !operand, true, setflags),
+            0b1110 => self.add_with_carry_to(Some(rd), operand, !a, true, setflags),
+            _ => return Err(self.undefined(instruction)),
+        }
+
+        Ok(())
+    }
+
+    fn plain_binary_immediate(&mut self, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let rn = hw1 & 0xF;
+        let rd = ((hw2 >> 8) & 0xF) as usize;
+        let imm12 = (hw1 & (1 << 10)) << 1 | (hw2 >> 4) & 0x700 | hw2 & 0xFF;
+        let imm16 = (hw1 & 0xF) << 12 | imm12;
+        let lsb = (hw2 >> 10) & 0x1C | (hw2 >> 6) & 3;
+        let width = (hw2 & 0x1F) + 1;
+
+        match (hw1 >> 4) & 0x1F {
+            // ADDW and ADR.
+            0b00000 => {
+                let base = if rn == PC as u32 {
+                    self.align_pc()
+                } else {
+                    self.reg(rn)
+                };
+                self.regs[rd] = base.wrapping_add(imm12);
+            }
+            // SUBW and ADR.
+            0b01010 => {
+                let base = if rn == PC as u32 {
+                    self.align_pc()
+                } else {
+                    self.reg(rn)
+                };
+                self.regs[rd] = base.wrapping_sub(imm12);
+            }
+            // MOVW.
+            0b00100 => self.regs[rd] = imm16,
+            // MOVT.
+            0b01100 => self.regs[rd] = self.regs[rd] & 0xFFFF | imm16 << 16,
+            // SBFX and UBFX.
+            op @ (0b10100 | 0b11100) => {
+                let field = self.reg(rn) >> lsb;
+                let unused = 32u32.saturating_sub(width);
+                self.regs[rd] = if op == 0b10100 {
+                    (((field << unused) as i32) >> unused) as u32
+                } else {
+                    (field << unused) >> unused
+                };
+            }
+            // BFI and BFC, where the field ends at the msb.
+            0b10110 => {
+                let msb = hw2 & 0x1F;
+                if msb < lsb {
+                    return Err(self.undefined(hw1 << 16 | hw2));
+                }
+                let mask = (u32::MAX >> (31 - msb + lsb)) << lsb;
+                let value = if rn == PC as u32 {
+                    0
+                } else {
+                    self.reg(rn) << lsb
+                };
+                self.regs[rd] = self.regs[rd] & !mask | value & mask;
+            }
+            _ => return Err(self.undefined(hw1 << 16 | hw2)),
+        }
+
+        Ok(())
+    }
+
+    fn branch_and_misc(&mut self, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let op = (hw1 >> 4) & 0x7F;
+        let s = (hw1 >> 10) & 1;
+        let j1 = (hw2 >> 13) & 1;
+        let j2 = (hw2 >> 11) & 1;
+
+        match (hw2 >> 12) & 0b101 {
+            // Conditional branch.
+            0b000 if op & 0b0111000 != 0b0111000 => {
+                let offset =
+                    s << 20 | j2 << 19 | j1 << 18 | (hw1 & 0x3F) << 12 | (hw2 & 0x7FF) << 1;
+                if self.condition_passed(((hw1 >> 6) & 0xF) as u8) {
+                    self.branch(self.reg(PC as u32).wrapping_add(sign_extend(offset, 21)));
+                }
+            }
+            // MSR.
+            0b000 if op & 0b1111110 == 0b0111000 => {
+                let value = self.reg(hw1 & 0xF);
+                match hw2 & 0xFF {
+                    0..=7 if hw2 & (1 << 11) != 0 => {
+                        self.apsr = value & (FLAG_N | FLAG_Z | FLAG_C | FLAG_V | FLAG_Q)
+                    }
+                    0..=7 => {}
+                    8 => self.regs[SP] = value & !3,
+                    9 => self.psp = value & !3,
+                    16 => self.primask = value & 1 != 0,
+                    17 => self.basepri = value as u8,
+                    18 if value as u8 != 0 && (value as u8) < self.basepri || self.basepri == 0 => {
+                        self.basepri = value as u8
+                    }
+                    18 => {}
+                    19 => self.faultmask = value & 1 != 0,
+                    20 => self.control = value as u8 & 0b101,
+                    _ => return Err(self.undefined(hw1 << 16 | hw2)),
+                }
+            }
+            // Hints and memory barriers, which have no effect without caches and interrupts.
+            0b000 if op == 0b0111010 || op == 0b0111011 => {}
+            // MRS.
+            0b000 if op & 0b1111110 == 0b0111110 => {
+                let value = match hw2 & 0xFF {
+                    0..=7 => self.apsr,
+                    8 => self.regs[SP],
+                    9 => self.psp,
+                    16 => self.primask as u32,
+                    17 | 18 => self.basepri as u32,
+                    19 => self.faultmask as u32,
+                    20 => self.control as u32,
+                    _ => return Err(self.undefined(hw1 << 16 | hw2)),
+                };
+                self.regs[((hw2 >> 8) & 0xF) as usize] = value;
+            }
+            // Unconditional branch and BL.
+            0b001 | 0b101 => {
+                let i1 = !(j1 ^ s) & 1;
+                let i2 = !(j2 ^ s) & 1;
+                let offset =
+                    s << 24 | i1 << 23 | i2 << 22 | (hw1 & 0x3FF) << 12 | (hw2 & 0x7FF) << 1;
+                if hw2 & (1 << 14) != 0 {
+                    self.regs[LR] = self.next | 1;
+                }
+                self.branch(self.reg(PC as u32).wrapping_add(sign_extend(offset, 25)));
+            }
+            _ => return Err(self.undefined(hw1 << 16 | hw2)),
+        }
+
+        Ok(())
+    }
+
+    fn load_store_single(&mut self, hw1: u32, hw2: u32, bus: &mut impl Bus) -> Result<(), Fault> {
+        let rn = hw1 & 0xF;
+        let rt = hw2 >> 12;
+        let load = hw1 & (1 << 4) != 0;
+        let size = 1 << ((hw1 >> 5) & 3);
+        let signed = load && hw1 & (1 << 8) != 0;
+
+        let (address, writeback) = if load && rn == PC as u32 {
+            let imm = hw2 & 0xFFF;
+            let address = if hw1 & (1 << 7) != 0 {
+                self.align_pc().wrapping_add(imm)
+            } else {
+                self.align_pc().wrapping_sub(imm)
+            };
+            (address, None)
+        } else if hw1 & (1 << 7) != 0 {
+            (self.reg(rn).wrapping_add(hw2 & 0xFFF), None)
+        } else if hw2 & (1 << 11) != 0 {
+            let (index, add, writeback) = (
+                hw2 & (1 << 10) != 0,
+                hw2 & (1 << 9) != 0,
+                hw2 & (1 << 8) != 0,
+            );
+            let imm = hw2 & 0xFF;
+            let offset = if add {
+                self.reg(rn).wrapping_add(imm)
+            } else {
+                self.reg(rn).wrapping_sub(imm)
+            };
+            let address = if index { offset } else { self.reg(rn) };
+            (address, writeback.then_some(offset))
+        } else if hw2 & 0x0FC0 == 0 {
+            let offset = self.reg(hw2 & 0xF) << ((hw2 >> 4) & 3);
+            (self.reg(rn).wrapping_add(offset), None)
+        } else {
+            return Err(self.undefined(hw1 << 16 | hw2));
+        };
+
+        if load {
+            // Loads of bytes and halfwords into the PC are preload hints.
+            if rt == PC as u32 && size != 4 {
+                return Ok(());
+            }
+            let value = self.load(bus, address, size, signed);
+            if let Some(offset) = writeback {
+                self.regs[rn as usize] = offset;
+            }
+            self.write_load(rt, value)?;
+        } else {
+            self.store(bus, address, size, self.reg(rt));
+            if let Some(offset) = writeback {
+                self.regs[rn as usize] = offset;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn data_processing_register(&mut self, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let rn = hw1 & 0xF;
+        let rd = (hw2 >> 8) & 0xF;
+        let rm = hw2 & 0xF;
+        let op1 = (hw1 >> 4) & 0xF;
+        let op2 = (hw2 >> 4) & 0xF;
+
+        match (op1, op2) {
+            // LSL, LSR, ASR and ROR (register).
+            (0b0000..=0b0111, 0b0000) => {
+                let shift = [Shift::Lsl, Shift::Lsr, Shift::Asr, Shift::Ror][(op1 >> 1) as usize];
+                let (result, carry) =
+                    shift_c(self.reg(rn), shift, self.reg(rm) & 0xFF, self.flag(FLAG_C));
+                self.logical(Some(rd), result, carry, op1 & 1 != 0);
+            }
+            // SXTAH, UXTAH, SXTAB and UXTAB, and their variants without addition.
+            (0b0000 | 0b0001 | 0b0100 | 0b0101, 0b1000..=0b1011) => {
+                let rotated = self.reg(rm).rotate_right((op2 & 3) * 8);
+                let extended = match op1 {
+                    0b0000 => rotated as i16 as u32,
+                    0b0001 => rotated & 0xFFFF,
+                    0b0100 => rotated as i8 as u32,
+                    _ => rotated & 0xFF,
+                };
+                let base = if rn == PC as u32 { 0 } else { self.reg(rn) };
+                self.regs[rd as usize] = base.wrapping_add(extended);
+            }
+            // REV, REV16, RBIT, REVSH and CLZ.
+            (0b1000..=0b1011, 0b1000..=0b1011) => {
+                let value = self.reg(rm);
+                self.regs[rd as usize] = match (op1 & 3, op2 & 3) {
+                    (0b01, 0b00) => value.swap_bytes(),
+                    (0b01, 0b01) => rev16(value),
+                    (0b01, 0b10) => value.reverse_bits(),
+                    (0b01, 0b11) => (value as u16).swap_bytes() as i16 as u32,
+                    (0b11, 0b00) => value.leading_zeros(),
+                    _ => return Err(self.undefined(hw1 << 16 | hw2)),
+                };
+            }
+            _ => return Err(self.undefined(hw1 << 16 | hw2)),
+        }
+
+        Ok(())
+    }
+
+    fn multiply(&mut self, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let ra = hw2 >> 12;
+        let product = self.reg(hw1 & 0xF).wrapping_mul(self.reg(hw2 & 0xF));
+        let result = match ((hw1 >> 4) & 7, (hw2 >> 4) & 3) {
+            (0b000, 0b00) if ra == PC as u32 => product,
+            (0b000, 0b00) => self.reg(ra).wrapping_add(product),
+            (0b000, 0b01) => self.reg(ra).wrapping_sub(product),
+            _ => return Err(self.undefined(hw1 << 16 | hw2)),
+        };
+        self.regs[((hw2 >> 8) & 0xF) as usize] = result;
+
+        Ok(())
+    }
+
+    fn long_multiply_divide(&mut self, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let n = self.reg(hw1 & 0xF);
+        let m = self.reg(hw2 & 0xF);
+        let rd_lo = (hw2 >> 12) as usize;
+        let rd_hi = ((hw2 >> 8) & 0xF) as usize;
+        let accumulator = (self.regs[rd_hi] as u64) << 32 | self.regs[rd_lo] as u64;
+
+        let result = match ((hw1 >> 4) & 7, (hw2 >> 4) & 0xF) {
+            // SDIV and UDIV, division by zero returns zero as long as DIV_0_TRP is clear.
+            // The overflowing 0x8000_0000 / -1 returns 0x8000_0000.
+            (0b001, 0b1111) => {
+                self.regs[rd_hi] = if m == 0 {
+                    0
+                } else {
+                    (n as i32).wrapping_div(m as i32) as u32
+                };
+                return Ok(());
+            }
+            (0b011, 0b1111) => {
+                self.regs[rd_hi] = n.checked_div(m).unwrap_or(0);
+                return Ok(());
+            }
+            (0b000, 0b0000) => (n as i32 as i64).wrapping_mul(m as i32 as i64) as u64,
+            (0b010, 0b0000) => (n as u64).wrapping_mul(m as u64),
+            (0b100, 0b0000) => {
+                ((n as i32 as i64).wrapping_mul(m as i32 as i64) as u64).wrapping_add(accumulator)
+            }
+            (0b110, 0b0000) => (n as u64).wrapping_mul(m as u64).wrapping_add(accumulator),
+            _ => return Err(self.undefined(hw1 << 16 | hw2)),
+        };
+        self.regs[rd_lo] = result as u32;
+        self.regs[rd_hi] = (result >> 32) as u32;
+
+        Ok(())
+    }
+
+    /// Writes the result of a logical operation, and updates N, Z and C.
+    fn logical(&mut self, rd: Option<u32>, result: u32, carry: bool, setflags: bool) {
+        if let Some(rd) = rd {
+            self.write_alu(rd, result);
+        }
+        if setflags {
+            self.set_nz(result);
+            self.set_flag(FLAG_C, carry);
+        }
+    }
+
+    fn add_sub(&mut self, rd: u32, a: u32, b: u32, subtract: bool, setflags: bool) {
+        if subtract {
+            self.add_with_carry_to(Some(rd), a, !b, true, setflags);
+        } else {
+            self.add_with_carry_to(Some(rd), a, b, false, setflags);
+        }
+    }
+
+    /// Updates the flags for `a - b`, or `a + b` for CMN.
+    fn compare(&mut self, a: u32, b: u32, subtract: bool) {
+        if subtract {
+            self.add_with_carry_to(None, a, !b, true, true);
+        } else {
+            self.add_with_carry_to(None, a, b, false, true);
+        }
+    }
+
+    fn add_with_carry_to(&mut self, rd: Option<u32>, a: u32, b: u32, carry: bool, setflags: bool) {
+        let (result, carry, overflow) = add_with_carry(a, b, carry);
+        if let Some(rd) = rd {
+            self.write_alu(rd, result);
+        }
+        if setflags {
+            self.set_nz(result);
+            self.set_flag(FLAG_C, carry);
+            self.set_flag(FLAG_V, overflow);
+        }
+    }
+}
+
+fn sign_extend(value: u32, bits: u32) -> u32 {
+    let unused = 32 - bits;
+    (((value << unused) as i32) >> unused) as u32
+}
+
+fn rev16(value: u32) -> u32 {
+    (value & 0xFF00_FF00) >> 8 | (value & 0x00FF_00FF) << 8
+}
+
+fn add_with_carry(a: u32, b: u32, carry: bool) -> (u32, bool, bool) {
+    let unsigned = a as u64 + b as u64 + carry as u64;
+    let signed = a as i32 as i64 + b as i32 as i64 + carry as i64;
+    let result = unsigned as u32;
+
+    (
+        result,
+        unsigned != result as u64,
+        signed != result as i32 as i64,
+    )
+}
+
+fn decode_imm_shift(shift: Shift, imm5: u32) -> (Shift, u32) {
+    match shift {
+        Shift::Lsr | Shift::Asr if imm5 == 0 => (shift, 32),
+        Shift::Ror if imm5 == 0 => (Shift::Rrx, 1),
+        _ => (shift, imm5),
+    }
+}
+
+fn shift_c(value: u32, shift: Shift, amount: u32, carry: bool) -> (u32, bool) {
+    if amount == 0 {
+        return (value, carry);
+    }
+
+    match shift {
+        Shift::Lsl if amount > 32 => (0, false),
+        Shift::Lsl => (
+            value.checked_shl(amount).unwrap_or(0),
+            value >> (32 - amount) & 1 != 0,
+        ),
+        Shift::Lsr if amount > 32 => (0, false),
+        Shift::Lsr => (
+            value.checked_shr(amount).unwrap_or(0),
+            value >> (amount - 1) & 1 != 0,
+        ),
+        Shift::Asr => {
+            let amount = amount.min(32);
+            let result = ((value as i32) >> amount.min(31)) as u32;
+            (result, (value as i32 >> (amount - 1)) & 1 != 0)
+        }
+        Shift::Ror => {
+            let result = value.rotate_right(amount % 32);
+            (result, result & (1 << 31) != 0)
+        }
+        Shift::Rrx => (value >> 1 | (carry as u32) << 31, value & 1 != 0),
+    }
+}
+
+fn thumb_expand_imm_c(imm12: u32, carry: bool) -> (u32, bool) {
+    let imm8 = imm12 & 0xFF;
+    if imm12 >> 10 == 0 {
+        let value = match (imm12 >> 8) & 3 {
+            0b00 => imm8,
+            0b01 => imm8 << 16 | imm8,
+            0b10 => imm8 << 24 | imm8 << 8,
+            _ => imm8 << 24 | imm8 << 16 | imm8 << 8 | imm8,
+        };
+        (value, carry)
+    } else {
+        let value = (0x80 | imm12 & 0x7F).rotate_right(imm12 >> 7);
+        (value, value & (1 << 31) != 0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A flat memory at address zero.
+    struct Memory(Vec<u8>);
+
+    impl Bus for Memory {
+        fn read(&mut self, address: u32, data: &mut [u8]) {
+            let address = address as usize;
+            data.copy_from_slice(&self.0[address..address + data.len()]);
+        }
+
+        fn write(&mut self, address: u32, data: &[u8]) {
+            let address = address as usize;
+            self.0[address..address + data.len()].copy_from_slice(data);
+        }
+    }
+
+    /// Runs the halfwords `code` at address zero, until the first `BKPT`.
+    fn run(code: &[u16]) -> Cpu {
+        let mut memory = Memory(vec![0; 0x1000]);
+        for (index, halfword) in code.iter().enumerate() {
+            memory.write(index as u32 * 2, &halfword.to_le_bytes());
+        }
+
+        let mut cpu = Cpu {
+            thumb: true,
+            ..Default::default()
+        };
+        cpu.regs[SP] = 0x1000;
+
+        for _ in 0..1000 {
+            if cpu.step(&mut memory).unwrap() == Step::Breakpoint {
+                return cpu;
+            }
+        }
+        panic!("The code did not reach a breakpoint");
+    }
+
+    #[test]
+    fn count_down_loop() {
+        let cpu = run(&[
+            0x200A, // movs r0, #10
+            0x2100, // movs r1, #0
+            0x1809, // loop: adds r1, r1, r0
+            0x3801, // subs r0, #1
+            0xD1FC, // bne loop
+            0xBE00, // bkpt
+        ]);
+
+        assert_eq!(cpu.regs[0], 0);
+        assert_eq!(cpu.regs[1], 55);
+        assert!(cpu.flag(FLAG_Z));
+    }
+
+    #[test]
+    fn call_with_stack_frame() {
+        let cpu = run(&[
+            0xF000, 0xF802, // bl function
+            0xBE00, // bkpt
+            0xBF00, // nop
+            0xB510, // function: push {r4, lr}
+            0xF240, 0x1423, // movw r4, #0x123
+            0xF2C4, 0x5467, // movt r4, #0x4567
+            0x0020, // movs r0, r4
+            0xBD10, // pop {r4, pc}
+        ]);
+
+        assert_eq!(cpu.regs[0], 0x4567_0123);
+        assert_eq!(cpu.regs[4], 0);
+        assert_eq!(cpu.regs[SP], 0x1000);
+        assert_eq!(cpu.regs[PC], 4);
+    }
+
+    #[test]
+    fn it_block_and_modified_immediate() {
+        let cpu = run(&[
+            0xF04F, 0x30FF, // mov.w r0, #0xFFFFFFFF
+            0xF110, 0x0F01, // cmn.w r0, #1
+            0xBF0C, // ite eq
+            0x2101, // moveq r1, #1
+            0x2102, // movne r1, #2
+            0xFBB0, 0xF2F1, // udiv r2, r0, r1
+            0xBE00, // bkpt
+        ]);
+
+        assert_eq!(cpu.regs[1], 1);
+        assert_eq!(cpu.regs[2], 0xFFFF_FFFF);
+        assert_eq!(cpu.itstate, 0);
+    }
+
+    #[test]
+    fn signed_division_edge_cases() {
+        let cpu = run(&[
+            0xF04F, 0x4000, // mov.w r0, #0x80000000
+            0xF04F, 0x31FF, // mov.w r1, #0xFFFFFFFF
+            0xFB90, 0xF2F1, // sdiv r2, r0, r1
+            0x2300, // movs r3, #0
+            0xFB90, 0xF4F3, // sdiv r4, r0, r3
+            0xBE00, // bkpt
+        ]);
+
+        assert_eq!(cpu.regs[0], 0x8000_0000);
+        assert_eq!(cpu.regs[2], 0x8000_0000);
+        assert_eq!(cpu.regs[4], 0);
+    }
+
+    #[test]
+    fn loads_and_stores() {
+        let cpu = run(&[
+            0x4804, // ldr r0, [pc, #16]
+            0xF44F, 0x7100, // mov.w r1, #0x200
+            0xF841, 0x0F04, // str r0, [r1, #4]!
+            0x880A, // ldrh r2, [r1]
+            0xF991, 0x3003, // ldrsb.w r3, [r1, #3]
+            0xBE00, // bkpt
+            0xBF00, // nop
+            0x5678, 0x9A34, // literal: 0x9A345678
+        ]);
+
+        assert_eq!(cpu.regs[0], 0x9A34_5678);
+        assert_eq!(cpu.regs[1], 0x204);
+        assert_eq!(cpu.regs[2], 0x5678);
+        assert_eq!(cpu.regs[3], 0xFFFF_FF9A);
+    }
+
+    #[test]
+    fn branch_to_arm_state_faults() {
+        let mut memory = Memory(vec![0; 0x100]);
+        memory.write(0, &0x4700u16.to_le_bytes()); // bx r0
+
+        let mut cpu = Cpu {
+            thumb: true,
+            ..Default::default()
+        };
+        cpu.regs[0] = 0x80;
+
+        assert_eq!(
+            cpu.step(&mut memory),
+            Err(Fault::InvalidState {
+                address: 0,
+                target: 0x80
+            })
+        );
+    }
+}
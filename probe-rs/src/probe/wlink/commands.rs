@@ -319,3 +319,26 @@ impl WchLinkCommand for UnprotectFlash {
         vec![0x02]
     }
 }
+
+/// Switch the 3.3V or 5V supply of the target on or off
+#[derive(Debug)]
+pub enum SetPower {
+    Enable3V3,
+    Disable3V3,
+    Enable5V,
+    Disable5V,
+}
+
+impl WchLinkCommand for SetPower {
+    const COMMAND_ID: CommandId = CommandId::Control;
+    type Response = ();
+
+    fn payload(&self) -> Vec<u8> {
+        match self {
+            SetPower::Enable3V3 => vec![0x09],
+            SetPower::Disable3V3 => vec![0x0A],
+            SetPower::Enable5V => vec![0x0B],
+            SetPower::Disable5V => vec![0x0C],
+        }
+    }
+}
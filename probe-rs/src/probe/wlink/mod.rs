@@ -15,8 +15,8 @@ use super::JTAGAccess;
 use crate::{
     architecture::riscv::communication_interface::{RiscvCommunicationInterface, RiscvError},
     probe::{
-        DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector, JtagChainItem,
-        ProbeCreationError, ProbeFactory, WireProtocol,
+        power::TargetPowerVoltage, DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector,
+        JtagChainItem, ProbeCreationError, ProbeFactory, WireProtocol,
     },
 };
 
@@ -362,6 +362,10 @@ impl DebugProbe for WchLink {
         self
     }
 
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        Some(self)
+    }
+
     fn has_riscv_interface(&self) -> bool {
         true
     }
@@ -382,6 +386,37 @@ impl DebugProbe for WchLink {
     ) -> Result<(), DebugProbeError> {
         Ok(())
     }
+
+    fn target_power_voltages(&self) -> Vec<TargetPowerVoltage> {
+        // Only the WCH-LinkE can switch its supply outputs.
+        if self.variant == WchLinkVariant::ECh32v305 {
+            vec![TargetPowerVoltage::V3_3, TargetPowerVoltage::V5]
+        } else {
+            vec![]
+        }
+    }
+
+    fn set_target_power(
+        &mut self,
+        on: bool,
+        voltage: TargetPowerVoltage,
+    ) -> Result<(), DebugProbeError> {
+        if self.variant != WchLinkVariant::ECh32v305 {
+            return Err(DebugProbeError::CommandNotSupportedByProbe {
+                command_name: "set_target_power",
+            });
+        }
+
+        let command = match (voltage, on) {
+            (TargetPowerVoltage::V3_3, true) => commands::SetPower::Enable3V3,
+            (TargetPowerVoltage::V3_3, false) => commands::SetPower::Disable3V3,
+            (TargetPowerVoltage::V5, true) => commands::SetPower::Enable5V,
+            (TargetPowerVoltage::V5, false) => commands::SetPower::Disable5V,
+        };
+        self.device.send_command(command)?;
+
+        Ok(())
+    }
 }
 
 /// Wrap WCH-Link's USB based DMI access as a fake JTAGAccess
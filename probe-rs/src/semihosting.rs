@@ -1,5 +1,6 @@
 use crate::{CoreInterface, Error, RegisterValue};
 use anyhow::{bail, Result};
+use std::num::NonZeroU32;
 
 /// Indicates the operation the target would like the debugger to perform.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -15,6 +16,40 @@ pub enum SemihostingCommand {
     /// The target indicates that it would like to read the command line arguments.
     GetCommandLine(GetCommandLineRequest),
 
+    /// The target would like to open a file on the host (`SYS_OPEN`).
+    Open(OpenRequest),
+
+    /// The target would like to close a previously opened file (`SYS_CLOSE`).
+    Close(CloseRequest),
+
+    /// The target would like to print a character or a string to the debug console
+    /// (`SYS_WRITEC` and `SYS_WRITE0`).
+    WriteConsole(WriteConsoleRequest),
+
+    /// The target would like to write to a previously opened file (`SYS_WRITE`).
+    Write(WriteRequest),
+
+    /// The target would like to read from a previously opened file (`SYS_READ`).
+    Read(ReadRequest),
+
+    /// The target would like to know whether a file handle is connected to an interactive device (`SYS_ISTTY`).
+    IsTty(IsTtyRequest),
+
+    /// The target would like to seek to a position in a previously opened file (`SYS_SEEK`).
+    Seek(SeekRequest),
+
+    /// The target would like to know the length of a previously opened file (`SYS_FLEN`).
+    FileLength(FileLengthRequest),
+
+    /// The target would like to know the execution time, in centiseconds (`SYS_CLOCK`).
+    Clock(ClockRequest),
+
+    /// The target would like to know the host time, in seconds since the Unix epoch (`SYS_TIME`).
+    Time(TimeRequest),
+
+    /// The target would like to know the error number of the last failed host operation (`SYS_ERRNO`).
+    Errno(ErrnoRequest),
+
     /// The target indicated that it would like to run a semihosting operation which we don't support yet.
     Unknown(UnknownCommandDetails),
 }
@@ -91,6 +126,275 @@ impl GetCommandLineRequest {
     }
 }
 
+/// Maps the `SYS_OPEN` mode numbers to the corresponding ISO C `fopen` modes.
+const OPEN_MODES: [&str; 12] = [
+    "r", "rb", "r+", "r+b", "w", "wb", "w+", "w+b", "a", "ab", "a+", "a+b",
+];
+
+/// The longest string accepted from the target by `SYS_WRITE0`.
+const MAX_CONSOLE_STRING_LENGTH: usize = 4096;
+
+/// A request to open a file on the host.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct OpenRequest {
+    path: u32,
+    path_len: u32,
+    mode: u32,
+}
+
+impl OpenRequest {
+    /// Reads the requested path from the target.
+    ///
+    /// The special path `:tt` refers to the console of the host.
+    pub fn path(&self, core: &mut dyn CoreInterface) -> Result<String> {
+        let mut buf = vec![0u8; self.path_len as usize];
+        core.read(self.path as u64, &mut buf)?;
+        Ok(String::from_utf8(buf)?)
+    }
+
+    /// Returns the requested access mode as an ISO C `fopen` mode string,
+    /// or `None` if the target passed an invalid mode.
+    pub fn mode(&self) -> Option<&'static str> {
+        OPEN_MODES.get(self.mode as usize).copied()
+    }
+
+    /// Signals a successful open to the target, passing the handle of the file.
+    /// You have to continue the core manually afterwards.
+    pub fn respond_with_handle(
+        &self,
+        core: &mut dyn CoreInterface,
+        handle: NonZeroU32,
+    ) -> Result<()> {
+        write_status(core, handle.get())
+    }
+}
+
+/// A request to close a file on the host.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct CloseRequest {
+    handle: u32,
+}
+
+impl CloseRequest {
+    /// Returns the handle of the file to close.
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Signals to the target that the file was closed.
+    /// You have to continue the core manually afterwards.
+    pub fn success(&self, core: &mut dyn CoreInterface) -> Result<()> {
+        write_status(core, 0)
+    }
+}
+
+/// A request to print to the debug console of the host.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum WriteConsoleRequest {
+    /// A single character is stored at the address (`SYS_WRITEC`).
+    Char(u32),
+
+    /// A zero-terminated string is stored at the address (`SYS_WRITE0`).
+    String(u32),
+}
+
+impl WriteConsoleRequest {
+    /// Reads the text to print from the target.
+    pub fn read(&self, core: &mut dyn CoreInterface) -> Result<String> {
+        match *self {
+            WriteConsoleRequest::Char(address) => {
+                let mut buf = [0u8];
+                core.read(address as u64, &mut buf)?;
+                Ok(char::from(buf[0]).to_string())
+            }
+            WriteConsoleRequest::String(address) => {
+                let mut text = vec![];
+                while text.len() < MAX_CONSOLE_STRING_LENGTH {
+                    // Read up to the next word boundary only, so we never read beyond the word
+                    // holding the terminator, which may be the last word of a memory region.
+                    let current = address as u64 + text.len() as u64;
+                    let mut chunk = [0u8; 4];
+                    let chunk = &mut chunk[..4 - (current % 4) as usize];
+                    core.read(current, chunk)?;
+                    if let Some(end) = chunk.iter().position(|&byte| byte == 0) {
+                        text.extend_from_slice(&chunk[..end]);
+                        break;
+                    }
+                    text.extend_from_slice(chunk);
+                }
+                Ok(String::from_utf8_lossy(&text).into_owned())
+            }
+        }
+    }
+}
+
+/// A request to write to a file on the host.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct WriteRequest {
+    handle: u32,
+    address: u32,
+    len: u32,
+}
+
+impl WriteRequest {
+    /// Returns the handle of the file to write to.
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Reads the data to write from the target.
+    pub fn read(&self, core: &mut dyn CoreInterface) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; self.len as usize];
+        core.read(self.address as u64, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Signals to the target how many bytes were written.
+    /// You have to continue the core manually afterwards.
+    pub fn respond(&self, core: &mut dyn CoreInterface, bytes_written: u32) -> Result<()> {
+        // The target expects the number of bytes that were *not* written.
+        write_status(core, self.len.saturating_sub(bytes_written))
+    }
+}
+
+/// A request to read from a file on the host.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ReadRequest {
+    handle: u32,
+    address: u32,
+    len: u32,
+}
+
+impl ReadRequest {
+    /// Returns the handle of the file to read from.
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Returns the maximum number of bytes the target would like to read.
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Returns `true` if the target requested to read zero bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Writes the data that was read to the target. Passing less data than
+    /// requested signals the end of the file. You have to continue the core manually afterwards.
+    pub fn respond(&self, core: &mut dyn CoreInterface, data: &[u8]) -> Result<()> {
+        if data.len() > self.len as usize {
+            bail!("buffer not large enough")
+        }
+        core.write_8(self.address as u64, data)?;
+        // The target expects the number of bytes that were *not* read.
+        write_status(core, self.len - data.len() as u32)
+    }
+}
+
+/// A request to check whether a file handle refers to an interactive device.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct IsTtyRequest {
+    handle: u32,
+}
+
+impl IsTtyRequest {
+    /// Returns the handle of the file to check.
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Signals to the target whether the handle is interactive.
+    /// You have to continue the core manually afterwards.
+    pub fn respond(&self, core: &mut dyn CoreInterface, is_tty: bool) -> Result<()> {
+        write_status(core, is_tty as u32)
+    }
+}
+
+/// A request to seek to an absolute position in a file on the host.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct SeekRequest {
+    handle: u32,
+    position: u32,
+}
+
+impl SeekRequest {
+    /// Returns the handle of the file to seek in.
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Returns the position to seek to, in bytes from the start of the file.
+    pub fn position(&self) -> u32 {
+        self.position
+    }
+
+    /// Signals a successful seek to the target.
+    /// You have to continue the core manually afterwards.
+    pub fn success(&self, core: &mut dyn CoreInterface) -> Result<()> {
+        write_status(core, 0)
+    }
+}
+
+/// A request to read the length of a file on the host.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct FileLengthRequest {
+    handle: u32,
+}
+
+impl FileLengthRequest {
+    /// Returns the handle of the file.
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Writes the length of the file to the target.
+    /// You have to continue the core manually afterwards.
+    pub fn respond(&self, core: &mut dyn CoreInterface, len: u32) -> Result<()> {
+        write_status(core, len)
+    }
+}
+
+/// A request to read the execution time of the target.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[non_exhaustive]
+pub struct ClockRequest;
+
+impl ClockRequest {
+    /// Writes the time since the start of the execution, in centiseconds, to the target.
+    /// You have to continue the core manually afterwards.
+    pub fn respond(&self, core: &mut dyn CoreInterface, centiseconds: u32) -> Result<()> {
+        write_status(core, centiseconds)
+    }
+}
+
+/// A request to read the time of the host.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[non_exhaustive]
+pub struct TimeRequest;
+
+impl TimeRequest {
+    /// Writes the number of seconds since the Unix epoch to the target.
+    /// You have to continue the core manually afterwards.
+    pub fn respond(&self, core: &mut dyn CoreInterface, seconds: u32) -> Result<()> {
+        write_status(core, seconds)
+    }
+}
+
+/// A request to read the error number of the last failed host operation.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[non_exhaustive]
+pub struct ErrnoRequest;
+
+impl ErrnoRequest {
+    /// Writes the error number to the target.
+    /// You have to continue the core manually afterwards.
+    pub fn respond(&self, core: &mut dyn CoreInterface, errno: u32) -> Result<()> {
+        write_status(core, errno)
+    }
+}
+
 fn write_status(core: &mut dyn CoreInterface, value: u32) -> Result<()> {
     let reg = core.registers().get_argument_register(0).unwrap();
     core.write_core_reg(reg.into(), RegisterValue::U32(value))?;
@@ -144,7 +448,9 @@ impl Buffer {
 }
 
 /// Decodes a semihosting syscall without running the requested action.
-/// Only supports SYS_EXIT, SYS_EXIT_EXTENDED and SYS_GET_CMDLINE at the moment
+///
+/// Operations that return a value are answered with an error status in advance,
+/// in case the application does not answer the request.
 pub fn decode_semihosting_syscall(
     core: &mut dyn CoreInterface,
     operation: u32,
@@ -153,6 +459,18 @@ pub fn decode_semihosting_syscall(
     // This is defined by the ARM Semihosting Specification:
     // <https://github.com/ARM-software/abi-aa/blob/main/semihosting/semihosting.rst#semihosting-operations>
 
+    const SYS_OPEN: u32 = 0x01;
+    const SYS_CLOSE: u32 = 0x02;
+    const SYS_WRITEC: u32 = 0x03;
+    const SYS_WRITE0: u32 = 0x04;
+    const SYS_WRITE: u32 = 0x05;
+    const SYS_READ: u32 = 0x06;
+    const SYS_ISTTY: u32 = 0x09;
+    const SYS_SEEK: u32 = 0x0A;
+    const SYS_FLEN: u32 = 0x0C;
+    const SYS_CLOCK: u32 = 0x10;
+    const SYS_TIME: u32 = 0x11;
+    const SYS_ERRNO: u32 = 0x13;
     const SYS_GET_CMDLINE: u32 = 0x15;
     const SYS_EXIT: u32 = 0x18;
     const SYS_EXIT_EXTENDED: u32 = 0x20;
@@ -198,6 +516,98 @@ pub fn decode_semihosting_syscall(
                 block_address,
             )?))
         }
+        (SYS_OPEN, block_address) => {
+            let mut block = [0u32; 3];
+            core.read_32(block_address as u64, &mut block)?;
+            write_status(core, u32::MAX)?;
+
+            SemihostingCommand::Open(OpenRequest {
+                path: block[0],
+                mode: block[1],
+                path_len: block[2],
+            })
+        }
+
+        (SYS_CLOSE, block_address) => {
+            let handle = read_handle(core, block_address)?;
+            write_status(core, u32::MAX)?;
+
+            SemihostingCommand::Close(CloseRequest { handle })
+        }
+
+        (SYS_WRITEC, address) => {
+            SemihostingCommand::WriteConsole(WriteConsoleRequest::Char(address))
+        }
+
+        (SYS_WRITE0, address) => {
+            SemihostingCommand::WriteConsole(WriteConsoleRequest::String(address))
+        }
+
+        (SYS_WRITE, block_address) => {
+            let mut block = [0u32; 3];
+            core.read_32(block_address as u64, &mut block)?;
+            // Nothing written, in case the application does not answer this request
+            write_status(core, block[2])?;
+
+            SemihostingCommand::Write(WriteRequest {
+                handle: block[0],
+                address: block[1],
+                len: block[2],
+            })
+        }
+
+        (SYS_READ, block_address) => {
+            let mut block = [0u32; 3];
+            core.read_32(block_address as u64, &mut block)?;
+            write_status(core, u32::MAX)?;
+
+            SemihostingCommand::Read(ReadRequest {
+                handle: block[0],
+                address: block[1],
+                len: block[2],
+            })
+        }
+
+        (SYS_ISTTY, block_address) => {
+            let handle = read_handle(core, block_address)?;
+            write_status(core, u32::MAX)?;
+
+            SemihostingCommand::IsTty(IsTtyRequest { handle })
+        }
+
+        (SYS_SEEK, block_address) => {
+            let mut block = [0u32; 2];
+            core.read_32(block_address as u64, &mut block)?;
+            write_status(core, u32::MAX)?;
+
+            SemihostingCommand::Seek(SeekRequest {
+                handle: block[0],
+                position: block[1],
+            })
+        }
+
+        (SYS_FLEN, block_address) => {
+            let handle = read_handle(core, block_address)?;
+            write_status(core, u32::MAX)?;
+
+            SemihostingCommand::FileLength(FileLengthRequest { handle })
+        }
+
+        (SYS_CLOCK, _) => {
+            write_status(core, u32::MAX)?;
+            SemihostingCommand::Clock(ClockRequest)
+        }
+
+        (SYS_TIME, _) => {
+            write_status(core, 0)?;
+            SemihostingCommand::Time(TimeRequest)
+        }
+
+        (SYS_ERRNO, _) => {
+            write_status(core, 0)?;
+            SemihostingCommand::Errno(ErrnoRequest)
+        }
+
         _ => {
             // signal to target: status = failure, in case the application does not answer this request
             // It is not guaranteed that a value of 255 will be treated as an error by the target, but it is a common value to indicate an error.
@@ -213,3 +623,10 @@ pub fn decode_semihosting_syscall(
         }
     })
 }
+
+/// Reads the file handle, which is the first word of the parameter block.
+fn read_handle(core: &mut dyn CoreInterface, block_address: u32) -> Result<u32, Error> {
+    let mut handle = [0u32];
+    core.read_32(block_address as u64, &mut handle)?;
+    Ok(handle[0])
+}
@@ -1,7 +1,7 @@
-use crate::architecture::arm::ap::AccessPort;
+use crate::architecture::arm::ap::{AccessPort, MemoryAp};
 use crate::architecture::arm::component::get_arm_components;
 use crate::architecture::arm::sequences::{ArmDebugSequence, DefaultArmSequence};
-use crate::architecture::arm::{ArmError, DpAddress};
+use crate::architecture::arm::{link_quality, ArmError, DpAddress};
 use crate::architecture::riscv::communication_interface::RiscvError;
 use crate::architecture::xtensa::communication_interface::{
     XtensaCommunicationInterface, XtensaError,
@@ -21,10 +21,11 @@ use crate::{
     config::DebugSequence,
 };
 use crate::{
-    probe::{list::Lister, AttachMethod, DebugProbeError, Probe},
+    probe::{list::Lister, uart::UartAccess, AttachMethod, DebugProbeError, Probe},
     Core, CoreType, Error,
 };
 use anyhow::anyhow;
+use probe_rs_target::CoreAccessOptions;
 use std::ops::DerefMut;
 use std::{fmt, sync::Arc, time::Duration};
 
@@ -107,7 +108,19 @@ impl Session {
         attach_method: AttachMethod,
         permissions: Permissions,
     ) -> Result<Self, Error> {
-        let (probe, target) = get_target_from_selector(target, attach_method, probe)?;
+        let (probe, mut target) = get_target_from_selector(target, attach_method, probe)?;
+
+        // Cores for which the target description does not select a debug port
+        // use the one selected on the probe.
+        if let DpAddress::Multidrop(targetsel) = probe.selected_debug_port() {
+            for core in &mut target.cores {
+                if let CoreAccessOptions::Arm(options) = &mut core.core_access_options {
+                    if options.psel == 0 {
+                        options.psel = targetsel;
+                    }
+                }
+            }
+        }
 
         let cores = target
             .cores
@@ -183,28 +196,32 @@ impl Session {
                 probe.set_scan_chain(scan_chain)?;
             }
         }
-        probe.attach_to_unspecified()?;
-
-        let interface = probe.try_into_arm_interface().map_err(|(_, err)| err)?;
 
-        let mut interface = interface
-            .initialize(sequence_handle.clone(), default_dp)
-            .map_err(|(_interface, e)| e)?;
-        let unlock_span = tracing::debug_span!("debug_device_unlock").entered();
+        let auto_speed = probe.auto_speed();
+        if auto_speed {
+            link_quality::select_max_speed(&mut probe)?;
+        }
+        probe.attach_to_unspecified()?;
 
-        // Enable debug mode
-        let unlock_res =
-            sequence_handle.debug_device_unlock(&mut *interface, default_memory_ap, &permissions);
-        drop(unlock_span);
+        let connect = |probe| {
+            Self::connect_arm_interface(
+                probe,
+                &sequence_handle,
+                default_dp,
+                default_memory_ap,
+                &permissions,
+            )
+        };
 
-        match unlock_res {
-            Ok(()) => (),
-            // In case this happens after unlock. Try to re-attach the probe once.
-            Err(ArmError::ReAttachRequired) => {
-                Self::reattach_arm_interface(&mut interface, &sequence_handle)?;
-            }
-            Err(e) => return Err(Error::Arm(e)),
-        }
+        let mut interface = if auto_speed {
+            let mut interface =
+                link_quality::negotiate_speed(probe, default_dp, Some(default_memory_ap), connect)
+                    .map_err(|(_probe, e)| e)?;
+            interface.monitor_link_quality();
+            interface
+        } else {
+            connect(probe).map_err(|(_probe, e)| e)?
+        };
 
         // For each core, setup debugging
         for core in &cores {
@@ -462,6 +479,16 @@ impl Session {
         Ok(SwoReader::new(interface))
     }
 
+    /// Get the UART interface of the debug probe, if it routes a target UART.
+    ///
+    /// This is currently only supported for ARM-based targets.
+    pub fn get_uart_interface(&mut self) -> Option<&mut dyn UartAccess> {
+        match &mut self.interface {
+            ArchitectureInterface::Arm(interface) => interface.uart_interface(),
+            _ => None,
+        }
+    }
+
     /// Get the Arm probe interface.
     pub fn get_arm_interface(&mut self) -> Result<&mut dyn ArmProbeInterface, ArmError> {
         let interface = match &mut self.interface {
@@ -494,6 +521,56 @@ impl Session {
         Ok(interface)
     }
 
+    /// Initializes the debug port and unlocks the device.
+    ///
+    /// On failure, the probe is handed back, so that the connection can be retried.
+    #[tracing::instrument(skip_all)]
+    fn connect_arm_interface(
+        probe: Probe,
+        sequence_handle: &Arc<dyn ArmDebugSequence>,
+        dp: DpAddress,
+        memory_ap: MemoryAp,
+        permissions: &Permissions,
+    ) -> Result<Box<dyn ArmProbeInterface>, (Probe, Error)> {
+        let mut interface = Self::initialize_arm_interface(probe, sequence_handle, dp)?;
+
+        let unlock_span = tracing::debug_span!("debug_device_unlock").entered();
+
+        // Enable debug mode
+        let unlock_res =
+            sequence_handle.debug_device_unlock(&mut *interface, memory_ap, permissions);
+        drop(unlock_span);
+
+        match unlock_res {
+            Ok(()) => Ok(interface),
+            // In case this happens after unlock. Try to re-attach the probe once.
+            Err(ArmError::ReAttachRequired) => {
+                tracing::debug!("Re-attaching Probe");
+                let mut probe = interface.close();
+                if let Err(e) = probe.detach().and_then(|()| probe.attach_to_unspecified()) {
+                    return Err((probe, e));
+                }
+
+                Self::initialize_arm_interface(probe, sequence_handle, dp)
+            }
+            Err(e) => Err((interface.close(), Error::Arm(e))),
+        }
+    }
+
+    fn initialize_arm_interface(
+        probe: Probe,
+        sequence_handle: &Arc<dyn ArmDebugSequence>,
+        dp: DpAddress,
+    ) -> Result<Box<dyn ArmProbeInterface>, (Probe, Error)> {
+        let interface = probe
+            .try_into_arm_interface()
+            .map_err(|(probe, err)| (probe, err.into()))?;
+
+        interface
+            .initialize(sequence_handle.clone(), dp)
+            .map_err(|(interface, err)| (interface.close(), err))
+    }
+
     #[tracing::instrument(skip_all)]
     fn reattach_arm_interface(
         interface: &mut Box<dyn ArmProbeInterface>,
@@ -571,19 +648,32 @@ impl Session {
         match erase_result {
             Ok(()) => (),
             // In case this happens after unlock. Try to re-attach the probe once.
-            Err(ArmError::ReAttachRequired) => {
-                Self::reattach_arm_interface(interface, debug_sequence)?;
-                // For re-setup debugging on all cores
-                for core_state in &self.cores {
-                    core_state.enable_arm_debug(interface.deref_mut())?;
-                }
-            }
+            Err(ArmError::ReAttachRequired) => self.reattach_arm()?,
             Err(e) => return Err(Error::Arm(e)),
         }
         tracing::info!("Device Erased Successfully");
         Ok(())
     }
 
+    /// Re-attaches the probe after an operation returned [`ArmError::ReAttachRequired`], and
+    /// sets up debugging on all cores again.
+    pub(crate) fn reattach_arm(&mut self) -> Result<(), Error> {
+        let ArchitectureInterface::Arm(ref mut interface) = self.interface else {
+            return Err(Error::NotImplemented("Re-attaching to a non-ARM target"));
+        };
+        let DebugSequence::Arm(ref debug_sequence) = self.target.debug_sequence else {
+            unreachable!("This should never happen. Please file a bug if it does.");
+        };
+
+        Self::reattach_arm_interface(interface, debug_sequence)?;
+        // For re-setup debugging on all cores
+        for core_state in &self.cores {
+            core_state.enable_arm_debug(interface.deref_mut())?;
+        }
+
+        Ok(())
+    }
+
     /// Reads all the available ARM CoresightComponents of the currently attached target.
     ///
     /// This will recursively parse the Romtable of the attached target
@@ -728,9 +818,9 @@ fn get_target_from_selector(
         TargetSelector::Auto => {
             let mut found_chip = None;
 
-            // We have no information about the target, so we must assume it's using the default DP.
-            // We cannot automatically detect DPs if SWD multi-drop is used.
-            let dp_address = DpAddress::Default;
+            // We have no information about the target, so we must assume it's using the DP
+            // selected on the probe. Without a selection, this is the default DP.
+            let dp_address = probe.selected_debug_port();
 
             // At this point we do not know what the target is, so we cannot use the chip specific reset sequence.
             // Thus, we try just using a normal reset for target detection if we want to do so under reset.
@@ -747,11 +837,8 @@ fn get_target_from_selector(
                             .initialize(DefaultArmSequence::create(), dp_address)
                             .map_err(|(_probe, err)| err)?;
 
-                        // TODO:
-                        let dp = DpAddress::Default;
-
                         let found_arm_chip = interface
-                            .read_chip_info_from_rom_table(dp)
+                            .read_chip_info_from_rom_table(dp_address)
                             .unwrap_or_else(|e| {
                                 tracing::info!("Error during auto-detection of ARM chips: {}", e);
                                 None
@@ -817,6 +904,8 @@ fn get_target_from_selector(
 pub struct Permissions {
     /// When set to true, all memory of the chip may be erased or reset to factory default
     erase_all: bool,
+    /// When set to true, the readout protection of the chip may be enabled
+    enable_protection: bool,
 }
 
 impl Permissions {
@@ -838,6 +927,19 @@ impl Permissions {
         }
     }
 
+    /// Allow the session to enable the readout protection of the chip.
+    ///
+    /// # Warning
+    /// Afterwards, the debugger can't access the chip anymore until the protection is disabled
+    /// again, which usually erases all memory of the chip.
+    #[must_use]
+    pub fn allow_enable_protection(self) -> Self {
+        Self {
+            enable_protection: true,
+            ..self
+        }
+    }
+
     pub(crate) fn erase_all(&self) -> Result<(), MissingPermissions> {
         if self.erase_all {
             Ok(())
@@ -845,6 +947,14 @@ impl Permissions {
             Err(MissingPermissions("erase_all".into()))
         }
     }
+
+    pub(crate) fn enable_protection(&self) -> Result<(), MissingPermissions> {
+        if self.enable_protection {
+            Ok(())
+        } else {
+            Err(MissingPermissions("enable_protection".into()))
+        }
+    }
 }
 
 #[derive(Debug, Clone, thiserror::Error)]
@@ -1,9 +1,10 @@
 use anyhow::{bail, Context, Result};
 use probe_rs::CoreType;
 use probe_rs_target::{
-    ArmCoreAccessOptions, Chip, ChipFamily, Core, CoreAccessOptions, MemoryRegion, NvmRegion,
-    RamRegion, TargetDescriptionSource::BuiltIn,
+    ArmCoreAccessOptions, BoardOverlay, Chip, ChipFamily, Core, CoreAccessOptions, MemoryRegion,
+    NvmRegion, RamRegion, TargetDescriptionSource::BuiltIn,
 };
+use serde::Serialize;
 use std::{
     fs::{File, OpenOptions},
     io::{BufRead, Write},
@@ -19,6 +20,8 @@ pub fn cmd_elf(
     output: Option<&Path>,
     update: bool,
     name: Option<String>,
+    board_overlay: Option<String>,
+    init_arguments: Vec<u32>,
 ) -> Result<()> {
     let elf_file =
         File::open(file).with_context(|| format!("Failed to open ELF file {}", file.display()))?;
@@ -28,8 +31,47 @@ pub fn cmd_elf(
     if let Some(name) = name {
         algorithm.name = name;
     }
+    algorithm.init_arguments = init_arguments;
 
-    if update {
+    if let Some(chip) = board_overlay {
+        // Add the algorithm to an existing chip, which must be known to probe-rs.
+        let target = probe_rs::config::get_target_by_name(chip)?;
+        let cores = target
+            .cores
+            .iter()
+            .map(|core| core.name.clone())
+            .collect::<Vec<_>>();
+        algorithm.cores.clone_from(&cores);
+
+        let overlay = BoardOverlay {
+            name: "<board name>".to_owned(),
+            chip: target.name,
+            memory_map: vec![MemoryRegion::Nvm(NvmRegion {
+                name: Some(algorithm.description.clone()),
+                range: algorithm.flash_properties.address_range.clone(),
+                is_boot_memory: false,
+                cores,
+                is_alias: false,
+            })],
+            flash_algorithms: vec![algorithm],
+        };
+
+        match output {
+            Some(output) => {
+                // Ensure we don't overwrite an existing file
+                let file = OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(output)
+                    .context(format!(
+                        "Failed to create board overlay file '{}'.",
+                        output.display()
+                    ))?;
+                serialize_to_yaml_file(&overlay, &file)?;
+            }
+            None => println!("{}", serde_yaml::to_string(&overlay)?),
+        }
+    } else if update {
         // Update an existing target file
 
         let target_description_file = output.unwrap(); // Argument is checked by structopt, so we now its present.
@@ -59,6 +101,10 @@ pub fn cmd_elf(
                 // core access cannot be determined, use the current value
                 algorithm.cores = current.cores.clone();
                 algorithm.description = current.description.clone();
+                // keep the init arguments of the board, unless new ones were given
+                if algorithm.init_arguments.is_empty() {
+                    algorithm.init_arguments = current.init_arguments.clone();
+                }
 
                 family.flash_algorithms[index] = algorithm
             },
@@ -76,6 +122,7 @@ pub fn cmd_elf(
             manufacturer: None,
             generated_from_pack: false,
             pack_file_release: None,
+            option_bytes: None,
             variants: vec![Chip {
                 cores: vec![Core {
                     name: "main".to_owned(),
@@ -109,6 +156,7 @@ pub fn cmd_elf(
                 rtt_scan_ranges: None,
                 jtag: None,
                 default_binary_format: None,
+                option_bytes: None,
             }],
             flash_algorithms: vec![algorithm],
             source: BuiltIn,
@@ -138,8 +186,11 @@ pub fn cmd_elf(
 /// - If `Option<T>` is `None`, it is serialized as `null` ... we want to omit it.
 /// - If `Vec<T>` is empty, it is serialized as `[]` ... we want to omit it.
 /// - `serde_yaml` serializes hex formatted integers as single quoted strings, e.g. '0x1234' ... we need to remove the single quotes so that it round-trips properly.
-pub fn serialize_to_yaml_file(family: &ChipFamily, file: &File) -> Result<(), anyhow::Error> {
-    let yaml_string = serde_yaml::to_string(&family)?;
+pub fn serialize_to_yaml_file(
+    description: &impl Serialize,
+    file: &File,
+) -> Result<(), anyhow::Error> {
+    let yaml_string = serde_yaml::to_string(description)?;
     let mut reader = std::io::BufReader::new(yaml_string.as_bytes());
     let mut reader_line = String::new();
     let mut writer = std::io::BufWriter::new(file);
@@ -50,6 +50,8 @@ pub fn cmd_test(
         Some(definition_export_path),
         true,
         Some(String::from(ALGORITHM_NAME)),
+        None,
+        vec![],
     )?;
 
     if let Err(error) = generate_debug_info(target_artifact) {
@@ -73,6 +73,7 @@ where
                 manufacturer: None,
                 generated_from_pack: true,
                 pack_file_release: pack_file_release.clone(),
+                option_bytes: None,
                 variants: Vec::new(),
                 flash_algorithms: Vec::new(),
                 source: probe_rs::config::TargetDescriptionSource::BuiltIn,
@@ -162,6 +163,7 @@ where
             rtt_scan_ranges: None,
             jtag: None, // TODO, parse scan chain from sdf
             default_binary_format: None,
+            option_bytes: None,
         });
     }
 
@@ -81,6 +81,17 @@ enum TargetGen {
         /// Update an existing flash algorithm
         #[clap(long = "update", short = 'u', requires = "output")]
         update: bool,
+        /// Generate a board overlay which adds the flash algorithm to the given chip
+        ///
+        /// Instead of a complete target description, a board overlay is generated. It adds the
+        /// flash algorithm, and a memory region for the flash it programs, to the chip.
+        #[clap(long = "board-overlay", value_name = "CHIP", conflicts_with = "update")]
+        board_overlay: Option<String>,
+        /// An argument passed to the `Init()` function of the flash algorithm, e.g. a chip-select pin
+        ///
+        /// Can be given multiple times, the arguments are passed in order.
+        #[clap(long = "init-arg", value_parser = parse_u32)]
+        init_arguments: Vec<u32>,
         /// Output file, if provided, the generated target description will be written to this file.
         #[clap(value_parser)]
         output: Option<PathBuf>,
@@ -110,6 +121,10 @@ pub fn parse_u64(input: &str) -> Result<u64, ParseIntError> {
     parse_int::parse(input)
 }
 
+pub fn parse_u32(input: &str) -> Result<u32, ParseIntError> {
+    parse_int::parse(input)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt()
@@ -133,12 +148,16 @@ async fn main() -> Result<()> {
             update,
             name,
             fixed_load_address,
+            board_overlay,
+            init_arguments,
         } => cmd_elf(
             elf.as_path(),
             fixed_load_address,
             output.as_deref(),
             update,
             name,
+            board_overlay,
+            init_arguments,
         )?,
         TargetGen::Arm {
             output_dir,